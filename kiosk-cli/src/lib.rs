@@ -0,0 +1,10822 @@
+//! The operations behind kiosk's CLI, for tools that drive kiosk from Rust, such as a GUI.
+//! Each command is a function taking its `*Args` and returning its `*Output`, without
+//! printing anything or reading the terminal: the `kiosk` binary parses arguments, reads
+//! stdin payloads, asks the questions of [`Confirm`] and formats the results.
+
+use crate::log_processing::{LineFormat, strip_ansi_lines};
+use crate::session_logs::{
+    SessionLogFile, complete_lines_len, enforce_retention, follow_lines, list_session_logs,
+    purge_session_logs,
+};
+use anyhow::Context;
+use kiosk_core::{
+    agent::{self, PromptAnswer},
+    agent_history::{self, AgentActivity, AgentHistoryStore, AgentSample},
+    agent_hooks,
+    audit::{AuditEntry, AuditLog},
+    branch_name,
+    config::{Config, SessionTemplate, SortOrder, validate_config_file},
+    constants::{WORKTREE_DIR_NAME, WORKTREE_NAME_SEPARATOR},
+    fetch_history::FetchHistoryStore,
+    frecency::{Frecency, FrecencyStore},
+    git::{
+        GitProgress, GitProvider, Repo, Worktree, WorktreeChanges, repo_name_from_url, worktree_of,
+    },
+    hidden::{HiddenStore, RepoFilter},
+    icons::Glyphs,
+    labels::LabelStore,
+    notes::NoteStore,
+    pending_delete::{
+        DeleteJournal, PendingDeleteStore, PendingWorktreeDelete, now_unix_secs,
+        remove_worktree_with_journal, restore_deleted_worktree,
+    },
+    placeholders::SessionContext,
+    process::CommandExt,
+    process_tree::{ProcessTable, ProcessTree},
+    recovery::{Interrupted, PendingCreateStore, PendingWorktreeCreate, Recovery},
+    scripting::{self, Hook},
+    session_journal::{OpenedSession, SessionJournal, kill_session},
+    state::{BranchEntry, sort_repos_by, worktree_dir},
+    statusline::{SessionSummary, StatuslineCache},
+    ticket::{self, Ticket},
+    tmux::{CaptureRange, SessionDetails, TmuxNotRun, TmuxProvider},
+    transcript::{TranscriptAgent, TranscriptDirs},
+    workspace::{self, WorkspaceStore},
+};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub mod log_processing;
+mod output;
+mod pane;
+pub mod plugin;
+pub mod session_logs;
+mod template;
+
+pub use output::{OutputFormat, RecordStream, SCHEMA_VERSION, envelope_schema, render};
+pub use pane::PaneTarget;
+pub use plugin::PluginOutput;
+
+pub type CliResult<T> = Result<T, CliError>;
+
+#[derive(Debug, Clone)]
+pub struct CliError {
+    message: String,
+    code: i32,
+    /// Extra fields merged into the JSON error payload
+    details: Option<serde_json::Value>,
+}
+
+/// Exit codes for each kind of failure, so scripts can tell them apart (0 is success).
+impl CliError {
+    /// Invalid arguments or a request that can't be carried out as asked
+    pub const USER: i32 = 1;
+    /// Unexpected failure, e.g. a git command or file operation failed
+    pub const SYSTEM: i32 = 2;
+    /// The repo, branch, worktree, session, pane or log doesn't exist
+    pub const NOT_FOUND: i32 = 3;
+    /// What was to be created already exists
+    pub const ALREADY_EXISTS: i32 = 4;
+    /// A wait, or a git or tmux command, timed out
+    pub const TIMEOUT: i32 = 5;
+    /// tmux couldn't be run
+    pub const TMUX_UNAVAILABLE: i32 = 6;
+
+    fn with_code(message: impl Into<String>, code: i32) -> Self {
+        Self {
+            message: message.into(),
+            code,
+            details: None,
+        }
+    }
+
+    pub fn user(message: impl Into<String>) -> Self {
+        Self::with_code(message, Self::USER)
+    }
+
+    pub fn system(message: impl Into<String>) -> Self {
+        Self::with_code(message, Self::SYSTEM)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::with_code(message, Self::NOT_FOUND)
+    }
+
+    pub fn already_exists(message: impl Into<String>) -> Self {
+        Self::with_code(message, Self::ALREADY_EXISTS)
+    }
+
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::with_code(message, Self::TIMEOUT)
+    }
+
+    pub fn tmux_unavailable(message: impl Into<String>) -> Self {
+        Self::with_code(message, Self::TMUX_UNAVAILABLE)
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    /// Structured details merged into the JSON error payload, if any.
+    pub fn details(&self) -> Option<&serde_json::Value> {
+        self.details.as_ref()
+    }
+
+    /// Append a line of context to the message.
+    #[must_use]
+    pub fn with_context(mut self, context: impl AsRef<str>) -> Self {
+        self.message = format!("{}\n{}", self.message, context.as_ref());
+        self
+    }
+
+    /// Attach structured details, which must serialize to a JSON object.
+    #[must_use]
+    pub fn with_details<T: Serialize>(mut self, details: &T) -> Self {
+        self.details = serde_json::to_value(details).ok();
+        self
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<anyhow::Error> for CliError {
+    /// Failures to start tmux at all are reported as [`CliError::TMUX_UNAVAILABLE`], and git
+    /// or tmux commands stopped by their timeout as [`CliError::TIMEOUT`]; commands that ran
+    /// and exited with a failure status stay system errors.
+    fn from(value: anyhow::Error) -> Self {
+        let io_error = value
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<std::io::Error>());
+        if io_error.is_some_and(|error| error.kind() == std::io::ErrorKind::TimedOut) {
+            Self::timeout(format!("{value:#}"))
+        } else if value.downcast_ref::<TmuxNotRun>().is_some() {
+            Self::tmux_unavailable(format!("{value}: {}", value.root_cause()))
+        } else {
+            Self::system(value.to_string())
+        }
+    }
+}
+
+/// Asks the user a yes/no question and returns the answer, e.g. on the terminal.
+pub type Ask = dyn Fn(&str) -> CliResult<bool> + Send + Sync;
+
+/// Answers the yes/no questions destructive commands ask before going ahead.
+#[derive(Clone)]
+pub struct Confirm {
+    assume_yes: bool,
+    ask: Option<Arc<Ask>>,
+}
+
+impl std::fmt::Debug for Confirm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Confirm")
+            .field("assume_yes", &self.assume_yes)
+            .field("interactive", &self.ask.is_some())
+            .finish()
+    }
+}
+
+impl Confirm {
+    /// Questions go to `ask`, unless `assume_yes` (e.g. `--yes`) answers them all.
+    pub fn new(
+        assume_yes: bool,
+        ask: impl Fn(&str) -> CliResult<bool> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            assume_yes,
+            ask: Some(Arc::new(ask)),
+        }
+    }
+
+    /// Never asks: every question is answered with `assume_yes`, e.g. for callers that ask
+    /// in their own UI first.
+    pub fn non_interactive(assume_yes: bool) -> Self {
+        Self {
+            assume_yes,
+            ask: None,
+        }
+    }
+
+    pub fn assume_yes(&self) -> bool {
+        self.assume_yes
+    }
+
+    /// Whether the user would answer `question` with yes. With `assume_yes` that's yes
+    /// without asking; with nobody to ask it's no, so scripts never block on input.
+    pub fn ask(&self, question: &str) -> CliResult<bool> {
+        if self.assume_yes {
+            return Ok(true);
+        }
+        self.ask.as_ref().map_or(Ok(false), |ask| ask(question))
+    }
+}
+
+/// Sort order for `kiosk list`; without one, repos are listed in discovery order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListSort {
+    Name,
+    /// Most recent session activity first
+    Activity,
+    /// Most often and recently opened first, falling back to session activity
+    Frecency,
+    Path,
+}
+
+/// Sort order for `kiosk top`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TopSort {
+    /// Busiest first
+    #[default]
+    Cpu,
+    /// Largest first
+    Memory,
+    Session,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ListArgs {
+    pub filter: Option<String>,
+    pub path_under: Option<PathBuf>,
+    pub sort: Option<ListSort>,
+    pub with_sessions_only: bool,
+    pub pinned: bool,
+    /// Also list repos matched by `ignore` or hidden from the TUI
+    pub include_hidden: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct BranchesArgs {
+    pub repo: String,
+    /// Only local branches (combined with `remote`, both kinds are listed)
+    pub local: bool,
+    /// Only remote-only branches
+    pub remote: bool,
+    /// Only this remote's branches among the remote-only ones
+    pub remote_name: Option<String>,
+    pub with_worktree: bool,
+    pub with_session: bool,
+    /// Only branches whose session has a coding agent in one of its panes
+    pub with_agent: bool,
+    /// Only branches containing this commit
+    pub contains: Option<String>,
+    /// Only branches with all of these labels
+    pub labels: Vec<String>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct OpenArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub new_branch: Option<String>,
+    /// Jira or Linear ticket to name the new branch after, with its link as the branch's note
+    pub ticket: Option<String>,
+    pub base: Option<String>,
+    /// Commit or tag to open a worktree at with a detached HEAD, instead of a branch
+    pub detached: Option<DetachedTarget>,
+    /// Remote to track the branch from when it's only on remotes, needed when several have it
+    pub remote: Option<String>,
+    pub no_switch: bool,
+    pub run: Option<String>,
+    /// Command for a new session's split pane, in place of the session template's
+    pub split_command: Option<String>,
+    /// Agent from `[agents]` to start in place of `run`, logging its output unless it opts out
+    pub agent: Option<String>,
+    pub wait: bool,
+    pub wait_timeout: u64,
+    pub wait_pane: PaneTarget,
+    pub log: bool,
+    pub atomic: bool,
+    pub detach_others: bool,
+    /// Check the branch out in the main checkout instead of a worktree
+    pub in_place: bool,
+    /// Open the same branch in each of `linked_repos` too, saving them all as a workspace
+    pub link: bool,
+    /// Report progress from git while fetching and checking out the worktree
+    pub progress: bool,
+    /// Environment for a new session, over `[session.env]`
+    pub env: Vec<(String, String)>,
+    pub format: OutputFormat,
+}
+
+/// What `kiosk open --commit` or `--tag` checks out with a detached HEAD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DetachedTarget {
+    Commit(String),
+    Tag(String),
+}
+
+impl DetachedTarget {
+    /// The revision to resolve, qualified so a tag can't be mistaken for a branch.
+    fn rev(&self) -> String {
+        match self {
+            Self::Commit(commit) => commit.clone(),
+            Self::Tag(tag) => format!("refs/tags/{tag}"),
+        }
+    }
+
+    /// Name for the worktree checking out `commit`: the tag, or the abbreviated commit.
+    fn worktree_name(&self, commit: &str) -> String {
+        match self {
+            Self::Commit(_) => commit.chars().take(12).collect(),
+            Self::Tag(tag) => tag.clone(),
+        }
+    }
+}
+
+impl OpenArgs {
+    /// Open `repo`'s main checkout without switching to it, with every option at its default.
+    pub fn for_repo(repo: String, format: OutputFormat) -> Self {
+        Self {
+            repo,
+            branch: None,
+            new_branch: None,
+            ticket: None,
+            detached: None,
+            base: None,
+            no_switch: true,
+            run: None,
+            split_command: None,
+            wait: false,
+            wait_timeout: 600,
+            wait_pane: PaneTarget::default(),
+            log: false,
+            atomic: false,
+            detach_others: false,
+            in_place: false,
+            link: false,
+            progress: false,
+            env: Vec::new(),
+            format,
+            remote: None,
+            agent: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StatusArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub format: OutputFormat,
+    pub lines: usize,
+    pub pane: PaneTarget,
+    /// Remove terminal escape sequences and control characters from the output
+    pub strip_ansi: bool,
+}
+
+/// `kiosk status --all`: the status of every running kiosk session.
+#[derive(Debug, Clone)]
+pub struct StatusAllArgs {
+    pub lines: usize,
+    pub pane: PaneTarget,
+    /// Remove terminal escape sequences and control characters from the output
+    pub strip_ansi: bool,
+    pub format: OutputFormat,
+}
+
+/// `kiosk capture`: raw `tmux capture-pane` output.
+#[derive(Debug, Clone)]
+pub struct CaptureArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub pane: PaneTarget,
+    /// Start this many lines back in the scrollback (shorthand for `start = -history`)
+    pub history: Option<usize>,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub escapes: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct SendArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub command: Option<String>,
+    pub keys: Option<String>,
+    pub text: Option<String>,
+    /// Paste this payload, read from stdin by the caller
+    pub stdin: Option<String>,
+    /// Paste the contents of this file
+    pub file: Option<PathBuf>,
+    /// Don't press Enter after pasting a `stdin`/`file` payload
+    pub no_enter: bool,
+    /// Only send if the pane's visible contents match this regex, e.g. its prompt
+    pub confirm_pattern: Option<String>,
+    pub pane: PaneTarget,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteArgs {
+    pub repo: String,
+    pub branch: String,
+    pub force: bool,
+    pub force_dirty: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct UndoArgs {
+    pub repo: Option<String>,
+    pub branch: Option<String>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReapArgs {
+    /// Hours without activity before a session is reaped
+    pub idle_hours: u64,
+    pub remove_merged: bool,
+    pub dry_run: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct PruneBranchesArgs {
+    /// Only prune branches of this repository
+    pub repo: Option<String>,
+    pub dry_run: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecoverArgs {
+    /// What to do with the interrupted operations, or `None` to only list them
+    pub direction: Option<RecoverDirection>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecoverDirection {
+    /// Finish each operation
+    Forward,
+    /// Undo each operation, restoring deleted worktrees
+    Back,
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryArgs {
+    /// Only show operations on this repository, its worktrees and their sessions
+    pub repo: Option<String>,
+    /// Only show the most recent operations
+    pub limit: Option<usize>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchArgs {
+    /// Only fetch this repository's remotes
+    pub repo: Option<String>,
+    /// Delete remote-tracking branches that are gone from their remote
+    pub prune: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct TopArgs {
+    /// Only show this repository's sessions
+    pub repo: Option<String>,
+    pub sort: TopSort,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportArgs {
+    /// A file written by `kiosk export`, or `-` for `stdin`
+    pub file: PathBuf,
+    /// The export, read from stdin by the caller when `file` is `-`
+    pub stdin: Option<String>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResumeArgs {
+    /// Lost sessions to recreate
+    pub sessions: Vec<String>,
+    /// Recreate every lost session
+    pub all: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceListArgs {
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceOpenArgs {
+    /// Workspace from `[workspaces]` in the config, or created from the TUI
+    pub name: String,
+    /// Open the sessions without switching to the first one
+    pub no_switch: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct CloneArgs {
+    pub url: String,
+    /// Search directory to clone into, instead of the first one
+    pub dir: Option<PathBuf>,
+    /// Open a session in the clone once it's done
+    pub open: bool,
+    pub format: OutputFormat,
+}
+
+/// What `kiosk diff` prints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffMode {
+    /// Changed files with their line counts
+    #[default]
+    Stat,
+    /// Changed file paths only
+    NameOnly,
+    /// The full unified diff
+    Patch,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffArgs {
+    pub repo: String,
+    pub branch: String,
+    /// Branch to diff against (default: the repo's default branch)
+    pub base: Option<String>,
+    pub mode: DiffMode,
+    pub format: OutputFormat,
+}
+
+/// What `kiosk path` prints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathKind {
+    /// The worktree directory
+    #[default]
+    Worktree,
+    /// The session name
+    Session,
+    /// The session's log file
+    Log,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub kind: PathKind,
+    pub format: OutputFormat,
+}
+
+/// `kiosk note`: show or change the note attached to a branch.
+#[derive(Debug, Clone)]
+pub struct NoteArgs {
+    pub repo: String,
+    pub branch: String,
+    /// The new note, where an empty one clears it; `None` only shows the current note
+    pub note: Option<String>,
+    pub format: OutputFormat,
+}
+
+/// `kiosk label add` or `kiosk label remove`.
+#[derive(Debug, Clone)]
+pub struct LabelArgs {
+    pub repo: String,
+    pub branch: String,
+    pub change: LabelChange,
+    pub labels: Vec<String>,
+    pub format: OutputFormat,
+}
+
+/// `kiosk unhide`: show a repo hidden from the TUI again.
+#[derive(Debug, Clone)]
+pub struct UnhideArgs {
+    pub repo: String,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelChange {
+    Add,
+    Remove,
+}
+
+/// `kiosk statusline`: a one-line summary of a session for status bars.
+#[derive(Debug, Clone)]
+pub struct StatuslineArgs {
+    /// Defaults to the session kiosk runs in
+    pub session: Option<String>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct PanesArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct PsArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct InterruptArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    /// The pane to interrupt, instead of the agent's (or pane 0 without one)
+    pub pane: Option<PaneTarget>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct KillPaneArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    /// The pane to close, instead of the agent's
+    pub pane: Option<PaneTarget>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnswerArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub format: OutputFormat,
+}
+
+/// Agents `kiosk transcript` can read the conversations of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TranscriptSource {
+    Claude,
+    Codex,
+}
+
+impl From<TranscriptSource> for TranscriptAgent {
+    fn from(source: TranscriptSource) -> Self {
+        match source {
+            TranscriptSource::Claude => Self::Claude,
+            TranscriptSource::Codex => Self::Codex,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TranscriptArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    /// Only look for this agent's transcripts
+    pub agent: Option<TranscriptSource>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentsRecordArgs {
+    /// Seconds between samples
+    pub interval: u64,
+    /// Take a single sample and exit, e.g. from cron
+    pub once: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentsReportArgs {
+    /// How far back to report on, in seconds
+    pub since: u64,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct WaitArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub timeout: u64,
+    pub pane: PaneTarget,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogArgs {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub tail: usize,
+    pub follow: bool,
+    pub grep: Option<String>,
+    pub line_format: LineFormat,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogListArgs {
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogPurgeArgs {
+    pub repo: Option<String>,
+    pub branch: Option<String>,
+    pub format: OutputFormat,
+}
+
+/// Commands whose `--format json` output has a schema, for `kiosk schema`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaCommand {
+    List,
+    Branches,
+    Open,
+    Status,
+    /// `status --all`
+    StatusAll,
+    Capture,
+    Sessions,
+    Delete,
+    Undo,
+    Reap,
+    PruneBranches,
+    Recover,
+    History,
+    Clone,
+    Fetch,
+    Top,
+    Export,
+    Import,
+    Resume,
+    /// `workspace list`
+    WorkspaceList,
+    /// `workspace open`
+    WorkspaceOpen,
+    Diff,
+    Path,
+    Note,
+    Label,
+    Unhide,
+    Statusline,
+    Send,
+    Panes,
+    Ps,
+    Interrupt,
+    KillPane,
+    Approve,
+    Deny,
+    Transcript,
+    Wait,
+    /// `agents report`
+    AgentsReport,
+    Log,
+    /// `open --progress`, one document per line ahead of the result
+    OpenProgress,
+    /// `log --follow`, one document per line
+    LogFollow,
+    /// `log --list`
+    LogList,
+    /// `log --purge`
+    LogPurge,
+    Clean,
+    /// `config validate`
+    ConfigValidate,
+    /// `config path`
+    ConfigPath,
+    Plugins,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigShowArgs {
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigPathArgs {
+    pub path: PathBuf,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigEditArgs {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigValidateArgs {
+    pub file: PathBuf,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct RepoOutput {
+    pub name: String,
+    pub path: PathBuf,
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct BranchOutput {
+    pub name: String,
+    pub worktree_path: Option<PathBuf>,
+    pub has_session: bool,
+    pub is_current: bool,
+    pub remote: Option<String>,
+    pub last_commit: Option<LastCommitOutput>,
+    pub note: Option<String>,
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct LastCommitOutput {
+    pub subject: String,
+    pub author: String,
+    /// Committer date, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct OpenOutput {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub session: String,
+    pub path: PathBuf,
+    pub created: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait: Option<WaitOutput>,
+    /// Idle sessions closed by `--detach-others` or `session.max_alive`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub closed_sessions: Vec<String>,
+    /// Sessions opened on the same branch in `linked_repos`, with `--link`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub linked: Vec<WorkspaceSession>,
+    /// Linked repos the branch couldn't be opened in; the rest are opened regardless
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub link_failed: Vec<WorkspaceFailure>,
+}
+
+/// One NDJSON record per git progress update for `kiosk open --progress --json`, printed
+/// before the final result.
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct OpenProgressOutput<'a> {
+    pub progress: ProgressDetails<'a>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ProgressDetails<'a> {
+    /// What git is doing, e.g. `Receiving objects`
+    pub phase: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+}
+
+impl<'a> From<&'a GitProgress> for OpenProgressOutput<'a> {
+    fn from(progress: &'a GitProgress) -> Self {
+        Self {
+            progress: ProgressDetails {
+                phase: &progress.phase,
+                percent: progress.percent,
+                current: progress.current,
+                total: progress.total,
+            },
+        }
+    }
+}
+
+/// Resources created by `open` before a later step failed.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+struct PartialOpen {
+    worktree: Option<PathBuf>,
+    branch: Option<String>,
+    session: Option<String>,
+}
+
+impl PartialOpen {
+    fn is_empty(&self) -> bool {
+        self.worktree.is_none() && self.branch.is_none() && self.session.is_none()
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(worktree) = &self.worktree {
+            parts.push(format!("worktree {}", worktree.display()));
+        }
+        if let Some(branch) = &self.branch {
+            parts.push(format!("branch {branch}"));
+        }
+        if let Some(session) = &self.session {
+            parts.push(format!("session {session}"));
+        }
+        parts.join(", ")
+    }
+
+    fn rollback_hint(&self, repo: &Repo, branch: Option<&str>) -> String {
+        let mut commands = Vec::new();
+        match (&self.worktree, branch, &self.session) {
+            (Some(_), Some(branch), _) => {
+                commands.push(format!("kiosk delete {} {branch} --force", repo.name));
+            }
+            (_, _, Some(session)) => commands.push(format!("tmux kill-session -t '={session}'")),
+            _ => {}
+        }
+        if let Some(branch) = &self.branch {
+            commands.push(format!("git -C {} branch -D {branch}", repo.path.display()));
+        }
+        commands.join(" && ")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct PartialOpenDetails {
+    created: PartialOpen,
+    rolled_back: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rollback: Option<String>,
+}
+
+/// Error details for a send refused because the pane didn't match `--confirm-pattern`.
+#[derive(Debug, Serialize)]
+struct ConfirmPatternDetails<'a> {
+    session: &'a str,
+    pane: usize,
+    /// The pane's visible contents
+    captured: &'a str,
+}
+
+/// Error details for a delete refused because the worktree holds work found nowhere else.
+#[derive(Debug, Serialize)]
+struct DirtyWorktreeDetails<'a> {
+    path: &'a Path,
+    #[serde(flatten)]
+    changes: &'a WorktreeChanges,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct StatusOutput {
+    pub session: String,
+    pub path: PathBuf,
+    pub attached: bool,
+    pub clients: usize,
+    pub source: StatusSource,
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct CaptureOutput {
+    pub session: String,
+    pub pane: usize,
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct SessionStatusOutput {
+    pub session: String,
+    pub repo: String,
+    pub branch: Option<String>,
+    pub path: PathBuf,
+    pub attached: bool,
+    pub clients: usize,
+    /// Coding agent running in one of the session's panes, if any
+    pub agent: Option<String>,
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusSource {
+    Live,
+    Log,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct SessionOutput {
+    pub session: String,
+    pub repo: String,
+    pub branch: Option<String>,
+    pub path: PathBuf,
+    pub attached: bool,
+    pub last_activity: u64,
+    pub pane_count: usize,
+    pub current_command: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct DeleteOutput {
+    pub deleted: bool,
+    pub repo: String,
+    pub branch: String,
+    pub session: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct UndoOutput {
+    pub repo: String,
+    pub branch: String,
+    pub path: PathBuf,
+    pub stash_restored: bool,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ReapOutput {
+    pub dry_run: bool,
+    pub reaped: Vec<ReapedSession>,
+    /// Sessions whose merged worktree couldn't be removed; the rest are reaped regardless
+    pub failed: Vec<ReapFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ReapedSession {
+    pub session: String,
+    pub repo: String,
+    pub branch: Option<String>,
+    pub path: PathBuf,
+    /// Seconds since the session's last activity
+    pub idle_secs: u64,
+    /// Whether the worktree was removed because its branch is merged
+    pub worktree_removed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ReapFailure {
+    pub session: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct PruneBranchesOutput {
+    pub dry_run: bool,
+    pub branches: Vec<PrunedBranch>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct PrunedBranch {
+    pub repo: String,
+    pub branch: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct RecoverOutput {
+    /// `None` when the operations were only listed
+    pub direction: Option<RecoverDirection>,
+    pub operations: Vec<RecoveredOperation>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct RecoveredOperation {
+    /// `"create"` or `"delete"`
+    pub kind: String,
+    pub repo_path: PathBuf,
+    pub branch: Option<String>,
+    pub path: PathBuf,
+    pub started_at_unix_secs: u64,
+    /// Why rolling the operation forward or back failed, leaving it to recover later
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct FetchOutput {
+    pub repos: Vec<FetchedRepo>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct FetchedRepo {
+    pub repo: String,
+    pub path: PathBuf,
+    /// The remotes `fetch.remotes` selects; empty when the repo has none of them
+    pub remotes: Vec<FetchedRemote>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct FetchedRemote {
+    pub remote: String,
+    /// Branches on the remote once fetched
+    pub branches: usize,
+    /// Why the fetch failed, leaving what was fetched before
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+pub struct TopEntry {
+    pub session: String,
+    pub repo: String,
+    pub branch: Option<String>,
+    pub path: PathBuf,
+    /// CPU use of every process under the session's panes: 100 is one core busy
+    pub cpu_percent: f64,
+    /// Resident memory of every process under the session's panes
+    pub memory_bytes: u64,
+    pub processes: usize,
+    /// The command of the process using the most CPU
+    pub busiest_command: Option<String>,
+}
+
+/// What `kiosk export` writes and `kiosk import` reads back.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ExportOutput {
+    pub worktrees: Vec<ExportedWorktree>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ExportedWorktree {
+    /// Repo name, as shown by `kiosk list`
+    pub repo: String,
+    /// `None` for the main checkout
+    pub branch: Option<String>,
+    /// Where `branch` is created from when it exists nowhere: the branch kiosk created it
+    /// from, or the repo's default branch when kiosk didn't create it
+    pub base: Option<String>,
+    /// How the worktree's session was set up, `None` when kiosk didn't set it up
+    #[serde(default)]
+    pub layout: Option<SessionLayout>,
+}
+
+/// The panes of a session kiosk set up, as `kiosk import` sets them up again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct SessionLayout {
+    /// Command in the split pane, with its placeholders. Without one, `kiosk import` uses the
+    /// session template's
+    pub split_command: Option<String>,
+    /// Command typed into the main pane
+    pub run: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ImportOutput {
+    pub opened: Vec<ImportedSession>,
+    /// Worktrees that couldn't be recreated; the rest are imported regardless
+    pub failed: Vec<ImportFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ImportedSession {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub session: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ImportFailure {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ResumeOutput {
+    pub resumed: Vec<ResumedSession>,
+    pub failed: Vec<ResumeFailure>,
+    /// Lost sessions that weren't asked for, left to resume later
+    pub lost: Vec<LostSession>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ResumedSession {
+    pub session: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ResumeFailure {
+    pub session: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct WorkspaceOutput {
+    pub name: String,
+    /// `repo/branch`, or `repo` for its main checkout
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct WorkspaceOpenOutput {
+    pub workspace: String,
+    pub opened: Vec<WorkspaceSession>,
+    /// Members that couldn't be opened; the rest are opened regardless
+    pub failed: Vec<WorkspaceFailure>,
+    /// Session switched to: the first one opened, unless `--no-switch` was given
+    pub switched_to: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct WorkspaceSession {
+    pub member: String,
+    pub session: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct WorkspaceFailure {
+    pub member: String,
+    pub error: String,
+}
+
+/// A session that went away with an earlier tmux server.
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct LostSession {
+    pub session: String,
+    pub repo: String,
+    /// `None` for a detached HEAD
+    pub branch: Option<String>,
+    pub path: PathBuf,
+    /// Command typed into the session when it was set up, typed again when it's resumed
+    pub run: Option<String>,
+}
+
+impl From<OpenedSession> for LostSession {
+    fn from(entry: OpenedSession) -> Self {
+        Self {
+            session: entry.session,
+            repo: entry
+                .repo_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            branch: entry.branch,
+            path: entry.worktree_path,
+            run: entry.run,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct CloneOutput {
+    /// Repo name, as shown by `kiosk list`
+    pub name: String,
+    pub path: PathBuf,
+    /// Session opened in the clone, with `--open`
+    pub session: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct PathOutput {
+    /// Worktree directory: the existing one, or where `kiosk open` would create it
+    pub path: PathBuf,
+    /// Whether the worktree exists yet
+    pub exists: bool,
+    pub session: String,
+    /// Where `kiosk open --log` writes the session's log
+    pub log: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct NoteOutput {
+    pub repo: String,
+    pub branch: String,
+    /// `None` when the branch has no note
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct LabelOutput {
+    pub repo: String,
+    pub branch: String,
+    /// All of the branch's labels after the change, sorted
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct UnhideOutput {
+    pub repo: String,
+    pub path: PathBuf,
+}
+
+/// Whether an agent is busy, going by when its session last had activity.
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentState {
+    Working,
+    /// Quiet for a while, so most likely waiting for input
+    Waiting,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct StatuslineOutput {
+    pub session: String,
+    pub repo: String,
+    /// `None` on a detached `HEAD` or outside a repo
+    pub branch: Option<String>,
+    /// Whether the worktree has uncommitted changes
+    pub dirty: bool,
+    /// The coding agent running in the session, if any
+    pub agent: Option<String>,
+    pub agent_state: Option<AgentState>,
+}
+
+/// A Waybar custom module's `return-type = "json"` output (see `man waybar-custom`).
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct WaybarOutput {
+    /// Empty when nothing needs attention, which hides the module
+    pub text: String,
+    pub tooltip: String,
+    /// `waiting` while an agent waits for input, for styling the module
+    pub class: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct DiffOutput {
+    pub repo: String,
+    pub branch: String,
+    pub base: String,
+    pub path: PathBuf,
+    pub files: Vec<DiffFileOutput>,
+    pub insertions: u64,
+    pub deletions: u64,
+    /// The unified diff, with `--patch`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct DiffFileOutput {
+    pub path: PathBuf,
+    /// Lines added, or null for a binary file
+    pub insertions: Option<u64>,
+    /// Lines removed, or null for a binary file
+    pub deletions: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct SendOutput {
+    pub session: String,
+    pub command: Option<String>,
+    pub keys: Option<String>,
+    pub text: Option<String>,
+    /// Bytes pasted from `--stdin` or `--file`
+    pub pasted_bytes: Option<usize>,
+    pub pane: usize,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct PaneInfo {
+    pub index: usize,
+    pub current_command: String,
+    pub pid: u32,
+    pub active: bool,
+    pub width: u32,
+    pub height: u32,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct PanesOutput {
+    pub session: String,
+    pub panes: Vec<PaneInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct InterruptOutput {
+    pub session: String,
+    pub pane: usize,
+    /// What was running in the pane
+    pub command: String,
+    /// The tmux keys sent to stop it
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct KillPaneOutput {
+    pub session: String,
+    pub pane: usize,
+    /// What was running in the pane
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct AnswerOutput {
+    pub session: String,
+    pub pane: usize,
+    pub agent: String,
+    /// Whether the prompt was approved rather than denied
+    pub approved: bool,
+    /// The tmux keys sent to answer it
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct TranscriptOutput {
+    pub repo: String,
+    pub branch: Option<String>,
+    pub path: PathBuf,
+    pub agent: String,
+    /// The agent's own transcript file
+    pub file: PathBuf,
+    pub messages: Vec<TranscriptMessageOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct TranscriptMessageOutput {
+    /// `user` or `assistant`
+    pub role: String,
+    pub text: String,
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct AgentsReportOutput {
+    /// Start of the window reported on, in seconds since the Unix epoch
+    pub since: u64,
+    /// Sessions that spent longest waiting come first
+    pub sessions: Vec<AgentTimeOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct AgentTimeOutput {
+    pub session: String,
+    pub repo: String,
+    pub branch: Option<String>,
+    /// Seconds the agent spent busy
+    pub working_secs: u64,
+    /// Seconds the agent sat quiet, most likely waiting for input
+    pub waiting_secs: u64,
+    /// Seconds the session had no agent running
+    pub idle_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+pub struct PsOutput {
+    pub session: String,
+    pub panes: Vec<PaneProcesses>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+pub struct PaneProcesses {
+    pub index: usize,
+    pub pid: u32,
+    /// The pane's process and everything below it; `None` when it's gone or the terminal
+    /// can't tell its pid
+    pub process: Option<ProcessOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+pub struct ProcessOutput {
+    pub pid: u32,
+    pub command: String,
+    pub args: String,
+    /// 100 is one core busy
+    pub cpu_percent: f64,
+    pub children: Vec<ProcessOutput>,
+}
+
+impl From<ProcessTree> for ProcessOutput {
+    fn from(tree: ProcessTree) -> Self {
+        Self {
+            pid: tree.process.pid,
+            command: tree.process.command,
+            args: tree.process.args,
+            cpu_percent: tree.process.cpu_percent,
+            children: tree.children.into_iter().map(Self::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct WaitOutput {
+    pub idle: bool,
+    pub timed_out: bool,
+    pub pane_command: String,
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct LogOutput {
+    pub session: String,
+    pub lines: Vec<String>,
+}
+
+/// One NDJSON record per line for `kiosk log --follow --json`.
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct LogLineOutput<'a> {
+    pub session: &'a str,
+    pub line: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct LogListOutput {
+    pub logs: Vec<SessionLogFile>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct LogPurgeOutput {
+    pub purged: Vec<PathBuf>,
+    pub bytes_freed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct CleanOutput {
+    pub orphaned: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+pub fn resolve_repo_exact<'a>(repos: &'a [Repo], name: &str) -> CliResult<&'a Repo> {
+    repos.iter().find(|repo| repo.name == name).ok_or_else(|| {
+        let available = repos
+            .iter()
+            .map(|repo| repo.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        if available.is_empty() {
+            CliError::not_found(format!("no repo named '{name}' found. Available: (none)"))
+        } else {
+            CliError::not_found(format!(
+                "no repo named '{name}' found. Available: {available}"
+            ))
+        }
+    })
+}
+
+fn resolve_repo_with_worktrees(
+    config: &Config,
+    git: &dyn GitProvider,
+    name: &str,
+) -> CliResult<Repo> {
+    let repos = git.discover_repos(&config.resolved_search_dirs());
+    let repo = resolve_repo_exact(&repos, name)?;
+    let mut repo = repo.clone();
+    repo.worktrees = git.list_worktrees(&repo.path);
+    Ok(repo)
+}
+
+fn discover_all_with_worktrees(config: &Config, git: &dyn GitProvider) -> Vec<Repo> {
+    let mut repos = git.discover_repos(&config.resolved_search_dirs());
+    for repo in &mut repos {
+        repo.worktrees = git.list_worktrees(&repo.path);
+    }
+    repos
+}
+
+/// Per-repo state kept between runs that ranks and filters `kiosk list`.
+#[derive(Debug, Default)]
+pub struct RepoPreferences {
+    pub frecency: Frecency,
+    pub pinned: HashSet<PathBuf>,
+    pub filter: RepoFilter,
+}
+
+pub fn list(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    prefs: &RepoPreferences,
+    args: &ListArgs,
+) -> CliResult<Vec<RepoOutput>> {
+    let filter = args
+        .filter
+        .as_deref()
+        .map(glob::Pattern::new)
+        .transpose()
+        .map_err(|e| CliError::user(format!("invalid --filter pattern: {e}")))?;
+    let path_under = args
+        .path_under
+        .as_deref()
+        .map(|dir| dunce::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf()));
+
+    // Worktrees are only needed to map repos to their sessions
+    let needs_sessions = args.with_sessions_only
+        || matches!(args.sort, Some(ListSort::Activity | ListSort::Frecency));
+    let mut repos = if needs_sessions {
+        discover_all_with_worktrees(config, git)
+    } else {
+        git.discover_repos(&config.resolved_search_dirs())
+    };
+
+    repos.retain(|repo| {
+        (args.include_hidden || !prefs.filter.is_hidden(&repo.path))
+            && (!args.pinned || prefs.pinned.contains(&repo.path))
+            && filter
+                .as_ref()
+                .is_none_or(|pattern| pattern.matches(&repo.name))
+            && path_under.as_ref().is_none_or(|dir| {
+                dunce::canonicalize(&repo.path)
+                    .unwrap_or_else(|_| repo.path.clone())
+                    .starts_with(dir)
+            })
+    });
+
+    if needs_sessions {
+        let activity: HashMap<String, u64> =
+            tmux.list_sessions_with_activity().into_iter().collect();
+        if args.with_sessions_only {
+            repos.retain(|repo| {
+                std::iter::once(&repo.path)
+                    .chain(repo.worktrees.iter().map(|worktree| &worktree.path))
+                    .any(|path| activity.contains_key(&repo.tmux_session_name(path)))
+            });
+        }
+        match args.sort {
+            Some(ListSort::Activity) => {
+                sort_repos_by(
+                    &mut repos,
+                    None,
+                    SortOrder::Activity,
+                    &activity,
+                    &HashMap::new(),
+                    &prefs.pinned,
+                );
+            }
+            Some(ListSort::Frecency) => sort_repos_by(
+                &mut repos,
+                None,
+                SortOrder::Frecency,
+                &activity,
+                &prefs.frecency.repo_scores(),
+                &prefs.pinned,
+            ),
+            _ => {}
+        }
+    }
+    match args.sort {
+        Some(ListSort::Name) => repos.sort_by(|a, b| a.name.cmp(&b.name)),
+        Some(ListSort::Path) => repos.sort_by(|a, b| a.path.cmp(&b.path)),
+        Some(ListSort::Activity | ListSort::Frecency) | None => {}
+    }
+
+    Ok(repos
+        .into_iter()
+        .map(|repo| RepoOutput {
+            pinned: prefs.pinned.contains(&repo.path),
+            name: repo.name,
+            path: repo.path,
+        })
+        .collect())
+}
+
+pub fn branches(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    notes: &NoteStore,
+    label_store: &LabelStore,
+    args: &BranchesArgs,
+) -> CliResult<Vec<BranchEntry>> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+
+    let local = git.list_branches(&repo.path);
+    let active_sessions = tmux.list_session_names();
+    // Neither or both of --local/--remote lists everything
+    let mut entries = if args.local || !args.remote {
+        BranchEntry::build(&repo, &local, &active_sessions)
+    } else {
+        Vec::new()
+    };
+    if args.remote || !args.local {
+        let remotes = git.list_remotes(&repo.path);
+        if let Some(name) = &args.remote_name
+            && !remotes.contains(name)
+        {
+            return Err(CliError::not_found(format!("remote '{name}' not found")));
+        }
+        for r in remotes
+            .iter()
+            .filter(|r| args.remote_name.as_ref().is_none_or(|name| name == *r))
+        {
+            let names = git.list_remote_branches_for_remote(&repo.path, r);
+            entries.extend(BranchEntry::build_remote(r, &names, &local));
+        }
+    }
+
+    let containing = args
+        .contains
+        .as_deref()
+        .map(|commit| {
+            git.branches_containing(&repo.path, commit)
+                .map(|names| names.into_iter().collect::<HashSet<_>>())
+                .map_err(|e| CliError::user(format!("invalid --contains commit '{commit}': {e}")))
+        })
+        .transpose()?;
+
+    BranchEntry::apply_labels(&mut entries, &label_store.load(&repo.path));
+    entries.retain(|entry| {
+        args.labels.iter().all(|label| entry.labels.contains(label))
+            && (!args.with_worktree || entry.worktree_path.is_some())
+            && (!args.with_session || entry.has_session)
+            && (!args.with_agent
+                || entry.has_session
+                    && entry.worktree_path.as_ref().is_some_and(|path| {
+                        detect_agent(config, tmux, &repo.name, &repo.tmux_session_name(path))
+                            .is_some()
+                    }))
+            && containing
+                .as_ref()
+                .is_none_or(|names| names.contains(&entry.ref_name()))
+    });
+    BranchEntry::apply_last_commits(&mut entries, &git.last_commits(&repo.path));
+    BranchEntry::apply_notes(&mut entries, &notes.load(&repo.path));
+    BranchEntry::sort_entries(&mut entries);
+    BranchEntry::group_by_labels(&mut entries, &config.label_order);
+
+    Ok(entries)
+}
+
+/// The state files that opening, closing and deleting sessions keep up to date.
+#[derive(Debug, Clone, Default)]
+pub struct Stores {
+    pub visits: FrecencyStore,
+    pub sessions: SessionJournal,
+    pub workspaces: WorkspaceStore,
+    pub deletes: DeleteJournal,
+    /// Deletes in progress, so two kiosks don't remove the same worktree
+    pub pending_deletes: PendingDeleteStore,
+}
+
+impl Stores {
+    /// Stores that never save or return anything.
+    pub fn disabled() -> Self {
+        Self {
+            visits: FrecencyStore::disabled(),
+            sessions: SessionJournal::disabled(),
+            workspaces: WorkspaceStore::disabled(),
+            deletes: DeleteJournal::disabled(),
+            pending_deletes: PendingDeleteStore::disabled(),
+        }
+    }
+}
+
+/// `kiosk open`: `--ticket` becomes a new branch, noted with the ticket's link once it's
+/// open, and `--link` opens the branch in the linked repos too.
+pub fn open(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    args: &OpenArgs,
+) -> CliResult<OpenOutput> {
+    let Some(id) = &args.ticket else {
+        return open_with_links(config, git, tmux, stores, args);
+    };
+    let ticket =
+        ticket::fetch(&config.ticket, id).map_err(|e| CliError::system(format!("{e:#}")))?;
+    let args = open_args_for_ticket(config, args, &ticket)?;
+    let output = open_with_links(config, git, tmux, stores, &args)?;
+    if let Some(branch) = &args.new_branch {
+        note_ticket(config, git, &output.repo, branch, &ticket);
+    }
+    Ok(output)
+}
+
+/// `args` with `--ticket` turned into `--new-branch`, named by `branch.name_template` (or
+/// `{ticket}-{slug}`). Fields other than `{ticket}` and `{slug}` can't be asked for here, so
+/// they need a default, like `{user}`.
+fn open_args_for_ticket(config: &Config, args: &OpenArgs, ticket: &Ticket) -> CliResult<OpenArgs> {
+    let template = config
+        .branch
+        .name_template
+        .as_deref()
+        .unwrap_or(ticket::DEFAULT_NAME_TEMPLATE);
+    let mut values = ticket.template_values();
+    for field in branch_name::template_fields(template) {
+        if values.iter().any(|(filled, _)| *filled == field) {
+            continue;
+        }
+        let value = branch_name::default_value(&field).ok_or_else(|| {
+            CliError::user(format!(
+                "branch.name_template needs {{{field}}}, which --ticket can't ask for"
+            ))
+        })?;
+        values.push((field, value));
+    }
+    let name = branch_name::from_template(template, &ticket.title, &values);
+    if let Err(problem) = branch_name::validate(&name) {
+        return Err(CliError::user(format!(
+            "invalid branch name '{name}' for {}: {problem}",
+            ticket.id
+        )));
+    }
+    Ok(OpenArgs {
+        new_branch: Some(name),
+        ticket: None,
+        detached: None,
+        ..args.clone()
+    })
+}
+
+/// Save the ticket's link as the note of the branch just created for it. The branch is
+/// already open by now, so failing to is only logged.
+fn note_ticket(config: &Config, git: &dyn GitProvider, repo: &str, branch: &str, ticket: &Ticket) {
+    let repos = git.discover_repos(&config.resolved_search_dirs());
+    let saved = resolve_repo_exact(&repos, repo)
+        .map_err(|e| e.message().to_string())
+        .and_then(|repo| {
+            NoteStore::default()
+                .set(&repo.path, branch, &ticket.url)
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = saved {
+        tracing::warn!("Failed to save the link to {} as a note: {e}", ticket.id);
+    }
+}
+
+pub fn render_open_progress(progress: &GitProgress) -> CliResult<String> {
+    output::render(
+        &OutputFormat::Json,
+        &OpenProgressOutput::from(progress),
+        |_| String::new(),
+    )
+}
+
+struct ResolvedWorktree {
+    path: PathBuf,
+    session_name: String,
+    created: bool,
+    branch: Option<String>,
+    /// Whether a new local branch was created alongside the worktree
+    created_branch: bool,
+}
+
+/// What `kiosk open` opened, for the placeholders in the session's commands.
+fn open_context(
+    git: &dyn GitProvider,
+    repo: &Repo,
+    resolved: &ResolvedWorktree,
+    args: &OpenArgs,
+) -> SessionContext {
+    SessionContext {
+        repo: repo.name.clone(),
+        branch: resolved.branch.clone(),
+        worktree: resolved.path.clone(),
+        session: resolved.session_name.clone(),
+        base: args
+            .base
+            .clone()
+            .filter(|_| args.new_branch.is_some())
+            .or_else(|| git.default_branch(&repo.path, &git.list_branches(&repo.path))),
+    }
+}
+
+/// The template to set up a session in `dir` with, with `args`' split command over its own.
+fn open_template(config: &Config, dir: &Path, args: &OpenArgs) -> SessionTemplate {
+    let mut template = config.session.for_dir(dir);
+    if let Some(split_command) = &args.split_command {
+        template.split_command = Some(split_command.clone());
+    }
+    template
+}
+
+fn is_worktree_already_used_error(error: &anyhow::Error) -> bool {
+    error.to_string().contains("already used by worktree")
+}
+
+fn stale_worktree_hint(repo_path: &std::path::Path) -> String {
+    format!(
+        "stale worktree metadata may be blocking this branch. Try `git -C {} worktree prune --expire now` (or `kiosk clean`).",
+        repo_path.display()
+    )
+}
+
+fn run_with_stale_worktree_retry<F>(
+    git: &dyn GitProvider,
+    repo_path: &std::path::Path,
+    mut operation: F,
+) -> CliResult<()>
+where
+    F: FnMut() -> anyhow::Result<()>,
+{
+    let first_error = match operation() {
+        Ok(()) => return Ok(()),
+        Err(error) => error,
+    };
+
+    if !is_worktree_already_used_error(&first_error) {
+        return Err(CliError::from(first_error));
+    }
+
+    if let Err(prune_error) = git.prune_worktrees(repo_path) {
+        return Err(CliError::system(format!(
+            "{first_error}\n{hint}\nFailed to prune stale metadata automatically: {prune_error}",
+            hint = stale_worktree_hint(repo_path)
+        )));
+    }
+
+    operation().map_err(|retry_error| {
+        CliError::system(format!("{retry_error}\n{}", stale_worktree_hint(repo_path)))
+    })
+}
+
+/// Add the worktree at `path` with `create`, recorded as in progress so `kiosk recover` can
+/// clean up if kiosk dies part-way through.
+fn create_worktree<F>(
+    git: &dyn GitProvider,
+    repo: &Repo,
+    path: &Path,
+    branch: Option<&str>,
+    create: F,
+) -> CliResult<()>
+where
+    F: FnMut() -> anyhow::Result<()>,
+{
+    let pending = PendingWorktreeCreate::new(
+        repo.path.clone(),
+        branch.map(String::from),
+        path.to_path_buf(),
+    );
+    PendingCreateStore::default().track(&pending, || {
+        run_with_stale_worktree_retry(git, &repo.path, create)
+    })
+}
+
+/// `args` with `--agent` turned into the agent's command to run, logging it unless the agent
+/// opts out.
+fn with_agent(config: &Config, args: &OpenArgs) -> CliResult<OpenArgs> {
+    let Some(name) = &args.agent else {
+        return Ok(args.clone());
+    };
+    if args.run.is_some() {
+        return Err(CliError::user("cannot use --run and --agent together"));
+    }
+    let Some(agent) = config.agents.get(name) else {
+        let configured: Vec<&str> = config.agents.keys().map(String::as_str).collect();
+        return Err(CliError::user(if configured.is_empty() {
+            format!("unknown agent '{name}': no agents are set up under [agents] in the config")
+        } else {
+            format!(
+                "unknown agent '{name}': expected one of {}",
+                configured.join(", ")
+            )
+        }));
+    };
+    Ok(OpenArgs {
+        run: Some(agent.command.clone()),
+        log: args.log || agent.log,
+        ..args.clone()
+    })
+}
+
+/// Reject `kiosk open` flags that don't go together.
+fn validate_open_args(tmux: &dyn TmuxProvider, args: &OpenArgs) -> CliResult<()> {
+    if args.branch.is_some() && args.new_branch.is_some() {
+        return Err(CliError::user(
+            "cannot use positional branch and --new-branch together",
+        ));
+    }
+    if args.base.is_some() && args.new_branch.is_none() {
+        return Err(CliError::user(
+            "--base can only be used together with --new-branch",
+        ));
+    }
+    if args.new_branch.is_some() && args.base.is_none() {
+        return Err(CliError::user("--new-branch requires --base"));
+    }
+    if let Some(name) = &args.new_branch
+        && let Err(problem) = branch_name::validate(name)
+    {
+        return Err(CliError::user(format!(
+            "invalid branch name '{name}': {problem}"
+        )));
+    }
+    if args.detached.is_some()
+        && (args.branch.is_some() || args.new_branch.is_some() || args.base.is_some())
+    {
+        return Err(CliError::user(
+            "--commit and --tag can't be used together with a branch",
+        ));
+    }
+    if args.remote.is_some() && (args.branch.is_none() || args.in_place) {
+        return Err(CliError::user(
+            "--remote can only be used together with a branch to open in a worktree",
+        ));
+    }
+    if args.wait && args.run.is_none() {
+        return Err(CliError::user("--wait requires --run or --agent"));
+    }
+    if args.in_place && args.branch.is_none() {
+        return Err(CliError::user("--in-place requires a branch"));
+    }
+    if args.link && (args.detached.is_some() || args.detach_others) {
+        return Err(CliError::user(
+            "--link can't be used together with --commit, --tag or --detach-others",
+        ));
+    }
+    ensure_can_switch(tmux, args.no_switch)
+}
+
+fn ensure_can_switch(tmux: &dyn TmuxProvider, no_switch: bool) -> CliResult<()> {
+    if !no_switch && !tmux.is_inside_tmux() {
+        return Err(CliError::user(
+            "not inside tmux. Use --no-switch to create the session without switching",
+        ));
+    }
+    Ok(())
+}
+
+/// Open `args`, then with `--link` the same branch in each of `linked_repos`, saving them all
+/// as a workspace named after the branch. The session is switched to once they're all open,
+/// as outside tmux the switch blocks until the client detaches.
+fn open_with_links(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    args: &OpenArgs,
+) -> CliResult<OpenOutput> {
+    if !args.link {
+        return open_session(config, git, tmux, stores, args);
+    }
+    ensure_can_switch(tmux, args.no_switch)?;
+    let no_switch = OpenArgs {
+        no_switch: true,
+        ..args.clone()
+    };
+    let mut output = open_session(config, git, tmux, stores, &no_switch)?;
+
+    let repos = git.discover_repos(&config.resolved_search_dirs());
+    let branch = output.branch.clone();
+    for name in config
+        .linked_repos
+        .iter()
+        .filter(|name| **name != output.repo)
+    {
+        let member = workspace::member_spec(name, branch.as_deref());
+        let opened = branch
+            .as_deref()
+            .ok_or_else(|| CliError::user("there's no branch to open, as HEAD is detached"))
+            .and_then(|branch| Ok((resolve_repo_exact(&repos, name)?, branch)))
+            .and_then(|(repo, branch)| {
+                let linked = branch_open_args(
+                    git,
+                    repo,
+                    Some(branch),
+                    linked_base(git, repo, args.base.as_deref()),
+                    &args.format,
+                );
+                open_session(config, git, tmux, stores, &linked)
+            });
+        match opened {
+            Ok(opened) => output.linked.push(WorkspaceSession {
+                member,
+                session: opened.session,
+                path: opened.path,
+            }),
+            Err(e) => output.link_failed.push(WorkspaceFailure {
+                member,
+                error: e.message().to_string(),
+            }),
+        }
+    }
+
+    if let Some(branch) = &branch
+        && !output.linked.is_empty()
+    {
+        let members = std::iter::once(workspace::member_spec(&output.repo, Some(branch)))
+            .chain(output.linked.iter().map(|linked| linked.member.clone()));
+        for member in members {
+            if let Err(e) = stores.workspaces.add(branch, &member) {
+                tracing::warn!("Failed to save workspace {branch}: {e}");
+            }
+        }
+    }
+    if !args.no_switch {
+        let closed = switch_and_close_idle_sessions(
+            config,
+            git,
+            tmux,
+            stores,
+            &output.session,
+            config.session.max_alive,
+        );
+        output.closed_sessions.extend(closed);
+    }
+    Ok(output)
+}
+
+/// What a linked repo creates a missing branch from: `base` where the repo has it, else the
+/// repo's default branch.
+fn linked_base(git: &dyn GitProvider, repo: &Repo, base: Option<&str>) -> Option<String> {
+    let branches = git.list_branches(&repo.path);
+    match base {
+        Some(base) if branches.iter().any(|branch| branch == base) => Some(base.to_string()),
+        _ => git.default_branch(&repo.path, &branches),
+    }
+}
+
+fn open_session(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    args: &OpenArgs,
+) -> CliResult<OpenOutput> {
+    let args = &with_agent(config, args)?;
+    validate_open_args(tmux, args)?;
+
+    let (repo, branch) = resolve_open_target(config, git, args)?;
+    let args = &OpenArgs {
+        branch,
+        ..args.clone()
+    };
+    let resolved = resolve_worktree_for_open(git, &repo, args)?;
+    let mut created = PartialOpen {
+        worktree: resolved.created.then(|| resolved.path.clone()),
+        branch: resolved
+            .created_branch
+            .then(|| resolved.branch.clone())
+            .flatten(),
+        session: None,
+    };
+
+    let context = open_context(git, &repo, &resolved, args);
+    let template = open_template(config, &resolved.path, args);
+    let session = template.expanded(&context);
+    if !tmux.session_exists(&resolved.session_name) {
+        if let Err(error) = create_session(
+            config,
+            tmux,
+            &context,
+            session.split_command.as_deref(),
+            &args.env,
+        ) {
+            return Err(partial_open_error(
+                git,
+                tmux,
+                &repo,
+                resolved.branch.as_deref(),
+                &created,
+                args.atomic,
+                error.into(),
+            ));
+        }
+        created.session = Some(resolved.session_name.clone());
+        if created.branch.is_some() {
+            run_script_hook(config, tmux, Hook::BranchCreated, &context);
+        }
+    }
+
+    // The template's command only runs in a session it just set up, and --run replaces it
+    let run = args
+        .run
+        .as_deref()
+        .map(|run| context.expand(run))
+        .or(created.session.as_ref().and(session.run));
+    let wait_output =
+        match prepare_opened_session(config, tmux, args, run.as_deref(), &resolved.session_name) {
+            Ok(wait_output) => wait_output,
+            Err(error) => {
+                return Err(partial_open_error(
+                    git,
+                    tmux,
+                    &repo,
+                    resolved.branch.as_deref(),
+                    &created,
+                    args.atomic,
+                    error,
+                ));
+            }
+        };
+
+    title_agent_pane(config, tmux, &context);
+
+    let closed_sessions =
+        switch_and_detach_others(config, git, tmux, stores, &resolved.session_name, args);
+    if let Err(error) = stores
+        .visits
+        .record_open(&repo.path, resolved.branch.as_deref())
+    {
+        tracing::warn!("Failed to record visit to {}: {error}", repo.name);
+    }
+    if created.session.is_some() || args.run.is_some() {
+        record_session(
+            &stores.sessions,
+            &repo.path,
+            &context,
+            template.split_command.as_deref(),
+            run.as_deref(),
+            created.branch.is_some() && args.new_branch.is_some(),
+        );
+    }
+
+    Ok(OpenOutput {
+        repo: repo.name,
+        branch: resolved.branch,
+        session: resolved.session_name,
+        path: resolved.path,
+        created: created.worktree.is_some() || created.session.is_some(),
+        wait: wait_output,
+        closed_sessions,
+        linked: Vec::new(),
+        link_failed: Vec::new(),
+    })
+}
+
+/// The main checkout `path` belongs to, and what the session `session` in `path` was
+/// opened on. `base` is the branch a new branch in `path` was created from, if it was.
+///
+/// `{repo}` (and so `[session.repo_env]`) is `repo` if the caller knows the repo's name, else
+/// the name of the discovered repo checked out there (see `repo_name`).
+pub fn session_context(
+    config: &Config,
+    git: &dyn GitProvider,
+    repo: Option<String>,
+    path: &Path,
+    session: &str,
+    base: Option<String>,
+) -> (PathBuf, SessionContext) {
+    let (repo_path, branch) = worktree_of(git, path).unzip();
+    let repo_path = repo_path.unwrap_or_else(|| path.to_path_buf());
+    let context = SessionContext {
+        repo: repo.unwrap_or_else(|| repo_name(config, git, &repo_path)),
+        branch: branch.flatten(),
+        worktree: path.to_path_buf(),
+        session: session.to_string(),
+        base: base.or_else(|| git.default_branch(&repo_path, &git.list_branches(&repo_path))),
+    };
+    (repo_path, context)
+}
+
+/// The name of the discovered repo whose main checkout is `repo_path` (canonical), as
+/// `kiosk open` names it. Only a repo outside the search dirs goes by its directory's name.
+fn repo_name(config: &Config, git: &dyn GitProvider, repo_path: &Path) -> String {
+    git.discover_repos(&config.resolved_search_dirs())
+        .into_iter()
+        .find(|repo| fs::canonicalize(&repo.path).is_ok_and(|path| path == repo_path))
+        .map_or_else(
+            || {
+                repo_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            },
+            |repo| repo.name,
+        )
+}
+
+/// Create the session for `context` with its environment (`env` over the configured one),
+/// naming its window after the branch with `session.set_titles`.
+pub fn create_session(
+    config: &Config,
+    tmux: &dyn TmuxProvider,
+    context: &SessionContext,
+    split_command: Option<&str>,
+    env: &[(String, String)],
+) -> anyhow::Result<()> {
+    let env = config.session.env_for(context, env);
+    tmux.create_session(&context.session, &context.worktree, split_command, &env)?;
+    if config.session.set_titles {
+        let name = context.branch.as_deref().unwrap_or(&context.repo);
+        if let Err(e) = tmux.rename_window(&context.session, name) {
+            tracing::warn!("Failed to name the window of {}: {e}", context.session);
+        }
+    }
+    run_script_hook(config, tmux, Hook::Open, context);
+    Ok(())
+}
+
+/// Call `hook` in the `[script]` for the session in `context`, typing any text it returns
+/// into the session.
+pub fn run_script_hook(
+    config: &Config,
+    tmux: &dyn TmuxProvider,
+    hook: Hook,
+    context: &SessionContext,
+) {
+    let event = scripting::session_event(context);
+    if let Some(text) = scripting::run_hook(&config.script, hook, &event)
+        && let Err(e) = tmux.send_keys(&context.session, &text)
+    {
+        tracing::warn!(
+            "Failed to send the {} text to {}: {e}",
+            hook.function(),
+            context.session
+        );
+    }
+}
+
+/// Remember a session kiosk just set up, so `kiosk resume` can recreate it if tmux loses it
+/// and `kiosk export` can save its layout, and the base of the branch when it was just created.
+pub fn record_session(
+    journal: &SessionJournal,
+    repo_path: &Path,
+    context: &SessionContext,
+    split_command: Option<&str>,
+    run: Option<&str>,
+    branch_created: bool,
+) {
+    let entry = OpenedSession {
+        split_command: split_command.map(ToString::to_string),
+        base: context.base.clone().filter(|_| branch_created),
+        ..OpenedSession::new(
+            context.session.clone(),
+            repo_path.to_path_buf(),
+            context.worktree.clone(),
+            context.branch.clone(),
+            run.map(ToString::to_string),
+        )
+    };
+    if let Err(e) = journal.record(entry) {
+        tracing::warn!("Failed to record session {}: {e}", context.session);
+    }
+}
+
+/// The agent running in `session`.
+fn detect_agent(
+    config: &Config,
+    tmux: &dyn TmuxProvider,
+    repo: &str,
+    session: &str,
+) -> Option<String> {
+    if !config.agent.detects_in(repo) {
+        return None;
+    }
+    agent::session_agent_pane(tmux, session).map(|(_, agent)| agent)
+}
+
+/// Title the pane an agent runs in after the agent, with `session.set_titles`. Called when a
+/// session is opened, so that commands polling sessions leave their panes alone.
+pub fn title_agent_pane(config: &Config, tmux: &dyn TmuxProvider, context: &SessionContext) {
+    if !config.session.set_titles || !config.agent.detects_in(&context.repo) {
+        return;
+    }
+    let session = &context.session;
+    if let Some((pane, agent)) = agent::session_agent_pane(tmux, session)
+        && let Err(e) = tmux.set_pane_title(session, &pane.to_string(), &agent)
+    {
+        tracing::warn!("Failed to title pane {pane} of {session}: {e}");
+    }
+}
+
+/// Switch to `session_name` unless `--no-switch` was given, then close idle sessions beyond
+/// `session.max_alive`, or all other idle ones with `--detach-others`. Returns those closed.
+fn switch_and_detach_others(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    session_name: &str,
+    args: &OpenArgs,
+) -> Vec<String> {
+    let max_alive = if args.detach_others {
+        Some(1)
+    } else {
+        config.session.max_alive
+    };
+    if args.no_switch {
+        max_alive.map_or_else(Vec::new, |max_alive| {
+            close_idle_sessions(config, git, tmux, stores, session_name, max_alive)
+        })
+    } else {
+        switch_and_close_idle_sessions(config, git, tmux, stores, session_name, max_alive)
+    }
+}
+
+/// Switch to `session_name`, then close idle sessions beyond `max_alive`. Outside tmux the
+/// switch attaches and blocks until the client detaches, so they're closed first instead.
+pub fn switch_and_close_idle_sessions(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    session_name: &str,
+    max_alive: Option<usize>,
+) -> Vec<String> {
+    let close = || {
+        max_alive.map_or_else(Vec::new, |max_alive| {
+            close_idle_sessions(config, git, tmux, stores, session_name, max_alive)
+        })
+    };
+    if tmux.is_inside_tmux() {
+        tmux.switch_to_session(session_name);
+        close()
+    } else {
+        let closed = close();
+        tmux.switch_to_session(session_name);
+        closed
+    }
+}
+
+/// Kill the least recently active kiosk sessions, other than `keep`, so that at most
+/// `max_alive` stay alive. Sessions with a client attached are never killed. Returns the
+/// names of the sessions killed.
+fn close_idle_sessions(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    keep: &str,
+    max_alive: usize,
+) -> Vec<String> {
+    let details = sessions_by_name(tmux);
+    let mut others: Vec<String> = active_sessions(config, git, tmux)
+        .into_iter()
+        .map(|active| active.session)
+        .filter(|session| session != keep)
+        .collect();
+    others.sort_by_key(|session| {
+        std::cmp::Reverse(details.get(session).map(|session| session.activity))
+    });
+
+    let mut closed = Vec::new();
+    for session in others.into_iter().skip(max_alive.saturating_sub(1)) {
+        if details
+            .get(&session)
+            .is_none_or(|session| session.clients == 0)
+        {
+            tracing::info!("Closing idle session {session} to stay within max_alive");
+            kill_session(tmux, &stores.sessions, &session);
+            closed.push(session);
+        }
+    }
+    closed
+}
+
+/// Pipe the output of `session_name`'s pane into its log, as `kiosk open --log` does.
+pub fn start_session_log(
+    config: &Config,
+    tmux: &dyn TmuxProvider,
+    session_name: &str,
+) -> CliResult<()> {
+    let log_path = log_path_for_session(session_name)?;
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| CliError::system(e.to_string()))?;
+    }
+    apply_log_retention(config);
+    tmux.pipe_pane(session_name, &log_path)
+        .map_err(CliError::from)
+}
+
+/// Run the `--log`, `--run` and `--wait` steps against a freshly resolved session.
+fn prepare_opened_session(
+    config: &Config,
+    tmux: &dyn TmuxProvider,
+    args: &OpenArgs,
+    run: Option<&str>,
+    session_name: &str,
+) -> CliResult<Option<WaitOutput>> {
+    if args.log {
+        start_session_log(config, tmux, session_name)?;
+    }
+
+    if let Some(command) = run {
+        tmux.send_keys(session_name, command)
+            .map_err(CliError::from)?;
+    }
+
+    if !args.wait {
+        return Ok(None);
+    }
+
+    let pane = args.wait_pane.resolve(tmux, session_name)?.to_string();
+    match wait_for_idle(
+        tmux,
+        session_name,
+        &pane,
+        args.wait_timeout,
+        config.agent.poll_interval(),
+    ) {
+        Ok(output) => Ok(Some(output)),
+        Err(e) if e.code() == CliError::TIMEOUT => Ok(Some(WaitOutput {
+            idle: false,
+            timed_out: true,
+            pane_command: tmux
+                .pane_current_command(session_name, &pane)
+                .unwrap_or_else(|_| "unknown".to_string()),
+            exit_code: None,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Attach details of anything `open` created before failing, rolling it back when `atomic` is set.
+fn partial_open_error(
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    repo: &Repo,
+    branch: Option<&str>,
+    created: &PartialOpen,
+    atomic: bool,
+    error: CliError,
+) -> CliError {
+    if created.is_empty() {
+        return error;
+    }
+
+    if atomic {
+        return match rollback_partial_open(git, tmux, &repo.path, created) {
+            Ok(()) => error
+                .with_context(format!("Rolled back: {}", created.describe()))
+                .with_details(&PartialOpenDetails {
+                    created: created.clone(),
+                    rolled_back: true,
+                    rollback: None,
+                }),
+            Err(rollback_error) => {
+                let hint = created.rollback_hint(repo, branch);
+                error
+                    .with_context(format!(
+                        "Rollback failed ({rollback_error}). Created: {}. To clean up manually: {hint}",
+                        created.describe()
+                    ))
+                    .with_details(&PartialOpenDetails {
+                        created: created.clone(),
+                        rolled_back: false,
+                        rollback: Some(hint),
+                    })
+            }
+        };
+    }
+
+    let hint = created.rollback_hint(repo, branch);
+    error
+        .with_context(format!(
+            "Created: {}. To roll back: {hint} (or pass --atomic)",
+            created.describe()
+        ))
+        .with_details(&PartialOpenDetails {
+            created: created.clone(),
+            rolled_back: false,
+            rollback: Some(hint),
+        })
+}
+
+fn rollback_partial_open(
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    repo_path: &std::path::Path,
+    created: &PartialOpen,
+) -> anyhow::Result<()> {
+    if let Some(session) = &created.session {
+        tmux.kill_session(session);
+    }
+    if let Some(worktree) = &created.worktree {
+        git.remove_worktree(worktree)?;
+        git.prune_worktrees(repo_path)?;
+    }
+    if let Some(branch) = &created.branch {
+        git.delete_branch(repo_path, branch)?;
+    }
+    Ok(())
+}
+
+/// Resolve the `open` target into a repo and optional branch.
+///
+/// Besides `<repo> [branch]`, accepts a bare `<branch>` of the known repo the
+/// current directory is inside and `<repo>/<branch>` shorthand, in that order, so a
+/// branch named like the shorthand opens in the current repo. Exact repo names always
+/// take precedence, and anything else is reported as an unknown repo.
+fn resolve_open_target(
+    config: &Config,
+    git: &dyn GitProvider,
+    args: &OpenArgs,
+) -> CliResult<(Repo, Option<String>)> {
+    let repos = git.discover_repos(&config.resolved_search_dirs());
+    let with_worktrees = |repo: &Repo| {
+        let mut repo = repo.clone();
+        repo.worktrees = git.list_worktrees(&repo.path);
+        repo
+    };
+
+    let shorthand_allowed = args.branch.is_none()
+        && args.new_branch.is_none()
+        && args.detached.is_none()
+        && !repos.iter().any(|repo| repo.name == args.repo);
+
+    if shorthand_allowed {
+        if let Some(repo) = find_repo_for_cwd(git, &repos)
+            && (git.list_branches(&repo.path).contains(&args.repo)
+                || git.list_remote_branches(&repo.path).contains(&args.repo))
+        {
+            return Ok((with_worktrees(repo), Some(args.repo.clone())));
+        }
+
+        if let Some((name, branch)) = args.repo.split_once('/')
+            && !branch.is_empty()
+            && let Some(repo) = repos.iter().find(|repo| repo.name == name)
+        {
+            return Ok((with_worktrees(repo), Some(branch.to_string())));
+        }
+    }
+
+    let repo = resolve_repo_exact(&repos, &args.repo)?;
+    Ok((with_worktrees(repo), args.branch.clone()))
+}
+
+/// Find the known repo whose main checkout or one of its worktrees is the current directory.
+fn find_repo_for_cwd<'a>(git: &dyn GitProvider, repos: &'a [Repo]) -> Option<&'a Repo> {
+    let canonical =
+        |path: &std::path::Path| dunce::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let cwd = canonical(&git.resolve_repo_from_cwd()?);
+    repos.iter().find(|repo| {
+        canonical(&repo.path) == cwd
+            || git
+                .list_worktrees(&repo.path)
+                .iter()
+                .any(|wt| canonical(&wt.path) == cwd)
+    })
+}
+
+fn in_place_stash_message(branch: &str) -> String {
+    format!("kiosk: in-place WIP on {branch}")
+}
+
+/// Switch the main checkout to `branch`, stashing its uncommitted changes under a name tied to
+/// the branch being left and reapplying any stashed when `branch` was last left the same way.
+fn checkout_in_place(
+    git: &dyn GitProvider,
+    repo: &Repo,
+    branch: &str,
+) -> CliResult<ResolvedWorktree> {
+    let resolved = ResolvedWorktree {
+        path: repo.path.clone(),
+        session_name: repo.tmux_session_name(&repo.path),
+        created: false,
+        branch: Some(branch.to_string()),
+        created_branch: false,
+    };
+    if let Some(worktree) = repo
+        .worktrees
+        .iter()
+        .find(|worktree| !worktree.is_main && worktree.branch.as_deref() == Some(branch))
+    {
+        return Err(CliError::user(format!(
+            "branch '{branch}' is checked out in worktree {}. Open it without --in-place",
+            worktree.path.display()
+        )));
+    }
+    let current = repo
+        .worktrees
+        .iter()
+        .find(|worktree| worktree.is_main)
+        .and_then(|worktree| worktree.branch.as_deref());
+    if current == Some(branch) {
+        return Ok(resolved);
+    }
+
+    let stash = git
+        .stash_changes(
+            &repo.path,
+            &in_place_stash_message(current.unwrap_or("HEAD")),
+        )
+        .map_err(CliError::from)?;
+    if let Err(error) = git.checkout_branch(&repo.path, branch) {
+        let mut error = CliError::from(error);
+        if let Some(stash) = stash
+            && let Err(pop_error) = git.pop_stash(&repo.path, &stash)
+        {
+            error = error.with_context(format!(
+                "Your changes are kept in stash {stash}, which failed to reapply: {pop_error}"
+            ));
+        }
+        return Err(error);
+    }
+    if let Some(stash) = &stash {
+        tracing::info!("Stashed changes in {} as {stash}", repo.name);
+    }
+
+    let Some(restore) = git
+        .find_stash(&repo.path, &in_place_stash_message(branch))
+        .map_err(CliError::from)?
+    else {
+        return Ok(resolved);
+    };
+    git.pop_stash(&repo.path, &restore).map_err(|error| {
+        CliError::system(format!(
+            "checked out '{branch}' but failed to reapply its stashed changes ({restore}): {error:#}"
+        ))
+    })?;
+    Ok(resolved)
+}
+
+/// The remote to track `branch` from: `requested` if it has the branch, otherwise the only
+/// remote that does. `None` when no remote has it.
+fn tracking_remote(
+    git: &dyn GitProvider,
+    repo: &Repo,
+    branch: &str,
+    requested: Option<&str>,
+) -> CliResult<Option<String>> {
+    let remotes: Vec<String> = git
+        .list_remotes(&repo.path)
+        .into_iter()
+        .filter(|remote| {
+            git.list_remote_branches_for_remote(&repo.path, remote)
+                .iter()
+                .any(|name| name == branch)
+        })
+        .collect();
+    match (requested, remotes.as_slice()) {
+        (Some(requested), _) if remotes.iter().any(|remote| remote == requested) => {
+            Ok(Some(requested.to_string()))
+        }
+        (Some(requested), _) => Err(CliError::not_found(format!(
+            "branch '{branch}' not found on remote '{requested}'"
+        ))),
+        (None, []) => Ok(None),
+        (None, [remote]) => Ok(Some(remote.clone())),
+        (None, _) => Err(CliError::user(format!(
+            "branch '{branch}' is on several remotes ({}); pick one with --remote",
+            remotes.join(", ")
+        ))),
+    }
+}
+
+fn resolve_worktree_for_open(
+    git: &dyn GitProvider,
+    repo: &Repo,
+    args: &OpenArgs,
+) -> CliResult<ResolvedWorktree> {
+    let local = git.list_branches(&repo.path);
+    let remote = git.list_remote_branches(&repo.path);
+
+    if args.in_place
+        && let Some(branch) = &args.branch
+    {
+        if !local.contains(branch) && !remote.contains(branch) {
+            return Err(CliError::not_found(format!("branch '{branch}' not found")));
+        }
+        return checkout_in_place(git, repo, branch);
+    }
+
+    if let Some(target) = &args.detached {
+        return open_detached(git, repo, target);
+    }
+
+    if let Some(new_branch) = &args.new_branch {
+        if local.iter().any(|branch| branch == new_branch)
+            || remote.iter().any(|branch| branch == new_branch)
+        {
+            return Err(CliError::already_exists(format!(
+                "branch '{new_branch}' already exists"
+            )));
+        }
+
+        let Some(base) = args.base.as_deref() else {
+            unreachable!("validated: --new-branch always requires --base");
+        };
+        if !local.iter().any(|branch| branch == base) {
+            return Err(CliError::not_found(format!(
+                "base branch '{base}' not found"
+            )));
+        }
+
+        let wt = worktree_dir(repo, new_branch).map_err(CliError::from)?;
+        create_worktree(git, repo, &wt, Some(new_branch), || {
+            git.create_branch_and_worktree(&repo.path, new_branch, base, &wt)
+        })?;
+        let session = repo.tmux_session_name(&wt);
+        Ok(ResolvedWorktree {
+            path: wt,
+            session_name: session,
+            created: true,
+            branch: Some(new_branch.clone()),
+            created_branch: true,
+        })
+    } else if let Some(branch) = &args.branch {
+        if let Some(existing) = find_worktree_by_branch(repo, branch) {
+            let session = repo.tmux_session_name(&existing);
+            Ok(ResolvedWorktree {
+                path: existing,
+                session_name: session,
+                created: false,
+                branch: Some(branch.clone()),
+                created_branch: false,
+            })
+        } else if local.iter().any(|name| name == branch) {
+            let wt = worktree_dir(repo, branch).map_err(CliError::from)?;
+            create_worktree(git, repo, &wt, Some(branch), || {
+                git.add_worktree(&repo.path, branch, &wt)
+            })?;
+            let session = repo.tmux_session_name(&wt);
+            Ok(ResolvedWorktree {
+                path: wt,
+                session_name: session,
+                created: true,
+                branch: Some(branch.clone()),
+                created_branch: false,
+            })
+        } else if let Some(remote) = tracking_remote(git, repo, branch, args.remote.as_deref())? {
+            let wt = worktree_dir(repo, branch).map_err(CliError::from)?;
+            create_worktree(git, repo, &wt, Some(branch), || {
+                git.create_tracking_branch_and_worktree(&repo.path, &remote, branch, &wt)
+            })?;
+            let session = repo.tmux_session_name(&wt);
+            Ok(ResolvedWorktree {
+                path: wt,
+                session_name: session,
+                created: true,
+                branch: Some(branch.clone()),
+                created_branch: true,
+            })
+        } else {
+            Err(CliError::not_found(format!(
+                "branch '{branch}' not found. Use --new-branch to create it"
+            )))
+        }
+    } else {
+        let wt = repo.path.clone();
+        let session = repo.tmux_session_name(&wt);
+        let branch = repo
+            .worktrees
+            .iter()
+            .find(|w| w.is_main)
+            .and_then(|w| w.branch.clone());
+        Ok(ResolvedWorktree {
+            path: wt,
+            session_name: session,
+            created: false,
+            branch,
+            created_branch: false,
+        })
+    }
+}
+
+/// Reuse a worktree already detached at `target`'s commit, or add one named after it.
+fn open_detached(
+    git: &dyn GitProvider,
+    repo: &Repo,
+    target: &DetachedTarget,
+) -> CliResult<ResolvedWorktree> {
+    let commit = git
+        .resolve_commit(&repo.path, &target.rev())
+        .map_err(|_| match target {
+            DetachedTarget::Commit(commit) => {
+                CliError::not_found(format!("commit '{commit}' not found"))
+            }
+            DetachedTarget::Tag(tag) => CliError::not_found(format!("tag '{tag}' not found")),
+        })?;
+    let existing = repo
+        .worktrees
+        .iter()
+        .filter(|wt| !wt.is_main && wt.branch.is_none())
+        .find(|wt| git.head_commit(&wt.path).is_ok_and(|head| head == commit));
+    let (path, created) = if let Some(existing) = existing {
+        (existing.path.clone(), false)
+    } else {
+        let wt = worktree_dir(repo, &target.worktree_name(&commit)).map_err(CliError::from)?;
+        create_worktree(git, repo, &wt, None, || {
+            git.add_detached_worktree(&repo.path, &commit, &wt)
+        })?;
+        (wt, true)
+    };
+    let session = repo.tmux_session_name(&path);
+    Ok(ResolvedWorktree {
+        path,
+        session_name: session,
+        created,
+        branch: None,
+        created_branch: false,
+    })
+}
+
+pub fn status(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &StatusArgs,
+) -> CliResult<StatusOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+
+    let worktree_path = if let Some(branch) = &args.branch {
+        find_worktree_by_branch(&repo, branch)
+            .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{branch}'")))?
+    } else {
+        repo.path.clone()
+    };
+
+    let lines = args.lines.max(1);
+    let session_name = repo.tmux_session_name(&worktree_path);
+    let session_exists = tmux.session_exists(&session_name);
+
+    let (output, clients, source) = if session_exists {
+        let pane = args.pane.resolve(tmux, &session_name)?;
+        let captured = tmux
+            .capture_pane_with_pane(&session_name, &pane.to_string(), lines)
+            .map_err(CliError::from)?;
+        let clients = tmux.list_clients(&session_name);
+        (captured, clients, StatusSource::Live)
+    } else {
+        let log_path = log_path_for_session(&session_name)?;
+        if !log_path.exists() {
+            return Err(CliError::not_found(format!(
+                "session '{session_name}' does not exist"
+            )));
+        }
+        let log = fs::read_to_string(&log_path)
+            .with_context(|| format!("failed to read log file {}", log_path.display()))
+            .map_err(CliError::from)?;
+        (tail_lines(&log, lines), Vec::new(), StatusSource::Log)
+    };
+
+    let output = if args.strip_ansi {
+        strip_ansi_lines(&output)
+    } else {
+        output
+    };
+
+    Ok(StatusOutput {
+        session: session_name,
+        path: worktree_path,
+        attached: !clients.is_empty(),
+        clients: clients.len(),
+        source,
+        output,
+    })
+}
+
+pub fn status_all(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &StatusAllArgs,
+) -> Vec<SessionStatusOutput> {
+    let lines = args.lines.max(1);
+    active_sessions(config, git, tmux)
+        .into_iter()
+        .map(|active| {
+            // One session without the requested pane shouldn't hide the others
+            let captured = args
+                .pane
+                .resolve(tmux, &active.session)
+                .and_then(|pane| {
+                    tmux.capture_pane_with_pane(&active.session, &pane.to_string(), lines)
+                        .map_err(CliError::from)
+                })
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to capture {}: {e}", active.session);
+                    String::new()
+                });
+            let clients = tmux.list_clients(&active.session).len();
+            SessionStatusOutput {
+                agent: detect_agent(config, tmux, &active.repo, &active.session),
+                attached: clients > 0,
+                clients,
+                output: if args.strip_ansi {
+                    strip_ansi_lines(&captured)
+                } else {
+                    captured
+                },
+                session: active.session,
+                repo: active.repo,
+                branch: active.branch,
+                path: active.path,
+            }
+        })
+        .collect()
+}
+
+/// Every session's details in one query, by session name.
+fn sessions_by_name(tmux: &dyn TmuxProvider) -> HashMap<String, SessionDetails> {
+    tmux.list_sessions_detailed()
+        .into_iter()
+        .map(|session| (session.name.clone(), session))
+        .collect()
+}
+
+/// A running kiosk session and the worktree it belongs to.
+struct ActiveSession {
+    session: String,
+    repo: String,
+    branch: Option<String>,
+    path: PathBuf,
+}
+
+/// Every running session that belongs to a discovered worktree, sorted by session name.
+fn active_sessions(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+) -> Vec<ActiveSession> {
+    let repos = discover_all_with_worktrees(config, git);
+    let running: HashSet<String> = tmux.list_session_names().into_iter().collect();
+    let mut sessions: Vec<ActiveSession> = repos
+        .iter()
+        .flat_map(|repo| {
+            repo.worktrees.iter().map(|worktree| ActiveSession {
+                session: repo.tmux_session_name(&worktree.path),
+                repo: repo.name.clone(),
+                branch: worktree.branch.clone(),
+                path: worktree.path.clone(),
+            })
+        })
+        .filter(|active| running.contains(&active.session))
+        .collect();
+    sessions.sort_by(|left, right| left.session.cmp(&right.session));
+    sessions
+}
+
+pub fn capture(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &CaptureArgs,
+) -> CliResult<CaptureOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+
+    let worktree_path = if let Some(branch) = &args.branch {
+        find_worktree_by_branch(&repo, branch)
+            .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{branch}'")))?
+    } else {
+        repo.path.clone()
+    };
+
+    let session_name = repo.tmux_session_name(&worktree_path);
+    ensure_session_exists(tmux, &session_name)?;
+
+    let pane = args.pane.resolve(tmux, &session_name)?;
+    let history_start = args
+        .history
+        .map(|lines| -i64::try_from(lines).unwrap_or(i64::MAX));
+    let range = CaptureRange {
+        start: args.start.or(history_start),
+        end: args.end,
+        escapes: args.escapes,
+    };
+    let output = tmux
+        .capture_pane_range(&session_name, &pane.to_string(), &range)
+        .map_err(CliError::from)?;
+
+    Ok(CaptureOutput {
+        session: session_name,
+        pane,
+        output,
+    })
+}
+
+pub fn sessions(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+) -> Vec<SessionOutput> {
+    let details = sessions_by_name(tmux);
+    active_sessions(config, git, tmux)
+        .into_iter()
+        .map(|active| {
+            let session = details.get(&active.session);
+            SessionOutput {
+                last_activity: session.map_or(0, |session| session.activity),
+                pane_count: session.map_or(1, |session| session.pane_count),
+                current_command: session.map_or_else(
+                    || "unknown".to_string(),
+                    |session| session.current_command.clone(),
+                ),
+                attached: session.is_some_and(|session| session.clients > 0),
+                session: active.session,
+                repo: active.repo,
+                branch: active.branch,
+                path: active.path,
+            }
+        })
+        .collect()
+}
+
+/// What the process trees under each session's panes use, from one `processes` snapshot.
+pub fn top(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    processes: &ProcessTable,
+    args: &TopArgs,
+) -> CliResult<Vec<TopEntry>> {
+    let repo = match &args.repo {
+        Some(name) => Some(resolve_repo_with_worktrees(config, git, name)?.name),
+        None => None,
+    };
+    let mut entries: Vec<TopEntry> = active_sessions(config, git, tmux)
+        .into_iter()
+        .filter(|active| repo.as_ref().is_none_or(|repo| *repo == active.repo))
+        .map(|active| {
+            // Providers that can't tell a pane's process report pid 0
+            let roots: Vec<u32> = tmux
+                .list_panes_detailed(&active.session)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|pane| pane.pid)
+                .filter(|pid| *pid != 0)
+                .collect();
+            let usage = processes.tree_usage(&roots);
+            TopEntry {
+                session: active.session,
+                repo: active.repo,
+                branch: active.branch,
+                path: active.path,
+                cpu_percent: usage.cpu_percent,
+                memory_bytes: usage.rss_kib * 1024,
+                processes: usage.processes,
+                busiest_command: usage.busiest,
+            }
+        })
+        .collect();
+    // Sessions come sorted by name, which stays the tie-break
+    match args.sort {
+        TopSort::Cpu => entries.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent)),
+        TopSort::Memory => entries.sort_by_key(|entry| std::cmp::Reverse(entry.memory_bytes)),
+        TopSort::Session => {}
+    }
+    Ok(entries)
+}
+
+pub fn delete(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &DeleteArgs,
+    confirm: &Confirm,
+    stores: &Stores,
+) -> CliResult<DeleteOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+    let local = git.list_branches(&repo.path);
+    let sessions = tmux.list_session_names();
+    let entries = BranchEntry::build_sorted(&repo, &local, &sessions);
+
+    let entry = entries
+        .iter()
+        .find(|entry| entry.name == args.branch)
+        .ok_or_else(|| CliError::not_found(format!("branch '{}' not found", args.branch)))?;
+
+    let worktree_path = entry
+        .worktree_path
+        .as_ref()
+        .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{}'", args.branch)))?;
+
+    if entry.is_current {
+        return Err(CliError::user(
+            "cannot delete the current branch's worktree",
+        ));
+    }
+
+    if !args.force_dirty {
+        ensure_clean_worktree(git, &args.branch, worktree_path)?;
+    }
+
+    let session_name = repo.tmux_session_name(worktree_path);
+    let session_exists = tmux.session_exists(&session_name);
+    if session_exists
+        && !args.force
+        && !tmux.list_clients(&session_name).is_empty()
+        && !confirm.ask(&format!(
+            "Session '{session_name}' is attached. Kill it and delete the worktree?"
+        ))?
+    {
+        return Err(CliError::user(format!(
+            "session '{session_name}' is attached. Use --force or --yes"
+        )));
+    }
+
+    let pending = &stores.pending_deletes;
+    let started = pending
+        .begin(PendingWorktreeDelete::new(
+            repo.path.clone(),
+            args.branch.clone(),
+            worktree_path.clone(),
+        ))
+        .map_err(CliError::from)?;
+    if !started {
+        return Err(CliError::user(
+            "worktree deletion already in progress. If kiosk was interrupted, run 'kiosk recover'",
+        ));
+    }
+
+    if session_exists {
+        kill_session(tmux, &stores.sessions, &session_name);
+    }
+    let log_path = log_path_for_session(&session_name)?;
+    if log_path.exists()
+        && let Err(error) = fs::remove_file(&log_path)
+    {
+        pending
+            .finish(|entry| entry.is_for(&repo.path, &args.branch))
+            .map_err(CliError::from)?;
+        return Err(CliError::system(format!(
+            "failed to remove log file {}: {error}",
+            log_path.display()
+        )));
+    }
+
+    let remove_result = remove_worktree_with_journal(
+        git,
+        &stores.deletes,
+        &repo.path,
+        &args.branch,
+        worktree_path,
+        config.clean.use_trash,
+    );
+
+    pending
+        .finish(|entry| entry.is_for(&repo.path, &args.branch))
+        .map_err(CliError::from)?;
+
+    remove_result.map_err(|error| {
+        CliError::system(format!(
+            "failed to remove worktree {}: {error:#}",
+            worktree_path.display()
+        ))
+    })?;
+    git.prune_worktrees(&repo.path).map_err(CliError::from)?;
+
+    Ok(DeleteOutput {
+        deleted: true,
+        repo: repo.name,
+        branch: args.branch.clone(),
+        session: session_name,
+    })
+}
+
+/// User error, with the changes as details, when the worktree of `branch` has uncommitted
+/// changes that deleting it would put away.
+fn ensure_clean_worktree(
+    git: &dyn GitProvider,
+    branch: &str,
+    worktree_path: &Path,
+) -> CliResult<()> {
+    let changes = git.worktree_changes(worktree_path).map_err(|error| {
+        CliError::system(format!(
+            "couldn't check the worktree for '{branch}' for uncommitted changes: {error:#}. \
+             Use --force-dirty to delete it anyway"
+        ))
+    })?;
+    if changes.is_dirty() {
+        return Err(CliError::user(format!(
+            "worktree for '{branch}' has {}. Use --force-dirty to delete it anyway",
+            changes.describe()
+        ))
+        .with_details(&DirtyWorktreeDetails {
+            path: worktree_path,
+            changes: &changes,
+        }));
+    }
+    Ok(())
+}
+
+pub fn undo(
+    config: &Config,
+    git: &dyn GitProvider,
+    args: &UndoArgs,
+    journal: &DeleteJournal,
+) -> CliResult<UndoOutput> {
+    let repos = git.discover_repos(&config.resolved_search_dirs());
+    let repo = args
+        .repo
+        .as_deref()
+        .map(|name| resolve_repo_exact(&repos, name))
+        .transpose()?;
+
+    let entry = journal
+        .latest(repo.map(|r| r.path.as_path()), args.branch.as_deref())
+        .ok_or_else(|| CliError::not_found("no deleted worktree to restore"))?;
+
+    restore_deleted_worktree(git, journal, &entry).map_err(CliError::from)?;
+
+    let repo_name = repos
+        .iter()
+        .find(|r| r.path == entry.repo_path)
+        .map_or_else(|| entry.repo_path.display().to_string(), |r| r.name.clone());
+    Ok(UndoOutput {
+        repo: repo_name,
+        branch: entry.branch_name,
+        path: entry.worktree_path,
+        stash_restored: entry.stash.is_some(),
+    })
+}
+
+pub const SECS_PER_HOUR: u64 = 60 * 60;
+
+/// Kill kiosk sessions idle for longer than `args.idle_hours` as of `now`, removing the
+/// worktrees of merged branches with `args.remove_merged`. Attached sessions are left alone.
+pub fn reap(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &ReapArgs,
+    confirm: &Confirm,
+    stores: &Stores,
+    now: SystemTime,
+) -> CliResult<ReapOutput> {
+    let now = now
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let details = sessions_by_name(tmux);
+    let repos = discover_all_with_worktrees(config, git);
+
+    let mut reaped = Vec::new();
+    let mut failed = Vec::new();
+    for active in active_sessions(config, git, tmux) {
+        // Without the session's activity there's no telling how long it's been idle.
+        let Some(session) = details.get(&active.session) else {
+            continue;
+        };
+        let idle_secs = now.saturating_sub(session.activity);
+        if idle_secs < args.idle_hours.saturating_mul(SECS_PER_HOUR) || session.clients > 0 {
+            continue;
+        }
+        let merged_branch = args
+            .remove_merged
+            .then(|| repos.iter().find(|repo| repo.name == active.repo))
+            .flatten()
+            .and_then(|repo| merged_worktree_branch(git, repo, &active));
+
+        let worktree_removed = merged_branch.is_some();
+        if !args.dry_run {
+            if let Some(branch) = merged_branch {
+                let delete_args = DeleteArgs {
+                    repo: active.repo.clone(),
+                    branch,
+                    force: true,
+                    force_dirty: false,
+                    format: args.format.clone(),
+                };
+                if let Err(e) = delete(config, git, tmux, &delete_args, confirm, stores) {
+                    failed.push(ReapFailure {
+                        session: active.session,
+                        error: e.message().to_string(),
+                    });
+                    continue;
+                }
+            } else {
+                kill_session(tmux, &stores.sessions, &active.session);
+            }
+            tracing::info!("Reaped {} after {idle_secs}s idle", active.session);
+        }
+        reaped.push(ReapedSession {
+            session: active.session,
+            repo: active.repo,
+            branch: active.branch,
+            path: active.path,
+            idle_secs,
+            worktree_removed,
+        });
+    }
+
+    Ok(ReapOutput {
+        dry_run: args.dry_run,
+        reaped,
+        failed,
+    })
+}
+
+/// The branch of `active`'s worktree if it's a linked worktree whose branch has been merged
+/// into the repo's default branch.
+fn merged_worktree_branch(
+    git: &dyn GitProvider,
+    repo: &Repo,
+    active: &ActiveSession,
+) -> Option<String> {
+    let branch = active.branch.as_ref()?;
+    let is_linked = repo
+        .worktrees
+        .iter()
+        .any(|worktree| worktree.path == active.path && !worktree.is_main);
+    let default_branch = git.default_branch(&repo.path, &git.list_branches(&repo.path))?;
+    if !is_linked || *branch == default_branch {
+        return None;
+    }
+    let merged = git
+        .branches_containing(&repo.path, branch)
+        .ok()?
+        .contains(&default_branch);
+    let clean = git
+        .worktree_changes(&active.path)
+        .is_ok_and(|changes| !changes.is_dirty());
+    (merged && clean).then(|| branch.clone())
+}
+
+/// Delete local branches that have no worktree and whose commits are all on the default branch
+/// or a remote, asking first unless `--yes` is given.
+pub fn prune_branches(
+    config: &Config,
+    git: &dyn GitProvider,
+    args: &PruneBranchesArgs,
+    confirm: &Confirm,
+) -> CliResult<PruneBranchesOutput> {
+    let repos = match &args.repo {
+        Some(name) => vec![resolve_repo_with_worktrees(config, git, name)?],
+        None => discover_all_with_worktrees(config, git),
+    };
+
+    let mut prunable = Vec::new();
+    for repo in &repos {
+        let local = git.list_branches(&repo.path);
+        let Some(default_branch) = git.default_branch(&repo.path, &local) else {
+            continue;
+        };
+        for branch in local {
+            let has_worktree = repo
+                .worktrees
+                .iter()
+                .any(|worktree| worktree.branch.as_ref() == Some(&branch));
+            if branch == default_branch || has_worktree {
+                continue;
+            }
+            if git
+                .unique_commits(&repo.path, &branch, &default_branch)
+                .is_ok_and(|count| count == 0)
+            {
+                prunable.push((repo, branch));
+            }
+        }
+    }
+
+    if !args.dry_run && !prunable.is_empty() {
+        let list = prunable
+            .iter()
+            .fold(String::new(), |mut list, (repo, branch)| {
+                let _ = write!(list, "\n  {} {branch}", repo.name);
+                list
+            });
+        if !confirm.ask(&format!(
+            "Delete {} branch(es) with no worktree or unpushed commits?{list}\n",
+            prunable.len()
+        ))? {
+            return Err(CliError::user(
+                "branches not deleted. Use --yes to delete them without asking",
+            ));
+        }
+        for (repo, branch) in &prunable {
+            git.delete_branch(&repo.path, branch)
+                .map_err(CliError::from)?;
+            tracing::info!("Pruned branch {branch} of {}", repo.name);
+        }
+    }
+
+    Ok(PruneBranchesOutput {
+        dry_run: args.dry_run,
+        branches: prunable
+            .into_iter()
+            .map(|(repo, branch)| PrunedBranch {
+                repo: repo.name.clone(),
+                branch,
+            })
+            .collect(),
+    })
+}
+
+/// List interrupted worktree operations, rolling them forward or back if `args.direction` is set.
+pub fn recover(
+    config: &Config,
+    git: &dyn GitProvider,
+    recovery: &Recovery,
+    args: &RecoverArgs,
+    confirm: &Confirm,
+) -> CliResult<RecoverOutput> {
+    let interrupted = recovery.interrupted();
+
+    let mut errors = vec![None; interrupted.len()];
+    if let Some(direction) = args.direction
+        && !interrupted.is_empty()
+    {
+        let (verb, rerun) = match direction {
+            RecoverDirection::Forward => ("Finish", "--forward"),
+            RecoverDirection::Back => ("Undo", "--back"),
+        };
+        let list = interrupted
+            .iter()
+            .fold(String::new(), |mut list, operation| {
+                let _ = write!(list, "\n  {}", operation.describe());
+                list
+            });
+        if !confirm.ask(&format!(
+            "{verb} {} interrupted worktree operation(s)?{list}\n",
+            interrupted.len()
+        ))? {
+            return Err(CliError::user(format!(
+                "nothing recovered. Use {rerun} --yes to recover without asking"
+            )));
+        }
+        for (operation, error) in interrupted.iter().zip(&mut errors) {
+            let use_trash = config.clean.use_trash;
+            let result = match direction {
+                RecoverDirection::Forward => recovery.roll_forward(git, operation, use_trash),
+                RecoverDirection::Back => recovery.roll_back(git, operation, use_trash),
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to recover {}: {e:#}", operation.describe());
+                *error = Some(format!("{e:#}"));
+            }
+        }
+    }
+
+    Ok(RecoverOutput {
+        direction: args.direction,
+        operations: interrupted
+            .iter()
+            .zip(errors)
+            .map(|(operation, error)| recovered_operation(operation, error))
+            .collect(),
+    })
+}
+
+/// Finish every interrupted worktree operation, or undo them if `undo`, as `kiosk recover
+/// --forward` or `--back` does, returning why any couldn't be.
+pub fn recover_interrupted(
+    config: &Config,
+    git: &dyn GitProvider,
+    undo: bool,
+) -> CliResult<Vec<String>> {
+    let args = RecoverArgs {
+        direction: Some(if undo {
+            RecoverDirection::Back
+        } else {
+            RecoverDirection::Forward
+        }),
+        format: OutputFormat::Table,
+    };
+    let output = recover(
+        config,
+        git,
+        &Recovery::default(),
+        &args,
+        &Confirm::non_interactive(true),
+    )?;
+    Ok(output
+        .operations
+        .into_iter()
+        .filter_map(|operation| {
+            let error = operation.error?;
+            let target = operation
+                .branch
+                .unwrap_or_else(|| operation.path.display().to_string());
+            Some(format!("{} {target}: {error}", operation.kind))
+        })
+        .collect())
+}
+
+/// The operations in the audit log, oldest first, optionally only those on one repo and only
+/// the most recent.
+pub fn history(
+    config: &Config,
+    git: &dyn GitProvider,
+    log: &AuditLog,
+    args: &HistoryArgs,
+) -> CliResult<Vec<AuditEntry>> {
+    let repo = args
+        .repo
+        .as_deref()
+        .map(|name| resolve_repo_with_worktrees(config, git, name))
+        .transpose()?;
+    log.recent(args.limit, |entry| {
+        repo.as_ref()
+            .is_none_or(|repo| is_audit_entry_for(repo, entry))
+    })
+    .map_err(CliError::from)
+}
+
+/// Whether `entry` is about `repo`, one of its worktrees (removed ones included, going by
+/// kiosk's worktree naming) or one of their sessions.
+fn is_audit_entry_for(repo: &Repo, entry: &AuditEntry) -> bool {
+    let worktree_prefix = format!("{}{WORKTREE_NAME_SEPARATOR}", repo.name);
+    let session_prefix = format!("{}{WORKTREE_NAME_SEPARATOR}", repo.session_name);
+    let worktree_root = repo
+        .path
+        .parent()
+        .map(|parent| parent.join(WORKTREE_DIR_NAME));
+    let is_worktree = |path: &Path| {
+        path == repo.path
+            || repo.worktrees.iter().any(|worktree| worktree.path == path)
+            || (path.parent() == worktree_root.as_deref()
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(&worktree_prefix)))
+    };
+    entry.repo.as_ref() == Some(&repo.path)
+        || entry.path.as_deref().is_some_and(is_worktree)
+        || entry.session.as_ref().is_some_and(|session| {
+            *session == repo.tmux_session_name(&repo.path) || session.starts_with(&session_prefix)
+        })
+}
+
+fn recovered_operation(operation: &Interrupted, error: Option<String>) -> RecoveredOperation {
+    RecoveredOperation {
+        kind: operation.kind().to_string(),
+        repo_path: operation.repo_path().to_path_buf(),
+        branch: operation.branch_name().map(str::to_string),
+        path: operation.worktree_path().to_path_buf(),
+        started_at_unix_secs: operation.started_at_unix_secs(),
+        error,
+    }
+}
+
+/// Fetch the `fetch.remotes` of one repo or all of them, one remote at a time, the way the
+/// branch picker does in the background. A repo whose remotes all fetched counts towards
+/// `fetch.min_interval_secs`, so the picker won't fetch it again straight away.
+pub fn fetch(
+    config: &Config,
+    git: &dyn GitProvider,
+    history: &FetchHistoryStore,
+    args: &FetchArgs,
+) -> CliResult<FetchOutput> {
+    let discovered = git.discover_repos(&config.resolved_search_dirs());
+    let repos = match &args.repo {
+        Some(name) => vec![resolve_repo_exact(&discovered, name)?.clone()],
+        None => discovered,
+    };
+
+    let mut fetched = Vec::new();
+    for repo in repos {
+        let remotes: Vec<FetchedRemote> = git
+            .list_remotes(&repo.path)
+            .into_iter()
+            .filter(|remote| config.fetch.includes_remote(remote))
+            .map(|remote| {
+                let error = git
+                    .fetch_remote(&repo.path, &remote, args.prune)
+                    .err()
+                    .map(|e| format!("{e:#}").trim().to_string());
+                let branches = git
+                    .list_remote_branches_for_remote(&repo.path, &remote)
+                    .len();
+                FetchedRemote {
+                    remote,
+                    branches,
+                    error,
+                }
+            })
+            .collect();
+        if !remotes.is_empty()
+            && remotes.iter().all(|remote| remote.error.is_none())
+            && let Err(e) = history.record_fetch(&repo.path)
+        {
+            tracing::warn!("failed to record fetch of {}: {e}", repo.name);
+        }
+        fetched.push(FetchedRepo {
+            repo: repo.name,
+            path: repo.path,
+            remotes,
+        });
+    }
+    Ok(FetchOutput { repos: fetched })
+}
+
+/// Clone `args.url` into a search dir, where discovery picks it up straight away, and open a
+/// session in it with `--open`. Outside tmux the session is created without switching to it.
+pub fn clone(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    args: &CloneArgs,
+) -> CliResult<CloneOutput> {
+    let search_dirs = config.resolved_search_dirs();
+    let dir = clone_search_dir(&search_dirs, args.dir.as_deref())?;
+    let name = repo_name_from_url(&args.url).ok_or_else(|| {
+        CliError::user(format!("can't tell a directory name from '{}'", args.url))
+    })?;
+    let dest = dir.join(&name);
+    if dest.exists() {
+        return Err(CliError::already_exists(format!(
+            "{} already exists",
+            dest.display()
+        )));
+    }
+
+    git.clone_repo(&args.url, &dest)?;
+    tracing::info!("Cloned {} into {}", args.url, dest.display());
+
+    let repo = git
+        .discover_repos(&search_dirs)
+        .into_iter()
+        .find(|repo| same_path(&repo.path, &dest))
+        .ok_or_else(|| {
+            CliError::system(format!(
+                "cloned into {}, but it wasn't found in the search dirs",
+                dest.display()
+            ))
+        })?;
+
+    let session = if args.open {
+        let open_args = OpenArgs {
+            no_switch: !tmux.is_inside_tmux(),
+            ..OpenArgs::for_repo(repo.name.clone(), args.format.clone())
+        };
+        Some(open_session(config, git, tmux, stores, &open_args)?.session)
+    } else {
+        None
+    };
+
+    Ok(CloneOutput {
+        name: repo.name,
+        path: repo.path,
+        session,
+    })
+}
+
+/// Every kiosk-managed worktree, with the base and session layout the session journal recorded
+/// when kiosk set it up.
+pub fn export(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    journal: &SessionJournal,
+) -> ExportOutput {
+    let running: HashSet<String> = tmux.list_session_names().into_iter().collect();
+    let recorded = journal.load();
+    let mut worktrees = Vec::new();
+    for repo in discover_all_with_worktrees(config, git) {
+        let managed: Vec<&Worktree> = repo
+            .worktrees
+            .iter()
+            .filter(|worktree| {
+                if worktree.is_main {
+                    running.contains(&repo.tmux_session_name(&worktree.path))
+                } else {
+                    worktree.branch.is_some() && is_kiosk_worktree(&worktree.path)
+                }
+            })
+            .collect();
+        if managed.is_empty() {
+            continue;
+        }
+        let default_branch = git.default_branch(&repo.path, &git.list_branches(&repo.path));
+        worktrees.extend(managed.into_iter().map(|worktree| {
+            let entry = recorded
+                .iter()
+                .find(|entry| same_path(&entry.worktree_path, &worktree.path));
+            ExportedWorktree {
+                repo: repo.name.clone(),
+                branch: worktree.branch.clone().filter(|_| !worktree.is_main),
+                base: entry
+                    .and_then(|entry| entry.base.clone())
+                    .or_else(|| default_branch.clone()),
+                layout: entry.map(|entry| SessionLayout {
+                    split_command: entry.split_command.clone(),
+                    run: entry.run.clone(),
+                }),
+            }
+        }));
+    }
+    ExportOutput { worktrees }
+}
+
+/// Whether `path` is in a `.kiosk_worktrees` directory, where kiosk creates worktrees.
+fn is_kiosk_worktree(path: &Path) -> bool {
+    path.parent()
+        .and_then(Path::file_name)
+        .is_some_and(|name| name == WORKTREE_DIR_NAME)
+}
+
+pub fn import(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    args: &ImportArgs,
+) -> CliResult<ImportOutput> {
+    let contents = match &args.stdin {
+        Some(contents) => contents.clone(),
+        None => fs::read_to_string(&args.file)
+            .map_err(|e| CliError::user(format!("failed to read {}: {e}", args.file.display())))?,
+    };
+    let state = parse_export(&contents).map_err(|e| {
+        CliError::user(format!("{} isn't a kiosk export: {e}", args.file.display()))
+    })?;
+
+    let repos = git.discover_repos(&config.resolved_search_dirs());
+    let mut output = ImportOutput {
+        opened: Vec::new(),
+        failed: Vec::new(),
+    };
+    for worktree in state.worktrees {
+        let opened = resolve_repo_exact(&repos, &worktree.repo).and_then(|repo| {
+            let mut args = branch_open_args(
+                git,
+                repo,
+                worktree.branch.as_deref(),
+                worktree.base.clone(),
+                &args.format,
+            );
+            if let Some(layout) = &worktree.layout {
+                args.split_command.clone_from(&layout.split_command);
+                args.run.clone_from(&layout.run);
+            }
+            open_session(config, git, tmux, stores, &args)
+        });
+        match opened {
+            Ok(opened) => output.opened.push(ImportedSession {
+                repo: worktree.repo,
+                branch: worktree.branch,
+                session: opened.session,
+                path: opened.path,
+            }),
+            Err(e) => output.failed.push(ImportFailure {
+                repo: worktree.repo,
+                branch: worktree.branch,
+                error: e.message().to_string(),
+            }),
+        }
+    }
+    Ok(output)
+}
+
+/// An export as `kiosk export` prints it, in its envelope, or just the document inside.
+fn parse_export(contents: &str) -> serde_json::Result<ExportOutput> {
+    let mut value: serde_json::Value = serde_json::from_str(contents)?;
+    if let Some(data) = value.get_mut("data") {
+        value = data.take();
+    }
+    serde_json::from_value(value)
+}
+
+/// How to open `branch` in `repo` (its main checkout for `None`): on the branch, or creating
+/// it from `base` when it exists neither locally nor on a remote.
+fn branch_open_args(
+    git: &dyn GitProvider,
+    repo: &Repo,
+    branch: Option<&str>,
+    base: Option<String>,
+    format: &OutputFormat,
+) -> OpenArgs {
+    let mut args = OpenArgs::for_repo(repo.name.clone(), format.clone());
+    if let Some(branch) = branch {
+        if git.list_branches(&repo.path).iter().any(|b| b == branch)
+            || git
+                .list_remote_branches(&repo.path)
+                .iter()
+                .any(|b| b == branch)
+        {
+            args.branch = Some(branch.to_string());
+        } else {
+            args.new_branch = Some(branch.to_string());
+            args.base = base;
+        }
+    }
+    args
+}
+
+/// Recreate every lost session, as `kiosk resume --all` does, returning why any couldn't be.
+pub fn resume_lost_sessions(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+) -> CliResult<Vec<String>> {
+    let args = ResumeArgs {
+        sessions: Vec::new(),
+        all: true,
+        format: OutputFormat::Table,
+    };
+    let output = resume(config, git, tmux, &Stores::default(), &args)?;
+    Ok(output
+        .failed
+        .into_iter()
+        .map(|failed| format!("{}: {}", failed.session, failed.error))
+        .collect())
+}
+
+/// Recreate the lost sessions `args` asks for, each on its branch and with its command,
+/// listing the rest.
+pub fn resume(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    args: &ResumeArgs,
+) -> CliResult<ResumeOutput> {
+    let running = tmux.list_session_names();
+    let server_started_at = tmux.server_started_at();
+    stores
+        .sessions
+        .forget_closed(&running, server_started_at)
+        .map_err(|e| CliError::system(format!("failed to update the session journal: {e}")))?;
+    let lost = stores.sessions.lost(&running, server_started_at);
+    if let Some(unknown) = args
+        .sessions
+        .iter()
+        .find(|name| !lost.iter().any(|entry| &entry.session == *name))
+    {
+        return Err(CliError::not_found(format!(
+            "no lost session named '{unknown}'. See `kiosk resume`"
+        )));
+    }
+
+    let repos = git.discover_repos(&config.resolved_search_dirs());
+    let mut output = ResumeOutput::default();
+    for entry in lost {
+        if !args.all && !args.sessions.contains(&entry.session) {
+            output.lost.push(entry.into());
+            continue;
+        }
+        let resumed = resume_open_args(&repos, &entry, &args.format)
+            .and_then(|open_args| open_session(config, git, tmux, stores, &open_args));
+        match resumed {
+            Ok(opened) => output.resumed.push(ResumedSession {
+                session: opened.session,
+                path: opened.path,
+            }),
+            Err(e) => output.failed.push(ResumeFailure {
+                session: entry.session,
+                error: e.message().to_string(),
+            }),
+        }
+    }
+    Ok(output)
+}
+
+/// How to open a lost session again: in the same repo, on the branch it had checked out
+/// (the main checkout when that's where it was), typing the same command.
+fn resume_open_args(
+    repos: &[Repo],
+    entry: &OpenedSession,
+    format: &OutputFormat,
+) -> CliResult<OpenArgs> {
+    let repo = repos
+        .iter()
+        .find(|repo| same_path(&repo.path, &entry.repo_path))
+        .ok_or_else(|| {
+            CliError::not_found(format!(
+                "{} is no longer in a search dir",
+                entry.repo_path.display()
+            ))
+        })?;
+    let branch = if same_path(&entry.worktree_path, &entry.repo_path) {
+        None
+    } else {
+        let branch = entry.branch.clone().ok_or_else(|| {
+            CliError::user(format!(
+                "{} had a detached HEAD, so there's no branch to open",
+                entry.worktree_path.display()
+            ))
+        })?;
+        Some(branch)
+    };
+    Ok(OpenArgs {
+        branch,
+        run: entry.run.clone(),
+        ..OpenArgs::for_repo(repo.name.clone(), format.clone())
+    })
+}
+
+pub fn workspace_list(config: &Config, store: &WorkspaceStore) -> Vec<WorkspaceOutput> {
+    workspace::all(&config.workspaces, store.load())
+        .into_iter()
+        .map(|workspace| WorkspaceOutput {
+            name: workspace.name,
+            members: workspace.members,
+        })
+        .collect()
+}
+
+/// Open every session of the workspace called `name` and switch to the first one, as
+/// `kiosk workspace open` does, returning why any couldn't be opened.
+pub fn open_workspace(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    name: &str,
+) -> CliResult<Vec<String>> {
+    let args = WorkspaceOpenArgs {
+        name: name.to_string(),
+        no_switch: false,
+        format: OutputFormat::Table,
+    };
+    let output = workspace_open(config, git, tmux, &Stores::default(), &args)?;
+    Ok(output
+        .failed
+        .into_iter()
+        .map(|failed| format!("{}: {}", failed.member, failed.error))
+        .collect())
+}
+
+/// Open each member of a workspace without switching, then switch to the first session
+/// opened unless `args.no_switch`.
+pub fn workspace_open(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    stores: &Stores,
+    args: &WorkspaceOpenArgs,
+) -> CliResult<WorkspaceOpenOutput> {
+    let workspaces = workspace::all(&config.workspaces, stores.workspaces.load());
+    let workspace = workspaces
+        .into_iter()
+        .find(|workspace| workspace.name == args.name)
+        .ok_or_else(|| {
+            CliError::not_found(format!(
+                "no workspace named '{}'. See `kiosk workspace list`",
+                args.name
+            ))
+        })?;
+
+    let repos = git.discover_repos(&config.resolved_search_dirs());
+    let mut output = WorkspaceOpenOutput {
+        workspace: workspace.name,
+        opened: Vec::new(),
+        failed: Vec::new(),
+        switched_to: None,
+    };
+    for member in workspace.members {
+        let opened = workspace::resolve_member(&repos, &member)
+            .ok_or_else(|| {
+                CliError::not_found(format!(
+                    "no repo named '{member}', or '<repo>/<branch>' with a known repo"
+                ))
+            })
+            .and_then(|(repo, branch)| {
+                let open_args = OpenArgs {
+                    branch,
+                    ..OpenArgs::for_repo(repo.name.clone(), args.format.clone())
+                };
+                open_session(config, git, tmux, stores, &open_args)
+            });
+        match opened {
+            Ok(opened) => output.opened.push(WorkspaceSession {
+                member,
+                session: opened.session,
+                path: opened.path,
+            }),
+            Err(e) => output.failed.push(WorkspaceFailure {
+                member,
+                error: e.message().to_string(),
+            }),
+        }
+    }
+
+    if !args.no_switch
+        && let Some(first) = output.opened.first()
+    {
+        switch_and_close_idle_sessions(
+            config,
+            git,
+            tmux,
+            stores,
+            &first.session,
+            config.session.max_alive,
+        );
+        output.switched_to = Some(first.session.clone());
+    }
+    Ok(output)
+}
+
+/// The search dir named by `--dir`, or the first one when it isn't given.
+fn clone_search_dir(search_dirs: &[(PathBuf, u16)], dir: Option<&Path>) -> CliResult<PathBuf> {
+    let Some(dir) = dir else {
+        return search_dirs
+            .first()
+            .map(|(dir, _)| dir.clone())
+            .ok_or_else(|| {
+                CliError::user(
+                    "no search dirs to clone into. Add one to `search_dirs` in the config",
+                )
+            });
+    };
+    let dir = kiosk_core::paths::expand_tilde(&dir.to_string_lossy())
+        .unwrap_or_else(|| dir.to_path_buf());
+    search_dirs
+        .iter()
+        .find(|(search_dir, _)| same_path(search_dir, &dir))
+        .map(|(search_dir, _)| search_dir.clone())
+        .ok_or_else(|| {
+            let available = search_dirs
+                .iter()
+                .map(|(search_dir, _)| search_dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            CliError::user(format!(
+                "{} is not a search dir. Available: {available}",
+                dir.display()
+            ))
+        })
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    let canonical = |path: &Path| dunce::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    canonical(a) == canonical(b)
+}
+
+/// Resolve where a worktree, its session and its log live without creating anything. A
+/// branch without a worktree gets the directory `kiosk open` would create for it.
+pub fn path(config: &Config, git: &dyn GitProvider, args: &PathArgs) -> CliResult<PathOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+    let (path, exists) = match &args.branch {
+        None => (repo.path.clone(), true),
+        Some(branch) => {
+            if let Some(existing) = find_worktree_by_branch(&repo, branch) {
+                (existing, true)
+            } else if git.list_branches(&repo.path).contains(branch)
+                || git.list_remote_branches(&repo.path).contains(branch)
+            {
+                (worktree_dir(&repo, branch).map_err(CliError::from)?, false)
+            } else {
+                return Err(CliError::not_found(format!("branch '{branch}' not found")));
+            }
+        }
+    };
+    let session = repo.tmux_session_name(&path);
+    let log = log_path_for_session(&session)?;
+    Ok(PathOutput {
+        path,
+        exists,
+        session,
+        log,
+    })
+}
+
+pub fn note(
+    config: &Config,
+    git: &dyn GitProvider,
+    notes: &NoteStore,
+    args: &NoteArgs,
+) -> CliResult<NoteOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+    ensure_local_branch(git, &repo, &args.branch)?;
+    if let Some(note) = &args.note {
+        notes
+            .set(&repo.path, &args.branch, note)
+            .map_err(CliError::from)?;
+    }
+    Ok(NoteOutput {
+        repo: repo.name,
+        note: notes.load(&repo.path).remove(&args.branch),
+        branch: args.branch.clone(),
+    })
+}
+
+pub fn label(
+    config: &Config,
+    git: &dyn GitProvider,
+    store: &LabelStore,
+    args: &LabelArgs,
+) -> CliResult<LabelOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+    ensure_local_branch(git, &repo, &args.branch)?;
+    let labels = match args.change {
+        LabelChange::Add => store.add(&repo.path, &args.branch, &args.labels),
+        LabelChange::Remove => store.remove(&repo.path, &args.branch, &args.labels),
+    }
+    .map_err(|e| CliError::user(e.to_string()))?;
+    Ok(LabelOutput {
+        repo: repo.name,
+        branch: args.branch.clone(),
+        labels,
+    })
+}
+
+/// Show a repo hidden from the TUI in the repo list and `kiosk list` again. Fails when it
+/// isn't hidden, including when only an `ignore` glob in the config leaves it out.
+pub fn unhide(
+    config: &Config,
+    git: &dyn GitProvider,
+    hidden: &HiddenStore,
+    args: &UnhideArgs,
+) -> CliResult<UnhideOutput> {
+    let repos = git.discover_repos(&config.resolved_search_dirs());
+    let repo = resolve_repo_exact(&repos, &args.repo)?;
+    if !hidden.unhide(&repo.path).map_err(CliError::from)? {
+        return Err(CliError::user(format!("'{}' isn't hidden", repo.name)));
+    }
+    Ok(UnhideOutput {
+        repo: repo.name.clone(),
+        path: repo.path.clone(),
+    })
+}
+
+/// Not-found error unless `branch` is one of `repo`'s local branches.
+fn ensure_local_branch(git: &dyn GitProvider, repo: &Repo, branch: &str) -> CliResult<()> {
+    if git
+        .list_branches(&repo.path)
+        .iter()
+        .any(|name| name == branch)
+    {
+        Ok(())
+    } else {
+        Err(CliError::not_found(format!("branch '{branch}' not found")))
+    }
+}
+
+/// A session with output within this many seconds counts as working.
+const AGENT_WORKING_SECS: u64 = 5;
+
+/// Status bars call this every few seconds, so while the session stays quiet its summary comes
+/// from `cache`, leaving one tmux call for its path and one for its activity.
+pub fn statusline(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    cache: &StatuslineCache,
+    args: &StatuslineArgs,
+) -> CliResult<StatuslineOutput> {
+    let session = args
+        .session
+        .clone()
+        .or_else(|| tmux.current_session())
+        .ok_or_else(|| CliError::user("not inside a session. Use --session to name one"))?;
+    let path = tmux
+        .session_path(&session)
+        .map_err(|_| CliError::not_found(format!("session '{session}' does not exist")))?;
+
+    let activity = tmux.session_activity(&session).unwrap_or_default();
+    let summary = session_summary(config, git, tmux, cache, &session, &path, activity);
+    let agent_state = summary.agent.as_ref().map(|_| agent_state(activity));
+
+    Ok(StatuslineOutput {
+        session,
+        repo: summary.repo,
+        branch: summary.branch,
+        dirty: summary.dirty,
+        agent: summary.agent,
+        agent_state,
+    })
+}
+
+/// What's in `session`'s worktree at `path`: the summary `cache` has from after the session
+/// last had activity (at `activity`), or else a fresh look at the worktree, saved for next time.
+fn session_summary(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    cache: &StatuslineCache,
+    session: &str,
+    path: &Path,
+    activity: u64,
+) -> SessionSummary {
+    let earlier = cache.get(session, path);
+    if let Some(summary) = earlier
+        .as_ref()
+        .filter(|summary| summary.is_fresh(activity))
+    {
+        return summary.clone();
+    }
+    let (repo_path, branch) = worktree_of(git, path).unzip();
+    // Repos are only discovered for a session's first summary, as its repo keeps its name
+    let repo = earlier.map_or_else(
+        || repo_name(config, git, repo_path.as_deref().unwrap_or(path)),
+        |earlier| earlier.repo,
+    );
+    let summary = SessionSummary {
+        session: session.to_string(),
+        path: path.to_path_buf(),
+        branch: branch.flatten(),
+        dirty: git
+            .worktree_changes(path)
+            .is_ok_and(|changes| changes.files_changed > 0),
+        agent: detect_agent(config, tmux, &repo, session),
+        repo,
+        checked_at_unix_secs: now_unix_secs(),
+    };
+    if let Err(e) = cache.save(summary.clone()) {
+        tracing::warn!("Failed to cache the summary of {session}: {e}");
+    }
+    summary
+}
+
+/// An agent in a session last active at `activity` (Unix seconds) is working while there's
+/// recent output, and waiting for input after that.
+fn agent_state(activity: u64) -> AgentState {
+    if now_unix_secs().saturating_sub(activity) <= AGENT_WORKING_SECS {
+        AgentState::Working
+    } else {
+        AgentState::Waiting
+    }
+}
+
+/// Sessions whose agent is waiting for input. Sessions with recent activity are passed over
+/// before looking for an agent, and quiet ones are summarised from `cache` as for `statusline`.
+pub fn waybar(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    cache: &StatuslineCache,
+) -> WaybarOutput {
+    let waiting: Vec<String> = tmux
+        .list_sessions_with_activity()
+        .into_iter()
+        .filter(|(_, activity)| agent_state(*activity) == AgentState::Waiting)
+        .filter_map(|(session, activity)| {
+            let Ok(path) = tmux.session_path(&session) else {
+                let agent = detect_agent(config, tmux, &session, &session)?;
+                return Some(format!("{session}: {agent}"));
+            };
+            let summary = session_summary(config, git, tmux, cache, &session, &path, activity);
+            let agent = summary.agent?;
+            let place = match summary.branch {
+                Some(branch) => format!("{} {branch}", summary.repo),
+                None => summary.repo,
+            };
+            Some(format!("{place}: {agent}"))
+        })
+        .collect();
+
+    if waiting.is_empty() {
+        return WaybarOutput {
+            text: String::new(),
+            tooltip: "No agents waiting".to_string(),
+            class: "none",
+        };
+    }
+    WaybarOutput {
+        text: format!("{} {}", config.icons.glyphs().agent_waiting, waiting.len()),
+        tooltip: format!("Waiting for input:\n{}", waiting.join("\n")),
+        class: "waiting",
+    }
+}
+
+/// `repo branch*`, then the agent with `●` while it works and `○` while it waits (or their
+/// equivalents in `glyphs`).
+pub fn format_statusline(output: &StatuslineOutput, glyphs: &Glyphs) -> String {
+    let mut line = output.repo.clone();
+    if let Some(branch) = &output.branch {
+        line.push(' ');
+        line.push_str(branch);
+    }
+    if output.dirty {
+        line.push_str(glyphs.dirty);
+    }
+    if let Some(agent) = &output.agent {
+        let icon = match output.agent_state {
+            Some(AgentState::Working) => glyphs.agent_working,
+            _ => glyphs.agent_waiting,
+        };
+        let _ = write!(line, " {icon} {agent}");
+    }
+    line
+}
+
+pub fn diff(config: &Config, git: &dyn GitProvider, args: &DiffArgs) -> CliResult<DiffOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+    let worktree_path = find_worktree_by_branch(&repo, &args.branch)
+        .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{}'", args.branch)))?;
+    let base = match &args.base {
+        Some(base) => base.clone(),
+        None => git
+            .default_branch(&repo.path, &git.list_branches(&repo.path))
+            .ok_or_else(|| {
+                CliError::user(format!(
+                    "couldn't detect the default branch of '{}'. Use --base",
+                    repo.name
+                ))
+            })?,
+    };
+
+    let files: Vec<DiffFileOutput> = git
+        .diff_files(&worktree_path, &base)
+        .map_err(CliError::from)?
+        .into_iter()
+        .map(|change| DiffFileOutput {
+            path: change.path,
+            insertions: change.insertions,
+            deletions: change.deletions,
+        })
+        .collect();
+    let patch = if args.mode == DiffMode::Patch {
+        Some(
+            git.diff_patch(&worktree_path, &base)
+                .map_err(CliError::from)?,
+        )
+    } else {
+        None
+    };
+
+    Ok(DiffOutput {
+        repo: repo.name,
+        branch: args.branch.clone(),
+        base,
+        path: worktree_path,
+        insertions: files.iter().filter_map(|file| file.insertions).sum(),
+        deletions: files.iter().filter_map(|file| file.deletions).sum(),
+        files,
+        patch,
+    })
+}
+
+pub fn format_diff_stat(output: &DiffOutput) -> String {
+    let path_width = output
+        .files
+        .iter()
+        .map(|file| file.path.display().to_string().len())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for file in &output.files {
+        let counts = match (file.insertions, file.deletions) {
+            (Some(insertions), Some(deletions)) => format!("+{insertions} -{deletions}"),
+            _ => "binary".to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{:<path_width$}  {counts}",
+            file.path.display().to_string()
+        );
+    }
+    let _ = writeln!(
+        out,
+        "{} files changed against {}, +{} -{}",
+        output.files.len(),
+        output.base,
+        output.insertions,
+        output.deletions
+    );
+    out
+}
+
+pub fn send(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &SendArgs,
+) -> CliResult<SendOutput> {
+    // Check mutually exclusive flags
+    let mode_count = [
+        args.command.is_some(),
+        args.keys.is_some(),
+        args.text.is_some(),
+        args.stdin.is_some(),
+        args.file.is_some(),
+    ]
+    .iter()
+    .filter(|&&x| x)
+    .count();
+
+    if mode_count == 0 {
+        return Err(CliError::user(
+            "one of --command, --keys, --text, --stdin or --file is required",
+        ));
+    }
+
+    if mode_count > 1 {
+        return Err(CliError::user(
+            "options --command, --keys, --text, --stdin and --file are mutually exclusive",
+        ));
+    }
+
+    let confirm_pattern = args
+        .confirm_pattern
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| CliError::user(format!("invalid --confirm-pattern: {e}")))?;
+    // Read the payload before touching tmux so a missing file fails without side effects
+    let paste = read_send_payload(args)?;
+
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+
+    let worktree_path = if let Some(branch) = &args.branch {
+        find_worktree_by_branch(&repo, branch)
+            .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{branch}'")))?
+    } else {
+        repo.path.clone()
+    };
+
+    let session_name = repo.tmux_session_name(&worktree_path);
+    ensure_session_exists(tmux, &session_name)?;
+
+    let pane_index = args.pane.resolve(tmux, &session_name)?;
+    let pane = &pane_index.to_string();
+    if let Some(pattern) = &confirm_pattern {
+        confirm_pane_matches(tmux, &session_name, pane_index, pattern)?;
+    }
+
+    if let Some(command) = &args.command {
+        tmux.send_text_raw(&session_name, pane, command)
+            .map_err(CliError::from)?;
+        tmux.send_keys_raw(&session_name, pane, &["Enter"])
+            .map_err(CliError::from)?;
+    } else if let Some(keys_str) = &args.keys {
+        let keys: Vec<&str> = keys_str.split_whitespace().collect();
+        tmux.send_keys_raw(&session_name, pane, &keys)
+            .map_err(CliError::from)?;
+    } else if let Some(text) = &args.text {
+        tmux.send_text_raw(&session_name, pane, text)
+            .map_err(CliError::from)?;
+    } else if let Some(payload) = &paste {
+        tmux.paste_text(&session_name, pane, payload)
+            .map_err(CliError::from)?;
+        if !args.no_enter {
+            tmux.send_keys_raw(&session_name, pane, &["Enter"])
+                .map_err(CliError::from)?;
+        }
+    }
+
+    Ok(SendOutput {
+        session: session_name,
+        command: args.command.clone(),
+        keys: args.keys.clone(),
+        text: args.text.clone(),
+        pasted_bytes: paste.as_ref().map(String::len),
+        pane: pane_index,
+    })
+}
+
+/// Refuse to send unless the pane's visible contents, without trailing whitespace, match
+/// `pattern`, so keys meant for a prompt don't land in a command that's still running.
+fn confirm_pane_matches(
+    tmux: &dyn TmuxProvider,
+    session: &str,
+    pane: usize,
+    pattern: &Regex,
+) -> CliResult<()> {
+    let captured = tmux
+        .capture_pane_range(session, &pane.to_string(), &CaptureRange::default())
+        .map_err(CliError::from)?;
+    let captured = captured.trim_end();
+    if pattern.is_match(captured) {
+        return Ok(());
+    }
+    Err(CliError::user(format!(
+        "pane {pane} of session '{session}' doesn't match --confirm-pattern '{pattern}', so nothing was sent"
+    ))
+    .with_details(&ConfirmPatternDetails {
+        session,
+        pane,
+        captured,
+    }))
+}
+
+/// The `--stdin` or `--file` payload of `kiosk send`, if either was given. A single trailing
+/// newline is dropped, since Enter is sent separately.
+fn read_send_payload(args: &SendArgs) -> CliResult<Option<String>> {
+    let payload = if let Some(payload) = &args.stdin {
+        payload.clone()
+    } else if let Some(path) = &args.file {
+        fs::read_to_string(path)
+            .map_err(|e| CliError::user(format!("failed to read {}: {e}", path.display())))?
+    } else {
+        return Ok(None);
+    };
+
+    let payload = payload.strip_suffix('\n').map_or(payload.as_str(), |text| {
+        text.strip_suffix('\r').unwrap_or(text)
+    });
+    if payload.is_empty() {
+        return Err(CliError::user("nothing to send: the payload is empty"));
+    }
+    Ok(Some(payload.to_string()))
+}
+
+impl From<&BranchEntry> for BranchOutput {
+    fn from(entry: &BranchEntry) -> Self {
+        Self {
+            name: entry.name.clone(),
+            worktree_path: entry.worktree_path.clone(),
+            has_session: entry.has_session,
+            is_current: entry.is_current,
+            remote: entry.remote.clone(),
+            last_commit: entry.last_commit.as_ref().map(|commit| LastCommitOutput {
+                subject: commit.subject.clone(),
+                author: commit.author.clone(),
+                timestamp: commit.timestamp,
+            }),
+            note: entry.note.clone(),
+            labels: entry.labels.clone(),
+        }
+    }
+}
+
+/// Not-found error for a missing session, unless tmux itself can't be run.
+fn ensure_session_exists(tmux: &dyn TmuxProvider, session_name: &str) -> CliResult<()> {
+    if tmux.session_exists(session_name) {
+        Ok(())
+    } else if tmux.is_available() {
+        Err(CliError::not_found(format!(
+            "session '{session_name}' does not exist"
+        )))
+    } else {
+        Err(CliError::tmux_unavailable(
+            "tmux could not be run. Is it installed and on PATH?",
+        ))
+    }
+}
+
+/// The running session of `branch`'s worktree, or of the main checkout without one.
+fn resolve_session(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    repo: &str,
+    branch: Option<&str>,
+) -> CliResult<String> {
+    let repo = resolve_repo_with_worktrees(config, git, repo)?;
+    let worktree_path = if let Some(branch) = branch {
+        find_worktree_by_branch(&repo, branch)
+            .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{branch}'")))?
+    } else {
+        repo.path.clone()
+    };
+    let session_name = repo.tmux_session_name(&worktree_path);
+    ensure_session_exists(tmux, &session_name)?;
+    Ok(session_name)
+}
+
+fn find_worktree_by_branch(repo: &Repo, branch: &str) -> Option<PathBuf> {
+    repo.worktrees
+        .iter()
+        .find(|worktree| worktree.branch.as_deref() == Some(branch))
+        .map(|worktree| worktree.path.clone())
+}
+
+fn log_path_for_session(session_name: &str) -> CliResult<PathBuf> {
+    if session_name.is_empty()
+        || session_name.starts_with('.')
+        || session_name.contains('/')
+        || session_name.contains('\\')
+        || session_name.contains("..")
+    {
+        return Err(CliError::system("Invalid session name"));
+    }
+    Ok(log_dir()?.join(format!("{session_name}.log")))
+}
+
+/// The last `tail` lines of `content`, counting only lines that match `grep` if given.
+fn select_log_lines(content: &str, grep: Option<&Regex>, tail: usize) -> String {
+    match grep {
+        Some(grep) => {
+            let matching = content
+                .lines()
+                .filter(|line| grep.is_match(line))
+                .collect::<Vec<_>>();
+            matching[matching.len().saturating_sub(tail)..].join("\n")
+        }
+        None => tail_lines(content, tail),
+    }
+}
+
+fn tail_lines(content: &str, lines: usize) -> String {
+    let mut selected = content.lines().rev().take(lines).collect::<Vec<_>>();
+    selected.reverse();
+    selected.join("\n")
+}
+
+pub fn format_repo_table(repos: &[RepoOutput]) -> String {
+    let name_header = "repo";
+    let path_header = "path";
+    let name_width = repos
+        .iter()
+        .map(|repo| repo.name.len())
+        .max()
+        .unwrap_or(name_header.len())
+        .max(name_header.len());
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{name_header:<name_width$}  {path_header}");
+    for repo in repos {
+        let _ = writeln!(out, "{:<name_width$}  {}", repo.name, repo.path.display());
+    }
+    out
+}
+
+/// The `labels` and `note` columns only show up when some branch has labels or a note.
+pub fn format_branch_table(entries: &[BranchEntry]) -> String {
+    let with_remotes = entries.iter().any(|entry| entry.remote.is_some());
+    let with_labels = entries.iter().any(|entry| !entry.labels.is_empty());
+    let with_notes = entries.iter().any(|entry| entry.note.is_some());
+    let optional = |label: String, note: String| {
+        with_labels
+            .then_some(label)
+            .into_iter()
+            .chain(with_notes.then_some(note))
+    };
+
+    let mut rows: Vec<Vec<String>> = vec![
+        ["branch", "stat"]
+            .map(str::to_string)
+            .into_iter()
+            .chain(with_remotes.then(|| "remote".to_string()))
+            .chain(["worktree".to_string()])
+            .chain(optional("labels".to_string(), "note".to_string()))
+            .collect(),
+    ];
+    for entry in entries {
+        let stat = format!(
+            "{}{}{}{}",
+            if entry.is_current { '*' } else { '-' },
+            if entry.worktree_path.is_some() {
+                'W'
+            } else {
+                '-'
+            },
+            if entry.has_session { 'S' } else { '-' },
+            if entry.remote.is_some() { 'R' } else { '-' },
+        );
+        let worktree = entry
+            .worktree_path
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |path| path.display().to_string());
+        let labels = if entry.labels.is_empty() {
+            "-".to_string()
+        } else {
+            entry.labels.join(",")
+        };
+        let note = entry.note.clone().unwrap_or_else(|| "-".to_string());
+        rows.push(
+            [entry.name.clone(), stat]
+                .into_iter()
+                .chain(
+                    with_remotes.then(|| entry.remote.clone().unwrap_or_else(|| "-".to_string())),
+                )
+                .chain([worktree])
+                .chain(optional(labels, note))
+                .collect(),
+        );
+    }
+
+    format_rows(&rows)
+}
+
+/// Lines up `rows` (the header first) in columns, leaving the last column unpadded.
+fn format_rows(rows: &[Vec<String>]) -> String {
+    let columns = rows[0].len();
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| rows.iter().map(|row| row[i].len()).max().unwrap_or(0))
+        .collect();
+    let mut out = String::new();
+    for row in rows {
+        let line = row
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if i + 1 == columns {
+                    cell.clone()
+                } else {
+                    format!("{cell:<width$}", width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        let _ = writeln!(out, "{line}");
+    }
+    out
+}
+
+/// One block per session: a header with its attached state and agent, then its indented output.
+pub fn format_status_blocks(rows: &[SessionStatusOutput]) -> String {
+    let mut out = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let attached = if row.attached { "attached" } else { "detached" };
+        match &row.agent {
+            Some(agent) => {
+                let _ = writeln!(out, "{} ({attached}, {agent})", row.session);
+            }
+            None => {
+                let _ = writeln!(out, "{} ({attached})", row.session);
+            }
+        }
+        for line in row.output.lines() {
+            let _ = writeln!(out, "  {line}");
+        }
+    }
+    out
+}
+
+pub fn format_session_table(rows: &[SessionOutput]) -> String {
+    let session_header = "session";
+    let repo_header = "repo";
+    let branch_header = "branch";
+    let path_header = "path";
+    let attached_header = "attached";
+
+    let session_width = rows
+        .iter()
+        .map(|row| row.session.len())
+        .max()
+        .unwrap_or(session_header.len())
+        .max(session_header.len());
+    let repo_width = rows
+        .iter()
+        .map(|row| row.repo.len())
+        .max()
+        .unwrap_or(repo_header.len())
+        .max(repo_header.len());
+    let branch_width = rows
+        .iter()
+        .map(|row| row.branch.as_deref().unwrap_or("(detached)").len())
+        .max()
+        .unwrap_or(branch_header.len())
+        .max(branch_header.len());
+    let path_width = rows
+        .iter()
+        .map(|row| row.path.display().to_string().len())
+        .max()
+        .unwrap_or(path_header.len())
+        .max(path_header.len());
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{session_header:<session_width$}  {repo_header:<repo_width$}  {branch_header:<branch_width$}  {path_header:<path_width$}  {attached_header}"
+    );
+    for row in rows {
+        let _ = writeln!(
+            out,
+            "{:<session_width$}  {:<repo_width$}  {:<branch_width$}  {:<path_width$}  {}",
+            row.session,
+            row.repo,
+            row.branch.as_deref().unwrap_or("(detached)"),
+            row.path.display(),
+            row.attached
+        );
+    }
+    out
+}
+
+pub fn format_top_table(entries: &[TopEntry]) -> String {
+    let mut rows = vec![vec![
+        "session".to_string(),
+        "cpu".to_string(),
+        "mem".to_string(),
+        "procs".to_string(),
+        "busiest".to_string(),
+    ]];
+    rows.extend(entries.iter().map(|entry| {
+        vec![
+            entry.session.clone(),
+            format!("{:.1}%", entry.cpu_percent),
+            format_bytes(entry.memory_bytes),
+            entry.processes.to_string(),
+            entry.busiest_command.clone().unwrap_or_default(),
+        ]
+    }));
+    format_rows(&rows)
+}
+
+fn log_dir() -> CliResult<PathBuf> {
+    let base = if let Ok(xdg_state_home) = std::env::var("XDG_STATE_HOME")
+        && !xdg_state_home.is_empty()
+    {
+        PathBuf::from(xdg_state_home)
+    } else {
+        let home = std::env::var("HOME")
+            .map(PathBuf::from)
+            .map_err(|_| CliError::system("HOME environment variable not set"))?;
+        home.join(".local").join("state")
+    };
+    Ok(base.join("kiosk").join("logs"))
+}
+
+/// Best-effort: a failure to rotate old logs shouldn't block reading or writing new ones.
+fn apply_log_retention(config: &Config) {
+    let result = log_dir().map_err(|error| anyhow::anyhow!(error.message().to_string()));
+    if let Err(error) =
+        result.and_then(|dir| enforce_retention(&dir, &config.log, std::time::SystemTime::now()))
+    {
+        tracing::warn!("Failed to apply session log retention: {error}");
+    }
+}
+
+pub fn format_log_table(logs: &[SessionLogFile]) -> String {
+    let session_header = "session";
+    let size_header = "size";
+    let path_header = "path";
+    let session_width = logs
+        .iter()
+        .map(|log| log.session.len())
+        .max()
+        .unwrap_or(session_header.len())
+        .max(session_header.len());
+    let sizes: Vec<String> = logs
+        .iter()
+        .map(|log| format_bytes(log.size_bytes))
+        .collect();
+    let size_width = sizes
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(size_header.len())
+        .max(size_header.len());
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{session_header:<session_width$}  {size_header:>size_width$}  {path_header}"
+    );
+    for (log, size) in logs.iter().zip(&sizes) {
+        let _ = writeln!(
+            out,
+            "{:<session_width$}  {size:>size_width$}  {}",
+            log.session,
+            log.path.display()
+        );
+    }
+    out
+}
+
+/// `secs` in its two largest units, e.g. `3h 20m`.
+pub fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {}s", secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+pub fn panes(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &PanesArgs,
+) -> CliResult<PanesOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+
+    let worktree_path = if let Some(branch) = &args.branch {
+        find_worktree_by_branch(&repo, branch)
+            .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{branch}'")))?
+    } else {
+        repo.path.clone()
+    };
+
+    let session_name = repo.tmux_session_name(&worktree_path);
+    ensure_session_exists(tmux, &session_name)?;
+
+    let panes = tmux
+        .list_panes_detailed(&session_name)
+        .map_err(CliError::from)?
+        .into_iter()
+        .map(|pane| PaneInfo {
+            index: pane.index,
+            current_command: pane.current_command,
+            pid: pane.pid,
+            active: pane.active,
+            width: pane.width,
+            height: pane.height,
+            title: pane.title,
+        })
+        .collect();
+
+    Ok(PanesOutput {
+        session: session_name,
+        panes,
+    })
+}
+
+/// Send the pane's program the keys that stop it without quitting it, so callers don't need
+/// to know each agent's cancel key.
+pub fn interrupt(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &InterruptArgs,
+) -> CliResult<InterruptOutput> {
+    let session_name = resolve_session(config, git, tmux, &args.repo, args.branch.as_deref())?;
+    let pane = match &args.pane {
+        Some(target) => target.resolve(tmux, &session_name)?,
+        None => agent::session_agent_pane(tmux, &session_name).map_or(0, |(pane, _)| pane),
+    };
+    let command = tmux
+        .pane_current_command(&session_name, &pane.to_string())
+        .map_err(CliError::from)?;
+    let keys = agent::interrupt_keys(&command);
+    tmux.send_keys_raw(&session_name, &pane.to_string(), keys)
+        .map_err(CliError::from)?;
+    Ok(InterruptOutput {
+        session: session_name,
+        pane,
+        command,
+        keys: keys.iter().map(|key| (*key).to_string()).collect(),
+    })
+}
+
+pub fn kill_pane(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &KillPaneArgs,
+) -> CliResult<KillPaneOutput> {
+    let session_name = resolve_session(config, git, tmux, &args.repo, args.branch.as_deref())?;
+    let pane = match &args.pane {
+        Some(target) => target.resolve(tmux, &session_name)?,
+        None => agent::session_agent_pane(tmux, &session_name)
+            .map(|(pane, _)| pane)
+            .ok_or_else(|| {
+                CliError::not_found(format!(
+                    "no agent running in session '{session_name}'; pick a pane with --pane"
+                ))
+            })?,
+    };
+    let command = tmux
+        .pane_current_command(&session_name, &pane.to_string())
+        .map_err(CliError::from)?;
+    tmux.kill_pane(&session_name, &pane.to_string())
+        .map_err(CliError::from)?;
+    Ok(KillPaneOutput {
+        session: session_name,
+        pane,
+        command,
+    })
+}
+
+/// Answer the permission prompt of the session's agent with the keys that agent expects.
+/// Only an agent with its prompt on screen is answered, so keys meant for a prompt don't land
+/// in the middle of its work.
+pub fn answer(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &AnswerArgs,
+    answer: PromptAnswer,
+) -> CliResult<AnswerOutput> {
+    let session_name = resolve_session(config, git, tmux, &args.repo, args.branch.as_deref())?;
+    let (pane, agent) = agent::session_agent_pane(tmux, &session_name).ok_or_else(|| {
+        CliError::not_found(format!("no agent running in session '{session_name}'"))
+    })?;
+    let keys = agent::answer_keys(&agent, answer).ok_or_else(|| {
+        CliError::user(format!(
+            "kiosk doesn't know how to answer {agent}'s prompts"
+        ))
+    })?;
+    let screen = tmux
+        .capture_pane_range(&session_name, &pane.to_string(), &CaptureRange::default())
+        .map_err(CliError::from)?;
+    if !agent::shows_prompt(&agent, &screen) {
+        return Err(CliError::user(format!(
+            "{agent} in session '{session_name}' isn't showing a permission prompt, so nothing was sent"
+        )));
+    }
+    tmux.send_keys_raw(&session_name, &pane.to_string(), keys)
+        .map_err(CliError::from)?;
+    Ok(AnswerOutput {
+        session: session_name,
+        pane,
+        agent,
+        approved: answer == PromptAnswer::Approve,
+        keys: keys.iter().map(|key| (*key).to_string()).collect(),
+    })
+}
+
+/// The latest conversation an agent saved for the worktree, read from the agent's own files.
+pub fn transcript(
+    config: &Config,
+    git: &dyn GitProvider,
+    dirs: &TranscriptDirs,
+    args: &TranscriptArgs,
+) -> CliResult<TranscriptOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+    let path = if let Some(branch) = &args.branch {
+        find_worktree_by_branch(&repo, branch)
+            .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{branch}'")))?
+    } else {
+        repo.path.clone()
+    };
+    let agent = args.agent.map(TranscriptAgent::from);
+    let transcript = dirs
+        .latest(&path, agent)
+        .map_err(CliError::from)?
+        .ok_or_else(|| {
+            let agents = agent.map_or("claude or codex", TranscriptAgent::name);
+            CliError::not_found(format!(
+                "no {agents} transcript found for {}",
+                path.display()
+            ))
+        })?;
+    Ok(TranscriptOutput {
+        repo: repo.name,
+        branch: args.branch.clone(),
+        path,
+        agent: transcript.agent.name().to_string(),
+        file: transcript.file,
+        messages: transcript
+            .messages
+            .into_iter()
+            .map(|message| TranscriptMessageOutput {
+                role: message.role.name().to_string(),
+                text: message.text,
+                timestamp: message.timestamp,
+            })
+            .collect(),
+    })
+}
+
+/// The conversation as markdown, a heading per turn.
+pub fn format_transcript(output: &TranscriptOutput) -> String {
+    let mut out = format!(
+        "# {} transcript for {}\n",
+        output.agent,
+        output.path.display()
+    );
+    for message in &output.messages {
+        let speaker = if message.role == "user" {
+            "User"
+        } else {
+            "Assistant"
+        };
+        let _ = write!(out, "\n## {speaker}\n\n{}\n", message.text);
+    }
+    out
+}
+
+/// Sample what the agent in every kiosk session is doing into `store`, every `interval`
+/// seconds until interrupted, or just once with `once`.
+pub fn agents_record(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    store: &AgentHistoryStore,
+    args: &AgentsRecordArgs,
+) -> CliResult<()> {
+    // Samples further apart than this, e.g. after the machine slept, aren't joined up
+    let max_gap = args.interval.saturating_mul(3);
+    loop {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        store
+            .record(&agent_samples(config, git, tmux), now, max_gap)
+            .map_err(CliError::from)?;
+        run_agent_hooks(config, tmux, store)?;
+        if args.once {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_secs(args.interval.max(1)));
+    }
+}
+
+/// Run the `[agent]` hooks for sessions that have been waiting or idle for long enough. A
+/// failing hook is logged rather than stopping the recorder.
+fn run_agent_hooks(
+    config: &Config,
+    tmux: &dyn TmuxProvider,
+    store: &AgentHistoryStore,
+) -> CliResult<()> {
+    let script = scripting::Script::load(&config.script).unwrap_or_else(|e| {
+        tracing::warn!("Failed to load the script: {e:#}");
+        None
+    });
+    let scripted = |activity| {
+        Hook::for_activity(activity).filter(|hook| {
+            script
+                .as_ref()
+                .is_some_and(|script| script.defines(hook.function()))
+        })
+    };
+    let mut activities = agent_hooks::hooked_activities(&config.agent);
+    for activity in [AgentActivity::Waiting, AgentActivity::Idle] {
+        if scripted(activity).is_some() && !activities.contains(&activity) {
+            activities.push(activity);
+        }
+    }
+    if activities.is_empty() {
+        return Ok(());
+    }
+    let due = store
+        .take_due(&activities, config.agent.debounce_secs)
+        .map_err(CliError::from)?;
+    for span in due {
+        if let Some(hook) = agent_hooks::hook_for(&config.agent, span.activity)
+            && let Err(e) = agent_hooks::run(hook, &span)
+        {
+            tracing::warn!("agent hook for {} failed: {e:#}", span.session);
+        }
+        if let (Some(hook), Some(script)) = (scripted(span.activity), &script) {
+            let sent = script
+                .call(hook.function(), &agent_hooks::payload(&span))
+                .and_then(|text| text.map_or(Ok(()), |text| tmux.send_keys(&span.session, &text)));
+            if let Err(e) = sent {
+                tracing::warn!("script hook for {} failed: {e:#}", span.session);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every running kiosk session, with what its agent is doing. An agent with its permission
+/// prompt on screen is waiting, however recently the session had output; otherwise it's
+/// waiting once it has gone quiet.
+fn agent_samples(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+) -> Vec<AgentSample> {
+    let activity: HashMap<String, u64> = tmux.list_sessions_with_activity().into_iter().collect();
+    active_sessions(config, git, tmux)
+        .into_iter()
+        .filter(|active| config.agent.detects_in(&active.repo))
+        .map(|active| {
+            let agent_pane = agent::session_agent_pane(tmux, &active.session);
+            let prompting = agent_pane.as_ref().is_some_and(|(pane, agent)| {
+                tmux.capture_pane_range(
+                    &active.session,
+                    &pane.to_string(),
+                    &CaptureRange::default(),
+                )
+                .is_ok_and(|screen| agent::shows_prompt(agent, &screen))
+            });
+            let state = activity.get(&active.session).copied().map(agent_state);
+            let activity = match (&agent_pane, state) {
+                (None, _) => AgentActivity::Idle,
+                (Some(_), Some(AgentState::Working)) if !prompting => AgentActivity::Working,
+                (Some(_), _) => AgentActivity::Waiting,
+            };
+            let agent = agent_pane.map(|(_, agent)| agent);
+            AgentSample {
+                session: active.session,
+                repo: active.repo,
+                branch: active.branch,
+                agent,
+                activity,
+            }
+        })
+        .collect()
+}
+
+pub fn agents_report(
+    store: &AgentHistoryStore,
+    args: &AgentsReportArgs,
+    now: u64,
+) -> AgentsReportOutput {
+    let since = now.saturating_sub(args.since);
+    let mut sessions: Vec<AgentTimeOutput> = agent_history::summarise(&store.load(), since, now)
+        .into_iter()
+        .map(|time| AgentTimeOutput {
+            session: time.session,
+            repo: time.repo,
+            branch: time.branch,
+            working_secs: time.working_secs,
+            waiting_secs: time.waiting_secs,
+            idle_secs: time.idle_secs,
+        })
+        .collect();
+    sessions.sort_by_key(|time| std::cmp::Reverse(time.waiting_secs));
+    AgentsReportOutput { since, sessions }
+}
+
+pub fn format_agents_report(output: &AgentsReportOutput) -> String {
+    if output.sessions.is_empty() {
+        return "No agent activity recorded in that time. Run `kiosk agents record` to record it\n"
+            .to_string();
+    }
+    let mut rows = vec![vec![
+        "repo".to_string(),
+        "branch".to_string(),
+        "working".to_string(),
+        "waiting".to_string(),
+        "idle".to_string(),
+        "waiting share".to_string(),
+    ]];
+    rows.extend(output.sessions.iter().map(|time| {
+        let running = time.working_secs + time.waiting_secs;
+        vec![
+            time.repo.clone(),
+            time.branch
+                .clone()
+                .unwrap_or_else(|| "(detached)".to_string()),
+            format_duration(time.working_secs),
+            format_duration(time.waiting_secs),
+            format_duration(time.idle_secs),
+            (time.waiting_secs * 100)
+                .checked_div(running)
+                .map_or_else(|| "-".to_string(), |share| format!("{share}%")),
+        ]
+    }));
+    format_rows(&rows)
+}
+
+pub fn ps(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    processes: &ProcessTable,
+    args: &PsArgs,
+) -> CliResult<PsOutput> {
+    let session_name = resolve_session(config, git, tmux, &args.repo, args.branch.as_deref())?;
+
+    let panes = tmux
+        .list_panes_detailed(&session_name)
+        .map_err(CliError::from)?
+        .into_iter()
+        .map(|pane| PaneProcesses {
+            index: pane.index,
+            pid: pane.pid,
+            process: (pane.pid != 0)
+                .then(|| processes.tree(pane.pid))
+                .flatten()
+                .map(ProcessOutput::from),
+        })
+        .collect();
+
+    Ok(PsOutput {
+        session: session_name,
+        panes,
+    })
+}
+
+/// Each pane's processes indented under their parents, with pid and CPU use.
+pub fn format_process_trees(output: &PsOutput) -> String {
+    fn write_process(out: &mut String, process: &ProcessOutput, depth: usize) {
+        let indent = "  ".repeat(depth);
+        let _ = writeln!(
+            out,
+            "{indent}{} {:.1}% {}",
+            process.pid, process.cpu_percent, process.args
+        );
+        for child in &process.children {
+            write_process(out, child, depth + 1);
+        }
+    }
+
+    let mut out = format!("session: {}\n", output.session);
+    for pane in &output.panes {
+        let _ = writeln!(out, "  pane {}:", pane.index);
+        match &pane.process {
+            Some(process) => write_process(&mut out, process, 2),
+            None => out.push_str("    (no processes found)\n"),
+        }
+    }
+    out
+}
+
+const KNOWN_SHELLS: &[&str] = &[
+    "bash", "zsh", "fish", "sh", "dash", "ash", "ksh", "tcsh", "csh", "nu", "nushell", "pwsh",
+];
+
+/// Core wait loop: blocks until the pane's foreground process is a shell, or timeout.
+/// Returns `Ok(WaitOutput)` on idle, `Err` on timeout or failure.
+fn wait_for_idle(
+    tmux: &dyn TmuxProvider,
+    session_name: &str,
+    pane: &str,
+    timeout_secs: u64,
+    poll_interval: std::time::Duration,
+) -> CliResult<WaitOutput> {
+    let start_time = std::time::Instant::now();
+    let timeout_duration = std::time::Duration::from_secs(timeout_secs);
+
+    loop {
+        if start_time.elapsed() >= timeout_duration {
+            return Err(CliError::timeout("wait timeout"));
+        }
+
+        match tmux.pane_current_command(session_name, pane) {
+            Ok(command) => {
+                if KNOWN_SHELLS.iter().any(|&shell| command == shell) {
+                    return Ok(WaitOutput {
+                        idle: true,
+                        timed_out: false,
+                        pane_command: command,
+                        exit_code: None,
+                    });
+                }
+            }
+            Err(e) => {
+                return Err(CliError::system(format!(
+                    "failed to get pane current command: {e}"
+                )));
+            }
+        }
+
+        std::thread::sleep(poll_interval);
+    }
+}
+
+pub fn wait(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &WaitArgs,
+) -> CliResult<WaitOutput> {
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+
+    let worktree_path = if let Some(branch) = &args.branch {
+        find_worktree_by_branch(&repo, branch)
+            .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{branch}'")))?
+    } else {
+        repo.path.clone()
+    };
+
+    let session_name = repo.tmux_session_name(&worktree_path);
+    ensure_session_exists(tmux, &session_name)?;
+
+    let pane = args.pane.resolve(tmux, &session_name)?.to_string();
+    match wait_for_idle(
+        tmux,
+        &session_name,
+        &pane,
+        args.timeout,
+        config.agent.poll_interval(),
+    ) {
+        Err(e) if e.code() == CliError::TIMEOUT => Ok(WaitOutput {
+            idle: false,
+            timed_out: true,
+            pane_command: tmux
+                .pane_current_command(&session_name, &pane)
+                .unwrap_or_else(|_| "unknown".to_string()),
+            exit_code: None,
+        }),
+        result => result,
+    }
+}
+
+/// The session's log, narrowed by `--grep` and `--tail`. With `--follow` those lines, then
+/// each matching line as it's written, go to `on_line` instead, until it returns false.
+pub fn log(
+    config: &Config,
+    git: &dyn GitProvider,
+    args: &LogArgs,
+    mut on_line: impl FnMut(&LogLineOutput) -> bool,
+) -> CliResult<LogOutput> {
+    if args.line_format.timestamps && !args.follow {
+        return Err(CliError::user(
+            "--timestamps requires --follow, as the log doesn't record when lines were written",
+        ));
+    }
+    let repo = resolve_repo_with_worktrees(config, git, &args.repo)?;
+
+    let worktree_path = if let Some(branch) = &args.branch {
+        find_worktree_by_branch(&repo, branch)
+            .ok_or_else(|| CliError::not_found(format!("no worktree for branch '{branch}'")))?
+    } else {
+        repo.path.clone()
+    };
+
+    let session_name = repo.tmux_session_name(&worktree_path);
+    let log_path = log_path_for_session(&session_name)?;
+    apply_log_retention(config);
+
+    if !log_path.exists() {
+        return Err(CliError::not_found(format!(
+            "no log file found for session '{session_name}'"
+        )));
+    }
+
+    let grep = args
+        .grep
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| CliError::user(format!("invalid --grep pattern: {e}")))?;
+    let matches = |line: &str| grep.as_ref().is_none_or(|grep| grep.is_match(line));
+
+    let log_bytes = fs::read(&log_path)
+        .with_context(|| format!("failed to read log file {}", log_path.display()))
+        .map_err(CliError::from)?;
+    // When following, a trailing partial line is printed once it is complete.
+    let read_len = if args.follow {
+        complete_lines_len(&log_bytes)
+    } else {
+        log_bytes.len()
+    };
+    let log_content = args
+        .line_format
+        .clean_all(&String::from_utf8_lossy(&log_bytes[..read_len]));
+    // Lines already in the log weren't recorded with their time, so with timestamps only
+    // the lines written from now on are shown
+    let tail_content = if args.line_format.timestamps {
+        String::new()
+    } else {
+        select_log_lines(&log_content, grep.as_ref(), args.tail)
+    };
+
+    let output = LogOutput {
+        session: session_name,
+        lines: Vec::new(),
+    };
+    if args.follow {
+        let mut emit = |line: &str| {
+            on_line(&LogLineOutput {
+                session: &output.session,
+                line,
+            })
+        };
+        if tail_content.lines().all(&mut emit) {
+            follow_lines(&log_path, read_len as u64, |line| {
+                let line = args.line_format.clean(line);
+                !matches(&line) || emit(&args.line_format.stamp(&line, SystemTime::now()))
+            })
+            .map_err(CliError::from)?;
+        }
+        return Ok(output);
+    }
+
+    Ok(LogOutput {
+        lines: tail_content
+            .lines()
+            .map(std::string::ToString::to_string)
+            .collect(),
+        ..output
+    })
+}
+
+pub fn log_list(config: &Config) -> CliResult<LogListOutput> {
+    apply_log_retention(config);
+    Ok(LogListOutput {
+        logs: list_session_logs(&log_dir()?).map_err(CliError::from)?,
+    })
+}
+
+pub fn log_purge(
+    config: &Config,
+    git: &dyn GitProvider,
+    args: &LogPurgeArgs,
+) -> CliResult<LogPurgeOutput> {
+    let session = match &args.repo {
+        Some(repo_name) => {
+            let repo = resolve_repo_with_worktrees(config, git, repo_name)?;
+            let worktree_path = match &args.branch {
+                Some(branch) => find_worktree_by_branch(&repo, branch).ok_or_else(|| {
+                    CliError::not_found(format!("no worktree for branch '{branch}'"))
+                })?,
+                None => repo.path.clone(),
+            };
+            Some(repo.tmux_session_name(&worktree_path))
+        }
+        None => None,
+    };
+
+    let purged = purge_session_logs(&log_dir()?, session.as_deref()).map_err(CliError::from)?;
+    Ok(LogPurgeOutput {
+        bytes_freed: purged.iter().map(|log| log.size_bytes).sum(),
+        purged: purged.into_iter().map(|log| log.path).collect(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ConfigPathOutput {
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+/// Open a repo or worktree in `configured` (the `editor` config setting), falling back to
+/// `$VISUAL` and `$EDITOR`, and wait for the editor to exit.
+pub fn open_in_editor(path: &Path, configured: Option<&str>) -> anyhow::Result<()> {
+    let visual = std::env::var("VISUAL").ok();
+    let editor = editor_command(
+        configured
+            .filter(|value| !value.trim().is_empty())
+            .or(visual.as_deref()),
+        std::env::var("EDITOR").ok().as_deref(),
+    );
+    let (program, editor_args) = editor
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("$EDITOR is empty"))?;
+    let status = std::process::Command::new(program)
+        .args(editor_args)
+        .arg(path)
+        .current_dir(path)
+        .traced_status()
+        .with_context(|| format!("failed to launch editor '{program}'"))?;
+    anyhow::ensure!(status.success(), "editor '{program}' exited with {status}");
+    Ok(())
+}
+
+/// The editor to run, split into program and arguments: `$VISUAL`, then `$EDITOR`, then a
+/// platform default. Values like `code --wait` are split on whitespace.
+pub fn editor_command(visual: Option<&str>, editor: Option<&str>) -> Vec<String> {
+    let default = if cfg!(windows) { "notepad" } else { "vi" };
+    [visual, editor]
+        .into_iter()
+        .flatten()
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or(default)
+        .split_whitespace()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ConfigValidateOutput {
+    pub file: PathBuf,
+    pub valid: bool,
+    pub problems: Vec<ConfigProblemOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct ConfigProblemOutput {
+    /// Dotted path to the offending key; empty when the problem is with the whole file
+    pub path: String,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+pub fn config_validate(file: &Path) -> CliResult<ConfigValidateOutput> {
+    if !file.exists() {
+        return Err(CliError::not_found(format!(
+            "config file not found at {}",
+            file.display()
+        )));
+    }
+    let problems: Vec<_> = validate_config_file(file)
+        .map_err(|e| CliError::system(format!("failed to read {}: {e}", file.display())))?
+        .into_iter()
+        .map(|problem| ConfigProblemOutput {
+            path: problem.path,
+            message: problem.message,
+            suggestion: problem.suggestion,
+        })
+        .collect();
+    Ok(ConfigValidateOutput {
+        file: file.to_path_buf(),
+        valid: problems.is_empty(),
+        problems,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginsArgs {
+    pub format: OutputFormat,
+}
+
+/// The plugins on `PATH`.
+pub fn plugins() -> Vec<PluginOutput> {
+    plugin::discover(std::env::var_os("PATH").as_deref())
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginArgs {
+    pub name: String,
+    pub args: Vec<String>,
+    /// Config file the config was loaded from, passed on to the plugin
+    pub config_path: PathBuf,
+}
+
+/// Run the plugin `kiosk-<name>` with `args`, inheriting the terminal, and return its exit
+/// code. It gets the context (config, current repo, branch and session) as JSON on stdin, the
+/// session placeholders as `KIOSK_*` environment variables, and the path of this executable
+/// and the config file as `KIOSK_BIN` and `KIOSK_CONFIG` so it can call back into kiosk.
+pub fn plugin(
+    config: &Config,
+    git: &dyn GitProvider,
+    tmux: &dyn TmuxProvider,
+    args: &PluginArgs,
+) -> CliResult<i32> {
+    let executable =
+        plugin::find(&args.name, std::env::var_os("PATH").as_deref()).ok_or_else(|| {
+            CliError::user(format!(
+                "unrecognized subcommand '{}', and no '{}{}' plugin on PATH. Use --help for usage",
+                args.name,
+                plugin::PLUGIN_PREFIX,
+                args.name
+            ))
+        })?;
+    let session = tmux
+        .is_inside_tmux()
+        .then(|| tmux.current_session())
+        .flatten();
+    let repo = git.resolve_repo_from_cwd().map(|worktree| {
+        session_context(
+            config,
+            git,
+            None,
+            &worktree,
+            session.as_deref().unwrap_or_default(),
+            None,
+        )
+    });
+    let env = repo.as_ref().map_or_else(
+        || {
+            SessionContext {
+                session: session.clone().unwrap_or_default(),
+                ..SessionContext::default()
+            }
+            .env()
+        },
+        |(_, context)| context.env(),
+    );
+    let context = plugin::PluginContext::new(
+        &args.name,
+        &args.args,
+        &args.config_path,
+        config,
+        repo,
+        session,
+    );
+    let payload = serde_json::to_vec(&context)
+        .map_err(|e| CliError::system(format!("failed to serialize the plugin context: {e}")))?;
+
+    let mut command = std::process::Command::new(&executable);
+    command
+        .args(&args.args)
+        .envs(env)
+        .env("KIOSK_CONFIG", &args.config_path)
+        .stdin(std::process::Stdio::piped());
+    if let Ok(kiosk) = std::env::current_exe() {
+        command.env("KIOSK_BIN", kiosk);
+    }
+    tracing::debug!("Running plugin {}", executable.display());
+    let mut child = command
+        .spawn()
+        .map_err(|e| CliError::system(format!("failed to run {}: {e}", executable.display())))?;
+    // Written from another thread so a plugin that never reads stdin can't block kiosk
+    if let Some(mut stdin) = child.stdin.take() {
+        std::thread::spawn(move || {
+            use std::io::Write as _;
+            let _ = stdin.write_all(&payload);
+        });
+    }
+    let status = child
+        .wait()
+        .map_err(|e| CliError::system(format!("failed to run {}: {e}", executable.display())))?;
+    // Killed by a signal
+    Ok(status.code().unwrap_or(CliError::SYSTEM))
+}
+
+pub fn schema_for_command(command: SchemaCommand) -> schemars::Schema {
+    match command {
+        SchemaCommand::List => envelope_schema::<Vec<RepoOutput>>(),
+        SchemaCommand::Branches => envelope_schema::<Vec<BranchOutput>>(),
+        SchemaCommand::Open => envelope_schema::<OpenOutput>(),
+        SchemaCommand::OpenProgress => envelope_schema::<OpenProgressOutput<'static>>(),
+        SchemaCommand::Status => envelope_schema::<StatusOutput>(),
+        SchemaCommand::StatusAll => envelope_schema::<Vec<SessionStatusOutput>>(),
+        SchemaCommand::Capture => envelope_schema::<CaptureOutput>(),
+        SchemaCommand::Sessions => envelope_schema::<Vec<SessionOutput>>(),
+        SchemaCommand::Delete => envelope_schema::<DeleteOutput>(),
+        SchemaCommand::Undo => envelope_schema::<UndoOutput>(),
+        SchemaCommand::Reap => envelope_schema::<ReapOutput>(),
+        SchemaCommand::PruneBranches => envelope_schema::<PruneBranchesOutput>(),
+        SchemaCommand::Recover => envelope_schema::<RecoverOutput>(),
+        SchemaCommand::History => envelope_schema::<Vec<AuditEntry>>(),
+        SchemaCommand::Clone => envelope_schema::<CloneOutput>(),
+        SchemaCommand::Fetch => envelope_schema::<FetchOutput>(),
+        SchemaCommand::Top => envelope_schema::<Vec<TopEntry>>(),
+        SchemaCommand::Export => envelope_schema::<ExportOutput>(),
+        SchemaCommand::Import => envelope_schema::<ImportOutput>(),
+        SchemaCommand::Resume => envelope_schema::<ResumeOutput>(),
+        SchemaCommand::WorkspaceList => envelope_schema::<Vec<WorkspaceOutput>>(),
+        SchemaCommand::WorkspaceOpen => envelope_schema::<WorkspaceOpenOutput>(),
+        SchemaCommand::Diff => envelope_schema::<DiffOutput>(),
+        SchemaCommand::Path => envelope_schema::<PathOutput>(),
+        SchemaCommand::Note => envelope_schema::<NoteOutput>(),
+        SchemaCommand::Label => envelope_schema::<LabelOutput>(),
+        SchemaCommand::Unhide => envelope_schema::<UnhideOutput>(),
+        SchemaCommand::Statusline => envelope_schema::<StatuslineOutput>(),
+        SchemaCommand::Send => envelope_schema::<SendOutput>(),
+        SchemaCommand::Panes => envelope_schema::<PanesOutput>(),
+        SchemaCommand::Ps => envelope_schema::<PsOutput>(),
+        SchemaCommand::Interrupt => envelope_schema::<InterruptOutput>(),
+        SchemaCommand::KillPane => envelope_schema::<KillPaneOutput>(),
+        SchemaCommand::Approve | SchemaCommand::Deny => envelope_schema::<AnswerOutput>(),
+        SchemaCommand::Transcript => envelope_schema::<TranscriptOutput>(),
+        SchemaCommand::AgentsReport => envelope_schema::<AgentsReportOutput>(),
+        SchemaCommand::Wait => envelope_schema::<WaitOutput>(),
+        SchemaCommand::Log => envelope_schema::<LogOutput>(),
+        SchemaCommand::LogFollow => envelope_schema::<LogLineOutput<'static>>(),
+        SchemaCommand::LogList => envelope_schema::<LogListOutput>(),
+        SchemaCommand::LogPurge => envelope_schema::<LogPurgeOutput>(),
+        SchemaCommand::Clean => envelope_schema::<CleanOutput>(),
+        SchemaCommand::ConfigValidate => envelope_schema::<ConfigValidateOutput>(),
+        SchemaCommand::ConfigPath => envelope_schema::<ConfigPathOutput>(),
+        SchemaCommand::Plugins => envelope_schema::<Vec<PluginOutput>>(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use kiosk_core::{
+        audit::{AuditOperation, AuditedGit, AuditedTmux},
+        config::{self, AgentProfile, Icons},
+        constants::GIT_DIR_ENTRY,
+        frecency::Visit,
+        git::mock::MockGitProvider,
+        git::progress::with_progress,
+        git::repo::Worktree,
+        git::{CommitInfo, FileChange},
+        tmux::mock::MockTmuxProvider,
+    };
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::{Arc, Mutex},
+    };
+
+    fn test_config() -> Config {
+        let mut config = config::load_config_from_str("search_dirs = [\"/tmp\"]").unwrap();
+        // Tests share session names, so a cache would leak agents between them
+        config.agent.cache_secs = 0;
+        config
+    }
+
+    /// Stores kept in `dir` rather than the real state dir.
+    fn stores_in(dir: &tempfile::TempDir) -> Stores {
+        Stores {
+            visits: FrecencyStore::at(dir.path().join("frecency.toml")),
+            sessions: SessionJournal::at(dir.path().join("sessions.toml")),
+            workspaces: WorkspaceStore::at(dir.path().join("workspaces.toml")),
+            deletes: DeleteJournal::at(dir.path().join("journal.toml")),
+            pending_deletes: PendingDeleteStore::at(dir.path().join("pending.toml")),
+        }
+    }
+
+    fn repo(path: &str, name: &str) -> Repo {
+        Repo {
+            name: name.to_string(),
+            session_name: name.to_string(),
+            path: PathBuf::from(path),
+            worktrees: vec![Worktree {
+                path: PathBuf::from(path),
+                branch: Some("main".to_string()),
+                is_main: true,
+            }],
+        }
+    }
+
+    #[test]
+    fn resolve_repo_exact_matches_only_exact_name() {
+        let repos = vec![repo("/tmp/a", "alpha"), repo("/tmp/b", "beta")];
+        let found = resolve_repo_exact(&repos, "beta").unwrap();
+        assert_eq!(found.name, "beta");
+        assert!(resolve_repo_exact(&repos, "bet").is_err());
+    }
+
+    #[test]
+    fn open_is_idempotent_when_worktree_and_session_exist() {
+        let config = test_config();
+        let mut git = MockGitProvider::default();
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo--feat-test".to_string()]),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        git.repos = vec![repo("/tmp/demo", "demo")];
+        git.worktrees = vec![
+            Worktree {
+                path: PathBuf::from("/tmp/demo"),
+                branch: Some("main".to_string()),
+                is_main: true,
+            },
+            Worktree {
+                path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-test"),
+                branch: Some("feat/test".to_string()),
+                is_main: false,
+            },
+        ];
+        git.branches = vec!["main".to_string(), "feat/test".to_string()];
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                branch: Some("feat/test".to_string()),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        )
+        .unwrap();
+
+        assert!(!output.created);
+        assert_eq!(output.repo, "demo");
+        assert_eq!(output.branch.as_deref(), Some("feat/test"));
+        assert_eq!(output.session, "demo--feat-test");
+        assert!(tmux.created_sessions.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn open_rejects_unknown_branch_with_new_branch_hint() {
+        let config = test_config();
+        let mut git = MockGitProvider::default();
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(Vec::new()),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        git.repos = vec![repo("/tmp/demo", "demo")];
+        git.worktrees = vec![Worktree {
+            path: PathBuf::from("/tmp/demo"),
+            branch: Some("main".to_string()),
+            is_main: true,
+        }];
+        git.branches = vec!["main".to_string()];
+
+        let error = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                branch: Some("missing".to_string()),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        )
+        .unwrap_err();
+
+        assert!(error.message().contains("Use --new-branch"));
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+    }
+
+    #[test]
+    fn open_with_run_sends_keys_after_session_creation() {
+        let config = test_config();
+        let mut git = MockGitProvider::default();
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(Vec::new()),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        git.repos = vec![repo("/tmp/demo", "demo")];
+        git.worktrees = vec![Worktree {
+            path: PathBuf::from("/tmp/demo"),
+            branch: Some("main".to_string()),
+            is_main: true,
+        }];
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                run: Some("echo MARKER".to_string()),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        )
+        .unwrap();
+
+        assert!(output.created);
+        assert_eq!(
+            tmux.sent_keys.lock().unwrap().as_slice(),
+            &[("demo".to_string(), "echo MARKER".to_string())]
+        );
+    }
+
+    #[test]
+    fn open_runs_the_project_template_only_in_a_new_session() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        let mut config = test_config();
+        config.session.templates.insert(
+            kiosk_core::project::ProjectKind::Rust,
+            kiosk_core::config::SessionTemplate {
+                split_command: None,
+                run: Some("bacon".to_string()),
+            },
+        );
+        let mut git = MockGitProvider::default();
+        let tmux = MockTmuxProvider {
+            inside_tmux: true,
+            ..Default::default()
+        };
+        let path = dir.path().to_string_lossy().to_string();
+        git.repos = vec![repo(&path, "demo")];
+        git.worktrees = vec![Worktree {
+            path: dir.path().to_path_buf(),
+            branch: Some("main".to_string()),
+            is_main: true,
+        }];
+        let args = OpenArgs::for_repo("demo".to_string(), OutputFormat::Table);
+
+        open_session(&config, &git, &tmux, &Stores::disabled(), &args).unwrap();
+        open_session(&config, &git, &tmux, &Stores::disabled(), &args).unwrap();
+        assert_eq!(
+            tmux.sent_keys.lock().unwrap().as_slice(),
+            &[("demo".to_string(), "bacon".to_string())]
+        );
+    }
+
+    #[test]
+    fn open_retries_after_stale_worktree_conflict() {
+        let config = test_config();
+        let mut git = MockGitProvider::default();
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(Vec::new()),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        git.repos = vec![repo("/tmp/demo", "demo")];
+        git.worktrees = vec![Worktree {
+            path: PathBuf::from("/tmp/demo"),
+            branch: Some("main".to_string()),
+            is_main: true,
+        }];
+        git.branches = vec!["main".to_string(), "feat/test".to_string()];
+        *git.add_worktree_result.lock().unwrap() = Some(Err(anyhow!(
+            "git worktree add failed: fatal: 'feat/test' is already used by worktree at '/tmp/.kiosk_worktrees/demo--feat-test'"
+        )));
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                branch: Some("feat/test".to_string()),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        )
+        .unwrap();
+
+        assert!(output.created);
+        assert_eq!(git.prune_worktrees_calls.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn open_shows_stale_worktree_hint_when_auto_prune_fails() {
+        let config = test_config();
+        let mut git = MockGitProvider::default();
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(Vec::new()),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        git.repos = vec![repo("/tmp/demo", "demo")];
+        git.worktrees = vec![Worktree {
+            path: PathBuf::from("/tmp/demo"),
+            branch: Some("main".to_string()),
+            is_main: true,
+        }];
+        git.branches = vec!["main".to_string(), "feat/test".to_string()];
+        *git.add_worktree_result.lock().unwrap() = Some(Err(anyhow!(
+            "git worktree add failed: fatal: 'feat/test' is already used by worktree at '/tmp/.kiosk_worktrees/demo--feat-test'"
+        )));
+        *git.prune_worktrees_result.lock().unwrap() = Some(Err(anyhow!("prune failed")));
+
+        let error = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                branch: Some("feat/test".to_string()),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        )
+        .unwrap_err();
+
+        assert!(error.message().contains("stale worktree metadata"));
+        assert!(error.message().contains("worktree prune --expire now"));
+    }
+
+    #[test]
+    fn status_reports_attached_from_client_count() {
+        let config = test_config();
+        let mut git = MockGitProvider::default();
+        let mut clients = HashMap::new();
+        clients.insert("demo".to_string(), vec!["/dev/pts/1".to_string()]);
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo".to_string()]),
+            clients,
+            capture_output: Mutex::new("line a\nline b".to_string()),
+            ..Default::default()
+        };
+
+        git.repos = vec![repo("/tmp/demo", "demo")];
+        git.worktrees = vec![Worktree {
+            path: PathBuf::from("/tmp/demo"),
+            branch: Some("main".to_string()),
+            is_main: true,
+        }];
+
+        let output = status(
+            &config,
+            &git,
+            &tmux,
+            &StatusArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                format: OutputFormat::Table,
+                lines: 10,
+                pane: PaneTarget::Index(0),
+                strip_ansi: false,
+            },
+        )
+        .unwrap();
+
+        assert!(output.attached);
+        assert_eq!(output.clients, 1);
+        assert_eq!(output.source, StatusSource::Live);
+        assert!(output.output.contains("line a"));
+    }
+
+    #[test]
+    fn tail_lines_returns_requested_suffix() {
+        let content = "a\nb\nc\nd\ne\n";
+        assert_eq!(tail_lines(content, 2), "d\ne");
+        assert_eq!(tail_lines(content, 10), "a\nb\nc\nd\ne");
+    }
+
+    #[test]
+    fn select_log_lines_tails_matching_lines() {
+        let content = "building\ntest a ... ok\ntest b ... FAILED\ntest c ... ok\ndone\n";
+        let grep = Regex::new(r"^test .* ok$").unwrap();
+        assert_eq!(
+            select_log_lines(content, Some(&grep), 10),
+            "test a ... ok\ntest c ... ok"
+        );
+        assert_eq!(select_log_lines(content, Some(&grep), 1), "test c ... ok");
+        assert_eq!(select_log_lines(content, None, 1), "done");
+    }
+
+    #[test]
+    fn format_repo_table_snapshot() {
+        let rows = vec![
+            RepoOutput {
+                name: "kiosk".to_string(),
+                path: PathBuf::from("/tmp/kiosk"),
+                pinned: false,
+            },
+            RepoOutput {
+                name: "dotfiles".to_string(),
+                path: PathBuf::from("/tmp/dotfiles"),
+                pinned: false,
+            },
+        ];
+        let rendered = format_repo_table(&rows);
+        assert_eq!(
+            rendered,
+            "repo      path\n\
+             kiosk     /tmp/kiosk\n\
+             dotfiles  /tmp/dotfiles\n"
+        );
+    }
+
+    #[test]
+    fn format_branch_table_snapshot() {
+        let rows = vec![
+            BranchEntry {
+                name: "main".to_string(),
+                worktree_path: Some(PathBuf::from("/tmp/repo")),
+                has_session: false,
+                is_current: true,
+                is_default: false,
+                remote: None,
+                session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
+            },
+            BranchEntry {
+                name: "feat/test".to_string(),
+                worktree_path: None,
+                has_session: false,
+                is_current: false,
+                is_default: false,
+                remote: Some("origin".to_string()),
+                session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
+            },
+        ];
+        let rendered = format_branch_table(&rows);
+        assert_eq!(
+            rendered,
+            "branch     stat  remote  worktree\n\
+             main       *W--  -       /tmp/repo\n\
+             feat/test  ---R  origin  -\n"
+        );
+    }
+
+    #[test]
+    fn format_log_table_snapshot() {
+        let logs = vec![
+            SessionLogFile {
+                session: "repo--feat".to_string(),
+                path: PathBuf::from("/state/logs/repo--feat.log"),
+                size_bytes: 2048,
+                modified_unix_secs: 0,
+                rotation: None,
+            },
+            SessionLogFile {
+                session: "repo--feat".to_string(),
+                path: PathBuf::from("/state/logs/repo--feat.log.1"),
+                size_bytes: 12,
+                modified_unix_secs: 0,
+                rotation: Some(1),
+            },
+        ];
+        assert_eq!(
+            format_log_table(&logs),
+            "session       size  path\n\
+             repo--feat  2.0 KB  /state/logs/repo--feat.log\n\
+             repo--feat    12 B  /state/logs/repo--feat.log.1\n"
+        );
+    }
+
+    #[test]
+    fn format_bytes_uses_binary_units() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn format_session_table_snapshot() {
+        let rows = vec![
+            SessionOutput {
+                session: "repo--feat".to_string(),
+                repo: "repo".to_string(),
+                branch: Some("feat/test".to_string()),
+                path: PathBuf::from("/tmp/repo-feat"),
+                attached: false,
+                last_activity: 1_234_567_890,
+                pane_count: 1,
+                current_command: "zsh".to_string(),
+            },
+            SessionOutput {
+                session: "repo".to_string(),
+                repo: "repo".to_string(),
+                branch: None,
+                path: PathBuf::from("/tmp/repo"),
+                attached: true,
+                last_activity: 1_234_567_891,
+                pane_count: 2,
+                current_command: "bash".to_string(),
+            },
+        ];
+        let rendered = format_session_table(&rows);
+        assert_eq!(
+            rendered,
+            "session     repo  branch      path            attached\n\
+             repo--feat  repo  feat/test   /tmp/repo-feat  false\n\
+             repo        repo  (detached)  /tmp/repo       true\n"
+        );
+    }
+
+    fn main_worktree() -> Worktree {
+        Worktree {
+            path: PathBuf::from("/tmp/demo"),
+            branch: Some("main".to_string()),
+            is_main: true,
+        }
+    }
+
+    fn demo_git(worktrees: Vec<Worktree>, branches: Vec<String>) -> MockGitProvider {
+        MockGitProvider {
+            repos: vec![repo("/tmp/demo", "demo")],
+            worktrees,
+            branches,
+            ..Default::default()
+        }
+    }
+
+    // --- list tests ---
+
+    #[test]
+    fn list_returns_discovered_repos_as_json() {
+        let config = test_config();
+        let git = MockGitProvider {
+            repos: vec![repo("/tmp/alpha", "alpha"), repo("/tmp/beta", "beta")],
+            ..Default::default()
+        };
+
+        let result = list(
+            &config,
+            &git,
+            &MockTmuxProvider::default(),
+            &RepoPreferences::default(),
+            &list_args(),
+        );
+        assert!(result.is_ok());
+    }
+
+    fn list_args() -> ListArgs {
+        ListArgs {
+            filter: None,
+            path_under: None,
+            sort: None,
+            with_sessions_only: false,
+            pinned: false,
+            include_hidden: false,
+            format: OutputFormat::Json,
+        }
+    }
+
+    fn listed_names(output: &[RepoOutput]) -> Vec<&str> {
+        output.iter().map(|repo| repo.name.as_str()).collect()
+    }
+
+    #[test]
+    fn list_filters_by_name_glob_and_path() {
+        let config = test_config();
+        let git = MockGitProvider {
+            repos: vec![
+                repo("/work/kiosk", "kiosk"),
+                repo("/work/kiosk-docs", "kiosk-docs"),
+                repo("/personal/dotfiles", "dotfiles"),
+            ],
+            ..Default::default()
+        };
+        let tmux = MockTmuxProvider::default();
+
+        let args = ListArgs {
+            filter: Some("kiosk*".to_string()),
+            ..list_args()
+        };
+        let output = list(&config, &git, &tmux, &RepoPreferences::default(), &args).unwrap();
+        assert_eq!(listed_names(&output), vec!["kiosk", "kiosk-docs"]);
+
+        let args = ListArgs {
+            path_under: Some(PathBuf::from("/personal")),
+            ..list_args()
+        };
+        let output = list(&config, &git, &tmux, &RepoPreferences::default(), &args).unwrap();
+        assert_eq!(listed_names(&output), vec!["dotfiles"]);
+    }
+
+    #[test]
+    fn list_rejects_invalid_filter_glob() {
+        let config = test_config();
+        let git = MockGitProvider::default();
+        let args = ListArgs {
+            filter: Some("[".to_string()),
+            ..list_args()
+        };
+
+        let error = list(
+            &config,
+            &git,
+            &MockTmuxProvider::default(),
+            &RepoPreferences::default(),
+            &args,
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), 1);
+        assert!(error.message().contains("--filter"));
+    }
+
+    #[test]
+    fn list_sorts_by_activity_and_keeps_only_repos_with_sessions() {
+        let config = test_config();
+        let git = MockGitProvider {
+            repos: vec![
+                repo("/tmp/alpha", "alpha"),
+                repo("/tmp/beta", "beta"),
+                repo("/tmp/gamma", "gamma"),
+            ],
+            ..Default::default()
+        };
+        let tmux = MockTmuxProvider {
+            sessions_with_activity: vec![("alpha".to_string(), 100), ("gamma".to_string(), 200)],
+            ..Default::default()
+        };
+
+        let args = ListArgs {
+            sort: Some(ListSort::Activity),
+            ..list_args()
+        };
+        let output = list(&config, &git, &tmux, &RepoPreferences::default(), &args).unwrap();
+        assert_eq!(listed_names(&output), vec!["gamma", "alpha", "beta"]);
+
+        let args = ListArgs {
+            with_sessions_only: true,
+            sort: Some(ListSort::Name),
+            ..list_args()
+        };
+        let output = list(&config, &git, &tmux, &RepoPreferences::default(), &args).unwrap();
+        assert_eq!(listed_names(&output), vec!["alpha", "gamma"]);
+    }
+
+    #[test]
+    fn list_sorts_by_frecency_before_activity() {
+        let config = test_config();
+        let git = MockGitProvider {
+            repos: vec![
+                repo("/tmp/alpha", "alpha"),
+                repo("/tmp/beta", "beta"),
+                repo("/tmp/gamma", "gamma"),
+            ],
+            ..Default::default()
+        };
+        let tmux = MockTmuxProvider {
+            sessions_with_activity: vec![("gamma".to_string(), 200)],
+            ..Default::default()
+        };
+        let prefs = RepoPreferences {
+            frecency: Frecency::new(
+                vec![Visit {
+                    repo_path: PathBuf::from("/tmp/beta"),
+                    branch: Some("main".to_string()),
+                    count: 3,
+                    last_open_unix_secs: 1_000,
+                }],
+                1_000,
+            ),
+            ..Default::default()
+        };
+
+        let args = ListArgs {
+            sort: Some(ListSort::Frecency),
+            ..list_args()
+        };
+        let output = list(&config, &git, &tmux, &prefs, &args).unwrap();
+        assert_eq!(listed_names(&output), vec!["beta", "gamma", "alpha"]);
+    }
+
+    #[test]
+    fn list_pinned_only_returns_pinned_repos() {
+        let config = test_config();
+        let git = MockGitProvider {
+            repos: vec![repo("/tmp/alpha", "alpha"), repo("/tmp/beta", "beta")],
+            ..Default::default()
+        };
+        let prefs = RepoPreferences {
+            pinned: HashSet::from([PathBuf::from("/tmp/beta")]),
+            ..Default::default()
+        };
+
+        let output = list(
+            &config,
+            &git,
+            &MockTmuxProvider::default(),
+            &prefs,
+            &list_args(),
+        )
+        .unwrap();
+        assert!(!output[0].pinned);
+        assert!(output[1].pinned);
+
+        let args = ListArgs {
+            pinned: true,
+            ..list_args()
+        };
+        let output = list(&config, &git, &MockTmuxProvider::default(), &prefs, &args).unwrap();
+        assert_eq!(listed_names(&output), vec!["beta"]);
+    }
+
+    #[test]
+    fn list_leaves_out_ignored_and_hidden_repos_unless_included() {
+        let config = test_config();
+        let git = MockGitProvider {
+            repos: vec![
+                repo("/tmp/alpha", "alpha"),
+                repo("/tmp/vendor/beta", "beta"),
+                repo("/tmp/gamma", "gamma"),
+            ],
+            ..Default::default()
+        };
+        let prefs = RepoPreferences {
+            filter: RepoFilter::new(
+                &["/tmp/vendor/*".to_string()],
+                HashSet::from([PathBuf::from("/tmp/gamma")]),
+            )
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let output = list(
+            &config,
+            &git,
+            &MockTmuxProvider::default(),
+            &prefs,
+            &list_args(),
+        )
+        .unwrap();
+        assert_eq!(listed_names(&output), vec!["alpha"]);
+
+        let args = ListArgs {
+            include_hidden: true,
+            ..list_args()
+        };
+        let output = list(&config, &git, &MockTmuxProvider::default(), &prefs, &args).unwrap();
+        assert_eq!(listed_names(&output), vec!["alpha", "beta", "gamma"]);
+    }
+
+    // --- branches tests ---
+
+    #[test]
+    fn branches_returns_error_for_unknown_repo() {
+        let config = test_config();
+        let git = MockGitProvider::default();
+        let tmux = MockTmuxProvider::default();
+
+        let args = BranchesArgs {
+            repo: "nonexistent".to_string(),
+            ..branches_args()
+        };
+        let error = branches(
+            &config,
+            &git,
+            &tmux,
+            &NoteStore::disabled(),
+            &LabelStore::disabled(),
+            &args,
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(error.message().contains("nonexistent"));
+    }
+
+    #[test]
+    fn branches_json_uses_branch_output_struct() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let tmux = MockTmuxProvider::default();
+
+        let result = branches(
+            &config,
+            &git,
+            &tmux,
+            &NoteStore::disabled(),
+            &LabelStore::disabled(),
+            &branches_args(),
+        );
+        assert!(result.is_ok());
+    }
+
+    fn branches_args() -> BranchesArgs {
+        BranchesArgs {
+            repo: "demo".to_string(),
+            local: false,
+            remote: false,
+            remote_name: None,
+            with_worktree: false,
+            with_session: false,
+            with_agent: false,
+            contains: None,
+            labels: Vec::new(),
+            format: OutputFormat::Json,
+        }
+    }
+
+    fn branch_names(entries: &[BranchEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    fn filtered_branches(
+        git: &MockGitProvider,
+        tmux: &MockTmuxProvider,
+        args: &BranchesArgs,
+    ) -> Vec<String> {
+        let entries = branches(
+            &test_config(),
+            git,
+            tmux,
+            &NoteStore::disabled(),
+            &LabelStore::disabled(),
+            args,
+        )
+        .unwrap();
+        branch_names(&entries)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    fn filter_demo_git() -> MockGitProvider {
+        demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/demo-wt"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+                Worktree {
+                    path: PathBuf::from("/tmp/demo-agent"),
+                    branch: Some("agent-task".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec![
+                "main".to_string(),
+                "feat".to_string(),
+                "agent-task".to_string(),
+                "idle".to_string(),
+            ],
+        )
+        .with_remote_branches("origin", ["main", "upstream-only"])
+    }
+
+    #[test]
+    fn branches_filters_local_and_remote() {
+        let git = filter_demo_git();
+        let tmux = MockTmuxProvider::default();
+
+        let local = BranchesArgs {
+            local: true,
+            ..branches_args()
+        };
+        let mut names = filtered_branches(&git, &tmux, &local);
+        names.sort();
+        assert_eq!(names, vec!["agent-task", "feat", "idle", "main"]);
+
+        let remote = BranchesArgs {
+            remote: true,
+            ..branches_args()
+        };
+        assert_eq!(
+            filtered_branches(&git, &tmux, &remote),
+            vec!["upstream-only"]
+        );
+    }
+
+    #[test]
+    fn branches_filters_by_remote_name() {
+        let git = filter_demo_git().with_remote_branches("upstream", ["release"]);
+        let tmux = MockTmuxProvider::default();
+        let upstream = BranchesArgs {
+            remote: true,
+            remote_name: Some("upstream".to_string()),
+            ..branches_args()
+        };
+        assert_eq!(filtered_branches(&git, &tmux, &upstream), vec!["release"]);
+
+        let missing = BranchesArgs {
+            remote_name: Some("fork".to_string()),
+            ..upstream
+        };
+        let error = branches(
+            &test_config(),
+            &git,
+            &tmux,
+            &NoteStore::disabled(),
+            &LabelStore::disabled(),
+            &missing,
+        )
+        .unwrap_err();
+        assert_eq!(error.message(), "remote 'fork' not found");
+    }
+
+    #[test]
+    fn branches_filters_by_worktree_session_and_agent() {
+        let git = filter_demo_git();
+        let tmux = MockTmuxProvider::default()
+            .with_session("demo-wt")
+            .with_session_running("demo-agent", "claude");
+
+        let with_worktree = BranchesArgs {
+            with_worktree: true,
+            ..branches_args()
+        };
+        let mut names = filtered_branches(&git, &tmux, &with_worktree);
+        names.sort();
+        assert_eq!(names, vec!["agent-task", "feat", "main"]);
+
+        let with_session = BranchesArgs {
+            with_session: true,
+            ..branches_args()
+        };
+        let mut names = filtered_branches(&git, &tmux, &with_session);
+        names.sort();
+        assert_eq!(names, vec!["agent-task", "feat"]);
+
+        let with_agent = BranchesArgs {
+            with_agent: true,
+            ..branches_args()
+        };
+        assert_eq!(
+            filtered_branches(&git, &tmux, &with_agent),
+            vec!["agent-task"]
+        );
+    }
+
+    #[test]
+    fn branches_filters_by_contained_commit() {
+        let git =
+            filter_demo_git().with_branches_containing("abc123", ["feat", "origin/upstream-only"]);
+        let tmux = MockTmuxProvider::default();
+
+        let args = BranchesArgs {
+            contains: Some("abc123".to_string()),
+            ..branches_args()
+        };
+        assert_eq!(
+            filtered_branches(&git, &tmux, &args),
+            vec!["feat", "upstream-only"]
+        );
+
+        let args = BranchesArgs {
+            contains: Some("nope".to_string()),
+            ..branches_args()
+        };
+        let error = branches(
+            &test_config(),
+            &git,
+            &tmux,
+            &NoteStore::disabled(),
+            &LabelStore::disabled(),
+            &args,
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), 1);
+        assert!(error.message().contains("--contains"));
+    }
+
+    #[test]
+    fn branches_include_last_commits() {
+        let commit = CommitInfo {
+            subject: "Upstream work".to_string(),
+            author: "Ada".to_string(),
+            timestamp: 1_700_000_000,
+            ahead_behind: None,
+        };
+        let git = MockGitProvider {
+            last_commits: [("origin/upstream-only".to_string(), commit.clone())].into(),
+            ..filter_demo_git()
+        };
+        let tmux = MockTmuxProvider::default();
+
+        let entries = branches(
+            &test_config(),
+            &git,
+            &tmux,
+            &NoteStore::disabled(),
+            &LabelStore::disabled(),
+            &branches_args(),
+        )
+        .unwrap();
+        let upstream = entries
+            .iter()
+            .find(|entry| entry.name == "upstream-only")
+            .unwrap();
+        assert_eq!(upstream.last_commit.as_ref(), Some(&commit));
+        let main = entries.iter().find(|entry| entry.name == "main").unwrap();
+        assert!(main.last_commit.is_none());
+
+        let json = serde_json::to_value(BranchOutput::from(upstream)).unwrap();
+        assert_eq!(
+            json["last_commit"],
+            serde_json::json!({
+                "subject": "Upstream work",
+                "author": "Ada",
+                "timestamp": 1_700_000_000,
+            })
+        );
+    }
+
+    // --- diff tests ---
+
+    #[test]
+    fn path_resolves_worktrees_without_creating_them() {
+        let mut git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat".to_string()],
+        );
+        git.remote_branches = vec!["fix/bug".to_string()];
+        let path = |branch: Option<&str>| {
+            let args = PathArgs {
+                repo: "demo".to_string(),
+                branch: branch.map(str::to_string),
+                kind: PathKind::Worktree,
+                format: OutputFormat::Json,
+            };
+            path(&test_config(), &git, &args)
+        };
+
+        let main = path(None).unwrap();
+        assert_eq!(main.path, PathBuf::from("/tmp/demo"));
+        assert!(main.exists);
+        assert_eq!(main.session, "demo");
+        assert!(main.log.ends_with("demo.log"));
+
+        let feat = path(Some("feat")).unwrap();
+        assert_eq!(feat.path, PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"));
+        assert!(feat.exists);
+
+        let fix = path(Some("fix/bug")).unwrap();
+        assert_eq!(
+            fix.path,
+            PathBuf::from("/tmp/.kiosk_worktrees/demo--fix-bug")
+        );
+        assert!(!fix.exists);
+
+        let missing = path(Some("nope")).unwrap_err();
+        assert_eq!(missing.code(), CliError::NOT_FOUND);
+    }
+
+    #[test]
+    fn note_sets_shows_and_clears_branch_notes() {
+        let git = demo_git(
+            vec![main_worktree()],
+            vec!["main".to_string(), "feat".to_string()],
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let notes = NoteStore::at(dir.path().join("notes.toml"));
+        let note = |branch: &str, text: Option<&str>| {
+            let args = NoteArgs {
+                repo: "demo".to_string(),
+                branch: branch.to_string(),
+                note: text.map(str::to_string),
+                format: OutputFormat::Json,
+            };
+            note(&test_config(), &git, &notes, &args)
+        };
+
+        assert_eq!(note("feat", None).unwrap().note, None);
+        let set = note("feat", Some("waiting for review")).unwrap();
+        assert_eq!(set.note.as_deref(), Some("waiting for review"));
+        assert_eq!(
+            note("feat", None).unwrap().note.as_deref(),
+            Some("waiting for review")
+        );
+        assert_eq!(note("feat", Some("")).unwrap().note, None);
+
+        let missing = note("nope", Some("x")).unwrap_err();
+        assert_eq!(missing.code(), CliError::NOT_FOUND);
+    }
+
+    #[test]
+    fn unhide_shows_a_hidden_repo_again() {
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let dir = tempfile::tempdir().unwrap();
+        let hidden = HiddenStore::at(dir.path().join("hidden.toml"));
+        hidden.hide(Path::new("/tmp/demo")).unwrap();
+        let unhide = |repo: &str| {
+            let args = UnhideArgs {
+                repo: repo.to_string(),
+                format: OutputFormat::Json,
+            };
+            unhide(&test_config(), &git, &hidden, &args)
+        };
+
+        let output = unhide("demo").unwrap();
+        assert_eq!(output.path, PathBuf::from("/tmp/demo"));
+        assert!(hidden.load().is_empty());
+
+        assert_eq!(unhide("demo").unwrap_err().code(), CliError::USER);
+        assert_eq!(unhide("nope").unwrap_err().code(), CliError::NOT_FOUND);
+    }
+
+    #[test]
+    fn branches_include_notes_and_filter_by_labels() {
+        let git = demo_git(
+            vec![main_worktree()],
+            vec![
+                "main".to_string(),
+                "feat".to_string(),
+                "sandbox".to_string(),
+            ],
+        );
+        let tmux = MockTmuxProvider::default();
+        let dir = tempfile::tempdir().unwrap();
+        let notes = NoteStore::at(dir.path().join("notes.toml"));
+        let labels = LabelStore::at(dir.path().join("labels.toml"));
+        let repo = Path::new("/tmp/demo");
+        notes.set(repo, "feat", "flaky test").unwrap();
+        let label = |branch: &str, names: &[&str]| {
+            let args = LabelArgs {
+                repo: "demo".to_string(),
+                branch: branch.to_string(),
+                change: LabelChange::Add,
+                labels: names.iter().map(ToString::to_string).collect(),
+                format: OutputFormat::Json,
+            };
+            label(&test_config(), &git, &labels, &args)
+        };
+        label("sandbox", &["agent"]).unwrap();
+        assert_eq!(
+            label("feat", &["agent", "review"]).unwrap().labels,
+            ["agent", "review"]
+        );
+        assert_eq!(
+            label("nope", &["agent"]).unwrap_err().code(),
+            CliError::NOT_FOUND
+        );
+        assert_eq!(label("feat", &["two words"]).unwrap_err().code(), 1);
+
+        let branches = |filter: &[&str]| {
+            let args = BranchesArgs {
+                labels: filter.iter().map(ToString::to_string).collect(),
+                ..branches_args()
+            };
+            branches(&test_config(), &git, &tmux, &notes, &labels, &args).unwrap()
+        };
+
+        let all = branches(&[]);
+        let feat = all.iter().find(|entry| entry.name == "feat").unwrap();
+        assert_eq!(feat.note.as_deref(), Some("flaky test"));
+        assert_eq!(
+            format_branch_table(&all).lines().next(),
+            Some("branch   stat  worktree   labels        note")
+        );
+        assert_eq!(branch_names(&branches(&["agent"])), ["feat", "sandbox"]);
+        assert_eq!(branch_names(&branches(&["agent", "review"])), ["feat"]);
+    }
+
+    #[test]
+    fn diff_compares_worktree_against_default_branch() {
+        let mut git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat".to_string()],
+        );
+        git.default_branch = Some("main".to_string());
+        git.diff_files = vec![
+            FileChange {
+                path: PathBuf::from("src/lib.rs"),
+                insertions: Some(10),
+                deletions: Some(2),
+            },
+            FileChange {
+                path: PathBuf::from("logo.png"),
+                insertions: None,
+                deletions: None,
+            },
+        ];
+        let args = DiffArgs {
+            repo: "demo".to_string(),
+            branch: "feat".to_string(),
+            base: None,
+            mode: DiffMode::Stat,
+            format: OutputFormat::Table,
+        };
+
+        let output = diff(&test_config(), &git, &args).unwrap();
+        assert_eq!(output.base, "main");
+        assert_eq!((output.insertions, output.deletions), (10, 2));
+        assert_eq!(output.patch, None);
+        assert_eq!(
+            git.diff_calls.lock().unwrap().as_slice(),
+            &[(
+                PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                "main".to_string()
+            )]
+        );
+        assert_eq!(
+            format_diff_stat(&output),
+            "src/lib.rs  +10 -2\nlogo.png    binary\n2 files changed against main, +10 -2\n"
+        );
+
+        let error = diff(
+            &test_config(),
+            &git,
+            &DiffArgs {
+                branch: "missing".to_string(),
+                ..args
+            },
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+    }
+
+    // --- delete tests ---
+
+    #[test]
+    fn delete_rejects_current_branch() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let tmux = MockTmuxProvider::default();
+
+        let error = delete(
+            &config,
+            &git,
+            &tmux,
+            &DeleteArgs {
+                repo: "demo".to_string(),
+                branch: "main".to_string(),
+                force: false,
+                force_dirty: false,
+                format: OutputFormat::Table,
+            },
+            &Confirm::non_interactive(false),
+            &Stores::disabled(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.code(), 1);
+        assert!(error.message().contains("current branch"));
+    }
+
+    #[test]
+    fn delete_rejects_branch_without_worktree() {
+        let config = test_config();
+        let git = demo_git(
+            vec![main_worktree()],
+            vec!["main".to_string(), "feat/no-wt".to_string()],
+        );
+        let tmux = MockTmuxProvider::default();
+
+        let error = delete(
+            &config,
+            &git,
+            &tmux,
+            &DeleteArgs {
+                repo: "demo".to_string(),
+                branch: "feat/no-wt".to_string(),
+                force: false,
+                force_dirty: false,
+                format: OutputFormat::Table,
+            },
+            &Confirm::non_interactive(false),
+            &Stores::disabled(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(error.message().contains("no worktree"));
+    }
+
+    #[test]
+    fn delete_rejects_attached_session_without_force() {
+        let config = test_config();
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-del"),
+                    branch: Some("feat/del".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat/del".to_string()],
+        );
+
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo--feat-del".to_string()]),
+            clients: HashMap::from([(
+                "demo--feat-del".to_string(),
+                vec!["/dev/pts/0".to_string()],
+            )]),
+            ..Default::default()
+        };
+
+        let error = delete(
+            &config,
+            &git,
+            &tmux,
+            &DeleteArgs {
+                repo: "demo".to_string(),
+                branch: "feat/del".to_string(),
+                force: false,
+                force_dirty: false,
+                format: OutputFormat::Table,
+            },
+            &Confirm::non_interactive(false),
+            &Stores::disabled(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.code(), 1);
+        assert!(error.message().contains("attached"));
+        assert!(error.message().contains("--force"));
+    }
+
+    #[test]
+    fn delete_with_force_kills_attached_session() {
+        let config = test_config();
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-del"),
+                    branch: Some("feat/del".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat/del".to_string()],
+        );
+
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo--feat-del".to_string()]),
+            clients: HashMap::from([(
+                "demo--feat-del".to_string(),
+                vec!["/dev/pts/0".to_string()],
+            )]),
+            ..Default::default()
+        };
+
+        let journal_dir = tempfile::tempdir().unwrap();
+        let stores = stores_in(&journal_dir);
+        stores
+            .sessions
+            .record(OpenedSession::new(
+                "demo--feat-del".to_string(),
+                PathBuf::from("/tmp/demo"),
+                PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-del"),
+                Some("feat/del".to_string()),
+                None,
+            ))
+            .unwrap();
+        let result = delete(
+            &config,
+            &git,
+            &tmux,
+            &DeleteArgs {
+                repo: "demo".to_string(),
+                branch: "feat/del".to_string(),
+                force: true,
+                force_dirty: false,
+                format: OutputFormat::Table,
+            },
+            &Confirm::non_interactive(false),
+            &stores,
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(
+            tmux.killed_sessions.lock().unwrap().as_slice(),
+            &["demo--feat-del".to_string()]
+        );
+        assert!(stores.sessions.load().is_empty());
+    }
+
+    #[test]
+    fn delete_already_in_progress_leaves_the_session_alone() {
+        let config = test_config();
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-del"),
+                    branch: Some("feat/del".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat/del".to_string()],
+        );
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo--feat-del".to_string()]),
+            ..Default::default()
+        };
+        let state = tempfile::tempdir().unwrap();
+        let stores = stores_in(&state);
+        stores
+            .pending_deletes
+            .begin(PendingWorktreeDelete::new(
+                PathBuf::from("/tmp/demo"),
+                "feat/del".to_string(),
+                PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-del"),
+            ))
+            .unwrap();
+
+        let error = delete(
+            &config,
+            &git,
+            &tmux,
+            &DeleteArgs {
+                repo: "demo".to_string(),
+                branch: "feat/del".to_string(),
+                force: true,
+                force_dirty: false,
+                format: OutputFormat::Table,
+            },
+            &Confirm::non_interactive(false),
+            &stores,
+        )
+        .unwrap_err();
+
+        assert!(error.message().contains("already in progress"));
+        assert!(tmux.killed_sessions.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn delete_with_yes_confirms_killing_attached_session() {
+        let config = test_config();
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-yes"),
+                    branch: Some("feat/yes".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat/yes".to_string()],
+        );
+        let tmux = MockTmuxProvider::default()
+            .with_session("demo--feat-yes")
+            .with_client("demo--feat-yes", "/dev/pts/0");
+
+        let journal_dir = tempfile::tempdir().unwrap();
+        delete(
+            &config,
+            &git,
+            &tmux,
+            &DeleteArgs {
+                repo: "demo".to_string(),
+                branch: "feat/yes".to_string(),
+                force: false,
+                force_dirty: false,
+                format: OutputFormat::Table,
+            },
+            &Confirm::non_interactive(true),
+            &stores_in(&journal_dir),
+        )
+        .unwrap();
+
+        assert_eq!(
+            tmux.killed_sessions.lock().unwrap().as_slice(),
+            &["demo--feat-yes".to_string()]
+        );
+    }
+
+    #[test]
+    fn delete_refuses_dirty_worktree_without_force_dirty() {
+        let config = test_config();
+        let path = PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-dirty");
+        let mut git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: path.clone(),
+                    branch: Some("feat/dirty".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat/dirty".to_string()],
+        );
+        git.worktree_changes.insert(
+            path.clone(),
+            WorktreeChanges {
+                files_changed: 2,
+                insertions: 7,
+                deletions: 1,
+                unpushed_commits: 1,
+            },
+        );
+        let tmux = MockTmuxProvider::default().with_session("demo--feat-dirty");
+        let journal_dir = tempfile::tempdir().unwrap();
+        let stores = stores_in(&journal_dir);
+        let mut args = DeleteArgs {
+            repo: "demo".to_string(),
+            branch: "feat/dirty".to_string(),
+            force: true,
+            force_dirty: false,
+            format: OutputFormat::Json,
+        };
+
+        let err = delete(
+            &config,
+            &git,
+            &tmux,
+            &args,
+            &Confirm::non_interactive(true),
+            &stores,
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), CliError::USER);
+        assert!(
+            err.message()
+                .contains("2 files changed (+7 -1), 1 unpushed commit"),
+            "{}",
+            err.message()
+        );
+        assert_eq!(
+            err.details.unwrap(),
+            serde_json::json!({
+                "path": path,
+                "files_changed": 2,
+                "insertions": 7,
+                "deletions": 1,
+                "unpushed_commits": 1,
+            })
+        );
+        assert!(tmux.killed_sessions.lock().unwrap().is_empty());
+        assert!(git.stash_calls.lock().unwrap().is_empty());
+
+        args.force_dirty = true;
+        *git.remove_worktree_result.lock().unwrap() =
+            Some(Err(anyhow!("git worktree remove failed: fatal: locked")));
+        let err = delete(
+            &config,
+            &git,
+            &tmux,
+            &args,
+            &Confirm::non_interactive(true),
+            &stores,
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), CliError::SYSTEM);
+        assert!(
+            err.message().contains(&path.display().to_string()) && err.message().contains("locked"),
+            "{}",
+            err.message()
+        );
+
+        delete(
+            &config,
+            &git,
+            &tmux,
+            &args,
+            &Confirm::non_interactive(true),
+            &stores,
+        )
+        .unwrap();
+        assert_eq!(git.prune_worktrees_calls.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn delete_refuses_a_worktree_git_cannot_inspect_without_force_dirty() {
+        let config = test_config();
+        let path = PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-broken");
+        let mut git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: path.clone(),
+                    branch: Some("feat/broken".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat/broken".to_string()],
+        );
+        git.worktree_changes_error = Some("fatal: not a git repository".to_string());
+        let tmux = MockTmuxProvider::default().with_session("demo--feat-broken");
+        let journal_dir = tempfile::tempdir().unwrap();
+        let stores = stores_in(&journal_dir);
+        let mut args = DeleteArgs {
+            repo: "demo".to_string(),
+            branch: "feat/broken".to_string(),
+            force: true,
+            force_dirty: false,
+            format: OutputFormat::Json,
+        };
+        let delete = |args: &DeleteArgs| {
+            delete(
+                &config,
+                &git,
+                &tmux,
+                args,
+                &Confirm::non_interactive(true),
+                &stores,
+            )
+        };
+
+        let err = delete(&args).unwrap_err();
+        assert_eq!(err.code(), CliError::SYSTEM);
+        assert!(
+            err.message().contains("not a git repository")
+                && err.message().contains("--force-dirty"),
+            "{}",
+            err.message()
+        );
+        assert!(tmux.killed_sessions.lock().unwrap().is_empty());
+        assert!(git.prune_worktrees_calls.lock().unwrap().is_empty());
+
+        args.force_dirty = true;
+        delete(&args).unwrap();
+        assert_eq!(git.prune_worktrees_calls.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn confirm_only_asks_when_not_assuming_yes() {
+        assert!(Confirm::non_interactive(true).ask("Go?").unwrap());
+        assert!(!Confirm::non_interactive(false).ask("Go?").unwrap());
+        let refuse = |_: &str| -> CliResult<bool> { Err(CliError::user("asked")) };
+        assert!(Confirm::new(true, refuse).ask("Go?").unwrap());
+        assert!(Confirm::new(false, refuse).ask("Go?").is_err());
+        assert!(
+            Confirm::new(false, |question| Ok(question == "Go?"))
+                .ask("Go?")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn delete_journals_stash_and_undo_restores_it() {
+        let config = test_config();
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-undo"),
+                    branch: Some("feat/undo".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat/undo".to_string()],
+        );
+        *git.stash_result.lock().unwrap() = Some(Ok(Some("abc123".to_string())));
+        let tmux = MockTmuxProvider::default();
+        let journal_dir = tempfile::tempdir().unwrap();
+        let stores = stores_in(&journal_dir);
+
+        delete(
+            &config,
+            &git,
+            &tmux,
+            &DeleteArgs {
+                repo: "demo".to_string(),
+                branch: "feat/undo".to_string(),
+                force: false,
+                force_dirty: false,
+                format: OutputFormat::Table,
+            },
+            &Confirm::non_interactive(false),
+            &stores,
+        )
+        .unwrap();
+
+        let entry = stores.deletes.latest(None, None).expect("journal entry");
+        assert_eq!(entry.branch_name, "feat/undo");
+        assert_eq!(entry.stash.as_deref(), Some("abc123"));
+
+        let output = undo(
+            &config,
+            &git,
+            &UndoArgs {
+                repo: Some("demo".to_string()),
+                branch: None,
+                format: OutputFormat::Table,
+            },
+            &stores.deletes,
+        )
+        .unwrap();
+
+        assert_eq!(output.repo, "demo");
+        assert_eq!(output.branch, "feat/undo");
+        assert!(output.stash_restored);
+        assert_eq!(
+            git.pop_stash_calls.lock().unwrap().as_slice(),
+            &[(
+                PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-undo"),
+                "abc123".to_string()
+            )]
+        );
+        assert!(stores.deletes.load().is_empty());
+    }
+
+    fn reap_worktree(branch: &str) -> Worktree {
+        Worktree {
+            path: PathBuf::from(format!("/tmp/.kiosk_worktrees/demo--{branch}")),
+            branch: Some(branch.to_string()),
+            is_main: false,
+        }
+    }
+
+    #[test]
+    fn reap_kills_idle_detached_sessions_and_removes_merged_worktrees() {
+        let hour = SECS_PER_HOUR;
+        let now = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(100 * hour);
+        let mut git = demo_git(
+            vec![
+                main_worktree(),
+                reap_worktree("merged"),
+                reap_worktree("open"),
+                reap_worktree("busy"),
+                reap_worktree("watched"),
+            ],
+            vec!["main".to_string(), "merged".to_string(), "open".to_string()],
+        );
+        git.default_branch = Some("main".to_string());
+        git.branches_containing = HashMap::from([
+            (
+                "merged".to_string(),
+                vec!["main".to_string(), "merged".to_string()],
+            ),
+            ("open".to_string(), vec!["open".to_string()]),
+        ]);
+        let sessions = [
+            ("demo", 10 * hour),
+            ("demo--merged", 50 * hour),
+            ("demo--open", 60 * hour),
+            ("demo--busy", 99 * hour),
+            ("demo--watched", 0),
+        ];
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(
+                sessions
+                    .iter()
+                    .map(|(name, _)| (*name).to_string())
+                    .collect(),
+            ),
+            sessions_with_activity: sessions
+                .iter()
+                .map(|(name, activity)| ((*name).to_string(), *activity))
+                .collect(),
+            ..Default::default()
+        }
+        .with_client("demo--watched", "/dev/pts/0");
+        let args = ReapArgs {
+            idle_hours: 24,
+            remove_merged: true,
+            dry_run: true,
+            format: OutputFormat::Json,
+        };
+        let journal_dir = tempfile::tempdir().unwrap();
+        let stores = stores_in(&journal_dir);
+        let reap = |args: &ReapArgs| {
+            reap(
+                &test_config(),
+                &git,
+                &tmux,
+                args,
+                &Confirm::non_interactive(false),
+                &stores,
+                now,
+            )
+            .unwrap()
+        };
+
+        let dry_run = reap(&args);
+        assert!(tmux.killed_sessions.lock().unwrap().is_empty());
+
+        let output = reap(&ReapArgs {
+            dry_run: false,
+            ..args
+        });
+        assert_eq!(output.reaped, dry_run.reaped);
+        let reaped: Vec<(&str, u64, bool)> = output
+            .reaped
+            .iter()
+            .map(|reaped| {
+                (
+                    reaped.session.as_str(),
+                    reaped.idle_secs,
+                    reaped.worktree_removed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            reaped,
+            vec![
+                ("demo", 90 * hour, false),
+                ("demo--merged", 50 * hour, true),
+                ("demo--open", 40 * hour, false),
+            ]
+        );
+        assert_eq!(
+            tmux.killed_sessions.lock().unwrap().as_slice(),
+            &[
+                "demo".to_string(),
+                "demo--merged".to_string(),
+                "demo--open".to_string()
+            ]
+        );
+        let deleted = stores
+            .deletes
+            .latest(None, None)
+            .map(|entry| entry.branch_name);
+        assert_eq!(deleted.as_deref(), Some("merged"));
+    }
+
+    #[test]
+    fn reap_skips_sessions_without_activity_and_reports_failed_deletes() {
+        let hour = SECS_PER_HOUR;
+        let now = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(100 * hour);
+        let mut git = demo_git(
+            vec![
+                main_worktree(),
+                reap_worktree("stuck"),
+                reap_worktree("open"),
+                reap_worktree("unknown"),
+            ],
+            vec!["main".to_string(), "stuck".to_string(), "open".to_string()],
+        );
+        git.default_branch = Some("main".to_string());
+        git.branches_containing = HashMap::from([
+            (
+                "stuck".to_string(),
+                vec!["main".to_string(), "stuck".to_string()],
+            ),
+            ("open".to_string(), vec!["open".to_string()]),
+        ]);
+        let tmux = MockTmuxProvider::default()
+            .with_session_activity("demo--stuck", 10 * hour)
+            .with_session_activity("demo--open", 10 * hour)
+            .with_session("demo--unknown");
+        let state = tempfile::tempdir().unwrap();
+        let stores = stores_in(&state);
+        stores
+            .pending_deletes
+            .begin(PendingWorktreeDelete::new(
+                PathBuf::from("/tmp/demo"),
+                "stuck".to_string(),
+                PathBuf::from("/tmp/.kiosk_worktrees/demo--stuck"),
+            ))
+            .unwrap();
+
+        let output = reap(
+            &test_config(),
+            &git,
+            &tmux,
+            &ReapArgs {
+                idle_hours: 24,
+                remove_merged: true,
+                dry_run: false,
+                format: OutputFormat::Json,
+            },
+            &Confirm::non_interactive(false),
+            &stores,
+            now,
+        )
+        .unwrap();
+
+        let reaped: Vec<_> = output.reaped.iter().map(|r| r.session.as_str()).collect();
+        assert_eq!(reaped, ["demo--open"]);
+        assert_eq!(output.failed.len(), 1);
+        assert_eq!(output.failed[0].session, "demo--stuck");
+        assert!(output.failed[0].error.contains("already in progress"));
+        assert_eq!(
+            tmux.killed_sessions.lock().unwrap().as_slice(),
+            &["demo--open".to_string()]
+        );
+    }
+
+    // --- prune_branches tests ---
+
+    #[test]
+    fn prune_branches_deletes_branches_without_worktree_or_unique_commits() {
+        let config = test_config();
+        let mut git = demo_git(
+            vec![main_worktree(), reap_worktree("checked-out")],
+            ["main", "checked-out", "merged", "pushed", "local-only"]
+                .map(String::from)
+                .to_vec(),
+        );
+        git.default_branch = Some("main".to_string());
+        git.unique_commits = HashMap::from([
+            ("checked-out".to_string(), 0),
+            ("local-only".to_string(), 2),
+        ]);
+        let mut args = PruneBranchesArgs {
+            repo: Some("demo".to_string()),
+            dry_run: true,
+            format: OutputFormat::Json,
+        };
+        let prune = |args: &PruneBranchesArgs, yes: bool| {
+            prune_branches(&config, &git, args, &Confirm::non_interactive(yes))
+        };
+
+        let output = prune(&args, false).unwrap();
+        let pruned: Vec<_> = output.branches.iter().map(|b| b.branch.as_str()).collect();
+        assert_eq!(pruned, ["merged", "pushed"]);
+        assert!(git.delete_branch_calls.lock().unwrap().is_empty());
+
+        args.dry_run = false;
+        let err = prune(&args, false).unwrap_err();
+        assert_eq!(err.code(), CliError::USER);
+        assert!(git.delete_branch_calls.lock().unwrap().is_empty());
+
+        let output = prune(&args, true).unwrap();
+        assert!(!output.dry_run);
+        assert_eq!(
+            git.delete_branch_calls.lock().unwrap().as_slice(),
+            &[
+                (PathBuf::from("/tmp/demo"), "merged".to_string()),
+                (PathBuf::from("/tmp/demo"), "pushed".to_string()),
+            ]
+        );
+    }
+
+    // --- recover tests ---
+
+    #[test]
+    fn recover_lists_interrupted_operations_and_keeps_those_that_fail() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let state = tempfile::tempdir().unwrap();
+        let recovery = Recovery {
+            creates: PendingCreateStore::at(state.path().join("creates.toml")),
+            deletes: PendingDeleteStore::at(state.path().join("deletes.toml")),
+            journal: DeleteJournal::at(state.path().join("journal.toml")),
+        };
+        let interrupted = PendingWorktreeDelete {
+            pid: u32::MAX,
+            ..PendingWorktreeDelete::new(
+                PathBuf::from("/tmp/demo"),
+                "feat".to_string(),
+                state.path().join("demo-feat"),
+            )
+        };
+        assert!(recovery.deletes.begin(interrupted).unwrap());
+        let recover = |direction, yes| {
+            let args = RecoverArgs {
+                direction,
+                format: OutputFormat::Json,
+            };
+            recover(
+                &config,
+                &git,
+                &recovery,
+                &args,
+                &Confirm::non_interactive(yes),
+            )
+        };
+
+        let listed = recover(None, false).unwrap();
+        assert_eq!(listed.operations.len(), 1);
+        assert_eq!(listed.operations[0].kind, "delete");
+        assert_eq!(listed.operations[0].branch.as_deref(), Some("feat"));
+        assert_eq!(listed.operations[0].error, None);
+
+        // The worktree is gone and nothing was journaled, so there's nothing to restore
+        let back = recover(Some(RecoverDirection::Back), true).unwrap();
+        assert!(back.operations[0].error.is_some());
+        assert_eq!(recovery.interrupted().len(), 1);
+
+        let declined = recover(Some(RecoverDirection::Forward), false).unwrap_err();
+        assert_eq!(declined.code(), CliError::USER);
+
+        let forward = recover(Some(RecoverDirection::Forward), true).unwrap();
+        assert_eq!(forward.direction, Some(RecoverDirection::Forward));
+        assert_eq!(forward.operations[0].error, None);
+        assert_eq!(
+            *git.prune_worktrees_calls.lock().unwrap(),
+            [PathBuf::from("/tmp/demo")]
+        );
+        assert!(recover(None, false).unwrap().operations.is_empty());
+    }
+
+    // --- history tests ---
+
+    #[test]
+    fn history_filters_by_repo_and_keeps_the_most_recent() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let state = tempfile::tempdir().unwrap();
+        let log = AuditLog::at(state.path().join("audit.ndjson"));
+        let audited_git = AuditedGit::new(Arc::new(demo_git(vec![], vec![])), log.clone());
+        let audited_tmux = AuditedTmux::new(Arc::new(MockTmuxProvider::default()), log.clone());
+
+        // Removed worktrees are only known by kiosk's naming
+        audited_git
+            .remove_worktree(Path::new("/tmp/.kiosk_worktrees/demo--feat"))
+            .unwrap();
+        audited_git
+            .remove_worktree(Path::new("/tmp/.kiosk_worktrees/other--feat"))
+            .unwrap();
+        audited_tmux.kill_session("demo--feat");
+        audited_tmux.kill_session("other");
+        let history = |repo: Option<&str>, limit| {
+            let args = HistoryArgs {
+                repo: repo.map(str::to_string),
+                limit,
+                format: OutputFormat::Json,
+            };
+            history(&config, &git, &log, &args).unwrap()
+        };
+
+        assert_eq!(history(None, None).len(), 4);
+        let demo = history(Some("demo"), None);
+        assert_eq!(
+            demo.iter().map(|e| e.operation).collect::<Vec<_>>(),
+            [
+                AuditOperation::WorktreeRemoved,
+                AuditOperation::SessionKilled
+            ]
+        );
+        assert_eq!(demo[1].session.as_deref(), Some("demo--feat"));
+        assert_eq!(demo[0].error, None);
+        let last = history(None, Some(1));
+        assert_eq!(last[0].session.as_deref(), Some("other"));
+    }
+
+    // --- fetch tests ---
+
+    #[test]
+    fn fetch_reports_each_remote_and_records_clean_fetches() {
+        let config = test_config();
+        let history_dir = tempfile::tempdir().unwrap();
+        let history = FetchHistoryStore::at(history_dir.path().join("fetches.toml"));
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()])
+            .with_remote_branches("origin", ["main", "feat"])
+            .with_remote_branches("upstream", ["main"]);
+        let args = FetchArgs {
+            repo: Some("demo".to_string()),
+            prune: true,
+            format: OutputFormat::Json,
+        };
+
+        let output = fetch(&config, &git, &history, &args).unwrap();
+        let remotes: Vec<_> = output.repos[0]
+            .remotes
+            .iter()
+            .map(|remote| {
+                (
+                    remote.remote.as_str(),
+                    remote.branches,
+                    remote.error.is_none(),
+                )
+            })
+            .collect();
+        assert_eq!(remotes, [("origin", 2, true), ("upstream", 1, true)]);
+        assert!(
+            git.fetch_remote_calls
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|call| call.2)
+        );
+        assert!(
+            history
+                .last_fetch_unix_secs(Path::new("/tmp/demo"))
+                .is_some()
+        );
+
+        // A failed remote is reported without stopping the others, and the repo isn't
+        // counted as fetched
+        let history = FetchHistoryStore::at(history_dir.path().join("failed.toml"));
+        git.fetch_remote_results.lock().unwrap().insert(
+            (PathBuf::from("/tmp/demo"), "origin".to_string()),
+            Err(anyhow::anyhow!("could not read from remote repository")),
+        );
+        let output = fetch(&config, &git, &history, &args).unwrap();
+        let errors: Vec<_> = output.repos[0]
+            .remotes
+            .iter()
+            .map(|remote| remote.error.as_deref())
+            .collect();
+        assert_eq!(
+            errors,
+            [Some("could not read from remote repository"), None]
+        );
+        assert!(
+            history
+                .last_fetch_unix_secs(Path::new("/tmp/demo"))
+                .is_none()
+        );
+
+        let missing = FetchArgs {
+            repo: Some("nope".to_string()),
+            ..args
+        };
+        let error = fetch(&config, &git, &history, &missing).unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+    }
+
+    // --- clone tests ---
+
+    #[test]
+    fn clone_into_chosen_search_dir_and_open_a_session() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let config = config::load_config_from_str(&format!(
+            "search_dirs = [{:?}, {:?}]",
+            first.path(),
+            second.path()
+        ))
+        .unwrap();
+        let dest = second.path().join("kiosk");
+        let git = MockGitProvider {
+            repos: vec![repo(&dest.to_string_lossy(), "kiosk")],
+            ..Default::default()
+        };
+        let tmux = MockTmuxProvider::default();
+        let url = "git@github.com:thomasschafer/kiosk.git";
+        let mut args = CloneArgs {
+            url: url.to_string(),
+            dir: Some(second.path().to_path_buf()),
+            open: true,
+            format: OutputFormat::Json,
+        };
+        let clone = |args: &CloneArgs| clone(&config, &git, &tmux, &Stores::disabled(), args);
+
+        let output = clone(&args).unwrap();
+        assert_eq!(output.name, "kiosk");
+        assert_eq!(output.session.as_deref(), Some("kiosk"));
+        assert_eq!(
+            git.clone_calls.lock().unwrap().as_slice(),
+            &[(url.to_string(), dest.clone())]
+        );
+        assert_eq!(tmux.created_sessions.lock().unwrap().len(), 1);
+
+        fs::create_dir(&dest).unwrap();
+        assert_eq!(clone(&args).unwrap_err().code(), CliError::ALREADY_EXISTS);
+
+        args.dir = Some(PathBuf::from("/elsewhere"));
+        assert_eq!(clone(&args).unwrap_err().code(), CliError::USER);
+        assert_eq!(git.clone_calls.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn undo_without_journal_entries_is_not_found() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let journal_dir = tempfile::tempdir().unwrap();
+
+        let err = undo(
+            &config,
+            &git,
+            &UndoArgs {
+                repo: None,
+                branch: None,
+                format: OutputFormat::Table,
+            },
+            &DeleteJournal::at(journal_dir.path().join("journal.toml")),
+        )
+        .unwrap_err();
+
+        assert_eq!(err.code(), CliError::NOT_FOUND);
+    }
+
+    #[test]
+    fn delete_unknown_branch_returns_not_found() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let tmux = MockTmuxProvider::default();
+
+        let error = delete(
+            &config,
+            &git,
+            &tmux,
+            &DeleteArgs {
+                repo: "demo".to_string(),
+                branch: "nonexistent".to_string(),
+                force: false,
+                force_dirty: false,
+                format: OutputFormat::Table,
+            },
+            &Confirm::non_interactive(false),
+            &Stores::disabled(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(error.message().contains("nonexistent"));
+    }
+
+    // --- sessions tests ---
+
+    #[test]
+    fn open_closes_least_recently_active_idle_sessions() {
+        let mut config = test_config();
+        config.session.max_alive = Some(3);
+        let worktree = |branch: &str| Worktree {
+            path: PathBuf::from(format!("/tmp/.kiosk_worktrees/demo--{branch}")),
+            branch: Some(branch.to_string()),
+            is_main: false,
+        };
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                worktree("a"),
+                worktree("b"),
+                worktree("c"),
+                worktree("d"),
+            ],
+            vec![],
+        );
+        let tmux = MockTmuxProvider {
+            sessions_with_activity: vec![
+                ("demo".to_string(), 500),
+                ("demo--a".to_string(), 400),
+                ("demo--b".to_string(), 300),
+                ("demo--c".to_string(), 200),
+                ("demo--d".to_string(), 100),
+                ("unrelated".to_string(), 0),
+            ],
+            clients: HashMap::from([("demo--c".to_string(), vec!["/dev/ttys001".to_string()])]),
+            inside_tmux: true,
+            ..Default::default()
+        };
+        let args = OpenArgs {
+            branch: Some("d".to_string()),
+            no_switch: false,
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+        };
+
+        let output = open_session(&config, &git, &tmux, &Stores::disabled(), &args).unwrap();
+
+        // demo--d is being opened, demo and demo--a are the most recent others, and a client
+        // is attached to demo--c
+        assert_eq!(output.closed_sessions, vec!["demo--b".to_string()]);
+        assert_eq!(
+            tmux.killed_sessions.lock().unwrap().as_slice(),
+            &["demo--b".to_string()]
+        );
+        assert_eq!(
+            tmux.switched_sessions.lock().unwrap().as_slice(),
+            &["demo--d".to_string()]
+        );
+    }
+
+    #[test]
+    fn open_sets_session_env_with_overrides_winning() {
+        let mut config = test_config();
+        config.session.env = BTreeMap::from([
+            ("RUST_LOG".to_string(), "info".to_string()),
+            ("EDITOR".to_string(), "hx".to_string()),
+        ]);
+        config.session.repo_env = BTreeMap::from([(
+            "demo".to_string(),
+            BTreeMap::from([("RUST_LOG".to_string(), "debug".to_string())]),
+        )]);
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+        let args = OpenArgs {
+            env: vec![("EDITOR".to_string(), "vim".to_string())],
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+        };
+
+        open_session(&config, &git, &tmux, &Stores::disabled(), &args).unwrap();
+
+        let envs = tmux.session_envs.lock().unwrap();
+        let env: Vec<(&str, &str)> = envs["demo"]
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            env,
+            [
+                ("EDITOR", "vim"),
+                ("KIOSK_BRANCH", "main"),
+                ("KIOSK_REPO", "demo"),
+                ("KIOSK_SESSION", "demo"),
+                ("KIOSK_WORKTREE", "/tmp/demo"),
+                ("RUST_LOG", "debug"),
+            ]
+        );
+    }
+
+    #[test]
+    fn session_context_names_the_repo_as_discovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkout = dir.path().join("checkout");
+        fs::create_dir_all(checkout.join(GIT_DIR_ENTRY)).unwrap();
+        let link = dir.path().join("demo");
+        std::os::unix::fs::symlink(&checkout, &link).unwrap();
+        let git = MockGitProvider {
+            repos: vec![repo(link.to_str().unwrap(), "demo")],
+            ..Default::default()
+        };
+        let config = test_config();
+
+        // Reached through its real directory, the repo still goes by its discovered name
+        let (_, context) = session_context(&config, &git, None, &checkout, "demo", None);
+        assert_eq!(context.repo, "demo");
+        let (_, context) = session_context(
+            &config,
+            &git,
+            Some("named".to_string()),
+            &checkout,
+            "demo",
+            None,
+        );
+        assert_eq!(context.repo, "named");
+        let outside = MockGitProvider::default();
+        let (_, context) = session_context(&config, &outside, None, &checkout, "demo", None);
+        assert_eq!(context.repo, "checkout");
+    }
+
+    #[test]
+    fn open_names_the_window_after_the_branch_with_set_titles() {
+        let mut config = test_config();
+        config.session.set_titles = true;
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec![],
+        );
+        let tmux = MockTmuxProvider::default();
+        let args = OpenArgs {
+            branch: Some("feat".to_string()),
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+        };
+
+        open_session(&config, &git, &tmux, &Stores::disabled(), &args).unwrap();
+
+        assert_eq!(
+            tmux.renamed_windows.lock().unwrap().as_slice(),
+            &[("demo--feat".to_string(), "feat".to_string())]
+        );
+    }
+
+    #[test]
+    fn open_titles_the_agent_pane_with_set_titles() {
+        let mut config = test_config();
+        config.session.set_titles = true;
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default().with_session_running("demo", "claude");
+
+        open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs::for_repo("demo".to_string(), OutputFormat::Table),
+        )
+        .unwrap();
+
+        assert_eq!(
+            tmux.pane_titles.lock().unwrap().as_slice(),
+            &[("demo".to_string(), "0".to_string(), "claude".to_string())]
+        );
+    }
+
+    #[test]
+    fn open_fills_placeholders_in_run() {
+        let git = MockGitProvider {
+            default_branch: Some("main".to_string()),
+            ..demo_git(vec![main_worktree()], vec!["main".to_string()])
+        };
+        let tmux = MockTmuxProvider::default();
+        let args = OpenArgs {
+            run: Some("cd {worktree} && echo {repo} {branch} {session} {base} {other}".to_string()),
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+        };
+
+        open_session(&test_config(), &git, &tmux, &Stores::disabled(), &args).unwrap();
+
+        assert_eq!(
+            tmux.sent_keys.lock().unwrap().as_slice(),
+            &[(
+                "demo".to_string(),
+                "cd /tmp/demo && echo demo main demo main {other}".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn open_with_agent_runs_the_configured_command() {
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let tmux = MockTmuxProvider::default();
+        let mut config = test_config();
+        config.agents.insert(
+            "claude".to_string(),
+            AgentProfile {
+                command: "claude --add-dir {worktree}".to_string(),
+                log: false,
+            },
+        );
+        let args = OpenArgs {
+            agent: Some("claude".to_string()),
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+        };
+
+        open_session(&config, &git, &tmux, &Stores::disabled(), &args).unwrap();
+
+        assert_eq!(
+            tmux.sent_keys.lock().unwrap().as_slice(),
+            &[("demo".to_string(), "claude --add-dir /tmp/demo".to_string())]
+        );
+        assert!(tmux.piped_sessions.lock().unwrap().is_empty());
+
+        config.agents.get_mut("claude").unwrap().log = true;
+        let logged = with_agent(&config, &args).unwrap();
+        assert_eq!(logged.run.as_deref(), Some("claude --add-dir {worktree}"));
+        assert!(logged.log);
+
+        let unknown = OpenArgs {
+            agent: Some("codex".to_string()),
+            ..args
+        };
+        let error = with_agent(&config, &unknown).unwrap_err();
+        assert_eq!(error.code(), CliError::USER);
+        assert!(error.message().contains("expected one of claude"));
+    }
+
+    #[test]
+    fn open_records_new_sessions_for_resume() {
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let tmux = MockTmuxProvider::default();
+        let dir = tempfile::tempdir().unwrap();
+        let stores = stores_in(&dir);
+        let args = OpenArgs {
+            run: Some("make dev".to_string()),
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+        };
+
+        open_session(&test_config(), &git, &tmux, &stores, &args).unwrap();
+
+        let recorded = stores.sessions.load();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].session, "demo");
+        assert_eq!(recorded[0].repo_path, PathBuf::from("/tmp/demo"));
+        assert_eq!(recorded[0].branch.as_deref(), Some("main"));
+        assert_eq!(recorded[0].run.as_deref(), Some("make dev"));
+    }
+
+    #[test]
+    fn resume_recreates_chosen_lost_sessions_and_lists_the_rest() {
+        let feat = PathBuf::from("/tmp/.kiosk_worktrees/demo--feat");
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: feat.clone(),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat".to_string()],
+        );
+        let tmux = MockTmuxProvider {
+            server_started_at: Some(100),
+            ..MockTmuxProvider::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let journal = SessionJournal::at(dir.path().join("sessions.toml"));
+        let lost =
+            |session: &str, worktree_path: &Path, branch: &str, run: Option<&str>| OpenedSession {
+                session: session.to_string(),
+                repo_path: PathBuf::from("/tmp/demo"),
+                worktree_path: worktree_path.to_path_buf(),
+                branch: Some(branch.to_string()),
+                run: run.map(ToString::to_string),
+                split_command: None,
+                base: None,
+                opened_at_unix_secs: 10,
+            };
+        journal
+            .record(lost("demo--feat", &feat, "feat", Some("make dev")))
+            .unwrap();
+        journal
+            .record(lost("demo", Path::new("/tmp/demo"), "main", None))
+            .unwrap();
+        let resume = |sessions: &[&str]| {
+            resume(
+                &test_config(),
+                &git,
+                &tmux,
+                &Stores {
+                    sessions: journal.clone(),
+                    ..Stores::disabled()
+                },
+                &ResumeArgs {
+                    sessions: sessions.iter().map(ToString::to_string).collect(),
+                    all: false,
+                    format: OutputFormat::Json,
+                },
+            )
+        };
+
+        let unknown = resume(&["gone"]).unwrap_err();
+        assert_eq!(unknown.code(), CliError::NOT_FOUND);
+
+        let output = resume(&["demo--feat"]).unwrap();
+        assert_eq!(
+            output.resumed,
+            [ResumedSession {
+                session: "demo--feat".to_string(),
+                path: feat,
+            }]
+        );
+        assert!(output.failed.is_empty());
+        let left: Vec<&str> = output
+            .lost
+            .iter()
+            .map(|lost| lost.session.as_str())
+            .collect();
+        assert_eq!(left, ["demo"]);
+        assert_eq!(
+            tmux.sent_keys.lock().unwrap().as_slice(),
+            &[("demo--feat".to_string(), "make dev".to_string())]
+        );
+    }
+
+    #[test]
+    fn export_lists_kiosk_worktrees_and_running_main_checkouts() {
+        let git = MockGitProvider {
+            default_branch: Some("main".to_string()),
+            ..demo_git(
+                vec![
+                    main_worktree(),
+                    Worktree {
+                        path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                        branch: Some("feat".to_string()),
+                        is_main: false,
+                    },
+                    Worktree {
+                        path: PathBuf::from("/tmp/elsewhere"),
+                        branch: Some("manual".to_string()),
+                        is_main: false,
+                    },
+                ],
+                vec!["main".to_string(), "feat".to_string()],
+            )
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let journal = SessionJournal::at(dir.path().join("sessions.toml"));
+        journal
+            .record(OpenedSession {
+                split_command: Some("hx {worktree}".to_string()),
+                base: Some("develop".to_string()),
+                ..OpenedSession::new(
+                    "demo--feat".to_string(),
+                    PathBuf::from("/tmp/demo"),
+                    PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                    Some("feat".to_string()),
+                    Some("make dev".to_string()),
+                )
+            })
+            .unwrap();
+        let exported_branches = |tmux: &MockTmuxProvider| -> Vec<Option<String>> {
+            export(&test_config(), &git, tmux, &journal)
+                .worktrees
+                .into_iter()
+                .map(|worktree| worktree.branch)
+                .collect()
+        };
+
+        assert_eq!(
+            exported_branches(&MockTmuxProvider::default()),
+            [Some("feat".to_string())]
+        );
+        assert_eq!(
+            exported_branches(&MockTmuxProvider::default().with_session("demo")),
+            [None, Some("feat".to_string())]
+        );
+        // The main checkout's session wasn't set up by kiosk, so it has the default branch
+        // and no layout, while `feat` has what kiosk recorded for it
+        let exported = export(
+            &test_config(),
+            &git,
+            &MockTmuxProvider::default().with_session("demo"),
+            &journal,
+        );
+        assert_eq!(exported.worktrees[0].base.as_deref(), Some("main"));
+        assert_eq!(exported.worktrees[0].layout, None);
+        assert_eq!(exported.worktrees[1].base.as_deref(), Some("develop"));
+        assert_eq!(
+            exported.worktrees[1].layout,
+            Some(SessionLayout {
+                split_command: Some("hx {worktree}".to_string()),
+                run: Some("make dev".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn open_records_the_base_of_a_new_branch_and_the_session_layout() {
+        let mut config = test_config();
+        config.session.split_command = Some("hx {worktree}".to_string());
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let dir = tempfile::tempdir().unwrap();
+        let stores = stores_in(&dir);
+
+        open_session(
+            &config,
+            &git,
+            &MockTmuxProvider::default(),
+            &stores,
+            &OpenArgs {
+                new_branch: Some("feat".to_string()),
+                base: Some("main".to_string()),
+                ..shorthand_open_args("demo")
+            },
+        )
+        .unwrap();
+
+        let recorded = stores.sessions.load();
+        assert_eq!(recorded[0].base.as_deref(), Some("main"));
+        assert_eq!(recorded[0].split_command.as_deref(), Some("hx {worktree}"));
+    }
+
+    #[test]
+    fn import_opens_exported_worktrees_and_reports_failures() {
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat".to_string()],
+        );
+        let tmux = MockTmuxProvider::default();
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        let export = |repo: &str, branch: Option<&str>| ExportedWorktree {
+            repo: repo.to_string(),
+            branch: branch.map(ToString::to_string),
+            base: Some("main".to_string()),
+            layout: None,
+        };
+        let state = ExportOutput {
+            worktrees: vec![
+                ExportedWorktree {
+                    layout: Some(SessionLayout {
+                        split_command: Some("hx".to_string()),
+                        run: Some("make dev".to_string()),
+                    }),
+                    ..export("demo", Some("feat"))
+                },
+                export("demo", Some("gone")),
+                export("missing", None),
+            ],
+        };
+        fs::write(
+            &file,
+            output::render(&OutputFormat::Json, &state, |_| String::new()).unwrap(),
+        )
+        .unwrap();
+
+        let stores = stores_in(&dir);
+        let output = import(
+            &test_config(),
+            &git,
+            &tmux,
+            &stores,
+            &ImportArgs {
+                file,
+                stdin: None,
+                format: OutputFormat::Json,
+            },
+        )
+        .unwrap();
+
+        let opened: Vec<Option<&str>> = output
+            .opened
+            .iter()
+            .map(|opened| opened.branch.as_deref())
+            .collect();
+        assert_eq!(opened, [Some("feat"), Some("gone")]);
+        assert_eq!(output.failed.len(), 1);
+        assert_eq!(output.failed[0].repo, "missing");
+        // `feat` was set up with its exported layout
+        assert_eq!(
+            tmux.sent_keys.lock().unwrap()[0],
+            ("demo--feat".to_string(), "make dev".to_string())
+        );
+        assert_eq!(
+            stores.sessions.load()[0].split_command.as_deref(),
+            Some("hx")
+        );
+        // `gone` existed nowhere, so it was branched off the exported base
+        let args = branch_open_args(
+            &git,
+            &git.repos[0],
+            state.worktrees[1].branch.as_deref(),
+            state.worktrees[1].base.clone(),
+            &OutputFormat::Json,
+        );
+        assert_eq!(args.new_branch.as_deref(), Some("gone"));
+        assert_eq!(args.base.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn workspace_open_opens_every_member_and_switches_to_the_first() {
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat".to_string()],
+        );
+        let tmux = MockTmuxProvider::default();
+        let dir = tempfile::tempdir().unwrap();
+        let stores = stores_in(&dir);
+        stores.workspaces.add("trio", "missing/main").unwrap();
+        let mut config = test_config();
+        config.workspaces.insert(
+            "trio".to_string(),
+            vec!["demo/feat".to_string(), "demo".to_string()],
+        );
+        let open = |name: &str, no_switch| {
+            workspace_open(
+                &config,
+                &git,
+                &tmux,
+                &stores,
+                &WorkspaceOpenArgs {
+                    name: name.to_string(),
+                    no_switch,
+                    format: OutputFormat::Json,
+                },
+            )
+        };
+
+        let output = open("trio", false).unwrap();
+        let opened: Vec<&str> = output
+            .opened
+            .iter()
+            .map(|opened| opened.session.as_str())
+            .collect();
+        assert_eq!(opened, ["demo--feat", "demo"]);
+        assert_eq!(output.failed.len(), 1);
+        assert_eq!(output.failed[0].member, "missing/main");
+        assert_eq!(output.switched_to.as_deref(), Some("demo--feat"));
+        assert_eq!(
+            tmux.switched_sessions.lock().unwrap().as_slice(),
+            ["demo--feat"]
+        );
+
+        assert_eq!(open("trio", true).unwrap().switched_to, None);
+        assert!(open("nope", true).is_err());
+        let listed = workspace_list(&config, &stores.workspaces);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].members, ["demo/feat", "demo", "missing/main"]);
+    }
+
+    #[test]
+    fn open_link_opens_the_branch_in_linked_repos_and_saves_a_workspace() {
+        let git = MockGitProvider {
+            repos: vec![repo("/tmp/demo", "demo"), repo("/tmp/api", "api")],
+            worktrees: vec![main_worktree()],
+            branches: vec!["main".to_string(), "feat".to_string()],
+            ..Default::default()
+        };
+        let tmux = MockTmuxProvider::default();
+        let dir = tempfile::tempdir().unwrap();
+        let stores = stores_in(&dir);
+        let mut config = test_config();
+        config.linked_repos = vec!["demo".to_string(), "api".to_string(), "web".to_string()];
+        let mut args = OpenArgs::for_repo("demo".to_string(), OutputFormat::Json);
+        args.branch = Some("feat".to_string());
+        args.link = true;
+
+        let output = open_with_links(&config, &git, &tmux, &stores, &args).unwrap();
+        let linked: Vec<&str> = output.linked.iter().map(|l| l.member.as_str()).collect();
+        assert_eq!(linked, ["api/feat"]);
+        assert_eq!(output.link_failed.len(), 1);
+        assert_eq!(output.link_failed[0].member, "web/feat");
+        assert!(tmux.switched_sessions.lock().unwrap().is_empty());
+        let saved = stores.workspaces.load();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].name, "feat");
+        assert_eq!(saved[0].members, ["demo/feat", "api/feat"]);
+    }
+
+    #[test]
+    fn open_detach_others_keeps_only_the_opened_session() {
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec![],
+        );
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo".to_string(), "demo--feat".to_string()]),
+            ..Default::default()
+        };
+        let args = OpenArgs {
+            branch: Some("feat".to_string()),
+            detach_others: true,
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+        };
+
+        let mut config = test_config();
+        config.session.max_alive = Some(5);
+        let output = open_session(&config, &git, &tmux, &Stores::disabled(), &args).unwrap();
+        assert_eq!(output.closed_sessions, vec!["demo".to_string()]);
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                detach_others: false,
+                ..args
+            },
+        )
+        .unwrap();
+        assert!(output.closed_sessions.is_empty());
+    }
+
+    #[test]
+    fn open_in_place_stashes_and_restores_changes_per_branch() {
+        let mut git = demo_git(
+            vec![main_worktree()],
+            vec!["main".to_string(), "feat".to_string()],
+        );
+        git.stashes = HashMap::from([(
+            "kiosk: in-place WIP on feat".to_string(),
+            "feat-wip".to_string(),
+        )]);
+        *git.stash_result.lock().unwrap() = Some(Ok(Some("main-wip".to_string())));
+        let tmux = MockTmuxProvider::default();
+        let mut args = OpenArgs {
+            branch: Some("feat".to_string()),
+            in_place: true,
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Json)
+        };
+        let open =
+            |args: &OpenArgs| open_session(&test_config(), &git, &tmux, &Stores::disabled(), args);
+
+        let output = open(&args).unwrap();
+        let demo = PathBuf::from("/tmp/demo");
+        assert_eq!(output.path, demo);
+        assert_eq!(output.session, "demo");
+        assert_eq!(output.branch.as_deref(), Some("feat"));
+        assert_eq!(
+            git.stash_calls.lock().unwrap().as_slice(),
+            std::slice::from_ref(&demo)
+        );
+        assert_eq!(
+            git.checkout_calls.lock().unwrap().as_slice(),
+            [(demo.clone(), "feat".to_string())]
+        );
+        assert_eq!(
+            git.pop_stash_calls.lock().unwrap().as_slice(),
+            [(demo.clone(), "feat-wip".to_string())]
+        );
+
+        // A failed checkout puts the stashed changes straight back
+        git.pop_stash_calls.lock().unwrap().clear();
+        *git.stash_result.lock().unwrap() = Some(Ok(Some("main-wip".to_string())));
+        *git.checkout_result.lock().unwrap() = Some(Err(anyhow!("would be overwritten")));
+        assert!(open(&args).is_err());
+        assert_eq!(
+            git.pop_stash_calls.lock().unwrap().as_slice(),
+            [(demo, "main-wip".to_string())]
+        );
+
+        args.branch = Some("missing".to_string());
+        assert_eq!(open(&args).unwrap_err().code(), CliError::NOT_FOUND);
+    }
+
+    #[test]
+    fn open_progress_renders_one_json_record_per_git_update() {
+        let mut git = demo_git(
+            vec![main_worktree()],
+            vec!["main".to_string(), "feat".to_string()],
+        );
+        git.progress = vec![
+            GitProgress::parse("Preparing worktree (checking out 'feat')").unwrap(),
+            GitProgress::parse("Updating files:  50% (1/2)").unwrap(),
+        ];
+        let args = OpenArgs {
+            branch: Some("feat".to_string()),
+            progress: true,
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Json)
+        };
+
+        let lines = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = std::rc::Rc::clone(&lines);
+        with_progress(
+            move |progress| {
+                sink.borrow_mut()
+                    .push(render_open_progress(progress).unwrap());
+            },
+            || {
+                open_session(
+                    &test_config(),
+                    &git,
+                    &MockTmuxProvider::default(),
+                    &Stores::disabled(),
+                    &args,
+                )
+            },
+        )
+        .unwrap();
+
+        let records: Vec<serde_json::Value> = lines
+            .borrow()
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            records,
+            [
+                serde_json::json!({
+                    "schema_version": SCHEMA_VERSION,
+                    "data": {"progress": {"phase": "Preparing worktree (checking out 'feat')"}},
+                }),
+                serde_json::json!({
+                    "schema_version": SCHEMA_VERSION,
+                    "data": {"progress": {
+                        "phase": "Updating files",
+                        "percent": 50,
+                        "current": 1,
+                        "total": 2,
+                    }},
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn sessions_only_returns_matching_worktree_sessions() {
+        let config = test_config();
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec![],
+        );
+
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo".to_string(), "unrelated-session".to_string()]),
+            ..Default::default()
+        };
+
+        let result = sessions(&config, &git, &tmux);
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn sessions_reports_attached_status() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo".to_string()]),
+            clients: HashMap::from([("demo".to_string(), vec!["/dev/pts/0".to_string()])]),
+            ..Default::default()
+        };
+
+        let result = sessions(&config, &git, &tmux);
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn top_sums_the_processes_under_each_session() {
+        let config = test_config();
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec![],
+        );
+        let mut tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo".to_string(), "demo--feat".to_string()]),
+            ..Default::default()
+        }
+        .with_panes("demo", &[("zsh", ""), ("nvim", "")])
+        .with_panes("demo--feat", &[("node", "")]);
+        tmux.panes.get_mut("demo--feat").unwrap()[0].pid = 2000;
+        let processes = ProcessTable::parse(
+            "1000 1 0.5 4000 zsh
+             1001 1 1.5 60000 nvim
+             1002 1000 3.0 20000 cargo
+             2000 1 90.0 500000 node
+             3000 1 99.0 900000 unrelated",
+        );
+        let top = |sort| {
+            top(
+                &config,
+                &git,
+                &tmux,
+                &processes,
+                &TopArgs {
+                    repo: None,
+                    sort,
+                    format: OutputFormat::Json,
+                },
+            )
+            .unwrap()
+        };
+
+        let entries = top(TopSort::Cpu);
+        assert_eq!(
+            entries[0],
+            TopEntry {
+                session: "demo--feat".to_string(),
+                repo: "demo".to_string(),
+                branch: Some("feat".to_string()),
+                path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+                cpu_percent: 90.0,
+                memory_bytes: 500_000 * 1024,
+                processes: 1,
+                busiest_command: Some("node".to_string()),
+            }
+        );
+        assert_eq!(entries[1].session, "demo");
+        assert!((entries[1].cpu_percent - 5.0).abs() < 1e-9);
+        assert_eq!(entries[1].memory_bytes, 84_000 * 1024);
+        assert_eq!(entries[1].processes, 3);
+        assert_eq!(entries[1].busiest_command.as_deref(), Some("cargo"));
+
+        let sessions = |entries: Vec<TopEntry>| {
+            entries
+                .into_iter()
+                .map(|entry| entry.session)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sessions(top(TopSort::Memory)), ["demo--feat", "demo"]);
+        assert_eq!(sessions(top(TopSort::Session)), ["demo", "demo--feat"]);
+        assert_eq!(
+            format_top_table(&top(TopSort::Session)),
+            "session     cpu    mem       procs  busiest\n\
+             demo        5.0%   82.0 MB   3      cargo\n\
+             demo--feat  90.0%  488.3 MB  1      node\n"
+        );
+    }
+
+    #[test]
+    fn ps_shows_the_process_tree_under_each_pane() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let mut tmux = MockTmuxProvider::default()
+            .with_session("demo")
+            .with_panes("demo", &[("zsh", ""), ("claude", ""), ("zsh", "")]);
+        tmux.panes.get_mut("demo").unwrap()[2].pid = 0;
+        let processes = ProcessTable::parse(
+            "1000 1 0.0 4000 -zsh
+             1001 1 12.5 90000 claude --resume
+             1002 1001 80.0 20000 cargo test --workspace
+             1003 1002 1.0 2000 rustc --edition 2024",
+        );
+
+        let output = ps(
+            &config,
+            &git,
+            &tmux,
+            &processes,
+            &PsArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                format: OutputFormat::Json,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(output.session, "demo");
+        let claude = output.panes[1].process.as_ref().unwrap();
+        assert_eq!(claude.command, "claude");
+        assert_eq!(claude.children[0].args, "cargo test --workspace");
+        assert_eq!(claude.children[0].children[0].pid, 1003);
+        assert_eq!(output.panes[2].process, None);
+        assert_eq!(
+            format_process_trees(&output),
+            "session: demo\n\
+             \x20 pane 0:\n\
+             \x20   1000 0.0% -zsh\n\
+             \x20 pane 1:\n\
+             \x20   1001 12.5% claude --resume\n\
+             \x20     1002 80.0% cargo test --workspace\n\
+             \x20       1003 1.0% rustc --edition 2024\n\
+             \x20 pane 2:\n\
+             \x20   (no processes found)\n"
+        );
+    }
+
+    #[test]
+    fn interrupt_sends_the_agents_cancel_key() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default()
+            .with_session("demo")
+            .with_panes("demo", &[("zsh", ""), ("claude", "")]);
+        let interrupt = |pane: Option<&str>| {
+            interrupt(
+                &config,
+                &git,
+                &tmux,
+                &InterruptArgs {
+                    repo: "demo".to_string(),
+                    branch: None,
+                    pane: pane.map(|pane| pane.parse().unwrap()),
+                    format: OutputFormat::Json,
+                },
+            )
+            .unwrap()
+        };
+
+        let output = interrupt(None);
+        assert_eq!(
+            output,
+            InterruptOutput {
+                session: "demo".to_string(),
+                pane: 1,
+                command: "claude".to_string(),
+                keys: vec!["Escape".to_string()],
+            }
+        );
+        assert_eq!(interrupt(Some("0")).keys, ["C-c"]);
+        assert_eq!(
+            *tmux.sent_keys.lock().unwrap(),
+            [
+                ("demo:1".to_string(), "Escape".to_string()),
+                ("demo:0".to_string(), "C-c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn kill_pane_closes_the_agents_pane() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let kill = |tmux: &MockTmuxProvider, pane: Option<&str>| {
+            kill_pane(
+                &config,
+                &git,
+                tmux,
+                &KillPaneArgs {
+                    repo: "demo".to_string(),
+                    branch: None,
+                    pane: pane.map(|pane| pane.parse().unwrap()),
+                    format: OutputFormat::Json,
+                },
+            )
+        };
+
+        let tmux = MockTmuxProvider::default()
+            .with_session("demo")
+            .with_panes("demo", &[("zsh", ""), ("codex", "")]);
+        assert_eq!(kill(&tmux, None).unwrap().command, "codex");
+        assert_eq!(kill(&tmux, Some("0")).unwrap().command, "zsh");
+        assert_eq!(*tmux.killed_panes.lock().unwrap(), ["demo:1", "demo:0"]);
+
+        let idle = MockTmuxProvider::default().with_session("demo");
+        let error = kill(&idle, None).unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(idle.killed_panes.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn approve_and_deny_answer_a_waiting_agent() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let answer = |tmux: &MockTmuxProvider, reply| {
+            super::answer(
+                &config,
+                &git,
+                tmux,
+                &AnswerArgs {
+                    repo: "demo".to_string(),
+                    branch: None,
+                    format: OutputFormat::Json,
+                },
+                reply,
+            )
+        };
+
+        let waiting = MockTmuxProvider::default()
+            .with_session("demo")
+            .with_panes("demo", &[("zsh", ""), ("codex", "")])
+            .with_capture_output("Would you like to run the following command?\n$ ls");
+        assert_eq!(
+            answer(&waiting, PromptAnswer::Approve).unwrap(),
+            AnswerOutput {
+                session: "demo".to_string(),
+                pane: 1,
+                agent: "codex".to_string(),
+                approved: true,
+                keys: vec!["y".to_string()],
+            }
+        );
+        assert!(!answer(&waiting, PromptAnswer::Deny).unwrap().approved);
+        assert_eq!(
+            *waiting.sent_keys.lock().unwrap(),
+            [
+                ("demo:1".to_string(), "y".to_string()),
+                ("demo:1".to_string(), "n".to_string()),
+            ]
+        );
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let working = MockTmuxProvider {
+            sessions_with_activity: vec![("demo".to_string(), now)],
+            ..Default::default()
+        }
+        .with_session_running("demo", "claude");
+        let error = answer(&working, PromptAnswer::Approve).unwrap_err();
+        assert!(
+            error
+                .message()
+                .contains("isn't showing a permission prompt"),
+            "{error:?}"
+        );
+        assert!(working.sent_keys.lock().unwrap().is_empty());
+
+        // A prompt is answered even while something else on screen keeps the session active
+        let prompting = MockTmuxProvider {
+            sessions_with_activity: vec![("demo".to_string(), now)],
+            ..Default::default()
+        }
+        .with_session_running("demo", "claude")
+        .with_capture_output("Do you want to proceed?\n> 1. Yes");
+        assert!(answer(&prompting, PromptAnswer::Approve).unwrap().approved);
+
+        // Waiting, but idle at its input rather than asking anything
+        let idle = MockTmuxProvider::default()
+            .with_session_running("demo", "claude")
+            .with_capture_output("> \n  ? for shortcuts");
+        let error = answer(&idle, PromptAnswer::Approve).unwrap_err();
+        assert_eq!(error.code(), CliError::USER);
+        assert!(
+            error
+                .message()
+                .contains("isn't showing a permission prompt")
+        );
+        assert!(idle.sent_keys.lock().unwrap().is_empty());
+
+        let unknown = MockTmuxProvider::default().with_session_running("demo", "goose");
+        let error = answer(&unknown, PromptAnswer::Approve).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "kiosk doesn't know how to answer goose's prompts"
+        );
+    }
+
+    #[test]
+    fn transcript_reads_the_worktrees_latest_conversation() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let home = tempfile::tempdir().unwrap();
+        let dirs = TranscriptDirs {
+            claude: home.path().join(".claude"),
+            codex: home.path().join(".codex"),
+        };
+        let transcript = |agent| {
+            transcript(
+                &config,
+                &git,
+                &dirs,
+                &TranscriptArgs {
+                    repo: "demo".to_string(),
+                    branch: None,
+                    agent,
+                    format: OutputFormat::Json,
+                },
+            )
+        };
+
+        let project = dirs.claude.join("projects").join("-tmp-demo");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(
+            project.join("session.jsonl"),
+            r#"{"type":"user","message":{"content":"Fix the build"}}
+{"type":"assistant","message":{"content":[{"type":"text","text":"Fixed."}]}}"#,
+        )
+        .unwrap();
+
+        let output = transcript(None).unwrap();
+        assert_eq!(output.agent, "claude");
+        assert_eq!(output.file, project.join("session.jsonl"));
+        assert_eq!(
+            format_transcript(&output),
+            "# claude transcript for /tmp/demo\n\n\
+             ## User\n\nFix the build\n\n\
+             ## Assistant\n\nFixed.\n"
+        );
+
+        let error = transcript(Some(TranscriptSource::Codex)).unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert_eq!(error.message(), "no codex transcript found for /tmp/demo");
+    }
+
+    #[test]
+    fn agent_samples_tell_working_waiting_and_idle_sessions_apart() {
+        let feat = Worktree {
+            path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat"),
+            branch: Some("feat".to_string()),
+            is_main: false,
+        };
+        let fix = Worktree {
+            path: PathBuf::from("/tmp/.kiosk_worktrees/demo--fix"),
+            branch: Some("fix".to_string()),
+            is_main: false,
+        };
+        let git = demo_git(vec![main_worktree(), feat, fix], vec![]);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let tmux = MockTmuxProvider {
+            sessions_with_activity: vec![
+                ("demo".to_string(), now),
+                ("demo--feat".to_string(), now - 600),
+                ("demo--fix".to_string(), now),
+            ],
+            ..MockTmuxProvider::default()
+        }
+        .with_panes("demo", &[("claude", "")])
+        .with_panes("demo--feat", &[("zsh", ""), ("codex", "")])
+        .with_panes("demo--fix", &[("zsh", "")])
+        .with_session("demo")
+        .with_session("demo--feat")
+        .with_session("demo--fix");
+
+        let samples: Vec<(String, Option<String>, AgentActivity)> =
+            agent_samples(&test_config(), &git, &tmux)
+                .into_iter()
+                .map(|sample| (sample.session, sample.agent, sample.activity))
+                .collect();
+        assert_eq!(
+            samples,
+            [
+                (
+                    "demo".to_string(),
+                    Some("claude".to_string()),
+                    AgentActivity::Working
+                ),
+                (
+                    "demo--feat".to_string(),
+                    Some("codex".to_string()),
+                    AgentActivity::Waiting
+                ),
+                ("demo--fix".to_string(), None, AgentActivity::Idle),
+            ]
+        );
+
+        // A permission prompt on screen means waiting, however recent the output
+        *tmux.capture_output.lock().unwrap() = "Do you want to proceed?".to_string();
+        let demo = agent_samples(&test_config(), &git, &tmux).remove(0);
+        assert_eq!(demo.activity, AgentActivity::Waiting);
+    }
+
+    #[test]
+    fn agents_report_puts_the_longest_waiting_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AgentHistoryStore::at(dir.path().join("agent_history.toml"));
+        let now = 1_800_000_000;
+        let sample = |session: &str, branch: &str, activity| AgentSample {
+            session: session.to_string(),
+            repo: "demo".to_string(),
+            branch: Some(branch.to_string()),
+            agent: Some("claude".to_string()),
+            activity,
+        };
+        store
+            .record(
+                &[
+                    sample("demo--feat", "feat", AgentActivity::Working),
+                    sample("demo--fix", "fix", AgentActivity::Working),
+                ],
+                now - 3600,
+                60,
+            )
+            .unwrap();
+        store
+            .record(
+                &[
+                    sample("demo--feat", "feat", AgentActivity::Working),
+                    sample("demo--fix", "fix", AgentActivity::Waiting),
+                ],
+                now - 3540,
+                60,
+            )
+            .unwrap();
+        store
+            .record(
+                &[
+                    sample("demo--feat", "feat", AgentActivity::Waiting),
+                    sample("demo--fix", "fix", AgentActivity::Waiting),
+                ],
+                now - 3480,
+                60,
+            )
+            .unwrap();
+
+        let args = AgentsReportArgs {
+            since: 86_400,
+            format: OutputFormat::Table,
+        };
+        let output = agents_report(&store, &args, now);
+        assert_eq!(output.since, now - 86_400);
+        assert_eq!(
+            format_agents_report(&output),
+            "repo  branch  working  waiting  idle  waiting share\n\
+             demo  fix     0s       2m 0s    0s    100%\n\
+             demo  feat    1m 0s    1m 0s    0s    50%\n"
+        );
+
+        let recent = AgentsReportArgs { since: 60, ..args };
+        assert_eq!(
+            format_agents_report(&agents_report(&store, &recent, now)),
+            "No agent activity recorded in that time. Run `kiosk agents record` to record it\n"
+        );
+    }
+
+    // --- status tests ---
+
+    #[test]
+    fn status_strips_ansi() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default()
+            .with_session("demo")
+            .with_capture_output("\u{1b}[32mPASS\u{1b}[0m a\nFAIL b");
+
+        let output = status(
+            &config,
+            &git,
+            &tmux,
+            &StatusArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                format: OutputFormat::Json,
+                lines: 10,
+                pane: PaneTarget::Index(0),
+                strip_ansi: true,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(output.output, "PASS a\nFAIL b");
+    }
+
+    #[test]
+    fn status_all_reports_every_running_session() {
+        let config = test_config();
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/demo-feat"),
+                    branch: Some("feat".to_string()),
+                    is_main: false,
+                },
+                Worktree {
+                    path: PathBuf::from("/tmp/demo-idle"),
+                    branch: Some("idle".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec![],
+        );
+        let feat_session =
+            repo("/tmp/demo", "demo").tmux_session_name(&PathBuf::from("/tmp/demo-feat"));
+        let tmux = MockTmuxProvider::default()
+            .with_session("demo")
+            .with_session_running(&feat_session, "claude")
+            .with_client("demo", "/dev/pts/1")
+            .with_capture_output("one\ntwo");
+
+        let output = status_all(
+            &config,
+            &git,
+            &tmux,
+            &StatusAllArgs {
+                lines: 10,
+                pane: PaneTarget::Index(0),
+                strip_ansi: false,
+                format: OutputFormat::Json,
+            },
+        );
+
+        let sessions: Vec<&str> = output.iter().map(|row| row.session.as_str()).collect();
+        assert_eq!(sessions, vec!["demo", feat_session.as_str()]);
+        assert!(output[0].attached);
+        assert_eq!(output[0].agent, None);
+        assert_eq!(output[1].branch.as_deref(), Some("feat"));
+        assert!(!output[1].attached);
+        assert_eq!(output[1].agent.as_deref(), Some("claude"));
+        assert_eq!(output[1].output, "one\ntwo");
+    }
+
+    #[test]
+    fn status_all_leaves_agent_pane_titles_alone() {
+        let mut config = test_config();
+        config.session.set_titles = true;
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default().with_session_running("demo", "codex");
+
+        let output = status_all(
+            &config,
+            &git,
+            &tmux,
+            &StatusAllArgs {
+                lines: 10,
+                pane: PaneTarget::Index(0),
+                strip_ansi: false,
+                format: OutputFormat::Json,
+            },
+        );
+
+        assert_eq!(output[0].agent.as_deref(), Some("codex"));
+        assert!(tmux.pane_titles.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn statusline_summarises_the_current_session() {
+        let git = MockGitProvider {
+            worktree_changes: HashMap::from([(
+                PathBuf::from("/tmp/demo"),
+                WorktreeChanges {
+                    files_changed: 2,
+                    ..WorktreeChanges::default()
+                },
+            )]),
+            ..MockGitProvider::default()
+        };
+        let tmux = MockTmuxProvider {
+            current_session: Some("demo".to_string()),
+            session_paths: HashMap::from([("demo".to_string(), PathBuf::from("/tmp/demo"))]),
+            ..MockTmuxProvider::default()
+        }
+        .with_session_running("demo", "claude");
+        let args = StatuslineArgs {
+            session: None,
+            format: OutputFormat::Table,
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let cache = |name: &str| StatuslineCache::at(dir.path().join(name));
+
+        let output = statusline(&test_config(), &git, &tmux, &cache("a.toml"), &args).unwrap();
+
+        assert_eq!(output.repo, "demo");
+        assert!(output.dirty);
+        assert_eq!(output.agent.as_deref(), Some("claude"));
+        // The mock's session activity is long past
+        assert_eq!(output.agent_state, Some(AgentState::Waiting));
+        assert_eq!(
+            format_statusline(&output, Icons::Unicode.glyphs()),
+            "demo* ○ claude"
+        );
+
+        let mut config = test_config();
+        config.agent.skip_repos = vec!["demo".to_string()];
+        let output = statusline(&config, &git, &tmux, &cache("b.toml"), &args).unwrap();
+        assert_eq!(output.agent, None);
+        assert_eq!(output.agent_state, None);
+    }
+
+    #[test]
+    fn statusline_reuses_its_summary_until_the_session_has_activity() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_path = dir.path().join("checkout");
+        fs::create_dir_all(repo_path.join(GIT_DIR_ENTRY)).unwrap();
+        let repo_path = fs::canonicalize(&repo_path).unwrap();
+        let dirty = MockGitProvider {
+            repos: vec![repo(repo_path.to_str().unwrap(), "app (work)")],
+            worktree_changes: HashMap::from([(
+                repo_path.clone(),
+                WorktreeChanges {
+                    files_changed: 1,
+                    ..WorktreeChanges::default()
+                },
+            )]),
+            ..MockGitProvider::default()
+        };
+        let clean = MockGitProvider {
+            repos: dirty.repos.clone(),
+            ..MockGitProvider::default()
+        };
+        let tmux = |activity| MockTmuxProvider {
+            sessions_with_activity: vec![("app".to_string(), activity)],
+            session_paths: HashMap::from([("app".to_string(), repo_path.clone())]),
+            ..MockTmuxProvider::default()
+        };
+        let cache = StatuslineCache::at(dir.path().join("statusline.toml"));
+        let args = StatuslineArgs {
+            session: Some("app".to_string()),
+            format: OutputFormat::Table,
+        };
+
+        let output = statusline(&test_config(), &dirty, &tmux(0), &cache, &args).unwrap();
+        // Named as discovered, not after the checkout's directory
+        assert_eq!(output.repo, "app (work)");
+        assert!(output.dirty);
+
+        let quiet = statusline(&test_config(), &clean, &tmux(0), &cache, &args).unwrap();
+        assert!(quiet.dirty);
+
+        let active = tmux(now_unix_secs() + 1);
+        let output = statusline(&test_config(), &clean, &active, &cache, &args).unwrap();
+        assert!(!output.dirty);
+        assert_eq!(output.repo, "app (work)");
+    }
+
+    #[test]
+    fn statusline_needs_a_session_outside_tmux() {
+        let args = StatuslineArgs {
+            session: None,
+            format: OutputFormat::Table,
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let cache = StatuslineCache::at(dir.path().join("statusline.toml"));
+        let error = statusline(
+            &test_config(),
+            &MockGitProvider::default(),
+            &MockTmuxProvider::default(),
+            &cache,
+            &args,
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), CliError::USER);
+
+        let args = StatuslineArgs {
+            session: Some("missing".to_string()),
+            ..args
+        };
+        let error = statusline(
+            &test_config(),
+            &MockGitProvider::default(),
+            &MockTmuxProvider::default(),
+            &cache,
+            &args,
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+    }
+
+    #[test]
+    fn waybar_lists_agents_waiting_for_input() {
+        let tmux = MockTmuxProvider {
+            sessions_with_activity: vec![
+                ("demo".to_string(), 0),
+                ("demo--feat".to_string(), 0),
+                ("busy".to_string(), u64::MAX),
+            ],
+            session_paths: HashMap::from([(
+                "demo--feat".to_string(),
+                PathBuf::from("/tmp/demo--feat"),
+            )]),
+            ..MockTmuxProvider::default()
+        }
+        .with_session_running("demo", "zsh")
+        .with_session_running("demo--feat", "claude")
+        .with_session_running("busy", "codex");
+
+        let config = Config {
+            icons: Icons::Unicode,
+            ..test_config()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let cache = StatuslineCache::at(dir.path().join("statusline.toml"));
+        let output = waybar(&config, &MockGitProvider::default(), &tmux, &cache);
+
+        assert_eq!(
+            output,
+            WaybarOutput {
+                text: "○ 1".to_string(),
+                tooltip: "Waiting for input:\ndemo--feat: claude".to_string(),
+                class: "waiting",
+            }
+        );
+        assert_eq!(
+            waybar(
+                &test_config(),
+                &MockGitProvider::default(),
+                &MockTmuxProvider::default(),
+                &cache
+            )
+            .class,
+            "none"
+        );
+    }
+
+    #[test]
+    fn format_statusline_snapshot() {
+        let output = StatuslineOutput {
+            session: "kiosk--feat".to_string(),
+            repo: "kiosk".to_string(),
+            branch: Some("feat".to_string()),
+            dirty: false,
+            agent: Some("codex".to_string()),
+            agent_state: Some(AgentState::Working),
+        };
+        assert_eq!(
+            format_statusline(&output, Icons::Unicode.glyphs()),
+            "kiosk feat ● codex"
+        );
+        assert_eq!(
+            format_statusline(&output, Icons::Ascii.glyphs()),
+            "kiosk feat ~ codex"
+        );
+    }
+
+    #[test]
+    fn format_status_blocks_snapshot() {
+        let row = |session: &str, attached, agent: Option<&str>| SessionStatusOutput {
+            session: session.to_string(),
+            repo: "repo".to_string(),
+            branch: None,
+            path: PathBuf::from("/tmp/repo"),
+            attached,
+            clients: usize::from(attached),
+            agent: agent.map(ToString::to_string),
+            output: "a\nb".to_string(),
+        };
+        let rendered = format_status_blocks(&[
+            row("repo", true, None),
+            row("repo--feat", false, Some("claude")),
+        ]);
+        assert_eq!(
+            rendered,
+            "repo (attached)\n  a\n  b\n\nrepo--feat (detached, claude)\n  a\n  b\n"
+        );
+    }
+
+    #[test]
+    fn status_returns_error_when_no_session_and_no_log() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+
+        let result = status(
+            &config,
+            &git,
+            &tmux,
+            &StatusArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                format: OutputFormat::Table,
+                lines: 10,
+                pane: PaneTarget::Index(0),
+                strip_ansi: false,
+            },
+        );
+
+        let error = result.unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(error.message().contains("does not exist"));
+    }
+
+    #[test]
+    fn status_returns_error_for_nonexistent_branch_worktree() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+
+        let error = status(
+            &config,
+            &git,
+            &tmux,
+            &StatusArgs {
+                repo: "demo".to_string(),
+                branch: Some("nonexistent".to_string()),
+                format: OutputFormat::Table,
+                lines: 10,
+                pane: PaneTarget::Index(0),
+                strip_ansi: false,
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(error.message().contains("no worktree"));
+    }
+
+    // --- log_path_for_session validation tests ---
+
+    #[test]
+    fn log_path_rejects_empty_session() {
+        assert!(log_path_for_session("").is_err());
+    }
+
+    #[test]
+    fn log_path_rejects_dot_prefix() {
+        assert!(log_path_for_session(".hidden").is_err());
+    }
+
+    #[test]
+    fn log_path_rejects_path_traversal() {
+        assert!(log_path_for_session("..").is_err());
+        assert!(log_path_for_session("foo/..").is_err());
+        assert!(log_path_for_session("foo/../bar").is_err());
+    }
+
+    #[test]
+    fn log_path_rejects_slashes() {
+        assert!(log_path_for_session("foo/bar").is_err());
+        assert!(log_path_for_session("foo\\bar").is_err());
+    }
+
+    #[test]
+    fn log_path_accepts_valid_session_names() {
+        assert!(log_path_for_session("demo").is_ok());
+        assert!(log_path_for_session("repo--feat-test").is_ok());
+        assert!(log_path_for_session("my_repo").is_ok());
+    }
+
+    // --- open output field tests ---
+
+    #[test]
+    fn open_output_includes_repo_and_branch() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(Vec::new()),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs::for_repo("demo".to_string(), OutputFormat::Table),
+        )
+        .unwrap();
+
+        assert_eq!(output.repo, "demo");
+        assert_eq!(output.branch.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn open_output_branch_field_set_when_branch_specified() {
+        let config = test_config();
+        let git = demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-x"),
+                    branch: Some("feat/x".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat/x".to_string()],
+        );
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo--feat-x".to_string()]),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                branch: Some("feat/x".to_string()),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(output.repo, "demo");
+        assert_eq!(output.branch.as_deref(), Some("feat/x"));
+    }
+
+    fn shorthand_open_args(target: &str) -> OpenArgs {
+        OpenArgs::for_repo(target.to_string(), OutputFormat::Table)
+    }
+
+    fn feat_x_git() -> MockGitProvider {
+        demo_git(
+            vec![
+                main_worktree(),
+                Worktree {
+                    path: PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-x"),
+                    branch: Some("feat/x".to_string()),
+                    is_main: false,
+                },
+            ],
+            vec!["main".to_string(), "feat/x".to_string()],
+        )
+    }
+
+    #[test]
+    fn open_accepts_repo_slash_branch_shorthand() {
+        let config = test_config();
+        let git = feat_x_git();
+        let tmux = MockTmuxProvider::default();
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &shorthand_open_args("demo/feat/x"),
+        )
+        .unwrap();
+
+        assert_eq!(output.repo, "demo");
+        assert_eq!(output.branch.as_deref(), Some("feat/x"));
+        assert_eq!(output.session, "demo--feat-x");
+    }
+
+    #[test]
+    fn open_resolves_bare_branch_from_cwd_worktree() {
+        let config = test_config();
+        let mut git = feat_x_git();
+        git.current_repo_path = Some(PathBuf::from("/tmp/.kiosk_worktrees/demo--feat-x"));
+        let tmux = MockTmuxProvider::default();
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &shorthand_open_args("main"),
+        )
+        .unwrap();
+
+        assert_eq!(output.repo, "demo");
+        assert_eq!(output.branch.as_deref(), Some("main"));
+        assert_eq!(output.path, PathBuf::from("/tmp/demo"));
+    }
+
+    #[test]
+    fn open_prefers_cwd_branch_over_repo_slash_branch_shorthand() {
+        let config = test_config();
+        let mut git = demo_git(
+            vec![main_worktree()],
+            vec!["main".to_string(), "demo/feat".to_string()],
+        );
+        git.current_repo_path = Some(PathBuf::from("/tmp/demo"));
+        let tmux = MockTmuxProvider::default();
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &shorthand_open_args("demo/feat"),
+        )
+        .unwrap();
+
+        assert_eq!(output.repo, "demo");
+        assert_eq!(output.branch.as_deref(), Some("demo/feat"));
+    }
+
+    #[test]
+    fn open_prefers_exact_repo_name_over_cwd_branch() {
+        let config = test_config();
+        let mut git = feat_x_git();
+        git.current_repo_path = Some(PathBuf::from("/tmp/demo"));
+        let tmux = MockTmuxProvider::default();
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &shorthand_open_args("demo"),
+        )
+        .unwrap();
+
+        assert_eq!(output.repo, "demo");
+        assert_eq!(output.path, PathBuf::from("/tmp/demo"));
+    }
+
+    #[test]
+    fn open_mistyped_repo_inside_known_repo_reports_unknown_repo() {
+        let config = test_config();
+        let mut git = feat_x_git();
+        git.current_repo_path = Some(PathBuf::from("/tmp/demo"));
+        let tmux = MockTmuxProvider::default();
+
+        let err = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &shorthand_open_args("dmeo"),
+        )
+        .unwrap_err();
+
+        assert_eq!(err.code(), CliError::NOT_FOUND);
+        assert!(err.message().contains("no repo named 'dmeo'"));
+    }
+
+    #[test]
+    fn open_bare_branch_outside_known_repo_reports_unknown_repo() {
+        let config = test_config();
+        let git = feat_x_git();
+        let tmux = MockTmuxProvider::default();
+
+        let err = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &shorthand_open_args("feat/x"),
+        )
+        .unwrap_err();
+
+        assert_eq!(err.code(), CliError::NOT_FOUND);
+        assert!(err.message().contains("no repo named 'feat/x'"));
+    }
+
+    #[test]
+    fn open_reports_created_worktree_when_session_creation_fails() {
+        let config = test_config();
+        let git = demo_git(
+            vec![main_worktree()],
+            vec!["main".to_string(), "feat/x".to_string()],
+        );
+        let tmux = MockTmuxProvider {
+            create_session_result: Mutex::new(Some(Err(anyhow::anyhow!("tmux exploded")))),
+            ..Default::default()
+        };
+
+        let err = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &shorthand_open_args("demo/feat/x"),
+        )
+        .unwrap_err();
+
+        assert!(err.message().starts_with("tmux exploded"));
+        let details = err.details.expect("partial open details");
+        assert_eq!(
+            details["created"]["worktree"],
+            "/tmp/.kiosk_worktrees/demo--feat-x"
+        );
+        assert!(details["created"]["session"].is_null());
+        assert_eq!(details["rolled_back"], false);
+        assert_eq!(details["rollback"], "kiosk delete demo feat/x --force");
+        assert!(git.prune_worktrees_calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn open_atomic_rolls_back_worktree_branch_and_session() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let tmux = MockTmuxProvider {
+            send_keys_result: Mutex::new(Some(Err(anyhow::anyhow!("send failed")))),
+            ..Default::default()
+        };
+
+        let err = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                new_branch: Some("feat/y".to_string()),
+                base: Some("main".to_string()),
+                run: Some("make".to_string()),
+                atomic: true,
+                ..shorthand_open_args("demo")
+            },
+        )
+        .unwrap_err();
+
+        let details = err.details.expect("partial open details");
+        assert_eq!(details["rolled_back"], true);
+        assert_eq!(details["created"]["branch"], "feat/y");
+        assert_eq!(details["created"]["session"], "demo--feat-y");
+        assert!(details.get("rollback").is_none());
+        assert_eq!(
+            tmux.killed_sessions.lock().unwrap().as_slice(),
+            &["demo--feat-y".to_string()]
+        );
+        assert_eq!(
+            git.delete_branch_calls.lock().unwrap().as_slice(),
+            &[(PathBuf::from("/tmp/demo"), "feat/y".to_string())]
+        );
+    }
+
+    #[test]
+    fn open_failure_without_new_resources_has_no_details() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()]);
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo".to_string()]),
+            send_keys_result: Mutex::new(Some(Err(anyhow::anyhow!("send failed")))),
+            ..Default::default()
+        };
+
+        let err = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                run: Some("make".to_string()),
+                ..shorthand_open_args("demo")
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(err.message(), "send failed");
+        assert!(err.details.is_none());
+    }
+
+    // --- open --wait tests ---
+
+    #[test]
+    fn open_with_wait_includes_wait_output() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(Vec::new()),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                run: Some("cargo test".to_string()),
+                wait: true,
+                wait_timeout: 5,
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Json)
+            },
+        )
+        .unwrap();
+
+        let wait = output.wait.expect("wait output should be present");
+        assert!(wait.idle);
+        assert!(!wait.timed_out);
+        assert_eq!(wait.pane_command, "zsh");
+    }
+
+    #[test]
+    fn open_without_wait_has_no_wait_output() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(Vec::new()),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                run: Some("echo hi".to_string()),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        )
+        .unwrap();
+
+        assert!(output.wait.is_none());
+    }
+
+    #[test]
+    fn open_wait_without_run_errors() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(Vec::new()),
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        let result = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                wait: true,
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        );
+
+        let error = result.unwrap_err();
+        assert_eq!(error.code(), 1);
+        assert!(error.message().contains("--wait requires --run or --agent"));
+    }
+
+    #[test]
+    fn ticket_names_the_new_branch() {
+        let mut config = test_config();
+        let ticket = Ticket {
+            id: "ABC-123".to_string(),
+            title: "Fix the login page".to_string(),
+            url: "https://acme.atlassian.net/browse/ABC-123".to_string(),
+        };
+        let args = OpenArgs {
+            ticket: Some("ABC-123".to_string()),
+            base: Some("main".to_string()),
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+        };
+
+        let opened = open_args_for_ticket(&config, &args, &ticket).unwrap();
+        assert_eq!(
+            opened.new_branch.as_deref(),
+            Some("ABC-123-fix-the-login-page")
+        );
+        assert_eq!(opened.ticket, None);
+        assert_eq!(opened.base.as_deref(), Some("main"));
+
+        config.branch.name_template = Some("feat/{ticket}/{slug}".to_string());
+        let opened = open_args_for_ticket(&config, &args, &ticket).unwrap();
+        assert_eq!(
+            opened.new_branch.as_deref(),
+            Some("feat/ABC-123/fix-the-login-page")
+        );
+
+        config.branch.name_template = Some("{team}/{ticket}".to_string());
+        let error = open_args_for_ticket(&config, &args, &ticket).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "branch.name_template needs {team}, which --ticket can't ask for"
+        );
+    }
+
+    #[test]
+    fn open_tracks_the_remote_the_branch_is_on() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec!["main".to_string()])
+            .with_remote_branches("origin", ["main", "shared"])
+            .with_remote_branches("upstream", ["main", "shared", "fix"]);
+        let tmux = MockTmuxProvider {
+            inside_tmux: true,
+            ..Default::default()
+        };
+        let open = |branch: &str, remote: Option<&str>| {
+            let args = OpenArgs {
+                branch: Some(branch.to_string()),
+                remote: remote.map(str::to_string),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            };
+            open_session(&config, &git, &tmux, &Stores::disabled(), &args)
+        };
+
+        assert!(open("fix", None).unwrap().created);
+        assert_eq!(
+            open("shared", None).unwrap_err().message(),
+            "branch 'shared' is on several remotes (origin, upstream); pick one with --remote"
+        );
+        assert_eq!(
+            open("fix", Some("origin")).unwrap_err().message(),
+            "branch 'fix' not found on remote 'origin'"
+        );
+        assert!(open("shared", Some("upstream")).unwrap().created);
+
+        let calls = git.create_tracking_branch_calls.lock().unwrap();
+        let tracked: Vec<_> = calls
+            .iter()
+            .map(|(remote, branch, _)| (remote.as_str(), branch.as_str()))
+            .collect();
+        assert_eq!(tracked, [("upstream", "fix"), ("upstream", "shared")]);
+    }
+
+    #[test]
+    fn open_checks_out_a_tag_with_a_detached_head() {
+        let config = test_config();
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let git = demo_git(vec![main_worktree()], vec![]).with_commit("refs/tags/v1.2.0", commit);
+        let tmux = MockTmuxProvider {
+            inside_tmux: true,
+            ..Default::default()
+        };
+        let args = OpenArgs {
+            detached: Some(DetachedTarget::Tag("v1.2.0".to_string())),
+            ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+        };
+
+        let output = open_session(&config, &git, &tmux, &Stores::disabled(), &args).unwrap();
+
+        assert_eq!(output.branch, None);
+        assert!(output.created);
+        let calls = git.add_detached_worktree_calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].1, commit);
+        assert_eq!(calls[0].2, output.path);
+        assert!(output.path.to_string_lossy().ends_with("v1.2.0"));
+
+        let missing = OpenArgs {
+            detached: Some(DetachedTarget::Tag("v9.9.9".to_string())),
+            ..args
+        };
+        let error = open_session(&config, &git, &tmux, &Stores::disabled(), &missing).unwrap_err();
+        assert_eq!(error.message(), "tag 'v9.9.9' not found");
+    }
+
+    #[test]
+    fn open_reuses_a_worktree_detached_at_the_same_commit() {
+        let config = test_config();
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let mut git = demo_git(vec![main_worktree()], vec![])
+            .with_detached_worktree("/tmp/demo-bisect")
+            .with_commit("0123456", commit);
+        git.head_commit = Some(commit.to_string());
+        let tmux = MockTmuxProvider {
+            inside_tmux: true,
+            ..Default::default()
+        };
+
+        let output = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                detached: Some(DetachedTarget::Commit("0123456".to_string())),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(output.path, PathBuf::from("/tmp/demo-bisect"));
+        assert!(git.add_detached_worktree_calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn open_rejects_invalid_new_branch_names() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(Vec::new()),
+            inside_tmux: true,
+            ..Default::default()
+        };
+        let result = open_session(
+            &config,
+            &git,
+            &tmux,
+            &Stores::disabled(),
+            &OpenArgs {
+                new_branch: Some("fix login".to_string()),
+                base: Some("main".to_string()),
+                ..OpenArgs::for_repo("demo".to_string(), OutputFormat::Table)
+            },
+        );
+
+        let error = result.unwrap_err();
+        assert_eq!(error.code(), 1);
+        assert_eq!(
+            error.message(),
+            "invalid branch name 'fix login': it can't contain spaces"
+        );
+    }
+
+    // --- BranchOutput conversion test ---
+
+    #[test]
+    fn branch_output_from_entry_omits_internal_fields() {
+        let entry = BranchEntry {
+            name: "feat/test".to_string(),
+            worktree_path: Some(PathBuf::from("/tmp/wt")),
+            has_session: true,
+            is_current: false,
+            is_default: true,
+            remote: None,
+            session_activity_ts: Some(12345),
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
+        };
+
+        let output = BranchOutput::from(&entry);
+        assert_eq!(output.name, "feat/test");
+        assert_eq!(output.worktree_path, Some(PathBuf::from("/tmp/wt")));
+        assert!(output.has_session);
+        assert!(!output.is_current);
+        assert!(output.remote.is_none());
+
+        let json = serde_json::to_value(&output).unwrap();
+        assert!(json.get("is_default").is_none());
+        assert!(json.get("session_activity_ts").is_none());
+    }
+
+    // --- send tests ---
+
+    #[test]
+    fn send_sends_keys_to_existing_session() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider {
+            sessions: Mutex::new(vec!["demo".to_string()]),
+            ..Default::default()
+        };
+
+        let result = send(
+            &config,
+            &git,
+            &tmux,
+            &SendArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                command: Some("echo hello".to_string()),
+                keys: None,
+                text: None,
+                stdin: None,
+                file: None,
+                no_enter: false,
+                confirm_pattern: None,
+                pane: PaneTarget::Index(0),
+                format: OutputFormat::Table,
+            },
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(
+            tmux.sent_keys.lock().unwrap().as_slice(),
+            &[
+                ("demo:0:text".to_string(), "echo hello".to_string()),
+                ("demo:0".to_string(), "Enter".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn send_returns_error_when_session_does_not_exist() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+
+        let error = send(
+            &config,
+            &git,
+            &tmux,
+            &SendArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                command: Some("echo hello".to_string()),
+                keys: None,
+                text: None,
+                stdin: None,
+                file: None,
+                no_enter: false,
+                confirm_pattern: None,
+                pane: PaneTarget::Index(0),
+                format: OutputFormat::Table,
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(error.message().contains("does not exist"));
+    }
+
+    #[test]
+    fn test_send_mutually_exclusive_flags() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+
+        // Test multiple flags error
+        let error = send(
+            &config,
+            &git,
+            &tmux,
+            &SendArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                command: Some("echo hello".to_string()),
+                keys: Some("C-c".to_string()),
+                text: None,
+                stdin: None,
+                file: None,
+                no_enter: false,
+                confirm_pattern: None,
+                pane: PaneTarget::Index(0),
+                format: OutputFormat::Table,
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.code(), 1);
+        assert!(error.message().contains("mutually exclusive"));
+
+        // Test no flags error
+        let error = send(
+            &config,
+            &git,
+            &tmux,
+            &SendArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                command: None,
+                keys: None,
+                text: None,
+                stdin: None,
+                file: None,
+                no_enter: false,
+                confirm_pattern: None,
+                pane: PaneTarget::Index(0),
+                format: OutputFormat::Table,
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.code(), 1);
+        assert!(
+            error
+                .message()
+                .contains("one of --command, --keys, --text, --stdin or --file is required")
+        );
+    }
+
+    #[test]
+    fn test_send_keys_mode() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+        tmux.sessions.lock().unwrap().push("demo".to_string());
+
+        let result = send(
+            &config,
+            &git,
+            &tmux,
+            &SendArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                command: None,
+                keys: Some("C-c Escape Enter".to_string()),
+                text: None,
+                stdin: None,
+                file: None,
+                no_enter: false,
+                confirm_pattern: None,
+                pane: PaneTarget::Index(1),
+                format: OutputFormat::Table,
+            },
+        );
+
+        assert!(result.is_ok());
+        let sent_keys = tmux.sent_keys.lock().unwrap();
+        assert_eq!(sent_keys.len(), 1);
+        assert_eq!(sent_keys[0].0, "demo:1");
+        assert_eq!(sent_keys[0].1, "C-c Escape Enter");
+    }
+
+    #[test]
+    fn test_send_text_mode() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+        tmux.sessions.lock().unwrap().push("demo".to_string());
+
+        let result = send(
+            &config,
+            &git,
+            &tmux,
+            &SendArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                command: None,
+                keys: None,
+                text: Some("hello world".to_string()),
+                stdin: None,
+                file: None,
+                no_enter: false,
+                confirm_pattern: None,
+                pane: PaneTarget::Index(2),
+                format: OutputFormat::Table,
+            },
+        );
+
+        assert!(result.is_ok());
+        let sent_keys = tmux.sent_keys.lock().unwrap();
+        assert_eq!(sent_keys.len(), 1);
+        assert_eq!(sent_keys[0].0, "demo:2:text");
+        assert_eq!(sent_keys[0].1, "hello world");
+    }
+
+    #[test]
+    fn test_send_targets_pane_by_command_name() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default()
+            .with_session("demo")
+            .with_panes("demo", &[("hx", "editor"), ("claude", "")]);
+        let args = |pane: &str| SendArgs {
+            repo: "demo".to_string(),
+            branch: None,
+            command: None,
+            keys: Some("Enter".to_string()),
+            text: None,
+            stdin: None,
+            file: None,
+            no_enter: false,
+            confirm_pattern: None,
+            pane: pane.parse().unwrap(),
+            format: OutputFormat::Table,
+        };
+
+        send(&config, &git, &tmux, &args("claude")).unwrap();
+        send(&config, &git, &tmux, &args("@editor")).unwrap();
+        let error = send(&config, &git, &tmux, &args("codex")).unwrap_err();
+        assert!(error.message().contains("no pane matching 'codex'"));
+
+        let sent_keys = tmux.sent_keys.lock().unwrap();
+        let targets: Vec<&str> = sent_keys
+            .iter()
+            .map(|(target, _)| target.as_str())
+            .collect();
+        assert_eq!(targets, ["demo:1", "demo:0"]);
+    }
+
+    #[test]
+    fn test_send_confirm_pattern_refuses_a_pane_not_at_its_prompt() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default()
+            .with_session("demo")
+            .with_capture_output("$ cargo build\n   Compiling demo\n\n");
+        let args = |pattern: &str| SendArgs {
+            repo: "demo".to_string(),
+            branch: None,
+            command: Some("cargo test".to_string()),
+            keys: None,
+            text: None,
+            stdin: None,
+            file: None,
+            no_enter: false,
+            confirm_pattern: Some(pattern.to_string()),
+            pane: PaneTarget::Index(0),
+            format: OutputFormat::Table,
+        };
+
+        let error = send(&config, &git, &tmux, &args(r"\$\z")).unwrap_err();
+        assert_eq!(error.code(), CliError::USER);
+        assert!(error.message().contains("nothing was sent"));
+        assert_eq!(
+            error.details().unwrap()["captured"],
+            "$ cargo build\n   Compiling demo"
+        );
+        let error = send(&config, &git, &tmux, &args("(")).unwrap_err();
+        assert!(error.message().contains("invalid --confirm-pattern"));
+        assert!(tmux.sent_keys.lock().unwrap().is_empty());
+
+        send(&config, &git, &tmux, &args(r"Compiling demo\z")).unwrap();
+        assert_eq!(tmux.sent_keys.lock().unwrap().len(), 2);
+        assert_eq!(
+            tmux.captured_ranges.lock().unwrap()[0],
+            ("demo:0".to_string(), CaptureRange::default())
+        );
+    }
+
+    fn file_send_args(file: PathBuf, no_enter: bool) -> SendArgs {
+        SendArgs {
+            repo: "demo".to_string(),
+            branch: None,
+            command: None,
+            keys: None,
+            text: None,
+            stdin: None,
+            file: Some(file),
+            no_enter,
+            confirm_pattern: None,
+            pane: PaneTarget::default(),
+            format: OutputFormat::Table,
+        }
+    }
+
+    #[test]
+    fn test_send_file_pastes_contents_then_presses_enter() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default().with_session("demo");
+        let dir = tempfile::tempdir().unwrap();
+        let prompt = dir.path().join("PROMPT.md");
+        fs::write(&prompt, "Fix the tests.\n\nThen run `cargo fmt`.\n").unwrap();
+
+        send(&config, &git, &tmux, &file_send_args(prompt.clone(), false)).unwrap();
+        send(&config, &git, &tmux, &file_send_args(prompt, true)).unwrap();
+
+        let sent_keys = tmux.sent_keys.lock().unwrap();
+        assert_eq!(
+            *sent_keys,
+            [
+                (
+                    "demo:0:paste".to_string(),
+                    "Fix the tests.\n\nThen run `cargo fmt`.".to_string()
+                ),
+                ("demo:0".to_string(), "Enter".to_string()),
+                (
+                    "demo:0:paste".to_string(),
+                    "Fix the tests.\n\nThen run `cargo fmt`.".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_send_file_rejects_missing_and_empty_files() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default().with_session("demo");
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty.md");
+        fs::write(&empty, "\n").unwrap();
+
+        let missing = send(
+            &config,
+            &git,
+            &tmux,
+            &file_send_args(dir.path().join("missing.md"), false),
+        )
+        .unwrap_err();
+        assert!(missing.message().contains("failed to read"));
+        let error = send(&config, &git, &tmux, &file_send_args(empty, false)).unwrap_err();
+        assert!(error.message().contains("payload is empty"));
+        assert!(tmux.sent_keys.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_panes_command() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+        tmux.sessions.lock().unwrap().push("demo".to_string());
+
+        let result = panes(
+            &config,
+            &git,
+            &tmux,
+            &PanesArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                format: OutputFormat::Json,
+            },
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_wait_command() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+        tmux.sessions.lock().unwrap().push("demo".to_string());
+
+        let result = wait(
+            &config,
+            &git,
+            &tmux,
+            &WaitArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                timeout: 1,
+                pane: PaneTarget::Index(0),
+                format: OutputFormat::Json,
+            },
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_wait_reports_the_command_still_running_at_the_timeout() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default().with_session_running("demo", "claude");
+        let args = WaitArgs {
+            repo: "demo".to_string(),
+            branch: None,
+            timeout: 0,
+            pane: PaneTarget::default(),
+            format: OutputFormat::Json,
+        };
+
+        let output = wait(&config, &git, &tmux, &args).unwrap();
+        assert!(output.timed_out);
+        assert_eq!(output.pane_command, "claude");
+    }
+
+    #[test]
+    fn errors_carry_distinct_exit_codes() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let wait = |repo: &str, tmux: &MockTmuxProvider| {
+            let args = WaitArgs {
+                repo: repo.to_string(),
+                branch: None,
+                timeout: 0,
+                pane: PaneTarget::default(),
+                format: OutputFormat::Json,
+            };
+            wait(&config, &git, tmux, &args).unwrap_err().code()
+        };
+
+        assert_eq!(
+            wait("nope", &MockTmuxProvider::default()),
+            CliError::NOT_FOUND
+        );
+        assert_eq!(
+            wait("demo", &MockTmuxProvider::default()),
+            CliError::NOT_FOUND
+        );
+        let no_tmux = MockTmuxProvider {
+            unavailable: true,
+            ..Default::default()
+        };
+        assert_eq!(wait("demo", &no_tmux), CliError::TMUX_UNAVAILABLE);
+
+        let spawn_error = anyhow::Error::new(TmuxNotRun(std::io::Error::from(
+            std::io::ErrorKind::NotFound,
+        )))
+        .context("list-panes for session demo");
+        assert_eq!(
+            CliError::from(spawn_error).code(),
+            CliError::TMUX_UNAVAILABLE
+        );
+        let other_spawn_error =
+            anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NotFound))
+                .context("failed to execute tmux-lookalike");
+        assert_eq!(CliError::from(other_spawn_error).code(), CliError::SYSTEM);
+        assert_eq!(
+            CliError::from(anyhow::anyhow!("tmux list-panes failed: bad target")).code(),
+            CliError::SYSTEM
+        );
+        let timed_out = anyhow::Error::new(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "git fetch origin timed out after 300s",
+        ));
+        assert_eq!(CliError::from(timed_out).code(), CliError::TIMEOUT);
+    }
+
+    #[test]
+    fn test_log_command_no_file() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+
+        let result = log(
+            &config,
+            &git,
+            &LogArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                tail: 10,
+                follow: false,
+                grep: None,
+                line_format: LineFormat::default(),
+                format: OutputFormat::Table,
+            },
+            |_| true,
+        );
+
+        assert!(result.is_err());
+        let error = result.unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(error.message().contains("no log file found"));
+
+        // Lines already logged have no time to stamp them with
+        let error = log(
+            &config,
+            &git,
+            &LogArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                tail: 10,
+                follow: false,
+                grep: None,
+                line_format: LineFormat {
+                    strip_ansi: false,
+                    timestamps: true,
+                },
+                format: OutputFormat::Table,
+            },
+            |_| true,
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), CliError::USER);
+    }
+
+    #[test]
+    fn config_validate_reports_problems() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.toml");
+        fs::write(
+            &file,
+            format!("search_dirs = [{:?}]\nsrot = \"activity\"\n", dir.path()),
+        )
+        .unwrap();
+
+        let output = config_validate(&file).unwrap();
+        assert!(!output.valid);
+        assert_eq!(output.problems.len(), 1);
+        assert_eq!(output.problems[0].suggestion.as_deref(), Some("sort"));
+
+        fs::write(&file, format!("search_dirs = [{:?}]\n", dir.path())).unwrap();
+        assert!(config_validate(&file).unwrap().valid);
+    }
+
+    #[test]
+    fn editor_command_prefers_visual_and_splits_arguments() {
+        assert_eq!(
+            editor_command(Some("code --wait"), Some("vim")),
+            ["code", "--wait"]
+        );
+        assert_eq!(editor_command(Some("  "), Some("hx")), ["hx"]);
+        assert_eq!(
+            editor_command(None, None),
+            [if cfg!(windows) { "notepad" } else { "vi" }]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn open_in_editor_runs_the_configured_editor() {
+        let dir = tempfile::tempdir().unwrap();
+        open_in_editor(dir.path(), Some("true")).unwrap();
+        let error = open_in_editor(dir.path(), Some("false")).unwrap_err();
+        assert!(error.to_string().contains("exited with"), "{error}");
+        let error = open_in_editor(dir.path(), Some("kiosk-no-such-editor")).unwrap_err();
+        assert!(error.to_string().contains("failed to launch"), "{error}");
+    }
+
+    #[test]
+    fn config_validate_missing_file_is_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = config_validate(&dir.path().join("missing.toml")).unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_status_with_pane() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+        tmux.sessions.lock().unwrap().push("demo".to_string());
+        *tmux.capture_output.lock().unwrap() = "test output".to_string();
+
+        let result = status(
+            &config,
+            &git,
+            &tmux,
+            &StatusArgs {
+                repo: "demo".to_string(),
+                branch: None,
+                format: OutputFormat::Json,
+                lines: 20,
+                pane: PaneTarget::Index(1),
+                strip_ansi: false,
+            },
+        );
+
+        assert!(result.is_ok());
+    }
+
+    fn capture_args(history: Option<usize>, start: Option<i64>) -> CaptureArgs {
+        CaptureArgs {
+            repo: "demo".to_string(),
+            branch: None,
+            pane: "claude".parse().unwrap(),
+            history,
+            start,
+            end: Some(-1),
+            escapes: true,
+            format: OutputFormat::Table,
+        }
+    }
+
+    #[test]
+    fn test_capture_passes_range_to_resolved_pane_verbatim() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default()
+            .with_session("demo")
+            .with_panes("demo", &[("zsh", ""), ("claude", "")])
+            .with_capture_output("\x1b[1mraw\x1b[0m\n");
+
+        let output = capture(&config, &git, &tmux, &capture_args(Some(500), None)).unwrap();
+        assert_eq!(output.pane, 1);
+        assert_eq!(
+            output.output, "\x1b[1mraw\x1b[0m\n",
+            "escapes aren't stripped"
+        );
+        capture(&config, &git, &tmux, &capture_args(None, Some(3))).unwrap();
+
+        let ranges = tmux.captured_ranges.lock().unwrap();
+        assert_eq!(
+            *ranges,
+            [
+                (
+                    "demo:1".to_string(),
+                    CaptureRange {
+                        start: Some(-500),
+                        end: Some(-1),
+                        escapes: true,
+                    }
+                ),
+                (
+                    "demo:1".to_string(),
+                    CaptureRange {
+                        start: Some(3),
+                        end: Some(-1),
+                        escapes: true,
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_capture_requires_running_session() {
+        let config = test_config();
+        let git = demo_git(vec![main_worktree()], vec![]);
+        let tmux = MockTmuxProvider::default();
+
+        let error = capture(&config, &git, &tmux, &capture_args(None, None)).unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(error.message().contains("does not exist"));
+    }
+
+    #[test]
+    fn schema_wraps_command_output_in_versioned_envelope() {
+        let schema = serde_json::to_value(schema_for_command(SchemaCommand::Open)).unwrap();
+        assert_eq!(
+            schema["required"],
+            serde_json::json!(["schema_version", "data"])
+        );
+        let open = &schema["$defs"]["OpenOutput"]["properties"];
+        assert!(open["session"].is_object(), "{schema}");
+        assert!(open["created"].is_object(), "{schema}");
+    }
+
+    #[test]
+    fn schema_for_list_commands_is_an_array() {
+        let schema = serde_json::to_value(schema_for_command(SchemaCommand::Sessions)).unwrap();
+        assert_eq!(schema["properties"]["data"]["type"], "array");
+    }
+}
@@ -0,0 +1,184 @@
+//! Post-processing for captured session output shared by `kiosk log` and `kiosk status`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+
+/// How captured lines are rewritten before being printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineFormat {
+    /// Remove terminal escape sequences and control characters.
+    pub strip_ansi: bool,
+    /// Prefix each line with an RFC 3339 UTC timestamp. Only for lines as they're written,
+    /// since earlier ones weren't recorded with their time.
+    pub timestamps: bool,
+}
+
+impl LineFormat {
+    /// Clean up a line before it is filtered; see [`strip_ansi`].
+    pub fn clean(self, line: &str) -> String {
+        if self.strip_ansi {
+            strip_ansi(line)
+        } else {
+            line.to_string()
+        }
+    }
+
+    /// Add the timestamp prefix, if enabled, for a line captured at `at`.
+    pub fn stamp(self, line: &str, at: SystemTime) -> String {
+        if self.timestamps {
+            format!("{} {line}", format_timestamp(at))
+        } else {
+            line.to_string()
+        }
+    }
+
+    /// Apply [`Self::clean`] to every line of `content`.
+    pub fn clean_all(self, content: &str) -> String {
+        if self.strip_ansi {
+            strip_ansi_lines(content)
+        } else {
+            content.to_string()
+        }
+    }
+}
+
+/// Apply [`strip_ansi`] to every line of `content`.
+pub fn strip_ansi_lines(content: &str) -> String {
+    content
+        .lines()
+        .map(strip_ansi)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Remove CSI/OSC escape sequences and control characters (other than tabs) from a line.
+/// Carriage returns are treated as the terminal would: only the text written after the
+/// last one remains visible, which collapses progress-bar redraws to their final state.
+pub fn strip_ansi(line: &str) -> String {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let visible = line.rsplit('\r').next().unwrap_or(line);
+
+    let mut out = String::with_capacity(visible.len());
+    let mut chars = visible.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ESC => match chars.next() {
+                // CSI: parameters and intermediates up to a final byte in @..~
+                Some('[') => {
+                    for next in chars.by_ref() {
+                        if ('@'..='~').contains(&next) {
+                            break;
+                        }
+                    }
+                }
+                // OSC, DCS, APC, PM: terminated by BEL or ST (ESC \)
+                Some(']' | 'P' | '_' | '^') => {
+                    while let Some(next) = chars.next() {
+                        if next == BEL || (next == ESC && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // Charset selection and similar take one more character
+                Some('(' | ')' | '*' | '+' | '#' | '%') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\t' => out.push(ch),
+            ch if ch.is_control() => {}
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Format as `YYYY-MM-DDTHH:MM:SS.mmmZ`.
+pub fn format_timestamp(at: SystemTime) -> String {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Days since 1970-01-01 to a (year, month, day) civil date (Howard Hinnant's algorithm).
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn strips_colors_and_cursor_movement() {
+        assert_eq!(
+            strip_ansi("\u{1b}[1;32mok\u{1b}[0m: \u{1b}[2Kdone"),
+            "ok: done"
+        );
+    }
+
+    #[test]
+    fn strips_osc_sequences_with_either_terminator() {
+        assert_eq!(strip_ansi("\u{1b}]0;title\u{7}prompt $ "), "prompt $ ");
+        assert_eq!(
+            strip_ansi("\u{1b}]8;;https://x.dev\u{1b}\\link\u{1b}]8;;\u{1b}\\"),
+            "link"
+        );
+    }
+
+    #[test]
+    fn keeps_tabs_and_collapses_carriage_returns() {
+        assert_eq!(strip_ansi("a\tb\r\n"), "a\tb");
+        assert_eq!(strip_ansi("10%\r50%\r100%\r"), "100%");
+        assert_eq!(strip_ansi("bell\u{7}"), "bell");
+    }
+
+    #[test]
+    fn formats_rfc3339_utc_timestamps() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let at = UNIX_EPOCH + Duration::from_millis(1_709_210_096_789);
+        assert_eq!(format_timestamp(at), "2024-02-29T12:34:56.789Z");
+    }
+
+    #[test]
+    fn line_format_is_a_no_op_by_default() {
+        let raw = "\u{1b}[31mred\u{1b}[0m";
+        let format = LineFormat::default();
+        assert_eq!(format.stamp(&format.clean(raw), UNIX_EPOCH), raw);
+        assert_eq!(format.clean_all("a\nb"), "a\nb");
+    }
+
+    #[test]
+    fn line_format_cleans_and_stamps_lines() {
+        let format = LineFormat {
+            strip_ansi: true,
+            timestamps: true,
+        };
+        assert_eq!(
+            format.stamp(&format.clean("\u{1b}[1ma\u{1b}[0m"), UNIX_EPOCH),
+            "1970-01-01T00:00:00.000Z a"
+        );
+        assert_eq!(format.clean_all("\u{1b}[1ma\u{1b}[0m\nb"), "a\nb");
+    }
+}
@@ -0,0 +1,328 @@
+use super::{CliError, CliResult, template::Template};
+use schemars::{JsonSchema, Schema};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{fmt::Write, str::FromStr};
+
+/// Output format shared by every CLI subcommand.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    /// Tab-separated values with a header row
+    Tsv,
+    /// One line per row, interpolating `{field}` placeholders
+    Template(Template),
+}
+
+impl OutputFormat {
+    /// `--json` is kept as shorthand for `--format json` and takes precedence.
+    pub fn resolve(format: Option<&Self>, json: bool) -> Self {
+        if json {
+            Self::Json
+        } else {
+            format.cloned().unwrap_or_default()
+        }
+    }
+}
+
+/// Accepts a format name, or a template when the value contains a `{field}` placeholder.
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "yaml" => Ok(Self::Yaml),
+            "tsv" => Ok(Self::Tsv),
+            template if template.contains('{') => template.parse().map(Self::Template),
+            other => Err(format!(
+                "unknown format '{other}' (expected table, json, yaml, tsv or a template like '{{name}}\\t{{path}}')"
+            )),
+        }
+    }
+}
+
+/// Version of the JSON output shapes. Bump it whenever a field is removed, renamed or changes
+/// type; adding fields is not a breaking change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Stable wrapper around every `--format json` payload, so integrators can detect shape changes.
+#[derive(Debug, Serialize, JsonSchema)]
+struct Envelope<T> {
+    schema_version: u32,
+    data: T,
+}
+
+/// JSON Schema for the enveloped JSON output of a command whose payload is `T`.
+pub fn envelope_schema<T: JsonSchema>() -> Schema {
+    schemars::schema_for!(Envelope<T>)
+}
+
+/// `value` in `format`, using `table` for the human-readable rendering.
+pub fn render<T: Serialize>(
+    format: &OutputFormat,
+    value: &T,
+    table: impl FnOnce(&T) -> String,
+) -> CliResult<String> {
+    match format {
+        OutputFormat::Table => Ok(table(value)),
+        OutputFormat::Json => serde_json::to_string(&Envelope {
+            schema_version: SCHEMA_VERSION,
+            data: value,
+        })
+        .map(|json| format!("{json}\n"))
+        .map_err(|e| CliError::system(e.to_string())),
+        OutputFormat::Yaml => {
+            serde_yaml_ng::to_string(value).map_err(|e| CliError::system(e.to_string()))
+        }
+        OutputFormat::Tsv => serde_json::to_value(value)
+            .map(|value| to_tsv(&value))
+            .map_err(|e| CliError::system(e.to_string())),
+        OutputFormat::Template(template) => {
+            let value = serde_json::to_value(value).map_err(|e| CliError::system(e.to_string()))?;
+            rows(&value)
+                .iter()
+                .map(|row| template.render(row))
+                .collect::<Result<String, _>>()
+                .map_err(CliError::user)
+        }
+    }
+}
+
+/// Renders a stream of records (e.g. `kiosk log --follow`): NDJSON for json, a YAML
+/// document stream for yaml, and TSV with a single header row.
+#[derive(Debug)]
+pub struct RecordStream {
+    format: OutputFormat,
+    started: bool,
+}
+
+impl RecordStream {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            started: false,
+        }
+    }
+
+    pub fn render<T: Serialize>(
+        &mut self,
+        value: &T,
+        table: impl FnOnce(&T) -> String,
+    ) -> CliResult<String> {
+        let rendered = render(&self.format, value, table)?;
+        let started = std::mem::replace(&mut self.started, true);
+        Ok(match self.format {
+            OutputFormat::Yaml => format!("---\n{rendered}"),
+            OutputFormat::Tsv if started => rendered
+                .split_once('\n')
+                .map_or(String::new(), |(_, rows)| rows.to_string()),
+            OutputFormat::Table
+            | OutputFormat::Json
+            | OutputFormat::Tsv
+            | OutputFormat::Template(_) => rendered,
+        })
+    }
+}
+
+/// Flatten a serialized output into the rows used by TSV and templates:
+/// - an array yields one row per element;
+/// - an object with a single array field yields one row per element of that array, with the
+///   object's other fields repeated as leading columns (e.g. `panes` keeps its `session`);
+/// - anything else is a single row.
+///
+/// Array elements that aren't objects become a column named after the array field.
+fn rows(value: &Value) -> Vec<Map<String, Value>> {
+    match value {
+        Value::Array(items) => items.iter().map(|item| row(item, "value")).collect(),
+        Value::Object(fields) => {
+            let arrays: Vec<_> = fields.iter().filter(|(_, v)| v.is_array()).collect();
+            match arrays.as_slice() {
+                [(array_key, Value::Array(items))] => {
+                    let shared: Map<String, Value> = fields
+                        .iter()
+                        .filter(|(key, _)| key != array_key)
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect();
+                    items
+                        .iter()
+                        .map(|item| {
+                            let mut merged = shared.clone();
+                            merged.extend(row(item, array_key));
+                            merged
+                        })
+                        .collect()
+                }
+                _ => vec![fields.clone()],
+            }
+        }
+        scalar => vec![row(scalar, "value")],
+    }
+}
+
+fn to_tsv(value: &Value) -> String {
+    let rows = rows(value);
+    let mut columns: Vec<&String> = Vec::new();
+    for row in &rows {
+        for key in row.keys() {
+            if !columns.contains(&key) {
+                columns.push(key);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    if columns.is_empty() {
+        return out;
+    }
+    let header: Vec<String> = columns.iter().map(|column| escape_cell(column)).collect();
+    let _ = writeln!(out, "{}", header.join("\t"));
+    for row in &rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| row.get(*column).map(cell).unwrap_or_default())
+            .collect();
+        let _ = writeln!(out, "{}", cells.join("\t"));
+    }
+    out
+}
+
+fn row(item: &Value, key: &str) -> Map<String, Value> {
+    match item {
+        Value::Object(fields) => fields.clone(),
+        other => Map::from_iter([(key.to_string(), other.clone())]),
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => escape_cell(text),
+        Value::Bool(_) | Value::Number(_) => value.to_string(),
+        nested => escape_cell(&nested.to_string()),
+    }
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn table(_: &Value) -> String {
+        "table\n".to_string()
+    }
+
+    #[test]
+    fn json_flag_overrides_format() {
+        assert_eq!(
+            OutputFormat::resolve(Some(&OutputFormat::Yaml), true),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            OutputFormat::resolve(Some(&OutputFormat::Tsv), false),
+            OutputFormat::Tsv
+        );
+        assert_eq!(OutputFormat::resolve(None, false), OutputFormat::Table);
+    }
+
+    #[test]
+    fn renders_each_format() {
+        let value = json!([{ "name": "kiosk", "path": "/src/kiosk" }]);
+        assert_eq!(
+            render(&OutputFormat::Table, &value, table).unwrap(),
+            "table\n"
+        );
+        assert_eq!(
+            render(&OutputFormat::Json, &value, table).unwrap(),
+            "{\"schema_version\":1,\"data\":[{\"name\":\"kiosk\",\"path\":\"/src/kiosk\"}]}\n"
+        );
+        assert_eq!(
+            render(&OutputFormat::Yaml, &value, table).unwrap(),
+            "- name: kiosk\n  path: /src/kiosk\n"
+        );
+        assert_eq!(
+            render(&OutputFormat::Tsv, &value, table).unwrap(),
+            "name\tpath\nkiosk\t/src/kiosk\n"
+        );
+    }
+
+    #[test]
+    fn parses_format_names_and_templates() {
+        assert_eq!("yaml".parse::<OutputFormat>(), Ok(OutputFormat::Yaml));
+        assert!(matches!(
+            "{name}".parse::<OutputFormat>(),
+            Ok(OutputFormat::Template(_))
+        ));
+        assert!("xml".parse::<OutputFormat>().is_err());
+        assert!("{name".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn template_renders_one_line_per_row_with_shared_fields() {
+        let format: OutputFormat = "{session}:{index}".parse().unwrap();
+        let value = json!({ "session": "demo", "panes": [{ "index": 0 }, { "index": 1 }] });
+        assert_eq!(render(&format, &value, table).unwrap(), "demo:0\ndemo:1\n");
+    }
+
+    #[test]
+    fn template_with_unknown_field_is_a_user_error() {
+        let format: OutputFormat = "{nope}".parse().unwrap();
+        let error = render(&format, &json!([{ "name": "kiosk" }]), table).unwrap_err();
+        assert_eq!(error.code(), 1);
+    }
+
+    #[test]
+    fn record_stream_writes_tsv_header_once() {
+        let mut stream = RecordStream::new(OutputFormat::Tsv);
+        let first = stream.render(&json!({ "line": "a" }), table).unwrap();
+        let second = stream.render(&json!({ "line": "b" }), table).unwrap();
+        assert_eq!(format!("{first}{second}"), "line\na\nb\n");
+    }
+
+    #[test]
+    fn record_stream_separates_yaml_documents() {
+        let mut stream = RecordStream::new(OutputFormat::Yaml);
+        let first = stream.render(&json!({ "line": "a" }), table).unwrap();
+        let second = stream.render(&json!({ "line": "b" }), table).unwrap();
+        assert_eq!(format!("{first}{second}"), "---\nline: a\n---\nline: b\n");
+    }
+
+    #[test]
+    fn tsv_expands_single_array_field_with_shared_columns() {
+        let value = json!({
+            "session": "demo",
+            "panes": [{ "index": 0, "active": true }, { "index": 1, "active": false }],
+        });
+        assert_eq!(
+            to_tsv(&value),
+            "session\tindex\tactive\ndemo\t0\ttrue\ndemo\t1\tfalse\n"
+        );
+    }
+
+    #[test]
+    fn tsv_names_scalar_array_elements_after_their_field() {
+        let value = json!({ "session": "demo", "lines": ["a", "b"] });
+        assert_eq!(to_tsv(&value), "session\tlines\ndemo\ta\ndemo\tb\n");
+    }
+
+    #[test]
+    fn tsv_escapes_control_characters_and_flattens_nested_values() {
+        let value = json!({ "output": "a\tb\nc", "branch": null, "meta": { "k": 1 } });
+        assert_eq!(
+            to_tsv(&value),
+            "output\tbranch\tmeta\na\\tb\\nc\t\t{\"k\":1}\n"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use super::{CliError, CliResult};
+use kiosk_core::tmux::{PaneDetails, TmuxProvider};
+use std::{fmt, str::FromStr};
+
+/// A `--pane` value: a pane index, a `@title`, or the name of the command running in the pane.
+///
+/// Names and titles are resolved to an index when the command runs, so scripts keep hitting
+/// the right pane when panes are added or reordered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaneTarget {
+    Index(usize),
+    /// Pane whose title (`select-pane -T`) matches exactly
+    Title(String),
+    /// Pane whose foreground command matches exactly, e.g. `claude`
+    Command(String),
+}
+
+impl Default for PaneTarget {
+    fn default() -> Self {
+        Self::Index(0)
+    }
+}
+
+impl FromStr for PaneTarget {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if let Some(title) = value.strip_prefix('@') {
+            return if title.is_empty() {
+                Err("pane title after '@' must not be empty".to_string())
+            } else {
+                Ok(Self::Title(title.to_string()))
+            };
+        }
+        if value.is_empty() {
+            return Err("pane must be an index, a command name or '@title'".to_string());
+        }
+        Ok(value
+            .parse()
+            .map_or_else(|_| Self::Command(value.to_string()), Self::Index))
+    }
+}
+
+impl fmt::Display for PaneTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{index}"),
+            Self::Title(title) => write!(f, "@{title}"),
+            Self::Command(command) => f.write_str(command),
+        }
+    }
+}
+
+impl PaneTarget {
+    /// The index of the pane in `session` this target refers to. Indices are used as-is;
+    /// names and titles pick the first matching pane.
+    pub fn resolve(&self, tmux: &dyn TmuxProvider, session: &str) -> CliResult<usize> {
+        let (wanted, field): (&str, fn(&PaneDetails) -> &str) = match self {
+            Self::Index(index) => return Ok(*index),
+            Self::Title(title) => (title, |pane| &pane.title),
+            Self::Command(command) => (command, |pane| &pane.current_command),
+        };
+        let panes = tmux.list_panes_detailed(session).map_err(CliError::from)?;
+        panes
+            .iter()
+            .find(|pane| field(pane) == wanted)
+            .map(|pane| pane.index)
+            .ok_or_else(|| {
+                let available = panes
+                    .iter()
+                    .map(|pane| {
+                        format!("{} ({}, @{})", pane.index, pane.current_command, pane.title)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                CliError::not_found(format!(
+                    "no pane matching '{self}' in session '{session}'. Available: {available}"
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kiosk_core::tmux::mock::MockTmuxProvider;
+
+    #[test]
+    fn parses_indices_titles_and_commands() {
+        assert_eq!("2".parse(), Ok(PaneTarget::Index(2)));
+        assert_eq!(
+            "@editor".parse(),
+            Ok(PaneTarget::Title("editor".to_string()))
+        );
+        assert_eq!(
+            "claude".parse(),
+            Ok(PaneTarget::Command("claude".to_string()))
+        );
+        assert!("@".parse::<PaneTarget>().is_err());
+        assert!("".parse::<PaneTarget>().is_err());
+    }
+
+    #[test]
+    fn resolves_by_command_and_title() {
+        let tmux = MockTmuxProvider::default().with_session("demo").with_panes(
+            "demo",
+            &[("zsh", "shell"), ("hx", "editor"), ("claude", "")],
+        );
+
+        let resolve = |target: &str| target.parse::<PaneTarget>().unwrap().resolve(&tmux, "demo");
+        assert_eq!(resolve("claude").unwrap(), 2);
+        assert_eq!(resolve("@editor").unwrap(), 1);
+        assert_eq!(resolve("5").unwrap(), 5, "indices aren't checked");
+
+        let error = resolve("codex").unwrap_err();
+        assert_eq!(error.code(), CliError::NOT_FOUND);
+        assert!(error.message().contains("Available: 0 (zsh, @shell)"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Plugins: executables named `kiosk-<name>` on `PATH`, run as `kiosk <name>` in the style of
+//! git and cargo. Built-in commands take precedence over plugins of the same name.
+
+use super::output::SCHEMA_VERSION;
+use kiosk_core::{config::Config, placeholders::SessionContext};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+    env,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+pub const PLUGIN_PREFIX: &str = "kiosk-";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct PluginOutput {
+    /// Subcommand the plugin runs as
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Every plugin on `path_var` (a `PATH`-style list of directories), sorted by name. Where
+/// several directories have a plugin of the same name, the first wins, as when running it.
+pub fn discover(path_var: Option<&OsStr>) -> Vec<PluginOutput> {
+    let mut plugins: Vec<PluginOutput> = Vec::new();
+    for dir in env::split_paths(path_var.unwrap_or_default()) {
+        let Ok(entries) = dir.read_dir() else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(PLUGIN_PREFIX))
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            if is_executable(&path) && !plugins.iter().any(|plugin| plugin.name == name) {
+                plugins.push(PluginOutput { name, path });
+            }
+        }
+    }
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+/// The executable `kiosk <name>` runs, if any.
+pub fn find(name: &str, path_var: Option<&OsStr>) -> Option<PathBuf> {
+    if name.is_empty() || name.contains(std::path::is_separator) {
+        return None;
+    }
+    env::split_paths(path_var.unwrap_or_default())
+        .map(|dir| dir.join(format!("{PLUGIN_PREFIX}{name}")))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// What a plugin is given as JSON on stdin.
+#[derive(Serialize)]
+pub struct PluginContext<'a> {
+    pub schema_version: u32,
+    pub name: &'a str,
+    pub args: &'a [String],
+    pub config_path: &'a Path,
+    /// The resolved config, with any profile merged in
+    pub config: &'a Config,
+    /// The repo the current directory is in, if any
+    pub repo: Option<PluginRepo>,
+    /// The tmux session kiosk was run from, if any
+    pub session: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PluginRepo {
+    pub name: String,
+    /// Main checkout
+    pub path: PathBuf,
+    /// Worktree the current directory is in
+    pub worktree: PathBuf,
+    /// `None` for a detached `HEAD`
+    pub branch: Option<String>,
+    pub base: Option<String>,
+}
+
+impl PluginContext<'_> {
+    pub(crate) fn new<'a>(
+        name: &'a str,
+        args: &'a [String],
+        config_path: &'a Path,
+        config: &'a Config,
+        repo: Option<(PathBuf, SessionContext)>,
+        session: Option<String>,
+    ) -> PluginContext<'a> {
+        PluginContext {
+            schema_version: SCHEMA_VERSION,
+            name,
+            args,
+            config_path,
+            config,
+            repo: repo.map(|(path, context)| PluginRepo {
+                name: context.repo,
+                path,
+                worktree: context.worktree,
+                branch: context.branch,
+                base: context.base,
+            }),
+            session,
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_script(dir: &Path, name: &str, mode: u32) {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn discover_lists_executables_with_the_prefix_and_the_first_of_each_name() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        write_script(first.path(), "kiosk-jira", 0o755);
+        write_script(first.path(), "kiosk-notes", 0o644);
+        write_script(first.path(), "other", 0o755);
+        write_script(second.path(), "kiosk-jira", 0o755);
+        write_script(second.path(), "kiosk-deploy", 0o755);
+        let path_var = env::join_paths([first.path(), second.path()]).unwrap();
+
+        let plugins = discover(Some(&path_var));
+        assert_eq!(
+            plugins,
+            [
+                PluginOutput {
+                    name: "deploy".to_string(),
+                    path: second.path().join("kiosk-deploy"),
+                },
+                PluginOutput {
+                    name: "jira".to_string(),
+                    path: first.path().join("kiosk-jira"),
+                },
+            ]
+        );
+        assert_eq!(
+            find("jira", Some(&path_var)),
+            Some(first.path().join("kiosk-jira"))
+        );
+        assert_eq!(find("notes", Some(&path_var)), None);
+        assert_eq!(find("../other", Some(&path_var)), None);
+    }
+}
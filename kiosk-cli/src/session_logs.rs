@@ -0,0 +1,384 @@
+use anyhow::{Context, Result};
+use kiosk_core::config::LogConfig;
+use notify::{RecursiveMode, Watcher};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const LOG_SUFFIX: &str = ".log";
+/// Fallback re-check interval while following, in case a change notification is missed.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const BYTES_PER_MB: u64 = 1024 * 1024;
+const SECS_PER_DAY: u64 = 60 * 60 * 24;
+
+/// A live (`<session>.log`) or rotated (`<session>.log.<n>`) session log file.
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct SessionLogFile {
+    pub session: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub modified_unix_secs: u64,
+    /// Rotation index, or `None` for the log tmux is currently writing to.
+    pub rotation: Option<usize>,
+}
+
+fn parse_log_file_name(name: &str) -> Option<(String, Option<usize>)> {
+    if let Some(session) = name.strip_suffix(LOG_SUFFIX) {
+        return (!session.is_empty()).then(|| (session.to_string(), None));
+    }
+    let (stem, index) = name.rsplit_once('.')?;
+    let index = index.parse().ok()?;
+    let session = stem.strip_suffix(LOG_SUFFIX)?;
+    (!session.is_empty()).then(|| (session.to_string(), Some(index)))
+}
+
+fn rotated_path(log_path: &Path, index: usize) -> PathBuf {
+    let mut name = log_path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+/// All session logs in `dir`, grouped by session with the live log first.
+pub fn list_session_logs(dir: &Path) -> Result<Vec<SessionLogFile>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+
+    let mut logs = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some((session, rotation)) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_log_file_name)
+        else {
+            continue;
+        };
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified_unix_secs = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs());
+        logs.push(SessionLogFile {
+            session,
+            path,
+            size_bytes: metadata.len(),
+            modified_unix_secs,
+            rotation,
+        });
+    }
+    logs.sort_by(|a, b| {
+        a.session
+            .cmp(&b.session)
+            .then(a.rotation.unwrap_or(0).cmp(&b.rotation.unwrap_or(0)))
+    });
+    Ok(logs)
+}
+
+/// Rotate with copy-and-truncate: tmux keeps appending to the same open file, so renaming
+/// it away would not stop it growing.
+fn rotate(log_path: &Path, max_files: usize) -> Result<()> {
+    if max_files > 0 {
+        for index in (1..max_files).rev() {
+            let from = rotated_path(log_path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(log_path, index + 1))
+                    .with_context(|| format!("failed to rotate {}", from.display()))?;
+            }
+        }
+        fs::copy(log_path, rotated_path(log_path, 1))
+            .with_context(|| format!("failed to rotate {}", log_path.display()))?;
+    }
+    truncate(log_path)
+}
+
+fn truncate(log_path: &Path) -> Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .open(log_path)
+        .and_then(|file| file.set_len(0))
+        .with_context(|| format!("failed to truncate {}", log_path.display()))
+}
+
+/// Apply the `[log]` size, age and file-count limits to every session log in `dir`. An expired
+/// live log is emptied rather than removed, as tmux may still have it open for appending.
+pub fn enforce_retention(dir: &Path, config: &LogConfig, now: SystemTime) -> Result<()> {
+    let now_secs = now
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    let max_age_secs = config.max_age_days.saturating_mul(SECS_PER_DAY);
+    let max_size_bytes = config.max_size_mb.saturating_mul(BYTES_PER_MB);
+
+    for log in list_session_logs(dir)? {
+        let expired = config.max_age_days > 0
+            && now_secs.saturating_sub(log.modified_unix_secs) > max_age_secs;
+        let surplus = log.rotation.is_some_and(|index| index > config.max_files);
+        if expired && log.rotation.is_none() {
+            if log.size_bytes > 0 {
+                truncate(&log.path)?;
+            }
+        } else if expired || surplus {
+            fs::remove_file(&log.path)
+                .with_context(|| format!("failed to remove {}", log.path.display()))?;
+        }
+    }
+
+    if config.max_size_mb > 0 {
+        for log in list_session_logs(dir)? {
+            if log.rotation.is_none() && log.size_bytes > max_size_bytes {
+                rotate(&log.path, config.max_files)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Delete session logs in `dir`, either for one session or all of them.
+pub fn purge_session_logs(dir: &Path, session: Option<&str>) -> Result<Vec<SessionLogFile>> {
+    let mut purged = Vec::new();
+    for log in list_session_logs(dir)? {
+        if session.is_some_and(|session| session != log.session) {
+            continue;
+        }
+        fs::remove_file(&log.path)
+            .with_context(|| format!("failed to remove {}", log.path.display()))?;
+        purged.push(log);
+    }
+    Ok(purged)
+}
+
+/// Length of `bytes` up to and including the last newline.
+pub fn complete_lines_len(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |index| index + 1)
+}
+
+/// Stream complete lines appended to `path` after byte `offset`, calling `on_line` for each
+/// until it returns `false`. If the file shrinks (e.g. it was rotated), reading restarts from
+/// the beginning.
+pub fn follow_lines(
+    path: &Path,
+    mut offset: u64,
+    mut on_line: impl FnMut(&str) -> bool,
+) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })
+    .context("failed to start file watcher")?;
+    watcher
+        .watch(path, RecursiveMode::NonRecursive)
+        .with_context(|| format!("failed to watch {}", path.display()))?;
+
+    let mut pending = Vec::new();
+    loop {
+        let len = fs::metadata(path)
+            .with_context(|| format!("failed to read {}", path.display()))?
+            .len();
+        if len < offset {
+            offset = 0;
+            pending.clear();
+        }
+        if len > offset {
+            let mut file =
+                File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+            file.seek(SeekFrom::Start(offset))?;
+            let read = file.take(len - offset).read_to_end(&mut pending)?;
+            offset += read as u64;
+
+            let complete = complete_lines_len(&pending);
+            let lines: Vec<u8> = pending.drain(..complete).collect();
+            for line in String::from_utf8_lossy(&lines).lines() {
+                if !on_line(line) {
+                    return Ok(());
+                }
+            }
+        }
+        let _ = receiver.recv_timeout(FOLLOW_POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_log(dir: &Path, name: &str, bytes: usize) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, vec![b'x'; bytes]).unwrap();
+        path
+    }
+
+    fn config(max_size_mb: u64, max_age_days: u64, max_files: usize) -> LogConfig {
+        LogConfig {
+            max_size_mb,
+            max_age_days,
+            max_files,
+        }
+    }
+
+    fn follow_in_background(
+        path: &Path,
+        offset: u64,
+        count: usize,
+    ) -> std::thread::JoinHandle<Vec<String>> {
+        let path = path.to_path_buf();
+        std::thread::spawn(move || {
+            let mut lines = Vec::new();
+            follow_lines(&path, offset, |line| {
+                lines.push(line.to_string());
+                lines.len() < count
+            })
+            .unwrap();
+            lines
+        })
+    }
+
+    fn append(path: &Path, content: &str) {
+        use std::io::Write;
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn follow_streams_appended_complete_lines() {
+        let tmp = tempdir().unwrap();
+        let log = write_log(tmp.path(), "s.log", 0);
+        append(&log, "old\n");
+
+        let handle = follow_in_background(&log, 4, 2);
+        append(&log, "first\nsec");
+        std::thread::sleep(Duration::from_millis(50));
+        append(&log, "ond\n");
+
+        assert_eq!(handle.join().unwrap(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn follow_restarts_after_truncation() {
+        let tmp = tempdir().unwrap();
+        let log = write_log(tmp.path(), "s.log", 0);
+        append(&log, "before rotation\n");
+
+        let handle = follow_in_background(&log, 16, 1);
+        std::thread::sleep(Duration::from_millis(50));
+        fs::write(&log, "").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        append(&log, "after\n");
+
+        assert_eq!(handle.join().unwrap(), vec!["after"]);
+    }
+
+    #[test]
+    fn complete_lines_len_excludes_partial_line() {
+        assert_eq!(complete_lines_len(b"a\nb\npart"), 4);
+        assert_eq!(complete_lines_len(b"a\n"), 2);
+        assert_eq!(complete_lines_len(b"partial"), 0);
+    }
+
+    #[test]
+    fn parses_live_and_rotated_names() {
+        assert_eq!(
+            parse_log_file_name("repo--feat.log"),
+            Some(("repo--feat".to_string(), None))
+        );
+        assert_eq!(
+            parse_log_file_name("repo.log.2"),
+            Some(("repo".to_string(), Some(2)))
+        );
+        assert_eq!(parse_log_file_name(".log"), None);
+        assert_eq!(parse_log_file_name("notes.txt"), None);
+        assert_eq!(parse_log_file_name("repo.log.bak"), None);
+    }
+
+    #[test]
+    fn list_groups_rotations_after_live_log() {
+        let tmp = tempdir().unwrap();
+        write_log(tmp.path(), "b.log", 1);
+        write_log(tmp.path(), "a.log.1", 2);
+        write_log(tmp.path(), "a.log", 3);
+        write_log(tmp.path(), "unrelated.txt", 4);
+
+        let logs = list_session_logs(tmp.path()).unwrap();
+        let names: Vec<_> = logs
+            .iter()
+            .map(|log| (log.session.as_str(), log.rotation, log.size_bytes))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("a", None, 3), ("a", Some(1), 2), ("b", None, 1)]
+        );
+    }
+
+    #[test]
+    fn oversized_log_is_copied_and_truncated() {
+        let tmp = tempdir().unwrap();
+        let live = write_log(tmp.path(), "s.log", 2 * 1024 * 1024);
+        write_log(tmp.path(), "s.log.1", 10);
+
+        enforce_retention(tmp.path(), &config(1, 0, 2), SystemTime::now()).unwrap();
+
+        assert_eq!(fs::metadata(&live).unwrap().len(), 0);
+        assert_eq!(
+            fs::metadata(tmp.path().join("s.log.1")).unwrap().len(),
+            2 * 1024 * 1024
+        );
+        assert_eq!(fs::metadata(tmp.path().join("s.log.2")).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn surplus_rotations_are_removed() {
+        let tmp = tempdir().unwrap();
+        write_log(tmp.path(), "s.log", 1);
+        write_log(tmp.path(), "s.log.1", 1);
+        write_log(tmp.path(), "s.log.2", 1);
+
+        enforce_retention(tmp.path(), &config(0, 0, 1), SystemTime::now()).unwrap();
+
+        assert!(tmp.path().join("s.log.1").exists());
+        assert!(!tmp.path().join("s.log.2").exists());
+    }
+
+    #[test]
+    fn stale_logs_are_removed_and_the_live_one_emptied() {
+        let tmp = tempdir().unwrap();
+        let live = write_log(tmp.path(), "s.log", 1);
+        let rotated = write_log(tmp.path(), "s.log.1", 1);
+        let later = SystemTime::now() + Duration::from_secs(3 * SECS_PER_DAY);
+
+        enforce_retention(tmp.path(), &config(0, 2, 3), later).unwrap();
+        assert_eq!(fs::metadata(&live).unwrap().len(), 0);
+        assert!(!rotated.exists());
+    }
+
+    #[test]
+    fn purge_can_target_one_session() {
+        let tmp = tempdir().unwrap();
+        write_log(tmp.path(), "a.log", 1);
+        write_log(tmp.path(), "a.log.1", 1);
+        write_log(tmp.path(), "b.log", 1);
+
+        let purged = purge_session_logs(tmp.path(), Some("a")).unwrap();
+
+        assert_eq!(purged.len(), 2);
+        assert!(tmp.path().join("b.log").exists());
+        assert!(purge_session_logs(tmp.path(), None).unwrap().len() == 1);
+        assert!(list_session_logs(tmp.path()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+/// A `--template` such as `{name}\t{path}`, rendered once per output row.
+///
+/// `{field}` interpolates a field of the row (`{a.b}` reaches into nested objects),
+/// `{{`/`}}` produce literal braces, and `\t`, `\n` and `\\` are unescaped so the
+/// template can be passed in single quotes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Vec<String>),
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(ch) => name.push(ch),
+                            None => return Err(format!("unclosed '{{' in template '{template}'")),
+                        }
+                    }
+                    let name = name.trim();
+                    if name.is_empty() || name.split('.').any(str::is_empty) {
+                        return Err(format!("invalid field '{{{name}}}' in template"));
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(
+                        name.split('.').map(ToString::to_string).collect(),
+                    ));
+                }
+                '}' => {
+                    return Err(format!(
+                        "unmatched '}}' in template '{template}' (use '}}}}')"
+                    ));
+                }
+                '\\' => match chars.peek() {
+                    Some('t') => {
+                        chars.next();
+                        literal.push('\t');
+                    }
+                    Some('n') => {
+                        chars.next();
+                        literal.push('\n');
+                    }
+                    Some('\\') => {
+                        chars.next();
+                        literal.push('\\');
+                    }
+                    _ => literal.push('\\'),
+                },
+                ch => literal.push(ch),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self { segments })
+    }
+}
+
+impl Template {
+    /// Render one row, followed by a newline. Unknown fields are an error so typos don't
+    /// silently produce empty columns; `null` renders as an empty string.
+    pub fn render(&self, row: &Map<String, Value>) -> Result<String, String> {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Field(path) => {
+                    let value = lookup(row, path).ok_or_else(|| {
+                        let available: Vec<&str> = row.keys().map(String::as_str).collect();
+                        format!(
+                            "unknown field '{}' in template. Available: {}",
+                            path.join("."),
+                            available.join(", ")
+                        )
+                    })?;
+                    match value {
+                        Value::Null => {}
+                        Value::String(text) => out.push_str(text),
+                        other => out.push_str(&other.to_string()),
+                    }
+                }
+            }
+        }
+        out.push('\n');
+        Ok(out)
+    }
+}
+
+fn lookup<'a>(row: &'a Map<String, Value>, path: &[String]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(row.get(first)?, |value, key| value.as_object()?.get(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(value: &Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn interpolates_fields_and_unescapes_separators() {
+        let template: Template = r"{name}\t{path}".parse().unwrap();
+        let rendered = template
+            .render(&row(&json!({ "name": "kiosk", "path": "/src/kiosk" })))
+            .unwrap();
+        assert_eq!(rendered, "kiosk\t/src/kiosk\n");
+    }
+
+    #[test]
+    fn renders_nested_fields_null_and_non_string_values() {
+        let template: Template = "{name} {session.attached} {branch} {ahead}"
+            .parse()
+            .unwrap();
+        let rendered = template
+            .render(&row(&json!({
+                "name": "feat",
+                "session": { "attached": true },
+                "branch": null,
+                "ahead": 3,
+            })))
+            .unwrap();
+        assert_eq!(rendered, "feat true  3\n");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        let template: Template = "{{{name}}}".parse().unwrap();
+        assert_eq!(
+            template.render(&row(&json!({ "name": "x" }))).unwrap(),
+            "{x}\n"
+        );
+    }
+
+    #[test]
+    fn unknown_field_lists_available_fields() {
+        let template: Template = "{nmae}".parse().unwrap();
+        let error = template
+            .render(&row(&json!({ "name": "x", "path": "/x" })))
+            .unwrap_err();
+        assert!(error.contains("unknown field 'nmae'"), "{error}");
+        assert!(error.contains("Available: name, path"), "{error}");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        assert!("{name".parse::<Template>().is_err());
+        assert!("name}".parse::<Template>().is_err());
+        assert!("{}".parse::<Template>().is_err());
+        assert!("{a..b}".parse::<Template>().is_err());
+    }
+}
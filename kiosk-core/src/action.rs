@@ -1,3 +1,5 @@
+use crate::config::keys::Command;
+
 /// Every user interaction produces an Action. UI never directly calls git/tmux.
 #[derive(Debug, Clone)]
 pub enum Action {
@@ -5,6 +7,8 @@ pub enum Action {
     OpenRepo,
     EnterRepo,
     OpenBranch,
+    OpenInEditor,
+    OpenWithAgent,
     GoBack,
     Quit,
 
@@ -25,6 +29,8 @@ pub enum Action {
     PageDown,
     MoveTop,
     MoveBottom,
+    /// Select an entry of the active list by its position in the filtered list (a click)
+    SelectRow(usize),
 
     // Cursor movement (for search input)
     CursorLeft,
@@ -35,11 +41,46 @@ pub enum Action {
     CursorEnd,
 
     // UI
+    TogglePin,
+    HideRepo,
     StartNewBranchFlow,
+    StartGlobalSearch,
+    ShowTags,
+    ShowWorkspaces,
+    AddToWorkspace,
+    /// Open the picked workspace, or add the branch to it
+    ConfirmWorkspace,
+    StartCloneFlow,
+    ConfirmClone,
+    StartNewRepoFlow,
+    ConfirmNewRepo,
     DeleteWorktree,
     ConfirmDeleteWorktree,
     CancelDeleteWorktree,
+    UndoDelete,
+    ConfirmResume,
+    CancelResume,
+    /// Finish the interrupted worktree operations
+    FinishInterrupted,
+    /// Undo the interrupted worktree operations
+    UndoInterrupted,
+    FetchRemotes,
+    EditNote,
+    SaveNote,
+    SaveBranchNameField,
+    TogglePreview,
+    /// Widen the preview pane by this many percent, or narrow it if negative
+    ResizePreview(i16),
     ShowHelp,
+    ShowCommandPalette,
+    /// Run a chained key binding's commands in order
+    RunCommands(Vec<Command>),
+    /// Call a function from `[script]` on the selected repo or branch
+    RunScript(String),
+    /// A digit of a count typed before a movement, vim-style
+    CountDigit(u32),
+    /// Run the command selected in the command palette
+    RunPaletteCommand,
 
     // Setup
     SetupContinue,
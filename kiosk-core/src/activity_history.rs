@@ -0,0 +1,144 @@
+use crate::{
+    paths::state_dir,
+    pending_delete::now_unix_secs,
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+
+const ACTIVITY_HISTORY_FILE_NAME: &str = "activity.toml";
+const ACTIVITY_HISTORY_STATE_VERSION: u32 = 1;
+/// How many activity timestamps are kept per session.
+const MAX_ENTRIES: usize = 16;
+/// Activity older than this is dropped, along with sessions that had none since.
+pub const HISTORY_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ActivityRecord {
+    session: String,
+    activity_unix_secs: Vec<u64>,
+}
+
+/// Recent activity timestamps of each tmux session, as seen each time kiosk loaded them,
+/// backing the activity heat in the TUI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivityHistoryStore {
+    path: PathBuf,
+}
+
+impl Default for ActivityHistoryStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(ACTIVITY_HISTORY_FILE_NAME))
+    }
+}
+
+impl ActivityHistoryStore {
+    /// Use history stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Each session's activity timestamps (Unix seconds), oldest first.
+    pub fn load(&self) -> HashMap<String, Vec<u64>> {
+        load_state_file::<ActivityRecord>(&self.path, ACTIVITY_HISTORY_STATE_VERSION)
+            .into_iter()
+            .map(|record| (record.session, record.activity_unix_secs))
+            .collect()
+    }
+
+    /// Add each session's last activity from `session_activity` to its history, unless it's
+    /// already there. Returns the updated history.
+    #[allow(clippy::implicit_hasher)]
+    pub fn record(
+        &self,
+        session_activity: &HashMap<String, u64>,
+    ) -> Result<HashMap<String, Vec<u64>>> {
+        let cutoff = now_unix_secs().saturating_sub(HISTORY_WINDOW_SECS);
+        let mut history = HashMap::new();
+        update_state_file(
+            &self.path,
+            ACTIVITY_HISTORY_STATE_VERSION,
+            |records: &mut Vec<ActivityRecord>| {
+                for (session, &activity) in session_activity {
+                    match records.iter_mut().find(|record| &record.session == session) {
+                        Some(record) => {
+                            if record.activity_unix_secs.last() < Some(&activity) {
+                                record.activity_unix_secs.push(activity);
+                            }
+                        }
+                        None => records.push(ActivityRecord {
+                            session: session.clone(),
+                            activity_unix_secs: vec![activity],
+                        }),
+                    }
+                }
+                for record in records.iter_mut() {
+                    record
+                        .activity_unix_secs
+                        .retain(|&activity| activity > cutoff);
+                    let excess = record.activity_unix_secs.len().saturating_sub(MAX_ENTRIES);
+                    record.activity_unix_secs.drain(..excess);
+                }
+                records.retain(|record| !record.activity_unix_secs.is_empty());
+                history = records
+                    .iter()
+                    .map(|record| (record.session.clone(), record.activity_unix_secs.clone()))
+                    .collect();
+            },
+        )?;
+        Ok(history)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn record_adds_new_activity_once_and_keeps_the_latest() {
+        let tmp = tempdir().unwrap();
+        let store = ActivityHistoryStore::at(tmp.path().join("activity.toml"));
+        let now = now_unix_secs();
+
+        store
+            .record(&HashMap::from([("a".to_string(), now - 100)]))
+            .unwrap();
+        store
+            .record(&HashMap::from([("a".to_string(), now - 100)]))
+            .unwrap();
+        let history = store
+            .record(&HashMap::from([("a".to_string(), now - 50)]))
+            .unwrap();
+        assert_eq!(history["a"], vec![now - 100, now - 50]);
+
+        for offset in 0..MAX_ENTRIES as u64 {
+            store
+                .record(&HashMap::from([("a".to_string(), now - 40 + offset)]))
+                .unwrap();
+        }
+        let history = store.load();
+        assert_eq!(history["a"].len(), MAX_ENTRIES);
+        assert_eq!(
+            history["a"].last(),
+            Some(&(now - 40 + MAX_ENTRIES as u64 - 1))
+        );
+    }
+
+    #[test]
+    fn record_forgets_activity_outside_the_window() {
+        let tmp = tempdir().unwrap();
+        let store = ActivityHistoryStore::at(tmp.path().join("activity.toml"));
+        let now = now_unix_secs();
+
+        let history = store
+            .record(&HashMap::from([
+                ("old".to_string(), now - HISTORY_WINDOW_SECS - 1),
+                ("new".to_string(), now),
+            ]))
+            .unwrap();
+        assert_eq!(history, HashMap::from([("new".to_string(), vec![now])]));
+        assert_eq!(store.load(), history);
+    }
+}
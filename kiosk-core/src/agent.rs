@@ -0,0 +1,310 @@
+//! Detection of coding agents running in tmux sessions.
+
+use crate::tmux::TmuxProvider;
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Foreground commands recognised as coding agents.
+pub const KNOWN_AGENTS: &[&str] = &[
+    "aider",
+    "amp",
+    "claude",
+    "codex",
+    "cursor-agent",
+    "gemini",
+    "goose",
+    "opencode",
+];
+
+/// Keys answering each agent's permission prompt: `(agent, approve, deny)`.
+const PROMPT_KEYS: &[(&str, &[&str], &[&str])] = &[
+    ("aider", &["y", "Enter"], &["n", "Enter"]),
+    ("claude", &["Enter"], &["Escape"]),
+    ("codex", &["y"], &["n"]),
+    ("gemini", &["Enter"], &["Escape"]),
+    ("opencode", &["Enter"], &["Escape"]),
+];
+
+/// What each agent's permission prompt shows, as a regex: `(agent, pattern)`. Answering is
+/// refused unless the prompt is on screen, since the keys would otherwise submit or type into
+/// the agent's input.
+const PROMPT_PATTERNS: &[(&str, &str)] = &[
+    ("aider", r"\(Y\)es/\(N\)o"),
+    ("claude", r"Do you want to (proceed|make this edit|create)"),
+    (
+        "codex",
+        r"Would you like to (run the following command|make the following edits)|Allow command\?",
+    ),
+    (
+        "gemini",
+        r"Allow execution|Apply this change\?|Do you want to proceed\?",
+    ),
+    ("opencode", r"Permission required|Allow once"),
+];
+
+/// Agents that stop what they're doing on Escape rather than Ctrl-C, which would quit them.
+const ESCAPE_INTERRUPTS: &[&str] = &["amp", "claude", "codex", "gemini", "opencode"];
+
+/// Whether a pane's foreground command (as reported by `#{pane_current_command}`) is an agent.
+pub fn is_agent_command(command: &str) -> bool {
+    KNOWN_AGENTS.contains(&command_name(command).as_str())
+}
+
+/// The tmux keys that stop what `command` is doing without quitting it: Escape for agents
+/// that cancel on it, Ctrl-C for other agents and everything else.
+pub fn interrupt_keys(command: &str) -> &'static [&'static str] {
+    if ESCAPE_INTERRUPTS.contains(&command_name(command).as_str()) {
+        &["Escape"]
+    } else {
+        &["C-c"]
+    }
+}
+
+/// An answer to an agent asking permission, e.g. to run a command or edit a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptAnswer {
+    Approve,
+    Deny,
+}
+
+/// The tmux keys giving `answer` to the permission prompt of the agent running as `command`,
+/// or `None` for agents whose prompts kiosk doesn't know.
+pub fn answer_keys(command: &str, answer: PromptAnswer) -> Option<&'static [&'static str]> {
+    let name = command_name(command);
+    PROMPT_KEYS
+        .iter()
+        .find(|(agent, _, _)| *agent == name)
+        .map(|(_, approve, deny)| match answer {
+            PromptAnswer::Approve => *approve,
+            PromptAnswer::Deny => *deny,
+        })
+}
+
+/// Whether `screen`, the visible contents of the pane running `command`, shows the agent's
+/// permission prompt. Always false for agents whose prompts kiosk doesn't know.
+pub fn shows_prompt(command: &str, screen: &str) -> bool {
+    let name = command_name(command);
+    PROMPT_PATTERNS
+        .iter()
+        .find(|(agent, _)| *agent == name)
+        .is_some_and(|(_, pattern)| {
+            Regex::new(pattern).is_ok_and(|pattern| pattern.is_match(screen))
+        })
+}
+
+fn command_name(command: &str) -> String {
+    Path::new(command.trim())
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+/// The agent running in any pane of `session`, if one is found.
+pub fn session_agent<T: TmuxProvider + ?Sized>(tmux: &T, session: &str) -> Option<String> {
+    session_agent_pane(tmux, session).map(|(_, agent)| agent)
+}
+
+/// The first pane of `session` running an agent, and the agent.
+pub fn session_agent_pane<T: TmuxProvider + ?Sized>(
+    tmux: &T,
+    session: &str,
+) -> Option<(usize, String)> {
+    let panes = tmux.pane_count(session).unwrap_or(1);
+    (0..panes)
+        .filter_map(|pane| {
+            tmux.pane_current_command(session, &pane.to_string())
+                .ok()
+                .map(|command| (pane, command))
+        })
+        .find(|(_, command)| is_agent_command(command))
+}
+
+/// A pane by its session, index and process, so that a pane replaced by another at the same
+/// index, e.g. in a session closed and reopened under the same name, isn't mistaken for it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PaneKey {
+    session: String,
+    index: usize,
+    pid: u32,
+}
+
+/// Whether a pane was running an agent, and when that was checked.
+#[derive(Debug, Clone)]
+struct CheckedPane {
+    agent: Option<String>,
+    checked_at: Instant,
+}
+
+/// Agents recently found in panes, kept for as long as the TUI runs so that reloading a
+/// repo's branches a few seconds apart doesn't ask tmux about every pane again.
+#[derive(Debug, Default)]
+pub struct AgentCache {
+    ttl: Duration,
+    panes: Mutex<HashMap<PaneKey, CheckedPane>>,
+}
+
+impl AgentCache {
+    /// A cache reusing what it found in a pane for `ttl_secs` seconds. With 0 it always
+    /// looks again.
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            ttl: Duration::from_secs(ttl_secs),
+            panes: Mutex::default(),
+        }
+    }
+
+    /// [`session_agent_pane`], asking tmux only about panes not checked within the TTL.
+    pub fn session_agent_pane<T: TmuxProvider + ?Sized>(
+        &self,
+        tmux: &T,
+        session: &str,
+    ) -> Option<(usize, String)> {
+        self.session_agent_pane_at(tmux, session, Instant::now())
+    }
+
+    fn session_agent_pane_at<T: TmuxProvider + ?Sized>(
+        &self,
+        tmux: &T,
+        session: &str,
+        now: Instant,
+    ) -> Option<(usize, String)> {
+        if self.ttl.is_zero() {
+            return session_agent_pane(tmux, session);
+        }
+        let Ok(panes) = tmux.list_panes_detailed(session) else {
+            return session_agent_pane(tmux, session);
+        };
+        let mut cache = self.panes.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.retain(|_, checked| now.saturating_duration_since(checked.checked_at) < self.ttl);
+        panes.into_iter().find_map(|pane| {
+            let key = PaneKey {
+                session: session.to_string(),
+                index: pane.index,
+                pid: pane.pid,
+            };
+            let checked = cache.entry(key).or_insert_with(|| CheckedPane {
+                agent: tmux
+                    .pane_current_command(session, &pane.index.to_string())
+                    .ok()
+                    .filter(|command| is_agent_command(command)),
+                checked_at: now,
+            });
+            checked.agent.clone().map(|agent| (pane.index, agent))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::sessions_running, tmux::mock::MockTmuxProvider};
+
+    #[test]
+    fn recognises_agents_by_command_name() {
+        assert!(is_agent_command("claude"));
+        assert!(is_agent_command("/usr/local/bin/codex"));
+        assert!(!is_agent_command("zsh"));
+        assert!(!is_agent_command("claude-helper"));
+    }
+
+    #[test]
+    fn interrupts_each_agent_with_its_cancel_key() {
+        assert_eq!(interrupt_keys("claude"), ["Escape"]);
+        assert_eq!(interrupt_keys("/opt/bin/codex"), ["Escape"]);
+        assert_eq!(interrupt_keys("aider"), ["C-c"]);
+        assert_eq!(interrupt_keys("cargo"), ["C-c"]);
+    }
+
+    #[test]
+    fn recognises_each_answerable_agents_prompt() {
+        for (agent, _, _) in PROMPT_KEYS {
+            let (_, pattern) = PROMPT_PATTERNS.iter().find(|(a, _)| a == agent).unwrap();
+            assert!(Regex::new(pattern).is_ok(), "{agent}: {pattern}");
+        }
+        let claude = "│ Bash command\n│ Do you want to proceed?\n│ ❯ 1. Yes\n";
+        assert!(shows_prompt("claude", claude));
+        assert!(!shows_prompt("claude", "> \n  ? for shortcuts"));
+        assert!(shows_prompt(
+            "aider",
+            "Run shell command? (Y)es/(N)o [Yes]:"
+        ));
+        assert!(!shows_prompt("goose", claude));
+    }
+
+    #[test]
+    fn answers_each_agents_prompts_with_its_keys() {
+        assert_eq!(
+            answer_keys("claude", PromptAnswer::Approve),
+            Some(&["Enter"][..])
+        );
+        assert_eq!(answer_keys("codex", PromptAnswer::Deny), Some(&["n"][..]));
+        assert_eq!(
+            answer_keys("aider", PromptAnswer::Approve),
+            Some(&["y", "Enter"][..])
+        );
+        assert_eq!(answer_keys("zsh", PromptAnswer::Approve), None);
+    }
+
+    #[test]
+    fn finds_agent_in_session() {
+        let tmux = sessions_running([("app", "zsh"), ("app--feat", "claude")]);
+        assert_eq!(session_agent(&tmux, "app--feat").as_deref(), Some("claude"));
+        assert_eq!(session_agent(&tmux, "app"), None);
+    }
+
+    #[test]
+    fn cache_reuses_recent_lookups_of_the_same_pane() {
+        let cache = AgentCache::new(3);
+        let now = Instant::now();
+        let claude = Some((0, "claude".to_string()));
+
+        let running = sessions_running([("app", "claude"), ("api", "zsh")]);
+        assert_eq!(cache.session_agent_pane_at(&running, "app", now), claude);
+        assert_eq!(cache.session_agent_pane_at(&running, "api", now), None);
+
+        // Within the TTL the same panes aren't asked about again
+        let later = now + Duration::from_secs(2);
+        let exited = sessions_running([("app", "zsh"), ("api", "codex")]);
+        assert_eq!(cache.session_agent_pane_at(&exited, "app", later), claude);
+        assert_eq!(cache.session_agent_pane_at(&exited, "api", later), None);
+        assert_eq!(
+            cache.session_agent_pane_at(&exited, "app", now + Duration::from_secs(3)),
+            None
+        );
+        assert_eq!(
+            AgentCache::new(0).session_agent_pane_at(&exited, "api", now),
+            Some((0, "codex".to_string()))
+        );
+    }
+
+    #[test]
+    fn cache_checks_a_replaced_pane_again() {
+        let cache = AgentCache::new(3);
+        let now = Instant::now();
+        let running = sessions_running([("app", "claude")]);
+        assert_eq!(
+            cache.session_agent_pane_at(&running, "app", now),
+            Some((0, "claude".to_string()))
+        );
+
+        // The session reopened under the same name, so its pane has another process
+        let mut reopened = MockTmuxProvider::default()
+            .with_session("app")
+            .with_panes("app", &[("zsh", "")]);
+        reopened.panes.get_mut("app").unwrap()[0].pid = 2000;
+        assert_eq!(cache.session_agent_pane_at(&reopened, "app", now), None);
+    }
+
+    #[test]
+    fn finds_the_pane_the_agent_is_in() {
+        let tmux = sessions_running([("app", "claude")]);
+        assert_eq!(
+            session_agent_pane(&tmux, "app"),
+            Some((0, "claude".to_string()))
+        );
+    }
+}
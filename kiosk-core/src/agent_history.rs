@@ -0,0 +1,341 @@
+//! How long the agents in kiosk sessions spent working, waiting for input and idle, sampled by
+//! `kiosk agents record` and summed up by `kiosk agents report`.
+
+use crate::{
+    paths::state_dir,
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+
+const AGENT_HISTORY_FILE_NAME: &str = "agent_history.toml";
+const AGENT_HISTORY_STATE_VERSION: u32 = 1;
+/// Spans that ended longer ago than this are dropped when recording.
+const AGENT_HISTORY_RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// What a session's agent was doing when sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentActivity {
+    /// The session had recent activity
+    Working,
+    /// Quiet for a while, so most likely waiting for input
+    Waiting,
+    /// No agent running in a session that had one
+    Idle,
+}
+
+/// One session as `kiosk agents record` found it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentSample {
+    pub session: String,
+    pub repo: String,
+    pub branch: Option<String>,
+    /// `None` when no agent is running
+    pub agent: Option<String>,
+    pub activity: AgentActivity,
+}
+
+/// A stretch of time a session's agent spent doing one thing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentSpan {
+    pub session: String,
+    pub repo: String,
+    pub branch: Option<String>,
+    pub agent: Option<String>,
+    pub activity: AgentActivity,
+    pub start_unix_secs: u64,
+    pub end_unix_secs: u64,
+    /// Whether the `[agent]` hook for this span has run
+    #[serde(default)]
+    pub notified: bool,
+}
+
+impl AgentSpan {
+    /// Seconds of this span that fall between `since` and `until`.
+    fn overlap(&self, since: u64, until: u64) -> u64 {
+        self.end_unix_secs
+            .min(until)
+            .saturating_sub(self.start_unix_secs.max(since))
+    }
+}
+
+/// Time a session's agent spent in each state over a report's window.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AgentTime {
+    pub session: String,
+    pub repo: String,
+    pub branch: Option<String>,
+    pub working_secs: u64,
+    pub waiting_secs: u64,
+    pub idle_secs: u64,
+}
+
+/// Spans recorded by `kiosk agents record`, backing `kiosk agents report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentHistoryStore {
+    path: PathBuf,
+}
+
+impl Default for AgentHistoryStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(AGENT_HISTORY_FILE_NAME))
+    }
+}
+
+impl AgentHistoryStore {
+    /// Use history stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn load(&self) -> Vec<AgentSpan> {
+        load_state_file(&self.path, AGENT_HISTORY_STATE_VERSION)
+    }
+
+    /// Record `samples` taken at `now`. A sample carries on its session's last span when it's
+    /// within `max_gap_secs` of it, extending the span if nothing changed and starting a new
+    /// one where it ended otherwise; after a longer gap the new span starts at `now`. Sessions
+    /// are only tracked once an agent has been seen in them.
+    pub fn record(&self, samples: &[AgentSample], now: u64, max_gap_secs: u64) -> Result<()> {
+        update_state_file(
+            &self.path,
+            AGENT_HISTORY_STATE_VERSION,
+            |spans: &mut Vec<AgentSpan>| {
+                spans.retain(|span| span.end_unix_secs + AGENT_HISTORY_RETENTION_SECS >= now);
+                for sample in samples {
+                    let last = spans
+                        .iter_mut()
+                        .filter(|span| span.session == sample.session)
+                        .max_by_key(|span| span.end_unix_secs);
+                    let (start, changed) = match last {
+                        None if sample.agent.is_none() => continue,
+                        None => (now, true),
+                        Some(last) if now.saturating_sub(last.end_unix_secs) > max_gap_secs => {
+                            (now, true)
+                        }
+                        Some(last) => {
+                            if last.activity == sample.activity && last.agent == sample.agent {
+                                last.end_unix_secs = now;
+                                (now, false)
+                            } else {
+                                (last.end_unix_secs, true)
+                            }
+                        }
+                    };
+                    if changed {
+                        spans.push(AgentSpan {
+                            session: sample.session.clone(),
+                            repo: sample.repo.clone(),
+                            branch: sample.branch.clone(),
+                            agent: sample.agent.clone(),
+                            activity: sample.activity,
+                            start_unix_secs: start,
+                            end_unix_secs: now,
+                            notified: false,
+                        });
+                    }
+                }
+            },
+        )
+    }
+
+    /// The spans each session is in now that are one of `activities` and have lasted at
+    /// least `min_secs`, marked as notified so they're only returned once.
+    pub fn take_due(&self, activities: &[AgentActivity], min_secs: u64) -> Result<Vec<AgentSpan>> {
+        update_state_file(
+            &self.path,
+            AGENT_HISTORY_STATE_VERSION,
+            |spans: &mut Vec<AgentSpan>| {
+                let mut latest: BTreeMap<&str, usize> = BTreeMap::new();
+                for (index, span) in spans.iter().enumerate() {
+                    let entry = latest.entry(&span.session).or_insert(index);
+                    if span.end_unix_secs >= spans[*entry].end_unix_secs {
+                        *entry = index;
+                    }
+                }
+                let mut due: Vec<usize> = latest
+                    .into_values()
+                    .filter(|&index| {
+                        let span = &spans[index];
+                        !span.notified
+                            && activities.contains(&span.activity)
+                            && span.end_unix_secs - span.start_unix_secs >= min_secs
+                    })
+                    .collect();
+                due.sort_unstable();
+                for &index in &due {
+                    spans[index].notified = true;
+                }
+                due.into_iter().map(|index| spans[index].clone()).collect()
+            },
+        )
+    }
+}
+
+/// Time spent in each state by every session with spans between `since` and `until`, named
+/// after its latest span and sorted by session.
+pub fn summarise(spans: &[AgentSpan], since: u64, until: u64) -> Vec<AgentTime> {
+    let mut times: BTreeMap<&str, (u64, AgentTime)> = BTreeMap::new();
+    for span in spans {
+        let secs = span.overlap(since, until);
+        if secs == 0 {
+            continue;
+        }
+        let (latest, time) = times.entry(&span.session).or_default();
+        if span.end_unix_secs >= *latest {
+            *latest = span.end_unix_secs;
+            time.session.clone_from(&span.session);
+            time.repo.clone_from(&span.repo);
+            time.branch.clone_from(&span.branch);
+        }
+        match span.activity {
+            AgentActivity::Working => time.working_secs += secs,
+            AgentActivity::Waiting => time.waiting_secs += secs,
+            AgentActivity::Idle => time.idle_secs += secs,
+        }
+    }
+    times.into_values().map(|(_, time)| time).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sample(agent: Option<&str>, activity: AgentActivity) -> AgentSample {
+        AgentSample {
+            session: "demo--feat".to_string(),
+            repo: "demo".to_string(),
+            branch: Some("feat".to_string()),
+            agent: agent.map(ToString::to_string),
+            activity,
+        }
+    }
+
+    #[test]
+    fn samples_extend_spans_until_the_state_changes() {
+        let tmp = tempdir().unwrap();
+        let store = AgentHistoryStore::at(tmp.path().join("agent_history.toml"));
+        let now = 1_800_000_000;
+
+        store
+            .record(&[sample(None, AgentActivity::Idle)], now, 30)
+            .unwrap();
+        assert!(store.load().is_empty());
+
+        for (offset, activity) in [
+            (0, AgentActivity::Working),
+            (10, AgentActivity::Working),
+            (20, AgentActivity::Waiting),
+            (30, AgentActivity::Waiting),
+        ] {
+            store
+                .record(&[sample(Some("claude"), activity)], now + offset, 30)
+                .unwrap();
+        }
+        store
+            .record(&[sample(None, AgentActivity::Idle)], now + 40, 30)
+            .unwrap();
+        // After a long gap the time in between isn't counted
+        store
+            .record(&[sample(None, AgentActivity::Idle)], now + 500, 30)
+            .unwrap();
+
+        let spans: Vec<(AgentActivity, u64, u64)> = store
+            .load()
+            .into_iter()
+            .map(|span| (span.activity, span.start_unix_secs, span.end_unix_secs))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (AgentActivity::Working, now, now + 10),
+                (AgentActivity::Waiting, now + 10, now + 30),
+                (AgentActivity::Idle, now + 30, now + 40),
+                (AgentActivity::Idle, now + 500, now + 500),
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_counts_time_within_the_window() {
+        let span = |activity, start, end| AgentSpan {
+            session: "demo--feat".to_string(),
+            repo: "demo".to_string(),
+            branch: Some("feat".to_string()),
+            agent: Some("claude".to_string()),
+            activity,
+            start_unix_secs: start,
+            end_unix_secs: end,
+            notified: false,
+        };
+        let spans = [
+            span(AgentActivity::Working, 0, 100),
+            span(AgentActivity::Waiting, 100, 400),
+            span(AgentActivity::Idle, 400, 500),
+        ];
+
+        assert_eq!(
+            summarise(&spans, 50, 450),
+            [AgentTime {
+                session: "demo--feat".to_string(),
+                repo: "demo".to_string(),
+                branch: Some("feat".to_string()),
+                working_secs: 50,
+                waiting_secs: 300,
+                idle_secs: 50,
+            }]
+        );
+        assert!(summarise(&spans, 600, 700).is_empty());
+    }
+
+    #[test]
+    fn spans_are_due_once_they_last_long_enough() {
+        let tmp = tempdir().unwrap();
+        let store = AgentHistoryStore::at(tmp.path().join("agent_history.toml"));
+        let now = 1_800_000_000;
+        let waiting = [AgentActivity::Waiting];
+
+        store
+            .record(&[sample(Some("claude"), AgentActivity::Working)], now, 30)
+            .unwrap();
+        store
+            .record(
+                &[sample(Some("claude"), AgentActivity::Waiting)],
+                now + 10,
+                30,
+            )
+            .unwrap();
+        assert!(store.take_due(&waiting, 20).unwrap().is_empty());
+
+        store
+            .record(
+                &[sample(Some("claude"), AgentActivity::Waiting)],
+                now + 30,
+                30,
+            )
+            .unwrap();
+        let due = store.take_due(&waiting, 20).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].start_unix_secs, now);
+        assert!(store.take_due(&waiting, 20).unwrap().is_empty());
+
+        store
+            .record(
+                &[sample(Some("claude"), AgentActivity::Working)],
+                now + 40,
+                30,
+            )
+            .unwrap();
+        store
+            .record(
+                &[sample(Some("claude"), AgentActivity::Waiting)],
+                now + 70,
+                30,
+            )
+            .unwrap();
+        assert_eq!(store.take_due(&waiting, 20).unwrap().len(), 1);
+    }
+}
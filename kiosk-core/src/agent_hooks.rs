@@ -0,0 +1,168 @@
+//! The commands and webhooks in `[agent]`, run when a session's agent needs attention.
+
+use crate::{
+    agent_history::{AgentActivity, AgentSpan},
+    config::{AgentConfig, AgentHook},
+    process::CommandExt,
+    ticket::curl_quote,
+};
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use std::process::Command;
+
+/// Seconds a webhook gets to answer.
+const WEBHOOK_TIMEOUT_SECS: u32 = 10;
+
+/// The hook `config` runs when a session's agent starts doing `activity`.
+pub fn hook_for(config: &AgentConfig, activity: AgentActivity) -> Option<&AgentHook> {
+    match activity {
+        AgentActivity::Waiting => config.on_waiting.as_ref(),
+        AgentActivity::Idle => config.on_idle.as_ref(),
+        AgentActivity::Working => None,
+    }
+}
+
+/// The activities `config` has hooks for.
+pub fn hooked_activities(config: &AgentConfig) -> Vec<AgentActivity> {
+    [AgentActivity::Waiting, AgentActivity::Idle]
+        .into_iter()
+        .filter(|&activity| hook_for(config, activity).is_some())
+        .collect()
+}
+
+/// What hooks are told about `span`.
+pub fn payload(span: &AgentSpan) -> Value {
+    let place = span.branch.as_ref().map_or_else(
+        || span.repo.clone(),
+        |branch| format!("{}/{branch}", span.repo),
+    );
+    let agent = span.agent.as_deref().unwrap_or("The agent");
+    let text = match span.activity {
+        AgentActivity::Working => format!("{agent} in {place} is working"),
+        AgentActivity::Waiting => format!("{agent} in {place} is waiting for input"),
+        AgentActivity::Idle => format!("The agent in {place} has exited"),
+    };
+    json!({
+        "session": span.session,
+        "repo": span.repo,
+        "branch": span.branch,
+        "agent": span.agent,
+        "state": span.activity,
+        "since": span.start_unix_secs,
+        "text": text,
+    })
+}
+
+/// Run `hook`'s command and POST to its webhook for `span`, trying both even if one fails.
+pub fn run(hook: &AgentHook, span: &AgentSpan) -> Result<()> {
+    let payload = payload(span).to_string();
+    let exec = hook
+        .exec
+        .as_deref()
+        .map_or(Ok(()), |exec| run_command(exec, span, &payload));
+    let url = hook
+        .url
+        .as_deref()
+        .map_or(Ok(()), |url| post(url, &payload));
+    exec.and(url)
+}
+
+fn run_command(exec: &str, span: &AgentSpan, payload: &str) -> Result<()> {
+    let state = json!(span.activity);
+    let output = Command::new("sh")
+        .args(["-c", exec])
+        .env("KIOSK_SESSION", &span.session)
+        .env("KIOSK_REPO", &span.repo)
+        .env("KIOSK_BRANCH", span.branch.as_deref().unwrap_or_default())
+        .env("KIOSK_AGENT", span.agent.as_deref().unwrap_or_default())
+        .env("KIOSK_AGENT_STATE", state.as_str().unwrap_or_default())
+        .traced_output_with_stdin(payload.as_bytes())
+        .with_context(|| format!("failed to run '{exec}'"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("'{exec}' failed: {}", stderr.trim());
+    }
+    Ok(())
+}
+
+/// POST `payload` to `url` with `curl`. The request goes in on stdin so the URL, which for
+/// webhooks is usually a secret, stays out of the process list.
+fn post(url: &str, payload: &str) -> Result<()> {
+    let request = [
+        format!("max-time = {WEBHOOK_TIMEOUT_SECS}"),
+        format!("url = {}", curl_quote(url)),
+        format!("header = {}", curl_quote("Content-Type: application/json")),
+        format!("data = {}", curl_quote(payload)),
+        String::new(),
+    ]
+    .join("\n");
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--config", "-"])
+        .traced_output_with_stdin(request.as_bytes())
+        .context("failed to run curl")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("failed to POST to the webhook: {}", stderr.trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn waiting_span() -> AgentSpan {
+        AgentSpan {
+            session: "demo--feat".to_string(),
+            repo: "demo".to_string(),
+            branch: Some("feat".to_string()),
+            agent: Some("claude".to_string()),
+            activity: AgentActivity::Waiting,
+            start_unix_secs: 1_800_000_000,
+            end_unix_secs: 1_800_000_060,
+            notified: false,
+        }
+    }
+
+    #[test]
+    fn payload_describes_the_span() {
+        assert_eq!(
+            payload(&waiting_span()),
+            json!({
+                "session": "demo--feat",
+                "repo": "demo",
+                "branch": "feat",
+                "agent": "claude",
+                "state": "waiting",
+                "since": 1_800_000_000,
+                "text": "claude in demo/feat is waiting for input",
+            })
+        );
+    }
+
+    #[test]
+    fn exec_gets_the_event_on_stdin_and_in_its_environment() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("event");
+        let hook = AgentHook {
+            exec: Some(format!(
+                "cat > '{0}' && echo \" $KIOSK_AGENT $KIOSK_AGENT_STATE $KIOSK_BRANCH\" >> '{0}'",
+                out.display()
+            )),
+            url: None,
+        };
+
+        run(&hook, &waiting_span()).unwrap();
+        let written = fs::read_to_string(&out).unwrap();
+        assert!(written.starts_with(r#"{"session":"demo--feat""#));
+        assert!(written.ends_with(" claude waiting feat\n"));
+
+        let failing = AgentHook {
+            exec: Some("echo nope >&2; exit 3".to_string()),
+            url: None,
+        };
+        let error = run(&failing, &waiting_span()).unwrap_err();
+        assert_eq!(error.to_string(), "'echo nope >&2; exit 3' failed: nope");
+    }
+}
@@ -0,0 +1,776 @@
+//! An append-only record of everything kiosk changes: worktrees added and removed, branches
+//! deleted, sessions created and killed, and keys sent, with who asked and how it went.
+//!
+//! [`AuditedGit`] and [`AuditedTmux`] wrap a runtime's providers (see
+//! [`RuntimeBuilder::audit`](crate::runtime::RuntimeBuilder::audit)), so every command and the
+//! TUI are covered whichever providers they run on. Entries are appended to `audit.ndjson` in
+//! the state directory, one JSON object per line, readable only by the user. Once the file
+//! passes 1 MiB it's moved aside to `audit.ndjson.1`, replacing the previous one.
+
+use crate::{
+    config::{GitConfig, TmuxConfig},
+    git::{CommitInfo, FileChange, GitProvider, Repo, Tag, Worktree, WorktreeChanges},
+    paths::state_dir,
+    pending_delete::now_unix_secs,
+    state_store::lock,
+    tmux::{CaptureRange, PaneDetails, SessionDetails, TmuxEvent, TmuxProvider},
+};
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    fs::{self, File},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock, mpsc::Receiver},
+};
+
+const AUDIT_FILE_NAME: &str = "audit.ndjson";
+/// Size past which the audit file is moved aside, keeping between one and two of these of
+/// history across it and the file moved aside before.
+const MAX_AUDIT_FILE_BYTES: u64 = 1024 * 1024;
+/// Longest `detail` recorded, in characters; longer ones are cut short.
+const MAX_DETAIL_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    WorktreeCreated,
+    WorktreeRemoved,
+    /// Stale worktree metadata pruned, e.g. after a worktree was moved to the trash
+    WorktreesPruned,
+    BranchDeleted,
+    SessionCreated,
+    SessionKilled,
+    PaneKilled,
+    KeysSent,
+}
+
+/// The operation's name as it's stored, e.g. `worktree_removed`.
+impl fmt::Display for AuditOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.serialize(f)
+    }
+}
+
+/// One operation, as a line of the audit file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEntry {
+    pub at_unix_secs: u64,
+    pub operation: AuditOperation,
+    /// The kiosk process that ran it
+    pub pid: u32,
+    /// The kiosk command line, e.g. `["kiosk", "delete", "app", "feat"]`
+    pub command: Vec<String>,
+    /// The command line of the process that ran kiosk, e.g. an agent's script
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// The worktree, or the directory a session was created in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pane: Option<String>,
+    /// Anything else the operation was given, e.g. the keys sent or the base of a new branch,
+    /// cut short past 200 characters. Pasted text is recorded by its size alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Why the operation failed; `None` when it succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    fn new(operation: AuditOperation) -> Self {
+        Self {
+            at_unix_secs: now_unix_secs(),
+            operation,
+            pid: std::process::id(),
+            command: std::env::args().collect(),
+            parent: None,
+            repo: None,
+            branch: None,
+            path: None,
+            session: None,
+            pane: None,
+            detail: None,
+            error: None,
+        }
+    }
+}
+
+/// The audit file, shared by the audited providers of a runtime.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    /// Size past which the file is moved aside
+    max_bytes: u64,
+    /// Looked up on the first entry, since that takes listing the processes
+    parent: Arc<OnceLock<Option<String>>>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::at(state_dir().join(AUDIT_FILE_NAME))
+    }
+}
+
+impl AuditLog {
+    /// Use the audit file at a specific path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes: MAX_AUDIT_FILE_BYTES,
+            parent: Arc::default(),
+        }
+    }
+
+    /// Every entry, oldest first. Lines that don't parse, e.g. from a newer kiosk, are skipped.
+    pub fn load(&self) -> Result<Vec<AuditEntry>> {
+        self.recent(None, |_| true)
+    }
+
+    /// The last `limit` entries `keep` accepts, or all of them without a limit, oldest first.
+    /// The file is read a line at a time, holding only the entries to return.
+    pub fn recent(
+        &self,
+        limit: Option<usize>,
+        keep: impl Fn(&AuditEntry) -> bool,
+    ) -> Result<Vec<AuditEntry>> {
+        let mut entries = VecDeque::new();
+        for path in [self.rotated_path(), self.path.clone()] {
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("failed to open {}", path.display()));
+                }
+            };
+            for line in BufReader::new(file).lines() {
+                let line = line.with_context(|| format!("failed to read {}", path.display()))?;
+                let Ok(entry) = serde_json::from_str(&line) else {
+                    continue;
+                };
+                if !keep(&entry) {
+                    continue;
+                }
+                entries.push_back(entry);
+                if limit.is_some_and(|limit| entries.len() > limit) {
+                    entries.pop_front();
+                }
+            }
+        }
+        Ok(entries.into())
+    }
+
+    /// Append `entry` as one line. Concurrent kiosks can share the file: each line goes out
+    /// in a single append.
+    pub fn append(&self, entry: &AuditEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.rotate_if_full()?;
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut options = File::options();
+        options.create(true).append(true);
+        // Entries hold command lines and keys typed into sessions
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(&self.path)
+            .with_context(|| format!("failed to open {}", self.path.display()))?;
+        file.write_all(&line)
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+
+    /// Move the file aside, over the one moved aside before, once it's reached `max_bytes`.
+    /// The size is checked again under the lock so that concurrent kiosks only move it once.
+    fn rotate_if_full(&self) -> Result<()> {
+        let is_full = || fs::metadata(&self.path).is_ok_and(|meta| meta.len() >= self.max_bytes);
+        if !is_full() {
+            return Ok(());
+        }
+        let _lock = lock(&self.path)?;
+        if is_full() {
+            fs::rename(&self.path, self.rotated_path())
+                .with_context(|| format!("failed to rotate {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn rotated_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".1");
+        self.path.with_file_name(name)
+    }
+
+    /// Record `operation`, filled in by `describe`, and how `result` says it went. Failing to
+    /// write the entry is logged rather than failing the operation.
+    fn record<T>(
+        &self,
+        operation: AuditOperation,
+        result: &Result<T>,
+        describe: impl FnOnce(&mut AuditEntry),
+    ) {
+        let mut entry = AuditEntry::new(operation);
+        entry
+            .parent
+            .clone_from(self.parent.get_or_init(parent_command));
+        describe(&mut entry);
+        entry.detail = entry.detail.map(truncate_detail);
+        entry.error = result.as_ref().err().map(|e| format!("{e:#}"));
+        if let Err(e) = self.append(&entry) {
+            tracing::warn!("Failed to record {operation} in the audit log: {e:#}");
+        }
+    }
+}
+
+/// `detail` cut to [`MAX_DETAIL_CHARS`], saying how much was left out.
+fn truncate_detail(detail: String) -> String {
+    let total = detail.chars().count();
+    if total <= MAX_DETAIL_CHARS {
+        return detail;
+    }
+    let kept: String = detail.chars().take(MAX_DETAIL_CHARS).collect();
+    format!("{kept}… ({} more characters)", total - MAX_DETAIL_CHARS)
+}
+
+#[cfg(unix)]
+fn parent_command() -> Option<String> {
+    let parent = std::os::unix::process::parent_id();
+    let table = crate::process_tree::ProcessTable::snapshot().ok()?;
+    table.tree(parent).map(|tree| tree.process.args)
+}
+
+#[cfg(not(unix))]
+fn parent_command() -> Option<String> {
+    None
+}
+
+/// A git provider recording the worktrees and branches it changes in an [`AuditLog`].
+pub struct AuditedGit {
+    inner: Arc<dyn GitProvider>,
+    log: AuditLog,
+}
+
+impl AuditedGit {
+    pub fn new(inner: Arc<dyn GitProvider>, log: AuditLog) -> Self {
+        Self { inner, log }
+    }
+}
+
+impl GitProvider for AuditedGit {
+    fn scan_repos(&self, dirs: &[(PathBuf, u16)]) -> Vec<Repo> {
+        self.inner.scan_repos(dirs)
+    }
+
+    fn scan_repos_streaming(&self, dir: &Path, depth: u16, on_found: &dyn Fn(Repo)) {
+        self.inner.scan_repos_streaming(dir, depth, on_found);
+    }
+
+    fn discover_repos(&self, dirs: &[(PathBuf, u16)]) -> Vec<Repo> {
+        self.inner.discover_repos(dirs)
+    }
+
+    fn list_branches(&self, repo_path: &Path) -> Vec<String> {
+        self.inner.list_branches(repo_path)
+    }
+
+    fn list_remote_branches(&self, repo_path: &Path) -> Vec<String> {
+        self.inner.list_remote_branches(repo_path)
+    }
+
+    fn list_remote_branches_for_remote(&self, repo_path: &Path, remote: &str) -> Vec<String> {
+        self.inner
+            .list_remote_branches_for_remote(repo_path, remote)
+    }
+
+    fn list_worktrees(&self, repo_path: &Path) -> Vec<Worktree> {
+        self.inner.list_worktrees(repo_path)
+    }
+
+    fn add_worktree(&self, repo_path: &Path, branch: &str, worktree_path: &Path) -> Result<()> {
+        let result = self.inner.add_worktree(repo_path, branch, worktree_path);
+        self.log
+            .record(AuditOperation::WorktreeCreated, &result, |entry| {
+                entry.repo = Some(repo_path.to_path_buf());
+                entry.branch = Some(branch.to_string());
+                entry.path = Some(worktree_path.to_path_buf());
+            });
+        result
+    }
+
+    fn create_branch_and_worktree(
+        &self,
+        repo_path: &Path,
+        new_branch: &str,
+        base: &str,
+        worktree_path: &Path,
+    ) -> Result<()> {
+        let result =
+            self.inner
+                .create_branch_and_worktree(repo_path, new_branch, base, worktree_path);
+        self.log
+            .record(AuditOperation::WorktreeCreated, &result, |entry| {
+                entry.repo = Some(repo_path.to_path_buf());
+                entry.branch = Some(new_branch.to_string());
+                entry.path = Some(worktree_path.to_path_buf());
+                entry.detail = Some(format!("new branch from {base}"));
+            });
+        result
+    }
+
+    fn remove_worktree(&self, worktree_path: &Path) -> Result<()> {
+        let result = self.inner.remove_worktree(worktree_path);
+        self.log
+            .record(AuditOperation::WorktreeRemoved, &result, |entry| {
+                entry.path = Some(worktree_path.to_path_buf());
+            });
+        result
+    }
+
+    fn prune_worktrees(&self, repo_path: &Path) -> Result<()> {
+        let result = self.inner.prune_worktrees(repo_path);
+        self.log
+            .record(AuditOperation::WorktreesPruned, &result, |entry| {
+                entry.repo = Some(repo_path.to_path_buf());
+            });
+        result
+    }
+
+    fn delete_branch(&self, repo_path: &Path, branch: &str) -> Result<()> {
+        let result = self.inner.delete_branch(repo_path, branch);
+        self.log
+            .record(AuditOperation::BranchDeleted, &result, |entry| {
+                entry.repo = Some(repo_path.to_path_buf());
+                entry.branch = Some(branch.to_string());
+            });
+        result
+    }
+
+    fn branches_containing(&self, repo_path: &Path, commit: &str) -> Result<Vec<String>> {
+        self.inner.branches_containing(repo_path, commit)
+    }
+
+    fn unique_commits(&self, repo_path: &Path, branch: &str, base: &str) -> Result<usize> {
+        self.inner.unique_commits(repo_path, branch, base)
+    }
+
+    fn last_commits(&self, repo_path: &Path) -> HashMap<String, CommitInfo> {
+        self.inner.last_commits(repo_path)
+    }
+
+    fn list_tags(&self, repo_path: &Path) -> Vec<Tag> {
+        self.inner.list_tags(repo_path)
+    }
+
+    fn diff_files(&self, worktree_path: &Path, base: &str) -> Result<Vec<FileChange>> {
+        self.inner.diff_files(worktree_path, base)
+    }
+
+    fn diff_patch(&self, worktree_path: &Path, base: &str) -> Result<String> {
+        self.inner.diff_patch(worktree_path, base)
+    }
+
+    fn worktree_changes(&self, worktree_path: &Path) -> Result<WorktreeChanges> {
+        self.inner.worktree_changes(worktree_path)
+    }
+
+    fn head_commit(&self, worktree_path: &Path) -> Result<String> {
+        self.inner.head_commit(worktree_path)
+    }
+
+    fn resolve_commit(&self, repo_path: &Path, rev: &str) -> Result<String> {
+        self.inner.resolve_commit(repo_path, rev)
+    }
+
+    fn add_detached_worktree(
+        &self,
+        repo_path: &Path,
+        commit: &str,
+        worktree_path: &Path,
+    ) -> Result<()> {
+        let result = self
+            .inner
+            .add_detached_worktree(repo_path, commit, worktree_path);
+        self.log
+            .record(AuditOperation::WorktreeCreated, &result, |entry| {
+                entry.repo = Some(repo_path.to_path_buf());
+                entry.path = Some(worktree_path.to_path_buf());
+                entry.detail = Some(format!("detached at {commit}"));
+            });
+        result
+    }
+
+    fn stash_changes(&self, worktree_path: &Path, message: &str) -> Result<Option<String>> {
+        self.inner.stash_changes(worktree_path, message)
+    }
+
+    fn pop_stash(&self, worktree_path: &Path, stash: &str) -> Result<()> {
+        self.inner.pop_stash(worktree_path, stash)
+    }
+
+    fn drop_stash(&self, repo_path: &Path, stash: &str) -> Result<()> {
+        self.inner.drop_stash(repo_path, stash)
+    }
+
+    fn find_stash(&self, worktree_path: &Path, message: &str) -> Result<Option<String>> {
+        self.inner.find_stash(worktree_path, message)
+    }
+
+    fn checkout_branch(&self, worktree_path: &Path, branch: &str) -> Result<()> {
+        self.inner.checkout_branch(worktree_path, branch)
+    }
+
+    fn create_tracking_branch_and_worktree(
+        &self,
+        repo_path: &Path,
+        remote: &str,
+        branch: &str,
+        worktree_path: &Path,
+    ) -> Result<()> {
+        let result = self.inner.create_tracking_branch_and_worktree(
+            repo_path,
+            remote,
+            branch,
+            worktree_path,
+        );
+        self.log
+            .record(AuditOperation::WorktreeCreated, &result, |entry| {
+                entry.repo = Some(repo_path.to_path_buf());
+                entry.branch = Some(branch.to_string());
+                entry.path = Some(worktree_path.to_path_buf());
+                entry.detail = Some(format!("tracking {remote}/{branch}"));
+            });
+        result
+    }
+
+    fn list_remotes(&self, repo_path: &Path) -> Vec<String> {
+        self.inner.list_remotes(repo_path)
+    }
+
+    fn fetch_remote(&self, repo_path: &Path, remote: &str, prune: bool) -> Result<()> {
+        self.inner.fetch_remote(repo_path, remote, prune)
+    }
+
+    fn clone_repo(&self, url: &str, dest: &Path) -> Result<()> {
+        self.inner.clone_repo(url, dest)
+    }
+
+    fn init_repo(&self, path: &Path) -> Result<()> {
+        self.inner.init_repo(path)
+    }
+
+    fn default_branch(&self, repo_path: &Path, local_branches: &[String]) -> Option<String> {
+        self.inner.default_branch(repo_path, local_branches)
+    }
+
+    fn resolve_repo_from_cwd(&self) -> Option<PathBuf> {
+        self.inner.resolve_repo_from_cwd()
+    }
+
+    fn apply_config(&self, config: &GitConfig) {
+        self.inner.apply_config(config);
+    }
+}
+
+/// A tmux provider recording the sessions it creates and kills, and the keys it sends, in an
+/// [`AuditLog`].
+pub struct AuditedTmux {
+    inner: Arc<dyn TmuxProvider>,
+    log: AuditLog,
+}
+
+impl AuditedTmux {
+    pub fn new(inner: Arc<dyn TmuxProvider>, log: AuditLog) -> Self {
+        Self { inner, log }
+    }
+
+    fn record_keys(&self, session: &str, pane: Option<&str>, keys: String, result: &Result<()>) {
+        self.log.record(AuditOperation::KeysSent, result, |entry| {
+            entry.session = Some(session.to_string());
+            entry.pane = pane.map(str::to_string);
+            entry.detail = Some(keys);
+        });
+    }
+}
+
+impl TmuxProvider for AuditedTmux {
+    fn list_sessions_with_activity(&self) -> Vec<(String, u64)> {
+        self.inner.list_sessions_with_activity()
+    }
+
+    fn list_session_names(&self) -> Vec<String> {
+        self.inner.list_session_names()
+    }
+
+    fn list_sessions_detailed(&self) -> Vec<SessionDetails> {
+        self.inner.list_sessions_detailed()
+    }
+
+    fn session_exists(&self, name: &str) -> bool {
+        self.inner.session_exists(name)
+    }
+
+    fn current_session(&self) -> Option<String> {
+        self.inner.current_session()
+    }
+
+    fn session_path(&self, session: &str) -> Result<PathBuf> {
+        self.inner.session_path(session)
+    }
+
+    fn server_started_at(&self) -> Option<u64> {
+        self.inner.server_started_at()
+    }
+
+    fn create_session(
+        &self,
+        name: &str,
+        dir: &Path,
+        split_command: Option<&str>,
+        env: &[(String, String)],
+    ) -> Result<()> {
+        let result = self.inner.create_session(name, dir, split_command, env);
+        self.log
+            .record(AuditOperation::SessionCreated, &result, |entry| {
+                entry.session = Some(name.to_string());
+                entry.path = Some(dir.to_path_buf());
+                entry.detail = split_command.map(|command| format!("split: {command}"));
+            });
+        result
+    }
+
+    fn capture_pane(&self, session: &str, lines: usize) -> Result<String> {
+        self.inner.capture_pane(session, lines)
+    }
+
+    fn capture_pane_with_pane(&self, session: &str, pane: &str, lines: usize) -> Result<String> {
+        self.inner.capture_pane_with_pane(session, pane, lines)
+    }
+
+    fn capture_pane_range(
+        &self,
+        session: &str,
+        pane: &str,
+        range: &CaptureRange,
+    ) -> Result<String> {
+        self.inner.capture_pane_range(session, pane, range)
+    }
+
+    fn pane_current_command(&self, session: &str, pane: &str) -> Result<String> {
+        self.inner.pane_current_command(session, pane)
+    }
+
+    fn session_activity(&self, session: &str) -> Result<u64> {
+        self.inner.session_activity(session)
+    }
+
+    fn pane_count(&self, session: &str) -> Result<usize> {
+        self.inner.pane_count(session)
+    }
+
+    fn list_panes_detailed(&self, session: &str) -> Result<Vec<PaneDetails>> {
+        self.inner.list_panes_detailed(session)
+    }
+
+    fn send_keys(&self, session: &str, keys: &str) -> Result<()> {
+        let result = self.inner.send_keys(session, keys);
+        self.record_keys(session, None, format!("{keys} Enter"), &result);
+        result
+    }
+
+    fn send_keys_raw(&self, session: &str, pane: &str, keys: &[&str]) -> Result<()> {
+        let result = self.inner.send_keys_raw(session, pane, keys);
+        self.record_keys(session, Some(pane), keys.join(" "), &result);
+        result
+    }
+
+    fn send_text_raw(&self, session: &str, pane: &str, text: &str) -> Result<()> {
+        let result = self.inner.send_text_raw(session, pane, text);
+        self.record_keys(session, Some(pane), text.to_string(), &result);
+        result
+    }
+
+    fn paste_text(&self, session: &str, pane: &str, text: &str) -> Result<()> {
+        let result = self.inner.paste_text(session, pane, text);
+        // Pastes can be whole files or prompts, too big and too private to keep
+        self.record_keys(
+            session,
+            Some(pane),
+            format!("{} bytes pasted", text.len()),
+            &result,
+        );
+        result
+    }
+
+    fn pipe_pane(&self, session: &str, log_path: &Path) -> Result<()> {
+        self.inner.pipe_pane(session, log_path)
+    }
+
+    fn rename_window(&self, session: &str, name: &str) -> Result<()> {
+        self.inner.rename_window(session, name)
+    }
+
+    fn set_pane_title(&self, session: &str, pane: &str, title: &str) -> Result<()> {
+        self.inner.set_pane_title(session, pane, title)
+    }
+
+    fn kill_pane(&self, session: &str, pane: &str) -> Result<()> {
+        let result = self.inner.kill_pane(session, pane);
+        self.log
+            .record(AuditOperation::PaneKilled, &result, |entry| {
+                entry.session = Some(session.to_string());
+                entry.pane = Some(pane.to_string());
+            });
+        result
+    }
+
+    fn list_clients(&self, session: &str) -> Vec<String> {
+        self.inner.list_clients(session)
+    }
+
+    fn switch_to_session(&self, name: &str) {
+        self.inner.switch_to_session(name);
+    }
+
+    fn kill_session(&self, name: &str) {
+        self.inner.kill_session(name);
+        self.log
+            .record(AuditOperation::SessionKilled, &Ok(()), |entry| {
+                entry.session = Some(name.to_string());
+            });
+    }
+
+    fn is_inside_tmux(&self) -> bool {
+        self.inner.is_inside_tmux()
+    }
+
+    fn is_available(&self) -> bool {
+        self.inner.is_available()
+    }
+
+    fn subscribe(&self) -> Option<Receiver<TmuxEvent>> {
+        self.inner.subscribe()
+    }
+
+    fn apply_config(&self, config: &TmuxConfig) {
+        self.inner.apply_config(config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockGitProvider, MockTmuxProvider};
+
+    #[test]
+    fn test_audited_providers_record_changes_but_not_reads() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::at(dir.path().join("audit.ndjson"));
+        let git = AuditedGit::new(Arc::new(MockGitProvider::default()), log.clone());
+        let tmux = AuditedTmux::new(Arc::new(MockTmuxProvider::default()), log.clone());
+
+        git.list_branches(Path::new("/tmp/app"));
+        git.add_worktree(Path::new("/tmp/app"), "feat", Path::new("/tmp/app-feat"))
+            .unwrap();
+        tmux.session_exists("app--feat");
+        tmux.send_keys("app--feat", "cargo test").unwrap();
+        tmux.kill_session("app--feat");
+
+        let entries = log.load().unwrap();
+        let operations: Vec<_> = entries.iter().map(|entry| entry.operation).collect();
+        assert_eq!(
+            operations,
+            [
+                AuditOperation::WorktreeCreated,
+                AuditOperation::KeysSent,
+                AuditOperation::SessionKilled,
+            ]
+        );
+        assert_eq!(entries[0].branch.as_deref(), Some("feat"));
+        assert_eq!(entries[0].path.as_deref(), Some(Path::new("/tmp/app-feat")));
+        assert_eq!(entries[1].detail.as_deref(), Some("cargo test Enter"));
+        assert!(entries.iter().all(|entry| entry.pid == std::process::id()));
+    }
+
+    #[test]
+    fn test_failures_are_recorded_with_their_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::at(dir.path().join("audit.ndjson"));
+        let mock = MockGitProvider::default();
+        *mock.remove_worktree_result.lock().unwrap() = Some(Err(anyhow::anyhow!("locked")));
+        let git = AuditedGit::new(Arc::new(mock), log.clone());
+
+        assert!(git.remove_worktree(Path::new("/tmp/app-feat")).is_err());
+        fs::write(
+            dir.path().join("audit.ndjson"),
+            format!(
+                "{}not json\n",
+                fs::read_to_string(dir.path().join("audit.ndjson")).unwrap()
+            ),
+        )
+        .unwrap();
+
+        let entries = log.load().unwrap();
+        assert_eq!(entries.len(), 1, "unparseable lines are skipped");
+        assert_eq!(entries[0].operation, AuditOperation::WorktreeRemoved);
+        assert_eq!(entries[0].error.as_deref(), Some("locked"));
+    }
+
+    #[test]
+    fn test_log_keeps_details_short_and_pastes_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::at(dir.path().join("audit.ndjson"));
+        let tmux = AuditedTmux::new(Arc::new(MockTmuxProvider::default()), log.clone());
+
+        tmux.send_text_raw("app", "0", &"x".repeat(250)).unwrap();
+        tmux.paste_text("app", "0", "secret prompt").unwrap();
+
+        let entries = log.load().unwrap();
+        let detail = entries[0].detail.as_deref().unwrap();
+        assert!(detail.starts_with(&"x".repeat(MAX_DETAIL_CHARS)));
+        assert!(detail.ends_with("… (50 more characters)"));
+        assert_eq!(entries[1].detail.as_deref(), Some("13 bytes pasted"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path().join("audit.ndjson"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_log_rotates_when_full_and_reads_across_both_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = AuditLog::at(dir.path().join("audit.ndjson"));
+        log.max_bytes = 1;
+        let tmux = AuditedTmux::new(Arc::new(MockTmuxProvider::default()), log.clone());
+
+        for session in ["a", "b", "c"] {
+            tmux.kill_session(session);
+        }
+
+        // Only the last two entries survive the rotations
+        assert!(dir.path().join("audit.ndjson.1").exists());
+        let sessions = |entries: Vec<AuditEntry>| -> Vec<String> {
+            entries.into_iter().filter_map(|e| e.session).collect()
+        };
+        assert_eq!(sessions(log.load().unwrap()), ["b", "c"]);
+        assert_eq!(sessions(log.recent(Some(1), |_| true).unwrap()), ["c"]);
+        let not_c = log.recent(Some(5), |e| e.session.as_deref() != Some("c"));
+        assert_eq!(sessions(not_c.unwrap()), ["b"]);
+        assert_eq!(AuditOperation::SessionKilled.to_string(), "session_killed");
+    }
+}
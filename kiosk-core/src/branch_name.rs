@@ -0,0 +1,166 @@
+//! Checking new branch names, and building them from `branch.name_template`.
+
+use crate::placeholders;
+
+/// The template placeholder filled from the text typed in the branch list.
+pub const SLUG_FIELD: &str = "slug";
+
+/// Check `name` against git's rules for branch names (as `git check-ref-format --branch`
+/// applies them), returning what's wrong with it.
+pub fn validate(name: &str) -> Result<(), String> {
+    let problem = if name.is_empty() {
+        "it's empty"
+    } else if name == "@" {
+        "it can't be '@'"
+    } else if name.starts_with('-') {
+        "it can't start with '-'"
+    } else if name.starts_with('/') || name.ends_with('/') {
+        "it can't start or end with '/'"
+    } else if name.ends_with('.') {
+        "it can't end with '.'"
+    } else if name.contains(' ') {
+        "it can't contain spaces"
+    } else if name.chars().any(|c| c.is_ascii_control()) {
+        "it can't contain control characters"
+    } else if let Some(bad) = ["..", "//", "@{"].iter().find(|bad| name.contains(*bad)) {
+        return Err(format!("it can't contain '{bad}'"));
+    } else if let Some(c) = name.chars().find(|c| "~^:?*[\\".contains(*c)) {
+        return Err(format!("it can't contain '{c}'"));
+    } else if name.split('/').any(|part| part.starts_with('.')) {
+        "no part of it can start with '.'"
+    } else if name
+        .split('/')
+        .any(|part| part.strip_suffix(".lock").is_some())
+    {
+        "no part of it can end with '.lock'"
+    } else {
+        return Ok(());
+    };
+    Err(problem.to_string())
+}
+
+/// `text` lowercased, with each run of characters other than letters and digits turned into a
+/// single `-`, for the `{slug}` of a branch name template.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// The placeholders in `template` to ask for, in order and without repeats: every `{field}`
+/// except `{slug}`.
+pub fn template_fields(template: &str) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let field = &rest[..end];
+        if !field.is_empty()
+            && field != SLUG_FIELD
+            && !field.contains('{')
+            && !fields.iter().any(|f| f == field)
+        {
+            fields.push(field.to_string());
+        }
+        rest = &rest[end + 1..];
+    }
+    fields
+}
+
+/// What a field starts off as: `$USER` for `{user}`.
+pub fn default_value(field: &str) -> Option<String> {
+    match field {
+        "user" => ["USER", "USERNAME"]
+            .into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty())),
+        _ => None,
+    }
+}
+
+/// `template` with `{slug}` made from `text` and each field filled from `values`. Fields
+/// without a value are left as they are.
+pub fn from_template(template: &str, text: &str, values: &[(String, String)]) -> String {
+    let slug = slugify(text);
+    let values: Vec<(&str, &str)> = std::iter::once((SLUG_FIELD, slug.as_str()))
+        .chain(
+            values
+                .iter()
+                .map(|(field, value)| (field.as_str(), value.as_str())),
+        )
+        .collect();
+    placeholders::expand(template, &values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_ordinary_names() {
+        for name in [
+            "main",
+            "feat/add-thing",
+            "alice/ABC-123-fix",
+            "v1.2",
+            "ü/naïve",
+        ] {
+            assert_eq!(validate(name), Ok(()), "{name}");
+        }
+    }
+
+    #[test]
+    fn rejects_names_git_would_refuse() {
+        for (name, problem) in [
+            ("", "it's empty"),
+            ("@", "it can't be '@'"),
+            ("-x", "it can't start with '-'"),
+            ("feat/", "it can't start or end with '/'"),
+            ("feat.", "it can't end with '.'"),
+            ("add thing", "it can't contain spaces"),
+            ("a..b", "it can't contain '..'"),
+            ("a//b", "it can't contain '//'"),
+            ("a@{1}", "it can't contain '@{'"),
+            ("what?", "it can't contain '?'"),
+            ("a~1", "it can't contain '~'"),
+            ("feat/.hidden", "no part of it can start with '.'"),
+            ("feat.lock/x", "no part of it can end with '.lock'"),
+        ] {
+            assert_eq!(validate(name), Err(problem.to_string()), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn slugs_are_lowercase_with_dashes() {
+        assert_eq!(slugify("Fix the Login  bug!"), "fix-the-login-bug");
+        assert_eq!(slugify("  --already-a-slug--"), "already-a-slug");
+        assert_eq!(slugify("?!"), "");
+    }
+
+    #[test]
+    fn template_fields_skip_the_slug_and_repeats() {
+        assert_eq!(
+            template_fields("{user}/{ticket}-{slug}-{user}"),
+            vec!["user", "ticket"]
+        );
+        assert!(template_fields("{slug}").is_empty());
+        assert!(template_fields("plain").is_empty());
+    }
+
+    #[test]
+    fn templates_fill_in_the_slug_and_fields() {
+        let values = vec![("user".to_string(), "alice".to_string())];
+        assert_eq!(
+            from_template("{user}/{ticket}-{slug}", "Fix login", &values),
+            "alice/{ticket}-fix-login"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::paths::expand_tilde;
+use anyhow::{Context, Result, bail};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+const INCLUDE_KEY: &str = "include";
+const PROFILES_KEY: &str = "profiles";
+
+/// Whether a parsed config file needs merging before it can be deserialized, i.e. it
+/// includes other files or a profile was selected.
+pub(super) fn needs_merge(table: &Table, profile: Option<&str>) -> bool {
+    profile.is_some() || table.get(INCLUDE_KEY).is_some_and(|v| !is_empty_array(v))
+}
+
+/// Read `config_file` with its includes and the selected profile merged in. Returns the
+/// merged table and every file read, starting with `config_file`.
+pub(super) fn read_merged(
+    config_file: &Path,
+    profile: Option<&str>,
+) -> Result<(Table, Vec<PathBuf>)> {
+    let mut files = Vec::new();
+    let mut merged = read_with_includes(config_file, &mut Vec::new(), &mut files)?;
+
+    let profiles = match merged.remove(PROFILES_KEY) {
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => bail!("'{PROFILES_KEY}' must be a table of profiles"),
+        None => Table::new(),
+    };
+    if let Some(name) = profile {
+        let Some(Value::Table(mut selected)) = profiles.get(name).cloned() else {
+            let available: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            bail!(
+                "unknown profile '{name}' (available: {})",
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            );
+        };
+        for include in take_includes(&mut selected, config_file)? {
+            let included = read_with_includes(&include, &mut Vec::new(), &mut files)?;
+            merge_tables(&mut merged, included);
+        }
+        merge_tables(&mut merged, selected);
+    }
+    Ok((merged, files))
+}
+
+/// Merge `overlay` into `base`: tables are merged key by key, arrays are appended (so
+/// `search_dirs` and `ignore` accumulate), and any other value is replaced.
+pub(super) fn merge_tables(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(overlay)) => {
+                merge_tables(existing, overlay);
+            }
+            (Some(Value::Array(existing)), Value::Array(overlay)) => existing.extend(overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn read_with_includes(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Table> {
+    let canonical = canonical_path(path);
+    if stack.contains(&canonical) {
+        bail!("config include cycle: {} includes itself", path.display());
+    }
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file {}", path.display()))?;
+    let mut table: Table =
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))?;
+    files.push(path.to_path_buf());
+
+    stack.push(canonical);
+    let mut merged = Table::new();
+    for include in take_includes(&mut table, path)? {
+        merge_tables(&mut merged, read_with_includes(&include, stack, files)?);
+    }
+    stack.pop();
+
+    // The including file's own settings take precedence over what it includes
+    merge_tables(&mut merged, table);
+    Ok(merged)
+}
+
+/// Remove `include` from `table`, resolving each entry against the including file's
+/// directory (`~` is expanded).
+fn take_includes(table: &mut Table, including_file: &Path) -> Result<Vec<PathBuf>> {
+    let Some(value) = table.remove(INCLUDE_KEY) else {
+        return Ok(Vec::new());
+    };
+    let Value::Array(entries) = value else {
+        bail!(
+            "'{INCLUDE_KEY}' in {} must be an array of paths",
+            including_file.display()
+        );
+    };
+    let base_dir = including_file.parent().unwrap_or(Path::new(""));
+    entries
+        .into_iter()
+        .map(|entry| match entry {
+            Value::String(include) => {
+                let path = expand_tilde(&include).unwrap_or_else(|| PathBuf::from(&include));
+                Ok(if path.is_absolute() {
+                    path
+                } else {
+                    base_dir.join(path)
+                })
+            }
+            other => bail!(
+                "'{INCLUDE_KEY}' in {} must contain paths, found {other}",
+                including_file.display()
+            ),
+        })
+        .collect()
+}
+
+fn is_empty_array(value: &Value) -> bool {
+    matches!(value, Value::Array(entries) if entries.is_empty())
+}
+
+fn canonical_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn includes_merge_under_the_including_file() {
+        let tmp = tempdir().unwrap();
+        write(
+            tmp.path(),
+            "work.toml",
+            "search_dirs = [\"~/Work\"]\nsort = \"activity\"\n[session]\nsplit_command = \"hx\"\n",
+        );
+        let main = write(
+            tmp.path(),
+            "config.toml",
+            "include = [\"work.toml\"]\nsearch_dirs = [\"~/Development\"]\nsort = \"alphabetical\"\n",
+        );
+
+        let (merged, files) = read_merged(&main, None).unwrap();
+        assert_eq!(
+            merged["search_dirs"],
+            Value::Array(vec!["~/Work".into(), "~/Development".into()])
+        );
+        assert_eq!(merged["sort"], Value::from("alphabetical"));
+        assert_eq!(merged["session"]["split_command"], Value::from("hx"));
+        assert!(!merged.contains_key(INCLUDE_KEY));
+        assert_eq!(files, [main, tmp.path().join("work.toml")]);
+    }
+
+    #[test]
+    fn selected_profile_is_merged_last() {
+        let tmp = tempdir().unwrap();
+        write(tmp.path(), "extra.toml", "ignore = [\"**/archive-*\"]\n");
+        let main = write(
+            tmp.path(),
+            "config.toml",
+            r#"
+search_dirs = ["~/Development"]
+
+[profiles.work]
+include = ["extra.toml"]
+search_dirs = ["~/Work"]
+session = { split_command = "claude" }
+
+[profiles.personal]
+search_dirs = ["~/Personal"]
+"#,
+        );
+
+        let (merged, files) = read_merged(&main, Some("work")).unwrap();
+        assert_eq!(
+            merged["search_dirs"],
+            Value::Array(vec!["~/Development".into(), "~/Work".into()])
+        );
+        assert_eq!(merged["session"]["split_command"], Value::from("claude"));
+        assert_eq!(merged["ignore"], Value::Array(vec!["**/archive-*".into()]));
+        assert!(!merged.contains_key(PROFILES_KEY));
+        assert_eq!(files.len(), 2);
+
+        let (unselected, _) = read_merged(&main, None).unwrap();
+        assert_eq!(
+            unselected["search_dirs"],
+            Value::Array(vec!["~/Development".into()])
+        );
+
+        let error = read_merged(&main, Some("home")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown profile 'home' (available: personal, work)"
+        );
+    }
+
+    #[test]
+    fn include_cycles_are_rejected() {
+        let tmp = tempdir().unwrap();
+        write(tmp.path(), "a.toml", "include = [\"b.toml\"]\n");
+        write(tmp.path(), "b.toml", "include = [\"a.toml\"]\n");
+
+        let error = read_merged(&tmp.path().join("a.toml"), None).unwrap_err();
+        assert!(error.to_string().contains("include cycle"), "{error}");
+    }
+
+    #[test]
+    fn missing_include_names_the_file() {
+        let tmp = tempdir().unwrap();
+        let main = write(tmp.path(), "config.toml", "include = [\"nope.toml\"]\n");
+        let error = read_merged(&main, None).unwrap_err();
+        assert!(error.to_string().contains("nope.toml"), "{error}");
+    }
+}
@@ -12,12 +12,17 @@ pub struct CommandLabels {
     pub description: &'static str,
 }
 
+/// Prefix of commands that run a function from `[script]`
+const SCRIPT_PREFIX: &str = "script:";
+
 /// Single source of truth for every `Command` variant and its metadata.
 ///
 /// Each entry defines: variant name, config string, optional parse aliases,
 /// footer hint, and help description. The macro generates the enum plus
 /// `FromStr`, `Display`, `Serialize`, and `labels()` — so adding a new
 /// command is a one-line change with no risk of forgetting a match arm.
+/// The enum also gets `Chain`, for keys bound to a list of commands, and `Script`, for
+/// `script:<function>`.
 macro_rules! define_commands {
     (
         $(
@@ -31,40 +36,70 @@ macro_rules! define_commands {
     ) => {
         /// Commands that can be bound to keys
         #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-        pub enum Command { $($variant),* }
+        pub enum Command {
+            $($variant,)*
+            /// Commands run in order, from a key bound to a list in the config
+            Chain(Vec<Command>),
+            /// A function in `[script]`, from `script:<function>`
+            Script(String),
+        }
 
         impl FromStr for Command {
             type Err = String;
             fn from_str(s: &str) -> Result<Self, Self::Err> {
                 match s {
                     $($config_name $($(| $alias)+)? => Ok(Command::$variant),)*
-                    _ => Err(format!("Unknown command: {s}")),
+                    _ => s
+                        .strip_prefix(SCRIPT_PREFIX)
+                        .filter(|function| !function.is_empty())
+                        .map(|function| Command::Script(function.to_string()))
+                        .ok_or_else(|| format!("Unknown command: {s}")),
                 }
             }
         }
 
         impl std::fmt::Display for Command {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                f.write_str(match self {
-                    $(Command::$variant => $config_name),*
-                })
+                match self {
+                    $(Command::$variant => f.write_str($config_name),)*
+                    Command::Chain(commands) => {
+                        let names: Vec<String> =
+                            commands.iter().map(ToString::to_string).collect();
+                        write!(f, "[{}]", names.join(", "))
+                    }
+                    Command::Script(function) => write!(f, "{SCRIPT_PREFIX}{function}"),
+                }
             }
         }
 
         impl Serialize for Command {
             fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-                serializer.serialize_str(&self.to_string())
+                match self {
+                    Command::Chain(commands) => commands.serialize(serializer),
+                    _ => serializer.serialize_str(&self.to_string()),
+                }
             }
         }
 
         impl Command {
+            /// Config names of every command, as accepted in `[keys]` tables.
+            pub const CONFIG_NAMES: &[&str] = &[$($config_name),*];
+
             /// Get the labels (footer hint + help description) for this command.
             pub fn labels(&self) -> CommandLabels {
                 match self {
                     $(Command::$variant => CommandLabels {
                         hint: $hint,
                         description: $desc,
-                    }),*
+                    },)*
+                    Command::Chain(_) => CommandLabels {
+                        hint: "macro",
+                        description: "Run a sequence of commands",
+                    },
+                    Command::Script(_) => CommandLabels {
+                        hint: "script",
+                        description: "Run a function from the script",
+                    },
                 }
             }
         }
@@ -91,6 +126,11 @@ define_commands! {
         hint: "help",
         description: "Show help",
     },
+    CommandPalette {
+        config_name: "command_palette",
+        hint: "commands",
+        description: "Search for a command and run it",
+    },
 
     // Navigation
     OpenRepo {
@@ -108,11 +148,46 @@ define_commands! {
         hint: "open",
         description: "Open branch in tmux",
     },
+    GlobalSearch {
+        config_name: "global_search",
+        hint: "jump",
+        description: "Search branches with worktrees across all repos",
+    },
+    OpenInEditor {
+        config_name: "open_in_editor",
+        hint: "editor",
+        description: "Open in editor instead of tmux",
+    },
+    OpenWithAgent {
+        config_name: "open_with_agent",
+        hint: "agent",
+        description: "Open with the default agent running in the session",
+    },
     GoBack {
         config_name: "go_back",
         hint: "back",
         description: "Go back",
     },
+    TogglePin {
+        config_name: "toggle_pin",
+        hint: "pin",
+        description: "Pin or unpin repository at the top of the list",
+    },
+    HideRepo {
+        config_name: "hide_repo",
+        hint: "hide",
+        description: "Hide repository from the list",
+    },
+    CloneRepo {
+        config_name: "clone_repo",
+        hint: "clone",
+        description: "Clone the URL typed in the search bar into a search directory",
+    },
+    NewRepo {
+        config_name: "new_repo",
+        hint: "new repo",
+        description: "Create a repo named after the search text from a template",
+    },
     NewBranch {
         config_name: "new_branch",
         hint: "new branch",
@@ -123,6 +198,51 @@ define_commands! {
         hint: "delete worktree",
         description: "Delete worktree",
     },
+    UndoDelete {
+        config_name: "undo_delete",
+        hint: "undo delete",
+        description: "Restore the most recently deleted worktree",
+    },
+    FetchRemotes {
+        config_name: "fetch_remotes",
+        hint: "fetch",
+        description: "Fetch the repo's remotes now",
+    },
+    EditNote {
+        config_name: "edit_note",
+        hint: "note",
+        description: "Add or change the note on a branch",
+    },
+    ShowTags {
+        config_name: "show_tags",
+        hint: "tags",
+        description: "Browse the repo's tags, to open one in a detached worktree",
+    },
+    ShowWorkspaces {
+        config_name: "show_workspaces",
+        hint: "workspaces",
+        description: "Browse workspaces, to open all of a workspace's sessions at once",
+    },
+    AddToWorkspace {
+        config_name: "add_to_workspace",
+        hint: "add to workspace",
+        description: "Add the branch to a workspace, or to a new one named in the search",
+    },
+    TogglePreview {
+        config_name: "toggle_preview",
+        hint: "preview",
+        description: "Show or hide the preview pane",
+    },
+    GrowPreview {
+        config_name: "grow_preview",
+        hint: "wider preview",
+        description: "Widen the preview pane",
+    },
+    ShrinkPreview {
+        config_name: "shrink_preview",
+        hint: "narrower preview",
+        description: "Narrow the preview pane",
+    },
 
     // List movement
     MoveUp {
@@ -325,21 +445,35 @@ pub struct KeysConfig {
     pub branch_select: KeyMap,
 }
 
+/// A key binding as written in the config: one command, or a list run in order
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawBinding {
+    Command(String),
+    Chain(Vec<String>),
+}
+
+impl From<&str> for RawBinding {
+    fn from(command: &str) -> Self {
+        RawBinding::Command(command.to_string())
+    }
+}
+
 /// Intermediate structure for deserializing key bindings
 #[derive(Debug, Deserialize)]
 struct KeysConfigRaw {
     #[serde(default)]
-    general: HashMap<String, String>,
+    general: HashMap<String, RawBinding>,
     #[serde(default)]
-    text_edit: HashMap<String, String>,
+    text_edit: HashMap<String, RawBinding>,
     #[serde(default)]
-    list_navigation: HashMap<String, String>,
+    list_navigation: HashMap<String, RawBinding>,
     #[serde(default)]
-    modal: HashMap<String, String>,
+    modal: HashMap<String, RawBinding>,
     #[serde(default)]
-    repo_select: HashMap<String, String>,
+    repo_select: HashMap<String, RawBinding>,
     #[serde(default)]
-    branch_select: HashMap<String, String>,
+    branch_select: HashMap<String, RawBinding>,
 }
 
 impl Default for KeysConfig {
@@ -515,6 +649,10 @@ impl KeysConfig {
             KeyEvent::new(KeyCode::Char('h'), KeyModifiers::CONTROL),
             Command::ShowHelp,
         );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT),
+            Command::CommandPalette,
+        );
         map
     }
 
@@ -645,6 +783,18 @@ impl KeysConfig {
             KeyEvent::new(KeyCode::Char('G'), KeyModifiers::ALT),
             Command::MoveBottom,
         );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('t'), KeyModifiers::ALT),
+            Command::TogglePreview,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('<'), KeyModifiers::ALT),
+            Command::GrowPreview,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('>'), KeyModifiers::ALT),
+            Command::ShrinkPreview,
+        );
         map
     }
 
@@ -675,6 +825,34 @@ impl KeysConfig {
             KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE),
             Command::EnterRepo,
         );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('p'), KeyModifiers::ALT),
+            Command::TogglePin,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('h'), KeyModifiers::ALT),
+            Command::HideRepo,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL),
+            Command::GlobalSearch,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('c'), KeyModifiers::ALT),
+            Command::CloneRepo,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL),
+            Command::NewRepo,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('e'), KeyModifiers::ALT),
+            Command::OpenInEditor,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('w'), KeyModifiers::ALT),
+            Command::ShowWorkspaces,
+        );
         map.insert(
             KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
             Command::Quit,
@@ -700,23 +878,80 @@ impl KeysConfig {
             KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL),
             Command::DeleteWorktree,
         );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE),
+            Command::UndoDelete,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+            Command::FetchRemotes,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('n'), KeyModifiers::ALT),
+            Command::EditNote,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL),
+            Command::ShowTags,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('w'), KeyModifiers::ALT),
+            Command::AddToWorkspace,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('e'), KeyModifiers::ALT),
+            Command::OpenInEditor,
+        );
+        map.insert(
+            KeyEvent::new(KeyCode::Char('a'), KeyModifiers::ALT),
+            Command::OpenWithAgent,
+        );
         map
     }
 
     /// Parse a string representation of keybindings into a `KeyMap`
-    fn parse_keymap(raw_map: &HashMap<String, String>) -> Result<KeyMap, String> {
+    fn parse_keymap(raw_map: &HashMap<String, RawBinding>) -> Result<KeyMap, String> {
         let mut keymap = KeyMap::new();
-        for (key_str, command_str) in raw_map {
+        for (key_str, binding) in raw_map {
             let key_event =
                 KeyEvent::from_str(key_str).map_err(|e| format!("Invalid key '{key_str}': {e}"))?;
-            let command = Command::from_str(command_str)
-                .map_err(|e| format!("Invalid command '{command_str}': {e}"))?;
+            let command = match binding {
+                RawBinding::Command(command_str) => Self::parse_command(command_str)?,
+                RawBinding::Chain(command_strs) => Self::parse_chain(key_str, command_strs)?,
+            };
             keymap.insert(key_event, command);
         }
         Ok(keymap)
     }
 
-    fn extend_layer(base: &mut KeyMap, raw_map: &HashMap<String, String>) -> Result<(), String> {
+    fn parse_command(command_str: &str) -> Result<Command, String> {
+        Command::from_str(command_str).map_err(|e| format!("Invalid command '{command_str}': {e}"))
+    }
+
+    /// A list of commands becomes a `Chain`, or the command itself if there's only one.
+    fn parse_chain(key_str: &str, command_strs: &[String]) -> Result<Command, String> {
+        let mut commands = command_strs
+            .iter()
+            .map(|command_str| Self::parse_command(command_str))
+            .collect::<Result<Vec<_>, _>>()?;
+        if commands.contains(&Command::Noop) {
+            return Err(format!(
+                "Invalid commands for '{key_str}': noop can't be part of a list"
+            ));
+        }
+        match commands.len() {
+            0 => Err(format!(
+                "Invalid commands for '{key_str}': the list is empty"
+            )),
+            1 => Ok(commands.remove(0)),
+            _ => Ok(Command::Chain(commands)),
+        }
+    }
+
+    fn extend_layer(
+        base: &mut KeyMap,
+        raw_map: &HashMap<String, RawBinding>,
+    ) -> Result<(), String> {
         base.extend(Self::parse_keymap(raw_map)?);
         Ok(())
     }
@@ -785,8 +1020,8 @@ mod tests {
     #[test]
     fn test_parse_keymap() {
         let mut raw_map = HashMap::new();
-        raw_map.insert("C-c".to_string(), "quit".to_string());
-        raw_map.insert("enter".to_string(), "confirm".to_string());
+        raw_map.insert("C-c".to_string(), "quit".into());
+        raw_map.insert("enter".to_string(), "confirm".into());
 
         let keymap = KeysConfig::parse_keymap(&raw_map).unwrap();
         assert_eq!(keymap.len(), 2);
@@ -801,7 +1036,7 @@ mod tests {
     #[test]
     fn test_parse_invalid_key() {
         let mut raw_map = HashMap::new();
-        raw_map.insert("invalid-key".to_string(), "quit".to_string());
+        raw_map.insert("invalid-key".to_string(), "quit".into());
 
         let result = KeysConfig::parse_keymap(&raw_map);
         assert!(result.is_err());
@@ -810,12 +1045,52 @@ mod tests {
     #[test]
     fn test_parse_invalid_command() {
         let mut raw_map = HashMap::new();
-        raw_map.insert("C-c".to_string(), "invalid_command".to_string());
+        raw_map.insert("C-c".to_string(), "invalid_command".into());
 
         let result = KeysConfig::parse_keymap(&raw_map);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_command_lists_as_chains() {
+        let config: KeysConfig = toml::from_str(
+            r#"
+[repo_select]
+"C-o" = ["enter_repo", "move_down", "open_branch"]
+"C-p" = ["toggle_pin"]
+"#,
+        )
+        .unwrap();
+
+        let ctrl_o = KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL);
+        let chain = Command::Chain(vec![
+            Command::EnterRepo,
+            Command::MoveDown,
+            Command::OpenBranch,
+        ]);
+        assert_eq!(config.repo_select.get(&ctrl_o), Some(&chain));
+        assert_eq!(chain.to_string(), "[enter_repo, move_down, open_branch]");
+        let ctrl_p = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL);
+        assert_eq!(config.repo_select.get(&ctrl_p), Some(&Command::TogglePin));
+
+        for bad in [
+            r#""C-o" = []"#,
+            r#""C-o" = ["quit", "noop"]"#,
+            r#""C-o" = ["qiut"]"#,
+        ] {
+            let result: Result<KeysConfig, _> = toml::from_str(&format!("[general]\n{bad}"));
+            assert!(result.is_err(), "{bad} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_parse_script_commands() {
+        let command: Command = "script:run_tests".parse().unwrap();
+        assert_eq!(command, Command::Script("run_tests".to_string()));
+        assert_eq!(command.to_string(), "script:run_tests");
+        assert!("script:".parse::<Command>().is_err());
+    }
+
     #[test]
     fn test_mode_precedence_more_specific_wins() {
         let raw = KeysConfigRaw {
@@ -825,7 +1100,7 @@ mod tests {
             modal: HashMap::new(),
             repo_select: {
                 let mut map = HashMap::new();
-                map.insert("C-c".to_string(), "show_help".to_string());
+                map.insert("C-c".to_string(), "show_help".into());
                 map
             },
             branch_select: HashMap::new(),
@@ -847,7 +1122,7 @@ mod tests {
             repo_select: HashMap::new(),
             branch_select: {
                 let mut map = HashMap::new();
-                map.insert("C-n".to_string(), "noop".to_string());
+                map.insert("C-n".to_string(), "noop".into());
                 map
             },
         };
@@ -890,7 +1165,7 @@ mod tests {
         let raw = KeysConfigRaw {
             general: {
                 let mut map = HashMap::new();
-                map.insert("enter".to_string(), "quit".to_string());
+                map.insert("enter".to_string(), "quit".into());
                 map
             },
             text_edit: HashMap::new(),
@@ -914,14 +1189,14 @@ mod tests {
         let raw = KeysConfigRaw {
             general: {
                 let mut map = HashMap::new();
-                map.insert("esc".to_string(), "quit".to_string());
+                map.insert("esc".to_string(), "quit".into());
                 map
             },
             text_edit: HashMap::new(),
             list_navigation: HashMap::new(),
             modal: {
                 let mut map = HashMap::new();
-                map.insert("esc".to_string(), "noop".to_string());
+                map.insert("esc".to_string(), "noop".into());
                 map
             },
             repo_select: HashMap::new(),
@@ -991,8 +1266,9 @@ mod tests {
         let raw = KeysConfigRaw {
             general: {
                 let mut map = HashMap::new();
-                map.insert("C-c".to_string(), "noop".to_string());
-                map.insert("C-h".to_string(), "show_help".to_string());
+                map.insert("C-c".to_string(), "noop".into());
+                map.insert("C-h".to_string(), "show_help".into());
+                map.insert("A-x".to_string(), "noop".into());
                 map
             },
             text_edit: HashMap::new(),
@@ -1024,7 +1300,7 @@ mod tests {
             branch_select: {
                 let mut map = HashMap::new();
                 // Override the inherited list_navigation C-n binding with noop
-                map.insert("C-n".to_string(), "noop".to_string());
+                map.insert("C-n".to_string(), "noop".into());
                 map
             },
         };
@@ -1062,8 +1338,9 @@ mod tests {
         let raw = KeysConfigRaw {
             general: {
                 let mut map = HashMap::new();
-                map.insert("C-c".to_string(), "noop".to_string());
-                map.insert("C-h".to_string(), "noop".to_string());
+                map.insert("C-c".to_string(), "noop".into());
+                map.insert("C-h".to_string(), "noop".into());
+                map.insert("A-x".to_string(), "noop".into());
                 map
             },
             text_edit: HashMap::new(),
@@ -1071,7 +1348,7 @@ mod tests {
             modal: HashMap::new(),
             repo_select: {
                 let mut map = HashMap::new();
-                map.insert("enter".to_string(), "open_repo".to_string());
+                map.insert("enter".to_string(), "open_repo".into());
                 map
             },
             branch_select: HashMap::new(),
@@ -1133,7 +1410,7 @@ mod tests {
             text_edit: HashMap::new(),
             list_navigation: {
                 let mut map = HashMap::new();
-                map.insert("enter".to_string(), "move_down".to_string());
+                map.insert("enter".to_string(), "move_down".into());
                 map
             },
             modal: HashMap::new(),
@@ -1218,12 +1495,16 @@ mod tests {
             Command::Noop,
             Command::Quit,
             Command::ShowHelp,
+            Command::CommandPalette,
             Command::OpenRepo,
             Command::EnterRepo,
             Command::OpenBranch,
             Command::GoBack,
             Command::NewBranch,
             Command::DeleteWorktree,
+            Command::UndoDelete,
+            Command::FetchRemotes,
+            Command::EditNote,
             Command::MoveUp,
             Command::MoveDown,
             Command::HalfPageUp,
@@ -1267,12 +1548,16 @@ mod tests {
             Command::Noop,
             Command::Quit,
             Command::ShowHelp,
+            Command::CommandPalette,
             Command::OpenRepo,
             Command::EnterRepo,
             Command::OpenBranch,
             Command::GoBack,
             Command::NewBranch,
             Command::DeleteWorktree,
+            Command::UndoDelete,
+            Command::FetchRemotes,
+            Command::EditNote,
             Command::MoveUp,
             Command::MoveDown,
             Command::HalfPageUp,
@@ -1312,8 +1597,8 @@ mod tests {
         let raw = KeysConfigRaw {
             general: {
                 let mut map = HashMap::new();
-                map.insert("C-c".to_string(), "noop".to_string());
-                map.insert("C-h".to_string(), "show_help".to_string());
+                map.insert("C-c".to_string(), "noop".into());
+                map.insert("C-h".to_string(), "show_help".into());
                 map
             },
             text_edit: HashMap::new(),
@@ -1351,6 +1636,9 @@ mod tests {
             Mode::RepoSelect,
             Mode::BranchSelect,
             Mode::SelectBaseBranch,
+            Mode::SelectCloneDir,
+            Mode::NewRepo,
+            Mode::GlobalSearch,
             Mode::ConfirmWorktreeDelete {
                 branch_name: "x".into(),
                 has_session: false,
@@ -1374,6 +1662,9 @@ mod tests {
             Mode::RepoSelect,
             Mode::BranchSelect,
             Mode::SelectBaseBranch,
+            Mode::SelectCloneDir,
+            Mode::NewRepo,
+            Mode::GlobalSearch,
             Mode::ConfirmWorktreeDelete {
                 branch_name: "x".into(),
                 has_session: false,
@@ -1,15 +1,21 @@
+mod include;
 pub mod keys;
+pub mod validate;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fmt::Write as _,
     fs,
     io::Write as _,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
+use crate::{placeholders::SessionContext, project::ProjectKind};
 pub use keys::{Command, KeysConfig};
+pub use validate::{ConfigProblem, validate_config_file, validate_config_str};
 
 pub const APP_NAME: &str = "kiosk";
 
@@ -58,6 +64,160 @@ pub struct Config {
     /// ```
     pub search_dirs: Vec<SearchDirEntry>,
 
+    /// Glob patterns over repo paths to leave out of the repo list and `kiosk list`, e.g.
+    /// archived or vendored repos. `*` matches within one path component and `**` across
+    /// directories; `~` is expanded. Repos can also be hidden from the TUI, which stores
+    /// them in `hidden.toml` in kiosk's state directory until `kiosk unhide` shows them
+    /// again. For example:
+    /// ```toml
+    /// ignore = ["~/Development/vendor/*", "**/archive-*"]
+    /// ```
+    #[serde(default, deserialize_with = "deserialize_globs")]
+    pub ignore: Vec<String>,
+
+    /// How repos and branches are ordered in the TUI, after the current repo and branch.
+    /// `"frecency"` (default) ranks them by how often and how recently you opened them,
+    /// falling back to session activity; `"activity"` ranks by most recent tmux session
+    /// activity; `"alphabetical"` sorts by name. For example:
+    /// ```toml
+    /// sort = "activity"
+    /// ```
+    #[serde(default)]
+    pub sort: SortOrder,
+
+    /// Last-commit details shown after each branch in the branch picker, in order: any of
+    /// `"age"`, `"author"` and `"subject"` (default: all three). An empty list hides them.
+    /// For example:
+    /// ```toml
+    /// commit_columns = ["age", "subject"]
+    /// ```
+    #[serde(default = "default_commit_columns")]
+    pub commit_columns: Vec<CommitColumn>,
+
+    /// Columns of the branch picker, in order, lined up across rows: any of `"name"`,
+    /// `"session"` (session or worktree marker), `"activity"` (how long ago the branch's
+    /// session was last used, coloured by how recent that is, and a heat strip of its
+    /// activity through the last day), `"agent"` (the coding agent running in the branch's
+    /// session), `"ahead_behind"` (commits ahead of and behind its upstream),
+    /// `"last_commit_age"`, `"last_commit_author"` and `"last_commit_subject"`.
+    /// Columns that are empty for every branch on screen are hidden. Defaults to `"name"`,
+    /// `"session"` and `"activity"`, followed by the `commit_columns`. For example:
+    /// ```toml
+    /// branch_columns = ["name", "agent", "ahead_behind", "last_commit_age", "session"]
+    /// ```
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_columns: Option<Vec<BranchColumn>>,
+
+    /// Labels (set with `kiosk label add`) that group branches in the branch picker and
+    /// `kiosk branches`, in this order and ahead of the rest; a branch with several goes with
+    /// the first one listed. Within a group, `sort` applies. For example:
+    /// ```toml
+    /// label_order = ["feature", "agent"]
+    /// ```
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub label_order: Vec<String>,
+
+    /// Other config files to merge in, relative to this file (`~` is expanded). Tables are
+    /// merged key by key, lists such as `search_dirs` are appended, and other values set in
+    /// this file take precedence. For example:
+    /// ```toml
+    /// include = ["work.toml", "~/dotfiles/kiosk/personal.toml"]
+    /// ```
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+
+    /// Named sets of settings merged on top of the rest of the config when kiosk is run with
+    /// `--profile <name>` (or `KIOSK_PROFILE`). A profile takes the same keys as the config
+    /// file, including `include`. For example:
+    /// ```toml
+    /// [profiles.work]
+    /// search_dirs = ["~/Work"]
+    /// session = { split_command = "claude" }
+    /// ```
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+
+    /// Let the TUI take the mouse: click a row to select it, double-click to open it, and
+    /// scroll lists with the wheel. While it's on, hold Shift (Option in some macOS terminals)
+    /// to select text. For example:
+    /// ```toml
+    /// mouse = true
+    /// ```
+    #[serde(default)]
+    pub mouse: bool,
+
+    /// Editor that `open_in_editor` (Alt-e in the repo and branch lists) opens the selected
+    /// repo or worktree in, instead of a tmux session. Falls back to `$VISUAL`, then `$EDITOR`.
+    /// For example:
+    /// ```toml
+    /// editor = "code"
+    /// ```
+    pub editor: Option<String>,
+
+    /// Coding agents that `kiosk open --agent <name>` starts in the session's main pane, in
+    /// place of `--run`. The agent's output is logged as with `kiosk open --log` unless `log`
+    /// is `false`, and `command` can use the same placeholders as `session.split_command`.
+    /// For example:
+    /// ```toml
+    /// [agents.claude]
+    /// command = "claude --dangerously-skip-permissions"
+    ///
+    /// [agents.codex]
+    /// command = "codex"
+    /// log = false
+    /// ```
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub agents: BTreeMap<String, AgentProfile>,
+
+    /// Agent from `agents` that `open_with_agent` (Alt-a in the branch list) starts in the
+    /// selected branch's session. Defaults to the only agent when just one is configured.
+    /// For example:
+    /// ```toml
+    /// default_agent = "claude"
+    /// ```
+    pub default_agent: Option<String>,
+
+    /// Named sets of branches that `kiosk workspace open <name>` (or the workspaces view,
+    /// Alt-w in the repo list) opens together, each written `repo/branch`, or just `repo` for
+    /// its main checkout. Workspaces can also be created from the branch list, which stores
+    /// them in `workspaces.toml` in kiosk's state directory. For example:
+    /// ```toml
+    /// [workspaces]
+    /// trio = ["frontend/main", "backend/main", "infra"]
+    /// ```
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub workspaces: BTreeMap<String, Vec<String>>,
+
+    /// Repos that `kiosk open --link` opens the same branch in, alongside the repo being
+    /// opened. A missing branch is created from `--base` where the repo has it, and from the
+    /// repo's default branch otherwise. They're all saved as a workspace named after the
+    /// branch. For example:
+    /// ```toml
+    /// linked_repos = ["api", "web"]
+    /// ```
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub linked_repos: Vec<String>,
+
+    /// Terminal multiplexer that hosts sessions. `"auto"` (default) uses tmux when it's
+    /// installed and WezTerm otherwise, e.g. on native Windows; `"tmux"` and `"wezterm"` force
+    /// one. Under WezTerm each session is a workspace, and `kiosk open --log` isn't available.
+    /// For example:
+    /// ```toml
+    /// multiplexer = "wezterm"
+    /// ```
+    #[serde(default)]
+    pub multiplexer: Multiplexer,
+
+    /// Glyphs for repos, branches, sessions and the like in the TUI, and for agent states and
+    /// uncommitted changes in `kiosk statusline`. `"nerd"` needs a Nerd Font, `"unicode"` uses
+    /// plain Unicode symbols and `"ascii"` only ASCII. `"auto"` (default) uses Unicode unless
+    /// the locale (`LC_ALL`, `LC_CTYPE` or `LANG`) isn't UTF-8. For example:
+    /// ```toml
+    /// icons = "nerd"
+    /// ```
+    #[serde(default)]
+    pub icons: Icons,
+
     /// Layout when creating a new tmux session.
     #[serde(default)]
     pub session: SessionConfig,
@@ -68,8 +228,132 @@ pub struct Config {
 
     /// Key binding configuration.
     /// To unbind an inherited key mapping, assign it to `noop`.
+    /// A key mapped to a list of commands runs them in order, e.g.
+    /// `"C-o" = ["enter_repo", "move_down", "open_branch"]`.
     #[serde(default)]
     pub keys: KeysConfig,
+
+    /// Worktree removal behaviour.
+    #[serde(default)]
+    pub clean: CleanConfig,
+
+    /// Retention for session logs written by `kiosk open --log`. Limits are applied whenever
+    /// kiosk starts a log or reads logs with `kiosk log`.
+    #[serde(default)]
+    pub log: LogConfig,
+
+    /// Idle sessions killed by `kiosk reap`.
+    #[serde(default)]
+    pub reap: ReapConfig,
+
+    /// Limits on the git commands kiosk runs.
+    #[serde(default)]
+    pub git: GitConfig,
+
+    /// Background fetching of remotes when a repo's branch list opens.
+    #[serde(default)]
+    pub fetch: FetchConfig,
+
+    /// Limits on the tmux commands kiosk runs.
+    #[serde(default)]
+    pub tmux: TmuxConfig,
+
+    /// Creating repos from the repo list with `new_repo`.
+    #[serde(default)]
+    pub new_repo: NewRepoConfig,
+
+    /// Creating branches from the branch list with `new_branch`.
+    #[serde(default)]
+    pub branch: BranchConfig,
+
+    /// Naming new branches after Jira or Linear tickets.
+    #[serde(default)]
+    pub ticket: TicketConfig,
+
+    /// Commands and webhooks run when an agent needs attention, while `kiosk agents record`
+    /// samples sessions.
+    #[serde(default)]
+    pub agent: AgentConfig,
+
+    /// Matching of the search in the TUI's lists.
+    #[serde(default)]
+    pub search: SearchConfig,
+
+    /// A Rhai script with functions kiosk calls on events and from key bindings.
+    #[serde(default)]
+    pub script: ScriptConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Frecency,
+    Activity,
+    Alphabetical,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitColumn {
+    Age,
+    Author,
+    Subject,
+}
+
+impl CommitColumn {
+    pub const ALL: [Self; 3] = [Self::Age, Self::Author, Self::Subject];
+}
+
+fn default_commit_columns() -> Vec<CommitColumn> {
+    CommitColumn::ALL.to_vec()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BranchColumn {
+    Name,
+    Session,
+    Activity,
+    Agent,
+    AheadBehind,
+    LastCommitAge,
+    LastCommitAuthor,
+    LastCommitSubject,
+}
+
+impl BranchColumn {
+    /// The columns ahead of the `commit_columns` when `branch_columns` isn't set.
+    pub const LEADING: [Self; 3] = [Self::Name, Self::Session, Self::Activity];
+}
+
+impl From<CommitColumn> for BranchColumn {
+    fn from(column: CommitColumn) -> Self {
+        match column {
+            CommitColumn::Age => Self::LastCommitAge,
+            CommitColumn::Author => Self::LastCommitAuthor,
+            CommitColumn::Subject => Self::LastCommitSubject,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Multiplexer {
+    #[default]
+    Auto,
+    Tmux,
+    Wezterm,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Icons {
+    #[default]
+    Auto,
+    Nerd,
+    Unicode,
+    Ascii,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -81,61 +365,797 @@ pub struct SessionConfig {
     /// [session]
     /// split_command = "hx"
     /// ```
+    /// This, a template's `split_command` and `run`, and `kiosk open --run` can use the
+    /// placeholders `{repo}`, `{branch}`, `{worktree}`, `{session}` and `{base}` (the branch
+    /// a new branch was created from, else the repo's default branch), each filled in as one
+    /// shell word, quoted where needed:
+    /// ```toml
+    /// [session]
+    /// split_command = "hx {worktree}/README.md"
+    /// ```
+    pub split_command: Option<String>,
+
+    /// Settings for new sessions in repos of a given kind, told apart by the file at the
+    /// worktree root: `rust` (`Cargo.toml`), `node` (`package.json`), `go` (`go.mod`) or
+    /// `python` (`pyproject.toml`). A template's `split_command` replaces the one above, and
+    /// `run` is typed into the main pane once the session is created (`kiosk open --run`
+    /// takes its place). For example:
+    /// ```toml
+    /// [session.templates.rust]
+    /// split_command = "bacon"
+    ///
+    /// [session.templates.node]
+    /// run = "npm run dev"
+    /// ```
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<ProjectKind, SessionTemplate>,
+
+    /// Most kiosk sessions to keep alive. Opening a session closes the least recently active
+    /// ones beyond this, unless a client is attached to them. For example:
+    /// ```toml
+    /// [session]
+    /// max_alive = 8
+    /// ```
+    pub max_alive: Option<usize>,
+
+    /// Name each new session's window after its branch (or repo, on a detached `HEAD`), and
+    /// title the pane an agent runs in after the agent (e.g. `claude`) when kiosk opens its
+    /// session. For example:
+    /// ```toml
+    /// [session]
+    /// set_titles = true
+    /// ```
+    #[serde(default)]
+    pub set_titles: bool,
+
+    /// Environment variables set on new sessions before their shell starts. `KIOSK_REPO`,
+    /// `KIOSK_BRANCH`, `KIOSK_WORKTREE`, `KIOSK_SESSION` and `KIOSK_BASE` are always set; these
+    /// add to or replace them. For example:
+    /// ```toml
+    /// [session.env]
+    /// RUST_LOG = "debug"
+    /// ```
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+
+    /// Environment variables for new sessions in one repo, by repo name. These replace the
+    /// ones in `[session.env]`, and `kiosk open --env` replaces both. For example:
+    /// ```toml
+    /// [session.repo_env.kiosk]
+    /// DATABASE_URL = "postgres://localhost/kiosk"
+    /// ```
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub repo_env: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl SessionConfig {
+    /// Settings for a new session in `dir`.
+    pub fn for_dir(&self, dir: &Path) -> SessionTemplate {
+        resolve_session(self.split_command.as_deref(), &self.templates, dir)
+    }
+
+    /// Environment for a new session: kiosk's own variables, then `[session.env]`, the repo's
+    /// `[session.repo_env]` and finally `overrides`, each replacing what came before.
+    pub fn env_for(
+        &self,
+        context: &SessionContext,
+        overrides: &[(String, String)],
+    ) -> Vec<(String, String)> {
+        let mut env: BTreeMap<String, String> = context.env().into_iter().collect();
+        let configured = self
+            .env
+            .iter()
+            .chain(self.repo_env.get(&context.repo).into_iter().flatten());
+        for (key, value) in configured.chain(overrides.iter().map(|(k, v)| (k, v))) {
+            env.insert(key.clone(), value.clone());
+        }
+        env.into_iter().collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SessionTemplate {
     pub split_command: Option<String>,
+    pub run: Option<String>,
+}
+
+impl SessionTemplate {
+    /// This template with the placeholders in its commands filled in from `context`.
+    #[must_use]
+    pub fn expanded(&self, context: &SessionContext) -> Self {
+        Self {
+            split_command: self.split_command.as_deref().map(|cmd| context.expand(cmd)),
+            run: self.run.as_deref().map(|cmd| context.expand(cmd)),
+        }
+    }
+}
+
+/// The template for `dir`'s project kind, with `split_command` filling in when the template
+/// doesn't set one.
+pub fn resolve_session(
+    split_command: Option<&str>,
+    templates: &BTreeMap<ProjectKind, SessionTemplate>,
+    dir: &Path,
+) -> SessionTemplate {
+    let template = ProjectKind::detect(dir)
+        .and_then(|kind| templates.get(&kind))
+        .cloned()
+        .unwrap_or_default();
+    SessionTemplate {
+        split_command: template
+            .split_command
+            .or_else(|| split_command.map(str::to_string)),
+        run: template.run,
+    }
+}
+
+/// A coding agent `kiosk open --agent` can start, from `[agents.<name>]`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct AgentProfile {
+    pub command: String,
+    #[serde(default = "default_true")]
+    pub log: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CleanConfig {
+    /// Move deleted and orphaned worktrees to the system trash instead of removing them
+    /// permanently. When no system trash is available, they are moved to a timestamped
+    /// quarantine directory under kiosk's state directory instead. For example:
+    /// ```toml
+    /// [clean]
+    /// use_trash = true
+    /// ```
+    #[serde(default)]
+    pub use_trash: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct LogConfig {
+    /// Rotate a session log once it grows past this many megabytes (default: 100). Set to 0
+    /// to disable size-based rotation.
+    pub max_size_mb: u64,
+
+    /// Delete session logs that haven't been written to for this many days (default: 14). A
+    /// session's current log is emptied instead, since tmux may still be writing to it. Set
+    /// to 0 to keep logs indefinitely.
+    pub max_age_days: u64,
+
+    /// Number of rotated files to keep per session (default: 3). For example:
+    /// ```toml
+    /// [log]
+    /// max_size_mb = 50
+    /// max_age_days = 7
+    /// max_files = 1
+    /// ```
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            max_size_mb: 100,
+            max_age_days: 14,
+            max_files: 3,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct ReapConfig {
+    /// Hours a kiosk session can go without activity before `kiosk reap` kills it
+    /// (default: 24). Sessions with a client attached are never reaped.
+    pub idle_hours: u64,
+
+    /// Also remove the worktree of a reaped session once its branch is merged into the repo's
+    /// default branch, as `kiosk delete` would. Worktrees with uncommitted changes or unpushed
+    /// commits are kept. For example:
+    /// ```toml
+    /// [reap]
+    /// idle_hours = 72
+    /// remove_merged = true
+    /// ```
+    pub remove_merged: bool,
+}
+
+impl Default for ReapConfig {
+    fn default() -> Self {
+        Self {
+            idle_hours: 24,
+            remove_merged: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct GitConfig {
+    /// Seconds a git command can run before kiosk stops it and reports a timeout (default:
+    /// 300), so a wedged credential helper can't freeze kiosk. Clones, fetches and creating
+    /// worktrees, which can rightly take longer on a big repo, aren't limited; cancel those
+    /// instead. Set to 0 to wait indefinitely. For example:
+    /// ```toml
+    /// [git]
+    /// command_timeout_secs = 60
+    /// ```
+    pub command_timeout_secs: u64,
+}
+
+impl GitConfig {
+    pub fn command_timeout(&self) -> Option<Duration> {
+        timeout_from_secs(self.command_timeout_secs)
+    }
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            command_timeout_secs: 300,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct FetchConfig {
+    /// Fetch the repo's remotes in the background whenever its branch list opens (default:
+    /// true). When off, remotes are only fetched with `fetch_remotes` (Ctrl-r in the branch
+    /// list). A repo another kiosk is already fetching is left to that one. For example:
+    /// ```toml
+    /// [fetch]
+    /// on_enter = false
+    /// ```
+    pub on_enter: bool,
+
+    /// Remotes to fetch, in the background, with `fetch_remotes`, and with `kiosk fetch`.
+    /// Remotes a repo doesn't have are skipped; an empty list (default) fetches all of them.
+    /// For example:
+    /// ```toml
+    /// [fetch]
+    /// remotes = ["origin"]
+    /// ```
+    pub remotes: Vec<String>,
+
+    /// Least number of seconds between background fetches of the same repo (default: 0, no
+    /// limit). Fetch times are kept in `fetches.toml` in kiosk's state directory, so the
+    /// limit holds across runs and counts fetches by `kiosk fetch`; `fetch_remotes` ignores
+    /// it. For example:
+    /// ```toml
+    /// [fetch]
+    /// min_interval_secs = 900
+    /// ```
+    pub min_interval_secs: u64,
+}
+
+impl FetchConfig {
+    /// Whether `remote` is one of the remotes to fetch.
+    pub fn includes_remote(&self, remote: &str) -> bool {
+        self.remotes.is_empty() || self.remotes.iter().any(|name| name == remote)
+    }
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            on_enter: true,
+            remotes: Vec::new(),
+            min_interval_secs: 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct TmuxConfig {
+    /// Seconds a tmux command can run before kiosk stops it and reports a timeout (default:
+    /// 10). Attaching to a session isn't limited. Set to 0 to wait indefinitely. For example:
+    /// ```toml
+    /// [tmux]
+    /// command_timeout_secs = 30
+    /// ```
+    pub command_timeout_secs: u64,
+
+    /// Name of the tmux server socket to use, as with `tmux -L` (default: tmux's own).
+    /// `kiosk --tmux-socket` takes its place. For example:
+    /// ```toml
+    /// [tmux]
+    /// socket = "work"
+    /// ```
+    pub socket: Option<String>,
+
+    /// Keep one control-mode connection (`tmux -C`) to the server for kiosk's queries
+    /// instead of running tmux for each, and refresh the TUI as sessions come and go or show
+    /// activity (default: false). The connection attaches to a hidden `_kiosk_control`
+    /// session, which closes when kiosk exits. Needs tmux 3.2 or later. For example:
+    /// ```toml
+    /// [tmux]
+    /// control_mode = true
+    /// ```
+    pub control_mode: bool,
+}
+
+impl TmuxConfig {
+    pub fn command_timeout(&self) -> Option<Duration> {
+        timeout_from_secs(self.command_timeout_secs)
+    }
+}
+
+impl Default for TmuxConfig {
+    fn default() -> Self {
+        Self {
+            command_timeout_secs: 10,
+            socket: None,
+            control_mode: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct NewRepoConfig {
+    /// Directory new repos are created in (default: the first of `search_dirs`). Repos made
+    /// outside `search_dirs` are opened, but won't be listed next time. For example:
+    /// ```toml
+    /// [new_repo]
+    /// dir = "~/scratch"
+    /// ```
+    pub dir: Option<String>,
+
+    /// Templates offered by `new_repo` (Ctrl-o in the repo list), which creates a repo named
+    /// after the text in the search bar. Besides these there are always `empty`, which only
+    /// runs `git init`, and `github`, which also creates a private GitHub repo with
+    /// `gh repo create`. A template's `command` runs through `sh -c` in the new repo's
+    /// directory with `{name}` replaced by the repo's name, and the directory is initialised
+    /// with `git init` if the command didn't; `github = true` creates a GitHub repo too. For
+    /// example:
+    /// ```toml
+    /// [new_repo.templates.rust]
+    /// command = "cargo init --name {name}"
+    ///
+    /// [new_repo.templates.python]
+    /// command = "copier copy gh:me/python-template ."
+    /// github = true
+    /// ```
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, RepoTemplate>,
+}
+
+impl NewRepoConfig {
+    /// The templates to offer: the built-in `empty` and `github`, then the configured ones
+    /// (which can replace them), by name.
+    pub fn all_templates(&self) -> BTreeMap<String, RepoTemplate> {
+        let mut templates = BTreeMap::from([
+            ("empty".to_string(), RepoTemplate::default()),
+            (
+                "github".to_string(),
+                RepoTemplate {
+                    command: None,
+                    github: true,
+                },
+            ),
+        ]);
+        templates.extend(self.templates.clone());
+        templates
+    }
+
+    /// Where new repos go: `dir`, or else the first of `search_dirs`.
+    pub fn resolved_dir(&self, search_dirs: &[(PathBuf, u16)]) -> Option<PathBuf> {
+        match &self.dir {
+            Some(dir) => crate::paths::expand_tilde(dir),
+            None => search_dirs.first().map(|(dir, _)| dir.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct BranchConfig {
+    /// Template for the names of branches created from the branch list. `{slug}` is the text
+    /// typed in the search bar, lowercased, with each run of characters other than letters and
+    /// digits turned into `-`. kiosk asks for every other `{field}` in turn, starting `{user}`
+    /// off as `$USER`. For example:
+    /// ```toml
+    /// [branch]
+    /// name_template = "{user}/{ticket}-{slug}"
+    /// ```
+    pub name_template: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketProvider {
+    Jira,
+    Linear,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct TicketConfig {
+    /// Where tickets are looked up: `jira` or `linear` (default: neither). With one set,
+    /// typing a ticket ID like `ABC-123` in the branch list, or passing it to
+    /// `kiosk open --ticket`, fetches the ticket with `curl` and names the new branch after
+    /// it: `{ticket}` in `branch.name_template` is the ID and `{slug}` comes from the
+    /// ticket's title (default template: `"{ticket}-{slug}"`). The ticket's link becomes the
+    /// branch's note. For example:
+    /// ```toml
+    /// [ticket]
+    /// provider = "linear"
+    /// ```
+    pub provider: Option<TicketProvider>,
+
+    /// Your Jira site, needed with `provider = "jira"`. For example:
+    /// ```toml
+    /// [ticket]
+    /// provider = "jira"
+    /// url = "https://acme.atlassian.net"
+    /// email = "me@acme.com"
+    /// ```
+    pub url: Option<String>,
+
+    /// Email of the Jira account the API token belongs to, needed with `provider = "jira"`.
+    pub email: Option<String>,
+
+    /// Environment variable holding the API token (default: `JIRA_API_TOKEN` for Jira,
+    /// `LINEAR_API_KEY` for Linear). For example:
+    /// ```toml
+    /// [ticket]
+    /// token_env = "WORK_LINEAR_KEY"
+    /// ```
+    pub token_env: Option<String>,
+}
+
+impl TicketConfig {
+    /// The environment variable the API token is read from.
+    pub fn token_env(&self, provider: TicketProvider) -> &str {
+        self.token_env.as_deref().unwrap_or(match provider {
+            TicketProvider::Jira => "JIRA_API_TOKEN",
+            TicketProvider::Linear => "LINEAR_API_KEY",
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct AgentConfig {
+    /// What to do when a session's agent starts waiting for input: run `exec` with `sh -c`,
+    /// POST to `url`, or both. Either gets the event as JSON (on stdin, or as the request
+    /// body): `session`, `repo`, `branch`, `agent`, `state`, `since` (seconds since the Unix
+    /// epoch) and a `text` summary, which Slack's incoming webhooks show. `exec` also gets
+    /// `KIOSK_SESSION`, `KIOSK_REPO`, `KIOSK_BRANCH`, `KIOSK_AGENT` and `KIOSK_AGENT_STATE`
+    /// in its environment. For example:
+    /// ```toml
+    /// [agent.on_waiting]
+    /// exec = "notify-send \"$KIOSK_AGENT is waiting\" \"$KIOSK_REPO $KIOSK_BRANCH\""
+    /// url = "https://hooks.slack.com/services/T000/B000/XXXX"
+    /// ```
+    pub on_waiting: Option<AgentHook>,
+
+    /// Like `on_waiting`, for when the agent in a session exits. For example:
+    /// ```toml
+    /// [agent.on_idle]
+    /// exec = "notify-send \"$KIOSK_AGENT finished\" \"$KIOSK_REPO $KIOSK_BRANCH\""
+    /// ```
+    pub on_idle: Option<AgentHook>,
+
+    /// Seconds an agent has to stay waiting or idle before its hook runs (default: 30), so
+    /// one pausing between steps doesn't set it off. Each stretch runs the hook once.
+    /// For example:
+    /// ```toml
+    /// [agent]
+    /// debounce_secs = 120
+    /// ```
+    pub debounce_secs: u64,
+
+    /// Milliseconds between checks while `kiosk wait` or `kiosk open --wait` waits for a
+    /// pane's agent to finish (default: 1000). Raise it to cut the tmux calls made with many
+    /// sessions waiting at once. For example:
+    /// ```toml
+    /// [agent]
+    /// poll_interval_ms = 5000
+    /// ```
+    pub poll_interval_ms: u64,
+
+    /// Repos, by name, where kiosk doesn't look for agents: their sessions show no agent in
+    /// the branch picker, `kiosk status`, `kiosk statusline` or `kiosk waybar`, and aren't
+    /// sampled by `kiosk agents record`. For example:
+    /// ```toml
+    /// [agent]
+    /// skip_repos = ["monorepo"]
+    /// ```
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skip_repos: Vec<String>,
+
+    /// Seconds the TUI reuses the agent found in a pane for (default: 3), so reloading
+    /// branches in quick succession doesn't check every pane again. 0 always checks. For
+    /// example:
+    /// ```toml
+    /// [agent]
+    /// cache_secs = 10
+    /// ```
+    pub cache_secs: u64,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            on_waiting: None,
+            on_idle: None,
+            debounce_secs: 30,
+            poll_interval_ms: 1000,
+            skip_repos: Vec::new(),
+            cache_secs: 3,
+        }
+    }
+}
+
+impl AgentConfig {
+    /// Whether to look for agents in `repo`'s sessions.
+    pub fn detects_in(&self, repo: &str) -> bool {
+        !self.skip_repos.iter().any(|skipped| skipped == repo)
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms.max(1))
+    }
+}
+
+/// A command and/or webhook run by `[agent.on_waiting]` or `[agent.on_idle]`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct AgentHook {
+    /// Shell command to run, given the event as JSON on stdin
+    pub exec: Option<String>,
+    /// Webhook to POST the event to as JSON, with `curl`
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct SearchConfig {
+    /// Algorithm that scores items against the search: `"skim"` (default), or `"nucleo"`,
+    /// the matcher from the Helix editor, which is faster on long lists and favours matches
+    /// at the start of words. For example:
+    /// ```toml
+    /// [search]
+    /// matcher = "nucleo"
+    /// ```
+    pub matcher: SearchMatcher,
+
+    /// `"smart"` (default) ignores case unless the search has an uppercase letter, `"ignore"`
+    /// always ignores it and `"respect"` never does. For example:
+    /// ```toml
+    /// [search]
+    /// case = "ignore"
+    /// ```
+    pub case: SearchCase,
+
+    /// `"fuzzy"` (default) matches items that have each word of the search's characters in
+    /// order, with anything between them; `"substring"` only matches items that contain each
+    /// word as written, as `'word` does in fuzzy mode. For example:
+    /// ```toml
+    /// [search]
+    /// mode = "substring"
+    /// ```
+    pub mode: SearchMode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct ScriptConfig {
+    /// [Rhai](https://rhai.rs) script defining any of `on_open(event)` (a session was
+    /// created), `on_branch_created(event)`, `on_agent_waiting(event)` and
+    /// `on_agent_idle(event)` (while `kiosk agents record` runs), plus functions bound to keys
+    /// as `"script:<function>"`. `event` is a map with `repo`, `branch`, `worktree` and
+    /// `session` (the agent hooks get the fields `[agent.on_waiting]` does). A function can
+    /// return a string to type into the event's session, followed by Enter. Scripts can run
+    /// shell commands with `sh("...")`, which returns stdout, and `print` to kiosk's log. For
+    /// example:
+    /// ```toml
+    /// [script]
+    /// file = "~/.config/kiosk/hooks.rhai"
+    ///
+    /// [keys.branch_select]
+    /// "A-t" = "script:run_tests"
+    /// ```
+    /// with `hooks.rhai`:
+    /// ```rhai
+    /// fn on_agent_idle(event) { "continue" }
+    /// fn run_tests(event) { "cargo test" }
+    /// ```
+    pub file: Option<String>,
+}
+
+impl ScriptConfig {
+    /// The script's path, with `~` expanded.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.file.as_deref().and_then(crate::paths::expand_tilde)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMatcher {
+    #[default]
+    Skim,
+    Nucleo,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchCase {
+    #[default]
+    Smart,
+    Ignore,
+    Respect,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    #[default]
+    Fuzzy,
+    Substring,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RepoTemplate {
+    pub command: Option<String>,
+    #[serde(default)]
+    pub github: bool,
+}
+
+/// A timeout of 0 seconds means there isn't one.
+fn timeout_from_secs(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
 }
 
 // The struct must be defined outside the macro so that xtask's syn parser
 // can discover it for README doc generation.
 #[derive(Debug, Deserialize, Serialize, Clone)]
-#[serde(deny_unknown_fields, default)]
+#[serde(from = "ThemeConfigFile")]
 pub struct ThemeConfig {
+    /// Whether the terminal has a "light" or "dark" background, which picks between the
+    /// `light` and `dark` variants. Detected from the terminal when kiosk starts if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<Background>,
+    /// Slots laid over the rest of `[theme]` on a light background, e.g.
+    /// `light = { preset = "catppuccin-latte" }`. Light backgrounds also start from darker
+    /// `muted` and `border` colors than the defaults below.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light: Option<ThemeLayer>,
+    /// Slots laid over the rest of `[theme]` on a dark background.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dark: Option<ThemeLayer>,
+    /// Built-in palette that the other slots start from, e.g. "catppuccin-mocha". Slots set
+    /// alongside it override the preset's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<ThemePreset>,
     /// Primary accent color (default: "magenta").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub accent: ThemeColor,
+    pub accent: ThemeStyle,
     /// Secondary accent color (default: "cyan").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub secondary: ThemeColor,
+    pub secondary: ThemeStyle,
     /// Tertiary accent color (default: "green").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub tertiary: ThemeColor,
+    pub tertiary: ThemeStyle,
     /// Success/positive color (default: "green").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub success: ThemeColor,
+    pub success: ThemeStyle,
     /// Error color (default: "red").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub error: ThemeColor,
+    pub error: ThemeStyle,
     /// Warning color (default: "yellow").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub warning: ThemeColor,
+    pub warning: ThemeStyle,
     /// Muted/dim text color (default: "`dark_gray`").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub muted: ThemeColor,
+    pub muted: ThemeStyle,
     /// Border color (default: "`dark_gray`").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub border: ThemeColor,
+    pub border: ThemeStyle,
     /// Hint/key binding color (default: "blue").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub hint: ThemeColor,
+    pub hint: ThemeStyle,
     /// Foreground color for highlighted/selected items (default: "black").
-    #[serde(deserialize_with = "deserialize_color")]
-    pub highlight_fg: ThemeColor,
+    pub highlight_fg: ThemeStyle,
+    /// Style of the selected row, laid over the list's own highlight (its accent color behind
+    /// `highlight_fg` text). Unset by default.
+    #[serde(skip_serializing_if = "ThemeStyle::is_empty")]
+    pub selection: ThemeStyle,
+    /// Style of every other row in the repo, branch and worktree search lists, e.g.
+    /// `{ bg = "#313244" }`. Unset by default, so rows aren't striped.
+    #[serde(skip_serializing_if = "ThemeStyle::is_empty")]
+    pub stripe: ThemeStyle,
+    /// The slots set in `[theme]` itself, which [`ThemeConfig::for_background`] works the
+    /// theme out from again.
+    #[serde(skip)]
+    pub base: ThemeLayer,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Background {
+    #[default]
+    Dark,
+    Light,
 }
 
-/// Single source of truth for theme defaults. Generates the `Default` impl
-/// so adding a field only requires updating one place (plus the struct above).
-macro_rules! theme_defaults {
-    ($($field:ident => $color:ident),* $(,)?) => {
+/// Single source of truth for theme slots and their defaults. Generates the `Default` impl,
+/// `ThemeLayer` and the deserialization of `[theme]`, so adding a slot only requires updating
+/// one place (plus the struct above).
+macro_rules! theme_slots {
+    ($($field:ident $(=> $color:ident)?),* $(,)?) => {
         impl Default for ThemeConfig {
             fn default() -> Self {
                 Self {
-                    $($field: ThemeColor::Named(NamedColor::$color)),*
+                    background: None,
+                    light: None,
+                    dark: None,
+                    preset: None,
+                    $($field: ThemeStyle {
+                        $(fg: Some(ThemeColor::Named(NamedColor::$color)),)?
+                        ..ThemeStyle::default()
+                    },)*
+                    base: ThemeLayer::default(),
+                }
+            }
+        }
+
+        /// The slots set in one part of `[theme]`: a preset, then slots that override it.
+        #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+        #[serde(deny_unknown_fields, default)]
+        pub struct ThemeLayer {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub preset: Option<ThemePreset>,
+            $(
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub $field: Option<ThemeStyle>,
+            )*
+        }
+
+        impl ThemeLayer {
+            fn apply(&self, config: &mut ThemeConfig) {
+                if let Some(preset) = self.preset {
+                    preset.apply(config);
                 }
+                $(if let Some(style) = &self.$field {
+                    config.$field = style.clone();
+                })*
+            }
+        }
+
+        /// `[theme]` as written, before its layers are laid over the defaults.
+        #[derive(Default, Deserialize)]
+        #[serde(deny_unknown_fields, default)]
+        struct ThemeConfigFile {
+            background: Option<Background>,
+            light: Option<ThemeLayer>,
+            dark: Option<ThemeLayer>,
+            preset: Option<ThemePreset>,
+            $($field: Option<ThemeStyle>,)*
+        }
+
+        impl From<ThemeConfigFile> for ThemeConfig {
+            fn from(file: ThemeConfigFile) -> Self {
+                let config = Self {
+                    background: file.background,
+                    light: file.light,
+                    dark: file.dark,
+                    base: ThemeLayer {
+                        preset: file.preset,
+                        $($field: file.$field,)*
+                    },
+                    ..Self::default()
+                };
+                config.for_background(file.background.unwrap_or_default())
             }
         }
     };
 }
 
-theme_defaults! {
+theme_slots! {
     accent       => Magenta,
     secondary    => Cyan,
     tertiary     => Green,
@@ -146,6 +1166,264 @@ theme_defaults! {
     border       => DarkGray,
     hint         => Blue,
     highlight_fg => Black,
+    selection,
+    stripe,
+}
+
+impl ThemeConfig {
+    /// The theme for a terminal with the given background: the defaults for it, then the
+    /// slots set in `[theme]`, then its `light` or `dark` variant.
+    #[must_use]
+    pub fn for_background(&self, background: Background) -> Self {
+        let mut config = Self {
+            background: self.background,
+            light: self.light.clone(),
+            dark: self.dark.clone(),
+            base: self.base.clone(),
+            ..Self::default()
+        };
+        if background == Background::Light {
+            // `dark_gray` is barely visible on many light color schemes
+            config.muted = ThemeColor::from_hex(0x6c_6f85).into();
+            config.border = ThemeColor::from_hex(0x9c_a0b0).into();
+        }
+        self.base.apply(&mut config);
+        let variant = match background {
+            Background::Light => &self.light,
+            Background::Dark => &self.dark,
+        };
+        if let Some(variant) = variant {
+            variant.apply(&mut config);
+        }
+        config
+    }
+}
+
+/// Single source of truth for the built-in palettes: each preset's config name and the
+/// colors it gives to slots (as `0xrrggbb`). Slots a preset doesn't name are left as they are.
+macro_rules! define_theme_presets {
+    ($(
+        $variant:ident {
+            name: $name:literal,
+            $($slot:ident: $hex:literal),* $(,)?
+        }
+    ),* $(,)?) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+        pub enum ThemePreset {
+            $(#[serde(rename = $name)] $variant),*
+        }
+
+        impl ThemePreset {
+            /// All presets with their config names.
+            pub const fn all() -> &'static [(&'static str, ThemePreset)] {
+                &[$(($name, ThemePreset::$variant)),*]
+            }
+
+            /// The default theme with this preset's colors.
+            pub fn config(self) -> ThemeConfig {
+                let mut config = ThemeConfig::default();
+                self.apply(&mut config);
+                config
+            }
+
+            fn apply(self, config: &mut ThemeConfig) {
+                config.preset = Some(self);
+                match self {
+                    $(ThemePreset::$variant => {
+                        $(config.$slot = ThemeColor::from_hex($hex).into();)*
+                    })*
+                }
+            }
+        }
+    };
+}
+
+define_theme_presets! {
+    CatppuccinMocha {
+        name: "catppuccin-mocha",
+        accent: 0xcb_a6f7,
+        secondary: 0x89_dceb,
+        tertiary: 0xa6_e3a1,
+        success: 0xa6_e3a1,
+        error: 0xf3_8ba8,
+        warning: 0xf9_e2af,
+        muted: 0x6c_7086,
+        border: 0x58_5b70,
+        hint: 0x89_b4fa,
+        highlight_fg: 0x11_111b,
+    },
+    CatppuccinLatte {
+        name: "catppuccin-latte",
+        accent: 0x88_39ef,
+        secondary: 0x04_a5e5,
+        tertiary: 0x40_a02b,
+        success: 0x40_a02b,
+        error: 0xd2_0f39,
+        warning: 0xdf_8e1d,
+        muted: 0x9c_a0b0,
+        border: 0xac_b0be,
+        hint: 0x1e_66f5,
+        highlight_fg: 0xef_f1f5,
+    },
+    Gruvbox {
+        name: "gruvbox",
+        accent: 0xd3_869b,
+        secondary: 0x8e_c07c,
+        tertiary: 0xb8_bb26,
+        success: 0xb8_bb26,
+        error: 0xfb_4934,
+        warning: 0xfa_bd2f,
+        muted: 0x92_8374,
+        border: 0x50_4945,
+        hint: 0x83_a598,
+        highlight_fg: 0x28_2828,
+    },
+    Nord {
+        name: "nord",
+        accent: 0xb4_8ead,
+        secondary: 0x88_c0d0,
+        tertiary: 0xa3_be8c,
+        success: 0xa3_be8c,
+        error: 0xbf_616a,
+        warning: 0xeb_cb8b,
+        muted: 0x61_6e88,
+        border: 0x4c_566a,
+        hint: 0x81_a1c1,
+        highlight_fg: 0x2e_3440,
+    },
+    Dracula {
+        name: "dracula",
+        accent: 0xbd_93f9,
+        secondary: 0x8b_e9fd,
+        tertiary: 0x50_fa7b,
+        success: 0x50_fa7b,
+        error: 0xff_5555,
+        warning: 0xf1_fa8c,
+        muted: 0x62_72a4,
+        border: 0x44_475a,
+        hint: 0xff_79c6,
+        highlight_fg: 0x28_2a36,
+    },
+    TokyoNight {
+        name: "tokyo-night",
+        accent: 0xbb_9af7,
+        secondary: 0x7d_cfff,
+        tertiary: 0x9e_ce6a,
+        success: 0x9e_ce6a,
+        error: 0xf7_768e,
+        warning: 0xe0_af68,
+        muted: 0x56_5f89,
+        border: 0x3b_4261,
+        hint: 0x7a_a2f7,
+        highlight_fg: 0x1a_1b26,
+    },
+}
+
+/// A theme slot: written as just a color (the foreground), or as a table such as
+/// `{ fg = "#ff00ff", bg = "black", bold = true }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ThemeStyle {
+    pub fg: Option<ThemeColor>,
+    pub bg: Option<ThemeColor>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub dim: bool,
+}
+
+/// Keys accepted in a theme slot's table.
+const THEME_STYLE_FIELDS: &[&str] = &["fg", "bg", "bold", "italic", "underline", "dim"];
+
+impl ThemeStyle {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn modifiers(&self) -> [(&'static str, bool); 4] {
+        [
+            ("bold", self.bold),
+            ("italic", self.italic),
+            ("underline", self.underline),
+            ("dim", self.dim),
+        ]
+    }
+}
+
+impl From<ThemeColor> for ThemeStyle {
+    fn from(color: ThemeColor) -> Self {
+        Self {
+            fg: Some(color),
+            ..Self::default()
+        }
+    }
+}
+
+impl Serialize for ThemeStyle {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let modifiers = self.modifiers();
+        if let Some(fg) = &self.fg
+            && self.bg.is_none()
+            && modifiers.iter().all(|(_, on)| !on)
+        {
+            return fg.serialize(serializer);
+        }
+        let mut map = serializer.serialize_map(None)?;
+        for (key, color) in [("fg", &self.fg), ("bg", &self.bg)] {
+            if let Some(color) = color {
+                map.serialize_entry(key, color)?;
+            }
+        }
+        for (key, on) in modifiers {
+            if on {
+                map.serialize_entry(key, &true)?;
+            }
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ThemeStyle {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StyleVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for StyleVisitor {
+            type Value = ThemeStyle;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a color or a table of fg, bg, bold, italic, underline and dim")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<ThemeStyle, E> {
+                parse_color(s).map(ThemeStyle::from)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<ThemeStyle, A::Error> {
+                let mut style = ThemeStyle::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "fg" => style.fg = Some(map.next_value()?),
+                        "bg" => style.bg = Some(map.next_value()?),
+                        "bold" => style.bold = map.next_value()?,
+                        "italic" => style.italic = map.next_value()?,
+                        "underline" => style.underline = map.next_value()?,
+                        "dim" => style.dim = map.next_value()?,
+                        other => {
+                            return Err(serde::de::Error::unknown_field(other, THEME_STYLE_FIELDS));
+                        }
+                    }
+                }
+                Ok(style)
+            }
+        }
+
+        deserializer.deserialize_any(StyleVisitor)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -224,6 +1502,12 @@ impl Serialize for ThemeColor {
 }
 
 impl ThemeColor {
+    /// An RGB color from `0xrrggbb`.
+    pub const fn from_hex(hex: u32) -> Self {
+        let [_, r, g, b] = hex.to_be_bytes();
+        Self::Rgb(r, g, b)
+    }
+
     pub fn parse(s: &str) -> Option<Self> {
         if let Some(hex) = s.strip_prefix('#')
             && hex.len() == 6
@@ -242,21 +1526,57 @@ impl ThemeColor {
     }
 }
 
-fn deserialize_color<'de, D>(deserializer: D) -> Result<ThemeColor, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let s = String::deserialize(deserializer)?;
-    ThemeColor::parse(&s).ok_or_else(|| {
+impl<'de> Deserialize<'de> for ThemeColor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse_color(&s)
+    }
+}
+
+fn parse_color<E: serde::de::Error>(s: &str) -> Result<ThemeColor, E> {
+    ThemeColor::parse(s).ok_or_else(|| {
         let names: Vec<&str> = NamedColor::all().iter().map(|(name, _)| *name).collect();
-        serde::de::Error::custom(format!(
+        E::custom(format!(
             "invalid color '{s}': expected a named color ({}) or hex (#rrggbb)",
             names.join(", "),
         ))
     })
 }
 
+fn deserialize_globs<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let patterns = Vec::<String>::deserialize(deserializer)?;
+    for pattern in &patterns {
+        glob::Pattern::new(pattern).map_err(|e| {
+            serde::de::Error::custom(format!("invalid glob pattern '{pattern}': {e}"))
+        })?;
+    }
+    Ok(patterns)
+}
+
 impl Config {
+    /// The agent `open_with_agent` starts: `default_agent`, or the only one in `agents`.
+    pub fn default_agent(&self) -> Option<&AgentProfile> {
+        match &self.default_agent {
+            Some(name) => self.agents.get(name),
+            None if self.agents.len() == 1 => self.agents.values().next(),
+            None => None,
+        }
+    }
+
+    /// The branch picker's columns: `branch_columns`, or else the name, session and
+    /// activity followed by the `commit_columns`.
+    pub fn branch_columns(&self) -> Vec<BranchColumn> {
+        self.branch_columns.clone().unwrap_or_else(|| {
+            BranchColumn::LEADING
+                .into_iter()
+                .chain(self.commit_columns.iter().copied().map(BranchColumn::from))
+                .collect()
+        })
+    }
+
     pub fn resolved_search_dirs(&self) -> Vec<(PathBuf, u16)> {
         self.search_dirs
             .iter()
@@ -323,6 +1643,13 @@ pub fn format_default_config(dirs: &[String]) -> String {
 /// Creates parent directories as needed. Returns the path written to.
 pub fn write_default_config(dirs: &[String]) -> Result<PathBuf> {
     let path = config_file();
+    write_default_config_at(&path, dirs)?;
+    Ok(path)
+}
+
+/// Write a default config file with the specified search directories to `path`, which must
+/// not exist yet. Creates parent directories as needed.
+pub fn write_default_config_at(path: &Path, dirs: &[String]) -> Result<()> {
     if let Some(dir) = path.parent() {
         fs::create_dir_all(dir)?;
     }
@@ -330,7 +1657,7 @@ pub fn write_default_config(dirs: &[String]) -> Result<PathBuf> {
     let mut file = match fs::OpenOptions::new()
         .write(true)
         .create_new(true)
-        .open(&path)
+        .open(path)
     {
         Ok(file) => file,
         Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
@@ -339,20 +1666,65 @@ pub fn write_default_config(dirs: &[String]) -> Result<PathBuf> {
         Err(e) => return Err(e.into()),
     };
     file.write_all(content.as_bytes())?;
-    Ok(path)
+    Ok(())
 }
 
-pub fn load_config(config_override: Option<&Path>) -> Result<Config> {
-    let config_file = match config_override {
-        Some(path) => path.to_path_buf(),
-        None => config_file(),
-    };
+/// The config file kiosk reads: `config_override` if given, otherwise the default location.
+pub fn config_file_path(config_override: Option<&Path>) -> PathBuf {
+    config_override.map_or_else(config_file, Path::to_path_buf)
+}
+
+/// Where a config is loaded from, kept so it can be loaded again when it changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    /// Profile merged on top, from `--profile`
+    pub profile: Option<String>,
+}
+
+impl ConfigSource {
+    /// Load the config and list the files it was read from; see [`load_config_with_files`].
+    pub fn load_with_files(&self) -> Result<(Config, Vec<PathBuf>)> {
+        load_config_with_files(Some(&self.path), self.profile.as_deref())
+    }
+}
+
+/// Load the config file, merging in its includes and the settings of `profile`.
+pub fn load_config(config_override: Option<&Path>, profile: Option<&str>) -> Result<Config> {
+    load_config_with_files(config_override, profile).map(|(config, _)| config)
+}
+
+/// Like [`load_config`], also returning every file the config was read from: the config
+/// file followed by any files it includes.
+pub fn load_config_with_files(
+    config_override: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<(Config, Vec<PathBuf>)> {
+    let config_file = config_file_path(config_override);
     if !config_file.exists() {
         anyhow::bail!("Config file not found at {}", config_file.display());
     }
     let contents = fs::read_to_string(&config_file)?;
-    let config: Config = toml::from_str(&contents)?;
-    Ok(config)
+    let table: toml::Table = toml::from_str(&contents)?;
+    if !include::needs_merge(&table, profile) {
+        // Deserialize from the text so errors point at a line in the file
+        let config: Config = toml::from_str(&contents)?;
+        return Ok((config, vec![config_file]));
+    }
+
+    let (merged, files) = include::read_merged(&config_file, profile)?;
+    let config = Config::deserialize(merged).map_err(|e| {
+        anyhow::anyhow!(
+            "{} (in {} merged with {})",
+            e.to_string().trim_end(),
+            config_file.display(),
+            profile.map_or_else(
+                || "its includes".to_string(),
+                |profile| format!("profile '{profile}'")
+            )
+        )
+    })?;
+    Ok((config, files))
 }
 
 #[cfg(test)]
@@ -366,6 +1738,131 @@ mod tests {
             matches!(&config.search_dirs[0], SearchDirEntry::Simple(s) if s == "~/Development")
         );
         assert!(config.session.split_command.is_none());
+        assert!(!config.clean.use_trash);
+        assert_eq!(config.log, LogConfig::default());
+        assert_eq!(config.git.command_timeout(), Some(Duration::from_mins(5)));
+        assert_eq!(config.tmux.command_timeout(), Some(Duration::from_secs(10)));
+        assert_eq!(config.fetch, FetchConfig::default());
+        assert!(config.fetch.on_enter);
+    }
+
+    #[test]
+    fn test_command_timeouts_can_be_disabled() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[git]
+command_timeout_secs = 0
+
+[tmux]
+command_timeout_secs = 30
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.git.command_timeout(), None);
+        assert_eq!(config.tmux.command_timeout(), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_agent_detection_can_be_tuned_and_skipped_per_repo() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[agent]
+poll_interval_ms = 250
+skip_repos = ["monorepo"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.agent.poll_interval(), Duration::from_millis(250));
+        assert!(!config.agent.detects_in("monorepo"));
+        assert!(config.agent.detects_in("kiosk"));
+        assert_eq!(
+            AgentConfig::default().poll_interval(),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn test_search_settings() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[search]
+matcher = "nucleo"
+case = "respect"
+mode = "substring"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.search,
+            SearchConfig {
+                matcher: SearchMatcher::Nucleo,
+                case: SearchCase::Respect,
+                mode: SearchMode::Substring,
+            }
+        );
+        let default = load_config_from_str("search_dirs = [\"~/Development\"]").unwrap();
+        assert_eq!(default.search.matcher, SearchMatcher::Skim);
+        assert_eq!(default.search.case, SearchCase::Smart);
+        assert_eq!(default.search.mode, SearchMode::Fuzzy);
+        assert!(load_config_from_str("search_dirs = []\n[search]\nmatcher = \"fzf\"").is_err());
+    }
+
+    #[test]
+    fn test_fetch_remotes_limit_which_remotes_are_fetched() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[fetch]
+on_enter = false
+remotes = ["origin"]
+min_interval_secs = 900
+"#,
+        )
+        .unwrap();
+        assert!(!config.fetch.on_enter);
+        assert_eq!(config.fetch.min_interval_secs, 900);
+        assert!(config.fetch.includes_remote("origin"));
+        assert!(!config.fetch.includes_remote("upstream"));
+        assert!(FetchConfig::default().includes_remote("upstream"));
+    }
+
+    #[test]
+    fn test_new_repo_templates_extend_the_built_in_ones() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[new_repo]
+dir = "/tmp/scratch"
+
+[new_repo.templates.rust]
+command = "cargo init --name {name}"
+github = true
+"#,
+        )
+        .unwrap();
+        let templates = config.new_repo.all_templates();
+        assert_eq!(
+            templates.keys().collect::<Vec<_>>(),
+            ["empty", "github", "rust"]
+        );
+        assert!(templates["github"].github && templates["github"].command.is_none());
+        assert!(templates["rust"].github);
+        assert_eq!(
+            config.new_repo.resolved_dir(&[(PathBuf::from("/dev"), 1)]),
+            Some(PathBuf::from("/tmp/scratch"))
+        );
+        assert_eq!(
+            NewRepoConfig::default().resolved_dir(&[(PathBuf::from("/dev"), 1)]),
+            Some(PathBuf::from("/dev"))
+        );
     }
 
     #[test]
@@ -376,6 +1873,13 @@ search_dirs = ["~/Development", "~/Work"]
 
 [session]
 split_command = "hx"
+
+[clean]
+use_trash = true
+
+[log]
+max_size_mb = 5
+max_files = 1
 "#,
         )
         .unwrap();
@@ -385,6 +1889,92 @@ split_command = "hx"
         );
         assert!(matches!(&config.search_dirs[1], SearchDirEntry::Simple(s) if s == "~/Work"));
         assert_eq!(config.session.split_command.as_deref(), Some("hx"));
+        assert!(config.clean.use_trash);
+        assert_eq!(config.log.max_size_mb, 5);
+        assert_eq!(config.log.max_age_days, LogConfig::default().max_age_days);
+        assert_eq!(config.log.max_files, 1);
+    }
+
+    #[test]
+    fn test_default_agent_falls_back_to_the_only_one() {
+        let mut config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[agents.claude]
+command = "claude --dangerously-skip-permissions"
+"#,
+        )
+        .unwrap();
+        let claude = AgentProfile {
+            command: "claude --dangerously-skip-permissions".to_string(),
+            log: true,
+        };
+        assert_eq!(config.default_agent(), Some(&claude));
+
+        let codex = AgentProfile {
+            command: "codex".to_string(),
+            log: false,
+        };
+        config.agents.insert("codex".to_string(), codex.clone());
+        assert_eq!(config.default_agent(), None);
+        config.default_agent = Some("codex".to_string());
+        assert_eq!(config.default_agent(), Some(&codex));
+    }
+
+    #[test]
+    fn test_session_templates_follow_the_project_kind() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[session]
+split_command = "hx"
+
+[session.templates.rust]
+split_command = "bacon"
+
+[session.templates.node]
+run = "npm run dev"
+"#,
+        )
+        .unwrap();
+
+        let rust = tempfile::tempdir().unwrap();
+        fs::write(rust.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(
+            config.session.for_dir(rust.path()),
+            SessionTemplate {
+                split_command: Some("bacon".to_string()),
+                run: None,
+            }
+        );
+
+        let node = tempfile::tempdir().unwrap();
+        fs::write(node.path().join("package.json"), "{}").unwrap();
+        assert_eq!(
+            config.session.for_dir(node.path()),
+            SessionTemplate {
+                split_command: Some("hx".to_string()),
+                run: Some("npm run dev".to_string()),
+            }
+        );
+
+        let other = tempfile::tempdir().unwrap();
+        assert_eq!(
+            config.session.for_dir(other.path()),
+            SessionTemplate {
+                split_command: Some("hx".to_string()),
+                run: None,
+            }
+        );
+
+        assert!(
+            load_config_from_str(
+                "search_dirs = []\n[session.templates.haskell]\nrun = \"stack build\"\n"
+            )
+            .is_err()
+        );
     }
 
     #[test]
@@ -404,6 +1994,30 @@ unknown_field = true
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_ignore_globs_are_validated() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+ignore = ["~/Development/vendor/*"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.ignore, vec!["~/Development/vendor/*"]);
+
+        let error = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+ignore = ["[unclosed"]
+"#,
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("invalid glob pattern"),
+            "{error}"
+        );
+    }
+
     #[test]
     fn test_tilde_expansion() {
         let config =
@@ -420,19 +2034,20 @@ unknown_field = true
     #[test]
     fn test_theme_config_defaults() {
         let config = load_config_from_str(r#"search_dirs = ["~/Development"]"#).unwrap();
-        assert_eq!(config.theme.accent, ThemeColor::Named(NamedColor::Magenta));
-        assert_eq!(config.theme.secondary, ThemeColor::Named(NamedColor::Cyan));
-        assert_eq!(config.theme.tertiary, ThemeColor::Named(NamedColor::Green));
-        assert_eq!(config.theme.success, ThemeColor::Named(NamedColor::Green));
-        assert_eq!(config.theme.error, ThemeColor::Named(NamedColor::Red));
-        assert_eq!(config.theme.warning, ThemeColor::Named(NamedColor::Yellow));
-        assert_eq!(config.theme.muted, ThemeColor::Named(NamedColor::DarkGray));
-        assert_eq!(config.theme.border, ThemeColor::Named(NamedColor::DarkGray));
-        assert_eq!(config.theme.hint, ThemeColor::Named(NamedColor::Blue));
-        assert_eq!(
-            config.theme.highlight_fg,
-            ThemeColor::Named(NamedColor::Black)
-        );
+        let fg = |color| Some(ThemeColor::Named(color));
+        assert_eq!(config.theme.preset, None);
+        assert_eq!(config.theme.accent.fg, fg(NamedColor::Magenta));
+        assert_eq!(config.theme.secondary.fg, fg(NamedColor::Cyan));
+        assert_eq!(config.theme.tertiary.fg, fg(NamedColor::Green));
+        assert_eq!(config.theme.success.fg, fg(NamedColor::Green));
+        assert_eq!(config.theme.error.fg, fg(NamedColor::Red));
+        assert_eq!(config.theme.warning.fg, fg(NamedColor::Yellow));
+        assert_eq!(config.theme.muted.fg, fg(NamedColor::DarkGray));
+        assert_eq!(config.theme.border.fg, fg(NamedColor::DarkGray));
+        assert_eq!(config.theme.hint.fg, fg(NamedColor::Blue));
+        assert_eq!(config.theme.highlight_fg.fg, fg(NamedColor::Black));
+        assert!(config.theme.selection.is_empty());
+        assert!(config.theme.stripe.is_empty());
     }
 
     #[test]
@@ -447,9 +2062,164 @@ secondary = "#ff00ff"
 "##,
         )
         .unwrap();
-        assert_eq!(config.theme.accent, ThemeColor::Named(NamedColor::Blue));
-        assert_eq!(config.theme.secondary, ThemeColor::Rgb(255, 0, 255));
-        assert_eq!(config.theme.success, ThemeColor::Named(NamedColor::Green));
+        assert_eq!(
+            config.theme.accent,
+            ThemeStyle::from(ThemeColor::Named(NamedColor::Blue))
+        );
+        assert_eq!(
+            config.theme.secondary,
+            ThemeStyle::from(ThemeColor::Rgb(255, 0, 255))
+        );
+        assert_eq!(
+            config.theme.success,
+            ThemeStyle::from(ThemeColor::Named(NamedColor::Green))
+        );
+    }
+
+    #[test]
+    fn test_theme_slot_table() {
+        let config = load_config_from_str(
+            r##"
+search_dirs = ["~/Development"]
+
+[theme]
+accent = { fg = "#ff00ff", bold = true }
+selection = { bg = "#313244", italic = true }
+"##,
+        )
+        .unwrap();
+        assert_eq!(
+            config.theme.accent,
+            ThemeStyle {
+                fg: Some(ThemeColor::Rgb(255, 0, 255)),
+                bold: true,
+                ..ThemeStyle::default()
+            }
+        );
+        assert_eq!(
+            config.theme.selection,
+            ThemeStyle {
+                bg: Some(ThemeColor::Rgb(0x31, 0x32, 0x44)),
+                italic: true,
+                ..ThemeStyle::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_theme_slot_unknown_key_rejected() {
+        let err = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[theme]
+accent = { fg = "blue", blink = true }
+"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("unknown field `blink`"), "Error was: {err}");
+    }
+
+    #[test]
+    fn test_theme_preset_with_overrides() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[theme]
+preset = "gruvbox"
+accent = "red"
+"#,
+        )
+        .unwrap();
+        let gruvbox = ThemePreset::Gruvbox.config();
+        assert_eq!(config.theme.preset, Some(ThemePreset::Gruvbox));
+        assert_eq!(
+            config.theme.accent,
+            ThemeStyle::from(ThemeColor::Named(NamedColor::Red))
+        );
+        assert_eq!(config.theme.secondary, gruvbox.secondary);
+        assert_eq!(
+            config.theme.secondary.fg,
+            Some(ThemeColor::from_hex(0x8e_c07c))
+        );
+    }
+
+    #[test]
+    fn test_theme_light_and_dark_variants() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[theme]
+accent = "blue"
+hint = "cyan"
+light = { preset = "catppuccin-latte", hint = "red" }
+dark = { accent = "yellow" }
+"#,
+        )
+        .unwrap();
+        let named = |color| ThemeStyle::from(ThemeColor::Named(color));
+        // Loading resolves the theme for a dark background
+        assert_eq!(config.theme.accent, named(NamedColor::Yellow));
+        assert_eq!(config.theme.hint, named(NamedColor::Cyan));
+
+        let light = config.theme.for_background(Background::Light);
+        let latte = ThemePreset::CatppuccinLatte.config();
+        assert_eq!(light.preset, Some(ThemePreset::CatppuccinLatte));
+        assert_eq!(light.accent, latte.accent);
+        assert_eq!(light.hint, named(NamedColor::Red));
+
+        let dark = light.for_background(Background::Dark);
+        assert_eq!(dark.accent, named(NamedColor::Yellow));
+        assert_eq!(dark.preset, None);
+    }
+
+    #[test]
+    fn test_theme_light_background_defaults() {
+        let theme = ThemeConfig::default();
+        let light = theme.for_background(Background::Light);
+        assert_ne!(light.border, theme.border);
+        assert_ne!(light.muted, theme.muted);
+        assert_eq!(light.accent, theme.accent);
+
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+
+[theme]
+background = "light"
+border = "black"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.theme.background, Some(Background::Light));
+        assert_eq!(
+            config.theme.border,
+            ThemeStyle::from(ThemeColor::Named(NamedColor::Black))
+        );
+        assert_eq!(config.theme.muted, light.muted);
+    }
+
+    #[test]
+    fn test_theme_style_serializes_plain_colors_as_strings() {
+        let mut theme = ThemeConfig {
+            accent: ThemeStyle {
+                fg: Some(ThemeColor::Named(NamedColor::Blue)),
+                bold: true,
+                ..ThemeStyle::default()
+            },
+            ..ThemeConfig::default()
+        };
+        theme.stripe.bg = Some(ThemeColor::Rgb(0, 0, 0));
+        let value = toml::Value::try_from(&theme).unwrap();
+        assert_eq!(value["secondary"].as_str(), Some("cyan"));
+        assert_eq!(value["accent"]["fg"].as_str(), Some("blue"));
+        assert_eq!(value["accent"]["bold"].as_bool(), Some(true));
+        assert_eq!(value["stripe"]["bg"].as_str(), Some("#000000"));
+        assert!(value.get("selection").is_none());
+        assert!(value.get("preset").is_none());
     }
 
     #[test]
@@ -653,16 +2423,59 @@ unknown = "bad"
         }
     }
 
+    #[test]
+    fn test_branch_columns() {
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+commit_columns = ["subject"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.branch_columns(),
+            vec![
+                BranchColumn::Name,
+                BranchColumn::Session,
+                BranchColumn::Activity,
+                BranchColumn::LastCommitSubject
+            ]
+        );
+
+        let config = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+branch_columns = ["name", "agent", "ahead_behind", "last_commit_age", "session"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.branch_columns(),
+            vec![
+                BranchColumn::Name,
+                BranchColumn::Agent,
+                BranchColumn::AheadBehind,
+                BranchColumn::LastCommitAge,
+                BranchColumn::Session
+            ]
+        );
+
+        let err = load_config_from_str(
+            r#"
+search_dirs = ["~/Development"]
+branch_columns = ["name", "size"]
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown variant"), "{err}");
+    }
+
     #[test]
     fn test_write_default_config_creates_file() {
         let tmp = tempfile::tempdir().unwrap();
         let path = tmp.path().join("kiosk").join("config.toml");
-        // Temporarily override config_file() is not possible, so test
-        // format_default_config + fs::write manually to verify the flow.
         let dirs = vec!["~/Development".to_string()];
-        let content = format_default_config(&dirs);
-        fs::create_dir_all(path.parent().unwrap()).unwrap();
-        fs::write(&path, &content).unwrap();
+        write_default_config_at(&path, &dirs).unwrap();
         let loaded = load_config_from_str(&fs::read_to_string(&path).unwrap()).unwrap();
         assert_eq!(loaded.search_dirs.len(), 1);
     }
@@ -673,14 +2486,8 @@ unknown = "bad"
         let path = tmp.path().join("config.toml");
         fs::write(&path, "existing").unwrap();
 
-        let result = fs::OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&path);
-        assert!(result.is_err());
-        assert_eq!(
-            result.unwrap_err().kind(),
-            std::io::ErrorKind::AlreadyExists
-        );
+        let error = write_default_config_at(&path, &[]).unwrap_err();
+        assert!(error.to_string().contains("already exists"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "existing");
     }
 }
@@ -0,0 +1,444 @@
+use super::{
+    AgentConfig, AgentHook, AgentProfile, BranchConfig, CleanConfig, Command, Config, FetchConfig,
+    GitConfig, KeysConfig, LogConfig, NewRepoConfig, ReapConfig, ScriptConfig, SearchConfig,
+    SessionConfig, ThemeConfig, TicketConfig, TmuxConfig, load_config, load_config_from_str,
+};
+use crate::{keyboard::KeyEvent, paths::expand_tilde};
+use anyhow::Result;
+use serde::{
+    Deserialize,
+    de::{self, Visitor},
+};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use toml::{Table, Value};
+
+/// Candidates scoring below this (Jaro-Winkler) aren't offered as suggestions.
+const SUGGESTION_THRESHOLD: f64 = 0.8;
+
+/// A problem found in a config file by [`validate_config_str`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// Dotted path to the offending key, e.g. `keys.general.C-x`; empty for the whole file
+    pub path: String,
+    pub message: String,
+    /// Closest valid name, for unknown keys and commands
+    pub suggestion: Option<String>,
+}
+
+impl ConfigProblem {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    fn unknown(path: String, name: &str, kind: &str, candidates: &[&str]) -> Self {
+        Self {
+            path,
+            message: format!("unknown {kind} '{name}'"),
+            suggestion: did_you_mean(name, candidates),
+        }
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        f.write_str(&self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{suggestion}'?)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the contents of a config file, reporting every problem found rather than stopping
+/// at the first one as loading does. Covers TOML syntax, unknown keys (including `[keys]`
+/// sections, which loading ignores), invalid keybindings, search directories that don't
+/// exist (skipped silently at startup) and values of the wrong type. Files named in
+/// `include` aren't read; see [`validate_config_file`].
+pub fn validate_config_str(contents: &str) -> Vec<ConfigProblem> {
+    let table = match toml::from_str::<Table>(contents) {
+        Ok(table) => table,
+        Err(e) => return vec![ConfigProblem::new("", e.to_string().trim_end())],
+    };
+
+    let mut sections = vec![(String::new(), &table)];
+    if let Some(Value::Table(profiles)) = table.get("profiles") {
+        for (name, profile) in profiles {
+            if let Value::Table(profile) = profile {
+                sections.push((join_path("profiles", name), profile));
+            }
+        }
+    }
+
+    let mut problems = Vec::new();
+    for (prefix, section) in &sections {
+        check_config_table(section, prefix, &mut problems);
+    }
+
+    // Once the keys are right, loading the config reports missing fields and bad values;
+    // before that it would only repeat the first unknown key. Included files may supply
+    // required fields, so those configs are only checked as a whole.
+    if problems.is_empty()
+        && !table.contains_key("include")
+        && let Err(e) = load_config_from_str(contents)
+    {
+        problems.push(ConfigProblem::new("", e.to_string().trim_end()));
+    }
+
+    for (prefix, section) in &sections {
+        if let Some(Value::Array(search_dirs)) = section.get("search_dirs") {
+            check_search_dirs(
+                search_dirs,
+                &join_path(prefix, "search_dirs"),
+                &mut problems,
+            );
+        }
+    }
+    problems
+}
+
+/// Check a config file as [`validate_config_str`] does, then load it with its includes and
+/// with each of its profiles to catch problems in the merged result.
+pub fn validate_config_file(path: &Path) -> Result<Vec<ConfigProblem>> {
+    let contents = fs::read_to_string(path)?;
+    let mut problems = validate_config_str(&contents);
+    if !problems.is_empty() {
+        return Ok(problems);
+    }
+
+    let table: Table = toml::from_str(&contents)?;
+    if table.contains_key("include")
+        && let Err(e) = load_config(Some(path), None)
+    {
+        problems.push(ConfigProblem::new("", e.to_string().trim_end()));
+    }
+    if let Some(Value::Table(profiles)) = table.get("profiles") {
+        for name in profiles.keys() {
+            if let Err(e) = load_config(Some(path), Some(name)) {
+                problems.push(ConfigProblem::new(
+                    join_path("profiles", name),
+                    e.to_string().trim_end(),
+                ));
+            }
+        }
+    }
+    Ok(problems)
+}
+
+/// Check the keys of the top level of a config, or of a profile (which can't nest profiles).
+fn check_config_table(table: &Table, prefix: &str, problems: &mut Vec<ConfigProblem>) {
+    let fields: Vec<&str> = struct_fields::<Config>()
+        .iter()
+        .copied()
+        .filter(|field| prefix.is_empty() || *field != "profiles")
+        .collect();
+    check_fields(table, prefix, &fields, problems);
+    for (section, fields) in [
+        ("session", struct_fields::<SessionConfig>()),
+        ("theme", struct_fields::<ThemeConfig>()),
+        ("clean", struct_fields::<CleanConfig>()),
+        ("log", struct_fields::<LogConfig>()),
+        ("reap", struct_fields::<ReapConfig>()),
+        ("git", struct_fields::<GitConfig>()),
+        ("fetch", struct_fields::<FetchConfig>()),
+        ("tmux", struct_fields::<TmuxConfig>()),
+        ("new_repo", struct_fields::<NewRepoConfig>()),
+        ("branch", struct_fields::<BranchConfig>()),
+        ("ticket", struct_fields::<TicketConfig>()),
+        ("agent", struct_fields::<AgentConfig>()),
+        ("search", struct_fields::<SearchConfig>()),
+        ("script", struct_fields::<ScriptConfig>()),
+    ] {
+        if let Some(Value::Table(values)) = table.get(section) {
+            check_fields(values, &join_path(prefix, section), fields, problems);
+        }
+    }
+    if let Some(Value::Table(agents)) = table.get("agents") {
+        for (name, agent) in agents {
+            if let Value::Table(fields) = agent {
+                let prefix = join_path(&join_path(prefix, "agents"), name);
+                check_fields(fields, &prefix, struct_fields::<AgentProfile>(), problems);
+            }
+        }
+    }
+    if let Some(Value::Table(agent)) = table.get("agent") {
+        for hook in ["on_waiting", "on_idle"] {
+            if let Some(Value::Table(fields)) = agent.get(hook) {
+                let prefix = join_path(&join_path(prefix, "agent"), hook);
+                check_fields(fields, &prefix, struct_fields::<AgentHook>(), problems);
+            }
+        }
+    }
+    if let Some(Value::Table(keys)) = table.get("keys") {
+        check_keybindings(keys, &join_path(prefix, "keys"), problems);
+    }
+}
+
+fn check_fields(table: &Table, prefix: &str, fields: &[&str], problems: &mut Vec<ConfigProblem>) {
+    for name in table.keys() {
+        if !fields.contains(&name.as_str()) {
+            problems.push(ConfigProblem::unknown(
+                join_path(prefix, name),
+                name,
+                "key",
+                fields,
+            ));
+        }
+    }
+}
+
+fn check_keybindings(keys: &Table, prefix: &str, problems: &mut Vec<ConfigProblem>) {
+    let sections = KeysConfig::docs_section_order_asc();
+    for (section, bindings) in keys {
+        let path = join_path(prefix, section);
+        if !sections.contains(&section.as_str()) {
+            problems.push(ConfigProblem::unknown(path, section, "section", &sections));
+            continue;
+        }
+        let Value::Table(bindings) = bindings else {
+            continue;
+        };
+        for (key, command) in bindings {
+            let path = join_path(&path, key);
+            if let Err(e) = KeyEvent::from_str(key) {
+                problems.push(ConfigProblem::new(
+                    path.clone(),
+                    format!("invalid key '{key}': {e}"),
+                ));
+            }
+            // A key can also run a list of commands in order
+            let commands = match command {
+                Value::String(command) => vec![command.as_str()],
+                Value::Array(chain) => chain.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            for command in commands {
+                if Command::from_str(command).is_err() {
+                    problems.push(ConfigProblem::unknown(
+                        path.clone(),
+                        command,
+                        "command",
+                        Command::CONFIG_NAMES,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn check_search_dirs(search_dirs: &[Value], prefix: &str, problems: &mut Vec<ConfigProblem>) {
+    for (index, entry) in search_dirs.iter().enumerate() {
+        let path = match entry {
+            Value::String(path) => path,
+            Value::Table(rich) => match rich.get("path") {
+                Some(Value::String(path)) => path,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let resolved = expand_tilde(path).unwrap_or_else(|| PathBuf::from(path));
+        if !resolved.is_dir() {
+            problems.push(ConfigProblem::new(
+                format!("{prefix}[{index}]"),
+                format!("'{path}' is not a directory"),
+            ));
+        }
+    }
+}
+
+fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}.{name}")
+    }
+}
+
+fn did_you_mean(name: &str, candidates: &[&str]) -> Option<String> {
+    candidates
+        .iter()
+        .map(|candidate| (strsim::jaro_winkler(name, candidate), candidate))
+        .filter(|(score, _)| *score >= SUGGESTION_THRESHOLD)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| (*candidate).to_string())
+}
+
+/// Field names of a `#[derive(Deserialize)]` struct, taken from what serde passes to
+/// `deserialize_struct`, so the lists can't drift from the struct definitions.
+fn struct_fields<T: for<'de> Deserialize<'de>>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldsProbe(&mut fields));
+    fields
+}
+
+struct FieldsProbe<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> de::Deserializer<'de> for FieldsProbe<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("expected a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("fields recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn config_in(dir: &std::path::Path, rest: &str) -> String {
+        format!("search_dirs = [{:?}]\n{rest}", dir.display().to_string())
+    }
+
+    #[test]
+    fn valid_config_has_no_problems() {
+        let tmp = tempdir().unwrap();
+        let contents = config_in(
+            tmp.path(),
+            "[theme]\naccent = \"blue\"\n[keys.general]\nC-x = \"quit\"\n",
+        );
+        assert_eq!(validate_config_str(&contents), Vec::new());
+    }
+
+    #[test]
+    fn reads_field_names_from_structs() {
+        let fields = struct_fields::<Config>();
+        assert!(fields.contains(&"search_dirs"));
+        assert!(fields.contains(&"log"));
+        assert_eq!(struct_fields::<CleanConfig>(), &["use_trash"]);
+    }
+
+    #[test]
+    fn reports_every_unknown_key_with_suggestions() {
+        let tmp = tempdir().unwrap();
+        let contents = config_in(
+            tmp.path(),
+            "srot = \"activity\"\n[theme]\naccnet = \"blue\"\n[keys.genral]\nC-x = \"quit\"\n",
+        );
+        let problems = validate_config_str(&contents);
+        let rendered: Vec<String> = problems.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            rendered,
+            [
+                "srot: unknown key 'srot' (did you mean 'sort'?)",
+                "theme.accnet: unknown key 'accnet' (did you mean 'accent'?)",
+                "keys.genral: unknown section 'genral' (did you mean 'general'?)",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_keys_and_unknown_commands() {
+        let tmp = tempdir().unwrap();
+        let contents = config_in(
+            tmp.path(),
+            "[keys.general]\nC-x = \"qiut\"\nC-y = [\"quit\", \"mvoe_down\"]\nnotakey = \"quit\"\n",
+        );
+        let problems = validate_config_str(&contents);
+        assert_eq!(problems.len(), 3);
+        assert_eq!(problems[0].path, "keys.general.C-x");
+        assert_eq!(problems[0].suggestion.as_deref(), Some("quit"));
+        assert_eq!(problems[1].path, "keys.general.C-y");
+        assert_eq!(problems[1].suggestion.as_deref(), Some("move_down"));
+        assert!(problems[2].message.starts_with("invalid key 'notakey'"));
+    }
+
+    #[test]
+    fn checks_the_fields_of_agents_and_agent_hooks() {
+        let tmp = tempdir().unwrap();
+        let contents = config_in(
+            tmp.path(),
+            "[agents.claude]\ncommand = \"claude\"\n[agents.codex]\ncomand = \"codex\"\n\
+             [agent.on_waiting]\nexce = \"notify-send waiting\"\n",
+        );
+        let problems = validate_config_str(&contents);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert_eq!(problems[0].path, "agents.codex.comand");
+        assert_eq!(problems[0].suggestion.as_deref(), Some("command"));
+        assert_eq!(problems[1].path, "agent.on_waiting.exce");
+        assert_eq!(problems[1].suggestion.as_deref(), Some("exec"));
+    }
+
+    #[test]
+    fn reports_missing_search_dirs_and_bad_values() {
+        let contents = "search_dirs = [\"/definitely/not/here\", { path = \"/nor/here\" }]\nsort = \"random\"\n";
+        let problems = validate_config_str(contents);
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems[0].message.contains("unknown variant `random`"));
+        assert_eq!(problems[1].path, "search_dirs[0]");
+        assert_eq!(problems[2].path, "search_dirs[1]");
+    }
+
+    #[test]
+    fn checks_profiles_like_the_top_level() {
+        let tmp = tempdir().unwrap();
+        let contents = config_in(
+            tmp.path(),
+            "[profiles.work]
+srot = \"activity\"\nsearch_dirs = [\"/definitely/not/here\"]\n[profiles.work.profiles.nested]\n",
+        );
+        let rendered: Vec<String> = validate_config_str(&contents)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                "profiles.work.profiles: unknown key 'profiles'",
+                "profiles.work.srot: unknown key 'srot' (did you mean 'sort'?)",
+                "profiles.work.search_dirs[0]: '/definitely/not/here' is not a directory",
+            ]
+        );
+    }
+
+    #[test]
+    fn file_validation_loads_includes_and_profiles() {
+        let tmp = tempdir().unwrap();
+        let shared = tmp.path().join("shared.toml");
+        fs::write(&shared, config_in(tmp.path(), "")).unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(
+            &path,
+            "include = [\"shared.toml\"]\n[profiles.work]\nsort = \"random\"\n",
+        )
+        .unwrap();
+
+        let problems = validate_config_file(&path).unwrap();
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert_eq!(problems[0].path, "profiles.work");
+        assert!(problems[0].message.contains("unknown variant `random`"));
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let problems = validate_config_str("search_dirs = [");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.contains("line 1"), "{problems:?}");
+    }
+}
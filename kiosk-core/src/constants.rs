@@ -2,4 +2,7 @@ pub const WORKTREE_DIR_NAME: &str = ".kiosk_worktrees";
 pub const GIT_DIR_ENTRY: &str = ".git";
 pub const GITDIR_FILE_PREFIX: &str = "gitdir: ";
 pub const WORKTREE_NAME_SEPARATOR: &str = "--";
+/// Characters in branch names replaced when naming a worktree directory: `/` and `\` would
+/// nest directories, and the rest aren't allowed in file names on Windows.
+pub const WORKTREE_NAME_UNSAFE_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
 pub const WORKTREE_DIR_DEDUP_MAX_ATTEMPTS: usize = 1000;
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, path::PathBuf};
 
-use crate::git::{Repo, Worktree};
+use crate::git::{CommitInfo, GitProgress, Repo, Worktree, WorktreeChanges};
 
 /// Events that arrive asynchronously from background tasks.
 /// These get merged into the main event loop alongside keyboard input.
@@ -20,7 +20,22 @@ pub enum AppEvent {
     ScanComplete { search_dirs: Vec<(PathBuf, u16)> },
 
     /// A background git operation completed successfully
-    WorktreeCreated { path: PathBuf, session_name: String },
+    WorktreeCreated {
+        path: PathBuf,
+        session_name: String,
+        /// The branch a new branch was created from
+        base: Option<String>,
+    },
+
+    /// A repo was cloned into a search dir and is ready to register
+    RepoCloned { repo: Repo },
+
+    /// A new repo was created from a template and is ready to register and open
+    RepoCreated {
+        repo: Repo,
+        /// Why its GitHub repo couldn't be created, if the template asked for one
+        github_error: Option<String>,
+    },
 
     /// A worktree was successfully removed
     WorktreeRemoved {
@@ -28,6 +43,15 @@ pub enum AppEvent {
         worktree_path: PathBuf,
     },
 
+    /// A deleted worktree was restored from the delete journal
+    WorktreeRestored,
+
+    /// Uncommitted changes and unpushed commits of a worktree about to be deleted
+    WorktreeChangesLoaded {
+        worktree_path: PathBuf,
+        changes: Result<WorktreeChanges, String>,
+    },
+
     /// A worktree removal failed
     WorktreeRemoveFailed {
         branch_name: String,
@@ -45,11 +69,19 @@ pub enum AppEvent {
         session_activity: HashMap<String, u64>,
     },
 
-    /// Remote branches loaded (appended after local)
+    /// A page of remote branches loaded (appended after local). Repos with many remote
+    /// branches send several.
     RemoteBranchesLoaded {
+        repo_path: PathBuf,
         branches: Vec<crate::state::BranchEntry>,
     },
 
+    /// Last commit of each branch in a repo, keyed by short ref name (`main`, `origin/main`)
+    BranchCommitsLoaded {
+        repo_path: PathBuf,
+        commits: HashMap<String, CommitInfo>,
+    },
+
     /// Background git fetch completed for one remote (or all remotes if `is_final`).
     GitFetchCompleted {
         branches: Vec<crate::state::BranchEntry>,
@@ -58,6 +90,15 @@ pub enum AppEvent {
         is_final: bool,
     },
 
+    /// Progress of the background git fetch of a repo's remotes
+    FetchProgress {
+        repo_path: PathBuf,
+        progress: GitProgress,
+    },
+
+    /// Progress of the git operation behind the loading dialog
+    LoadingProgress { progress: GitProgress },
+
     /// Single repo enriched with worktree data (streamed from phase 2)
     RepoEnriched {
         repo_path: PathBuf,
@@ -69,6 +110,28 @@ pub enum AppEvent {
         session_activity: HashMap<String, u64>,
     },
 
+    /// A ticket typed as a new branch name was fetched, or why it couldn't be
+    TicketFetched(Result<crate::ticket::Ticket, String>),
+
+    /// The selected repo's tags were listed for the tag picker
+    TagsLoaded {
+        repo_path: PathBuf,
+        tags: Vec<crate::state::TagEntry>,
+    },
+
+    /// Matches for `query` among a long list's `items`, scored in the background. Sent
+    /// after each chunk with that chunk's matches, best first; the `first` chunk's replace
+    /// the list's earlier matches and later ones are merged in.
+    FilterResults {
+        generation: u64,
+        query: String,
+        /// The `SearchableList::items_id` of the list `items` came from
+        items_id: u64,
+        items: std::sync::Arc<[String]>,
+        first: bool,
+        matches: Vec<(usize, i64)>,
+    },
+
     /// A background git operation failed
     GitError(String),
 }
@@ -0,0 +1,113 @@
+use crate::{
+    paths::state_dir,
+    pending_delete::now_unix_secs,
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const FETCH_HISTORY_FILE_NAME: &str = "fetches.toml";
+const FETCH_HISTORY_STATE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FetchRecord {
+    repo_path: PathBuf,
+    last_fetch_unix_secs: u64,
+}
+
+/// When each repo's remotes were last fetched in the background, backing
+/// `fetch.min_interval_secs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchHistoryStore {
+    path: PathBuf,
+}
+
+impl Default for FetchHistoryStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(FETCH_HISTORY_FILE_NAME))
+    }
+}
+
+impl FetchHistoryStore {
+    /// Use history stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn last_fetch_unix_secs(&self, repo_path: &Path) -> Option<u64> {
+        self.load()
+            .into_iter()
+            .find(|record| record.repo_path == repo_path)
+            .map(|record| record.last_fetch_unix_secs)
+    }
+
+    /// Whether `repo_path` was last fetched at least `min_interval_secs` ago, or never.
+    pub fn is_due(&self, repo_path: &Path, min_interval_secs: u64) -> bool {
+        min_interval_secs == 0
+            || self
+                .last_fetch_unix_secs(repo_path)
+                .is_none_or(|last| now_unix_secs().saturating_sub(last) >= min_interval_secs)
+    }
+
+    /// Note that `repo_path` was fetched just now.
+    pub fn record_fetch(&self, repo_path: &Path) -> Result<()> {
+        let now = now_unix_secs();
+        update_state_file(
+            &self.path,
+            FETCH_HISTORY_STATE_VERSION,
+            |records: &mut Vec<FetchRecord>| {
+                if let Some(record) = records
+                    .iter_mut()
+                    .find(|record| record.repo_path == repo_path)
+                {
+                    record.last_fetch_unix_secs = now;
+                } else {
+                    records.push(FetchRecord {
+                        repo_path: repo_path.to_path_buf(),
+                        last_fetch_unix_secs: now,
+                    });
+                }
+            },
+        )
+    }
+
+    fn load(&self) -> Vec<FetchRecord> {
+        load_state_file(&self.path, FETCH_HISTORY_STATE_VERSION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_store::save_state_file;
+    use tempfile::tempdir;
+
+    #[test]
+    fn repo_is_due_until_fetched_within_the_interval() {
+        let tmp = tempdir().unwrap();
+        let store = FetchHistoryStore::at(tmp.path().join("fetches.toml"));
+        let repo = Path::new("/a");
+
+        assert!(store.is_due(repo, 60));
+        store.record_fetch(repo).unwrap();
+        assert!(!store.is_due(repo, 60));
+        assert!(store.is_due(repo, 0));
+        assert!(store.is_due(Path::new("/b"), 60));
+    }
+
+    #[test]
+    fn stale_fetch_is_due_again() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("fetches.toml");
+        let record = FetchRecord {
+            repo_path: PathBuf::from("/a"),
+            last_fetch_unix_secs: now_unix_secs() - 120,
+        };
+        save_state_file(&path, FETCH_HISTORY_STATE_VERSION, &[record]).unwrap();
+
+        let store = FetchHistoryStore::at(&path);
+        assert!(store.is_due(Path::new("/a"), 60));
+        assert!(!store.is_due(Path::new("/a"), 600));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    paths::state_dir,
+    pending_delete::now_unix_secs,
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+const FRECENCY_FILE_NAME: &str = "frecency.toml";
+const FRECENCY_STATE_VERSION: u32 = 1;
+/// Visits to a repo/branch that hasn't been opened for this long are forgotten.
+const FRECENCY_RETENTION_SECS: u64 = 60 * 60 * 24 * 90;
+
+const HOUR_SECS: u64 = 60 * 60;
+const DAY_SECS: u64 = 24 * HOUR_SECS;
+const WEEK_SECS: u64 = 7 * DAY_SECS;
+
+/// How often and how recently a branch (or detached checkout) of a repo was opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Visit {
+    pub repo_path: PathBuf,
+    /// Branch checked out in the opened worktree, `None` for a detached HEAD
+    pub branch: Option<String>,
+    pub count: u32,
+    pub last_open_unix_secs: u64,
+}
+
+impl Visit {
+    /// Open count weighted by recency, as in zoxide: ×16 within the last hour, ×8 within the
+    /// last day, ×2 within the last week and ×1 after that.
+    pub fn score(&self, now_unix_secs: u64) -> u64 {
+        let age = now_unix_secs.saturating_sub(self.last_open_unix_secs);
+        let weight = match age {
+            age if age < HOUR_SECS => 16,
+            age if age < DAY_SECS => 8,
+            age if age < WEEK_SECS => 2,
+            _ => 1,
+        };
+        u64::from(self.count) * weight
+    }
+}
+
+/// Frecency scores for repos and branches, as of when the visits were loaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frecency {
+    visits: Vec<Visit>,
+    now_unix_secs: u64,
+}
+
+impl Frecency {
+    pub fn new(visits: Vec<Visit>, now_unix_secs: u64) -> Self {
+        Self {
+            visits,
+            now_unix_secs,
+        }
+    }
+
+    /// Score per repo path, summed over all of the repo's branches.
+    pub fn repo_scores(&self) -> HashMap<PathBuf, u64> {
+        let mut scores = HashMap::new();
+        for visit in &self.visits {
+            *scores.entry(visit.repo_path.clone()).or_default() += visit.score(self.now_unix_secs);
+        }
+        scores
+    }
+
+    /// Score per branch name within one repo.
+    pub fn branch_scores(&self, repo_path: &Path) -> HashMap<String, u64> {
+        self.visits
+            .iter()
+            .filter(|visit| visit.repo_path == repo_path)
+            .filter_map(|visit| {
+                let branch = visit.branch.clone()?;
+                Some((branch, visit.score(self.now_unix_secs)))
+            })
+            .collect()
+    }
+}
+
+/// Persisted open history backing the `frecency` sort order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrecencyStore {
+    path: Option<PathBuf>,
+}
+
+impl Default for FrecencyStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(FRECENCY_FILE_NAME))
+    }
+}
+
+impl FrecencyStore {
+    /// Use history stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// A store that never records or returns any history.
+    pub fn disabled() -> Self {
+        Self { path: None }
+    }
+
+    pub fn load(&self) -> Frecency {
+        Frecency::new(self.load_visits(), now_unix_secs())
+    }
+
+    /// Count one open of `branch` in `repo_path`.
+    pub fn record_open(&self, repo_path: &Path, branch: Option<&str>) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let now = now_unix_secs();
+        update_state_file(path, FRECENCY_STATE_VERSION, |visits: &mut Vec<Visit>| {
+            visits.retain(|visit| is_retained(visit, now));
+            if let Some(visit) = visits
+                .iter_mut()
+                .find(|visit| visit.repo_path == repo_path && visit.branch.as_deref() == branch)
+            {
+                visit.count = visit.count.saturating_add(1);
+                visit.last_open_unix_secs = now;
+            } else {
+                visits.push(Visit {
+                    repo_path: repo_path.to_path_buf(),
+                    branch: branch.map(ToString::to_string),
+                    count: 1,
+                    last_open_unix_secs: now,
+                });
+            }
+        })
+    }
+
+    fn load_visits(&self) -> Vec<Visit> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        let now = now_unix_secs();
+        load_state_file::<Visit>(path, FRECENCY_STATE_VERSION)
+            .into_iter()
+            .filter(|visit| is_retained(visit, now))
+            .collect()
+    }
+}
+
+fn is_retained(visit: &Visit, now_unix_secs: u64) -> bool {
+    now_unix_secs.saturating_sub(visit.last_open_unix_secs) <= FRECENCY_RETENTION_SECS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_store::save_state_file;
+    use tempfile::tempdir;
+
+    fn visit(repo: &str, branch: &str, count: u32, last_open_unix_secs: u64) -> Visit {
+        Visit {
+            repo_path: PathBuf::from(repo),
+            branch: Some(branch.to_string()),
+            count,
+            last_open_unix_secs,
+        }
+    }
+
+    #[test]
+    fn recent_visits_outweigh_older_frequent_ones() {
+        let now = 10 * WEEK_SECS;
+        let daily = visit("/r", "daily", 3, now - 10);
+        let old = visit("/r", "old", 20, now - 2 * WEEK_SECS);
+        assert_eq!(daily.score(now), 48);
+        assert_eq!(old.score(now), 20);
+    }
+
+    #[test]
+    fn repo_scores_sum_branches_and_branch_scores_are_per_repo() {
+        let now = WEEK_SECS;
+        let frecency = Frecency::new(
+            vec![
+                visit("/a", "main", 1, now),
+                visit("/a", "feat", 2, now),
+                visit("/b", "main", 1, now - 2 * DAY_SECS),
+            ],
+            now,
+        );
+
+        let repos = frecency.repo_scores();
+        assert_eq!(repos[Path::new("/a")], 48);
+        assert_eq!(repos[Path::new("/b")], 2);
+
+        let branches = frecency.branch_scores(Path::new("/a"));
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches["feat"], 32);
+    }
+
+    #[test]
+    fn record_open_increments_existing_visit() {
+        let tmp = tempdir().unwrap();
+        let store = FrecencyStore::at(tmp.path().join("frecency.toml"));
+
+        store.record_open(Path::new("/a"), Some("main")).unwrap();
+        store.record_open(Path::new("/a"), Some("main")).unwrap();
+        store.record_open(Path::new("/a"), Some("feat")).unwrap();
+
+        let branches = store.load().branch_scores(Path::new("/a"));
+        assert_eq!(branches["main"], 32);
+        assert_eq!(branches["feat"], 16);
+    }
+
+    #[test]
+    fn load_forgets_expired_visits() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("frecency.toml");
+        let stale = visit("/a", "main", 5, 0);
+        save_state_file(&path, FRECENCY_STATE_VERSION, &[stale]).unwrap();
+
+        assert!(FrecencyStore::at(&path).load().repo_scores().is_empty());
+    }
+
+    #[test]
+    fn disabled_store_records_nothing() {
+        let store = FrecencyStore::disabled();
+        store.record_open(Path::new("/a"), Some("main")).unwrap();
+        assert_eq!(store.load().repo_scores(), HashMap::new());
+    }
+}
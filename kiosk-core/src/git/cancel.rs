@@ -0,0 +1,70 @@
+use std::{cell::RefCell, io};
+
+type Check = Box<dyn Fn() -> bool>;
+
+thread_local! {
+    static CHECK: RefCell<Option<Check>> = const { RefCell::new(None) };
+}
+
+/// Run `work`, stopping the long-running git commands it runs on this thread (fetches and
+/// worktree checkouts) once `is_cancelled` returns true. A stopped command fails with an
+/// error that [`is_cancellation`] recognises.
+pub fn with_cancel<T>(is_cancelled: impl Fn() -> bool + 'static, work: impl FnOnce() -> T) -> T {
+    super::with_thread_hook(&CHECK, Box::new(is_cancelled), work)
+}
+
+/// Whether anything on this thread can cancel git commands.
+pub(crate) fn is_cancellable() -> bool {
+    CHECK.with(|check| check.borrow().is_some())
+}
+
+/// Whether this thread's git commands have been cancelled.
+pub(crate) fn is_cancelled() -> bool {
+    CHECK.with(|check| {
+        check
+            .borrow()
+            .as_ref()
+            .is_some_and(|is_cancelled| is_cancelled())
+    })
+}
+
+/// Whether `error` came from a git command stopped by [`with_cancel`].
+pub fn is_cancellation(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|error| error.kind() == io::ErrorKind::Interrupted)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn test_with_cancel_checks_only_inside_work() {
+        let cancelled = Rc::new(Cell::new(false));
+        let flag = Rc::clone(&cancelled);
+
+        with_cancel(
+            move || flag.get(),
+            || {
+                assert!(is_cancellable());
+                assert!(!is_cancelled());
+                cancelled.set(true);
+                assert!(is_cancelled());
+            },
+        );
+        assert!(!is_cancellable());
+        assert!(!is_cancelled());
+    }
+
+    #[test]
+    fn test_is_cancellation_looks_through_context() {
+        let error =
+            anyhow::Error::from(crate::process::cancelled_error()).context("git fetch failed");
+        assert!(is_cancellation(&error));
+        assert!(!is_cancellation(&anyhow::anyhow!("cancelled")));
+    }
+}
@@ -1,16 +1,162 @@
 use super::{
+    LAST_COMMIT_FORMAT, TAG_FORMAT, cancel, parse_last_commits, parse_numstat, parse_tags,
     parse_worktree_porcelain,
+    progress::{self, GitProgress},
     provider::GitProvider,
-    repo::{Repo, Worktree},
+    repo::{CommitInfo, FileChange, Repo, Tag, Worktree, WorktreeChanges},
+};
+use crate::{
+    config::GitConfig,
+    constants::{GIT_DIR_ENTRY, GITDIR_FILE_PREFIX},
+    process::CommandPolicy,
 };
-use crate::constants::{GIT_DIR_ENTRY, GITDIR_FILE_PREFIX};
 use anyhow::Result;
 use std::{
+    collections::HashMap,
+    io,
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Output},
+    sync::{PoisonError, RwLock},
 };
 
-pub struct CliGitProvider;
+/// Runs the `git` CLI within the config's limits.
+pub struct CliGitProvider {
+    policy: RwLock<CommandPolicy>,
+}
+
+/// Failures worth retrying in a command that's safe to run again: another git process holding
+/// a lock, or a flaky network while fetching.
+const TRANSIENT_ERRORS: &[&str] = &[
+    ".lock': File exists",
+    "Could not resolve host",
+    "Connection reset by peer",
+    "Connection timed out",
+    "the remote end hung up unexpectedly",
+    "early EOF",
+];
+
+fn command_policy(config: &GitConfig) -> CommandPolicy {
+    CommandPolicy {
+        timeout: config.command_timeout(),
+        transient_errors: TRANSIENT_ERRORS,
+    }
+}
+
+impl Default for CliGitProvider {
+    fn default() -> Self {
+        Self::new(&GitConfig::default())
+    }
+}
+
+impl CliGitProvider {
+    pub fn new(config: &GitConfig) -> Self {
+        Self {
+            policy: RwLock::new(command_policy(config)),
+        }
+    }
+
+    fn policy(&self) -> CommandPolicy {
+        *self.policy.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Run a git command that changes something. It isn't retried, since it may have got
+    /// partway before failing.
+    fn run(&self, command: &mut Command) -> io::Result<Output> {
+        self.policy().output(command)
+    }
+
+    /// Run a git command that only reads, retrying transient failures.
+    fn read(&self, command: &mut Command) -> io::Result<Output> {
+        self.policy().read_output(command)
+    }
+
+    /// Run a long git command, streaming its progress to this thread's listener and stopping
+    /// it once this thread's operation is cancelled. It has no time limit, since a clone,
+    /// fetch or checkout of a big repo can rightly outlast any.
+    fn progress_output(&self, command: &mut Command) -> io::Result<Output> {
+        let policy = CommandPolicy {
+            timeout: None,
+            ..self.policy()
+        };
+        if !progress::is_reporting() && !cancel::is_cancellable() {
+            return policy.output(command);
+        }
+        if cancel::is_cancelled() {
+            return Err(crate::process::cancelled_error());
+        }
+        policy.output_with_stderr_lines(
+            command,
+            &mut |line| {
+                if let Some(update) = GitProgress::parse(line) {
+                    progress::report(&update);
+                }
+            },
+            &cancel::is_cancelled,
+        )
+    }
+
+    /// [`Self::progress_output`] for a fetch, retrying transient failures such as a dropped
+    /// connection, since fetching again is harmless.
+    fn fetch_output(&self, command: &mut Command) -> io::Result<Output> {
+        self.policy()
+            .retry_transient(command, |command| self.progress_output(command))
+    }
+
+    /// The commit where the worktree's `HEAD` diverged from `base`.
+    fn merge_base(&self, worktree_path: &Path, base: &str) -> Result<String> {
+        let output = self.read(
+            Command::new("git")
+                .args(["merge-base", base, "HEAD"])
+                .current_dir(worktree_path),
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git merge-base {base} HEAD failed: {}", stderr.trim());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Commits on `HEAD` missing from its upstream, or from every remote when it has none. A
+    /// repo without remotes has nothing to push to, so it never has unpushed commits.
+    fn unpushed_commits(&self, worktree_path: &Path) -> Result<usize> {
+        let upstream = self.read(
+            Command::new("git")
+                .args(["rev-parse", "--verify", "--quiet", "@{upstream}"])
+                .current_dir(worktree_path),
+        )?;
+        let range: &[&str] = if upstream.status.success() {
+            &["@{upstream}..HEAD"]
+        } else {
+            let remotes =
+                self.read(Command::new("git").arg("remote").current_dir(worktree_path))?;
+            if remotes.stdout.iter().all(u8::is_ascii_whitespace) {
+                return Ok(0);
+            }
+            &["HEAD", "--not", "--remotes"]
+        };
+
+        self.rev_list_count(worktree_path, range)
+    }
+
+    fn rev_list_count(&self, dir: &Path, range: &[&str]) -> Result<usize> {
+        let output = self.read(
+            Command::new("git")
+                .args(["rev-list", "--count"])
+                .args(range)
+                .current_dir(dir),
+        )?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git rev-list --count failed: {}", stderr.trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .unwrap_or_default())
+    }
+}
 
 impl GitProvider for CliGitProvider {
     fn scan_repos(&self, dirs: &[(PathBuf, u16)]) -> Vec<Repo> {
@@ -38,10 +184,11 @@ impl GitProvider for CliGitProvider {
     }
 
     fn list_branches(&self, repo_path: &Path) -> Vec<String> {
-        let output = Command::new("git")
-            .args(["branch", "--format=%(refname:short)"])
-            .current_dir(repo_path)
-            .output();
+        let output = self.read(
+            Command::new("git")
+                .args(["branch", "--format=%(refname:short)"])
+                .current_dir(repo_path),
+        );
 
         let Ok(output) = output else {
             return Vec::new();
@@ -54,10 +201,11 @@ impl GitProvider for CliGitProvider {
     }
 
     fn list_remote_branches(&self, repo_path: &Path) -> Vec<String> {
-        let output = Command::new("git")
-            .args(["branch", "-r", "--format=%(refname:short)"])
-            .current_dir(repo_path)
-            .output();
+        let output = self.read(
+            Command::new("git")
+                .args(["branch", "-r", "--format=%(refname:short)"])
+                .current_dir(repo_path),
+        );
 
         let Ok(output) = output else {
             return Vec::new();
@@ -79,16 +227,17 @@ impl GitProvider for CliGitProvider {
 
     fn list_remote_branches_for_remote(&self, repo_path: &Path, remote: &str) -> Vec<String> {
         let pattern = format!("{remote}/*");
-        let output = Command::new("git")
-            .args([
-                "branch",
-                "-r",
-                "--format=%(refname:short)",
-                "--list",
-                &pattern,
-            ])
-            .current_dir(repo_path)
-            .output();
+        let output = self.read(
+            Command::new("git")
+                .args([
+                    "branch",
+                    "-r",
+                    "--format=%(refname:short)",
+                    "--list",
+                    &pattern,
+                ])
+                .current_dir(repo_path),
+        );
 
         let Ok(output) = output else {
             return Vec::new();
@@ -107,30 +256,32 @@ impl GitProvider for CliGitProvider {
     }
 
     fn list_worktrees(&self, repo_path: &Path) -> Vec<Worktree> {
-        let output = Command::new("git")
-            .args(["worktree", "list", "--porcelain"])
-            .current_dir(repo_path)
-            .output();
+        let output = self.read(
+            Command::new("git")
+                .args(["worktree", "list", "--porcelain"])
+                .current_dir(repo_path),
+        );
 
         let Ok(output) = output else {
-            return vec![Self::main_worktree(repo_path)];
+            return vec![self.main_worktree(repo_path)];
         };
 
         let stdout = String::from_utf8_lossy(&output.stdout);
         let worktrees = parse_worktree_porcelain(&stdout);
 
         if worktrees.is_empty() {
-            vec![Self::main_worktree(repo_path)]
+            vec![self.main_worktree(repo_path)]
         } else {
             worktrees
         }
     }
 
     fn add_worktree(&self, repo_path: &Path, branch: &str, worktree_path: &Path) -> Result<()> {
-        let output = Command::new("git")
-            .args(["worktree", "add", &worktree_path.to_string_lossy(), branch])
-            .current_dir(repo_path)
-            .output()?;
+        let output = self.progress_output(
+            Command::new("git")
+                .args(["worktree", "add", &worktree_path.to_string_lossy(), branch])
+                .current_dir(repo_path),
+        )?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -147,17 +298,18 @@ impl GitProvider for CliGitProvider {
         base: &str,
         worktree_path: &Path,
     ) -> Result<()> {
-        let output = Command::new("git")
-            .args([
-                "worktree",
-                "add",
-                "-b",
-                new_branch,
-                &worktree_path.to_string_lossy(),
-                base,
-            ])
-            .current_dir(repo_path)
-            .output()?;
+        let output = self.progress_output(
+            Command::new("git")
+                .args([
+                    "worktree",
+                    "add",
+                    "-b",
+                    new_branch,
+                    &worktree_path.to_string_lossy(),
+                    base,
+                ])
+                .current_dir(repo_path),
+        )?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -170,10 +322,11 @@ impl GitProvider for CliGitProvider {
     fn remove_worktree(&self, worktree_path: &Path) -> Result<()> {
         let canonical =
             std::fs::canonicalize(worktree_path).unwrap_or_else(|_| worktree_path.to_path_buf());
-        let output = Command::new("git")
-            .env("LC_ALL", "C")
-            .args(["worktree", "remove", &canonical.to_string_lossy()])
-            .output()?;
+        let output = self.run(Command::new("git").env("LC_ALL", "C").args([
+            "worktree",
+            "remove",
+            &canonical.to_string_lossy(),
+        ]))?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -190,10 +343,11 @@ impl GitProvider for CliGitProvider {
     }
 
     fn prune_worktrees(&self, repo_path: &Path) -> Result<()> {
-        let output = Command::new("git")
-            .args(["worktree", "prune", "--expire", "now"])
-            .current_dir(repo_path)
-            .output()?;
+        let output = self.run(
+            Command::new("git")
+                .args(["worktree", "prune", "--expire", "now"])
+                .current_dir(repo_path),
+        )?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -203,25 +357,312 @@ impl GitProvider for CliGitProvider {
         Ok(())
     }
 
+    fn delete_branch(&self, repo_path: &Path, branch: &str) -> Result<()> {
+        let output = self.run(
+            Command::new("git")
+                .args(["branch", "-D", branch])
+                .current_dir(repo_path),
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git branch -D failed: {stderr}");
+        }
+
+        Ok(())
+    }
+
+    fn branches_containing(&self, repo_path: &Path, commit: &str) -> Result<Vec<String>> {
+        let output = self.read(
+            Command::new("git")
+                .args([
+                    "branch",
+                    "--all",
+                    "--format=%(refname)",
+                    "--contains",
+                    commit,
+                ])
+                .current_dir(repo_path),
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git branch --contains failed: {}", stderr.trim());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|refname| {
+                refname
+                    .strip_prefix("refs/heads/")
+                    .or_else(|| refname.strip_prefix("refs/remotes/"))
+            })
+            .filter(|name| !name.ends_with("/HEAD"))
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    fn diff_files(&self, worktree_path: &Path, base: &str) -> Result<Vec<FileChange>> {
+        let merge_base = self.merge_base(worktree_path, base)?;
+        let output = self.read(
+            Command::new("git")
+                .args(["diff", "--numstat", "-z", "--no-renames", &merge_base])
+                .current_dir(worktree_path),
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git diff --numstat failed: {}", stderr.trim());
+        }
+
+        Ok(parse_numstat(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    fn diff_patch(&self, worktree_path: &Path, base: &str) -> Result<String> {
+        let merge_base = self.merge_base(worktree_path, base)?;
+        let output = self.read(
+            Command::new("git")
+                .args(["diff", "--no-color", "--no-ext-diff", &merge_base])
+                .current_dir(worktree_path),
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git diff failed: {}", stderr.trim());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn worktree_changes(&self, worktree_path: &Path) -> Result<WorktreeChanges> {
+        let status = self.read(
+            Command::new("git")
+                .args(["status", "--porcelain", "-z", "--no-renames"])
+                .current_dir(worktree_path),
+        )?;
+        if !status.status.success() {
+            let stderr = String::from_utf8_lossy(&status.stderr);
+            anyhow::bail!("git status failed: {}", stderr.trim());
+        }
+
+        let diff = self.read(
+            Command::new("git")
+                .args(["diff", "HEAD", "--numstat", "-z", "--no-renames"])
+                .current_dir(worktree_path),
+        )?;
+        if !diff.status.success() {
+            let stderr = String::from_utf8_lossy(&diff.stderr);
+            anyhow::bail!("git diff HEAD --numstat failed: {}", stderr.trim());
+        }
+        let files = parse_numstat(&String::from_utf8_lossy(&diff.stdout));
+
+        Ok(WorktreeChanges {
+            files_changed: status
+                .stdout
+                .split(|byte| *byte == 0)
+                .filter(|entry| !entry.is_empty())
+                .count(),
+            insertions: files.iter().filter_map(|file| file.insertions).sum(),
+            deletions: files.iter().filter_map(|file| file.deletions).sum(),
+            unpushed_commits: self.unpushed_commits(worktree_path)?,
+        })
+    }
+
+    fn head_commit(&self, worktree_path: &Path) -> Result<String> {
+        let output = self.read(
+            Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .current_dir(worktree_path),
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git rev-parse HEAD failed: {stderr}");
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn resolve_commit(&self, repo_path: &Path, rev: &str) -> Result<String> {
+        let output = self.read(
+            Command::new("git")
+                .args([
+                    "rev-parse",
+                    "--verify",
+                    "--quiet",
+                    "--end-of-options",
+                    &format!("{rev}^{{commit}}"),
+                ])
+                .current_dir(repo_path),
+        )?;
+
+        if !output.status.success() {
+            anyhow::bail!("no commit found for '{rev}'");
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn add_detached_worktree(
+        &self,
+        repo_path: &Path,
+        commit: &str,
+        worktree_path: &Path,
+    ) -> Result<()> {
+        let output = self.progress_output(
+            Command::new("git")
+                .args([
+                    "worktree",
+                    "add",
+                    "--detach",
+                    &worktree_path.to_string_lossy(),
+                    commit,
+                ])
+                .current_dir(repo_path),
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git worktree add failed: {stderr}");
+        }
+
+        Ok(())
+    }
+
+    fn stash_changes(&self, worktree_path: &Path, message: &str) -> Result<Option<String>> {
+        let status = self.read(
+            Command::new("git")
+                .args(["status", "--porcelain"])
+                .current_dir(worktree_path),
+        )?;
+        if !status.status.success() {
+            let stderr = String::from_utf8_lossy(&status.stderr);
+            anyhow::bail!("git status failed: {stderr}");
+        }
+        if status.stdout.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+
+        let output = self.run(
+            Command::new("git")
+                .args(["stash", "push", "--include-untracked", "-m", message])
+                .current_dir(worktree_path),
+        )?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git stash push failed: {stderr}");
+        }
+
+        let output = self.read(
+            Command::new("git")
+                .args(["rev-parse", "refs/stash"])
+                .current_dir(worktree_path),
+        )?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git rev-parse refs/stash failed: {stderr}");
+        }
+
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    }
+
+    fn pop_stash(&self, worktree_path: &Path, stash: &str) -> Result<()> {
+        let output = self.run(
+            Command::new("git")
+                .args(["stash", "apply", stash])
+                .current_dir(worktree_path),
+        )?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git stash apply failed: {stderr}");
+        }
+
+        self.drop_stash(worktree_path, stash)
+    }
+
+    fn drop_stash(&self, repo_path: &Path, stash: &str) -> Result<()> {
+        // The stash may have been dropped manually in the meantime; that's fine.
+        let list = self.read(
+            Command::new("git")
+                .args(["stash", "list", "--format=%H"])
+                .current_dir(repo_path),
+        )?;
+        let index = String::from_utf8_lossy(&list.stdout)
+            .lines()
+            .position(|sha| sha.trim() == stash);
+        if let Some(index) = index {
+            let output = self.run(
+                Command::new("git")
+                    .args(["stash", "drop", &format!("stash@{{{index}}}")])
+                    .current_dir(repo_path),
+            )?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("git stash drop failed: {stderr}");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find_stash(&self, worktree_path: &Path, message: &str) -> Result<Option<String>> {
+        let output = self.read(
+            Command::new("git")
+                .args(["stash", "list", "--format=%H %gs"])
+                .current_dir(worktree_path),
+        )?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git stash list failed: {stderr}");
+        }
+
+        // Subjects read `On <branch>: <message>`, and branch names can't contain a colon
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| {
+                let (sha, subject) = line.split_once(' ')?;
+                let (_, stash_message) = subject.split_once(": ")?;
+                (stash_message == message).then(|| sha.to_string())
+            }))
+    }
+
+    fn checkout_branch(&self, worktree_path: &Path, branch: &str) -> Result<()> {
+        let output = self.run(
+            Command::new("git")
+                .args(["checkout", branch])
+                .current_dir(worktree_path),
+        )?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git checkout failed: {}", stderr.trim());
+        }
+
+        Ok(())
+    }
+
     fn create_tracking_branch_and_worktree(
         &self,
         repo_path: &Path,
+        remote: &str,
         branch: &str,
         worktree_path: &Path,
     ) -> Result<()> {
-        // git worktree add <path> -b <branch> --track origin/<branch>
-        let output = Command::new("git")
-            .args([
-                "worktree",
-                "add",
-                &worktree_path.to_string_lossy(),
-                "-b",
-                branch,
-                "--track",
-                &format!("origin/{branch}"),
-            ])
-            .current_dir(repo_path)
-            .output()?;
+        // git worktree add <path> -b <branch> --track <remote>/<branch>
+        let output = self.progress_output(
+            Command::new("git")
+                .args([
+                    "worktree",
+                    "add",
+                    &worktree_path.to_string_lossy(),
+                    "-b",
+                    branch,
+                    "--track",
+                    &format!("{remote}/{branch}"),
+                ])
+                .current_dir(repo_path),
+        )?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -232,17 +673,13 @@ impl GitProvider for CliGitProvider {
     }
 
     fn list_remotes(&self, repo_path: &Path) -> Vec<String> {
-        let Ok(output) = Command::new("git")
-            .arg("remote")
-            .current_dir(repo_path)
-            .output()
-        else {
+        let Ok(output) = self.read(Command::new("git").arg("remote").current_dir(repo_path)) else {
             return Vec::new();
         };
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            log::warn!(
+            tracing::warn!(
                 "git remote failed for {}: {}",
                 repo_path.display(),
                 stderr.trim()
@@ -257,11 +694,74 @@ impl GitProvider for CliGitProvider {
             .collect()
     }
 
-    fn fetch_remote(&self, repo_path: &Path, remote: &str) -> Result<()> {
-        let output = Command::new("git")
-            .args(["fetch", remote])
-            .current_dir(repo_path)
-            .output()?;
+    fn unique_commits(&self, repo_path: &Path, branch: &str, base: &str) -> Result<usize> {
+        let branch_ref = format!("refs/heads/{branch}");
+        self.rev_list_count(repo_path, &[&branch_ref, "--not", base, "--remotes"])
+    }
+
+    fn last_commits(&self, repo_path: &Path) -> HashMap<String, CommitInfo> {
+        let Ok(output) = self.read(
+            Command::new("git")
+                .args([
+                    "for-each-ref",
+                    LAST_COMMIT_FORMAT,
+                    "refs/heads",
+                    "refs/remotes",
+                ])
+                .current_dir(repo_path),
+        ) else {
+            return HashMap::new();
+        };
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::warn!(
+                "git for-each-ref failed for {}: {}",
+                repo_path.display(),
+                stderr.trim()
+            );
+            return HashMap::new();
+        }
+
+        parse_last_commits(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn list_tags(&self, repo_path: &Path) -> Vec<Tag> {
+        let Ok(output) = self.read(
+            Command::new("git")
+                .args([
+                    "for-each-ref",
+                    "--sort=-creatordate",
+                    TAG_FORMAT,
+                    "refs/tags",
+                ])
+                .current_dir(repo_path),
+        ) else {
+            return Vec::new();
+        };
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::warn!(
+                "git for-each-ref failed for {}: {}",
+                repo_path.display(),
+                stderr.trim()
+            );
+            return Vec::new();
+        }
+
+        parse_tags(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn fetch_remote(&self, repo_path: &Path, remote: &str, prune: bool) -> Result<()> {
+        let output = self.fetch_output(
+            Command::new("git")
+                .arg("fetch")
+                .args(progress::is_reporting().then_some("--progress"))
+                .args(prune.then_some("--prune"))
+                .arg(remote)
+                .current_dir(repo_path),
+        )?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -271,14 +771,43 @@ impl GitProvider for CliGitProvider {
         Ok(())
     }
 
+    fn clone_repo(&self, url: &str, dest: &Path) -> Result<()> {
+        let output = self.progress_output(
+            Command::new("git")
+                .arg("clone")
+                .args(progress::is_reporting().then_some("--progress"))
+                .arg("--")
+                .arg(url)
+                .arg(dest),
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git clone {url} failed: {stderr}");
+        }
+
+        Ok(())
+    }
+
+    fn init_repo(&self, path: &Path) -> Result<()> {
+        let output = self.run(Command::new("git").arg("init").current_dir(path))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git init failed: {stderr}");
+        }
+
+        Ok(())
+    }
+
     fn default_branch(&self, repo_path: &Path, local_branches: &[String]) -> Option<String> {
         // Try symbolic-ref first; fall through on spawn/IO errors so the
         // local-branch heuristic below still runs.
-        if let Ok(output) = Command::new("git")
-            .args(["symbolic-ref", "refs/remotes/origin/HEAD"])
-            .current_dir(repo_path)
-            .output()
-            && output.status.success()
+        if let Ok(output) = self.read(
+            Command::new("git")
+                .args(["symbolic-ref", "refs/remotes/origin/HEAD"])
+                .current_dir(repo_path),
+        ) && output.status.success()
         {
             let refname = String::from_utf8_lossy(&output.stdout).trim().to_string();
             if let Some(branch) = refname.strip_prefix("refs/remotes/origin/") {
@@ -297,9 +826,8 @@ impl GitProvider for CliGitProvider {
     }
 
     fn resolve_repo_from_cwd(&self) -> Option<PathBuf> {
-        let output = Command::new("git")
-            .args(["rev-parse", "--show-toplevel"])
-            .output()
+        let output = self
+            .read(Command::new("git").args(["rev-parse", "--show-toplevel"]))
             .ok()?;
 
         if output.status.success() {
@@ -311,6 +839,10 @@ impl GitProvider for CliGitProvider {
 
         None
     }
+
+    fn apply_config(&self, config: &GitConfig) {
+        *self.policy.write().unwrap_or_else(PoisonError::into_inner) = command_policy(config);
+    }
 }
 
 #[cfg(test)]
@@ -360,7 +892,7 @@ mod tests {
 
         fs::create_dir_all(tmp.path().join("not-a-repo")).unwrap();
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let repos = provider.discover_repos(&[(tmp.path().to_path_buf(), 1)]);
         assert_eq!(repos.len(), 1);
         assert_eq!(repos[0].name, "my-repo");
@@ -376,7 +908,7 @@ mod tests {
         fs::create_dir_all(&repo_dir).unwrap();
         init_test_repo(&repo_dir);
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let repos = provider.scan_repos(&[(tmp.path().to_path_buf(), 1)]);
         assert_eq!(repos.len(), 1);
         assert_eq!(repos[0].name, "my-repo");
@@ -399,7 +931,7 @@ mod tests {
         init_test_repo(&repo1);
         init_test_repo(&repo2);
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let scanned = provider.scan_repos(&[
             (tmp1.path().to_path_buf(), 1),
             (tmp2.path().to_path_buf(), 1),
@@ -421,7 +953,7 @@ mod tests {
             init_test_repo(&d);
         }
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let repos = provider.discover_repos(&[(tmp.path().to_path_buf(), 1)]);
         let names: Vec<&str> = repos.iter().map(|r| r.name.as_str()).collect();
         assert_eq!(names, vec!["alpha", "Middle", "zebra"]);
@@ -444,7 +976,7 @@ mod tests {
         init_test_repo(&repo1);
         init_test_repo(&repo2);
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let discovered = provider.discover_repos(&[
             (tmp1.path().to_path_buf(), 1),
             (tmp2.path().to_path_buf(), 1),
@@ -478,7 +1010,7 @@ mod tests {
             .output()
             .unwrap();
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let branches = provider.list_branches(tmp.path());
         assert!(branches.contains(&"master".to_string()));
         assert!(branches.contains(&"feat/test".to_string()));
@@ -497,7 +1029,7 @@ mod tests {
             .output()
             .unwrap();
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let wt_path = tmp.path().join("repo-feat-wt-test");
         provider
             .add_worktree(&repo, "feat/wt-test", &wt_path)
@@ -517,7 +1049,7 @@ mod tests {
         fs::create_dir_all(&repo).unwrap();
         init_test_repo(&repo);
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let wt_path = tmp.path().join("repo-new-branch");
         provider
             .create_branch_and_worktree(&repo, "new-branch", "master", &wt_path)
@@ -528,12 +1060,306 @@ mod tests {
         assert!(branches.contains(&"new-branch".to_string()));
     }
 
+    #[test]
+    fn test_add_detached_worktree_at_a_tag() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        fs::create_dir_all(&repo).unwrap();
+        init_test_repo(&repo);
+        Command::new("git")
+            .args(["tag", "-a", "v1.0.0", "-m", "Release"])
+            .current_dir(&repo)
+            .output()
+            .unwrap();
+
+        let provider = CliGitProvider::default();
+        let head = provider.head_commit(&repo).unwrap();
+        // Annotated tags resolve to the commit they point at, not the tag object
+        assert_eq!(
+            provider.resolve_commit(&repo, "refs/tags/v1.0.0").unwrap(),
+            head
+        );
+        assert_eq!(provider.resolve_commit(&repo, &head[..7]).unwrap(), head);
+        assert!(provider.resolve_commit(&repo, "v2.0.0").is_err());
+        assert!(provider.resolve_commit(&repo, "--all").is_err());
+        let tags = provider.list_tags(&repo);
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].name, "v1.0.0");
+        assert_eq!(tags[0].commit, head);
+        assert_eq!(tags[0].annotation.as_deref(), Some("Release"));
+
+        let wt_path = tmp.path().join("repo-v1.0.0");
+        provider
+            .add_detached_worktree(&repo, &head, &wt_path)
+            .unwrap();
+        assert_eq!(provider.head_commit(&wt_path).unwrap(), head);
+        let worktree = provider
+            .list_worktrees(&repo)
+            .into_iter()
+            .find(|wt| !wt.is_main)
+            .unwrap();
+        assert_eq!(worktree.branch, None);
+    }
+
+    #[test]
+    fn test_journaled_delete_and_restore_preserves_uncommitted_changes() {
+        use crate::pending_delete::{
+            DeleteJournal, remove_worktree_with_journal, restore_deleted_worktree,
+        };
+
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        fs::create_dir_all(&repo).unwrap();
+        init_test_repo(&repo);
+
+        let provider = CliGitProvider::default();
+        let journal = DeleteJournal::at(tmp.path().join("journal.toml"));
+        let wt_path = tmp.path().join("repo-wip");
+        provider
+            .create_branch_and_worktree(&repo, "wip", "master", &wt_path)
+            .unwrap();
+        fs::write(wt_path.join("README.md"), "# edited").unwrap();
+        fs::write(wt_path.join("notes.txt"), "untracked").unwrap();
+
+        remove_worktree_with_journal(&provider, &journal, &repo, "wip", &wt_path, false).unwrap();
+        assert!(!wt_path.exists());
+
+        let entry = journal.latest(Some(&repo), Some("wip")).unwrap();
+        assert!(entry.stash.is_some());
+        restore_deleted_worktree(&provider, &journal, &entry).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(wt_path.join("README.md")).unwrap(),
+            "# edited"
+        );
+        assert_eq!(
+            fs::read_to_string(wt_path.join("notes.txt")).unwrap(),
+            "untracked"
+        );
+        assert!(journal.load().is_empty());
+        let stashes = Command::new("git")
+            .args(["stash", "list"])
+            .current_dir(&repo)
+            .output()
+            .unwrap();
+        assert!(stashes.stdout.is_empty());
+    }
+
+    #[test]
+    fn test_delete_branch() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_test_repo(tmp.path());
+
+        let provider = CliGitProvider::default();
+        let wt_path = tmp.path().join("wt-gone");
+        provider
+            .create_branch_and_worktree(tmp.path(), "gone", "master", &wt_path)
+            .unwrap();
+        provider.remove_worktree(&wt_path).unwrap();
+        provider.prune_worktrees(tmp.path()).unwrap();
+        provider.delete_branch(tmp.path(), "gone").unwrap();
+
+        assert!(
+            !provider
+                .list_branches(tmp.path())
+                .contains(&"gone".to_string())
+        );
+    }
+
+    #[test]
+    fn test_branches_containing() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_test_repo(tmp.path());
+        let provider = CliGitProvider::default();
+        let base = provider.head_commit(tmp.path()).unwrap();
+
+        Command::new("git")
+            .args(["checkout", "-q", "-b", "feature"])
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        Command::new("git")
+            .args(["commit", "-q", "--allow-empty", "-m", "feature work"])
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        let feature_tip = provider.head_commit(tmp.path()).unwrap();
+
+        let mut containing_base = provider.branches_containing(tmp.path(), &base).unwrap();
+        containing_base.sort();
+        assert_eq!(containing_base, vec!["feature", "master"]);
+        assert_eq!(
+            provider
+                .branches_containing(tmp.path(), &feature_tip)
+                .unwrap(),
+            vec!["feature"]
+        );
+        assert!(
+            provider
+                .branches_containing(tmp.path(), "not-a-commit")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_diff_against_base_includes_commits_and_uncommitted_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_test_repo(tmp.path());
+        let base = current_branch(tmp.path());
+        run_git(tmp.path(), &["checkout", "-q", "-b", "feature"]);
+        fs::write(tmp.path().join("new.txt"), "one\ntwo\n").unwrap();
+        run_git(tmp.path(), &["add", "new.txt"]);
+        run_git(tmp.path(), &["commit", "-q", "-m", "add new.txt"]);
+        fs::write(tmp.path().join("README.md"), "# changed\n").unwrap();
+
+        let provider = CliGitProvider::default();
+        let mut files = provider.diff_files(tmp.path(), &base).unwrap();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            files,
+            vec![
+                FileChange {
+                    path: PathBuf::from("README.md"),
+                    insertions: Some(1),
+                    deletions: Some(1),
+                },
+                FileChange {
+                    path: PathBuf::from("new.txt"),
+                    insertions: Some(2),
+                    deletions: Some(0),
+                },
+            ]
+        );
+        let patch = provider.diff_patch(tmp.path(), &base).unwrap();
+        assert!(patch.contains("+# changed"));
+        assert!(patch.contains("+two"));
+        assert!(provider.diff_files(tmp.path(), "no-such-branch").is_err());
+    }
+
+    #[test]
+    fn test_worktree_changes_counts_uncommitted_files_and_unpushed_commits() {
+        let tmp = tempfile::tempdir().unwrap();
+        let remote = tmp.path().join("remote.git");
+        let clone = tmp.path().join("clone");
+        let origin = tmp.path().join("origin");
+        fs::create_dir_all(&origin).unwrap();
+        init_test_repo(&origin);
+        run_git(
+            tmp.path(),
+            &["clone", "-q", "--bare", "origin", "remote.git"],
+        );
+        run_git(
+            tmp.path(),
+            &["clone", "-q", &remote.to_string_lossy(), "clone"],
+        );
+        run_git(&clone, &["config", "user.email", "test@test.com"]);
+        run_git(&clone, &["config", "user.name", "Test"]);
+
+        let provider = CliGitProvider::default();
+        assert!(!provider.worktree_changes(&clone).unwrap().is_dirty());
+
+        run_git(
+            &clone,
+            &["commit", "-q", "--allow-empty", "-m", "local only"],
+        );
+        fs::write(clone.join("README.md"), "# changed\nmore\n").unwrap();
+        fs::write(clone.join("untracked.txt"), "new").unwrap();
+        assert_eq!(
+            provider.worktree_changes(&clone).unwrap(),
+            WorktreeChanges {
+                files_changed: 2,
+                insertions: 2,
+                deletions: 1,
+                unpushed_commits: 1,
+            }
+        );
+
+        // Without a remote there's nothing to push to
+        assert_eq!(
+            provider.worktree_changes(&origin).unwrap(),
+            WorktreeChanges::default()
+        );
+    }
+
+    #[test]
+    fn test_unique_commits_ignores_commits_on_base_or_remotes() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_test_repo(tmp.path());
+        let base = current_branch(tmp.path());
+        run_git(tmp.path(), &["branch", "merged"]);
+        run_git(tmp.path(), &["checkout", "-q", "-b", "local"]);
+        run_git(tmp.path(), &["commit", "-q", "--allow-empty", "-m", "one"]);
+        run_git(tmp.path(), &["commit", "-q", "--allow-empty", "-m", "two"]);
+        run_git(tmp.path(), &["checkout", "-q", &base]);
+
+        let provider = CliGitProvider::default();
+        assert_eq!(
+            provider
+                .unique_commits(tmp.path(), "merged", &base)
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            provider.unique_commits(tmp.path(), "local", &base).unwrap(),
+            2
+        );
+
+        run_git(
+            tmp.path(),
+            &["update-ref", "refs/remotes/origin/local", "local"],
+        );
+        assert_eq!(
+            provider.unique_commits(tmp.path(), "local", &base).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_checkout_branch_and_find_stash_by_message() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_test_repo(tmp.path());
+        let base = current_branch(tmp.path());
+        run_git(tmp.path(), &["branch", "other"]);
+
+        let provider = CliGitProvider::default();
+        fs::write(tmp.path().join("wip.txt"), "wip").unwrap();
+        let stash = provider
+            .stash_changes(tmp.path(), "kiosk: WIP on main")
+            .unwrap()
+            .unwrap();
+        provider.checkout_branch(tmp.path(), "other").unwrap();
+        assert_eq!(current_branch(tmp.path()), "other");
+
+        assert_eq!(
+            provider
+                .find_stash(tmp.path(), "kiosk: WIP on main")
+                .unwrap(),
+            Some(stash)
+        );
+        assert_eq!(
+            provider.find_stash(tmp.path(), "WIP on main").unwrap(),
+            None
+        );
+        assert!(provider.checkout_branch(tmp.path(), "missing").is_err());
+        assert_eq!(current_branch(tmp.path()), "other");
+        provider.checkout_branch(tmp.path(), &base).unwrap();
+    }
+
+    fn current_branch(dir: &Path) -> String {
+        let output = Command::new("git")
+            .args(["branch", "--show-current"])
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
     #[test]
     fn test_add_worktree_fails_for_nonexistent_branch() {
         let tmp = tempfile::tempdir().unwrap();
         init_test_repo(tmp.path());
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let wt_path = tmp.path().join("wt-nope");
         let result = provider.add_worktree(tmp.path(), "nonexistent-branch", &wt_path);
         assert!(result.is_err());
@@ -548,7 +1374,7 @@ mod tests {
         fs::create_dir_all(&nested).unwrap();
         init_test_repo(&nested);
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         // Depth 1 should NOT find it (it's 2 levels deep)
         let repos = provider.discover_repos(&[(tmp.path().to_path_buf(), 1)]);
         assert_eq!(repos.len(), 0);
@@ -563,7 +1389,7 @@ mod tests {
         fs::create_dir_all(&nested).unwrap();
         init_test_repo(&nested);
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         // Depth 2 should find it
         let repos = provider.discover_repos(&[(tmp.path().to_path_buf(), 2)]);
         assert_eq!(repos.len(), 1);
@@ -584,7 +1410,7 @@ mod tests {
         fs::create_dir_all(&nested).unwrap();
         init_test_repo(&nested);
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         // Should find the parent but not recurse into it (it has .git)
         let repos = provider.discover_repos(&[(tmp.path().to_path_buf(), 3)]);
         assert_eq!(repos.len(), 1);
@@ -607,7 +1433,7 @@ mod tests {
             &["worktree", "add", &linked_worktree_dir_str, "feat/worktree"],
         );
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let repos = provider.discover_repos(&[(tmp.path().to_path_buf(), 1)]);
         assert_eq!(
             repos.len(),
@@ -637,7 +1463,7 @@ mod tests {
             &["worktree", "add", &linked_worktree_dir_str, "feat/worktree"],
         );
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let repos = provider.scan_repos(&[(tmp.path().to_path_buf(), 1)]);
         assert_eq!(
             repos.len(),
@@ -667,7 +1493,7 @@ mod tests {
             &["worktree", "add", &linked_worktree_dir_str, "feat/worktree"],
         );
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let streamed = RefCell::new(Vec::new());
         provider.scan_repos_streaming(tmp.path(), 1, &|repo| streamed.borrow_mut().push(repo));
         let streamed = streamed.into_inner();
@@ -731,7 +1557,7 @@ mod tests {
         run_git(&clone_dir, &["commit", "-m", "feature"]);
         run_git(&clone_dir, &["push", "origin", "new-feature"]);
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
 
         // list_remotes should return the configured remote
         let remotes = provider.list_remotes(&local_dir);
@@ -744,13 +1570,48 @@ mod tests {
             "Should not see new-feature before fetch: {before:?}"
         );
 
+        // A cancelled fetch doesn't run
+        let error = cancel::with_cancel(
+            || true,
+            || provider.fetch_remote(&local_dir, "origin", false),
+        )
+        .unwrap_err();
+        assert!(cancel::is_cancellation(&error), "{error:#}");
+
         // Fetch the single remote and verify the branch appears
-        provider.fetch_remote(&local_dir, "origin").unwrap();
+        let updates = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let sink = std::rc::Rc::clone(&updates);
+        progress::with_progress(
+            move |update| sink.borrow_mut().push(update.phase.clone()),
+            || provider.fetch_remote(&local_dir, "origin", false).unwrap(),
+        );
+        assert!(
+            updates
+                .borrow()
+                .iter()
+                .any(|phase| phase.ends_with("objects")),
+            "{updates:?}"
+        );
         let after = provider.list_remote_branches(&local_dir);
         assert!(
             after.contains(&"new-feature".to_string()),
             "Should see new-feature after fetch: {after:?}"
         );
+
+        // Only a pruning fetch forgets a branch deleted from the remote
+        run_git(&clone_dir, &["push", "origin", "--delete", "new-feature"]);
+        provider.fetch_remote(&local_dir, "origin", false).unwrap();
+        assert!(
+            provider
+                .list_remote_branches(&local_dir)
+                .contains(&"new-feature".to_string())
+        );
+        provider.fetch_remote(&local_dir, "origin", true).unwrap();
+        assert!(
+            !provider
+                .list_remote_branches(&local_dir)
+                .contains(&"new-feature".to_string())
+        );
     }
 
     #[test]
@@ -758,7 +1619,7 @@ mod tests {
         let tmp = tempfile::tempdir().unwrap();
         init_test_repo(tmp.path());
 
-        let provider = CliGitProvider;
+        let provider = CliGitProvider::default();
         let remotes = provider.list_remotes(tmp.path());
         assert!(remotes.is_empty(), "Repo with no remotes: {remotes:?}");
     }
@@ -768,10 +1629,44 @@ mod tests {
         let tmp = tempfile::tempdir().unwrap();
         init_test_repo(tmp.path());
 
-        let provider = CliGitProvider;
-        let result = provider.fetch_remote(tmp.path(), "nonexistent");
+        let provider = CliGitProvider::default();
+        let result = provider.fetch_remote(tmp.path(), "nonexistent", false);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_clone_repo_into_search_dir() {
+        let source = tempfile::tempdir().unwrap();
+        init_test_repo(source.path());
+        let search_dir = tempfile::tempdir().unwrap();
+        let dest = search_dir.path().join("cloned");
+
+        let provider = CliGitProvider::default();
+        provider
+            .clone_repo(&source.path().to_string_lossy(), &dest)
+            .unwrap();
+        let repos = provider.discover_repos(&[(search_dir.path().to_path_buf(), 1)]);
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].name, "cloned");
+
+        let error = provider
+            .clone_repo(&source.path().to_string_lossy(), &dest)
+            .unwrap_err();
+        assert!(error.to_string().contains("git clone"), "{error}");
+    }
+
+    #[test]
+    fn test_init_repo_makes_a_discoverable_repo() {
+        let search_dir = tempfile::tempdir().unwrap();
+        let path = search_dir.path().join("scratch");
+        std::fs::create_dir(&path).unwrap();
+
+        CliGitProvider::default().init_repo(&path).unwrap();
+        let repos =
+            CliGitProvider::default().discover_repos(&[(search_dir.path().to_path_buf(), 1)]);
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].name, "scratch");
+    }
 }
 
 impl CliGitProvider {
@@ -876,7 +1771,7 @@ impl CliGitProvider {
         let mut seen_paths = std::collections::HashSet::new();
         repos_with_dirs.retain(|(repo, _)| seen_paths.insert(repo.path.clone()));
 
-        repos_with_dirs.sort_by(|a, b| a.0.name.to_lowercase().cmp(&b.0.name.to_lowercase()));
+        repos_with_dirs.sort_by_key(|(repo, _)| repo.name.to_lowercase());
 
         let mut name_counts = std::collections::HashMap::<String, usize>::new();
         for (repo, _) in &repos_with_dirs {
@@ -919,11 +1814,13 @@ impl CliGitProvider {
         })
     }
 
-    fn main_worktree(repo_path: &Path) -> Worktree {
-        let branch = Command::new("git")
-            .args(["rev-parse", "--abbrev-ref", "HEAD"])
-            .current_dir(repo_path)
-            .output()
+    fn main_worktree(&self, repo_path: &Path) -> Worktree {
+        let branch = self
+            .read(
+                Command::new("git")
+                    .args(["rev-parse", "--abbrev-ref", "HEAD"])
+                    .current_dir(repo_path),
+            )
             .ok()
             .and_then(|o| {
                 let s = String::from_utf8_lossy(&o.stdout).trim().to_string();
@@ -1,6 +1,8 @@
 use super::{
+    cancel,
+    progress::{self, GitProgress},
     provider::GitProvider,
-    repo::{Repo, Worktree},
+    repo::{CommitInfo, FileChange, Repo, Tag, Worktree, WorktreeChanges},
 };
 use anyhow::Result;
 use std::{
@@ -9,6 +11,8 @@ use std::{
     sync::Mutex,
 };
 
+/// In-memory [`GitProvider`]. Fields hold canned data; `*_result` fields inject a one-shot
+/// result for the next call and `*_calls` fields record the arguments of each call.
 #[derive(Default)]
 pub struct MockGitProvider {
     pub repos: Vec<Repo>,
@@ -18,16 +22,191 @@ pub struct MockGitProvider {
     pub worktrees: Vec<Worktree>,
     pub add_worktree_result: Mutex<Option<Result<()>>>,
     pub create_branch_result: Mutex<Option<Result<()>>>,
+    /// The remote, branch, and worktree path of each `create_tracking_branch_and_worktree`.
+    pub create_tracking_branch_calls: Mutex<Vec<(String, String, PathBuf)>>,
     pub remove_worktree_result: Mutex<Option<Result<()>>>,
     pub prune_worktrees_result: Mutex<Option<Result<()>>>,
     pub prune_worktrees_calls: Mutex<Vec<PathBuf>>,
+    pub delete_branch_result: Mutex<Option<Result<()>>>,
+    pub delete_branch_calls: Mutex<Vec<(PathBuf, String)>>,
+    pub head_commit: Option<String>,
+    /// Result of `resolve_commit`, keyed by revision; unknown revisions are an error.
+    pub commits: HashMap<String, String>,
+    pub add_detached_worktree_calls: Mutex<Vec<(PathBuf, String, PathBuf)>>,
+    /// Result of `diff_files`, whatever the worktree and base.
+    pub diff_files: Vec<FileChange>,
+    /// Result of `diff_patch`, whatever the worktree and base.
+    pub diff_patch: String,
+    pub diff_calls: Mutex<Vec<(PathBuf, String)>>,
+    /// Result of `worktree_changes`, keyed by worktree path; other worktrees are clean.
+    pub worktree_changes: HashMap<PathBuf, WorktreeChanges>,
+    /// Error for every `worktree_changes`, e.g. when git can't inspect the worktree.
+    pub worktree_changes_error: Option<String>,
+    pub last_commits: HashMap<String, CommitInfo>,
+    pub tags: Vec<Tag>,
+    /// Result of `branches_containing`, keyed by commit; unknown commits are an error.
+    pub branches_containing: HashMap<String, Vec<String>>,
+    /// Result of `unique_commits`, keyed by branch; other branches have none.
+    pub unique_commits: HashMap<String, usize>,
+    pub stash_result: Mutex<Option<Result<Option<String>>>>,
+    pub stash_calls: Mutex<Vec<PathBuf>>,
+    pub pop_stash_calls: Mutex<Vec<(PathBuf, String)>>,
+    pub drop_stash_calls: Mutex<Vec<(PathBuf, String)>>,
+    /// Result of `find_stash`, keyed by stash message.
+    pub stashes: HashMap<String, String>,
+    pub checkout_result: Mutex<Option<Result<()>>>,
+    pub checkout_calls: Mutex<Vec<(PathBuf, String)>>,
     pub remotes: Vec<String>,
     pub fetch_remote_results: Mutex<HashMap<(PathBuf, String), Result<()>>>,
-    pub fetch_remote_calls: Mutex<Vec<(PathBuf, String)>>,
+    /// The repo, remote, and whether to prune of each `fetch_remote`.
+    pub fetch_remote_calls: Mutex<Vec<(PathBuf, String, bool)>>,
+    pub clone_result: Mutex<Option<Result<()>>>,
+    pub clone_calls: Mutex<Vec<(String, PathBuf)>>,
+    pub init_calls: Mutex<Vec<PathBuf>>,
+    /// Reported by every fetch and worktree creation.
+    pub progress: Vec<GitProgress>,
     pub default_branch: Option<String>,
     pub current_repo_path: Option<PathBuf>,
 }
 
+impl MockGitProvider {
+    /// Stand-in for a long git command: reports `progress`, then stops if it has been
+    /// cancelled.
+    fn run_long_command(&self) -> Result<()> {
+        for update in &self.progress {
+            progress::report(update);
+        }
+        if cancel::is_cancelled() {
+            return Err(crate::process::cancelled_error().into());
+        }
+        Ok(())
+    }
+
+    /// Add a repo whose main checkout is on `main`.
+    #[must_use]
+    pub fn with_repo(mut self, name: &str, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let main = Worktree {
+            path: path.clone(),
+            branch: Some("main".to_string()),
+            is_main: true,
+        };
+        self.repos.push(Repo {
+            name: name.to_string(),
+            session_name: name.to_string(),
+            path,
+            worktrees: vec![main.clone()],
+        });
+        if !self.worktrees.iter().any(|wt| wt.is_main) {
+            self.worktrees.insert(0, main);
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn with_branches<I, S>(mut self, branches: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.branches.extend(branches.into_iter().map(Into::into));
+        self
+    }
+
+    #[must_use]
+    pub fn with_remote_branches<I, S>(mut self, remote: &str, branches: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let branches: Vec<String> = branches.into_iter().map(Into::into).collect();
+        if !self.remotes.iter().any(|r| r == remote) {
+            self.remotes.push(remote.to_string());
+        }
+        self.remote_branches.extend(branches.iter().cloned());
+        self.remote_branches_by_remote
+            .entry(remote.to_string())
+            .or_default()
+            .extend(branches);
+        self
+    }
+
+    /// Add a linked worktree checked out on `branch`.
+    #[must_use]
+    pub fn with_worktree(mut self, path: impl Into<PathBuf>, branch: &str) -> Self {
+        self.worktrees.push(Worktree {
+            path: path.into(),
+            branch: Some(branch.to_string()),
+            is_main: false,
+        });
+        self
+    }
+
+    /// Add a linked worktree with a detached HEAD.
+    #[must_use]
+    pub fn with_detached_worktree(mut self, path: impl Into<PathBuf>) -> Self {
+        self.worktrees.push(Worktree {
+            path: path.into(),
+            branch: None,
+            is_main: false,
+        });
+        self
+    }
+
+    /// Resolve `rev` to `commit`.
+    #[must_use]
+    pub fn with_commit(mut self, rev: &str, commit: &str) -> Self {
+        self.commits.insert(rev.to_string(), commit.to_string());
+        self
+    }
+
+    /// Report `branches` as containing `commit`.
+    #[must_use]
+    pub fn with_branches_containing<I, S>(mut self, commit: &str, branches: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.branches_containing.insert(
+            commit.to_string(),
+            branches.into_iter().map(Into::into).collect(),
+        );
+        self
+    }
+
+    /// Report `branch` as the repo's default branch.
+    #[must_use]
+    pub fn with_default_branch(mut self, branch: &str) -> Self {
+        self.default_branch = Some(branch.to_string());
+        self
+    }
+
+    /// Report uncommitted or unpushed work in the worktree at `path`.
+    #[must_use]
+    pub fn with_worktree_changes(
+        mut self,
+        path: impl Into<PathBuf>,
+        changes: WorktreeChanges,
+    ) -> Self {
+        self.worktree_changes.insert(path.into(), changes);
+        self
+    }
+
+    /// Report `count` commits on `branch` that aren't on its base.
+    #[must_use]
+    pub fn with_unique_commits(mut self, branch: &str, count: usize) -> Self {
+        self.unique_commits.insert(branch.to_string(), count);
+        self
+    }
+
+    /// Pretend the current directory is inside this repo or worktree.
+    #[must_use]
+    pub fn with_current_repo(mut self, path: impl Into<PathBuf>) -> Self {
+        self.current_repo_path = Some(path.into());
+        self
+    }
+}
+
 impl GitProvider for MockGitProvider {
     fn scan_repos(&self, _dirs: &[(PathBuf, u16)]) -> Vec<Repo> {
         self.repos
@@ -56,6 +235,7 @@ impl GitProvider for MockGitProvider {
     }
 
     fn add_worktree(&self, _repo_path: &Path, _branch: &str, _worktree_path: &Path) -> Result<()> {
+        self.run_long_command()?;
         self.add_worktree_result
             .lock()
             .unwrap()
@@ -70,6 +250,7 @@ impl GitProvider for MockGitProvider {
         _base: &str,
         _worktree_path: &Path,
     ) -> Result<()> {
+        self.run_long_command()?;
         self.create_branch_result
             .lock()
             .unwrap()
@@ -97,12 +278,145 @@ impl GitProvider for MockGitProvider {
             .unwrap_or(Ok(()))
     }
 
+    fn delete_branch(&self, repo_path: &Path, branch: &str) -> Result<()> {
+        self.delete_branch_calls
+            .lock()
+            .unwrap()
+            .push((repo_path.to_path_buf(), branch.to_string()));
+        self.delete_branch_result
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or(Ok(()))
+    }
+
+    fn branches_containing(&self, _repo_path: &Path, commit: &str) -> Result<Vec<String>> {
+        self.branches_containing
+            .get(commit)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("malformed object name {commit}"))
+    }
+
+    fn unique_commits(&self, _repo_path: &Path, branch: &str, _base: &str) -> Result<usize> {
+        Ok(self.unique_commits.get(branch).copied().unwrap_or_default())
+    }
+
+    fn last_commits(&self, _repo_path: &Path) -> HashMap<String, CommitInfo> {
+        self.last_commits.clone()
+    }
+
+    fn list_tags(&self, _repo_path: &Path) -> Vec<Tag> {
+        self.tags.clone()
+    }
+
+    fn diff_files(&self, worktree_path: &Path, base: &str) -> Result<Vec<FileChange>> {
+        self.diff_calls
+            .lock()
+            .unwrap()
+            .push((worktree_path.to_path_buf(), base.to_string()));
+        Ok(self.diff_files.clone())
+    }
+
+    fn diff_patch(&self, worktree_path: &Path, base: &str) -> Result<String> {
+        self.diff_calls
+            .lock()
+            .unwrap()
+            .push((worktree_path.to_path_buf(), base.to_string()));
+        Ok(self.diff_patch.clone())
+    }
+
+    fn worktree_changes(&self, worktree_path: &Path) -> Result<WorktreeChanges> {
+        if let Some(error) = &self.worktree_changes_error {
+            anyhow::bail!("{error}");
+        }
+        Ok(self
+            .worktree_changes
+            .get(worktree_path)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn head_commit(&self, _worktree_path: &Path) -> Result<String> {
+        Ok(self
+            .head_commit
+            .clone()
+            .unwrap_or_else(|| "HEAD".to_string()))
+    }
+
+    fn resolve_commit(&self, _repo_path: &Path, rev: &str) -> Result<String> {
+        self.commits
+            .get(rev)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no commit found for '{rev}'"))
+    }
+
+    fn add_detached_worktree(
+        &self,
+        repo_path: &Path,
+        commit: &str,
+        worktree_path: &Path,
+    ) -> Result<()> {
+        self.add_detached_worktree_calls.lock().unwrap().push((
+            repo_path.to_path_buf(),
+            commit.to_string(),
+            worktree_path.to_path_buf(),
+        ));
+        self.run_long_command()
+    }
+
+    fn stash_changes(&self, worktree_path: &Path, _message: &str) -> Result<Option<String>> {
+        self.stash_calls
+            .lock()
+            .unwrap()
+            .push(worktree_path.to_path_buf());
+        self.stash_result.lock().unwrap().take().unwrap_or(Ok(None))
+    }
+
+    fn pop_stash(&self, worktree_path: &Path, stash: &str) -> Result<()> {
+        self.pop_stash_calls
+            .lock()
+            .unwrap()
+            .push((worktree_path.to_path_buf(), stash.to_string()));
+        Ok(())
+    }
+
+    fn drop_stash(&self, repo_path: &Path, stash: &str) -> Result<()> {
+        self.drop_stash_calls
+            .lock()
+            .unwrap()
+            .push((repo_path.to_path_buf(), stash.to_string()));
+        Ok(())
+    }
+
+    fn find_stash(&self, _worktree_path: &Path, message: &str) -> Result<Option<String>> {
+        Ok(self.stashes.get(message).cloned())
+    }
+
+    fn checkout_branch(&self, worktree_path: &Path, branch: &str) -> Result<()> {
+        self.checkout_calls
+            .lock()
+            .unwrap()
+            .push((worktree_path.to_path_buf(), branch.to_string()));
+        self.checkout_result
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or(Ok(()))
+    }
+
     fn create_tracking_branch_and_worktree(
         &self,
         _repo_path: &Path,
-        _branch: &str,
-        _worktree_path: &Path,
+        remote: &str,
+        branch: &str,
+        worktree_path: &Path,
     ) -> Result<()> {
+        self.run_long_command()?;
+        self.create_tracking_branch_calls.lock().unwrap().push((
+            remote.to_string(),
+            branch.to_string(),
+            worktree_path.to_path_buf(),
+        ));
         self.create_branch_result
             .lock()
             .unwrap()
@@ -121,11 +435,13 @@ impl GitProvider for MockGitProvider {
         self.remotes.clone()
     }
 
-    fn fetch_remote(&self, repo_path: &Path, remote: &str) -> Result<()> {
-        self.fetch_remote_calls
-            .lock()
-            .unwrap()
-            .push((repo_path.to_path_buf(), remote.to_string()));
+    fn fetch_remote(&self, repo_path: &Path, remote: &str, prune: bool) -> Result<()> {
+        self.run_long_command()?;
+        self.fetch_remote_calls.lock().unwrap().push((
+            repo_path.to_path_buf(),
+            remote.to_string(),
+            prune,
+        ));
         self.fetch_remote_results
             .lock()
             .unwrap()
@@ -133,6 +449,20 @@ impl GitProvider for MockGitProvider {
             .unwrap_or(Ok(()))
     }
 
+    fn clone_repo(&self, url: &str, dest: &Path) -> Result<()> {
+        self.run_long_command()?;
+        self.clone_calls
+            .lock()
+            .unwrap()
+            .push((url.to_string(), dest.to_path_buf()));
+        self.clone_result.lock().unwrap().take().unwrap_or(Ok(()))
+    }
+
+    fn init_repo(&self, path: &Path) -> Result<()> {
+        self.init_calls.lock().unwrap().push(path.to_path_buf());
+        Ok(())
+    }
+
     fn default_branch(&self, _repo_path: &Path, _local_branches: &[String]) -> Option<String> {
         self.default_branch.clone()
     }
@@ -1,11 +1,48 @@
+pub mod cancel;
 pub mod cli;
+#[cfg(any(test, feature = "testing"))]
 pub mod mock;
+pub mod progress;
 pub mod provider;
 pub mod repo;
 
 pub use cli::CliGitProvider;
+pub use progress::GitProgress;
 pub use provider::GitProvider;
-pub use repo::{Repo, Worktree};
+pub use repo::{CommitInfo, FileChange, Repo, Tag, Worktree, WorktreeChanges};
+
+use crate::constants::{GIT_DIR_ENTRY, GITDIR_FILE_PREFIX};
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+    thread::LocalKey,
+};
+
+/// Install `hook` in this thread's `slot` while `work` runs, putting the previous hook back
+/// afterwards, even if `work` panics.
+fn with_thread_hook<H: 'static, T>(
+    slot: &'static LocalKey<RefCell<Option<H>>>,
+    hook: H,
+    work: impl FnOnce() -> T,
+) -> T {
+    struct Restore<H: 'static> {
+        slot: &'static LocalKey<RefCell<Option<H>>>,
+        previous: Option<H>,
+    }
+
+    impl<H> Drop for Restore<H> {
+        fn drop(&mut self) {
+            self.slot.with(|slot| slot.replace(self.previous.take()));
+        }
+    }
+
+    let _restore = Restore {
+        slot,
+        previous: slot.with(|slot| slot.replace(Some(hook))),
+    };
+    work()
+}
 
 /// Parse `git worktree list --porcelain` output into worktrees
 pub fn parse_worktree_porcelain(output: &str) -> Vec<Worktree> {
@@ -44,6 +81,165 @@ pub fn parse_worktree_porcelain(output: &str) -> Vec<Worktree> {
     worktrees
 }
 
+/// Format string for `git for-each-ref` read by [`parse_last_commits`].
+pub(crate) const LAST_COMMIT_FORMAT: &str = concat!(
+    "--format=%(refname:short)%00%(subject)%00%(authorname)%00%(committerdate:unix)",
+    "%00%(upstream)%00%(upstream:track,nobracket)"
+);
+
+/// Parse `git for-each-ref` output in [`LAST_COMMIT_FORMAT`] into the last commit of each ref,
+/// keyed by short ref name (`main`, `origin/main`). Symbolic remote `HEAD`s are skipped.
+pub fn parse_last_commits(output: &str) -> std::collections::HashMap<String, CommitInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let refname = fields.next()?;
+            let subject = fields.next()?;
+            let author = fields.next()?;
+            let timestamp = fields.next()?.trim().parse().ok()?;
+            let upstream = fields.next().unwrap_or_default();
+            let track = fields.next().unwrap_or_default();
+            if refname.is_empty() || refname.ends_with("/HEAD") {
+                return None;
+            }
+            Some((
+                refname.to_string(),
+                CommitInfo {
+                    subject: subject.to_string(),
+                    author: author.to_string(),
+                    timestamp,
+                    ahead_behind: (!upstream.is_empty())
+                        .then(|| parse_upstream_track(track))
+                        .flatten(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Format string for `git for-each-ref` read by [`parse_tags`].
+pub(crate) const TAG_FORMAT: &str = concat!(
+    "--format=%(refname:short)%00%(objecttype)%00%(objectname)%00%(*objectname)",
+    "%00%(creatordate:unix)%00%(contents:subject)"
+);
+
+/// Parse `git for-each-ref` output in [`TAG_FORMAT`] into tags, in the order listed. Tags of
+/// anything but a commit are skipped.
+pub fn parse_tags(output: &str) -> Vec<Tag> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let name = fields.next().filter(|name| !name.is_empty())?;
+            let object_type = fields.next()?;
+            let object = fields.next()?;
+            let peeled = fields.next()?;
+            let timestamp = fields.next()?.trim().parse().ok()?;
+            let subject = fields.next().unwrap_or_default();
+            let (commit, annotation) = match object_type {
+                "commit" => (object, None),
+                // An annotated tag of a commit; `peeled` is empty if it tags something else
+                "tag" if !peeled.is_empty() => (peeled, Some(subject.to_string())),
+                _ => return None,
+            };
+            Some(Tag {
+                name: name.to_string(),
+                commit: commit.to_string(),
+                timestamp,
+                annotation,
+            })
+        })
+        .collect()
+}
+
+/// The main checkout of the repo `path` belongs to, and the branch checked out at `path`.
+pub fn worktree_of(git: &dyn GitProvider, path: &Path) -> Option<(PathBuf, Option<String>)> {
+    let repo_path =
+        resolve_main_repo_root(path).map(|root| fs::canonicalize(&root).unwrap_or(root))?;
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let branch = git
+        .list_worktrees(&repo_path)
+        .into_iter()
+        .find(|worktree| worktree.path == path)
+        .and_then(|worktree| worktree.branch);
+    Some((repo_path, branch))
+}
+
+/// If `path` is a secondary git worktree root, resolve to the main repository root.
+/// Returns the path unchanged if it's already a main repository root.
+pub fn resolve_main_repo_root(path: &Path) -> Option<PathBuf> {
+    let git_entry = path.join(GIT_DIR_ENTRY);
+    if git_entry.is_file() {
+        // Secondary worktree: .git is a file containing "gitdir: /path/to/main/.git/worktrees/name"
+        let content = fs::read_to_string(&git_entry).ok()?;
+        let gitdir_str = content
+            .lines()
+            .find(|l| l.starts_with(GITDIR_FILE_PREFIX))?
+            .strip_prefix(GITDIR_FILE_PREFIX)?
+            .trim();
+        let gitdir_raw = Path::new(gitdir_str);
+        // Resolve relative gitdir paths against the worktree root
+        let gitdir = if gitdir_raw.is_relative() {
+            path.join(gitdir_raw)
+        } else {
+            gitdir_raw.to_path_buf()
+        };
+        // .git/worktrees/<name> → .git/worktrees → .git → repo root
+        gitdir.parent()?.parent()?.parent().map(Path::to_path_buf)
+    } else if git_entry.is_dir() {
+        Some(path.to_path_buf())
+    } else {
+        None
+    }
+}
+
+/// Parse `%(upstream:track,nobracket)`, e.g. `ahead 2, behind 1`, into the commits ahead and
+/// behind. Empty means in sync; `None` if the upstream is gone.
+fn parse_upstream_track(track: &str) -> Option<(u32, u32)> {
+    let mut counts = (0, 0);
+    for part in track
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        match part.split_once(' ')? {
+            ("ahead", n) => counts.0 = n.parse().ok()?,
+            ("behind", n) => counts.1 = n.parse().ok()?,
+            _ => return None,
+        }
+    }
+    Some(counts)
+}
+
+/// Parse `git diff --numstat -z --no-renames` output: one `insertions\tdeletions\tpath` record
+/// per NUL, with `-` counts for binary files.
+pub fn parse_numstat(output: &str) -> Vec<FileChange> {
+    output
+        .split('\0')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\t');
+            let insertions = fields.next()?;
+            let deletions = fields.next()?;
+            let path = fields.next().filter(|path| !path.is_empty())?;
+            Some(FileChange {
+                path: std::path::PathBuf::from(path),
+                insertions: insertions.parse().ok(),
+                deletions: deletions.parse().ok(),
+            })
+        })
+        .collect()
+}
+
+/// Directory name `git clone` picks for `url`: its last path component, without a `.git`
+/// suffix. `None` if the URL doesn't end in a usable name.
+pub fn repo_name_from_url(url: &str) -> Option<String> {
+    let trimmed = url.trim().trim_end_matches(['/', '\\']);
+    let last = trimmed.rsplit(['/', '\\', ':']).next()?;
+    let name = last.strip_suffix(".git").unwrap_or(last);
+    (!name.is_empty() && name != "." && name != "..").then(|| name.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,9 +290,132 @@ branch refs/heads/feat/thing
         assert_eq!(wts[0].branch.as_deref(), Some("main"));
     }
 
+    #[test]
+    fn test_parse_last_commits() {
+        let output = [
+            [
+                "main",
+                "Fix the thing",
+                "Ada",
+                "1700000000",
+                "refs/remotes/origin/main",
+                "ahead 2, behind 1",
+            ]
+            .join("\0"),
+            [
+                "synced",
+                "Sync",
+                "Ada",
+                "1700000000",
+                "refs/remotes/origin/synced",
+                "",
+            ]
+            .join("\0"),
+            [
+                "gone",
+                "Gone",
+                "Ada",
+                "1700000000",
+                "refs/remotes/origin/gone",
+                "gone",
+            ]
+            .join("\0"),
+            ["origin/HEAD", "Fix the thing", "Ada", "1700000000"].join("\0"),
+            [
+                "origin/feat",
+                "Subject: with colons",
+                "Grace Hopper",
+                "1600000000",
+            ]
+            .join("\0"),
+            ["broken", "no timestamp"].join("\0"),
+        ]
+        .join("\n");
+        let commits = parse_last_commits(&output);
+        assert_eq!(commits.len(), 4);
+        assert_eq!(
+            commits["main"],
+            CommitInfo {
+                subject: "Fix the thing".to_string(),
+                author: "Ada".to_string(),
+                timestamp: 1_700_000_000,
+                ahead_behind: Some((2, 1)),
+            }
+        );
+        assert_eq!(commits["synced"].ahead_behind, Some((0, 0)));
+        assert_eq!(commits["gone"].ahead_behind, None);
+        assert_eq!(commits["origin/feat"].author, "Grace Hopper");
+        assert_eq!(commits["origin/feat"].ahead_behind, None);
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let output = [
+            ["v2.0.0", "tag", "aaa", "ccc", "1700000000", "Release 2.0"].join("\0"),
+            ["v1.0.0", "commit", "bbb", "", "1600000000", "Fix the thing"].join("\0"),
+            ["tree-tag", "tag", "ddd", "", "1500000000", "A tree"].join("\0"),
+            ["broken", "commit", "eee", "", "soon", ""].join("\0"),
+        ]
+        .join("\n");
+        assert_eq!(
+            parse_tags(&output),
+            vec![
+                Tag {
+                    name: "v2.0.0".to_string(),
+                    commit: "ccc".to_string(),
+                    timestamp: 1_700_000_000,
+                    annotation: Some("Release 2.0".to_string()),
+                },
+                Tag {
+                    name: "v1.0.0".to_string(),
+                    commit: "bbb".to_string(),
+                    timestamp: 1_600_000_000,
+                    annotation: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_numstat() {
+        let changes = parse_numstat("3\t1\tsrc/main.rs\0-\t-\tlogo.png\0");
+        assert_eq!(
+            changes,
+            vec![
+                FileChange {
+                    path: PathBuf::from("src/main.rs"),
+                    insertions: Some(3),
+                    deletions: Some(1),
+                },
+                FileChange {
+                    path: PathBuf::from("logo.png"),
+                    insertions: None,
+                    deletions: None,
+                },
+            ]
+        );
+        assert!(parse_numstat("").is_empty());
+    }
+
     #[test]
     fn test_parse_worktree_porcelain_empty() {
         let wts = parse_worktree_porcelain("");
         assert!(wts.is_empty());
     }
+
+    #[test]
+    fn test_repo_name_from_url() {
+        for (url, name) in [
+            ("https://github.com/thomasschafer/kiosk.git", Some("kiosk")),
+            ("https://github.com/thomasschafer/kiosk/", Some("kiosk")),
+            ("git@github.com:thomasschafer/kiosk.git", Some("kiosk")),
+            ("host:kiosk", Some("kiosk")),
+            ("/srv/git/kiosk.git", Some("kiosk")),
+            ("https://github.com/", Some("github.com")),
+            ("..", None),
+            ("", None),
+        ] {
+            assert_eq!(repo_name_from_url(url).as_deref(), name, "{url}");
+        }
+    }
 }
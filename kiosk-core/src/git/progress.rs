@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, fmt};
+
+/// One progress update from a long-running git command, e.g. `Receiving objects: 45%
+/// (450/1000)`. Commands that don't count anything report just a phase.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GitProgress {
+    pub phase: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+}
+
+impl GitProgress {
+    /// Parse a line of git's progress output, such as `remote: Counting objects:  40% (2/5)`,
+    /// `Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s` or `Preparing worktree
+    /// (checking out 'feat')`. Errors, warnings and hints aren't progress.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let line = line.strip_prefix("remote:").map_or(line, str::trim);
+        if line.is_empty()
+            || ["error:", "fatal:", "warning:", "hint:"]
+                .iter()
+                .any(|prefix| line.starts_with(prefix))
+        {
+            return None;
+        }
+
+        let Some((phase, rest)) = line.split_once(": ") else {
+            return Some(Self::phase(line));
+        };
+        let rest = rest.trim_start();
+        if let Some((percent, rest)) = rest.split_once('%')
+            && let Ok(percent) = percent.trim().parse()
+        {
+            let counts = rest
+                .trim_start()
+                .strip_prefix('(')
+                .and_then(|rest| rest.split_once(')'))
+                .and_then(|(counts, _)| counts.split_once('/'));
+            return Some(Self {
+                phase: phase.to_string(),
+                percent: Some(percent),
+                current: counts.and_then(|(current, _)| current.parse().ok()),
+                total: counts.and_then(|(_, total)| total.parse().ok()),
+            });
+        }
+        // `Enumerating objects: 5, done.` counts without knowing the total
+        let count = rest.split(|c: char| !c.is_ascii_digit()).next();
+        Some(Self {
+            current: count.and_then(|count| count.parse().ok()),
+            ..Self::phase(phase)
+        })
+    }
+
+    fn phase(phase: &str) -> Self {
+        Self {
+            phase: phase.to_string(),
+            percent: None,
+            current: None,
+            total: None,
+        }
+    }
+}
+
+impl fmt::Display for GitProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.phase)?;
+        match (self.percent, self.current, self.total) {
+            (Some(percent), Some(current), Some(total)) => {
+                write!(f, ": {percent}% ({current}/{total})")
+            }
+            (Some(percent), _, _) => write!(f, ": {percent}%"),
+            (None, Some(current), _) => write!(f, ": {current}"),
+            (None, None, _) => Ok(()),
+        }
+    }
+}
+
+type Reporter = Box<dyn Fn(&GitProgress)>;
+
+thread_local! {
+    static REPORTER: RefCell<Option<Reporter>> = const { RefCell::new(None) };
+}
+
+/// Run `work`, passing progress from the long-running git commands it runs on this thread
+/// (fetches and worktree checkouts) to `on_progress`.
+pub fn with_progress<T>(
+    on_progress: impl Fn(&GitProgress) + 'static,
+    work: impl FnOnce() -> T,
+) -> T {
+    super::with_thread_hook(&REPORTER, Box::new(on_progress), work)
+}
+
+/// Whether anything on this thread is listening for progress.
+pub(crate) fn is_reporting() -> bool {
+    REPORTER.with(|reporter| reporter.borrow().is_some())
+}
+
+/// Pass `progress` to this thread's listener, if any.
+pub(crate) fn report(progress: &GitProgress) {
+    REPORTER.with(|reporter| {
+        if let Some(on_progress) = reporter.borrow().as_ref() {
+            on_progress(progress);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_parse_counted_progress() {
+        let progress =
+            GitProgress::parse("Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s")
+                .unwrap();
+        assert_eq!(
+            progress,
+            GitProgress {
+                phase: "Receiving objects".to_string(),
+                percent: Some(45),
+                current: Some(450),
+                total: Some(1000),
+            }
+        );
+        assert_eq!(progress.to_string(), "Receiving objects: 45% (450/1000)");
+
+        let remote = GitProgress::parse("remote: Counting objects: 100% (5/5), done.").unwrap();
+        assert_eq!(remote.phase, "Counting objects");
+        assert_eq!(remote.percent, Some(100));
+    }
+
+    #[test]
+    fn test_parse_uncounted_progress() {
+        let enumerating = GitProgress::parse("remote: Enumerating objects: 5, done.").unwrap();
+        assert_eq!(enumerating.to_string(), "Enumerating objects: 5");
+        assert_eq!(enumerating.total, None);
+
+        let preparing = GitProgress::parse("Preparing worktree (checking out 'feat')").unwrap();
+        assert_eq!(
+            preparing.to_string(),
+            "Preparing worktree (checking out 'feat')"
+        );
+
+        assert_eq!(GitProgress::parse("fatal: couldn't find remote ref"), None);
+        assert_eq!(GitProgress::parse("remote: "), None);
+    }
+
+    #[test]
+    fn test_with_progress_reports_only_inside_work() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        let progress = GitProgress::phase("Fetching");
+
+        report(&progress);
+        with_progress(
+            move |progress| sink.borrow_mut().push(progress.clone()),
+            || {
+                assert!(is_reporting());
+                report(&progress);
+            },
+        );
+        assert!(!is_reporting());
+        report(&progress);
+
+        assert_eq!(seen.borrow().as_slice(), [progress]);
+    }
+}
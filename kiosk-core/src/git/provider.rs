@@ -1,6 +1,10 @@
-use super::repo::{Repo, Worktree};
+use super::repo::{CommitInfo, FileChange, Repo, Tag, Worktree, WorktreeChanges};
+use crate::config::GitConfig;
 use anyhow::Result;
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 pub trait GitProvider: Send + Sync {
     /// Fast directory scan: returns repos with empty worktrees (no git calls).
@@ -29,20 +33,71 @@ pub trait GitProvider: Send + Sync {
     ) -> Result<()>;
     fn remove_worktree(&self, worktree_path: &Path) -> Result<()>;
     fn prune_worktrees(&self, repo_path: &Path) -> Result<()>;
-    /// Create a local tracking branch from a remote branch and add a worktree for it
+    /// Force-delete a local branch.
+    fn delete_branch(&self, repo_path: &Path, branch: &str) -> Result<()>;
+    /// Branches whose history contains `commit`: local branch names, plus `<remote>/<branch>`
+    /// for remote-tracking branches. Fails if `commit` doesn't resolve.
+    fn branches_containing(&self, repo_path: &Path, commit: &str) -> Result<Vec<String>>;
+    /// Count the commits on local `branch` found on neither `base` nor any remote-tracking
+    /// branch, i.e. those deleting the branch would lose.
+    fn unique_commits(&self, repo_path: &Path, branch: &str, base: &str) -> Result<usize>;
+    /// The last commit of every local and remote-tracking branch, keyed by short ref name
+    /// (`main`, `origin/main`). Empty if git fails.
+    fn last_commits(&self, repo_path: &Path) -> HashMap<String, CommitInfo>;
+    /// The repo's tags of commits, most recently made first. Empty if git fails.
+    fn list_tags(&self, repo_path: &Path) -> Vec<Tag>;
+    /// Files changed in a worktree since it diverged from `base`, uncommitted changes included.
+    fn diff_files(&self, worktree_path: &Path, base: &str) -> Result<Vec<FileChange>>;
+    /// Unified diff of a worktree since it diverged from `base`, uncommitted changes included.
+    fn diff_patch(&self, worktree_path: &Path, base: &str) -> Result<String>;
+    /// Uncommitted changes and unpushed commits in a worktree.
+    fn worktree_changes(&self, worktree_path: &Path) -> Result<WorktreeChanges>;
+    /// Resolve the commit currently checked out in a worktree.
+    fn head_commit(&self, worktree_path: &Path) -> Result<String>;
+    /// Resolve `rev` (a commit, tag or other revision) to a full commit hash.
+    fn resolve_commit(&self, repo_path: &Path, rev: &str) -> Result<String>;
+    /// Add a worktree with a detached HEAD at `commit`, or anything git resolves to one, like
+    /// `refs/tags/v1.0.0`.
+    fn add_detached_worktree(
+        &self,
+        repo_path: &Path,
+        commit: &str,
+        worktree_path: &Path,
+    ) -> Result<()>;
+    /// Stash uncommitted (including untracked) changes, returning the stash commit if any.
+    fn stash_changes(&self, worktree_path: &Path, message: &str) -> Result<Option<String>>;
+    /// Apply a stash commit in a worktree and drop it from the stash list.
+    fn pop_stash(&self, worktree_path: &Path, stash: &str) -> Result<()>;
+    /// Drop a stash commit from the stash list, if it's still there.
+    fn drop_stash(&self, repo_path: &Path, stash: &str) -> Result<()>;
+    /// The most recent stash commit created with `message`, if any.
+    fn find_stash(&self, worktree_path: &Path, message: &str) -> Result<Option<String>>;
+    /// Check out an existing branch in a worktree, creating a tracking branch for a remote-only
+    /// one.
+    fn checkout_branch(&self, worktree_path: &Path, branch: &str) -> Result<()>;
+    /// Create a local branch tracking `branch` on `remote` and add a worktree for it
     fn create_tracking_branch_and_worktree(
         &self,
         repo_path: &Path,
+        remote: &str,
         branch: &str,
         worktree_path: &Path,
     ) -> Result<()>;
     /// List configured remotes for a repository.
     fn list_remotes(&self, repo_path: &Path) -> Vec<String>;
-    /// Fetch a single remote.
-    fn fetch_remote(&self, repo_path: &Path, remote: &str) -> Result<()>;
+    /// Fetch a single remote, with `prune` also deleting remote-tracking branches that are gone
+    /// from it.
+    fn fetch_remote(&self, repo_path: &Path, remote: &str, prune: bool) -> Result<()>;
+    /// Clone the repository at `url` into `dest`, which must not exist yet.
+    fn clone_repo(&self, url: &str, dest: &Path) -> Result<()>;
+    /// Create an empty repository in the existing directory `path`.
+    fn init_repo(&self, path: &Path) -> Result<()>;
     /// Detect the default branch (main/master) for a repository.
     /// Accepts the already-fetched local branch list to avoid redundant git calls in the fallback.
     fn default_branch(&self, repo_path: &Path, local_branches: &[String]) -> Option<String>;
     /// Resolve the current working directory to a git repository root
     fn resolve_repo_from_cwd(&self) -> Option<PathBuf>;
+    /// Take up a reloaded config's `[git]` settings, such as the command timeout. Providers
+    /// with nothing to configure ignore it.
+    fn apply_config(&self, _config: &GitConfig) {}
 }
@@ -9,6 +9,85 @@ pub struct Worktree {
     pub is_main: bool,
 }
 
+/// The last commit on a branch.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommitInfo {
+    pub subject: String,
+    pub author: String,
+    /// Committer date, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Commits the branch is ahead of and behind its upstream, if it has one. Read along
+    /// with the commit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ahead_behind: Option<(u32, u32)>,
+}
+
+/// A tag, as listed by [`GitProvider::list_tags`](super::GitProvider::list_tags).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    /// The commit the tag points at.
+    pub commit: String,
+    /// When the tag was made, or its commit's date for a lightweight tag, in seconds since
+    /// the Unix epoch.
+    pub timestamp: u64,
+    /// The subject of an annotated tag's message; `None` for a lightweight tag.
+    pub annotation: Option<String>,
+}
+
+/// A file changed in a worktree, as reported by `git diff --numstat`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// Lines added, or `None` for a binary file.
+    pub insertions: Option<u64>,
+    /// Lines removed, or `None` for a binary file.
+    pub deletions: Option<u64>,
+}
+
+/// Work in a worktree that exists nowhere else yet.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WorktreeChanges {
+    /// Files with uncommitted changes, untracked files included.
+    pub files_changed: usize,
+    /// Lines added by uncommitted changes to tracked files.
+    pub insertions: u64,
+    /// Lines removed by uncommitted changes to tracked files.
+    pub deletions: u64,
+    /// Commits on `HEAD` that aren't on its upstream, or on any remote if it has none.
+    pub unpushed_commits: usize,
+}
+
+impl WorktreeChanges {
+    pub fn is_dirty(&self) -> bool {
+        self.files_changed > 0 || self.unpushed_commits > 0
+    }
+
+    /// Summarise what deleting the worktree would lose, e.g. `2 files changed (+10 -3), 1
+    /// unpushed commit`. Empty when the worktree is clean.
+    pub fn describe(&self) -> String {
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        let mut parts = Vec::new();
+        if self.files_changed > 0 {
+            parts.push(format!(
+                "{} file{} changed (+{} -{})",
+                self.files_changed,
+                plural(self.files_changed),
+                self.insertions,
+                self.deletions
+            ));
+        }
+        if self.unpushed_commits > 0 {
+            parts.push(format!(
+                "{} unpushed commit{}",
+                self.unpushed_commits,
+                plural(self.unpushed_commits)
+            ));
+        }
+        parts.join(", ")
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Repo {
     pub name: String,
@@ -0,0 +1,163 @@
+use crate::{
+    paths::{expand_tilde, state_dir},
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+const HIDDEN_FILE_NAME: &str = "hidden.toml";
+const HIDDEN_STATE_VERSION: u32 = 1;
+/// `*` stays within one path component; `**` spans directories.
+const MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct HiddenRepo {
+    path: PathBuf,
+}
+
+/// Persisted set of repos hidden from the TUI, which the repo list and `kiosk list` leave out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HiddenStore {
+    path: PathBuf,
+}
+
+impl Default for HiddenStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(HIDDEN_FILE_NAME))
+    }
+}
+
+impl HiddenStore {
+    /// Use hidden repos stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn load(&self) -> HashSet<PathBuf> {
+        load_state_file::<HiddenRepo>(&self.path, HIDDEN_STATE_VERSION)
+            .into_iter()
+            .map(|hidden| hidden.path)
+            .collect()
+    }
+
+    /// Hide `repo_path`. Returns the updated set.
+    pub fn hide(&self, repo_path: &Path) -> Result<HashSet<PathBuf>> {
+        self.update(|hidden| {
+            hidden.insert(repo_path.to_path_buf());
+            hidden.clone()
+        })
+    }
+
+    /// Show `repo_path` again. Returns whether it was hidden.
+    pub fn unhide(&self, repo_path: &Path) -> Result<bool> {
+        self.update(|hidden| hidden.remove(repo_path))
+    }
+
+    fn update<R>(&self, change: impl FnOnce(&mut HashSet<PathBuf>) -> R) -> Result<R> {
+        update_state_file(
+            &self.path,
+            HIDDEN_STATE_VERSION,
+            |entries: &mut Vec<HiddenRepo>| {
+                let mut hidden: HashSet<PathBuf> =
+                    entries.drain(..).map(|hidden| hidden.path).collect();
+                let result = change(&mut hidden);
+                entries.extend(hidden.into_iter().map(|path| HiddenRepo { path }));
+                entries.sort_by(|a, b| a.path.cmp(&b.path));
+                result
+            },
+        )
+    }
+}
+
+/// Decides which discovered repos are kept out of the repo list and `kiosk list`: those
+/// matching an `ignore` glob from the config, and those hidden from the TUI.
+#[derive(Debug, Clone, Default)]
+pub struct RepoFilter {
+    ignore: Vec<glob::Pattern>,
+    pub hidden: HashSet<PathBuf>,
+}
+
+impl RepoFilter {
+    /// Build a filter from `ignore` globs (`~` is expanded) and the set of hidden repos.
+    pub fn new(ignore: &[String], hidden: HashSet<PathBuf>) -> Result<Self> {
+        let ignore = ignore
+            .iter()
+            .map(|pattern| {
+                let expanded = expand_tilde(pattern).map_or_else(
+                    || pattern.clone(),
+                    |path| path.to_string_lossy().into_owned(),
+                );
+                glob::Pattern::new(&expanded)
+                    .map_err(|e| anyhow::anyhow!("invalid ignore pattern '{pattern}': {e}"))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { ignore, hidden })
+    }
+
+    pub fn is_hidden(&self, repo_path: &Path) -> bool {
+        self.hidden.contains(repo_path)
+            || self
+                .ignore
+                .iter()
+                .any(|pattern| pattern.matches_path_with(repo_path, MATCH_OPTIONS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn hide_and_unhide_persist() {
+        let tmp = tempdir().unwrap();
+        let store = HiddenStore::at(tmp.path().join("hidden.toml"));
+
+        store.hide(Path::new("/a")).unwrap();
+        let hidden = store.hide(Path::new("/a")).unwrap();
+        assert_eq!(hidden, HashSet::from([PathBuf::from("/a")]));
+        store.hide(Path::new("/b")).unwrap();
+
+        assert!(store.unhide(Path::new("/a")).unwrap());
+        assert!(!store.unhide(Path::new("/a")).unwrap());
+        assert_eq!(store.load(), HashSet::from([PathBuf::from("/b")]));
+    }
+
+    #[test]
+    fn missing_file_hides_nothing() {
+        let tmp = tempdir().unwrap();
+        assert!(
+            HiddenStore::at(tmp.path().join("hidden.toml"))
+                .load()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn filter_matches_ignore_globs_and_hidden_repos() {
+        let filter = RepoFilter::new(
+            &["/src/vendor/*".to_string(), "**/archive-*".to_string()],
+            HashSet::from([PathBuf::from("/src/old")]),
+        )
+        .unwrap();
+
+        assert!(filter.is_hidden(Path::new("/src/vendor/lib")));
+        assert!(filter.is_hidden(Path::new("/src/work/archive-2020")));
+        assert!(filter.is_hidden(Path::new("/src/old")));
+        assert!(!filter.is_hidden(Path::new("/src/kiosk")));
+    }
+
+    #[test]
+    fn filter_rejects_invalid_globs() {
+        let error = RepoFilter::new(&["[".to_string()], HashSet::new()).unwrap_err();
+        assert!(error.to_string().contains("invalid ignore pattern"));
+    }
+}
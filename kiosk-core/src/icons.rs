@@ -0,0 +1,176 @@
+//! Glyphs for the TUI and `kiosk statusline`, in sets for Nerd Fonts, plain Unicode and ASCII.
+
+use crate::config::Icons;
+
+/// The glyphs of one icon set. Markers that follow a name include their leading space.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Glyphs {
+    /// Before each repo in the repo list
+    pub repo: &'static str,
+    /// Before each branch in the branch list
+    pub branch: &'static str,
+    /// Before pinned repos
+    pub pinned: &'static str,
+    /// After branches with a session
+    pub session: &'static str,
+    /// After branches with a worktree but no session
+    pub worktree: &'static str,
+    /// After the checked-out branch
+    pub current: &'static str,
+    /// After worktrees with a detached HEAD
+    pub detached: &'static str,
+    /// Before the selected row of a list
+    pub selected: &'static str,
+    /// Between key hints in the footer
+    pub separator: &'static str,
+    /// After a branch with uncommitted changes
+    pub dirty: &'static str,
+    /// An agent that's producing output
+    pub agent_working: &'static str,
+    /// An agent that's waiting for input
+    pub agent_waiting: &'static str,
+    /// Before the commits a branch is ahead of its upstream
+    pub ahead: &'static str,
+    /// Before the commits a branch is behind its upstream
+    pub behind: &'static str,
+    /// Levels of the activity heat, from no activity to the most
+    pub heat: [&'static str; 4],
+}
+
+const NERD: Glyphs = Glyphs {
+    repo: "\u{f1d3} ",
+    branch: "\u{e725} ",
+    pinned: "\u{f005} ",
+    session: " \u{f489}",
+    worktree: " \u{f07b}",
+    current: " \u{f444}",
+    detached: " \u{f02b}",
+    selected: "\u{f054} ",
+    separator: " \u{e0b1} ",
+    dirty: " \u{f040}",
+    agent_working: "\u{f013}",
+    agent_waiting: "\u{f059}",
+    ahead: "\u{f062}",
+    behind: "\u{f063}",
+    heat: ["·", "▂", "▅", "█"],
+};
+
+const UNICODE: Glyphs = Glyphs {
+    repo: "",
+    branch: "",
+    pinned: "★ ",
+    session: " (session)",
+    worktree: " (worktree)",
+    current: " *",
+    detached: " (detached)",
+    selected: "▸ ",
+    separator: " │ ",
+    dirty: "*",
+    agent_working: "●",
+    agent_waiting: "○",
+    ahead: "↑",
+    behind: "↓",
+    heat: ["·", "▂", "▅", "█"],
+};
+
+const ASCII: Glyphs = Glyphs {
+    repo: "",
+    branch: "",
+    pinned: "+ ",
+    session: " (session)",
+    worktree: " (worktree)",
+    current: " *",
+    detached: " (detached)",
+    selected: "> ",
+    separator: " | ",
+    dirty: "*",
+    agent_working: "~",
+    agent_waiting: "?",
+    ahead: "+",
+    behind: "-",
+    heat: [".", "-", "=", "#"],
+};
+
+impl Icons {
+    /// The glyphs for this setting. `auto` uses Unicode unless the locale says the terminal
+    /// can't show it, and ASCII then.
+    pub fn glyphs(self) -> &'static Glyphs {
+        match self {
+            Icons::Nerd => &NERD,
+            Icons::Unicode => &UNICODE,
+            Icons::Ascii => &ASCII,
+            Icons::Auto => {
+                let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+                    .iter()
+                    .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()));
+                if locale.is_none_or(|locale| is_utf8_locale(&locale)) {
+                    &UNICODE
+                } else {
+                    &ASCII
+                }
+            }
+        }
+    }
+}
+
+/// Whether a locale such as `en_GB.UTF-8` uses UTF-8.
+fn is_utf8_locale(locale: &str) -> bool {
+    let locale = locale.to_ascii_lowercase();
+    locale.contains("utf-8") || locale.contains("utf8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_utf8_locales() {
+        assert!(is_utf8_locale("en_GB.UTF-8"));
+        assert!(is_utf8_locale("C.utf8"));
+        assert!(!is_utf8_locale("C"));
+        assert!(!is_utf8_locale("POSIX"));
+        assert!(!is_utf8_locale("en_US.ISO-8859-1"));
+    }
+
+    #[test]
+    fn ascii_glyphs_are_ascii() {
+        let Glyphs {
+            repo,
+            branch,
+            pinned,
+            session,
+            worktree,
+            current,
+            detached,
+            selected,
+            separator,
+            dirty,
+            agent_working,
+            agent_waiting,
+            ahead,
+            behind,
+            heat,
+        } = Icons::Ascii.glyphs();
+        for glyph in [
+            repo,
+            branch,
+            pinned,
+            session,
+            worktree,
+            current,
+            detached,
+            selected,
+            separator,
+            dirty,
+            agent_working,
+            agent_waiting,
+            ahead,
+            behind,
+        ]
+        .into_iter()
+        .chain(heat)
+        {
+            assert!(glyph.is_ascii(), "{glyph:?} isn't ASCII");
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Which kiosk, if any, is fetching a repo's remotes.
+//!
+//! A kiosk fetching a repo holds an exclusive lock on a file for that repo, with its pid
+//! written inside, until the fetch ends. Another kiosk (say, in another terminal) that comes
+//! to fetch the same repo sees the lock is taken and leaves the fetch to it. The OS drops the
+//! lock with the process, so a crashed kiosk never leaves a stale one behind.
+
+use crate::paths::state_dir;
+use anyhow::{Context, Result};
+use std::{
+    fs::{self, File, TryLockError},
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+const FETCH_LOCK_DIR_NAME: &str = "fetch-locks";
+
+/// An exclusive lock on a file, held until it's dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+#[derive(Debug)]
+pub enum Instance {
+    /// No other kiosk holds the lock, so this one does
+    First(InstanceLock),
+    /// Another kiosk holds the lock: this is its pid, if it could be read
+    Another { pid: Option<u32> },
+}
+
+impl Instance {
+    /// Take the lock at `path` if no other process holds it.
+    pub fn claim_at(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::options()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {
+                file.set_len(0)?;
+                file.rewind()?;
+                write!(file, "{}", std::process::id())?;
+                Ok(Self::First(InstanceLock { _file: file }))
+            }
+            Err(TryLockError::WouldBlock) => {
+                let mut contents = String::new();
+                let pid = file
+                    .read_to_string(&mut contents)
+                    .ok()
+                    .and_then(|_| contents.trim().parse().ok());
+                Ok(Self::Another { pid })
+            }
+            Err(TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("failed to lock {}", path.display()))
+            }
+        }
+    }
+}
+
+/// What came of claiming the lock on fetching a repo.
+#[derive(Debug)]
+pub enum FetchClaim {
+    /// Go ahead with the fetch, holding the lock until it's dropped if there is one
+    Granted(Option<InstanceLock>),
+    /// Another kiosk is fetching the repo: this is its pid, if it could be read
+    Busy { pid: Option<u32> },
+}
+
+/// Locks on fetching each repo's remotes, one file per repo under `fetch-locks/` in the
+/// state directory. Without a directory (the default) every fetch goes ahead.
+#[derive(Debug, Clone, Default)]
+pub struct FetchLocks {
+    dir: Option<PathBuf>,
+}
+
+impl FetchLocks {
+    /// Locks in the state directory.
+    pub fn new() -> Self {
+        Self::in_dir(state_dir().join(FETCH_LOCK_DIR_NAME))
+    }
+
+    /// Locks in a specific directory.
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: Some(dir.into()),
+        }
+    }
+
+    /// Take the lock on fetching `repo_path` unless another process holds it. Failing to
+    /// check counts as granted, so the fetch isn't skipped for nobody.
+    pub fn claim(&self, repo_path: &Path) -> FetchClaim {
+        let Some(dir) = &self.dir else {
+            return FetchClaim::Granted(None);
+        };
+        match Instance::claim_at(&dir.join(lock_file_name(repo_path))) {
+            Ok(Instance::First(lock)) => FetchClaim::Granted(Some(lock)),
+            Ok(Instance::Another { pid }) => FetchClaim::Busy { pid },
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to check whether another kiosk is fetching {}: {e:#}",
+                    repo_path.display()
+                );
+                FetchClaim::Granted(None)
+            }
+        }
+    }
+}
+
+/// `repo_path` flattened into a file name. Paths that flatten alike share a lock, which only
+/// means they're never fetched at the same time.
+fn lock_file_name(repo_path: &Path) -> String {
+    let flat: String = repo_path
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{flat}.lock")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_a_second_claim_sees_the_first_until_it_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("instance.lock");
+
+        let first = Instance::claim_at(&path).unwrap();
+        assert!(matches!(first, Instance::First(_)));
+        let pid = std::process::id();
+        assert!(matches!(
+            Instance::claim_at(&path).unwrap(),
+            Instance::Another { pid: Some(other) } if other == pid
+        ));
+
+        drop(first);
+        assert!(matches!(
+            Instance::claim_at(&path).unwrap(),
+            Instance::First(_)
+        ));
+    }
+
+    #[test]
+    fn test_a_repo_is_fetched_by_one_kiosk_at_a_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let locks = FetchLocks::in_dir(dir.path());
+        let repo = Path::new("/tmp/alpha");
+
+        let FetchClaim::Granted(Some(lock)) = locks.claim(repo) else {
+            panic!("first claim should take the lock");
+        };
+        assert!(matches!(
+            locks.claim(repo),
+            FetchClaim::Busy { pid: Some(pid) } if pid == std::process::id()
+        ));
+        assert!(matches!(
+            locks.claim(Path::new("/tmp/beta")),
+            FetchClaim::Granted(Some(_))
+        ));
+
+        drop(lock);
+        assert!(matches!(locks.claim(repo), FetchClaim::Granted(Some(_))));
+        assert!(matches!(
+            FetchLocks::default().claim(repo),
+            FetchClaim::Granted(None)
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    paths::state_dir,
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+const LABELS_FILE_NAME: &str = "labels.toml";
+const LABELS_STATE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct BranchLabels {
+    repo_path: PathBuf,
+    branch: String,
+    labels: Vec<String>,
+}
+
+/// Labels such as `agent` attached to branches with `kiosk label`, for filtering and grouping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelStore {
+    path: Option<PathBuf>,
+}
+
+impl Default for LabelStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(LABELS_FILE_NAME))
+    }
+}
+
+impl LabelStore {
+    /// Use a store at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// A store that never saves or returns any labels.
+    pub fn disabled() -> Self {
+        Self { path: None }
+    }
+
+    fn entries(&self) -> Vec<BranchLabels> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        load_state_file::<BranchLabels>(path, LABELS_STATE_VERSION)
+    }
+
+    /// Labels of `repo_path`'s branches, keyed by branch name and sorted.
+    pub fn load(&self, repo_path: &Path) -> HashMap<String, Vec<String>> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| entry.repo_path == repo_path)
+            .map(|entry| (entry.branch, entry.labels))
+            .collect()
+    }
+
+    /// Add `labels` to `branch`, returning all of its labels.
+    pub fn add(&self, repo_path: &Path, branch: &str, labels: &[String]) -> Result<Vec<String>> {
+        for label in labels {
+            validate_label(label)?;
+        }
+        self.update(repo_path, branch, |current| {
+            current.extend(labels.iter().cloned());
+        })
+    }
+
+    /// Take `labels` off `branch`, returning the ones it has left.
+    pub fn remove(&self, repo_path: &Path, branch: &str, labels: &[String]) -> Result<Vec<String>> {
+        self.update(repo_path, branch, |current| {
+            current.retain(|label| !labels.contains(label));
+        })
+    }
+
+    fn update(
+        &self,
+        repo_path: &Path,
+        branch: &str,
+        change: impl FnOnce(&mut Vec<String>),
+    ) -> Result<Vec<String>> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+        update_state_file(
+            path,
+            LABELS_STATE_VERSION,
+            |entries: &mut Vec<BranchLabels>| {
+                let position = entries
+                    .iter()
+                    .position(|entry| entry.repo_path == repo_path && entry.branch == branch);
+                let mut labels = position
+                    .map(|i| entries.remove(i).labels)
+                    .unwrap_or_default();
+                change(&mut labels);
+                labels.sort();
+                labels.dedup();
+                if !labels.is_empty() {
+                    entries.push(BranchLabels {
+                        repo_path: repo_path.to_path_buf(),
+                        branch: branch.to_string(),
+                        labels: labels.clone(),
+                    });
+                }
+                labels
+            },
+        )
+    }
+}
+
+/// Labels are single words, so they can be typed as `#label` in the branch search.
+fn validate_label(label: &str) -> Result<()> {
+    if label.is_empty() {
+        bail!("labels can't be empty");
+    }
+    if label.contains(|c: char| c.is_whitespace() || c == '#' || c == ',') {
+        bail!("label '{label}' can't contain whitespace, '#' or ','");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn labels(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn add_and_remove_keep_labels_sorted_and_unique() {
+        let tmp = tempdir().unwrap();
+        let store = LabelStore::at(tmp.path().join("labels.toml"));
+        let repo = Path::new("/src/kiosk");
+
+        store.add(repo, "feat", &labels(&["agent"])).unwrap();
+        let all = store
+            .add(repo, "feat", &labels(&["review", "agent"]))
+            .unwrap();
+        assert_eq!(all, labels(&["agent", "review"]));
+        store
+            .add(Path::new("/src/other"), "feat", &labels(&["x"]))
+            .unwrap();
+
+        assert_eq!(
+            store.load(repo),
+            HashMap::from([("feat".to_string(), labels(&["agent", "review"]))])
+        );
+
+        assert_eq!(
+            store.remove(repo, "feat", &labels(&["agent"])).unwrap(),
+            labels(&["review"])
+        );
+        store.remove(repo, "feat", &labels(&["review"])).unwrap();
+        assert!(store.load(repo).is_empty());
+    }
+
+    #[test]
+    fn add_rejects_labels_that_cannot_be_searched() {
+        let tmp = tempdir().unwrap();
+        let store = LabelStore::at(tmp.path().join("labels.toml"));
+        for bad in ["", "two words", "#agent", "a,b"] {
+            assert!(store.add(Path::new("/r"), "feat", &labels(&[bad])).is_err());
+        }
+        assert!(store.load(Path::new("/r")).is_empty());
+    }
+}
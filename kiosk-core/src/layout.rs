@@ -0,0 +1,125 @@
+use crate::{
+    paths::state_dir,
+    state_store::{load_state_file, save_state_file},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const LAYOUT_FILE_NAME: &str = "layout.toml";
+const LAYOUT_STATE_VERSION: u32 = 1;
+
+/// How the TUI is laid out, restored the next time it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayoutPrefs {
+    /// Whether the preview pane is shown beside the repo and branch lists
+    pub preview: bool,
+    /// Width of the preview pane, as a percentage of the terminal
+    pub preview_width: u16,
+}
+
+impl LayoutPrefs {
+    pub const MIN_PREVIEW_WIDTH: u16 = 20;
+    pub const MAX_PREVIEW_WIDTH: u16 = 80;
+    /// How far each resize moves the split, in percent.
+    pub const RESIZE_STEP: i16 = 5;
+
+    /// Widen the preview pane by `delta` percent (narrow it if negative), within
+    /// [`Self::MIN_PREVIEW_WIDTH`] and [`Self::MAX_PREVIEW_WIDTH`].
+    pub fn resize_preview(&mut self, delta: i16) {
+        self.preview_width = self
+            .preview_width
+            .saturating_add_signed(delta)
+            .clamp(Self::MIN_PREVIEW_WIDTH, Self::MAX_PREVIEW_WIDTH);
+    }
+}
+
+impl Default for LayoutPrefs {
+    fn default() -> Self {
+        Self {
+            preview: false,
+            preview_width: 40,
+        }
+    }
+}
+
+/// Where the layout is saved between runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutStore {
+    path: PathBuf,
+}
+
+impl Default for LayoutStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(LAYOUT_FILE_NAME))
+    }
+}
+
+impl LayoutStore {
+    /// Use a layout stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The saved layout, or the default if none was saved.
+    pub fn load(&self) -> LayoutPrefs {
+        load_state_file::<LayoutPrefs>(&self.path, LAYOUT_STATE_VERSION)
+            .into_iter()
+            .next()
+            .map(|mut layout| {
+                layout.resize_preview(0);
+                layout
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, layout: LayoutPrefs) -> Result<()> {
+        save_state_file(&self.path, LAYOUT_STATE_VERSION, &[layout])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn layout_round_trips() {
+        let tmp = tempdir().unwrap();
+        let store = LayoutStore::at(tmp.path().join("layout.toml"));
+        assert_eq!(store.load(), LayoutPrefs::default());
+
+        let layout = LayoutPrefs {
+            preview: true,
+            preview_width: 55,
+        };
+        store.save(layout).unwrap();
+        assert_eq!(store.load(), layout);
+    }
+
+    #[test]
+    fn preview_width_stays_in_bounds() {
+        let mut layout = LayoutPrefs::default();
+        layout.resize_preview(LayoutPrefs::RESIZE_STEP);
+        assert_eq!(layout.preview_width, 45);
+        layout.resize_preview(100);
+        assert_eq!(layout.preview_width, LayoutPrefs::MAX_PREVIEW_WIDTH);
+        layout.resize_preview(-100);
+        assert_eq!(layout.preview_width, LayoutPrefs::MIN_PREVIEW_WIDTH);
+    }
+
+    #[test]
+    fn saved_width_out_of_bounds_is_clamped() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("layout.toml");
+        let layout = LayoutPrefs {
+            preview: true,
+            preview_width: 99,
+        };
+        save_state_file(&path, LAYOUT_STATE_VERSION, &[layout]).unwrap();
+        assert_eq!(
+            LayoutStore::at(&path).load().preview_width,
+            LayoutPrefs::MAX_PREVIEW_WIDTH
+        );
+    }
+}
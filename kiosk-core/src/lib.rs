@@ -1,13 +1,45 @@
 pub mod action;
+pub mod activity_history;
+pub mod agent;
+pub mod agent_history;
+pub mod agent_hooks;
+pub mod audit;
+pub mod branch_name;
 pub mod config;
 pub mod constants;
 pub mod event;
+pub mod fetch_history;
+pub mod frecency;
 pub mod git;
+pub mod hidden;
+pub mod icons;
+pub mod instance;
 pub mod keyboard;
+pub mod labels;
+pub mod layout;
+pub mod new_repo;
+pub mod notes;
 pub mod paths;
 pub mod pending_delete;
+pub mod pins;
+pub mod placeholders;
+pub mod process;
+pub mod process_tree;
+pub mod project;
+pub mod recovery;
+pub mod removal;
+pub mod runtime;
+pub mod scripting;
+pub mod session_journal;
 pub mod state;
+pub mod state_store;
+pub mod statusline;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod ticket;
 pub mod tmux;
+pub mod transcript;
+pub mod workspace;
 
 // Re-export commonly used types at crate root
 pub use action::Action;
@@ -15,5 +47,6 @@ pub use config::Config;
 pub use event::AppEvent;
 pub use git::{GitProvider, Repo, Worktree};
 pub use keyboard::KeyEvent;
+pub use runtime::Runtime;
 pub use state::{AppState, BranchEntry, Mode};
 pub use tmux::TmuxProvider;
@@ -0,0 +1,131 @@
+use crate::{
+    config::RepoTemplate, constants::GIT_DIR_ENTRY, git::GitProvider, placeholders,
+    process::CommandExt,
+};
+use anyhow::{Context, Result};
+use std::{fs, path::Path, process::Command};
+
+/// A repo made by [`create_repo`].
+#[derive(Debug, Default)]
+pub struct CreatedRepo {
+    /// Why its GitHub repo couldn't be created, when the template asked for one. The local
+    /// repo is there to use either way.
+    pub github_error: Option<String>,
+}
+
+/// Create the repo `name` at `dest` from `template`: run the template's command in the new
+/// directory, `git init` it if the command didn't, then create its GitHub repo if the
+/// template asks for one. A failed command or `git init` removes the directory again.
+pub fn create_repo(
+    git: &dyn GitProvider,
+    dest: &Path,
+    name: &str,
+    template: &RepoTemplate,
+) -> Result<CreatedRepo> {
+    fs::create_dir(dest).with_context(|| format!("failed to create {}", dest.display()))?;
+    let initialised = template
+        .command
+        .as_deref()
+        .map_or(Ok(()), |command| run_template_command(command, dest, name))
+        .and_then(|()| {
+            if dest.join(GIT_DIR_ENTRY).exists() {
+                Ok(())
+            } else {
+                git.init_repo(dest)
+            }
+        });
+    if let Err(e) = initialised {
+        let _ = fs::remove_dir_all(dest);
+        return Err(e);
+    }
+    let github_error = template
+        .github
+        .then(|| create_github_repo(dest, name).err())
+        .flatten()
+        .map(|e| format!("{e:#}"));
+    Ok(CreatedRepo { github_error })
+}
+
+fn run_template_command(command: &str, dir: &Path, name: &str) -> Result<()> {
+    let command = placeholders::expand(command, &[("name", &placeholders::shell_quote(name))]);
+    let output = Command::new("sh")
+        .args(["-c", &command])
+        .current_dir(dir)
+        .traced_output()
+        .with_context(|| format!("failed to run '{command}'"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("'{command}' failed: {stderr}");
+    }
+
+    Ok(())
+}
+
+fn create_github_repo(dir: &Path, name: &str) -> Result<()> {
+    let output = Command::new("gh")
+        .args(["repo", "create", name, "--private", "--source", "."])
+        .current_dir(dir)
+        .traced_output()
+        .context("failed to run gh; is the GitHub CLI installed?")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("gh repo create {name} failed: {stderr}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::mock::MockGitProvider;
+
+    #[test]
+    fn template_command_runs_in_the_new_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dest = tmp.path().join("scratch");
+        let git = MockGitProvider::default();
+        let template = RepoTemplate {
+            command: Some("echo hi > {name}.txt".to_string()),
+            github: false,
+        };
+
+        let created = create_repo(&git, &dest, "scratch", &template).unwrap();
+        assert!(created.github_error.is_none());
+        assert!(dest.join("scratch.txt").is_file());
+        assert_eq!(*git.init_calls.lock().unwrap(), vec![dest]);
+    }
+
+    #[test]
+    fn template_command_gets_the_name_as_one_shell_word() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dest = tmp.path().join("odd name");
+        let git = MockGitProvider::default();
+        let template = RepoTemplate {
+            command: Some("touch {name}".to_string()),
+            github: false,
+        };
+
+        create_repo(&git, &dest, "it's; touch pwned", &template).unwrap();
+        assert!(dest.join("it's; touch pwned").is_file());
+        assert!(!dest.join("pwned").exists());
+    }
+
+    #[test]
+    fn failed_template_command_removes_the_repo_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dest = tmp.path().join("scratch");
+        let git = MockGitProvider::default();
+        let template = RepoTemplate {
+            command: Some("echo nope >&2; exit 1".to_string()),
+            github: false,
+        };
+
+        let error = create_repo(&git, &dest, "scratch", &template).unwrap_err();
+        assert!(error.to_string().contains("nope"), "{error}");
+        assert!(!dest.exists());
+        assert!(git.init_calls.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    paths::state_dir,
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+const NOTES_FILE_NAME: &str = "notes.toml";
+const NOTES_STATE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct BranchNote {
+    repo_path: PathBuf,
+    branch: String,
+    note: String,
+}
+
+/// Free-text notes attached to branches with `kiosk note` or the branch picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteStore {
+    path: Option<PathBuf>,
+}
+
+impl Default for NoteStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(NOTES_FILE_NAME))
+    }
+}
+
+impl NoteStore {
+    /// Use a store at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// A store that never saves or returns any notes.
+    pub fn disabled() -> Self {
+        Self { path: None }
+    }
+
+    fn entries(&self) -> Vec<BranchNote> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        load_state_file::<BranchNote>(path, NOTES_STATE_VERSION)
+    }
+
+    /// Notes for `repo_path`'s branches, keyed by branch name.
+    pub fn load(&self, repo_path: &Path) -> HashMap<String, String> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| entry.repo_path == repo_path)
+            .map(|entry| (entry.branch, entry.note))
+            .collect()
+    }
+
+    /// Attach `note` to `branch`, replacing any earlier note. A blank note removes it.
+    pub fn set(&self, repo_path: &Path, branch: &str, note: &str) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        update_state_file(
+            path,
+            NOTES_STATE_VERSION,
+            |entries: &mut Vec<BranchNote>| {
+                entries.retain(|entry| entry.repo_path != repo_path || entry.branch != branch);
+                let note = note.trim();
+                if !note.is_empty() {
+                    entries.push(BranchNote {
+                        repo_path: repo_path.to_path_buf(),
+                        branch: branch.to_string(),
+                        note: note.to_string(),
+                    });
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn set_replaces_and_clears_notes_per_repo() {
+        let tmp = tempdir().unwrap();
+        let store = NoteStore::at(tmp.path().join("notes.toml"));
+        let repo = Path::new("/src/kiosk");
+        let other = Path::new("/src/other");
+
+        store.set(repo, "feat", "first").unwrap();
+        store.set(repo, "feat", "  waiting for review ").unwrap();
+        store.set(repo, "fix", "flaky test").unwrap();
+        store.set(other, "feat", "elsewhere").unwrap();
+        store.set(repo, "fix", " ").unwrap();
+
+        assert_eq!(
+            store.load(repo),
+            HashMap::from([("feat".to_string(), "waiting for review".to_string())])
+        );
+        assert_eq!(store.load(other).len(), 1);
+    }
+
+    #[test]
+    fn disabled_store_keeps_nothing() {
+        let store = NoteStore::disabled();
+        store.set(Path::new("/r"), "feat", "note").unwrap();
+        assert!(store.load(Path::new("/r")).is_empty());
+    }
+}
@@ -1,5 +1,32 @@
+use crate::config::APP_NAME;
 use std::path::PathBuf;
 
+/// Directory for kiosk's runtime state (pending deletes, the delete journal, quarantined
+/// worktrees). Honours `$XDG_STATE_HOME` on Unix.
+pub fn state_dir() -> PathBuf {
+    #[cfg(unix)]
+    {
+        if let Ok(xdg_state_home) = std::env::var("XDG_STATE_HOME")
+            && !xdg_state_home.is_empty()
+        {
+            return PathBuf::from(xdg_state_home).join(APP_NAME);
+        }
+        dirs::home_dir()
+            .expect("Unable to find home directory")
+            .join(".local")
+            .join("state")
+            .join(APP_NAME)
+    }
+    #[cfg(windows)]
+    {
+        if let Some(local_data) = dirs::data_local_dir() {
+            local_data.join(APP_NAME)
+        } else {
+            std::env::temp_dir().join(APP_NAME)
+        }
+    }
+}
+
 /// Expand a leading `~` to the user's home directory.
 ///
 /// Returns `None` when the path starts with `~` but the home directory
@@ -1,15 +1,22 @@
-use anyhow::Result;
+use crate::{
+    git::GitProvider,
+    paths::state_dir,
+    removal::remove_worktree_dir,
+    state_store::{load_state_file, save_state_file, update_state_file},
+};
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::{
-    fs,
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::{SystemTime, UNIX_EPOCH},
 };
 
-const APP_NAME: &str = "kiosk";
 const PENDING_DELETE_FILE_NAME: &str = "pending_deletes.toml";
 const PENDING_DELETE_STATE_VERSION: u32 = 1;
 const PENDING_DELETE_TTL_SECS: u64 = 60 * 60 * 24;
+const DELETE_JOURNAL_FILE_NAME: &str = "delete_journal.toml";
+const DELETE_JOURNAL_STATE_VERSION: u32 = 1;
+const DELETE_JOURNAL_RETENTION_SECS: u64 = 60 * 60 * 24 * 7;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PendingWorktreeDelete {
@@ -17,6 +24,9 @@ pub struct PendingWorktreeDelete {
     pub branch_name: String,
     pub worktree_path: PathBuf,
     pub started_at_unix_secs: u64,
+    /// The kiosk process deleting it, to tell a delete in progress from one cut short
+    #[serde(default)]
+    pub pid: u32,
 }
 
 impl PendingWorktreeDelete {
@@ -26,6 +36,7 @@ impl PendingWorktreeDelete {
             branch_name,
             worktree_path,
             started_at_unix_secs: now_unix_secs(),
+            pid: std::process::id(),
         }
     }
 
@@ -33,85 +44,284 @@ impl PendingWorktreeDelete {
         let age_secs = now_unix_secs().saturating_sub(self.started_at_unix_secs);
         age_secs > PENDING_DELETE_TTL_SECS
     }
+
+    pub fn is_for(&self, repo_path: &Path, branch_name: &str) -> bool {
+        self.repo_path == repo_path && self.branch_name == branch_name
+    }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
-struct PendingDeleteFile {
-    version: u32,
-    entries: Vec<PendingWorktreeDelete>,
+/// Worktree deletes in progress, shared by every kiosk process so they don't remove the same
+/// worktree twice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingDeleteStore {
+    path: Option<PathBuf>,
 }
 
-fn state_dir() -> PathBuf {
-    #[cfg(unix)]
-    {
-        if let Ok(xdg_state_home) = std::env::var("XDG_STATE_HOME")
-            && !xdg_state_home.is_empty()
-        {
-            return PathBuf::from(xdg_state_home).join(APP_NAME);
-        }
-        dirs::home_dir()
-            .expect("Unable to find home directory")
-            .join(".local")
-            .join("state")
-            .join(APP_NAME)
+impl Default for PendingDeleteStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(PENDING_DELETE_FILE_NAME))
     }
-    #[cfg(windows)]
-    {
-        if let Some(local_data) = dirs::data_local_dir() {
-            local_data.join(APP_NAME)
-        } else {
-            std::env::temp_dir().join(APP_NAME)
+}
+
+impl PendingDeleteStore {
+    /// Use deletes stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
         }
     }
+
+    /// A store that records nothing, so every delete may begin.
+    pub fn disabled() -> Self {
+        Self { path: None }
+    }
+
+    /// Non-expired deletes, oldest first.
+    pub fn load(&self) -> Vec<PendingWorktreeDelete> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        load_state_file::<PendingWorktreeDelete>(path, PENDING_DELETE_STATE_VERSION)
+            .into_iter()
+            .filter(|entry| !entry.is_expired())
+            .collect()
+    }
+
+    /// Record `entry` as in progress, unless a delete of its branch already is. Returns
+    /// whether it was recorded.
+    pub fn begin(&self, entry: PendingWorktreeDelete) -> Result<bool> {
+        let Some(path) = &self.path else {
+            return Ok(true);
+        };
+        update_state_file(
+            path,
+            PENDING_DELETE_STATE_VERSION,
+            |entries: &mut Vec<PendingWorktreeDelete>| {
+                entries.retain(|existing| !existing.is_expired());
+                if entries
+                    .iter()
+                    .any(|existing| existing.is_for(&entry.repo_path, &entry.branch_name))
+                {
+                    return false;
+                }
+                entries.push(entry);
+                true
+            },
+        )
+    }
+
+    /// Drop the deletes `finished` matches.
+    pub fn finish(&self, finished: impl Fn(&PendingWorktreeDelete) -> bool) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        update_state_file(
+            path,
+            PENDING_DELETE_STATE_VERSION,
+            |entries: &mut Vec<PendingWorktreeDelete>| {
+                entries.retain(|entry| !entry.is_expired() && !finished(entry));
+            },
+        )
+    }
+}
+
+/// A removed worktree recorded in the delete journal so it can be restored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeletedWorktree {
+    pub repo_path: PathBuf,
+    pub branch_name: String,
+    pub worktree_path: PathBuf,
+    /// Commit the branch pointed to when the worktree was removed
+    pub head: String,
+    /// Stash commit holding uncommitted changes, if there were any
+    pub stash: Option<String>,
+    pub deleted_at_unix_secs: u64,
+}
+
+impl DeletedWorktree {
+    pub fn is_expired(&self) -> bool {
+        let age_secs = now_unix_secs().saturating_sub(self.deleted_at_unix_secs);
+        age_secs > DELETE_JOURNAL_RETENTION_SECS
+    }
 }
 
-fn state_file() -> PathBuf {
-    state_dir().join(PENDING_DELETE_FILE_NAME)
+/// Journal of recently deleted worktrees, backing `kiosk undo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeleteJournal {
+    path: Option<PathBuf>,
 }
 
-pub fn load_pending_worktree_deletes() -> Vec<PendingWorktreeDelete> {
-    let file_path = state_file();
-    let Ok(contents) = fs::read_to_string(&file_path) else {
-        return Vec::new();
-    };
+impl Default for DeleteJournal {
+    fn default() -> Self {
+        Self::at(state_dir().join(DELETE_JOURNAL_FILE_NAME))
+    }
+}
 
-    let Ok(parsed) = toml::from_str::<PendingDeleteFile>(&contents) else {
-        return Vec::new();
-    };
+impl DeleteJournal {
+    /// Use a journal stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
 
-    if parsed.version != PENDING_DELETE_STATE_VERSION {
-        return Vec::new();
+    /// A journal that never records or returns any deletions.
+    pub fn disabled() -> Self {
+        Self { path: None }
     }
 
-    parsed
-        .entries
-        .into_iter()
-        .filter(|entry| !entry.is_expired())
-        .collect()
-}
+    /// Load non-expired journal entries, oldest first.
+    pub fn load(&self) -> Vec<DeletedWorktree> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        load_state_file::<DeletedWorktree>(path, DELETE_JOURNAL_STATE_VERSION)
+            .into_iter()
+            .filter(|entry| !entry.is_expired())
+            .collect()
+    }
+
+    pub fn save(&self, entries: &[DeletedWorktree]) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        save_state_file(path, DELETE_JOURNAL_STATE_VERSION, entries)
+    }
+
+    fn record(&self, entry: DeletedWorktree) -> Result<()> {
+        self.update(|entries| {
+            entries.retain(|existing| existing.worktree_path != entry.worktree_path);
+            entries.push(entry);
+        })
+    }
+
+    pub(crate) fn forget(&self, entry: &DeletedWorktree) -> Result<()> {
+        self.update(|entries| entries.retain(|existing| existing != entry))
+    }
 
-pub fn save_pending_worktree_deletes(entries: &[PendingWorktreeDelete]) -> Result<()> {
-    let state_dir = state_dir();
-    fs::create_dir_all(&state_dir)?;
+    fn update<T>(&self, edit: impl FnOnce(&mut Vec<DeletedWorktree>) -> T) -> Result<T>
+    where
+        T: Default,
+    {
+        let Some(path) = &self.path else {
+            return Ok(T::default());
+        };
+        update_state_file(path, DELETE_JOURNAL_STATE_VERSION, edit)
+    }
 
-    let file_path = state_file();
-    if entries.is_empty() {
-        if file_path.exists() {
-            fs::remove_file(file_path)?;
+    /// Forget entries past the retention period and drop their stashes, which nothing would
+    /// otherwise ever reapply.
+    pub fn expire(&self, git: &dyn GitProvider) -> Result<()> {
+        let expired = self.update(|entries| {
+            let (expired, kept) = std::mem::take(entries)
+                .into_iter()
+                .partition::<Vec<_>, _>(DeletedWorktree::is_expired);
+            *entries = kept;
+            expired
+        })?;
+        for entry in expired {
+            let Some(stash) = &entry.stash else {
+                continue;
+            };
+            if let Err(e) = git.drop_stash(&entry.repo_path, stash) {
+                tracing::warn!(
+                    "Failed to drop stash {stash} of deleted worktree {}: {e:#}",
+                    entry.worktree_path.display()
+                );
+            }
         }
-        return Ok(());
+        Ok(())
+    }
+
+    /// Most recently deleted worktree, optionally restricted to a repo and branch.
+    pub fn latest(
+        &self,
+        repo_path: Option<&Path>,
+        branch: Option<&str>,
+    ) -> Option<DeletedWorktree> {
+        self.load()
+            .into_iter()
+            .filter(|entry| repo_path.is_none_or(|path| entry.repo_path == path))
+            .filter(|entry| branch.is_none_or(|name| entry.branch_name == name))
+            .max_by_key(|entry| entry.deleted_at_unix_secs)
     }
+}
 
-    let state = PendingDeleteFile {
-        version: PENDING_DELETE_STATE_VERSION,
-        entries: entries.to_vec(),
+/// Remove a worktree, first stashing uncommitted changes and recording it in the journal.
+///
+/// If removal fails, the stash is reapplied and the journal entry dropped.
+pub fn remove_worktree_with_journal(
+    git: &dyn GitProvider,
+    journal: &DeleteJournal,
+    repo_path: &Path,
+    branch_name: &str,
+    worktree_path: &Path,
+    use_trash: bool,
+) -> Result<()> {
+    // Nothing to restore if the worktree is already broken; just clean it up.
+    let Ok(head) = git.head_commit(worktree_path) else {
+        return remove_worktree_dir(git, repo_path, worktree_path, use_trash);
+    };
+    journal.expire(git)?;
+    let stash = git.stash_changes(worktree_path, &format!("kiosk: WIP on {branch_name}"))?;
+    let entry = DeletedWorktree {
+        repo_path: repo_path.to_path_buf(),
+        branch_name: branch_name.to_string(),
+        worktree_path: worktree_path.to_path_buf(),
+        head,
+        stash,
+        deleted_at_unix_secs: now_unix_secs(),
     };
-    let serialized = toml::to_string(&state)?;
-    fs::write(file_path, serialized)?;
+    journal.record(entry.clone())?;
+
+    if let Err(error) = remove_worktree_dir(git, repo_path, worktree_path, use_trash) {
+        if let Some(stash) = &entry.stash {
+            git.pop_stash(worktree_path, stash)
+                .with_context(|| format!("{error}; also failed to reapply stash {stash}"))?;
+        }
+        journal.forget(&entry)?;
+        return Err(error);
+    }
+
     Ok(())
 }
 
-fn now_unix_secs() -> u64 {
+/// Recreate a journaled worktree on its branch and reapply any stashed changes.
+pub fn restore_deleted_worktree(
+    git: &dyn GitProvider,
+    journal: &DeleteJournal,
+    entry: &DeletedWorktree,
+) -> Result<()> {
+    if entry.worktree_path.exists() {
+        anyhow::bail!(
+            "cannot restore worktree: {} already exists",
+            entry.worktree_path.display()
+        );
+    }
+
+    // Stale metadata from the removal would otherwise block re-adding the branch.
+    git.prune_worktrees(&entry.repo_path)?;
+    let local = git.list_branches(&entry.repo_path);
+    if local.iter().any(|branch| branch == &entry.branch_name) {
+        git.add_worktree(&entry.repo_path, &entry.branch_name, &entry.worktree_path)?;
+    } else {
+        git.create_branch_and_worktree(
+            &entry.repo_path,
+            &entry.branch_name,
+            &entry.head,
+            &entry.worktree_path,
+        )?;
+    }
+
+    if let Some(stash) = &entry.stash {
+        git.pop_stash(&entry.worktree_path, stash)
+            .with_context(|| format!("worktree restored, but failed to reapply stash {stash}"))?;
+    }
+
+    journal.forget(entry)
+}
+
+pub fn now_unix_secs() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_or(0, |duration| duration.as_secs())
@@ -120,6 +330,8 @@ fn now_unix_secs() -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::git::mock::MockGitProvider;
+    use std::sync::Mutex;
 
     #[test]
     fn test_pending_delete_expiry() {
@@ -128,7 +340,124 @@ mod tests {
             branch_name: "dev".to_string(),
             worktree_path: PathBuf::from("/tmp/repo-dev"),
             started_at_unix_secs: 0,
+            pid: 0,
         };
         assert!(entry.is_expired());
     }
+
+    #[test]
+    fn test_pending_delete_store_begins_each_branch_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PendingDeleteStore::at(dir.path().join("pending.toml"));
+        let dev = || {
+            PendingWorktreeDelete::new(
+                PathBuf::from("/tmp/repo"),
+                "dev".to_string(),
+                PathBuf::from("/tmp/repo-dev"),
+            )
+        };
+        let mut expired = dev();
+        expired.branch_name = "old".to_string();
+        expired.started_at_unix_secs = 0;
+        save_state_file(
+            store.path.as_ref().unwrap(),
+            PENDING_DELETE_STATE_VERSION,
+            &[expired],
+        )
+        .unwrap();
+
+        assert!(store.begin(dev()).unwrap());
+        assert!(!store.begin(dev()).unwrap(), "already in progress");
+        let branches: Vec<_> = store.load().into_iter().map(|e| e.branch_name).collect();
+        assert_eq!(branches, ["dev"], "expired deletes are dropped");
+
+        store
+            .finish(|entry| entry.is_for(Path::new("/tmp/repo"), "dev"))
+            .unwrap();
+        assert!(store.load().is_empty());
+        assert!(store.begin(dev()).unwrap());
+    }
+
+    fn deleted(branch: &str, deleted_at_unix_secs: u64) -> DeletedWorktree {
+        DeletedWorktree {
+            repo_path: PathBuf::from("/tmp/repo"),
+            branch_name: branch.to_string(),
+            worktree_path: PathBuf::from(format!("/tmp/repo-{branch}")),
+            head: "abc123".to_string(),
+            stash: None,
+            deleted_at_unix_secs,
+        }
+    }
+
+    #[test]
+    fn test_delete_journal_latest_skips_expired_and_filters_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = DeleteJournal::at(dir.path().join("journal.toml"));
+        let now = now_unix_secs();
+        journal
+            .save(&[deleted("old", 0), deleted("a", now - 10), deleted("b", now)])
+            .unwrap();
+
+        assert_eq!(journal.load().len(), 2);
+        assert_eq!(journal.latest(None, None).unwrap().branch_name, "b");
+        assert_eq!(journal.latest(None, Some("a")).unwrap().branch_name, "a");
+        assert!(
+            journal
+                .latest(Some(Path::new("/tmp/other")), None)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_delete_journal_expire_drops_stashes_of_expired_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = DeleteJournal::at(dir.path().join("journal.toml"));
+        let git = MockGitProvider::default();
+        let stashed = |branch, deleted_at| DeletedWorktree {
+            stash: Some(format!("{branch}-stash")),
+            ..deleted(branch, deleted_at)
+        };
+        journal
+            .save(&[stashed("old", 0), stashed("new", now_unix_secs())])
+            .unwrap();
+
+        journal.expire(&git).unwrap();
+
+        assert_eq!(
+            git.drop_stash_calls.lock().unwrap().as_slice(),
+            &[(PathBuf::from("/tmp/repo"), "old-stash".to_string())]
+        );
+        let entries = load_state_file::<DeletedWorktree>(
+            journal.path.as_deref().unwrap(),
+            DELETE_JOURNAL_STATE_VERSION,
+        );
+        assert_eq!(entries, [stashed("new", entries[0].deleted_at_unix_secs)]);
+    }
+
+    #[test]
+    fn test_failed_removal_reapplies_stash_and_forgets_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = DeleteJournal::at(dir.path().join("journal.toml"));
+        let git = MockGitProvider {
+            stash_result: Mutex::new(Some(Ok(Some("stash-sha".to_string())))),
+            remove_worktree_result: Mutex::new(Some(Err(anyhow::anyhow!("locked")))),
+            ..Default::default()
+        };
+
+        let result = remove_worktree_with_journal(
+            &git,
+            &journal,
+            Path::new("/tmp/repo"),
+            "dev",
+            Path::new("/tmp/repo-dev"),
+            false,
+        );
+
+        assert!(result.is_err());
+        assert!(journal.load().is_empty());
+        assert_eq!(
+            git.pop_stash_calls.lock().unwrap().as_slice(),
+            &[(PathBuf::from("/tmp/repo-dev"), "stash-sha".to_string())]
+        );
+    }
 }
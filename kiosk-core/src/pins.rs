@@ -0,0 +1,92 @@
+use crate::{
+    paths::state_dir,
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+const PINS_FILE_NAME: &str = "pins.toml";
+const PINS_STATE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct PinnedRepo {
+    path: PathBuf,
+}
+
+/// Persisted set of pinned repos, which always sort to the top of the repo list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinStore {
+    path: PathBuf,
+}
+
+impl Default for PinStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(PINS_FILE_NAME))
+    }
+}
+
+impl PinStore {
+    /// Use pins stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn load(&self) -> HashSet<PathBuf> {
+        load_state_file::<PinnedRepo>(&self.path, PINS_STATE_VERSION)
+            .into_iter()
+            .map(|pin| pin.path)
+            .collect()
+    }
+
+    /// Pin `repo_path`, or unpin it if it was already pinned. Returns the updated set.
+    pub fn toggle(&self, repo_path: &Path) -> Result<HashSet<PathBuf>> {
+        update_state_file(
+            &self.path,
+            PINS_STATE_VERSION,
+            |entries: &mut Vec<PinnedRepo>| {
+                let mut pinned: HashSet<PathBuf> = entries.drain(..).map(|pin| pin.path).collect();
+                if !pinned.remove(repo_path) {
+                    pinned.insert(repo_path.to_path_buf());
+                }
+                entries.extend(pinned.iter().map(|path| PinnedRepo { path: path.clone() }));
+                entries.sort_by(|a, b| a.path.cmp(&b.path));
+                pinned
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn toggle_pins_and_unpins() {
+        let tmp = tempdir().unwrap();
+        let store = PinStore::at(tmp.path().join("pins.toml"));
+
+        let pinned = store.toggle(Path::new("/a")).unwrap();
+        assert!(pinned.contains(Path::new("/a")));
+        store.toggle(Path::new("/b")).unwrap();
+        assert_eq!(store.load().len(), 2);
+
+        let pinned = store.toggle(Path::new("/a")).unwrap();
+        assert!(!pinned.contains(Path::new("/a")));
+        assert_eq!(
+            store.load(),
+            HashSet::from([PathBuf::from("/b")]),
+            "unpinning persists"
+        );
+    }
+
+    #[test]
+    fn missing_file_has_no_pins() {
+        let tmp = tempdir().unwrap();
+        assert!(PinStore::at(tmp.path().join("pins.toml")).load().is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+
+/// Replace each `{key}` in `text` with its value from `values`. Placeholders without a value
+/// are left as they are, and values are inserted as is, so a value can't introduce further
+/// placeholders.
+pub fn expand(text: &str, values: &[(&str, &str)]) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let key = &after[..end];
+            values
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| (*value, end))
+        });
+        if let Some((value, end)) = value {
+            expanded.push_str(value);
+            rest = &after[end + 1..];
+        } else {
+            expanded.push('{');
+            rest = after;
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// `value` as a single shell word: as it is when that's safe, else single-quoted.
+pub fn shell_quote(value: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:@%+=,".contains(c);
+    if !value.is_empty() && value.chars().all(is_safe) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// What a session was opened on, for the `{repo}`, `{branch}`, `{worktree}`, `{session}` and
+/// `{base}` placeholders in its commands and for its `KIOSK_*` environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionContext {
+    pub repo: String,
+    /// `None` for a detached `HEAD`
+    pub branch: Option<String>,
+    pub worktree: PathBuf,
+    pub session: String,
+    /// The branch `branch` was created from, or else the repo's default branch
+    pub base: Option<String>,
+}
+
+impl SessionContext {
+    fn values(&self) -> [(&'static str, String); 5] {
+        [
+            ("repo", self.repo.clone()),
+            ("branch", self.branch.clone().unwrap_or_default()),
+            ("worktree", self.worktree.to_string_lossy().into_owned()),
+            ("session", self.session.clone()),
+            ("base", self.base.clone().unwrap_or_default()),
+        ]
+    }
+
+    /// `command` with this session's placeholders filled in, each quoted as one shell word
+    /// where it needs to be; unknown ones are left alone and unknown values (`{branch}` on a
+    /// detached `HEAD`) become `''`.
+    pub fn expand(&self, command: &str) -> String {
+        let values: Vec<(&str, String)> = self
+            .values()
+            .into_iter()
+            .map(|(key, value)| (key, shell_quote(&value)))
+            .collect();
+        let values: Vec<(&str, &str)> = values
+            .iter()
+            .map(|(key, value)| (*key, value.as_str()))
+            .collect();
+        expand(command, &values)
+    }
+
+    /// `KIOSK_REPO`, `KIOSK_BRANCH`, `KIOSK_WORKTREE`, `KIOSK_SESSION` and `KIOSK_BASE`, leaving
+    /// out any that are unknown.
+    pub fn env(&self) -> Vec<(String, String)> {
+        self.values()
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (format!("KIOSK_{}", key.to_uppercase()), value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> SessionContext {
+        SessionContext {
+            repo: "kiosk".to_string(),
+            branch: Some("feat/{base}".to_string()),
+            worktree: PathBuf::from("/src/.kiosk_worktrees/kiosk--feat_x"),
+            session: "kiosk--feat_x".to_string(),
+            base: None,
+        }
+    }
+
+    #[test]
+    fn expand_fills_known_placeholders_and_keeps_the_rest() {
+        assert_eq!(
+            context().expand("cd {worktree} && echo {repo}:{branch} {base}{unknown} {"),
+            "cd /src/.kiosk_worktrees/kiosk--feat_x && echo kiosk:'feat/{base}' ''{unknown} {"
+        );
+    }
+
+    #[test]
+    fn expand_quotes_values_the_shell_would_split_or_run() {
+        let context = SessionContext {
+            branch: Some("fix/it's $(rm -rf ~)".to_string()),
+            worktree: PathBuf::from("/src/my repo"),
+            ..context()
+        };
+        assert_eq!(
+            context.expand("cd {worktree} && git log {branch}"),
+            r"cd '/src/my repo' && git log 'fix/it'\''s $(rm -rf ~)'"
+        );
+    }
+
+    #[test]
+    fn env_leaves_out_unknown_values() {
+        let env = context().env();
+        let keys: Vec<&str> = env.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "KIOSK_REPO",
+                "KIOSK_BRANCH",
+                "KIOSK_WORKTREE",
+                "KIOSK_SESSION"
+            ]
+        );
+    }
+}
@@ -0,0 +1,557 @@
+#[cfg(unix)]
+use rustix::process::{Pid, Signal, kill_process};
+use std::{
+    io::{self, Read, Seek, SeekFrom, Write},
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::mpsc,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// Tracing target for every git/tmux subprocess kiosk runs. Filter on it with
+/// `KIOSK_LOG=kiosk::command=debug`.
+pub const COMMAND_TRACE_TARGET: &str = "kiosk::command";
+
+/// Run a [`Command`] while recording its command line, working directory, duration and
+/// exit status at debug level.
+pub trait CommandExt {
+    /// Traced equivalent of [`Command::output`].
+    fn traced_output(&mut self) -> io::Result<Output>;
+
+    /// Traced equivalent of [`Command::status`].
+    fn traced_status(&mut self) -> io::Result<ExitStatus>;
+
+    /// Like [`CommandExt::traced_output`], writing `input` to the command's stdin.
+    fn traced_output_with_stdin(&mut self, input: &[u8]) -> io::Result<Output>;
+
+    /// Like [`CommandExt::traced_output`], calling `on_line` with each line of stderr as it's
+    /// written. Lines ending in `\r`, as progress meters redraw them, count as lines too.
+    /// Once `is_cancelled` returns true the command is stopped and this fails with
+    /// [`io::ErrorKind::Interrupted`].
+    fn traced_output_with_stderr_lines(
+        &mut self,
+        on_line: &mut dyn FnMut(&str),
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> io::Result<Output>;
+}
+
+impl CommandExt for Command {
+    fn traced_output(&mut self) -> io::Result<Output> {
+        traced(self, Command::output)
+    }
+
+    fn traced_status(&mut self) -> io::Result<ExitStatus> {
+        let started = Instant::now();
+        let result = self.status();
+        match &result {
+            Ok(status) => record(self, started.elapsed(), *status, &[]),
+            Err(error) => record_spawn_failure(self, error),
+        }
+        result
+    }
+
+    fn traced_output_with_stdin(&mut self, input: &[u8]) -> io::Result<Output> {
+        traced(self, |command| {
+            supervise(command, Some(input), &mut |_| {}, &|| false, None)
+        })
+    }
+
+    fn traced_output_with_stderr_lines(
+        &mut self,
+        on_line: &mut dyn FnMut(&str),
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> io::Result<Output> {
+        traced(self, |command| {
+            supervise(command, None, on_line, is_cancelled, None)
+        })
+    }
+}
+
+/// Run `command` with `run`, recording how it went.
+fn traced(
+    command: &mut Command,
+    run: impl FnOnce(&mut Command) -> io::Result<Output>,
+) -> io::Result<Output> {
+    let started = Instant::now();
+    let result = run(command);
+    match &result {
+        Ok(output) => record(command, started.elapsed(), output.status, &output.stderr),
+        Err(error) => record_spawn_failure(command, error),
+    }
+    result
+}
+
+/// How a provider runs its program's commands: the [`CommandExt`] methods, with a time limit
+/// and, for commands that only read, retries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommandPolicy {
+    /// Stop commands still running after this long. `None` lets them run until they exit.
+    pub timeout: Option<Duration>,
+    /// Stderr snippets that mark a failure as transient, such as a lock held by another git
+    /// process. [`CommandPolicy::retry_transient`] retries those up to [`MAX_RETRIES`] times.
+    pub transient_errors: &'static [&'static str],
+}
+
+/// Attempts after the first for a command that failed transiently.
+pub const MAX_RETRIES: u32 = 2;
+
+/// Wait before the first retry, doubling for each one after it.
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+impl CommandPolicy {
+    /// [`CommandExt::traced_output`], stopping `command` once it outlives the timeout. A
+    /// failure isn't retried, since the command may have changed something before failing.
+    pub fn output(&self, command: &mut Command) -> io::Result<Output> {
+        match self.timeout {
+            None => command.traced_output(),
+            Some(timeout) => traced(command, |command| output_within(command, timeout)),
+        }
+    }
+
+    /// [`CommandPolicy::output`] for a command that changes nothing, so is safe to run again:
+    /// transient failures are retried.
+    pub fn read_output(&self, command: &mut Command) -> io::Result<Output> {
+        self.retry_transient(command, |command| self.output(command))
+    }
+
+    /// Run `command` with `run`, running it again while it fails transiently. Only for
+    /// commands that are safe to run again.
+    pub fn retry_transient(
+        &self,
+        command: &mut Command,
+        mut run: impl FnMut(&mut Command) -> io::Result<Output>,
+    ) -> io::Result<Output> {
+        let mut backoff = RETRY_BACKOFF;
+        for attempt in 1..=MAX_RETRIES {
+            match run(command) {
+                Ok(output) if self.is_transient(&output) => {
+                    tracing::debug!(
+                        target: COMMAND_TRACE_TARGET,
+                        command = %command_line(command),
+                        attempt,
+                        "retrying after a transient failure"
+                    );
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
+        run(command)
+    }
+
+    /// [`CommandExt::traced_output_with_stdin`] within the timeout.
+    pub fn output_with_stdin(&self, command: &mut Command, input: &[u8]) -> io::Result<Output> {
+        traced(command, |command| {
+            supervise(command, Some(input), &mut |_| {}, &|| false, self.timeout)
+        })
+    }
+
+    /// [`CommandExt::traced_output_with_stderr_lines`] within the timeout.
+    pub fn output_with_stderr_lines(
+        &self,
+        command: &mut Command,
+        on_line: &mut dyn FnMut(&str),
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> io::Result<Output> {
+        traced(command, |command| {
+            supervise(command, None, on_line, is_cancelled, self.timeout)
+        })
+    }
+
+    fn is_transient(&self, output: &Output) -> bool {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        !output.status.success()
+            && self
+                .transient_errors
+                .iter()
+                .any(|error| stderr.contains(error))
+    }
+}
+
+/// Run `command`, stopping it once it outlives `timeout`. Its output goes to temporary files
+/// rather than pipes, so nothing has to read them while it runs and no thread is started.
+fn output_within(command: &mut Command, timeout: Duration) -> io::Result<Output> {
+    let mut stdout = tempfile::tempfile()?;
+    let mut stderr = tempfile::tempfile()?;
+    let started = Instant::now();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(stdout.try_clone()?)
+        .stderr(stderr.try_clone()?)
+        .spawn()?;
+    let mut poll = Duration::from_millis(1);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            terminate(&mut child)?;
+            return Err(timed_out_error(command, timeout));
+        }
+        std::thread::sleep(poll);
+        poll = (poll * 2).min(CANCEL_POLL_INTERVAL);
+    };
+    Ok(Output {
+        status,
+        stdout: read_from_start(&mut stdout)?,
+        stderr: read_from_start(&mut stderr)?,
+    })
+}
+
+fn read_from_start(file: &mut std::fs::File) -> io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// Run `command` with its output piped, writing `input` to its stdin and passing each line of
+/// its stderr to `on_line`. It's stopped once `is_cancelled` returns true or it outlives
+/// `timeout`.
+fn supervise(
+    command: &mut Command,
+    input: Option<&[u8]>,
+    on_line: &mut dyn FnMut(&str),
+    is_cancelled: &dyn Fn() -> bool,
+    timeout: Option<Duration>,
+) -> io::Result<Output> {
+    let started = Instant::now();
+    let mut child = command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_in_background(child.stdout.take(), |_| {});
+    let (line_tx, lines) = mpsc::channel();
+    let stderr = read_in_background(child.stderr.take(), move |line| {
+        let _ = line_tx.send(line.to_string());
+    });
+    // Dropping stdin after writing closes it so the command sees EOF
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input)?;
+    }
+
+    let mut stderr_open = true;
+    let status = loop {
+        if stderr_open {
+            match lines.recv_timeout(CANCEL_POLL_INTERVAL) {
+                Ok(line) => on_line(&line),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => stderr_open = false,
+            }
+        } else if let Some(status) = child.try_wait()? {
+            break status;
+        } else {
+            std::thread::sleep(EXIT_POLL_INTERVAL);
+        }
+        if is_cancelled() {
+            terminate(&mut child)?;
+            return Err(cancelled_error());
+        }
+        if let Some(timeout) = timeout
+            && started.elapsed() >= timeout
+        {
+            terminate(&mut child)?;
+            return Err(timed_out_error(command, timeout));
+        }
+    };
+    Ok(Output {
+        status,
+        stdout: join_reader(stdout)?,
+        stderr: join_reader(stderr)?,
+    })
+}
+
+fn timed_out_error(command: &Command, timeout: Duration) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!(
+            "{} timed out after {}s",
+            command_line(command),
+            timeout.as_secs()
+        ),
+    )
+}
+
+/// The error a command fails with once it has been cancelled.
+pub(crate) fn cancelled_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "cancelled")
+}
+
+/// How often a cancellable command checks whether it has been cancelled.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often a command that has closed stderr is checked for having exited.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How long a cancelled command gets to clean up after `SIGTERM` before it's killed.
+#[cfg(unix)]
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// Stop `child`, asking nicely first so git can remove its lock files and half-created
+/// worktrees, as it does when interrupted. Without `SIGTERM` (on Windows) it's killed
+/// straight away.
+fn terminate(child: &mut Child) -> io::Result<ExitStatus> {
+    #[cfg(unix)]
+    {
+        let asked = kill_process(Pid::from_child(child), Signal::TERM).is_ok();
+        let deadline = Instant::now() + TERMINATE_GRACE_PERIOD;
+        while asked && Instant::now() < deadline {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status);
+            }
+            std::thread::sleep(CANCEL_POLL_INTERVAL);
+        }
+    }
+    child.kill()?;
+    child.wait()
+}
+
+/// Read `reader` on another thread with [`read_lines`], as a child writing to a full pipe
+/// would otherwise block.
+fn read_in_background(
+    reader: Option<impl Read + Send + 'static>,
+    mut on_line: impl FnMut(&str) + Send + 'static,
+) -> JoinHandle<io::Result<Vec<u8>>> {
+    std::thread::spawn(move || match reader {
+        Some(reader) => read_lines(reader, &mut on_line),
+        None => Ok(Vec::new()),
+    })
+}
+
+fn join_reader(reader: JoinHandle<io::Result<Vec<u8>>>) -> io::Result<Vec<u8>> {
+    reader
+        .join()
+        .map_err(|_| io::Error::other("output reader panicked"))?
+}
+
+/// Read `reader` to the end, calling `on_line` with every non-empty line terminated by `\n` or
+/// `\r`, and return everything read.
+fn read_lines(mut reader: impl Read, on_line: &mut dyn FnMut(&str)) -> io::Result<Vec<u8>> {
+    let mut all = Vec::new();
+    let mut line_start = 0;
+    let mut chunk = [0; 4096];
+    loop {
+        let read = reader.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        all.extend_from_slice(&chunk[..read]);
+        while let Some(end) = all[line_start..]
+            .iter()
+            .position(|byte| matches!(byte, b'\n' | b'\r'))
+        {
+            let line = String::from_utf8_lossy(&all[line_start..line_start + end]);
+            if !line.trim().is_empty() {
+                on_line(line.trim_end());
+            }
+            line_start += end + 1;
+        }
+    }
+    let rest = String::from_utf8_lossy(&all[line_start..]);
+    if !rest.trim().is_empty() {
+        on_line(rest.trim_end());
+    }
+    Ok(all)
+}
+
+fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn record(command: &Command, elapsed: Duration, status: ExitStatus, stderr: &[u8]) {
+    let duration_ms = elapsed.as_secs_f64() * 1000.0;
+    let cwd = command
+        .get_current_dir()
+        .map_or_else(|| ".".to_string(), |dir| dir.display().to_string());
+    if status.success() {
+        tracing::debug!(
+            target: COMMAND_TRACE_TARGET,
+            command = %command_line(command),
+            cwd = %cwd,
+            duration_ms,
+            exit_code = status.code(),
+            "command succeeded"
+        );
+    } else {
+        tracing::debug!(
+            target: COMMAND_TRACE_TARGET,
+            command = %command_line(command),
+            cwd = %cwd,
+            duration_ms,
+            exit_code = status.code(),
+            stderr = %String::from_utf8_lossy(stderr).trim(),
+            "command failed"
+        );
+    }
+}
+
+fn record_spawn_failure(command: &Command, error: &io::Error) {
+    tracing::warn!(
+        target: COMMAND_TRACE_TARGET,
+        command = %command_line(command),
+        %error,
+        "failed to run command"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CaptureWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for CaptureWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture(run: impl FnOnce()) -> String {
+        let writer = CaptureWriter::default();
+        let sink = writer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || sink.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, run);
+        String::from_utf8(writer.0.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn traced_output_records_command_and_exit_status() {
+        let logs = capture(|| {
+            let output = Command::new("git")
+                .args(["--version"])
+                .traced_output()
+                .unwrap();
+            assert!(output.status.success());
+        });
+
+        assert!(logs.contains("command succeeded"), "{logs}");
+        assert!(logs.contains("command=git --version"), "{logs}");
+        assert!(logs.contains("exit_code=0"), "{logs}");
+        assert!(logs.contains("duration_ms="), "{logs}");
+    }
+
+    #[test]
+    fn traced_output_records_stderr_on_failure() {
+        let logs = capture(|| {
+            let output = Command::new("git")
+                .args(["not-a-real-subcommand"])
+                .traced_output()
+                .unwrap();
+            assert!(!output.status.success());
+        });
+
+        assert!(logs.contains("command failed"), "{logs}");
+        assert!(logs.contains("not-a-real-subcommand"), "{logs}");
+        assert!(logs.contains("stderr="), "{logs}");
+    }
+
+    #[test]
+    fn traced_output_with_stdin_feeds_input() {
+        let output = Command::new("cat")
+            .traced_output_with_stdin(b"line one\nline two\n")
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"line one\nline two\n");
+    }
+
+    #[test]
+    fn read_lines_splits_progress_meters_on_carriage_returns() {
+        let mut lines = Vec::new();
+        let input = b"Receiving objects:  50% (1/2)\rReceiving objects: 100% (2/2), done.\nlast";
+        let all = read_lines(&input[..], &mut |line| lines.push(line.to_string())).unwrap();
+        assert_eq!(
+            lines,
+            [
+                "Receiving objects:  50% (1/2)",
+                "Receiving objects: 100% (2/2), done.",
+                "last"
+            ]
+        );
+        assert_eq!(all, input);
+    }
+
+    #[test]
+    fn traced_output_with_stderr_lines_streams_and_can_be_cancelled() {
+        let mut lines = Vec::new();
+        let output = Command::new("sh")
+            .args(["-c", "echo out; printf 'one\\rtwo\\n' >&2"])
+            .traced_output_with_stderr_lines(&mut |line| lines.push(line.to_string()), &|| false)
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(lines, ["one", "two"]);
+
+        let started = Instant::now();
+        let error = Command::new("sleep")
+            .arg("30")
+            .traced_output_with_stderr_lines(&mut |_| {}, &|| true)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn command_policy_times_out_and_only_retries_reads() {
+        let policy = CommandPolicy {
+            timeout: Some(Duration::from_millis(200)),
+            transient_errors: &["lost server"],
+        };
+
+        let started = Instant::now();
+        let error = policy.output(Command::new("sleep").arg("30")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(error.to_string().contains("timed out after"), "{error}");
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let dir = tempfile::tempdir().unwrap();
+        let flaky =
+            "if [ -e failed ]; then echo ok; else touch failed; echo 'lost server' >&2; exit 1; fi";
+        let mut command = Command::new("sh");
+        command.args(["-c", flaky]).current_dir(dir.path());
+        let output = policy.output(&mut command).unwrap();
+        assert!(!output.status.success());
+        assert_eq!(output.stderr, b"lost server\n");
+        std::fs::remove_file(dir.path().join("failed")).unwrap();
+        let output = policy.read_output(&mut command).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"ok\n");
+
+        let output = policy
+            .read_output(Command::new("sh").args(["-c", "echo 'fatal: bad revision' >&2; exit 1"]))
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(output.stderr, b"fatal: bad revision\n");
+    }
+
+    #[test]
+    fn traced_status_records_spawn_failures() {
+        let logs = capture(|| {
+            let result = Command::new("kiosk-definitely-missing-binary").traced_status();
+            assert!(result.is_err());
+        });
+
+        assert!(logs.contains("failed to run command"), "{logs}");
+        assert!(logs.contains("kiosk-definitely-missing-binary"), "{logs}");
+    }
+}
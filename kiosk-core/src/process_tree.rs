@@ -0,0 +1,235 @@
+//! The process trees rooted at sessions' panes, and the CPU and memory they use.
+
+use crate::process::CommandExt;
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::{HashMap, HashSet},
+    process::Command,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub parent: u32,
+    /// CPU use as `ps` reports it: 100 is one core busy
+    pub cpu_percent: f64,
+    /// Resident memory in KiB
+    pub rss_kib: u64,
+    /// Name of the program, like `node`
+    pub command: String,
+    /// The full command line
+    pub args: String,
+}
+
+/// Every process on the machine at one moment, as `ps` saw them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessTable {
+    processes: Vec<ProcessInfo>,
+}
+
+/// A process with everything it started, and everything they started, below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessTree {
+    pub process: ProcessInfo,
+    /// Ordered by pid, so oldest first unless pids wrapped
+    pub children: Vec<ProcessTree>,
+}
+
+/// What a process and all of its descendants use together.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TreeUsage {
+    pub cpu_percent: f64,
+    pub rss_kib: u64,
+    pub processes: usize,
+    /// The command of the process using the most CPU, to tell what's busy
+    pub busiest: Option<String>,
+}
+
+impl ProcessTable {
+    /// Read the process table with `ps`.
+    pub fn snapshot() -> Result<Self> {
+        let output = Command::new("ps")
+            .args(["-A", "-o", "pid=,ppid=,pcpu=,rss=,args="])
+            .traced_output()
+            .context("failed to run ps")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("ps failed: {}", stderr.trim());
+        }
+        Ok(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Parse `ps -o pid=,ppid=,pcpu=,rss=,args=` output, skipping lines that don't fit.
+    pub fn parse(output: &str) -> Self {
+        let processes = output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pid = fields.next()?.parse().ok()?;
+                let parent = fields.next()?.parse().ok()?;
+                let cpu_percent = fields.next()?.parse().ok()?;
+                let rss_kib = fields.next()?.parse().ok()?;
+                // The command line comes last and has spaces of its own
+                let args = fields.collect::<Vec<_>>().join(" ");
+                Some(ProcessInfo {
+                    pid,
+                    parent,
+                    cpu_percent,
+                    rss_kib,
+                    command: command_name(&args),
+                    args,
+                })
+            })
+            .collect();
+        Self { processes }
+    }
+
+    /// Whether `pid` was running when the table was read.
+    pub fn is_running(&self, pid: u32) -> bool {
+        self.processes.iter().any(|process| process.pid == pid)
+    }
+
+    /// `root` and everything below it, or `None` if it's gone.
+    pub fn tree(&self, root: u32) -> Option<ProcessTree> {
+        fn build(
+            process: &ProcessInfo,
+            children: &HashMap<u32, Vec<&ProcessInfo>>,
+            seen: &mut HashSet<u32>,
+        ) -> ProcessTree {
+            seen.insert(process.pid);
+            let mut below: Vec<&ProcessInfo> = children
+                .get(&process.pid)
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|child| !seen.contains(&child.pid))
+                .collect();
+            below.sort_by_key(|child| child.pid);
+            ProcessTree {
+                process: process.clone(),
+                children: below
+                    .into_iter()
+                    .map(|child| build(child, children, seen))
+                    .collect(),
+            }
+        }
+
+        let process = self.processes.iter().find(|process| process.pid == root)?;
+        Some(build(process, &self.children(), &mut HashSet::new()))
+    }
+
+    /// Usage of `roots` and everything below them, each process counted once.
+    pub fn tree_usage(&self, roots: &[u32]) -> TreeUsage {
+        let children = self.children();
+        let by_pid: HashMap<u32, &ProcessInfo> = self
+            .processes
+            .iter()
+            .map(|process| (process.pid, process))
+            .collect();
+
+        let mut usage = TreeUsage::default();
+        let mut busiest: Option<&ProcessInfo> = None;
+        let mut seen = HashSet::new();
+        let mut stack: Vec<&ProcessInfo> = roots
+            .iter()
+            .filter_map(|pid| by_pid.get(pid).copied())
+            .collect();
+        while let Some(process) = stack.pop() {
+            if !seen.insert(process.pid) {
+                continue;
+            }
+            usage.cpu_percent += process.cpu_percent;
+            usage.rss_kib += process.rss_kib;
+            usage.processes += 1;
+            if busiest.is_none_or(|busiest| process.cpu_percent > busiest.cpu_percent) {
+                busiest = Some(process);
+            }
+            stack.extend(children.get(&process.pid).into_iter().flatten());
+        }
+        usage.busiest = busiest.map(|process| process.command.clone());
+        usage
+    }
+
+    /// Each process's children, by the parent's pid.
+    fn children(&self) -> HashMap<u32, Vec<&ProcessInfo>> {
+        let mut children: HashMap<u32, Vec<&ProcessInfo>> = HashMap::new();
+        // pid 0 parents itself on some systems
+        for process in self.processes.iter().filter(|process| process.pid != 0) {
+            children.entry(process.parent).or_default().push(process);
+        }
+        children
+    }
+}
+
+/// The program `args` runs, without its directory or the `-` login shells start with.
+pub(crate) fn command_name(args: &str) -> String {
+    let program = args.split_whitespace().next().unwrap_or_default();
+    let name = program.rsplit('/').next().unwrap_or(program);
+    name.trim_start_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PS_OUTPUT: &str = "    1     0  0.0  1200 /sbin/init
+  100     1  0.5  4000 -zsh
+  101   100 95.0 800000 node server.js
+  102   101  2.5 20000 /usr/lib/esbuild --service
+  103   100  0.0  2000 tail -f log
+  200     1  0.1  3000 -zsh
+  300     1 50.0  9000 unrelated
+garbage line
+";
+
+    #[test]
+    fn parses_ps_output() {
+        let table = ProcessTable::parse(PS_OUTPUT);
+        assert_eq!(table.processes.len(), 7);
+        assert_eq!(
+            table.processes[3],
+            ProcessInfo {
+                pid: 102,
+                parent: 101,
+                cpu_percent: 2.5,
+                rss_kib: 20000,
+                command: "esbuild".to_string(),
+                args: "/usr/lib/esbuild --service".to_string(),
+            }
+        );
+        assert_eq!(table.processes[1].command, "zsh");
+    }
+
+    #[test]
+    fn sums_the_trees_under_the_roots() {
+        let table = ProcessTable::parse(PS_OUTPUT);
+        let usage = table.tree_usage(&[100, 200]);
+        assert!((usage.cpu_percent - 98.1).abs() < 1e-9, "{usage:?}");
+        assert_eq!(usage.rss_kib, 4000 + 800_000 + 20000 + 2000 + 3000);
+        assert_eq!(usage.processes, 5);
+        assert_eq!(usage.busiest.as_deref(), Some("node"));
+
+        // Roots inside another root's tree, or gone, aren't counted twice
+        assert_eq!(table.tree_usage(&[100, 101, 999]).processes, 4);
+        assert_eq!(table.tree_usage(&[999]), TreeUsage::default());
+    }
+
+    #[test]
+    fn builds_the_tree_under_a_process() {
+        let table = ProcessTable::parse(PS_OUTPUT);
+        let tree = table.tree(100).unwrap();
+        let shape = |tree: &ProcessTree| {
+            tree.children
+                .iter()
+                .map(|child| {
+                    let grandchildren: Vec<u32> =
+                        child.children.iter().map(|c| c.process.pid).collect();
+                    (child.process.pid, grandchildren)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tree.process.args, "-zsh");
+        assert_eq!(shape(&tree), [(101, vec![102]), (103, vec![])]);
+        assert_eq!(table.tree(999), None);
+    }
+}
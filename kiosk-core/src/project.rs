@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Kind of project in a repo or worktree, going by the build file at its root.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectKind {
+    Rust,
+    Node,
+    Go,
+    Python,
+}
+
+impl ProjectKind {
+    /// Marker files in the order they're checked, so a Rust crate with a `package.json` for
+    /// its web assets is still a Rust project.
+    const MARKERS: [(&str, Self); 4] = [
+        ("Cargo.toml", Self::Rust),
+        ("package.json", Self::Node),
+        ("go.mod", Self::Go),
+        ("pyproject.toml", Self::Python),
+    ];
+
+    pub fn detect(dir: &Path) -> Option<Self> {
+        Self::MARKERS
+            .iter()
+            .find(|(marker, _)| dir.join(marker).is_file())
+            .map(|&(_, kind)| kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn detects_kind_from_marker_files_in_priority_order() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(ProjectKind::detect(dir.path()), None);
+
+        fs::write(dir.path().join("pyproject.toml"), "").unwrap();
+        assert_eq!(ProjectKind::detect(dir.path()), Some(ProjectKind::Python));
+
+        fs::write(dir.path().join("package.json"), "{}").unwrap();
+        assert_eq!(ProjectKind::detect(dir.path()), Some(ProjectKind::Node));
+
+        fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(ProjectKind::detect(dir.path()), Some(ProjectKind::Rust));
+    }
+
+    #[test]
+    fn marker_directories_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("go.mod")).unwrap();
+        assert_eq!(ProjectKind::detect(dir.path()), None);
+    }
+}
@@ -0,0 +1,541 @@
+//! Worktree creates and deletes cut short by a crash or a killed terminal. Both are recorded
+//! in the state directory while they run, so one whose process has gone can be rolled forward
+//! (finished) or back (undone) by `kiosk recover` or the TUI's prompt, rather than leaving a
+//! half-made worktree for `kiosk clean` to find.
+
+use crate::{
+    git::GitProvider,
+    paths::state_dir,
+    pending_delete::{
+        DeleteJournal, DeletedWorktree, PendingDeleteStore, PendingWorktreeDelete, now_unix_secs,
+        remove_worktree_with_journal, restore_deleted_worktree,
+    },
+    process_tree::ProcessTable,
+    removal::{discard_dir, remove_worktree_dir},
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const PENDING_CREATE_FILE_NAME: &str = "pending_creates.toml";
+const PENDING_CREATE_STATE_VERSION: u32 = 1;
+
+/// A worktree being added, recorded before git starts on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingWorktreeCreate {
+    pub repo_path: PathBuf,
+    /// `None` for a detached worktree
+    pub branch_name: Option<String>,
+    pub worktree_path: PathBuf,
+    pub started_at_unix_secs: u64,
+    /// The kiosk process creating it
+    pub pid: u32,
+}
+
+impl PendingWorktreeCreate {
+    pub fn new(repo_path: PathBuf, branch_name: Option<String>, worktree_path: PathBuf) -> Self {
+        Self {
+            repo_path,
+            branch_name,
+            worktree_path,
+            started_at_unix_secs: now_unix_secs(),
+            pid: std::process::id(),
+        }
+    }
+}
+
+/// Worktrees being created by any kiosk process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingCreateStore {
+    path: PathBuf,
+}
+
+impl Default for PendingCreateStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(PENDING_CREATE_FILE_NAME))
+    }
+}
+
+impl PendingCreateStore {
+    /// Use creates stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn load(&self) -> Vec<PendingWorktreeCreate> {
+        load_state_file(&self.path, PENDING_CREATE_STATE_VERSION)
+    }
+
+    /// Run `create`, with `entry` recorded until it returns. Refuses to start while another
+    /// process has a create of the same worktree recorded, e.g. a kiosk in another terminal.
+    /// Failing to record it is logged rather than stopping the create.
+    pub fn track<T, E>(
+        &self,
+        entry: &PendingWorktreeCreate,
+        create: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        E: From<anyhow::Error>,
+    {
+        let recorded = update_state_file(
+            &self.path,
+            PENDING_CREATE_STATE_VERSION,
+            |entries: &mut Vec<PendingWorktreeCreate>| {
+                if entries.iter().any(|pending| {
+                    pending.worktree_path == entry.worktree_path && pending.pid != entry.pid
+                }) {
+                    return false;
+                }
+                entries.push(entry.clone());
+                true
+            },
+        );
+        match &recorded {
+            Ok(true) => {}
+            Ok(false) => {
+                return Err(anyhow!(
+                    "{} is already being created by another kiosk. If it was interrupted, run 'kiosk recover'",
+                    entry.worktree_path.display()
+                )
+                .into());
+            }
+            Err(e) => tracing::warn!(
+                "Failed to record creating {}: {e}",
+                entry.worktree_path.display()
+            ),
+        }
+        let result = create();
+        if recorded.is_ok()
+            && let Err(e) = self.finish(entry)
+        {
+            tracing::warn!(
+                "Failed to record creating {}: {e}",
+                entry.worktree_path.display()
+            );
+        }
+        result
+    }
+
+    fn finish(&self, finished: &PendingWorktreeCreate) -> Result<()> {
+        update_state_file(
+            &self.path,
+            PENDING_CREATE_STATE_VERSION,
+            |entries: &mut Vec<PendingWorktreeCreate>| entries.retain(|entry| entry != finished),
+        )
+    }
+}
+
+/// An operation whose process went away before it finished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Interrupted {
+    Create(PendingWorktreeCreate),
+    Delete(PendingWorktreeDelete),
+}
+
+impl Interrupted {
+    pub fn repo_path(&self) -> &Path {
+        match self {
+            Self::Create(entry) => &entry.repo_path,
+            Self::Delete(entry) => &entry.repo_path,
+        }
+    }
+
+    pub fn branch_name(&self) -> Option<&str> {
+        match self {
+            Self::Create(entry) => entry.branch_name.as_deref(),
+            Self::Delete(entry) => Some(&entry.branch_name),
+        }
+    }
+
+    pub fn worktree_path(&self) -> &Path {
+        match self {
+            Self::Create(entry) => &entry.worktree_path,
+            Self::Delete(entry) => &entry.worktree_path,
+        }
+    }
+
+    pub fn started_at_unix_secs(&self) -> u64 {
+        match self {
+            Self::Create(entry) => entry.started_at_unix_secs,
+            Self::Delete(entry) => entry.started_at_unix_secs,
+        }
+    }
+
+    /// `"create"` or `"delete"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Create(_) => "create",
+            Self::Delete(_) => "delete",
+        }
+    }
+
+    /// E.g. "deleting the worktree of feat/login".
+    pub fn describe(&self) -> String {
+        let verb = match self {
+            Self::Create(_) => "creating",
+            Self::Delete(_) => "deleting",
+        };
+        match self.branch_name() {
+            Some(branch) => format!("{verb} the worktree of {branch}"),
+            None => format!("{verb} {}", self.worktree_path().display()),
+        }
+    }
+}
+
+/// The records of running creates and deletes, and the journal deletes stash changes in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recovery {
+    pub creates: PendingCreateStore,
+    pub deletes: PendingDeleteStore,
+    pub journal: DeleteJournal,
+}
+
+impl Recovery {
+    /// Operations whose process has exited, oldest first. Nothing is reported when the
+    /// running processes can't be listed, so an operation in progress is never offered.
+    pub fn interrupted(&self) -> Vec<Interrupted> {
+        let creates = self.creates.load();
+        let deletes = self.deletes.load();
+        if creates.is_empty() && deletes.is_empty() {
+            return Vec::new();
+        }
+        match ProcessTable::snapshot() {
+            Ok(table) => interrupted_among(creates, deletes, |pid| table.is_running(pid)),
+            Err(e) => {
+                tracing::warn!("Can't tell which worktree operations were interrupted: {e:#}");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Finish `operation`: add the worktree a create was making, or remove the one a delete
+    /// was removing.
+    pub fn roll_forward(
+        &self,
+        git: &dyn GitProvider,
+        operation: &Interrupted,
+        use_trash: bool,
+    ) -> Result<()> {
+        match operation {
+            Interrupted::Create(entry) => {
+                if !is_registered(git, &entry.repo_path, &entry.worktree_path) {
+                    discard_partial_worktree(
+                        git,
+                        &entry.repo_path,
+                        &entry.worktree_path,
+                        use_trash,
+                    )?;
+                    let Some(branch) = entry
+                        .branch_name
+                        .as_ref()
+                        .filter(|branch| git.list_branches(&entry.repo_path).contains(branch))
+                    else {
+                        bail!(
+                            "can't finish creating {}: its branch is gone. Roll it back instead",
+                            entry.worktree_path.display()
+                        );
+                    };
+                    git.add_worktree(&entry.repo_path, branch, &entry.worktree_path)?;
+                }
+                self.creates.finish(entry)
+            }
+            Interrupted::Delete(entry) => {
+                if entry.worktree_path.exists() {
+                    if self.journaled(&entry.worktree_path).is_some() {
+                        // Changes were already stashed; stashing again would lose track of them
+                        remove_worktree_dir(
+                            git,
+                            &entry.repo_path,
+                            &entry.worktree_path,
+                            use_trash,
+                        )?;
+                    } else {
+                        remove_worktree_with_journal(
+                            git,
+                            &self.journal,
+                            &entry.repo_path,
+                            &entry.branch_name,
+                            &entry.worktree_path,
+                            use_trash,
+                        )?;
+                    }
+                }
+                git.prune_worktrees(&entry.repo_path)?;
+                self.deletes.finish(|pending| pending == entry)
+            }
+        }
+    }
+
+    /// Undo `operation`: remove what a create added, or restore what a delete removed,
+    /// including any changes it stashed.
+    pub fn roll_back(
+        &self,
+        git: &dyn GitProvider,
+        operation: &Interrupted,
+        use_trash: bool,
+    ) -> Result<()> {
+        match operation {
+            Interrupted::Create(entry) => {
+                if !is_registered(git, &entry.repo_path, &entry.worktree_path) {
+                    discard_partial_worktree(
+                        git,
+                        &entry.repo_path,
+                        &entry.worktree_path,
+                        use_trash,
+                    )?;
+                } else if let Some(branch) = &entry.branch_name {
+                    remove_worktree_with_journal(
+                        git,
+                        &self.journal,
+                        &entry.repo_path,
+                        branch,
+                        &entry.worktree_path,
+                        use_trash,
+                    )?;
+                } else {
+                    remove_worktree_dir(git, &entry.repo_path, &entry.worktree_path, use_trash)?;
+                }
+                self.creates.finish(entry)
+            }
+            Interrupted::Delete(entry) => {
+                match self.journaled(&entry.worktree_path) {
+                    Some(deleted) if entry.worktree_path.exists() => {
+                        if let Some(stash) = &deleted.stash {
+                            git.pop_stash(&entry.worktree_path, stash)?;
+                        }
+                        self.journal.forget(&deleted)?;
+                    }
+                    Some(deleted) => restore_deleted_worktree(git, &self.journal, &deleted)?,
+                    None if entry.worktree_path.exists() => {}
+                    None => bail!(
+                        "{} is already gone, with nothing to restore it from",
+                        entry.worktree_path.display()
+                    ),
+                }
+                self.deletes.finish(|pending| pending == entry)
+            }
+        }
+    }
+
+    fn journaled(&self, worktree_path: &Path) -> Option<DeletedWorktree> {
+        self.journal
+            .load()
+            .into_iter()
+            .find(|deleted| deleted.worktree_path == worktree_path)
+    }
+}
+
+/// The operations of processes other than this one that are no longer running, oldest first.
+fn interrupted_among(
+    creates: Vec<PendingWorktreeCreate>,
+    deletes: Vec<PendingWorktreeDelete>,
+    is_running: impl Fn(u32) -> bool,
+) -> Vec<Interrupted> {
+    let is_gone = |pid| pid != std::process::id() && !is_running(pid);
+    let creates = creates.into_iter().filter(|entry| is_gone(entry.pid));
+    let deletes = deletes.into_iter().filter(|entry| is_gone(entry.pid));
+    let mut interrupted: Vec<Interrupted> = creates
+        .map(Interrupted::Create)
+        .chain(deletes.map(Interrupted::Delete))
+        .collect();
+    interrupted.sort_by_key(Interrupted::started_at_unix_secs);
+    interrupted
+}
+
+fn is_registered(git: &dyn GitProvider, repo_path: &Path, worktree_path: &Path) -> bool {
+    worktree_path.exists()
+        && git
+            .list_worktrees(repo_path)
+            .iter()
+            .any(|worktree| worktree.path == worktree_path)
+}
+
+/// Remove whatever git left of a worktree it didn't finish adding, and its metadata.
+fn discard_partial_worktree(
+    git: &dyn GitProvider,
+    repo_path: &Path,
+    worktree_path: &Path,
+    use_trash: bool,
+) -> Result<()> {
+    if worktree_path.exists() {
+        discard_dir(worktree_path, use_trash)?;
+    }
+    git.prune_worktrees(repo_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        git::{Worktree, mock::MockGitProvider},
+        state_store::save_state_file,
+    };
+    use std::fs;
+
+    fn recovery(dir: &Path) -> Recovery {
+        Recovery {
+            creates: PendingCreateStore::at(dir.join("creates.toml")),
+            deletes: PendingDeleteStore::at(dir.join("deletes.toml")),
+            journal: DeleteJournal::at(dir.join("journal.toml")),
+        }
+    }
+
+    fn create(dir: &Path, pid: u32) -> PendingWorktreeCreate {
+        PendingWorktreeCreate {
+            pid,
+            ..PendingWorktreeCreate::new(
+                PathBuf::from("/tmp/repo"),
+                Some("dev".to_string()),
+                dir.join("repo-dev"),
+            )
+        }
+    }
+
+    #[test]
+    fn test_track_records_the_create_while_it_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PendingCreateStore::at(dir.path().join("creates.toml"));
+        let entry = create(dir.path(), std::process::id());
+
+        // Another kiosk, e.g. in another terminal, creating the same worktree blocks this one
+        let elsewhere = create(dir.path(), 1);
+        save_state_file(
+            &store.path,
+            PENDING_CREATE_STATE_VERSION,
+            std::slice::from_ref(&elsewhere),
+        )
+        .unwrap();
+        let error = store
+            .track(&entry, || anyhow::Ok(()))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("already being created"), "{error}");
+        store.finish(&elsewhere).unwrap();
+
+        let seen = store.track(&entry, || anyhow::Ok(store.load())).unwrap();
+        assert_eq!(seen, [entry]);
+        assert!(store.load().is_empty());
+    }
+
+    #[test]
+    fn test_interrupted_skips_operations_still_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let recovery = recovery(dir.path());
+        let running = create(dir.path(), 7);
+        let mut gone = create(dir.path(), 8);
+        gone.started_at_unix_secs -= 10;
+        save_state_file(
+            &recovery.creates.path,
+            PENDING_CREATE_STATE_VERSION,
+            &[running, gone.clone()],
+        )
+        .unwrap();
+        let delete = PendingWorktreeDelete {
+            pid: 9,
+            ..PendingWorktreeDelete::new(
+                PathBuf::from("/tmp/repo"),
+                "old".to_string(),
+                dir.path().join("repo-old"),
+            )
+        };
+        assert!(recovery.deletes.begin(delete.clone()).unwrap());
+        assert!(
+            recovery
+                .deletes
+                .begin(PendingWorktreeDelete::new(
+                    PathBuf::from("/tmp/repo"),
+                    "mine".to_string(),
+                    dir.path().join("repo-mine"),
+                ))
+                .unwrap()
+        );
+
+        let interrupted =
+            interrupted_among(recovery.creates.load(), recovery.deletes.load(), |pid| {
+                pid == 7
+            });
+        assert_eq!(
+            interrupted,
+            [Interrupted::Create(gone), Interrupted::Delete(delete)]
+        );
+        assert_eq!(interrupted[1].describe(), "deleting the worktree of old");
+    }
+
+    #[test]
+    fn test_rolling_a_partial_create_forward_and_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let recovery = recovery(dir.path());
+        let entry = create(dir.path(), 0);
+        let operation = Interrupted::Create(entry.clone());
+        let git = MockGitProvider {
+            branches: vec!["dev".to_string()],
+            ..Default::default()
+        };
+
+        // A directory git never registered is discarded and the worktree added again
+        fs::create_dir_all(&entry.worktree_path).unwrap();
+        recovery.roll_forward(&git, &operation, false).unwrap();
+        assert!(!entry.worktree_path.exists());
+        assert_eq!(
+            *git.prune_worktrees_calls.lock().unwrap(),
+            [entry.repo_path.as_path()]
+        );
+
+        fs::create_dir_all(&entry.worktree_path).unwrap();
+        recovery.roll_back(&git, &operation, false).unwrap();
+        assert!(!entry.worktree_path.exists());
+
+        let no_branch = MockGitProvider::default();
+        let error = recovery
+            .roll_forward(&no_branch, &operation, false)
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("Roll it back instead"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_rolling_back_a_delete_reapplies_its_stash() {
+        let dir = tempfile::tempdir().unwrap();
+        let recovery = recovery(dir.path());
+        let worktree = dir.path().join("repo-dev");
+        fs::create_dir_all(&worktree).unwrap();
+        let entry = PendingWorktreeDelete::new(
+            PathBuf::from("/tmp/repo"),
+            "dev".to_string(),
+            worktree.clone(),
+        );
+        assert!(recovery.deletes.begin(entry.clone()).unwrap());
+        recovery
+            .journal
+            .save(&[DeletedWorktree {
+                repo_path: PathBuf::from("/tmp/repo"),
+                branch_name: "dev".to_string(),
+                worktree_path: worktree.clone(),
+                head: "abc123".to_string(),
+                stash: Some("stash-sha".to_string()),
+                deleted_at_unix_secs: now_unix_secs(),
+            }])
+            .unwrap();
+        let git = MockGitProvider {
+            worktrees: vec![Worktree {
+                path: worktree.clone(),
+                branch: Some("dev".to_string()),
+                is_main: false,
+            }],
+            ..Default::default()
+        };
+
+        recovery
+            .roll_back(&git, &Interrupted::Delete(entry), false)
+            .unwrap();
+        assert_eq!(
+            *git.pop_stash_calls.lock().unwrap(),
+            [(worktree, "stash-sha".to_string())]
+        );
+        assert!(recovery.journal.load().is_empty());
+        assert!(recovery.deletes.load().is_empty());
+    }
+}
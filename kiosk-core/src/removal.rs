@@ -0,0 +1,135 @@
+use crate::{git::GitProvider, paths::state_dir};
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const QUARANTINE_DIR_NAME: &str = "trash";
+
+/// Where worktrees are moved when `clean.use_trash` is set but the system trash is unavailable.
+pub fn quarantine_dir() -> PathBuf {
+    state_dir().join(QUARANTINE_DIR_NAME)
+}
+
+/// Remove a directory, moving it to the trash instead of deleting it when `use_trash` is set.
+pub fn discard_dir(path: &Path, use_trash: bool) -> Result<()> {
+    if use_trash {
+        move_to_trash(path).map(|_| ())
+    } else {
+        fs::remove_dir_all(path).with_context(|| format!("failed to remove {}", path.display()))
+    }
+}
+
+/// Remove a worktree, honouring `use_trash`. Trashed worktrees leave stale git metadata
+/// behind, so the repository's worktree list is pruned afterwards.
+pub fn remove_worktree_dir(
+    git: &dyn GitProvider,
+    repo_path: &Path,
+    worktree_path: &Path,
+    use_trash: bool,
+) -> Result<()> {
+    if !use_trash {
+        return git.remove_worktree(worktree_path);
+    }
+    move_to_trash(worktree_path)?;
+    git.prune_worktrees(repo_path)
+}
+
+/// Move a directory to the system trash, falling back to kiosk's quarantine directory.
+/// Returns the quarantine path when the fallback was used.
+pub fn move_to_trash(path: &Path) -> Result<Option<PathBuf>> {
+    match trash::delete(path) {
+        Ok(()) => Ok(None),
+        Err(error) => {
+            tracing::warn!(
+                "System trash unavailable for {} ({error}); quarantining instead",
+                path.display()
+            );
+            quarantine_into(path, &quarantine_dir()).map(Some)
+        }
+    }
+}
+
+fn quarantine_into(path: &Path, quarantine_dir: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("cannot quarantine {}", path.display()))?;
+    fs::create_dir_all(quarantine_dir)
+        .with_context(|| format!("failed to create {}", quarantine_dir.display()))?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let base = format!("{timestamp}-{}", name.to_string_lossy());
+    let mut target = quarantine_dir.join(&base);
+    let mut attempt = 1;
+    while target.exists() {
+        target = quarantine_dir.join(format!("{base}-{attempt}"));
+        attempt += 1;
+    }
+
+    fs::rename(path, &target)
+        .with_context(|| format!("failed to move {} to {}", path.display(), target.display()))?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn quarantine_moves_directory_with_timestamped_name() {
+        let tmp = tempdir().unwrap();
+        let worktree = tmp.path().join("repo--feat");
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(worktree.join("notes.txt"), "keep me").unwrap();
+        let quarantine = tmp.path().join("trash");
+
+        let target = quarantine_into(&worktree, &quarantine).unwrap();
+
+        assert!(!worktree.exists());
+        assert!(target.starts_with(&quarantine));
+        assert!(
+            target
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .ends_with("-repo--feat")
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("notes.txt")).unwrap(),
+            "keep me"
+        );
+    }
+
+    #[test]
+    fn quarantine_does_not_overwrite_existing_entries() {
+        let tmp = tempdir().unwrap();
+        let quarantine = tmp.path().join("trash");
+        let first_dir = tmp.path().join("a").join("wt");
+        let second_dir = tmp.path().join("b").join("wt");
+        fs::create_dir_all(&first_dir).unwrap();
+        fs::create_dir_all(&second_dir).unwrap();
+
+        let first = quarantine_into(&first_dir, &quarantine).unwrap();
+        let second = quarantine_into(&second_dir, &quarantine).unwrap();
+
+        assert_ne!(first, second);
+        assert!(first.exists());
+        assert!(second.exists());
+    }
+
+    #[test]
+    fn discard_dir_without_trash_deletes() {
+        let tmp = tempdir().unwrap();
+        let dir = tmp.path().join("gone");
+        fs::create_dir_all(&dir).unwrap();
+
+        discard_dir(&dir, false).unwrap();
+
+        assert!(!dir.exists());
+    }
+}
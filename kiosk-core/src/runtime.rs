@@ -0,0 +1,159 @@
+//! Entry point for embedding kiosk with your own git or tmux backends.
+//!
+//! [`GitProvider`] and [`TmuxProvider`] are ordinary object-safe traits (not sealed), so any
+//! type outside this crate can implement them. A [`Runtime`] bundles a config with one provider
+//! of each kind; providers left unset fall back to the ones the `kiosk` binary uses.
+//!
+//! ```
+//! use std::sync::Arc;
+//! use kiosk_core::{Config, GitProvider, runtime::Runtime};
+//!
+//! fn repo_names(config: Config, git: Arc<dyn GitProvider>) -> Vec<String> {
+//!     let runtime = Runtime::builder(config).git(git).build();
+//!     runtime.run(|config, git, _tmux| {
+//!         let repos = git.discover_repos(&config.resolved_search_dirs());
+//!         repos.into_iter().map(|repo| repo.name).collect()
+//!     })
+//! }
+//! ```
+//!
+//! The TUI runs on a runtime with `kiosk_tui::run_runtime`, and each `kiosk` command is a
+//! function in `kiosk_cli` taking the config and providers, e.g.
+//! `runtime.run(|config, git, tmux| kiosk_cli::status(config, git, tmux, &args))`.
+
+use crate::{
+    audit::{AuditLog, AuditedGit, AuditedTmux},
+    config::Config,
+    git::{CliGitProvider, GitProvider},
+    tmux::{self, TmuxProvider},
+};
+use std::sync::Arc;
+
+/// A config plus the git and tmux providers every operation runs against.
+pub struct Runtime {
+    config: Config,
+    git: Arc<dyn GitProvider>,
+    tmux: Arc<dyn TmuxProvider>,
+}
+
+impl Runtime {
+    pub fn builder(config: Config) -> RuntimeBuilder {
+        RuntimeBuilder {
+            config,
+            git: None,
+            tmux: None,
+            audit: None,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn git(&self) -> &Arc<dyn GitProvider> {
+        &self.git
+    }
+
+    pub fn tmux(&self) -> &Arc<dyn TmuxProvider> {
+        &self.tmux
+    }
+
+    /// Run a single operation, such as one of `kiosk_cli`'s commands, against this runtime.
+    pub fn run<T>(&self, op: impl FnOnce(&Config, &dyn GitProvider, &dyn TmuxProvider) -> T) -> T {
+        op(&self.config, self.git.as_ref(), self.tmux.as_ref())
+    }
+}
+
+pub struct RuntimeBuilder {
+    config: Config,
+    git: Option<Arc<dyn GitProvider>>,
+    tmux: Option<Arc<dyn TmuxProvider>>,
+    audit: Option<AuditLog>,
+}
+
+impl RuntimeBuilder {
+    /// Use `git` instead of running the `git` CLI.
+    #[must_use]
+    pub fn git(mut self, git: Arc<dyn GitProvider>) -> Self {
+        self.git = Some(git);
+        self
+    }
+
+    /// Use `tmux` instead of the multiplexer picked by the config's `multiplexer` setting.
+    #[must_use]
+    pub fn tmux(mut self, tmux: Arc<dyn TmuxProvider>) -> Self {
+        self.tmux = Some(tmux);
+        self
+    }
+
+    /// Record every change the providers make, such as worktrees removed or keys sent, in
+    /// `log`.
+    #[must_use]
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Build the runtime. Default providers get the config's command timeouts and tmux
+    /// socket.
+    pub fn build(self) -> Runtime {
+        let git = self
+            .git
+            .unwrap_or_else(|| Arc::new(CliGitProvider::new(&self.config.git)));
+        let tmux = self
+            .tmux
+            .unwrap_or_else(|| tmux::provider_for(self.config.multiplexer, &self.config.tmux));
+        let (git, tmux): (Arc<dyn GitProvider>, Arc<dyn TmuxProvider>) = match self.audit {
+            Some(log) => (
+                Arc::new(AuditedGit::new(git, log.clone())),
+                Arc::new(AuditedTmux::new(tmux, log)),
+            ),
+            None => (git, tmux),
+        };
+        Runtime {
+            config: self.config,
+            git,
+            tmux,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockGitProvider, MockTmuxProvider, repo_with_branches};
+    use std::path::Path;
+
+    fn config() -> Config {
+        crate::config::load_config_from_str(r#"search_dirs = ["/tmp"]"#).unwrap()
+    }
+
+    #[test]
+    fn test_run_uses_the_supplied_providers() {
+        let runtime = Runtime::builder(config())
+            .git(Arc::new(repo_with_branches("app", "/tmp/app", 1)))
+            .tmux(Arc::new(MockTmuxProvider::default().with_session("app")))
+            .build();
+
+        let (branches, sessions) = runtime.run(|_, git, tmux| {
+            (
+                git.list_branches(Path::new("/tmp/app")),
+                tmux.list_session_names(),
+            )
+        });
+        assert_eq!(branches, ["main", "feature-1"]);
+        assert_eq!(sessions, ["app"]);
+    }
+
+    #[test]
+    fn test_providers_are_shared_with_the_caller() {
+        let tmux = Arc::new(MockTmuxProvider::default().with_session("app"));
+        let runtime = Runtime::builder(config())
+            .git(Arc::new(MockGitProvider::default()))
+            .tmux(tmux.clone())
+            .build();
+
+        runtime.run(|_, _, tmux| tmux.kill_session("app"));
+        assert_eq!(*tmux.killed_sessions.lock().unwrap(), ["app"]);
+    }
+}
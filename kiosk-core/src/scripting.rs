@@ -0,0 +1,246 @@
+//! The Rhai script in `[script]`: hooks kiosk calls on events, and functions bound to keys.
+
+use crate::{
+    agent_history::AgentActivity, config::ScriptConfig, placeholders::SessionContext,
+    process::CommandExt,
+};
+use anyhow::{Context, Result, anyhow};
+use rhai::{AST, Dynamic, Engine, EvalAltResult};
+use serde_json::{Value, json};
+use std::{cell::RefCell, fs, path::PathBuf, process::Command, rc::Rc, time::SystemTime};
+
+/// Most operations one call into a script may take, so a runaway loop fails rather than
+/// hanging kiosk.
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+thread_local! {
+    /// The script last loaded on this thread, with its file's modification time.
+    static LOADED: RefCell<Option<(PathBuf, SystemTime, Rc<Script>)>> = const { RefCell::new(None) };
+}
+
+/// Events a script can handle by defining the function named after them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// A session was created
+    Open,
+    BranchCreated,
+    AgentWaiting,
+    AgentIdle,
+}
+
+impl Hook {
+    pub fn function(self) -> &'static str {
+        match self {
+            Self::Open => "on_open",
+            Self::BranchCreated => "on_branch_created",
+            Self::AgentWaiting => "on_agent_waiting",
+            Self::AgentIdle => "on_agent_idle",
+        }
+    }
+
+    pub fn for_activity(activity: AgentActivity) -> Option<Self> {
+        match activity {
+            AgentActivity::Waiting => Some(Self::AgentWaiting),
+            AgentActivity::Idle => Some(Self::AgentIdle),
+            AgentActivity::Working => None,
+        }
+    }
+}
+
+/// A compiled script.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+impl Script {
+    /// Compile the configured script, or `None` when there isn't one. The script last loaded
+    /// on this thread is reused until its file changes.
+    pub fn load(config: &ScriptConfig) -> Result<Option<Rc<Self>>> {
+        let Some(path) = config.path() else {
+            return Ok(None);
+        };
+        let modified = fs::metadata(&path).and_then(|metadata| metadata.modified());
+        LOADED.with_borrow_mut(|loaded| {
+            if let (Some((loaded_path, loaded_modified, script)), Ok(modified)) =
+                (loaded.as_ref(), &modified)
+                && *loaded_path == path
+                && loaded_modified == modified
+            {
+                return Ok(Some(Rc::clone(script)));
+            }
+            let source = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let script =
+                Rc::new(Self::compile(&source).with_context(|| format!("in {}", path.display()))?);
+            *loaded = modified
+                .ok()
+                .map(|modified| (path, modified, Rc::clone(&script)));
+            Ok(Some(script))
+        })
+    }
+
+    pub fn compile(source: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| tracing::info!(target: "kiosk::script", "{text}"));
+        engine.on_debug(|text, _, _| tracing::debug!(target: "kiosk::script", "{text}"));
+        engine.register_fn("sh", sh);
+        let ast = engine.compile(source).map_err(|e| anyhow!("{e}"))?;
+        Ok(Self { engine, ast })
+    }
+
+    /// Whether the script defines `function` taking one argument.
+    pub fn defines(&self, function: &str) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|f| f.name == function && f.params.len() == 1)
+    }
+
+    /// Call `function` with `event`, returning the text it asks to type into the event's
+    /// session. Does nothing when the script doesn't define `function`.
+    pub fn call(&self, function: &str, event: &Value) -> Result<Option<String>> {
+        if !self.defines(function) {
+            return Ok(None);
+        }
+        let event = rhai::serde::to_dynamic(event).map_err(|e| anyhow!("{e}"))?;
+        let result: Dynamic = self
+            .engine
+            .call_fn(&mut rhai::Scope::new(), &self.ast, function, (event,))
+            .map_err(|e| anyhow!("{function}: {e}"))?;
+        text_to_send(function, result)
+    }
+}
+
+/// Call `hook` in the configured script, if it handles it. Failures are logged.
+pub fn run_hook(config: &ScriptConfig, hook: Hook, event: &Value) -> Option<String> {
+    let result = Script::load(config).and_then(|script| match script {
+        Some(script) => script.call(hook.function(), event),
+        None => Ok(None),
+    });
+    result.unwrap_or_else(|e| {
+        tracing::warn!("script hook {} failed: {e:#}", hook.function());
+        None
+    })
+}
+
+/// What scripts are told about a session.
+pub fn session_event(context: &SessionContext) -> Value {
+    json!({
+        "repo": context.repo,
+        "branch": context.branch,
+        "worktree": context.worktree,
+        "session": context.session,
+        "base": context.base,
+    })
+}
+
+fn text_to_send(function: &str, result: Dynamic) -> Result<Option<String>> {
+    if result.is_unit() {
+        return Ok(None);
+    }
+    result
+        .into_string()
+        .map(|text| (!text.is_empty()).then_some(text))
+        .map_err(|kind| anyhow!("{function} returned a {kind}, not a string"))
+}
+
+/// `sh("...")`: run a shell command, returning its stdout or failing with its stderr.
+fn sh(command: &str) -> Result<String, Box<EvalAltResult>> {
+    let output = Command::new("sh")
+        .args(["-c", command])
+        .traced_output()
+        .map_err(|e| format!("failed to run '{command}': {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("'{command}' failed: {}", stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_returns_text_for_the_session() {
+        let script = Script::compile(
+            r"
+            fn on_agent_idle(event) { `continue in ${event.repo}/${event.branch}` }
+            fn on_open(event) { print(event.session); }
+            ",
+        )
+        .unwrap();
+        let event = json!({"repo": "app", "branch": "feat", "session": "app--feat"});
+
+        assert_eq!(
+            script.call("on_agent_idle", &event).unwrap().as_deref(),
+            Some("continue in app/feat")
+        );
+        assert_eq!(script.call("on_open", &event).unwrap(), None);
+        assert_eq!(script.call("on_branch_created", &event).unwrap(), None);
+    }
+
+    #[test]
+    fn test_errors_name_the_function() {
+        let script =
+            Script::compile("fn bad(event) { 42 }\nfn boom(event) { throw \"no\" }").unwrap();
+        let bad = script.call("bad", &json!({})).unwrap_err().to_string();
+        assert!(bad.contains("bad returned a i64"), "{bad}");
+        let boom = script.call("boom", &json!({})).unwrap_err().to_string();
+        assert!(boom.starts_with("boom:"), "{boom}");
+        assert!(Script::compile("fn broken( {").is_err());
+    }
+
+    #[test]
+    fn test_sh_returns_stdout() {
+        let script =
+            Script::compile(r#"fn run(event) { let out = sh("echo hi"); out.trim(); out }"#)
+                .unwrap();
+        assert_eq!(
+            script.call("run", &json!({})).unwrap().as_deref(),
+            Some("hi")
+        );
+    }
+
+    #[test]
+    fn test_runaway_loops_fail() {
+        let script = Script::compile("fn spin(event) { loop {} }").unwrap();
+        assert!(script.call("spin", &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_load_reuses_the_script_until_its_file_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("hooks.rhai");
+        fs::write(&path, r#"fn on_open(event) { "one" }"#).unwrap();
+        let config = ScriptConfig {
+            file: Some(path.to_string_lossy().into_owned()),
+        };
+
+        let first = Script::load(&config).unwrap().unwrap();
+        let second = Script::load(&config).unwrap().unwrap();
+        assert!(Rc::ptr_eq(&first, &second));
+
+        fs::write(&path, r#"fn on_open(event) { "two" }"#).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let edited = Script::load(&config).unwrap().unwrap();
+        assert_eq!(
+            edited.call("on_open", &json!({})).unwrap().as_deref(),
+            Some("two")
+        );
+    }
+
+    #[test]
+    fn test_run_hook_without_a_script_does_nothing() {
+        assert_eq!(
+            run_hook(&ScriptConfig::default(), Hook::Open, &json!({})),
+            None
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    paths::state_dir,
+    pending_delete::now_unix_secs,
+    state_store::{load_state_file, update_state_file},
+    tmux::TmuxProvider,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const SESSION_JOURNAL_FILE_NAME: &str = "sessions.toml";
+const SESSION_JOURNAL_STATE_VERSION: u32 = 1;
+
+/// A session kiosk set up, recorded so it can be recreated once tmux has lost it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenedSession {
+    pub session: String,
+    pub repo_path: PathBuf,
+    pub worktree_path: PathBuf,
+    /// Branch checked out in the worktree, `None` for a detached HEAD
+    pub branch: Option<String>,
+    /// Command typed into the session when it was set up
+    pub run: Option<String>,
+    /// Command the session's split pane was set up with, before placeholders are filled in
+    #[serde(default)]
+    pub split_command: Option<String>,
+    /// Branch kiosk created `branch` from, kept when the session is set up again
+    #[serde(default)]
+    pub base: Option<String>,
+    pub opened_at_unix_secs: u64,
+}
+
+impl OpenedSession {
+    /// An entry for `session`, set up just now.
+    pub fn new(
+        session: String,
+        repo_path: PathBuf,
+        worktree_path: PathBuf,
+        branch: Option<String>,
+        run: Option<String>,
+    ) -> Self {
+        Self {
+            session,
+            repo_path,
+            worktree_path,
+            branch,
+            run,
+            split_command: None,
+            base: None,
+            opened_at_unix_secs: now_unix_secs(),
+        }
+    }
+}
+
+/// Sessions kiosk set up, backing `kiosk resume`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionJournal {
+    path: Option<PathBuf>,
+}
+
+impl Default for SessionJournal {
+    fn default() -> Self {
+        Self::at(state_dir().join(SESSION_JOURNAL_FILE_NAME))
+    }
+}
+
+impl SessionJournal {
+    /// Use a journal stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// A journal that never records or returns any sessions.
+    pub fn disabled() -> Self {
+        Self { path: None }
+    }
+
+    pub fn load(&self) -> Vec<OpenedSession> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        load_state_file::<OpenedSession>(path, SESSION_JOURNAL_STATE_VERSION)
+    }
+
+    fn update(&self, edit: impl FnOnce(&mut Vec<OpenedSession>)) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        update_state_file(path, SESSION_JOURNAL_STATE_VERSION, edit)
+    }
+
+    /// Record `entry`, replacing any earlier entry for its session but keeping its base.
+    pub fn record(&self, mut entry: OpenedSession) -> Result<()> {
+        self.update(|entries| {
+            if let Some(index) = entries
+                .iter()
+                .position(|existing| existing.session == entry.session)
+            {
+                let earlier = entries.remove(index);
+                entry.base = entry.base.or(earlier.base);
+            }
+            entries.push(entry);
+        })
+    }
+
+    /// Stop offering to recreate `sessions`.
+    pub fn forget(&self, sessions: &[String]) -> Result<()> {
+        self.update(|entries| entries.retain(|entry| !sessions.contains(&entry.session)))
+    }
+
+    /// Sessions that went away with an earlier server: set up before `server_started_at` (or
+    /// at any time, when no server is running) and not among `running`, oldest first.
+    pub fn lost(&self, running: &[String], server_started_at: Option<u64>) -> Vec<OpenedSession> {
+        let mut lost: Vec<OpenedSession> = self
+            .load()
+            .into_iter()
+            .filter(|entry| {
+                !running.contains(&entry.session) && !was_closed(entry, server_started_at)
+            })
+            .collect();
+        lost.sort_by_key(|entry| entry.opened_at_unix_secs);
+        lost
+    }
+
+    /// Drop sessions that were set up under the current server and have since gone, as
+    /// they were closed on purpose rather than lost.
+    pub fn forget_closed(&self, running: &[String], server_started_at: Option<u64>) -> Result<()> {
+        self.update(|entries| {
+            entries.retain(|entry| {
+                running.contains(&entry.session) || !was_closed(entry, server_started_at)
+            });
+        })
+    }
+}
+
+/// Kill `session` on purpose, forgetting it so it isn't offered to resume.
+pub fn kill_session<T: TmuxProvider + ?Sized>(tmux: &T, journal: &SessionJournal, session: &str) {
+    tmux.kill_session(session);
+    if let Err(e) = journal.forget(&[session.to_string()]) {
+        tracing::warn!("Failed to forget {session} in the session journal: {e}");
+    }
+}
+
+fn was_closed(entry: &OpenedSession, server_started_at: Option<u64>) -> bool {
+    server_started_at.is_some_and(|started| entry.opened_at_unix_secs >= started)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn opened(session: &str, opened_at_unix_secs: u64) -> OpenedSession {
+        OpenedSession {
+            session: session.to_string(),
+            repo_path: PathBuf::from("/r"),
+            worktree_path: PathBuf::from(format!("/.kiosk_worktrees/{session}")),
+            branch: Some(session.to_string()),
+            run: None,
+            split_command: None,
+            base: None,
+            opened_at_unix_secs,
+        }
+    }
+
+    #[test]
+    fn record_replaces_earlier_entry_for_the_session() {
+        let tmp = tempdir().unwrap();
+        let journal = SessionJournal::at(tmp.path().join("sessions.toml"));
+
+        journal
+            .record(OpenedSession {
+                base: Some("main".to_string()),
+                ..opened("a", 1)
+            })
+            .unwrap();
+        journal.record(opened("b", 2)).unwrap();
+        journal.record(opened("a", 3)).unwrap();
+
+        let sessions: Vec<(String, u64, Option<String>)> = journal
+            .load()
+            .into_iter()
+            .map(|entry| (entry.session, entry.opened_at_unix_secs, entry.base))
+            .collect();
+        assert_eq!(
+            sessions,
+            [
+                ("b".to_string(), 2, None),
+                ("a".to_string(), 3, Some("main".to_string()))
+            ]
+        );
+    }
+
+    #[test]
+    fn lost_offers_sessions_from_earlier_servers_and_forget_closed_drops_the_rest() {
+        let tmp = tempdir().unwrap();
+        let journal = SessionJournal::at(tmp.path().join("sessions.toml"));
+        journal.record(opened("before", 10)).unwrap();
+        journal.record(opened("running", 20)).unwrap();
+        journal.record(opened("closed", 200)).unwrap();
+        let running = ["running".to_string()];
+
+        assert_eq!(journal.lost(&running, Some(100)), [opened("before", 10)]);
+        assert_eq!(journal.load().len(), 3);
+
+        journal.forget_closed(&running, Some(100)).unwrap();
+        let remaining: Vec<String> = journal
+            .load()
+            .into_iter()
+            .map(|entry| entry.session)
+            .collect();
+        assert_eq!(remaining, ["before", "running"]);
+    }
+
+    #[test]
+    fn lost_offers_every_session_without_a_server() {
+        let tmp = tempdir().unwrap();
+        let journal = SessionJournal::at(tmp.path().join("sessions.toml"));
+        journal.record(opened("b", 200)).unwrap();
+        journal.record(opened("a", 10)).unwrap();
+
+        let lost = journal.lost(&[], None);
+
+        assert_eq!(lost, [opened("a", 10), opened("b", 200)]);
+    }
+
+    #[test]
+    fn forget_and_disabled_journal() {
+        let tmp = tempdir().unwrap();
+        let journal = SessionJournal::at(tmp.path().join("sessions.toml"));
+        journal.record(opened("a", 1)).unwrap();
+        journal.forget(&["a".to_string()]).unwrap();
+        assert!(journal.load().is_empty());
+
+        let disabled = SessionJournal::disabled();
+        disabled.record(opened("a", 1)).unwrap();
+        assert!(disabled.load().is_empty());
+    }
+}
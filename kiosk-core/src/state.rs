@@ -1,17 +1,44 @@
 use crate::{
-    config::keys::{Command, FlattenedKeybindingRow},
-    constants::{WORKTREE_DIR_DEDUP_MAX_ATTEMPTS, WORKTREE_DIR_NAME, WORKTREE_NAME_SEPARATOR},
-    git::Repo,
-    pending_delete::PendingWorktreeDelete,
+    activity_history::ActivityHistoryStore,
+    agent::AgentCache,
+    branch_name,
+    config::{
+        AgentProfile, BranchColumn, CleanConfig, CommitColumn, Config, ConfigSource, FetchConfig,
+        NewRepoConfig, ScriptConfig, SearchConfig, SessionTemplate, SortOrder, TicketConfig,
+        keys::{Command, FlattenedKeybindingRow},
+        resolve_session,
+    },
+    constants::{
+        WORKTREE_DIR_DEDUP_MAX_ATTEMPTS, WORKTREE_DIR_NAME, WORKTREE_NAME_SEPARATOR,
+        WORKTREE_NAME_UNSAFE_CHARS,
+    },
+    fetch_history::FetchHistoryStore,
+    frecency::Frecency,
+    git::{CommitInfo, GitProgress, Repo, Tag, WorktreeChanges},
+    hidden::{HiddenStore, RepoFilter},
+    instance::FetchLocks,
+    labels::LabelStore,
+    layout::{LayoutPrefs, LayoutStore},
+    notes::NoteStore,
+    pending_delete::{DeleteJournal, PendingDeleteStore, PendingWorktreeDelete},
+    pins::PinStore,
+    project::ProjectKind,
+    recovery::{Interrupted, PendingCreateStore},
+    session_journal::SessionJournal,
+    workspace::{Workspace, WorkspaceStore},
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
 };
 use unicode_segmentation::UnicodeSegmentation;
 
-/// Reusable text input with cursor, shared by `SearchableList` and `SetupState`.
+/// Reusable text input with cursor, shared by `SearchableList`, `SetupState` and `NoteEdit`.
 #[derive(Debug, Clone)]
 pub struct TextInput {
     pub text: String,
@@ -267,6 +294,15 @@ pub struct SearchableList {
     pub filtered: Vec<(usize, i64)>,
     pub selected: Option<usize>,
     pub scroll_offset: usize,
+    /// Identifies the items `filtered` indexes into, unique across lists and renewed by
+    /// [`Self::items_changed`], so results scored for other items can be told apart
+    pub items_id: u64,
+}
+
+/// The next [`SearchableList::items_id`].
+fn next_items_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
 }
 
 impl SearchableList {
@@ -276,6 +312,7 @@ impl SearchableList {
             filtered: (0..item_count).map(|i| (i, 0)).collect(),
             selected: if item_count > 0 { Some(0) } else { None },
             scroll_offset: 0,
+            items_id: next_items_id(),
         }
     }
 
@@ -284,6 +321,12 @@ impl SearchableList {
         self.filtered = (0..item_count).map(|i| (i, 0)).collect();
         self.selected = if item_count > 0 { Some(0) } else { None };
         self.scroll_offset = 0;
+        self.items_changed();
+    }
+
+    /// Note that the items this list filters were added to, removed or reordered.
+    pub fn items_changed(&mut self) {
+        self.items_id = next_items_id();
     }
 
     // ── Convenience accessors for backward compatibility ──
@@ -369,6 +412,22 @@ pub struct BranchEntry {
     pub remote: Option<String>,
     /// Last activity timestamp for the session (if any)
     pub session_activity_ts: Option<u64>,
+    /// The branch's last commit, once loaded.
+    #[serde(default)]
+    pub last_commit: Option<CommitInfo>,
+    /// The coding agent running in the branch's session, when the branch picker shows agents.
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// The note attached to this branch with `kiosk note`.
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Labels attached to this branch with `kiosk label`, sorted.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// A worktree with a detached HEAD, opened at a commit or tag rather than a branch. Its
+    /// name is the worktree's.
+    #[serde(default)]
+    pub detached: bool,
 }
 
 impl BranchEntry {
@@ -468,6 +527,11 @@ impl BranchEntry {
                     is_default,
                     remote: None,
                     session_activity_ts,
+                    last_commit: None,
+                    agent: None,
+                    note: None,
+                    labels: Vec::new(),
+                    detached: false,
                 }
             })
             .collect()
@@ -493,11 +557,148 @@ impl BranchEntry {
                 is_default: false,
                 remote: Some(remote.to_string()),
                 session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
+            })
+            .collect()
+    }
+
+    /// Build entries for the repo's linked worktrees with a detached HEAD, named after their
+    /// directory without the repo prefix (the tag or commit they were opened at).
+    #[allow(clippy::implicit_hasher)]
+    pub fn build_detached(
+        repo: &crate::git::Repo,
+        active_sessions: &[String],
+        session_activity: &HashMap<String, u64>,
+    ) -> Vec<Self> {
+        let prefix = format!("{}{WORKTREE_NAME_SEPARATOR}", repo.name);
+        repo.worktrees
+            .iter()
+            .filter(|wt| !wt.is_main && wt.branch.is_none())
+            .map(|wt| {
+                let dir_name = wt
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let session_name = repo.tmux_session_name(&wt.path);
+                Self {
+                    name: dir_name
+                        .strip_prefix(&prefix)
+                        .map_or_else(|| dir_name.clone(), str::to_string),
+                    worktree_path: Some(wt.path.clone()),
+                    has_session: active_sessions.contains(&session_name),
+                    is_current: false,
+                    is_default: false,
+                    remote: None,
+                    session_activity_ts: session_activity.get(&session_name).copied(),
+                    last_commit: None,
+                    agent: None,
+                    note: None,
+                    labels: Vec::new(),
+                    detached: true,
+                }
             })
             .collect()
     }
 
+    /// Whether this is a local branch, as opposed to a remote-only branch or a detached
+    /// worktree.
+    pub fn is_local(&self) -> bool {
+        self.remote.is_none() && !self.detached
+    }
+
+    /// Short ref name: the branch name, or `<remote>/<branch>` for a remote-only branch.
+    pub fn ref_name(&self) -> String {
+        match &self.remote {
+            Some(remote) => format!("{remote}/{}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Fill in each entry's last commit from a map keyed by [`Self::ref_name`].
+    #[allow(clippy::implicit_hasher)]
+    pub fn apply_last_commits(entries: &mut [Self], commits: &HashMap<String, CommitInfo>) {
+        for entry in entries.iter_mut().filter(|entry| !entry.detached) {
+            entry.last_commit = commits.get(&entry.ref_name()).cloned();
+        }
+    }
+
+    /// Fill in each local branch's note from a map keyed by branch name.
+    #[allow(clippy::implicit_hasher)]
+    pub fn apply_notes(entries: &mut [Self], notes: &HashMap<String, String>) {
+        for entry in entries {
+            if entry.is_local() {
+                entry.note = notes.get(&entry.name).cloned();
+            }
+        }
+    }
+
+    /// Fill in each local branch's labels from a map keyed by branch name.
+    #[allow(clippy::implicit_hasher)]
+    pub fn apply_labels(entries: &mut [Self], labels: &HashMap<String, Vec<String>>) {
+        for entry in entries {
+            if entry.is_local() {
+                entry.labels = labels.get(&entry.name).cloned().unwrap_or_default();
+            }
+        }
+    }
+
+    /// Text matched against the branch search: the name, then `#label` for each label.
+    pub fn search_text(&self) -> String {
+        let mut text = self.name.clone();
+        for label in &self.labels {
+            text.push_str(" #");
+            text.push_str(label);
+        }
+        text
+    }
+
+    /// Group already sorted entries by the first of `label_order` they carry, in that order
+    /// and ahead of unlabelled ones, keeping remote branches last and the current and default
+    /// branches first.
+    pub fn group_by_labels(entries: &mut [Self], label_order: &[String]) {
+        if label_order.is_empty() {
+            return;
+        }
+        let rank = |entry: &Self| {
+            label_order
+                .iter()
+                .position(|label| entry.labels.contains(label))
+                .unwrap_or(label_order.len())
+        };
+        entries.sort_by(|a, b| {
+            a.remote
+                .is_some()
+                .cmp(&b.remote.is_some())
+                .then(b.is_current.cmp(&a.is_current))
+                .then(b.is_default.cmp(&a.is_default))
+                .then_with(|| rank(a).cmp(&rank(b)))
+        });
+    }
+
     pub fn sort_entries(entries: &mut [Self]) {
+        Self::sort_entries_by(entries, SortOrder::Activity, &HashMap::new());
+    }
+
+    /// Sort with the configured order. `frecency` maps local branch names to their score.
+    #[allow(clippy::implicit_hasher)]
+    pub fn sort_entries_by(
+        entries: &mut [Self],
+        order: SortOrder,
+        frecency: &HashMap<String, u64>,
+    ) {
+        let score = |entry: &Self| {
+            entry
+                .remote
+                .is_none()
+                .then(|| frecency.get(&entry.name).copied())
+                .flatten()
+                .unwrap_or(0)
+        };
         entries.sort_by(|a, b| {
             // Remote branches always sort after local
             a.remote
@@ -507,18 +708,25 @@ impl BranchEntry {
                 .then(b.is_current.cmp(&a.is_current))
                 // Default branch second
                 .then(b.is_default.cmp(&a.is_default))
-                // Branches with sessions, ordered by recency (most recent first)
-                .then(cmp_optional_recency(
-                    a.session_activity_ts,
-                    b.session_activity_ts,
-                ))
-                // Branches with sessions (even without activity timestamps) before those without
-                .then(b.has_session.cmp(&a.has_session))
-                // Branches with worktrees before those without
-                .then(b.worktree_path.is_some().cmp(&a.worktree_path.is_some()))
+                .then_with(|| match order {
+                    SortOrder::Frecency => score(b)
+                        .cmp(&score(a))
+                        .then_with(|| Self::cmp_activity(a, b)),
+                    SortOrder::Activity => Self::cmp_activity(a, b),
+                    SortOrder::Alphabetical => std::cmp::Ordering::Equal,
+                })
                 .then(a.name.cmp(&b.name))
         });
     }
+
+    fn cmp_activity(a: &Self, b: &Self) -> std::cmp::Ordering {
+        // Branches with sessions, ordered by recency (most recent first)
+        cmp_optional_recency(a.session_activity_ts, b.session_activity_ts)
+            // Branches with sessions (even without activity timestamps) before those without
+            .then(b.has_session.cmp(&a.has_session))
+            // Branches with worktrees before those without
+            .then(b.worktree_path.is_some().cmp(&a.worktree_path.is_some()))
+    }
 }
 
 /// Compare two optional timestamps for recency-based sorting (most recent first).
@@ -538,6 +746,28 @@ pub fn sort_repos(
     repos: &mut [Repo],
     current_repo_path: Option<&Path>,
     session_activity: &HashMap<String, u64>,
+) {
+    sort_repos_by(
+        repos,
+        current_repo_path,
+        SortOrder::Activity,
+        session_activity,
+        &HashMap::new(),
+        &HashSet::new(),
+    );
+}
+
+/// Sort repos with the configured order, keeping the current repo first and pinned repos
+/// next. `frecency` maps repo paths to their score; repos with equal scores fall back to
+/// session activity.
+#[allow(clippy::implicit_hasher)]
+pub fn sort_repos_by(
+    repos: &mut [Repo],
+    current_repo_path: Option<&Path>,
+    order: SortOrder,
+    session_activity: &HashMap<String, u64>,
+    frecency: &HashMap<PathBuf, u64>,
+    pinned: &HashSet<PathBuf>,
 ) {
     let current_repo_path = current_repo_path
         .and_then(|path| std::fs::canonicalize(path).ok())
@@ -547,37 +777,92 @@ pub fn sort_repos(
         let canonical = std::fs::canonicalize(&repo.path).unwrap_or_else(|_| repo.path.clone());
         canonical_by_path.insert(repo.path.clone(), canonical);
     }
+    let cmp_activity = |a: &Repo, b: &Repo| {
+        let a_activity = repo_max_activity(a, session_activity);
+        let b_activity = repo_max_activity(b, session_activity);
+        cmp_optional_recency(a_activity, b_activity)
+    };
     repos.sort_by(|a, b| {
         let a_path = canonical_by_path.get(&a.path).unwrap_or(&a.path);
         let b_path = canonical_by_path.get(&b.path).unwrap_or(&b.path);
         let a_is_current = current_repo_path.as_ref().is_some_and(|p| a_path == p);
         let b_is_current = current_repo_path.as_ref().is_some_and(|p| b_path == p);
 
-        // Current repo first
+        let a_is_pinned = pinned.contains(a_path) || pinned.contains(&a.path);
+        let b_is_pinned = pinned.contains(b_path) || pinned.contains(&b.path);
+
+        // Current repo first, then pinned repos
         b_is_current
             .cmp(&a_is_current)
-            .then_with(|| {
-                let a_activity = repo_max_activity(a, session_activity);
-                let b_activity = repo_max_activity(b, session_activity);
-                cmp_optional_recency(a_activity, b_activity)
+            .then(b_is_pinned.cmp(&a_is_pinned))
+            .then_with(|| match order {
+                SortOrder::Frecency => {
+                    let score = |repo: &Repo, canonical: &PathBuf| {
+                        frecency
+                            .get(canonical)
+                            .or_else(|| frecency.get(&repo.path))
+                            .copied()
+                            .unwrap_or(0)
+                    };
+                    let a_score = score(a, a_path);
+                    let b_score = score(b, b_path);
+                    b_score.cmp(&a_score).then_with(|| cmp_activity(a, b))
+                }
+                SortOrder::Activity => cmp_activity(a, b),
+                SortOrder::Alphabetical => std::cmp::Ordering::Equal,
             })
             .then_with(|| a.name.cmp(&b.name))
     });
 }
 
 /// Get the most recent session activity for a repo (across all its worktrees).
-fn repo_max_activity(repo: &Repo, session_activity: &HashMap<String, u64>) -> Option<u64> {
-    let main_session = std::iter::once(repo.tmux_session_name(&repo.path));
-    let wt_sessions = repo
-        .worktrees
-        .iter()
-        .map(|wt| repo.tmux_session_name(&wt.path));
-    main_session
-        .chain(wt_sessions)
+#[allow(clippy::implicit_hasher)]
+pub fn repo_max_activity(repo: &Repo, session_activity: &HashMap<String, u64>) -> Option<u64> {
+    repo_session_names(repo)
         .filter_map(|name| session_activity.get(&name).copied())
         .max()
 }
 
+/// Recent activity of `session`, oldest first: its `history` followed by its current
+/// `session_activity` when that's newer.
+#[allow(clippy::implicit_hasher)]
+pub fn session_activity_history(
+    session: &str,
+    session_activity: &HashMap<String, u64>,
+    history: &HashMap<String, Vec<u64>>,
+) -> Vec<u64> {
+    let mut activity = history.get(session).cloned().unwrap_or_default();
+    if let Some(&current) = session_activity.get(session)
+        && activity.last() < Some(&current)
+    {
+        activity.push(current);
+    }
+    activity
+}
+
+/// Recent activity across all of a repo's sessions, oldest first.
+#[allow(clippy::implicit_hasher)]
+pub fn repo_activity_history(
+    repo: &Repo,
+    session_activity: &HashMap<String, u64>,
+    history: &HashMap<String, Vec<u64>>,
+) -> Vec<u64> {
+    let mut activity: Vec<u64> = repo_session_names(repo)
+        .flat_map(|name| session_activity_history(&name, session_activity, history))
+        .collect();
+    activity.sort_unstable();
+    activity
+}
+
+/// The sessions of a repo's main checkout and its worktrees.
+fn repo_session_names(repo: &Repo) -> impl Iterator<Item = String> + '_ {
+    std::iter::once(repo.tmux_session_name(&repo.path)).chain(
+        repo.worktrees
+            .iter()
+            .map(|wt| repo.tmux_session_name(&wt.path)),
+    )
+}
+
 /// Setup wizard step
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SetupStep {
@@ -617,12 +902,28 @@ impl Default for SetupState {
     }
 }
 
+/// What `kiosk pick` lets the user choose. The picked path is printed instead of opening a
+/// session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickScope {
+    /// A repo (its primary checkout) or a branch worktree
+    Any,
+    /// Only repos: the branch view and global search are unavailable
+    Repos,
+    /// Only branch worktrees: Enter on a repo opens its branch view
+    Branches,
+}
+
 /// What mode the app is in
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Mode {
     RepoSelect,
     BranchSelect,
     SelectBaseBranch,
+    /// Picking the search dir to clone a repo into
+    SelectCloneDir,
+    /// Picking the template to create a new repo from
+    NewRepo,
     /// Blocking loading state — shows spinner, no input except Ctrl+C
     Loading(String),
     /// Confirmation dialog for worktree deletion
@@ -630,19 +931,41 @@ pub enum Mode {
         branch_name: String,
         has_session: bool,
     },
+    /// Typing the note for a branch, over the branch picker
+    EditNote,
+    /// Typing the fields of `branch.name_template` for a new branch, over the branch picker
+    BranchNameFields,
+    /// Offer to recreate the sessions lost when the tmux server last went away
+    ConfirmResume {
+        sessions: Vec<String>,
+    },
+    /// Offer to finish or undo the worktree operations a crashed kiosk left half-done
+    ConfirmRecover {
+        operations: Vec<Interrupted>,
+    },
     /// Help overlay showing key bindings
     Help {
         previous: Box<Mode>,
     },
+    /// The help overlay's rows as a palette: picking one runs its command in `previous`
+    CommandPalette {
+        previous: Box<Mode>,
+    },
     /// Setup wizard for first-time config
     Setup(SetupStep),
+    /// Fuzzy search across the worktrees of every repo
+    GlobalSearch,
+    /// Picking one of the selected repo's tags to open in a detached worktree
+    TagSelect,
+    /// Picking a workspace to open, or to add the selected branch to
+    WorkspaceSelect,
 }
 
 impl Mode {
     /// The effective mode, looking through overlays like Help.
     pub fn effective(&self) -> &Mode {
         match self {
-            Mode::Help { previous } => previous.effective(),
+            Mode::Help { previous } | Mode::CommandPalette { previous } => previous.effective(),
             other => other,
         }
     }
@@ -663,19 +986,36 @@ impl Mode {
                 Command::ShowHelp,
                 Command::Quit,
             ],
-            Mode::SelectBaseBranch => &[
+            Mode::SelectBaseBranch
+            | Mode::SelectCloneDir
+            | Mode::NewRepo
+            | Mode::GlobalSearch
+            | Mode::TagSelect
+            | Mode::WorkspaceSelect
+            | Mode::EditNote
+            | Mode::BranchNameFields => &[
                 Command::Cancel,
                 Command::Confirm,
                 Command::ShowHelp,
                 Command::Quit,
             ],
-            Mode::ConfirmWorktreeDelete { .. } => &[
+            Mode::ConfirmWorktreeDelete { .. } | Mode::ConfirmResume { .. } => &[
+                Command::Confirm,
+                Command::Cancel,
+                Command::ShowHelp,
+                Command::Quit,
+            ],
+            Mode::ConfirmRecover { .. } => &[
                 Command::Confirm,
+                Command::TabComplete,
                 Command::Cancel,
                 Command::ShowHelp,
                 Command::Quit,
             ],
-            Mode::Setup(_) | Mode::Loading(_) | Mode::Help { .. } => &[],
+
+            Mode::Setup(_) | Mode::Loading(_) | Mode::Help { .. } | Mode::CommandPalette { .. } => {
+                &[]
+            }
         }
     }
 
@@ -685,7 +1025,15 @@ impl Mode {
             Mode::RepoSelect
                 | Mode::BranchSelect
                 | Mode::SelectBaseBranch
+                | Mode::SelectCloneDir
+                | Mode::NewRepo
+                | Mode::GlobalSearch
+                | Mode::TagSelect
+                | Mode::WorkspaceSelect
+                | Mode::EditNote
+                | Mode::BranchNameFields
                 | Mode::Help { .. }
+                | Mode::CommandPalette { .. }
                 | Mode::Setup(SetupStep::SearchDirs)
         )
     }
@@ -696,7 +1044,13 @@ impl Mode {
             Mode::RepoSelect
                 | Mode::BranchSelect
                 | Mode::SelectBaseBranch
+                | Mode::SelectCloneDir
+                | Mode::NewRepo
+                | Mode::GlobalSearch
+                | Mode::TagSelect
+                | Mode::WorkspaceSelect
                 | Mode::Help { .. }
+                | Mode::CommandPalette { .. }
                 | Mode::Setup(SetupStep::SearchDirs)
         )
     }
@@ -704,7 +1058,19 @@ impl Mode {
     pub(crate) fn supports_modal_actions(&self) -> bool {
         matches!(
             self,
-            Mode::SelectBaseBranch | Mode::ConfirmWorktreeDelete { .. } | Mode::Setup(_)
+            Mode::SelectBaseBranch
+                | Mode::SelectCloneDir
+                | Mode::NewRepo
+                | Mode::GlobalSearch
+                | Mode::TagSelect
+                | Mode::WorkspaceSelect
+                | Mode::EditNote
+                | Mode::BranchNameFields
+                | Mode::CommandPalette { .. }
+                | Mode::ConfirmWorktreeDelete { .. }
+                | Mode::ConfirmResume { .. }
+                | Mode::ConfirmRecover { .. }
+                | Mode::Setup(_)
         )
     }
 
@@ -717,6 +1083,86 @@ impl Mode {
     }
 }
 
+/// The note being typed in [`Mode::EditNote`]
+#[derive(Debug, Clone)]
+pub struct NoteEdit {
+    pub branch: String,
+    pub input: TextInput,
+}
+
+/// The fields of `branch.name_template` being typed in [`Mode::BranchNameFields`], one at a
+/// time
+#[derive(Debug, Clone)]
+pub struct BranchNameFields {
+    pub template: String,
+    /// What was typed in the branch list, which fills `{slug}`
+    pub text: String,
+    /// The fields typed so far, with their values
+    pub values: Vec<(String, String)>,
+    /// The fields still to type, starting with the one in `input`
+    pub remaining: Vec<String>,
+    pub input: TextInput,
+    /// Note to save on the new branch, like the link to its ticket
+    pub note: Option<String>,
+}
+
+impl BranchNameFields {
+    /// Ask for the fields of `template` that `values` doesn't already fill.
+    pub fn new(template: String, text: String, values: Vec<(String, String)>) -> Self {
+        let remaining = branch_name::template_fields(&template)
+            .into_iter()
+            .filter(|field| !values.iter().any(|(filled, _)| filled == field))
+            .collect();
+        let mut fields = Self {
+            template,
+            text,
+            values,
+            remaining,
+            input: TextInput::new(),
+            note: None,
+        };
+        fields.prefill();
+        fields
+    }
+
+    /// The field being typed.
+    pub fn current(&self) -> Option<&str> {
+        self.remaining.first().map(String::as_str)
+    }
+
+    /// The branch name with the fields typed so far, `input` for the current one, and the
+    /// rest left as placeholders.
+    pub fn name(&self) -> String {
+        let mut values = self.values.clone();
+        if let Some(field) = self.current() {
+            values.push((field.to_string(), self.input.text.clone()));
+        }
+        branch_name::from_template(&self.template, &self.text, &values)
+    }
+
+    /// Keep `input` as the current field's value and move on to the next field. Returns
+    /// whether any fields are left.
+    pub fn advance(&mut self) -> bool {
+        if self.remaining.is_empty() {
+            return false;
+        }
+        let field = self.remaining.remove(0);
+        let value = std::mem::take(&mut self.input.text);
+        self.values.push((field, value));
+        self.prefill();
+        !self.remaining.is_empty()
+    }
+
+    fn prefill(&mut self) {
+        let value = self
+            .current()
+            .and_then(branch_name::default_value)
+            .unwrap_or_default();
+        self.input.cursor = value.len();
+        self.input.text = value;
+    }
+}
+
 /// The new-branch flow state
 #[derive(Debug, Clone)]
 pub struct BaseBranchSelection {
@@ -725,6 +1171,107 @@ pub struct BaseBranchSelection {
     /// Base branches to pick from
     pub bases: Vec<String>,
     pub list: SearchableList,
+    /// Note to save on the new branch once it's created, like the link to its ticket
+    pub note: Option<String>,
+}
+
+/// The clone flow's state while a search dir is picked
+#[derive(Debug, Clone)]
+pub struct CloneDirSelection {
+    /// The URL to clone (what the user typed)
+    pub url: String,
+    /// Name of the directory the clone creates
+    pub name: String,
+    /// Search dirs to clone into
+    pub dirs: Vec<PathBuf>,
+    pub list: SearchableList,
+}
+
+/// The new-repo flow's state while a template is picked
+#[derive(Debug, Clone)]
+pub struct NewRepoSelection {
+    /// The new repo's name (what the user typed)
+    pub name: String,
+    /// Template names to pick from
+    pub templates: Vec<String>,
+    pub list: SearchableList,
+}
+
+/// A worktree offered by global search, labelled `repo/branch`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalSearchEntry {
+    pub repo_name: String,
+    pub branch: String,
+    pub path: PathBuf,
+    pub session_name: String,
+    pub has_session: bool,
+}
+
+impl GlobalSearchEntry {
+    /// Text matched against the search, e.g. `kiosk/feat/search`
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.repo_name, self.branch)
+    }
+}
+
+/// The global search state
+#[derive(Debug, Clone)]
+pub struct GlobalSearchState {
+    pub entries: Vec<GlobalSearchEntry>,
+    pub list: SearchableList,
+}
+
+/// A tag offered in [`Mode::TagSelect`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagEntry {
+    pub tag: Tag,
+    /// A worktree already detached at the tag's commit, if there is one
+    pub worktree_path: Option<PathBuf>,
+}
+
+/// The tag picker's state
+#[derive(Debug, Clone)]
+pub struct TagSelectState {
+    pub tags: Vec<TagEntry>,
+    pub list: SearchableList,
+    /// Whether the tags are still being listed
+    pub loading: bool,
+}
+
+/// The workspace picker's state
+#[derive(Debug, Clone)]
+pub struct WorkspaceSelectState {
+    pub workspaces: Vec<Workspace>,
+    pub list: SearchableList,
+    /// The branch being added to the picked workspace (as `repo/branch`), or `None` when
+    /// picking one to open
+    pub adding: Option<String>,
+}
+
+/// What deleting the worktree in `Mode::ConfirmWorktreeDelete` would throw away
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletePreview {
+    pub worktree_path: PathBuf,
+    /// `None` until loaded in the background
+    pub changes: Option<Result<WorktreeChanges, String>>,
+    /// Set by the first confirm on a worktree that isn't known to be clean; the next one deletes
+    pub armed: bool,
+}
+
+impl DeletePreview {
+    pub fn new(worktree_path: PathBuf) -> Self {
+        Self {
+            worktree_path,
+            changes: None,
+            armed: false,
+        }
+    }
+
+    /// Whether confirming should only arm the delete: the worktree has work of its own, or
+    /// it's not known yet whether it does.
+    pub fn needs_second_confirm(&self) -> bool {
+        !self.armed && !matches!(&self.changes, Some(Ok(changes)) if !changes.is_dirty())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -733,8 +1280,18 @@ pub struct HelpOverlayState {
     pub rows: Vec<FlattenedKeybindingRow>,
 }
 
+/// A git operation running behind [`Mode::Loading`] that the user can cancel.
+#[derive(Debug, Clone)]
+pub struct LoadingOperation {
+    /// The mode the operation was started from, restored when it's cancelled.
+    pub return_mode: Mode,
+    /// Set on cancelling, to stop the operation's git commands.
+    pub cancelled: Arc<AtomicBool>,
+}
+
 /// Central application state. Components read from this, actions modify it.
 #[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct AppState {
     pub repos: Vec<Repo>,
     pub repo_list: SearchableList,
@@ -745,17 +1302,102 @@ pub struct AppState {
     pub branch_list: SearchableList,
 
     pub base_branch_selection: Option<BaseBranchSelection>,
+    pub clone_dir_selection: Option<CloneDirSelection>,
+    pub new_repo_selection: Option<NewRepoSelection>,
+    pub global_search: Option<GlobalSearchState>,
+    pub tag_select: Option<TagSelectState>,
+    pub workspace_select: Option<WorkspaceSelectState>,
     pub help_overlay: Option<HelpOverlayState>,
     pub setup: Option<SetupState>,
+    /// Changes in the worktree the delete dialog is asking about
+    pub delete_preview: Option<DeletePreview>,
+    pub note_edit: Option<NoteEdit>,
+    pub branch_name_fields: Option<BranchNameFields>,
+    /// Template for new branch names (`branch.name_template` in the config).
+    pub branch_name_template: Option<String>,
+    /// Where ticket IDs typed as new branch names are looked up (`[ticket]` in the config).
+    pub ticket: TicketConfig,
+    /// The rest of a chained key binding, waiting for branches to finish loading
+    pub pending_commands: Vec<Command>,
+    /// Count typed with alt and digits, applied to the next movement
+    pub pending_count: Option<usize>,
 
     pub split_command: Option<String>,
+    /// Per-project-kind session settings (`session.templates` in the config).
+    pub session_templates: BTreeMap<ProjectKind, SessionTemplate>,
+    /// Editor for `open_in_editor` (`editor` in the config).
+    pub editor: Option<String>,
+    /// Agent for `open_with_agent` (`default_agent`, or the only one in `[agents]`).
+    pub agent: Option<AgentProfile>,
+    /// Whether the session being opened starts `agent`, as it does after `open_with_agent`.
+    pub launch_agent: bool,
+    /// Repos whose sessions aren't checked for agents (`agent.skip_repos` in the config).
+    pub skip_agent_repos: Vec<String>,
+    /// Agents found in sessions' panes, reused for `agent.cache_secs` (in the config).
+    pub agent_cache: Arc<AgentCache>,
+    /// How list searches match items (`search` in the config).
+    pub search: SearchConfig,
+    /// Script whose functions keys can run (`[script]` in the config).
+    pub script: ScriptConfig,
+    /// Workspaces from the config (`workspaces`), shown alongside `workspace_store`'s.
+    pub workspaces: BTreeMap<String, Vec<String>>,
+    pub workspace_store: WorkspaceStore,
+    /// Set when running as `kiosk pick`.
+    pub pick: Option<PickScope>,
     pub mode: Mode,
     pub loading_branches: bool,
     pub fetching_remotes: bool,
+    /// Latest progress of the git operation behind [`Mode::Loading`].
+    pub loading_progress: Option<GitProgress>,
+    /// The git operation behind [`Mode::Loading`], if the user can cancel it.
+    pub loading_operation: Option<LoadingOperation>,
+    /// Latest progress of the background fetch while `fetching_remotes` is set.
+    pub fetch_progress: Option<GitProgress>,
+    /// When and which remotes are fetched (`fetch` in the config).
+    pub fetch: FetchConfig,
+    pub fetch_history: FetchHistoryStore,
+    /// Locks on fetching each repo, so two kiosks don't fetch the same one at once
+    pub fetch_locks: FetchLocks,
     pub error: Option<String>,
     active_list_page_rows: usize,
     pub pending_worktree_deletes: Vec<PendingWorktreeDelete>,
+    pub delete_journal: DeleteJournal,
+    pub pending_delete_store: PendingDeleteStore,
+    pub pending_create_store: PendingCreateStore,
+    /// Sessions kiosk set up, forgotten here when the user declines to resume them
+    pub session_journal: SessionJournal,
+    /// Branch notes, shown in the branch picker
+    pub note_store: NoteStore,
+    /// Branch labels, searched as `#label` in the branch picker
+    pub label_store: LabelStore,
+    /// Labels that group branches, in order (`label_order` in the config).
+    pub label_order: Vec<String>,
+    /// Worktree removal settings (e.g. `clean.use_trash`).
+    pub clean: CleanConfig,
+    /// Order for the repo and branch lists (`sort` in the config).
+    pub sort: SortOrder,
+    /// Whether the TUI captures the mouse (`mouse` in the config).
+    pub mouse: bool,
+    /// The preview pane's visibility and width, saved between runs in `layout_store`
+    pub layout: LayoutPrefs,
+    pub layout_store: LayoutStore,
+    /// Columns of the branch picker (`branch_columns` and `commit_columns` in the config).
+    pub branch_columns: Vec<BranchColumn>,
+    /// Last commit of each branch in the selected repo, keyed by short ref name. Loaded in
+    /// the background after the branches.
+    pub branch_commits: HashMap<String, CommitInfo>,
+    /// Open history used when `sort` is `frecency`.
+    pub frecency: Frecency,
+    pub pin_store: PinStore,
+    /// Pinned repo paths, sorted to the top of the repo list.
+    pub pinned: HashSet<PathBuf>,
+    pub hidden_store: HiddenStore,
+    /// Repos kept out of the repo list (`ignore` in the config, or hidden from the TUI).
+    pub repo_filter: RepoFilter,
     pub session_activity: HashMap<String, u64>,
+    pub activity_store: ActivityHistoryStore,
+    /// Earlier activity of each session, oldest first, for the activity heat
+    pub activity_history: HashMap<String, Vec<u64>>,
     /// Main repo root path from CWD (for repo ordering)
     pub current_repo_path: Option<PathBuf>,
     /// CWD resolved to repo/worktree root (for branch current detection)
@@ -763,6 +1405,12 @@ pub struct AppState {
     /// Tracks repo paths already seen during streaming discovery (O(1) dedup).
     /// Cleared when a new scan starts.
     pub seen_repo_paths: HashSet<PathBuf>,
+    /// Config watched for changes while the TUI runs; `None` disables live reload.
+    pub config_source: Option<ConfigSource>,
+    /// Directories scanned for repos, which new clones go into.
+    pub search_dirs: Vec<(PathBuf, u16)>,
+    /// Where new repos go and their templates (`new_repo` in the config).
+    pub new_repo: NewRepoConfig,
 }
 
 impl AppState {
@@ -775,22 +1423,109 @@ impl AppState {
             branches: Vec::new(),
             branch_list: SearchableList::new(0),
             base_branch_selection: None,
+            clone_dir_selection: None,
+            new_repo_selection: None,
+            global_search: None,
+            tag_select: None,
+            workspace_select: None,
             help_overlay: None,
             setup: None,
+            delete_preview: None,
+            note_edit: None,
+            branch_name_fields: None,
+            branch_name_template: None,
+            ticket: TicketConfig::default(),
+            pending_commands: Vec::new(),
+            pending_count: None,
             split_command: None,
+            session_templates: BTreeMap::new(),
+            editor: None,
+            agent: None,
+            launch_agent: false,
+            skip_agent_repos: Vec::new(),
+            agent_cache: Arc::default(),
+            search: SearchConfig::default(),
+            script: ScriptConfig::default(),
+            workspaces: BTreeMap::new(),
+            workspace_store: WorkspaceStore::default(),
+            pick: None,
             mode,
             loading_branches: false,
             fetching_remotes: false,
+            loading_progress: None,
+            loading_operation: None,
+            fetch_progress: None,
+            fetch: FetchConfig::default(),
+            fetch_history: FetchHistoryStore::default(),
+            fetch_locks: FetchLocks::default(),
             error: None,
             active_list_page_rows: 10,
             pending_worktree_deletes: Vec::new(),
+            delete_journal: DeleteJournal::default(),
+            pending_delete_store: PendingDeleteStore::default(),
+            pending_create_store: PendingCreateStore::default(),
+            session_journal: SessionJournal::default(),
+            note_store: NoteStore::default(),
+            label_store: LabelStore::default(),
+            label_order: Vec::new(),
+            clean: CleanConfig::default(),
+            sort: SortOrder::default(),
+            mouse: false,
+            layout: LayoutPrefs::default(),
+            layout_store: LayoutStore::default(),
+            branch_columns: BranchColumn::LEADING
+                .into_iter()
+                .chain(CommitColumn::ALL.map(BranchColumn::from))
+                .collect(),
+            branch_commits: HashMap::new(),
+            frecency: Frecency::default(),
+            pin_store: PinStore::default(),
+            pinned: HashSet::new(),
+            hidden_store: HiddenStore::default(),
+            repo_filter: RepoFilter::default(),
             session_activity: HashMap::new(),
+            activity_store: ActivityHistoryStore::default(),
+            activity_history: HashMap::new(),
             current_repo_path: None,
             cwd_worktree_path: None,
             seen_repo_paths: HashSet::new(),
+            config_source: None,
+            search_dirs: Vec::new(),
+            new_repo: NewRepoConfig::default(),
         }
     }
 
+    /// Settings for a new session in `dir`, from its project kind's template.
+    pub fn session_for(&self, dir: &Path) -> SessionTemplate {
+        resolve_session(self.split_command.as_deref(), &self.session_templates, dir)
+    }
+
+    /// Take the TUI's settings from `config`, when it starts and whenever the config is
+    /// reloaded. Invalid `ignore` patterns are the error, with everything else applied.
+    pub fn apply_config(&mut self, config: &Config) -> anyhow::Result<()> {
+        self.split_command.clone_from(&config.session.split_command);
+        self.session_templates.clone_from(&config.session.templates);
+        self.branch_columns = config.branch_columns();
+        self.agent = config.default_agent().cloned();
+        self.skip_agent_repos.clone_from(&config.agent.skip_repos);
+        self.agent_cache = Arc::new(AgentCache::new(config.agent.cache_secs));
+        self.search = config.search;
+        self.script.clone_from(&config.script);
+        self.workspaces.clone_from(&config.workspaces);
+        self.sort = config.sort;
+        self.mouse = config.mouse;
+        self.label_order.clone_from(&config.label_order);
+        self.clean.clone_from(&config.clean);
+        self.branch_name_template
+            .clone_from(&config.branch.name_template);
+        self.ticket.clone_from(&config.ticket);
+        self.editor.clone_from(&config.editor);
+        self.fetch.clone_from(&config.fetch);
+        self.new_repo.clone_from(&config.new_repo);
+        self.repo_filter = RepoFilter::new(&config.ignore, self.repo_filter.hidden.clone())?;
+        Ok(())
+    }
+
     pub fn new(repos: Vec<Repo>, split_command: Option<String>) -> Self {
         let repo_list = SearchableList::new(repos.len());
         let seen_repo_paths: HashSet<PathBuf> = repos.iter().map(|r| r.path.clone()).collect();
@@ -811,6 +1546,39 @@ impl AppState {
         }
     }
 
+    /// Whether a discovered repo should be left out of the repo list. The repo kiosk was
+    /// launched from is always shown.
+    pub fn is_repo_hidden(&self, repo_path: &Path) -> bool {
+        self.current_repo_path.as_deref() != Some(repo_path)
+            && self.repo_filter.is_hidden(repo_path)
+    }
+
+    /// Show the loading dialog with `message` while a background git operation runs. The
+    /// returned flag is set if the user cancels the operation.
+    pub fn start_loading(&mut self, message: String) -> Arc<AtomicBool> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.loading_operation = Some(LoadingOperation {
+            return_mode: std::mem::replace(&mut self.mode, Mode::Loading(message)),
+            cancelled: Arc::clone(&cancelled),
+        });
+        self.loading_progress = None;
+        cancelled
+    }
+
+    /// Cancel the git operation behind the loading dialog and go back to where it was
+    /// started. Returns the dialog's message, or `None` if nothing cancellable is loading.
+    pub fn cancel_loading(&mut self) -> Option<String> {
+        let Mode::Loading(message) = &self.mode else {
+            return None;
+        };
+        let message = message.clone();
+        let operation = self.loading_operation.take()?;
+        operation.cancelled.store(true, Ordering::Relaxed);
+        self.mode = operation.return_mode;
+        self.loading_progress = None;
+        Some(message)
+    }
+
     pub fn set_error(&mut self, msg: &str) {
         // Collapse newlines/whitespace runs into single spaces so multi-line
         // stderr output (e.g. from git) renders cleanly in the error toast.
@@ -833,6 +1601,8 @@ impl AppState {
     pub fn active_text_input(&mut self) -> Option<&mut TextInput> {
         match self.mode {
             Mode::Setup(SetupStep::SearchDirs) => self.setup.as_mut().map(|s| &mut s.input),
+            Mode::EditNote => self.note_edit.as_mut().map(|n| &mut n.input),
+            Mode::BranchNameFields => self.branch_name_fields.as_mut().map(|b| &mut b.input),
             _ => self.active_list_mut().map(|list| &mut list.input),
         }
     }
@@ -843,7 +1613,12 @@ impl AppState {
             Mode::RepoSelect => Some(&mut self.repo_list),
             Mode::BranchSelect => Some(&mut self.branch_list),
             Mode::SelectBaseBranch => self.base_branch_selection.as_mut().map(|f| &mut f.list),
-            Mode::Help { .. } => self.active_help_list_mut(),
+            Mode::SelectCloneDir => self.clone_dir_selection.as_mut().map(|f| &mut f.list),
+            Mode::NewRepo => self.new_repo_selection.as_mut().map(|f| &mut f.list),
+            Mode::GlobalSearch => self.global_search.as_mut().map(|g| &mut g.list),
+            Mode::TagSelect => self.tag_select.as_mut().map(|t| &mut t.list),
+            Mode::WorkspaceSelect => self.workspace_select.as_mut().map(|w| &mut w.list),
+            Mode::Help { .. } | Mode::CommandPalette { .. } => self.active_help_list_mut(),
             _ => None,
         }
     }
@@ -854,11 +1629,45 @@ impl AppState {
             Mode::RepoSelect => Some(&self.repo_list),
             Mode::BranchSelect => Some(&self.branch_list),
             Mode::SelectBaseBranch => self.base_branch_selection.as_ref().map(|f| &f.list),
-            Mode::Help { .. } => self.active_help_list(),
+            Mode::SelectCloneDir => self.clone_dir_selection.as_ref().map(|f| &f.list),
+            Mode::NewRepo => self.new_repo_selection.as_ref().map(|f| &f.list),
+            Mode::GlobalSearch => self.global_search.as_ref().map(|g| &g.list),
+            Mode::TagSelect => self.tag_select.as_ref().map(|t| &t.list),
+            Mode::WorkspaceSelect => self.workspace_select.as_ref().map(|w| &w.list),
+            Mode::Help { .. } | Mode::CommandPalette { .. } => self.active_help_list(),
             _ => None,
         }
     }
 
+    /// Every known worktree with a branch, across all repos: worktrees with the most recently
+    /// active sessions first, then the rest in repo list order.
+    pub fn global_search_entries(&self) -> Vec<GlobalSearchEntry> {
+        let mut entries: Vec<(GlobalSearchEntry, Option<u64>)> = self
+            .repos
+            .iter()
+            .flat_map(|repo| {
+                repo.worktrees.iter().filter_map(move |wt| {
+                    let branch = wt.branch.clone()?;
+                    let session_name = repo.tmux_session_name(&wt.path);
+                    let activity = self.session_activity.get(&session_name).copied();
+                    Some((
+                        GlobalSearchEntry {
+                            repo_name: repo.name.clone(),
+                            branch,
+                            path: wt.path.clone(),
+                            has_session: activity.is_some(),
+                            session_name,
+                        },
+                        activity,
+                    ))
+                })
+            })
+            .collect();
+        // Stable, so entries without sessions keep repo list order
+        entries.sort_by(|(_, a), (_, b)| cmp_optional_recency(*a, *b));
+        entries.into_iter().map(|(entry, _)| entry).collect()
+    }
+
     pub fn active_help_list_mut(&mut self) -> Option<&mut SearchableList> {
         self.help_overlay.as_mut().map(|overlay| &mut overlay.list)
     }
@@ -870,7 +1679,7 @@ impl AppState {
     pub fn is_branch_pending_delete(&self, repo_path: &Path, branch_name: &str) -> bool {
         self.pending_worktree_deletes
             .iter()
-            .any(|pending| pending.repo_path == repo_path && pending.branch_name == branch_name)
+            .any(|pending| pending.is_for(repo_path, branch_name))
     }
 
     pub fn set_active_list_page_rows(&mut self, rows: usize) {
@@ -882,9 +1691,8 @@ impl AppState {
     }
 
     pub fn mark_pending_worktree_delete(&mut self, pending: PendingWorktreeDelete) {
-        self.pending_worktree_deletes.retain(|entry| {
-            !(entry.repo_path == pending.repo_path && entry.branch_name == pending.branch_name)
-        });
+        self.pending_worktree_deletes
+            .retain(|entry| !entry.is_for(&pending.repo_path, &pending.branch_name));
         self.pending_worktree_deletes.push(pending);
     }
 
@@ -901,9 +1709,8 @@ impl AppState {
         branch_name: &str,
     ) -> bool {
         let before = self.pending_worktree_deletes.len();
-        self.pending_worktree_deletes.retain(|pending| {
-            !(pending.repo_path == repo_path && pending.branch_name == branch_name)
-        });
+        self.pending_worktree_deletes
+            .retain(|pending| !pending.is_for(repo_path, branch_name));
         before != self.pending_worktree_deletes.len()
     }
 
@@ -933,7 +1740,7 @@ impl AppState {
 pub fn worktree_dir(repo: &Repo, branch: &str) -> anyhow::Result<PathBuf> {
     let parent = repo.path.parent().unwrap_or(&repo.path);
     let worktree_root = parent.join(WORKTREE_DIR_NAME);
-    let safe_branch = branch.replace('/', "-");
+    let safe_branch = branch.replace(WORKTREE_NAME_UNSAFE_CHARS, "-");
     let base = format!("{}{WORKTREE_NAME_SEPARATOR}{safe_branch}", repo.name);
     let candidate = worktree_root.join(&base);
     if !candidate.exists() {
@@ -1174,6 +1981,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_worktree_dir_replaces_characters_windows_rejects() {
+        let tmp = tempdir().unwrap();
+        let repo = make_repo(tmp.path(), "repo");
+        let result = worktree_dir(&repo, "fix/a<b>|\"c\"").unwrap();
+        assert_eq!(
+            result.file_name().unwrap(),
+            format!("repo{WORKTREE_NAME_SEPARATOR}fix-a-b---c-").as_str()
+        );
+    }
+
     #[test]
     fn test_worktree_dir_dedup() {
         let tmp = tempdir().unwrap();
@@ -1284,6 +2102,56 @@ mod tests {
         assert!(entries.is_empty());
     }
 
+    #[test]
+    fn test_build_detached_lists_worktrees_without_a_branch() {
+        let tag_path = PathBuf::from(format!(
+            "/tmp/.kiosk_worktrees/myrepo{WORKTREE_NAME_SEPARATOR}v1.2.0"
+        ));
+        let repo = Repo {
+            name: "myrepo".to_string(),
+            session_name: "myrepo".to_string(),
+            path: PathBuf::from("/tmp/myrepo"),
+            worktrees: vec![
+                Worktree {
+                    path: PathBuf::from("/tmp/myrepo"),
+                    branch: None,
+                    is_main: true,
+                },
+                Worktree {
+                    path: PathBuf::from("/tmp/myrepo-dev"),
+                    branch: Some("dev".to_string()),
+                    is_main: false,
+                },
+                Worktree {
+                    path: tag_path.clone(),
+                    branch: None,
+                    is_main: false,
+                },
+            ],
+        };
+        let session = repo.tmux_session_name(&tag_path);
+
+        let mut entries = BranchEntry::build_detached(
+            &repo,
+            std::slice::from_ref(&session),
+            &HashMap::from([(session.clone(), 42)]),
+        );
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "v1.2.0");
+        assert_eq!(entries[0].worktree_path, Some(tag_path));
+        assert!(entries[0].detached);
+        assert!(entries[0].has_session);
+        assert_eq!(entries[0].session_activity_ts, Some(42));
+        assert!(!entries[0].is_local());
+
+        // A branch that happens to share the tag's name doesn't lend it a note
+        BranchEntry::apply_notes(
+            &mut entries,
+            &HashMap::from([("v1.2.0".to_string(), "note".to_string())]),
+        );
+        assert_eq!(entries[0].note, None);
+    }
+
     #[test]
     fn test_sort_remote_after_local() {
         let repo = Repo {
@@ -1327,6 +2195,25 @@ mod tests {
         assert!(!state.is_branch_pending_delete(&repo_path, "dev"));
     }
 
+    #[test]
+    fn test_cancel_loading_restores_mode_and_flags_operation() {
+        let mut state = AppState::new(vec![make_repo(std::path::Path::new("/tmp"), "repo")], None);
+        state.mode = Mode::BranchSelect;
+
+        let cancelled = state.start_loading("Creating worktree for dev...".to_string());
+        assert!(matches!(state.mode, Mode::Loading(_)));
+        assert_eq!(
+            state.cancel_loading().as_deref(),
+            Some("Creating worktree for dev...")
+        );
+        assert!(cancelled.load(Ordering::Relaxed));
+        assert_eq!(state.mode, Mode::BranchSelect);
+
+        // Loading that wasn't started by the user, like repo discovery, can't be cancelled
+        state.mode = Mode::Loading("Discovering repos...".to_string());
+        assert_eq!(state.cancel_loading(), None);
+    }
+
     #[test]
     fn test_scroll_anchor_behavior_down_then_up() {
         let mut list = SearchableList::new(100);
@@ -1723,6 +2610,36 @@ mod tests {
         assert_eq!(repos[1].name, "repo-b");
     }
 
+    #[test]
+    fn test_repo_activity_history_merges_its_sessions_with_current_activity() {
+        let repo = Repo {
+            name: "repo-a".to_string(),
+            session_name: "repo-a".to_string(),
+            path: PathBuf::from("/tmp/repo-a"),
+            worktrees: vec![Worktree {
+                path: PathBuf::from("/tmp/repo-a--feat"),
+                branch: Some("feat".to_string()),
+                is_main: false,
+            }],
+        };
+        let history = HashMap::from([
+            ("repo-a".to_string(), vec![10, 40]),
+            ("repo-a--feat".to_string(), vec![20]),
+            ("repo-b".to_string(), vec![30]),
+        ]);
+        let activity =
+            HashMap::from([("repo-a".to_string(), 40), ("repo-a--feat".to_string(), 50)]);
+
+        assert_eq!(
+            session_activity_history("repo-a", &activity, &history),
+            vec![10, 40]
+        );
+        assert_eq!(
+            repo_activity_history(&repo, &activity, &history),
+            vec![10, 20, 40, 50]
+        );
+    }
+
     #[test]
     fn test_sort_repos_empty() {
         let mut repos: Vec<Repo> = vec![];
@@ -1730,6 +2647,144 @@ mod tests {
         assert!(repos.is_empty());
     }
 
+    #[test]
+    fn test_sort_repos_by_frecency_ranks_visited_repo_without_session_first() {
+        let mut repos = vec![
+            Repo {
+                name: "alpha".to_string(),
+                session_name: "alpha".to_string(),
+                path: PathBuf::from("/tmp/alpha"),
+                worktrees: vec![],
+            },
+            Repo {
+                name: "daily".to_string(),
+                session_name: "daily".to_string(),
+                path: PathBuf::from("/tmp/daily"),
+                worktrees: vec![],
+            },
+            Repo {
+                name: "zebra".to_string(),
+                session_name: "zebra".to_string(),
+                path: PathBuf::from("/tmp/zebra"),
+                worktrees: vec![],
+            },
+        ];
+        let activity = HashMap::from([("zebra".to_string(), 500)]);
+        let frecency = HashMap::from([(PathBuf::from("/tmp/daily"), 64)]);
+
+        let no_pins = HashSet::new();
+        sort_repos_by(
+            &mut repos,
+            None,
+            SortOrder::Frecency,
+            &activity,
+            &frecency,
+            &no_pins,
+        );
+
+        // Frecency first, then session activity, then alphabetical
+        let names: Vec<_> = repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["daily", "zebra", "alpha"]);
+
+        sort_repos_by(
+            &mut repos,
+            None,
+            SortOrder::Alphabetical,
+            &activity,
+            &frecency,
+            &no_pins,
+        );
+        let names: Vec<_> = repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["alpha", "daily", "zebra"]);
+    }
+
+    #[test]
+    fn test_sort_repos_by_puts_pinned_after_current() {
+        let mut repos: Vec<Repo> = ["alpha", "current", "pinned", "zebra"]
+            .iter()
+            .map(|name| Repo {
+                name: (*name).to_string(),
+                session_name: (*name).to_string(),
+                path: PathBuf::from(format!("/tmp/{name}")),
+                worktrees: vec![],
+            })
+            .collect();
+        let activity = HashMap::from([("zebra".to_string(), 500)]);
+        let pinned = HashSet::from([PathBuf::from("/tmp/pinned")]);
+
+        sort_repos_by(
+            &mut repos,
+            Some(Path::new("/tmp/current")),
+            SortOrder::Activity,
+            &activity,
+            &HashMap::new(),
+            &pinned,
+        );
+
+        let names: Vec<_> = repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["current", "pinned", "zebra", "alpha"]);
+    }
+
+    #[test]
+    fn test_branch_sort_by_frecency_after_current_and_default() {
+        let mut entries: Vec<BranchEntry> = ["main", "feat-a", "feat-b", "feat-c"]
+            .iter()
+            .map(|name| BranchEntry {
+                name: (*name).to_string(),
+                worktree_path: None,
+                has_session: false,
+                is_current: false,
+                is_default: *name == "main",
+                remote: None,
+                session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
+            })
+            .collect();
+        entries[3].has_session = true;
+        entries[3].session_activity_ts = Some(100);
+        let frecency = HashMap::from([("feat-b".to_string(), 10), ("main".to_string(), 1)]);
+
+        BranchEntry::sort_entries_by(&mut entries, SortOrder::Frecency, &frecency);
+
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["main", "feat-b", "feat-c", "feat-a"]);
+    }
+
+    #[test]
+    fn test_group_by_labels_follows_label_order() {
+        let mut entries: Vec<BranchEntry> = ["main", "a", "b", "c", "d"]
+            .iter()
+            .map(|name| BranchEntry {
+                name: (*name).to_string(),
+                worktree_path: None,
+                has_session: false,
+                is_current: false,
+                is_default: *name == "main",
+                remote: None,
+                session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
+            })
+            .collect();
+        entries[0].labels = vec!["agent".to_string()];
+        entries[1].labels = vec!["agent".to_string()];
+        entries[3].labels = vec!["agent".to_string(), "feature".to_string()];
+        entries[4].labels = vec!["feature".to_string()];
+
+        BranchEntry::group_by_labels(&mut entries, &["feature".to_string(), "agent".to_string()]);
+
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["main", "c", "d", "a", "b"]);
+        assert_eq!(entries[1].search_text(), "c #agent #feature");
+    }
+
     #[test]
     fn test_branch_sort_current_is_also_default() {
         let repo = Repo {
@@ -1937,6 +2992,11 @@ mod tests {
                 is_default: false,
                 remote: Some("origin".to_string()),
                 session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
             },
             BranchEntry {
                 name: "zzz-local".to_string(),
@@ -1946,6 +3006,11 @@ mod tests {
                 is_default: false,
                 remote: None,
                 session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
             },
             BranchEntry {
                 name: "mmm-local".to_string(),
@@ -1955,6 +3020,11 @@ mod tests {
                 is_default: false,
                 remote: None,
                 session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
             },
         ];
 
@@ -2143,6 +3213,11 @@ mod tests {
             is_default: false,
             remote: None,
             session_activity_ts: Some(12345),
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         };
 
         let json = serde_json::to_string(&entry).unwrap();
@@ -2210,6 +3285,24 @@ mod tests {
         assert!(state.error.is_none());
     }
 
+    #[test]
+    fn test_apply_config_applies_everything_but_invalid_ignore_patterns() {
+        let mut state = AppState::new(Vec::new(), None);
+        state.repo_filter.hidden = HashSet::from([PathBuf::from("/hidden")]);
+        let mut config = crate::config::load_config_from_str(
+            "search_dirs = []\nsort = \"activity\"\nmouse = true\n\n[session]\nsplit_command = \"hx\"\n",
+        )
+        .unwrap();
+        // Loading rejects these, so only a config built in code has them
+        config.ignore = vec!["[".to_string()];
+
+        assert!(state.apply_config(&config).is_err());
+        assert_eq!(state.sort, SortOrder::Activity);
+        assert!(state.mouse);
+        assert_eq!(state.split_command.as_deref(), Some("hx"));
+        assert!(state.repo_filter.is_hidden(Path::new("/hidden")));
+    }
+
     #[test]
     fn test_mode_effective_plain() {
         assert_eq!(*Mode::BranchSelect.effective(), Mode::BranchSelect);
@@ -0,0 +1,158 @@
+//! Versioned TOML files under the state directory (pending deletes, frecency, notes, caches, …).
+//!
+//! Several kiosk processes can share these files, e.g. the TUI and a `kiosk` command run from
+//! another pane. Writes go to a temporary file that is renamed over the original, so a reader
+//! never sees a half-written file, and read-modify-write updates hold an exclusive lock on a
+//! sibling `.lock` file, so concurrent updates don't lose each other's changes.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StateFile<E> {
+    version: u32,
+    entries: E,
+}
+
+/// Entries in `file_path`, or none if it's missing, unreadable or from another version.
+pub(crate) fn load_state_file<T: DeserializeOwned>(file_path: &Path, version: u32) -> Vec<T> {
+    let Ok(contents) = fs::read_to_string(file_path) else {
+        return Vec::new();
+    };
+
+    let Ok(parsed) = toml::from_str::<StateFile<Vec<T>>>(&contents) else {
+        return Vec::new();
+    };
+
+    if parsed.version != version {
+        return Vec::new();
+    }
+
+    parsed.entries
+}
+
+/// Replace the entries in `file_path`, removing the file when there are none.
+pub(crate) fn save_state_file<T: Serialize>(
+    file_path: &Path,
+    version: u32,
+    entries: &[T],
+) -> Result<()> {
+    let _lock = lock(file_path)?;
+    write_state_file(file_path, version, entries)
+}
+
+/// Load the entries in `file_path`, let `edit` change them and save the result, holding the
+/// file's lock throughout.
+pub(crate) fn update_state_file<T, R>(
+    file_path: &Path,
+    version: u32,
+    edit: impl FnOnce(&mut Vec<T>) -> R,
+) -> Result<R>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let _lock = lock(file_path)?;
+    let mut entries = load_state_file(file_path, version);
+    let result = edit(&mut entries);
+    write_state_file(file_path, version, &entries)?;
+    Ok(result)
+}
+
+/// Block until this process holds the lock for `file_path`, released when the handle drops.
+pub(crate) fn lock(file_path: &Path) -> Result<File> {
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let lock_path = lock_path(file_path);
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("failed to open {}", lock_path.display()))?;
+    file.lock()
+        .with_context(|| format!("failed to lock {}", lock_path.display()))?;
+    Ok(file)
+}
+
+fn lock_path(file_path: &Path) -> PathBuf {
+    let mut name = file_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    file_path.with_file_name(name)
+}
+
+fn write_state_file<T: Serialize>(file_path: &Path, version: u32, entries: &[T]) -> Result<()> {
+    if entries.is_empty() {
+        if file_path.exists() {
+            fs::remove_file(file_path)?;
+        }
+        return Ok(());
+    }
+
+    let serialized = toml::to_string(&StateFile { version, entries })?;
+    let dir = file_path.parent().unwrap_or(Path::new("."));
+    let mut temp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("failed to create a temporary file in {}", dir.display()))?;
+    temp.write_all(serialized.as_bytes())?;
+    temp.as_file().sync_all()?;
+    temp.persist(file_path)
+        .with_context(|| format!("failed to write {}", file_path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_round_trip_and_empty_removes_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("state.toml");
+
+        save_state_file(&path, 1, &["a".to_string(), "b".to_string()]).unwrap();
+        assert_eq!(load_state_file::<String>(&path, 1), ["a", "b"]);
+        assert!(load_state_file::<String>(&path, 2).is_empty());
+
+        save_state_file::<String>(&path, 1, &[]).unwrap();
+        assert!(!path.exists());
+        let names: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(
+            names,
+            ["state.toml.lock"],
+            "no temporary files are left behind"
+        );
+    }
+
+    #[test]
+    fn test_concurrent_updates_keep_every_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("counter.toml");
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        update_state_file::<u32, _>(&path, 1, |entries| {
+                            match entries.first_mut() {
+                                Some(count) => *count += 1,
+                                None => entries.push(1),
+                            }
+                        })
+                        .unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(load_state_file::<u32>(&path, 1), [80]);
+    }
+}
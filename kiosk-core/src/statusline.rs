@@ -0,0 +1,120 @@
+use crate::{
+    paths::state_dir,
+    pending_delete::now_unix_secs,
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const STATUSLINE_CACHE_FILE_NAME: &str = "statusline.toml";
+const STATUSLINE_CACHE_STATE_VERSION: u32 = 1;
+/// How long a summary of a quiet session is reused, to catch changes made from outside it.
+const MAX_AGE_SECS: u64 = 60;
+/// Summaries unused for this long are dropped, as their session is most likely gone.
+const FORGET_AFTER_SECS: u64 = 24 * 60 * 60;
+
+/// What `kiosk statusline` found in a session's worktree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub session: String,
+    pub path: PathBuf,
+    pub repo: String,
+    /// `None` on a detached `HEAD` or outside a repo
+    pub branch: Option<String>,
+    pub dirty: bool,
+    pub agent: Option<String>,
+    pub checked_at_unix_secs: u64,
+}
+
+impl SessionSummary {
+    /// Whether nothing has happened in the session since the summary was made, going by its
+    /// last `activity` (Unix seconds), and the summary is recent enough to trust.
+    pub fn is_fresh(&self, activity: u64) -> bool {
+        self.checked_at_unix_secs > activity
+            && now_unix_secs().saturating_sub(self.checked_at_unix_secs) < MAX_AGE_SECS
+    }
+}
+
+/// The last summary of each session, so status bars calling `kiosk statusline` every few
+/// seconds only look at worktrees whose sessions had activity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatuslineCache {
+    path: PathBuf,
+}
+
+impl Default for StatuslineCache {
+    fn default() -> Self {
+        Self::at(state_dir().join(STATUSLINE_CACHE_FILE_NAME))
+    }
+}
+
+impl StatuslineCache {
+    /// Use a cache stored at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The last summary of `session` while it was in `path`, fresh or not.
+    pub fn get(&self, session: &str, path: &Path) -> Option<SessionSummary> {
+        load_state_file::<SessionSummary>(&self.path, STATUSLINE_CACHE_STATE_VERSION)
+            .into_iter()
+            .find(|summary| summary.session == session && summary.path == path)
+    }
+
+    /// Save `summary` in place of the session's earlier one, forgetting long-unused ones.
+    pub fn save(&self, summary: SessionSummary) -> Result<()> {
+        let now = now_unix_secs();
+        update_state_file(
+            &self.path,
+            STATUSLINE_CACHE_STATE_VERSION,
+            |summaries: &mut Vec<SessionSummary>| {
+                summaries.retain(|existing| {
+                    existing.session != summary.session
+                        && now.saturating_sub(existing.checked_at_unix_secs) < FORGET_AFTER_SECS
+                });
+                summaries.push(summary);
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn summary(session: &str, checked_at_unix_secs: u64) -> SessionSummary {
+        SessionSummary {
+            session: session.to_string(),
+            path: PathBuf::from(format!("/w/{session}")),
+            repo: "r".to_string(),
+            branch: Some(session.to_string()),
+            dirty: false,
+            agent: None,
+            checked_at_unix_secs,
+        }
+    }
+
+    #[test]
+    fn summary_is_fresh_until_the_session_has_activity_or_it_ages() {
+        let now = now_unix_secs();
+        assert!(summary("a", now).is_fresh(now - 10));
+        assert!(!summary("a", now).is_fresh(now));
+        assert!(!summary("a", now - MAX_AGE_SECS).is_fresh(now - MAX_AGE_SECS - 10));
+    }
+
+    #[test]
+    fn save_replaces_the_sessions_summary_and_forgets_old_ones() {
+        let tmp = tempdir().unwrap();
+        let cache = StatuslineCache::at(tmp.path().join("statusline.toml"));
+        let now = now_unix_secs();
+        cache.save(summary("old", now - FORGET_AFTER_SECS)).unwrap();
+        cache.save(summary("a", now - 5)).unwrap();
+        cache.save(summary("a", now)).unwrap();
+
+        assert_eq!(cache.get("a", Path::new("/w/a")), Some(summary("a", now)));
+        assert_eq!(cache.get("a", Path::new("/w/b")), None);
+        assert_eq!(cache.get("old", Path::new("/w/old")), None);
+    }
+}
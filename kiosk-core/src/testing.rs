@@ -0,0 +1,107 @@
+//! In-memory providers for testing tools built on kiosk-core without real git or tmux.
+//!
+//! Enable with the `testing` feature, typically as a dev-dependency:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! kiosk-core = { version = "0.2", features = ["testing"] }
+//! ```
+//!
+//! The mocks expose builder methods (`with_repo`, `with_session_running`, ...) for
+//! one-off setups, and the functions below cover common scenarios. Pass them to
+//! [`Runtime::builder`](crate::runtime::Runtime::builder) to run kiosk against them.
+
+use std::path::PathBuf;
+
+pub use crate::git::mock::MockGitProvider;
+pub use crate::tmux::mock::MockTmuxProvider;
+
+/// A single repo with a `main` branch plus `feature-1` through `feature-{count}`.
+pub fn repo_with_branches(name: &str, path: impl Into<PathBuf>, count: usize) -> MockGitProvider {
+    MockGitProvider::default()
+        .with_repo(name, path)
+        .with_branches(
+            std::iter::once("main".to_string()).chain((1..=count).map(|i| format!("feature-{i}"))),
+        )
+}
+
+/// A tmux server (with the caller inside it) hosting one session per `(name, command)` pair,
+/// where `command` is the pane's foreground process, e.g. `("app--feat", "claude")`.
+pub fn sessions_running<'a>(
+    sessions: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> MockTmuxProvider {
+    sessions.into_iter().fold(
+        MockTmuxProvider::default().inside_tmux(),
+        |tmux, (name, command)| tmux.with_session_running(name, command),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        git::{GitProvider, WorktreeChanges},
+        tmux::TmuxProvider,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn test_repo_with_branches() {
+        let git = repo_with_branches("app", "/tmp/app", 3);
+        let repos = git.discover_repos(&[]);
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].name, "app");
+        assert_eq!(
+            git.list_branches(Path::new("/tmp/app")),
+            ["main", "feature-1", "feature-2", "feature-3"]
+        );
+        assert_eq!(git.list_worktrees(Path::new("/tmp/app")).len(), 1);
+    }
+
+    #[test]
+    fn test_sessions_running() {
+        let tmux = sessions_running([("app", "zsh"), ("app--feat", "claude")]);
+        assert!(tmux.is_inside_tmux());
+        assert_eq!(tmux.list_session_names(), ["app", "app--feat"]);
+        assert_eq!(
+            tmux.pane_current_command("app--feat", "0").unwrap(),
+            "claude"
+        );
+    }
+
+    #[test]
+    fn test_git_builders() {
+        let changes = WorktreeChanges {
+            files_changed: 2,
+            ..WorktreeChanges::default()
+        };
+        let git = repo_with_branches("app", "/tmp/app", 1)
+            .with_default_branch("main")
+            .with_worktree_changes("/tmp/app", changes)
+            .with_unique_commits("feature-1", 3);
+        let repo = Path::new("/tmp/app");
+        assert_eq!(git.default_branch(repo, &[]).as_deref(), Some("main"));
+        assert!(git.worktree_changes(repo).unwrap().is_dirty());
+        assert_eq!(git.unique_commits(repo, "feature-1", "main").unwrap(), 3);
+        assert_eq!(git.unique_commits(repo, "main", "main").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_tmux_builders() {
+        let tmux = MockTmuxProvider::default()
+            .with_session_activity("app", 100)
+            .with_session_activity("api", 200)
+            .attached_to("app");
+        assert_eq!(
+            tmux.list_sessions_with_activity()[1],
+            ("api".to_string(), 200)
+        );
+        assert_eq!(tmux.current_session().as_deref(), Some("app"));
+        assert!(tmux.is_inside_tmux());
+
+        let tmux = MockTmuxProvider::default().with_session_path("app", "/tmp/app");
+        assert_eq!(tmux.session_path("app").unwrap(), Path::new("/tmp/app"));
+        assert!(tmux.session_exists("app"));
+        assert!(!MockTmuxProvider::default().unavailable().is_available());
+    }
+}
@@ -0,0 +1,323 @@
+//! Looking up Jira and Linear tickets to name new branches after.
+
+use crate::{
+    branch_name,
+    config::{TicketConfig, TicketProvider},
+    process::CommandExt,
+};
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::process::Command;
+
+/// The `branch.name_template` placeholder filled with the ticket's ID.
+pub const TICKET_FIELD: &str = "ticket";
+/// How branches are named after tickets when `branch.name_template` isn't set.
+pub const DEFAULT_NAME_TEMPLATE: &str = "{ticket}-{slug}";
+
+const LINEAR_API_URL: &str = "https://api.linear.app/graphql";
+const LINEAR_QUERY: &str = "query($id: String!) { issue(id: $id) { identifier title url } }";
+/// Seconds to wait for the ticket's API before giving up.
+const REQUEST_TIMEOUT_SECS: u32 = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ticket {
+    pub id: String,
+    pub title: String,
+    /// Link to the ticket, saved as the new branch's note
+    pub url: String,
+}
+
+impl Ticket {
+    /// The branch name for this ticket from `template` (or [`DEFAULT_NAME_TEMPLATE`]), with
+    /// `{slug}` made from its title and `{ticket}` its ID. Other fields are left as
+    /// placeholders.
+    pub fn branch_name(&self, template: Option<&str>) -> String {
+        branch_name::from_template(
+            template.unwrap_or(DEFAULT_NAME_TEMPLATE),
+            &self.title,
+            &self.template_values(),
+        )
+    }
+
+    /// The template fields this ticket fills in.
+    pub fn template_values(&self) -> Vec<(String, String)> {
+        vec![(TICKET_FIELD.to_string(), self.id.clone())]
+    }
+}
+
+/// Whether `text` is shaped like a ticket ID: a project key, `-`, then a number, like
+/// `ABC-123`.
+pub fn is_ticket_id(text: &str) -> bool {
+    text.split_once('-').is_some_and(|(key, number)| {
+        key.starts_with(|c: char| c.is_ascii_alphabetic())
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+/// Fetch ticket `id` from the configured provider with `curl`.
+pub fn fetch(config: &TicketConfig, id: &str) -> Result<Ticket> {
+    let Some(provider) = config.provider else {
+        bail!("no ticket provider configured; set ticket.provider to \"jira\" or \"linear\"");
+    };
+    let token_env = config.token_env(provider);
+    let token = std::env::var(token_env)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .with_context(|| format!("set ${token_env} to your API token to fetch {id}"))?;
+    let request = curl_request(config, provider, id, &token)?;
+    // The request, token included, goes in on stdin so it stays out of the process list
+    // and the command log
+    let output = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail-with-body",
+            "--config",
+            "-",
+        ])
+        .traced_output_with_stdin(request.as_bytes())
+        .context("failed to run curl")?;
+    let body = String::from_utf8_lossy(&output.stdout);
+    let json = serde_json::from_str::<Value>(&body).ok();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = json
+            .as_ref()
+            .and_then(api_error)
+            .unwrap_or_else(|| stderr.trim().to_string());
+        bail!("failed to fetch {id}: {reason}");
+    }
+    let json = json.with_context(|| format!("failed to fetch {id}: the response wasn't JSON"))?;
+    match provider {
+        TicketProvider::Jira => parse_jira(&json, jira_site(config)?),
+        TicketProvider::Linear => parse_linear(&json),
+    }
+    .with_context(|| format!("failed to fetch {id}"))
+}
+
+fn jira_site(config: &TicketConfig) -> Result<&str> {
+    config
+        .url
+        .as_deref()
+        .map(|url| url.trim_end_matches('/'))
+        .context("set ticket.url to your Jira site, like https://acme.atlassian.net")
+}
+
+/// The `curl --config` file asking `provider` for ticket `id`.
+fn curl_request(
+    config: &TicketConfig,
+    provider: TicketProvider,
+    id: &str,
+    token: &str,
+) -> Result<String> {
+    let mut lines = vec![format!("max-time = {REQUEST_TIMEOUT_SECS}")];
+    match provider {
+        TicketProvider::Jira => {
+            let email = config
+                .email
+                .as_deref()
+                .context("set ticket.email to the email of your Jira account")?;
+            let site = jira_site(config)?;
+            lines.push(format!(
+                "url = {}",
+                curl_quote(&format!("{site}/rest/api/2/issue/{id}?fields=summary"))
+            ));
+            lines.push(format!(
+                "user = {}",
+                curl_quote(&format!("{email}:{token}"))
+            ));
+        }
+        TicketProvider::Linear => {
+            let body = serde_json::json!({ "query": LINEAR_QUERY, "variables": { "id": id } });
+            lines.push(format!("url = {}", curl_quote(LINEAR_API_URL)));
+            lines.push(format!(
+                "header = {}",
+                curl_quote(&format!("Authorization: {token}"))
+            ));
+            lines.push(format!(
+                "header = {}",
+                curl_quote("Content-Type: application/json")
+            ));
+            lines.push(format!("data = {}", curl_quote(&body.to_string())));
+        }
+    }
+    lines.push(String::new());
+    Ok(lines.join("\n"))
+}
+
+/// `value` as a double-quoted `curl --config` string.
+pub(crate) fn curl_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The first error message in a Jira or Linear error response.
+fn api_error(json: &Value) -> Option<String> {
+    json.pointer("/errorMessages/0")
+        .or_else(|| json.pointer("/errors/0/message"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn parse_jira(json: &Value, site: &str) -> Result<Ticket> {
+    let id = json.get("key").and_then(Value::as_str);
+    let title = json.pointer("/fields/summary").and_then(Value::as_str);
+    let (Some(id), Some(title)) = (id, title) else {
+        bail!(
+            "{}",
+            api_error(json).unwrap_or_else(|| "no issue in the response".to_string())
+        );
+    };
+    Ok(Ticket {
+        id: id.to_string(),
+        title: title.to_string(),
+        url: format!("{site}/browse/{id}"),
+    })
+}
+
+fn parse_linear(json: &Value) -> Result<Ticket> {
+    let issue = json.pointer("/data/issue");
+    let field = |name: &str| {
+        issue
+            .and_then(|issue| issue.get(name))
+            .and_then(Value::as_str)
+    };
+    let (Some(id), Some(title), Some(url)) = (field("identifier"), field("title"), field("url"))
+    else {
+        bail!(
+            "{}",
+            api_error(json).unwrap_or_else(|| "no issue in the response".to_string())
+        );
+    };
+    Ok(Ticket {
+        id: id.to_string(),
+        title: title.to_string(),
+        url: url.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ticket() -> Ticket {
+        Ticket {
+            id: "ABC-123".to_string(),
+            title: "Fix the login page".to_string(),
+            url: "https://linear.app/acme/issue/ABC-123".to_string(),
+        }
+    }
+
+    #[test]
+    fn recognises_ticket_ids() {
+        for id in ["ABC-123", "abc-1", "A1_B-42"] {
+            assert!(is_ticket_id(id), "{id}");
+        }
+        for text in [
+            "ABC",
+            "ABC-",
+            "-123",
+            "1AB-2",
+            "fix-login",
+            "ABC-12-x",
+            "AB C-1",
+        ] {
+            assert!(!is_ticket_id(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn branches_are_named_after_the_ticket() {
+        assert_eq!(ticket().branch_name(None), "ABC-123-fix-the-login-page");
+        assert_eq!(
+            ticket().branch_name(Some("{user}/{ticket}/{slug}")),
+            "{user}/ABC-123/fix-the-login-page"
+        );
+    }
+
+    #[test]
+    fn parses_jira_issues() {
+        let json = json!({ "key": "ABC-123", "fields": { "summary": "Fix the login page" } });
+        assert_eq!(
+            parse_jira(&json, "https://acme.atlassian.net").unwrap(),
+            Ticket {
+                url: "https://acme.atlassian.net/browse/ABC-123".to_string(),
+                ..ticket()
+            }
+        );
+    }
+
+    #[test]
+    fn parses_linear_issues() {
+        let json = json!({ "data": { "issue": {
+            "identifier": "ABC-123",
+            "title": "Fix the login page",
+            "url": "https://linear.app/acme/issue/ABC-123",
+        } } });
+        assert_eq!(parse_linear(&json).unwrap(), ticket());
+    }
+
+    #[test]
+    fn reports_api_errors() {
+        let jira = json!({ "errorMessages": ["Issue does not exist"], "errors": {} });
+        assert_eq!(api_error(&jira).as_deref(), Some("Issue does not exist"));
+        let linear = json!({ "data": null, "errors": [{ "message": "Entity not found" }] });
+        assert_eq!(
+            parse_linear(&linear).unwrap_err().to_string(),
+            "Entity not found"
+        );
+    }
+
+    #[test]
+    fn linear_requests_send_the_query_and_key() {
+        let config = TicketConfig {
+            provider: Some(TicketProvider::Linear),
+            ..TicketConfig::default()
+        };
+        let request = curl_request(&config, TicketProvider::Linear, "ABC-123", "key").unwrap();
+        assert_eq!(
+            request.lines().collect::<Vec<_>>(),
+            [
+                "max-time = 30",
+                "url = \"https://api.linear.app/graphql\"",
+                "header = \"Authorization: key\"",
+                "header = \"Content-Type: application/json\"",
+                "data = \"{\\\"query\\\":\\\"query($id: String!) { issue(id: $id) { identifier \
+                 title url } }\\\",\\\"variables\\\":{\\\"id\\\":\\\"ABC-123\\\"}}\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn jira_requests_need_a_site_and_email() {
+        let mut config = TicketConfig {
+            provider: Some(TicketProvider::Jira),
+            url: Some("https://acme.atlassian.net/".to_string()),
+            ..TicketConfig::default()
+        };
+        assert!(curl_request(&config, TicketProvider::Jira, "ABC-1", "token").is_err());
+        config.email = Some("me@acme.com".to_string());
+        let request = curl_request(&config, TicketProvider::Jira, "ABC-1", "token").unwrap();
+        assert!(request.contains(
+            "url = \"https://acme.atlassian.net/rest/api/2/issue/ABC-1?fields=summary\""
+        ));
+        assert!(request.contains("user = \"me@acme.com:token\""));
+    }
+}
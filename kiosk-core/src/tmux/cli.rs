@@ -1,28 +1,339 @@
-use super::provider::TmuxProvider;
+use super::provider::{CaptureRange, PaneDetails, SessionDetails, TmuxNotRun, TmuxProvider};
+use crate::{
+    config::TmuxConfig,
+    process::{CommandExt, CommandPolicy},
+};
 use anyhow::{Context, Result, bail};
-use std::{path::Path, process::Command};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::{Mutex, OnceLock, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
 
-pub struct CliTmuxProvider;
+/// Runs the `tmux` CLI within the config's limits.
+pub struct CliTmuxProvider {
+    policy: RwLock<CommandPolicy>,
+    /// Server socket name passed to every tmux command as `-L` (`tmux.socket` in the config)
+    socket: RwLock<Option<String>>,
+    base: Mutex<BaseLookup>,
+}
+
+/// What's known of the server's base indices, which its config may use to renumber windows
+/// and panes.
+#[derive(Debug, Clone, Copy, Default)]
+enum BaseLookup {
+    #[default]
+    Unknown,
+    Found(BaseIndices),
+    /// No server was running when last asked
+    NoServer(Instant),
+}
+
+/// How long to go on assuming there's no server before asking again, so that one started
+/// outside kiosk is noticed without querying on every command.
+const NO_SERVER_RECHECK: Duration = Duration::from_secs(2);
+
+/// Failures worth retrying: the server going away while it's starting or shutting down.
+const TRANSIENT_ERRORS: &[&str] = &["server exited unexpectedly", "lost server"];
+
+/// A command that never ran is [`TmuxNotRun`], except one stopped by its time limit: that
+/// tmux did start.
+fn not_run(error: io::Error) -> anyhow::Error {
+    if error.kind() == io::ErrorKind::TimedOut {
+        error.into()
+    } else {
+        TmuxNotRun(error).into()
+    }
+}
+
+fn command_policy(config: &TmuxConfig) -> CommandPolicy {
+    CommandPolicy {
+        timeout: config.command_timeout(),
+        transient_errors: TRANSIENT_ERRORS,
+    }
+}
+
+impl Default for CliTmuxProvider {
+    fn default() -> Self {
+        Self::new(&TmuxConfig::default())
+    }
+}
+
+impl CliTmuxProvider {
+    /// A provider on `config`'s server (`tmux.socket`).
+    pub fn new(config: &TmuxConfig) -> Self {
+        Self {
+            policy: RwLock::new(command_policy(config)),
+            socket: RwLock::new(config.socket.clone()),
+            base: Mutex::default(),
+        }
+    }
+
+    pub(super) fn policy(&self) -> CommandPolicy {
+        *self.policy.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Run a tmux command that changes something. It isn't retried, since it may have taken
+    /// effect before failing.
+    fn run(&self, command: &mut Command) -> Result<Output> {
+        self.policy().output(command).map_err(not_run)
+    }
+
+    /// Run a tmux command that only reads, retrying transient failures.
+    fn read(&self, command: &mut Command) -> Result<Output> {
+        self.policy().read_output(command).map_err(not_run)
+    }
+
+    /// A tmux command on the configured server.
+    pub(super) fn tmux(&self) -> Command {
+        let mut command = Command::new("tmux");
+        if let Some(socket) = self
+            .socket
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_deref()
+        {
+            command.args(["-L", socket]);
+        }
+        command
+    }
+
+    /// The server's base indices, read once it's running. Until then targets use the
+    /// defaults, which is all there is to target.
+    pub(super) fn base_indices(&self) -> BaseIndices {
+        let mut base = self.base.lock().unwrap_or_else(PoisonError::into_inner);
+        match *base {
+            BaseLookup::Found(indices) => return indices,
+            BaseLookup::NoServer(at) if at.elapsed() < NO_SERVER_RECHECK => {
+                return BaseIndices::default();
+            }
+            BaseLookup::Unknown | BaseLookup::NoServer(_) => {}
+        }
+        let found = self.query_base_indices();
+        *base = found.map_or_else(|| BaseLookup::NoServer(Instant::now()), BaseLookup::Found);
+        found.unwrap_or_default()
+    }
+
+    fn query_base_indices(&self) -> Option<BaseIndices> {
+        let output = self
+            .read(self.tmux().args([
+                "show-options",
+                "-gv",
+                "base-index",
+                ";",
+                "show-options",
+                "-gwv",
+                "pane-base-index",
+            ]))
+            .ok()
+            .filter(|output| output.status.success())?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines().map(|line| line.trim().parse().ok());
+        Some(BaseIndices {
+            window: lines.next()??,
+            pane: lines.next()??,
+        })
+    }
+
+    /// Forget a missing server once kiosk has started one.
+    fn server_started(&self) {
+        let mut base = self.base.lock().unwrap_or_else(PoisonError::into_inner);
+        if matches!(*base, BaseLookup::NoServer(_)) {
+            *base = BaseLookup::Unknown;
+        }
+    }
+}
+
+/// The server's `base-index` and `pane-base-index`. kiosk numbers panes from 0 and works in
+/// a session's first window, so targets are shifted by these.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct BaseIndices {
+    window: usize,
+    pane: usize,
+}
+
+/// The first window of `session`.
+pub(super) fn window_target(base: BaseIndices, session: &str) -> String {
+    format!("={session}:{}", base.window)
+}
+
+/// Pane `pane` of the first window of `session`, where `pane` counts from 0. Anything other
+/// than an index is passed through.
+pub(super) fn pane_target(base: BaseIndices, session: &str, pane: &str) -> String {
+    let pane = pane.parse::<usize>().map_or_else(
+        |_| pane.to_string(),
+        |index| (index + base.pane).to_string(),
+    );
+    format!("{}.{pane}", window_target(base, session))
+}
+
+pub(super) const SESSIONS_FORMAT: &str = "#{session_name}:#{session_activity}";
+
+/// The detached session `ControlTmuxProvider`'s control clients attach to, so they never
+/// count as clients of the user's sessions. It's left out of every session listing.
+pub(super) const CONTROL_SESSION: &str = "_kiosk_control";
+
+/// Tab-separated so commands containing `:` survive; the title goes last because it may
+/// itself contain tabs.
+pub(super) const PANE_DETAILS_FORMAT: &str = "#{pane_index}\t#{pane_current_command}\t#{pane_pid}\t#{pane_active}\t#{pane_width}\t#{pane_height}\t#{pane_title}";
+
+/// Panes from `tmux list-panes -F PANE_DETAILS_FORMAT`, numbered from 0.
+pub(super) fn parse_panes(stdout: &str, base: BaseIndices) -> Vec<PaneDetails> {
+    stdout
+        .lines()
+        .filter_map(parse_pane_details)
+        .map(|pane| PaneDetails {
+            index: pane.index.saturating_sub(base.pane),
+            ..pane
+        })
+        .collect()
+}
+
+/// Sessions from `tmux list-sessions -F SESSIONS_FORMAT`.
+pub(super) fn parse_sessions(stdout: &str) -> Vec<(String, u64)> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, ts) = line.rsplit_once(':')?;
+            let ts = ts.parse::<u64>().ok()?;
+            (name != CONTROL_SESSION).then(|| (name.to_string(), ts))
+        })
+        .collect()
+}
+
+fn parse_pane_details(line: &str) -> Option<PaneDetails> {
+    let mut parts = line.splitn(7, '\t');
+    Some(PaneDetails {
+        index: parts.next()?.parse().ok()?,
+        current_command: parts.next()?.to_string(),
+        pid: parts.next()?.parse().unwrap_or(0),
+        active: parts.next()? == "1",
+        width: parts.next()?.parse().unwrap_or(0),
+        height: parts.next()?.parse().unwrap_or(0),
+        title: parts.next().unwrap_or_default().to_string(),
+    })
+}
+
+/// One line per pane of every session for `list_sessions_detailed`, with the session name
+/// last since it's the only free text. `ControlTmuxProvider`'s clients attach to
+/// [`CONTROL_SESSION`], so `session_attached` counts only the user's clients.
+pub(super) const SESSION_PANES_FORMAT: &str = "#{window_active}\t#{window_index}\t#{pane_index}\t#{session_activity}\t#{session_attached}\t#{pane_current_command}\t#{session_name}";
+
+/// Control-mode clients, like `ControlTmuxProvider`'s, only listen, so they're left out of
+/// the clients kiosk reports.
+pub(super) const CLIENTS_FORMAT: &str = "#{client_control_mode}\t#{client_tty}\t#{client_session}";
+
+/// The `(session, tty)` of each client from `tmux list-clients -F CLIENTS_FORMAT`, other than
+/// control-mode ones.
+pub(super) fn parse_clients(stdout: &str) -> Vec<(String, String)> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let (control_mode, tty, session) = (parts.next()?, parts.next()?, parts.next()?);
+            (control_mode != "1").then(|| (session.to_string(), tty.to_string()))
+        })
+        .collect()
+}
+
+/// Sessions from `tmux list-panes -a -F SESSION_PANES_FORMAT`, in the order tmux lists them.
+pub(super) fn parse_session_panes(stdout: &str, base: BaseIndices) -> Vec<SessionDetails> {
+    let mut sessions: Vec<SessionDetails> = Vec::new();
+    for line in stdout.lines() {
+        let mut parts = line.splitn(7, '\t');
+        let (
+            Some(window_active),
+            Some(window),
+            Some(pane),
+            Some(activity),
+            Some(clients),
+            Some(command),
+            Some(name),
+        ) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        )
+        else {
+            continue;
+        };
+        if name == CONTROL_SESSION {
+            continue;
+        }
+        if sessions.last().is_none_or(|session| session.name != name) {
+            sessions.push(SessionDetails {
+                name: name.to_string(),
+                activity: activity.parse().unwrap_or(0),
+                clients: clients.parse().unwrap_or(0),
+                pane_count: 0,
+                current_command: String::new(),
+            });
+        }
+        let Some(session) = sessions.last_mut() else {
+            continue;
+        };
+        if window_active == "1" {
+            session.pane_count += 1;
+        }
+        if window.parse() == Ok(base.window) && pane.parse() == Ok(base.pane) {
+            command.clone_into(&mut session.current_command);
+        }
+    }
+    sessions
+}
+
+fn capture_range_args(target: &str, range: &CaptureRange) -> Vec<String> {
+    let mut args = vec![
+        "capture-pane".to_string(),
+        "-t".to_string(),
+        target.to_string(),
+        "-p".to_string(),
+    ];
+    if range.escapes {
+        args.push("-e".to_string());
+    }
+    if let Some(start) = range.start {
+        args.extend(["-S".to_string(), start.to_string()]);
+    }
+    if let Some(end) = range.end {
+        args.extend(["-E".to_string(), end.to_string()]);
+    }
+    args
+}
 
 fn create_session_commands(
     name: &str,
     dir_str: &str,
     split_command: Option<&str>,
+    env: &[(String, String)],
 ) -> Vec<Vec<String>> {
-    let mut commands = vec![vec![
+    let mut new_session = vec![
         "new-session".to_string(),
         "-ds".to_string(),
         name.to_string(),
         "-c".to_string(),
         dir_str.to_string(),
-    ]];
+    ];
+    // `-e` goes into the session's environment, as `set-environment` would, but in time for
+    // the first shell; later panes (the split included) inherit it from there
+    for (key, value) in env {
+        new_session.extend(["-e".to_string(), format!("{key}={value}")]);
+    }
+    let mut commands = vec![new_session];
 
     if let Some(cmd) = split_command.filter(|cmd| !cmd.trim().is_empty()) {
         commands.push(vec![
             "split-window".to_string(),
             "-h".to_string(),
             "-t".to_string(),
-            format!("={name}:0"),
+            // The session's only window, whatever `base-index` numbers it
+            format!("={name}:"),
             "-c".to_string(),
             dir_str.to_string(),
             cmd.to_string(),
@@ -34,60 +345,114 @@ fn create_session_commands(
 
 impl TmuxProvider for CliTmuxProvider {
     fn list_sessions_with_activity(&self) -> Vec<(String, u64)> {
-        let output = Command::new("tmux")
-            .args(["list-sessions", "-F", "#{session_name}:#{session_activity}"])
-            .output();
+        let output = self.read(self.tmux().args(["list-sessions", "-F", SESSIONS_FORMAT]));
 
         let Ok(output) = output else {
             return Vec::new();
         };
 
-        String::from_utf8_lossy(&output.stdout)
-            .lines()
-            .filter_map(|line| {
-                let (name, ts) = line.rsplit_once(':')?;
-                let ts = ts.parse::<u64>().ok()?;
-                Some((name.to_string(), ts))
-            })
-            .collect()
+        parse_sessions(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn list_sessions_detailed(&self) -> Vec<SessionDetails> {
+        let output = self.read(
+            self.tmux()
+                .args(["list-panes", "-a", "-F", SESSION_PANES_FORMAT]),
+        );
+        match output {
+            Ok(output) if output.status.success() => parse_session_panes(
+                &String::from_utf8_lossy(&output.stdout),
+                self.base_indices(),
+            ),
+            _ => Vec::new(),
+        }
     }
 
     fn session_exists(&self, name: &str) -> bool {
-        Command::new("tmux")
-            .args(["has-session", "-t", &format!("={name}")])
-            .output()
+        self.read(self.tmux().args(["has-session", "-t", &format!("={name}")]))
             .is_ok_and(|o| o.status.success())
     }
 
-    fn create_session(&self, name: &str, dir: &Path, split_command: Option<&str>) -> Result<()> {
+    fn current_session(&self) -> Option<String> {
+        if !self.is_inside_tmux() {
+            return None;
+        }
+        let mut command = self.tmux();
+        command.args(["display-message", "-p"]);
+        // The pane this process runs in, rather than the client's current one
+        if let Ok(pane) = std::env::var("TMUX_PANE") {
+            command.args(["-t", &pane]);
+        }
+        let output = self.read(command.arg("#{session_name}")).ok()?;
+        let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !name.is_empty()).then_some(name)
+    }
+
+    fn server_started_at(&self) -> Option<u64> {
+        let output = self
+            .read(self.tmux().args(["display-message", "-p", "#{start_time}"]))
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    }
+
+    fn session_path(&self, session: &str) -> Result<PathBuf> {
+        let output = self
+            .read(self.tmux().args([
+                "display-message",
+                "-t",
+                &window_target(self.base_indices(), session),
+                "-p",
+                "#{session_path}",
+            ]))
+            .with_context(|| {
+                format!("failed to execute tmux display-message for session {session}")
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("tmux display-message failed: {}", stderr.trim());
+        }
+        Ok(PathBuf::from(
+            String::from_utf8_lossy(&output.stdout).trim(),
+        ))
+    }
+
+    fn create_session(
+        &self,
+        name: &str,
+        dir: &Path,
+        split_command: Option<&str>,
+        env: &[(String, String)],
+    ) -> Result<()> {
         let dir_str = dir.to_string_lossy();
 
-        for args in create_session_commands(name, &dir_str, split_command) {
-            let output = Command::new("tmux")
-                .args(&args)
-                .output()
+        for args in create_session_commands(name, &dir_str, split_command, env) {
+            let output = self
+                .run(self.tmux().args(&args))
                 .with_context(|| format!("failed to execute tmux {}", args.join(" ")))?;
             if !output.status.success() {
                 let stderr = String::from_utf8_lossy(&output.stderr);
                 bail!("tmux {} failed: {}", args.join(" "), stderr.trim());
             }
         }
+        self.server_started();
 
         Ok(())
     }
 
     fn capture_pane(&self, session: &str, lines: usize) -> Result<String> {
-        let target = format!("={session}:0.0");
-        let output = Command::new("tmux")
-            .args([
+        let target = pane_target(self.base_indices(), session, "0");
+        let output = self
+            .read(self.tmux().args([
                 "capture-pane",
                 "-t",
                 &target,
                 "-p",
                 "-S",
                 &format!("-{lines}"),
-            ])
-            .output()
+            ]))
             .with_context(|| {
                 format!("failed to execute tmux capture-pane for session {session}")
             })?;
@@ -99,20 +464,18 @@ impl TmuxProvider for CliTmuxProvider {
     }
 
     fn send_keys(&self, session: &str, keys: &str) -> Result<()> {
-        let target = format!("={session}:0.0");
+        let target = pane_target(self.base_indices(), session, "0");
         // Use -l (literal) so tmux doesn't interpret words like "Enter" or "Escape"
         // as special key names, then send Enter separately to submit.
-        let literal = Command::new("tmux")
-            .args(["send-keys", "-t", &target, "-l", keys])
-            .output()
+        let literal = self
+            .run(self.tmux().args(["send-keys", "-t", &target, "-l", keys]))
             .with_context(|| format!("failed to execute tmux send-keys for session {session}"))?;
         if !literal.status.success() {
             let stderr = String::from_utf8_lossy(&literal.stderr);
             bail!("tmux send-keys failed: {}", stderr.trim());
         }
-        let enter = Command::new("tmux")
-            .args(["send-keys", "-t", &target, "Enter"])
-            .output()
+        let enter = self
+            .run(self.tmux().args(["send-keys", "-t", &target, "Enter"]))
             .with_context(|| {
                 format!("failed to execute tmux send-keys Enter for session {session}")
             })?;
@@ -124,11 +487,11 @@ impl TmuxProvider for CliTmuxProvider {
     }
 
     fn send_keys_raw(&self, session: &str, pane: &str, keys: &[&str]) -> Result<()> {
-        let target = format!("={session}:0.{pane}");
+        let target = pane_target(self.base_indices(), session, pane);
         let mut args = vec!["send-keys", "-t", &target];
         args.extend(keys);
 
-        let output = Command::new("tmux").args(&args).output().with_context(|| {
+        let output = self.run(self.tmux().args(&args)).with_context(|| {
             format!("failed to execute tmux send-keys for session {session} pane {pane}")
         })?;
         if !output.status.success() {
@@ -139,10 +502,9 @@ impl TmuxProvider for CliTmuxProvider {
     }
 
     fn send_text_raw(&self, session: &str, pane: &str, text: &str) -> Result<()> {
-        let target = format!("={session}:0.{pane}");
-        let output = Command::new("tmux")
-            .args(["send-keys", "-t", &target, "-l", text])
-            .output()
+        let target = pane_target(self.base_indices(), session, pane);
+        let output = self
+            .run(self.tmux().args(["send-keys", "-t", &target, "-l", text]))
             .with_context(|| {
                 format!("failed to execute tmux send-keys for session {session} pane {pane}")
             })?;
@@ -153,18 +515,71 @@ impl TmuxProvider for CliTmuxProvider {
         Ok(())
     }
 
+    fn paste_text(&self, session: &str, pane: &str, text: &str) -> Result<()> {
+        let target = pane_target(self.base_indices(), session, pane);
+        // A per-process buffer name keeps concurrent sends from pasting each other's text.
+        // The text goes through stdin rather than argv, so its length isn't limited.
+        let buffer = format!("kiosk-send-{}", std::process::id());
+        let output = self
+            .policy()
+            .output_with_stdin(
+                self.tmux().args(["load-buffer", "-b", &buffer, "-"]),
+                text.as_bytes(),
+            )
+            .map_err(not_run)
+            .with_context(|| format!("failed to execute tmux load-buffer for session {session}"))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("tmux load-buffer failed: {}", stderr.trim());
+        }
+
+        // -p wraps the text in bracketed-paste markers when the program asked for them,
+        // -d deletes the buffer afterwards
+        let output = self
+            .run(
+                self.tmux()
+                    .args(["paste-buffer", "-p", "-d", "-b", &buffer, "-t", &target]),
+            )
+            .with_context(|| {
+                format!("failed to execute tmux paste-buffer for session {session} pane {pane}")
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("tmux paste-buffer failed: {}", stderr.trim());
+        }
+        Ok(())
+    }
+
     fn capture_pane_with_pane(&self, session: &str, pane: &str, lines: usize) -> Result<String> {
-        let target = format!("={session}:0.{pane}");
-        let output = Command::new("tmux")
-            .args([
+        let target = pane_target(self.base_indices(), session, pane);
+        let output = self
+            .read(self.tmux().args([
                 "capture-pane",
                 "-t",
                 &target,
                 "-p",
                 "-S",
                 &format!("-{lines}"),
-            ])
-            .output()
+            ]))
+            .with_context(|| {
+                format!("failed to execute tmux capture-pane for session {session} pane {pane}")
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("tmux capture-pane failed: {}", stderr.trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn capture_pane_range(
+        &self,
+        session: &str,
+        pane: &str,
+        range: &CaptureRange,
+    ) -> Result<String> {
+        let target = pane_target(self.base_indices(), session, pane);
+        let output = self
+            .read(self.tmux().args(capture_range_args(&target, range)))
             .with_context(|| {
                 format!("failed to execute tmux capture-pane for session {session} pane {pane}")
             })?;
@@ -176,16 +591,15 @@ impl TmuxProvider for CliTmuxProvider {
     }
 
     fn pane_current_command(&self, session: &str, pane: &str) -> Result<String> {
-        let target = format!("={session}:0.{pane}");
-        let output = Command::new("tmux")
-            .args([
+        let target = pane_target(self.base_indices(), session, pane);
+        let output = self
+            .read(self.tmux().args([
                 "display-message",
                 "-t",
                 &target,
                 "-p",
                 "#{pane_current_command}",
-            ])
-            .output()
+            ]))
             .with_context(|| {
                 format!("failed to execute tmux display-message for session {session} pane {pane}")
             })?;
@@ -197,15 +611,14 @@ impl TmuxProvider for CliTmuxProvider {
     }
 
     fn session_activity(&self, session: &str) -> Result<u64> {
-        let output = Command::new("tmux")
-            .args([
+        let output = self
+            .read(self.tmux().args([
                 "display-message",
                 "-t",
                 &format!("={session}"),
                 "-p",
                 "#{session_activity}",
-            ])
-            .output()
+            ]))
             .with_context(|| {
                 format!("failed to execute tmux display-message for session {session}")
             })?;
@@ -221,15 +634,14 @@ impl TmuxProvider for CliTmuxProvider {
     }
 
     fn pane_count(&self, session: &str) -> Result<usize> {
-        let output = Command::new("tmux")
-            .args([
+        let output = self
+            .read(self.tmux().args([
                 "list-panes",
                 "-t",
                 &format!("={session}"),
                 "-F",
                 "#{pane_index}",
-            ])
-            .output()
+            ]))
             .with_context(|| format!("failed to execute tmux list-panes for session {session}"))?;
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -240,13 +652,36 @@ impl TmuxProvider for CliTmuxProvider {
         Ok(pane_count)
     }
 
+    fn list_panes_detailed(&self, session: &str) -> Result<Vec<PaneDetails>> {
+        let output = self
+            .read(self.tmux().args([
+                "list-panes",
+                "-t",
+                &window_target(self.base_indices(), session),
+                "-F",
+                PANE_DETAILS_FORMAT,
+            ]))
+            .with_context(|| format!("failed to execute tmux list-panes for session {session}"))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("tmux list-panes failed: {}", stderr.trim());
+        }
+
+        Ok(parse_panes(
+            &String::from_utf8_lossy(&output.stdout),
+            self.base_indices(),
+        ))
+    }
+
     fn pipe_pane(&self, session: &str, log_path: &Path) -> Result<()> {
-        let target = format!("={session}:0.0");
+        let target = pane_target(self.base_indices(), session, "0");
         let escaped_path = log_path.to_string_lossy().replace('\'', "'\\''");
         let command = format!("cat >> '{escaped_path}'");
-        let output = Command::new("tmux")
-            .args(["pipe-pane", "-t", &target, "-o", &command])
-            .output()
+        let output = self
+            .run(
+                self.tmux()
+                    .args(["pipe-pane", "-t", &target, "-o", &command]),
+            )
             .with_context(|| format!("failed to execute tmux pipe-pane for session {session}"))?;
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -255,58 +690,210 @@ impl TmuxProvider for CliTmuxProvider {
         Ok(())
     }
 
+    fn rename_window(&self, session: &str, name: &str) -> Result<()> {
+        let target = window_target(self.base_indices(), session);
+        let output = self
+            .run(self.tmux().args(["rename-window", "-t", &target, name]))
+            .with_context(|| {
+                format!("failed to execute tmux rename-window for session {session}")
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("tmux rename-window failed: {}", stderr.trim());
+        }
+        Ok(())
+    }
+
+    fn set_pane_title(&self, session: &str, pane: &str, title: &str) -> Result<()> {
+        let target = pane_target(self.base_indices(), session, pane);
+        let output = self
+            .run(
+                self.tmux()
+                    .args(["select-pane", "-t", &target, "-T", title]),
+            )
+            .with_context(|| {
+                format!("failed to execute tmux select-pane for session {session} pane {pane}")
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("tmux select-pane failed: {}", stderr.trim());
+        }
+        Ok(())
+    }
+
+    fn kill_pane(&self, session: &str, pane: &str) -> Result<()> {
+        let target = pane_target(self.base_indices(), session, pane);
+        let output = self
+            .run(self.tmux().args(["kill-pane", "-t", &target]))
+            .with_context(|| {
+                format!("failed to execute tmux kill-pane for session {session} pane {pane}")
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("tmux kill-pane failed: {}", stderr.trim());
+        }
+        Ok(())
+    }
+
     fn list_clients(&self, session: &str) -> Vec<String> {
-        let output = Command::new("tmux")
-            .args([
-                "list-clients",
-                "-t",
-                &format!("={session}"),
-                "-F",
-                "#{client_tty}",
-            ])
-            .output();
+        let output = self.read(self.tmux().args([
+            "list-clients",
+            "-t",
+            &format!("={session}"),
+            "-F",
+            CLIENTS_FORMAT,
+        ]));
         let Ok(output) = output else {
             return Vec::new();
         };
         if !output.status.success() {
             return Vec::new();
         }
-        String::from_utf8_lossy(&output.stdout)
-            .lines()
-            .map(ToString::to_string)
+        parse_clients(&String::from_utf8_lossy(&output.stdout))
+            .into_iter()
+            .map(|(_, tty)| tty)
             .collect()
     }
 
     fn switch_to_session(&self, name: &str) {
         if self.is_inside_tmux() {
-            let _ = Command::new("tmux")
+            let _ = self
+                .tmux()
                 .args(["switch-client", "-t", &format!("={name}")])
-                .status();
+                .traced_status();
         } else {
-            let _ = Command::new("tmux")
+            let _ = self
+                .tmux()
                 .args(["attach-session", "-t", &format!("={name}")])
-                .status();
+                .traced_status();
         }
     }
 
     fn kill_session(&self, name: &str) {
-        let _ = Command::new("tmux")
+        let _ = self
+            .tmux()
             .args(["kill-session", "-t", &format!("={name}")])
-            .status();
+            .traced_status();
     }
 
     fn is_inside_tmux(&self) -> bool {
         std::env::var("TMUX").is_ok()
     }
+
+    fn is_available(&self) -> bool {
+        // The binary doesn't come or go while kiosk runs, and `multiplexer = "auto"` asks
+        // each time a provider is made
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(|| self.read(self.tmux().arg("-V")).is_ok())
+    }
+
+    fn apply_config(&self, config: &TmuxConfig) {
+        *self.policy.write().unwrap_or_else(PoisonError::into_inner) = command_policy(config);
+        let mut socket = self.socket.write().unwrap_or_else(PoisonError::into_inner);
+        if *socket != config.socket {
+            socket.clone_from(&config.socket);
+            *self.base.lock().unwrap_or_else(PoisonError::into_inner) = BaseLookup::Unknown;
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::create_session_commands;
+    use super::{
+        BaseIndices, CaptureRange, CliTmuxProvider, PaneDetails, SessionDetails, TmuxConfig,
+        TmuxProvider, capture_range_args, create_session_commands, pane_target, parse_clients,
+        parse_pane_details, parse_session_panes,
+    };
+
+    #[test]
+    fn test_capture_range_args_only_passes_given_bounds() {
+        assert_eq!(
+            capture_range_args("=demo:0.1", &CaptureRange::default()),
+            ["capture-pane", "-t", "=demo:0.1", "-p"]
+        );
+        assert_eq!(
+            capture_range_args(
+                "=demo:0.0",
+                &CaptureRange {
+                    start: Some(-500),
+                    end: Some(-1),
+                    escapes: true,
+                }
+            ),
+            [
+                "capture-pane",
+                "-t",
+                "=demo:0.0",
+                "-p",
+                "-e",
+                "-S",
+                "-500",
+                "-E",
+                "-1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_session_panes_groups_panes_by_session() {
+        // kiosk's own control session is left out
+        let stdout = "\
+1\t1\t1\t1700000000\t1\tclaude\tkiosk
+1\t1\t2\t1700000000\t1\tzsh\tkiosk
+0\t2\t1\t1700000000\t1\tnvim\tkiosk
+1\t1\t1\t1700000050\t2\tcat\t_kiosk_control
+0\t1\t1\t1700000100\t0\tzsh\tkiosk--feat
+1\t2\t1\t1700000100\t0\tcargo\tkiosk--feat
+";
+        assert_eq!(
+            parse_session_panes(stdout, BaseIndices { window: 1, pane: 1 }),
+            [
+                SessionDetails {
+                    name: "kiosk".to_string(),
+                    activity: 1_700_000_000,
+                    clients: 1,
+                    pane_count: 2,
+                    current_command: "claude".to_string(),
+                },
+                SessionDetails {
+                    name: "kiosk--feat".to_string(),
+                    activity: 1_700_000_100,
+                    clients: 0,
+                    pane_count: 1,
+                    current_command: "zsh".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_clients_leaves_out_control_clients() {
+        assert_eq!(
+            parse_clients("0\t/dev/pts/3\tkiosk\n1\t\tkiosk--feat\n"),
+            [("kiosk".to_string(), "/dev/pts/3".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_pane_details_keeps_tabs_in_title() {
+        assert_eq!(
+            parse_pane_details("1\tclaude\t4242\t1\t80\t24\tagent\tmain"),
+            Some(PaneDetails {
+                index: 1,
+                current_command: "claude".to_string(),
+                pid: 4242,
+                active: true,
+                width: 80,
+                height: 24,
+                title: "agent\tmain".to_string(),
+            })
+        );
+        assert_eq!(parse_pane_details("garbage"), None);
+    }
 
     #[test]
     fn test_create_session_commands_with_split_command_uses_split_window_command_arg() {
-        let commands = create_session_commands("demo", "/tmp/demo", Some("hx"));
+        let commands = create_session_commands("demo", "/tmp/demo", Some("hx"), &[]);
         assert_eq!(commands.len(), 2);
 
         assert_eq!(
@@ -325,7 +912,7 @@ mod tests {
                 "split-window".to_string(),
                 "-h".to_string(),
                 "-t".to_string(),
-                "=demo:0".to_string(),
+                "=demo:".to_string(),
                 "-c".to_string(),
                 "/tmp/demo".to_string(),
                 "hx".to_string(),
@@ -335,7 +922,51 @@ mod tests {
 
     #[test]
     fn test_create_session_commands_without_split_command() {
-        let commands = create_session_commands("demo", "/tmp/demo", None);
+        let commands = create_session_commands("demo", "/tmp/demo", None, &[]);
         assert_eq!(commands.len(), 1);
     }
+
+    #[test]
+    fn test_create_session_commands_set_env_on_new_session() {
+        let env = [
+            ("KIOSK_REPO".to_string(), "demo".to_string()),
+            ("RUST_LOG".to_string(), "debug".to_string()),
+        ];
+        let commands = create_session_commands("demo", "/tmp/demo", Some("hx"), &env);
+        assert_eq!(
+            commands[0][5..],
+            ["-e", "KIOSK_REPO=demo", "-e", "RUST_LOG=debug"]
+        );
+        assert!(!commands[1].contains(&"-e".to_string()));
+    }
+
+    #[test]
+    fn test_pane_target_follows_base_indices() {
+        assert_eq!(
+            pane_target(BaseIndices::default(), "demo", "0"),
+            "=demo:0.0"
+        );
+        let base = BaseIndices { window: 1, pane: 1 };
+        assert_eq!(pane_target(base, "demo", "0"), "=demo:1.1");
+        assert_eq!(pane_target(base, "demo", "2"), "=demo:1.3");
+    }
+
+    #[test]
+    fn test_reloaded_config_moves_commands_to_the_new_socket() {
+        let args = |provider: &CliTmuxProvider| {
+            provider
+                .tmux()
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        let provider = CliTmuxProvider::default();
+        assert!(args(&provider).is_empty());
+
+        provider.apply_config(&TmuxConfig {
+            socket: Some("kiosk-test".to_string()),
+            ..TmuxConfig::default()
+        });
+        assert_eq!(args(&provider), ["-L", "kiosk-test"]);
+    }
 }
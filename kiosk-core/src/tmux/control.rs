@@ -0,0 +1,619 @@
+//! A tmux provider that keeps one control-mode connection (`tmux -C`) to the server, so the
+//! queries kiosk makes most often don't each start a tmux process, and that hears about
+//! sessions coming and going, and their panes' output, as it happens. Everything else, and
+//! any query made while no connection can be opened, goes through [`CliTmuxProvider`].
+
+use super::{
+    cli::{
+        CLIENTS_FORMAT, CONTROL_SESSION, CliTmuxProvider, PANE_DETAILS_FORMAT,
+        SESSION_PANES_FORMAT, SESSIONS_FORMAT, pane_target, parse_clients, parse_panes,
+        parse_session_panes, parse_sessions, window_target,
+    },
+    provider::{CaptureRange, PaneDetails, SessionDetails, TmuxEvent, TmuxProvider},
+};
+use crate::config::TmuxConfig;
+use anyhow::{Context, Result, anyhow};
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        Arc, Mutex, PoisonError, Weak,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+/// How often a provider with subscribers checks that it's connected, so that it starts
+/// listening once a tmux server is running and again after the server restarts.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Name of the `refresh-client -B` subscription to every session's activity, which tmux
+/// re-evaluates each second and reports when any pane's output has moved it on.
+const ACTIVITY_SUBSCRIPTION: &str = "kiosk-activity";
+
+/// A command's output, or the error tmux gave for it.
+type Reply = std::result::Result<String, String>;
+
+type Subscribers = Arc<Mutex<Vec<Sender<TmuxEvent>>>>;
+
+pub struct ControlTmuxProvider {
+    cli: Arc<CliTmuxProvider>,
+    connection: Arc<Mutex<Option<Connection>>>,
+    subscribers: Subscribers,
+    /// Whether a thread is keeping the connection open for subscribers
+    watching: AtomicBool,
+}
+
+/// A control client attached to [`CONTROL_SESSION`], a detached session of its own.
+struct Connection {
+    child: Child,
+    stdin: ChildStdin,
+    replies: Receiver<Reply>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Connection {
+    /// Attach to [`CONTROL_SESSION`], creating it if no other kiosk has, as a client that
+    /// doesn't resize windows or receive its own session's output. The session closes once
+    /// no kiosk is attached to it.
+    fn open(
+        mut tmux: Command,
+        subscribers: &Subscribers,
+        timeout: Option<Duration>,
+    ) -> Result<Self> {
+        let mut child = tmux
+            .args([
+                "-C",
+                "new-session",
+                "-A",
+                "-s",
+                CONTROL_SESSION,
+                "-f",
+                "ignore-size,no-output",
+                // Rather than a shell, which would read the user's profile for nothing
+                "cat",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("failed to start a tmux control client")?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            return Err(anyhow!("failed to connect to the tmux control client"));
+        };
+        let (sender, replies) = mpsc::channel();
+        let subscribers = Arc::clone(subscribers);
+        thread::spawn(move || read_messages(BufReader::new(stdout), &sender, &subscribers));
+
+        let mut connection = Self {
+            child,
+            stdin,
+            replies,
+        };
+        // tmux answers the attach before anything else
+        connection
+            .reply(timeout)?
+            .map_err(|e| anyhow!("failed to attach a tmux control client: {e}"))?;
+        let activity =
+            format!("{ACTIVITY_SUBSCRIPTION}::#{{S:#{{session_name}}=#{{session_activity}},}}");
+        // Both apply to the session the client is attached to
+        for args in [
+            &["set-option", "destroy-unattached", "on"][..],
+            &["refresh-client", "-B", &activity],
+        ] {
+            connection
+                .run(args, timeout)?
+                .map_err(|e| anyhow!("failed to set up the tmux control client: {e}"))?;
+        }
+        Ok(connection)
+    }
+
+    /// Whether the control client is still running.
+    fn is_open(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    fn run(&mut self, args: &[&str], timeout: Option<Duration>) -> Result<Reply> {
+        writeln!(self.stdin, "{}", command_line(args))
+            .and_then(|()| self.stdin.flush())
+            .context("lost the tmux control client")?;
+        self.reply(timeout)
+    }
+
+    fn reply(&self, timeout: Option<Duration>) -> Result<Reply> {
+        let reply = match timeout {
+            Some(timeout) => self.replies.recv_timeout(timeout),
+            None => self
+                .replies
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        reply.map_err(|e| match e {
+            RecvTimeoutError::Timeout => anyhow!("the tmux control client stopped answering"),
+            RecvTimeoutError::Disconnected => anyhow!("lost the tmux control client"),
+        })
+    }
+}
+
+impl ControlTmuxProvider {
+    /// A provider that connects on first use, within `config`'s command timeout.
+    pub fn new(config: &TmuxConfig) -> Self {
+        Self {
+            cli: Arc::new(CliTmuxProvider::new(config)),
+            connection: Arc::default(),
+            subscribers: Arc::default(),
+            watching: AtomicBool::new(false),
+        }
+    }
+
+    /// Run a tmux command over the connection, opening one if there isn't one yet. The
+    /// connection ends with the server or if its session is killed, so a lost one is
+    /// replaced once. Fails only when no connection could be used; tmux's own errors are the
+    /// `Reply`.
+    fn query(&self, args: &[&str]) -> Result<Reply> {
+        let mut connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let reconnect = connection.is_some();
+        match self.run(&mut connection, args) {
+            Err(_) if reconnect => self.run(&mut connection, args),
+            reply => reply,
+        }
+    }
+
+    fn run(&self, connection: &mut Option<Connection>, args: &[&str]) -> Result<Reply> {
+        let open = match connection {
+            Some(open) => open,
+            None => connection.insert(connect(&self.cli, &self.subscribers)?),
+        };
+        let reply = open.run(args, self.cli.policy().timeout);
+        if reply.is_err() {
+            *connection = None;
+        }
+        reply
+    }
+
+    /// Keep a connection open for subscribers until the provider is dropped, checking every
+    /// [`RECONNECT_INTERVAL`].
+    fn keep_connected(&self) {
+        if self.watching.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let cli = Arc::clone(&self.cli);
+        let connection = Arc::downgrade(&self.connection);
+        let subscribers = Arc::clone(&self.subscribers);
+        thread::spawn(move || {
+            loop {
+                reconnect(&cli, &connection, &subscribers);
+                thread::sleep(RECONNECT_INTERVAL);
+                if connection.strong_count() == 0 {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// `parse` the reply to `args`, or ask `fallback` when there's no connection.
+    fn answer<T>(
+        &self,
+        args: &[&str],
+        parse: impl FnOnce(Reply) -> T,
+        fallback: impl FnOnce(&CliTmuxProvider) -> T,
+    ) -> T {
+        match self.query(args) {
+            Ok(reply) => parse(reply),
+            Err(e) => {
+                tracing::debug!("Running tmux {} without control mode: {e:#}", args[0]);
+                fallback(&self.cli)
+            }
+        }
+    }
+}
+
+/// Connect to the server, if one is running: connecting never starts one.
+fn connect(cli: &CliTmuxProvider, subscribers: &Subscribers) -> Result<Connection> {
+    if cli.server_started_at().is_none() {
+        return Err(anyhow!("no tmux server to attach a control client to"));
+    }
+    Connection::open(cli.tmux(), subscribers, cli.policy().timeout)
+}
+
+/// Open a connection in place of a missing or closed one, telling subscribers to look again
+/// when one opens, since they missed whatever happened while there wasn't one.
+fn reconnect(
+    cli: &CliTmuxProvider,
+    connection: &Weak<Mutex<Option<Connection>>>,
+    subscribers: &Subscribers,
+) {
+    let Some(connection) = connection.upgrade() else {
+        return;
+    };
+    let mut connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+    if connection.as_mut().is_some_and(Connection::is_open) {
+        return;
+    }
+    match connect(cli, subscribers) {
+        Ok(open) => {
+            *connection = Some(open);
+            notify(subscribers, TmuxEvent::SessionsChanged);
+        }
+        Err(e) => {
+            *connection = None;
+            tracing::debug!("No tmux control connection to listen on yet: {e:#}");
+        }
+    }
+}
+
+/// `args` as a line of tmux commands, each double-quoted so formats aren't read as comments.
+fn command_line(args: &[&str]) -> String {
+    args.iter()
+        .map(|arg| {
+            let escaped = arg
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('$', "\\$");
+            format!("\"{escaped}\"")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Read the control client's output until it exits: each command's reply, framed by
+/// `%begin` and `%end` or `%error`, goes to `replies`, and notifications to `subscribers`.
+fn read_messages(mut reader: impl BufRead, replies: &Sender<Reply>, subscribers: &Subscribers) {
+    // The command number from `%begin`, which its `%end` or `%error` repeats
+    let mut reply: Option<(String, Vec<String>)> = None;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\n', '\r']);
+        if let Some((number, body)) = &mut reply {
+            let mut guard = text.split(' ');
+            let end = guard.next();
+            if matches!(end, Some("%end" | "%error")) && guard.nth(1) == Some(number.as_str()) {
+                let body = body.join("\n");
+                let _ = replies.send(if end == Some("%end") {
+                    Ok(body)
+                } else {
+                    Err(body)
+                });
+                reply = None;
+            } else {
+                body.push(text.to_string());
+            }
+        } else if let Some(header) = text.strip_prefix("%begin ") {
+            let number = header.split(' ').nth(1).unwrap_or_default();
+            reply = Some((number.to_string(), Vec::new()));
+        } else if text == "%sessions-changed" || text.starts_with("%session-renamed ") {
+            notify(subscribers, TmuxEvent::SessionsChanged);
+        } else if text
+            .strip_prefix("%subscription-changed ")
+            .is_some_and(|rest| rest.split(' ').next() == Some(ACTIVITY_SUBSCRIPTION))
+        {
+            notify(subscribers, TmuxEvent::ActivityChanged);
+        }
+    }
+    // The server going away ends the connection too, so listeners should look again
+    notify(subscribers, TmuxEvent::SessionsChanged);
+}
+
+fn notify(subscribers: &Subscribers, event: TmuxEvent) {
+    subscribers
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .retain(|subscriber| subscriber.send(event).is_ok());
+}
+
+/// `reply`'s output, or `what` failing with tmux's error.
+fn output(reply: Reply, what: &str) -> Result<String> {
+    reply.map_err(|e| anyhow!("tmux {what} failed: {}", e.trim()))
+}
+
+impl TmuxProvider for ControlTmuxProvider {
+    fn list_sessions_with_activity(&self) -> Vec<(String, u64)> {
+        self.answer(
+            &["list-sessions", "-F", SESSIONS_FORMAT],
+            |reply| reply.map_or_else(|_| Vec::new(), |stdout| parse_sessions(&stdout)),
+            CliTmuxProvider::list_sessions_with_activity,
+        )
+    }
+
+    fn list_sessions_detailed(&self) -> Vec<SessionDetails> {
+        self.answer(
+            &["list-panes", "-a", "-F", SESSION_PANES_FORMAT],
+            |reply| {
+                reply.map_or_else(
+                    |_| Vec::new(),
+                    |stdout| parse_session_panes(&stdout, self.cli.base_indices()),
+                )
+            },
+            CliTmuxProvider::list_sessions_detailed,
+        )
+    }
+
+    fn session_exists(&self, name: &str) -> bool {
+        self.answer(
+            &["has-session", "-t", &format!("={name}")],
+            |reply| reply.is_ok(),
+            |cli| cli.session_exists(name),
+        )
+    }
+
+    fn current_session(&self) -> Option<String> {
+        self.cli.current_session()
+    }
+
+    fn session_path(&self, session: &str) -> Result<PathBuf> {
+        self.answer(
+            &[
+                "display-message",
+                "-p",
+                "-t",
+                &window_target(self.cli.base_indices(), session),
+                "#{session_path}",
+            ],
+            |reply| output(reply, "display-message").map(|path| PathBuf::from(path.trim())),
+            |cli| cli.session_path(session),
+        )
+    }
+
+    fn server_started_at(&self) -> Option<u64> {
+        self.cli.server_started_at()
+    }
+
+    fn create_session(
+        &self,
+        name: &str,
+        dir: &Path,
+        split_command: Option<&str>,
+        env: &[(String, String)],
+    ) -> Result<()> {
+        self.cli.create_session(name, dir, split_command, env)
+    }
+
+    fn capture_pane(&self, session: &str, lines: usize) -> Result<String> {
+        self.cli.capture_pane(session, lines)
+    }
+
+    fn capture_pane_with_pane(&self, session: &str, pane: &str, lines: usize) -> Result<String> {
+        self.cli.capture_pane_with_pane(session, pane, lines)
+    }
+
+    fn capture_pane_range(
+        &self,
+        session: &str,
+        pane: &str,
+        range: &CaptureRange,
+    ) -> Result<String> {
+        self.cli.capture_pane_range(session, pane, range)
+    }
+
+    fn pane_current_command(&self, session: &str, pane: &str) -> Result<String> {
+        self.answer(
+            &[
+                "display-message",
+                "-p",
+                "-t",
+                &pane_target(self.cli.base_indices(), session, pane),
+                "#{pane_current_command}",
+            ],
+            |reply| output(reply, "display-message").map(|command| command.trim().to_string()),
+            |cli| cli.pane_current_command(session, pane),
+        )
+    }
+
+    fn session_activity(&self, session: &str) -> Result<u64> {
+        self.answer(
+            &[
+                "display-message",
+                "-p",
+                "-t",
+                &window_target(self.cli.base_indices(), session),
+                "#{session_activity}",
+            ],
+            |reply| {
+                output(reply, "display-message")?
+                    .trim()
+                    .parse()
+                    .context("failed to parse session activity")
+            },
+            |cli| cli.session_activity(session),
+        )
+    }
+
+    fn pane_count(&self, session: &str) -> Result<usize> {
+        self.answer(
+            &[
+                "list-panes",
+                "-t",
+                &format!("={session}"),
+                "-F",
+                "#{pane_index}",
+            ],
+            |reply| output(reply, "list-panes").map(|panes| panes.lines().count()),
+            |cli| cli.pane_count(session),
+        )
+    }
+
+    fn list_panes_detailed(&self, session: &str) -> Result<Vec<PaneDetails>> {
+        self.answer(
+            &[
+                "list-panes",
+                "-t",
+                &window_target(self.cli.base_indices(), session),
+                "-F",
+                PANE_DETAILS_FORMAT,
+            ],
+            |reply| {
+                output(reply, "list-panes")
+                    .map(|panes| parse_panes(&panes, self.cli.base_indices()))
+            },
+            |cli| cli.list_panes_detailed(session),
+        )
+    }
+
+    fn send_keys(&self, session: &str, keys: &str) -> Result<()> {
+        self.cli.send_keys(session, keys)
+    }
+
+    fn send_keys_raw(&self, session: &str, pane: &str, keys: &[&str]) -> Result<()> {
+        self.cli.send_keys_raw(session, pane, keys)
+    }
+
+    fn send_text_raw(&self, session: &str, pane: &str, text: &str) -> Result<()> {
+        self.cli.send_text_raw(session, pane, text)
+    }
+
+    fn paste_text(&self, session: &str, pane: &str, text: &str) -> Result<()> {
+        self.cli.paste_text(session, pane, text)
+    }
+
+    fn pipe_pane(&self, session: &str, log_path: &Path) -> Result<()> {
+        self.cli.pipe_pane(session, log_path)
+    }
+
+    fn rename_window(&self, session: &str, name: &str) -> Result<()> {
+        self.cli.rename_window(session, name)
+    }
+
+    fn set_pane_title(&self, session: &str, pane: &str, title: &str) -> Result<()> {
+        self.cli.set_pane_title(session, pane, title)
+    }
+
+    fn kill_pane(&self, session: &str, pane: &str) -> Result<()> {
+        self.cli.kill_pane(session, pane)
+    }
+
+    fn list_clients(&self, session: &str) -> Vec<String> {
+        self.answer(
+            &[
+                "list-clients",
+                "-t",
+                &format!("={session}"),
+                "-F",
+                CLIENTS_FORMAT,
+            ],
+            |reply| {
+                reply.map_or_else(
+                    |_| Vec::new(),
+                    |stdout| {
+                        parse_clients(&stdout)
+                            .into_iter()
+                            .map(|(_, tty)| tty)
+                            .collect()
+                    },
+                )
+            },
+            |cli| cli.list_clients(session),
+        )
+    }
+
+    fn switch_to_session(&self, name: &str) {
+        self.cli.switch_to_session(name);
+    }
+
+    fn kill_session(&self, name: &str) {
+        self.cli.kill_session(name);
+    }
+
+    fn is_inside_tmux(&self) -> bool {
+        self.cli.is_inside_tmux()
+    }
+
+    fn is_available(&self) -> bool {
+        self.cli.is_available()
+    }
+
+    fn apply_config(&self, config: &TmuxConfig) {
+        self.cli.apply_config(config);
+        // The socket may have changed, so reconnect to whichever server it now names
+        *self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    fn subscribe(&self) -> Option<Receiver<TmuxEvent>> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(sender);
+        // Notifications only arrive while connected
+        self.keep_connected();
+        Some(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn quotes_arguments_for_the_command_line() {
+        assert_eq!(
+            command_line(&["display-message", "-t", "=o'brien", "#{a} \"$HOME\" \\"]),
+            r##""display-message" "-t" "=o'brien" "#{a} \"\$HOME\" \\""##
+        );
+    }
+
+    #[test]
+    fn reads_replies_and_notifications() {
+        let stdout = "\
+%begin 1 267 0
+%end 1 267 0
+%session-changed $0 demo
+%begin 1 272 1
+demo:1700000000
+%end 1 999 1 in a pane title
+%end 1 272 1
+%sessions-changed
+%subscription-changed kiosk-activity $1 - - - : demo=1700000001,
+%subscription-changed other $1 - - - : 1
+%begin 1 273 1
+can't find session: nope
+%error 1 273 1
+";
+        let (sender, replies) = mpsc::channel();
+        let (subscriber, events) = mpsc::channel();
+        let subscribers: Subscribers = Arc::new(Mutex::new(vec![subscriber]));
+
+        read_messages(Cursor::new(stdout), &sender, &subscribers);
+
+        assert_eq!(
+            replies.try_iter().collect::<Vec<_>>(),
+            [
+                Ok(String::new()),
+                Ok("demo:1700000000\n%end 1 999 1 in a pane title".to_string()),
+                Err("can't find session: nope".to_string()),
+            ]
+        );
+        // Once when sessions changed, once when a pane's output moved activity on, and once
+        // when the connection ended
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                TmuxEvent::SessionsChanged,
+                TmuxEvent::ActivityChanged,
+                TmuxEvent::SessionsChanged
+            ]
+        );
+    }
+}
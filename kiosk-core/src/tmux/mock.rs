@@ -1,25 +1,136 @@
-use super::provider::TmuxProvider;
+use super::provider::{CaptureRange, PaneDetails, TmuxProvider};
 use anyhow::Result;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
+/// In-memory [`TmuxProvider`]. Fields hold canned data; `*_result` fields inject a one-shot
+/// result for the next call and the remaining `Mutex<Vec<..>>` fields record calls.
 #[derive(Default)]
 pub struct MockTmuxProvider {
     pub sessions: Mutex<Vec<String>>,
     pub sessions_with_activity: Vec<(String, u64)>,
     pub inside_tmux: bool,
+    /// Reported by `current_session`
+    pub current_session: Option<String>,
+    /// Directory per session, reported by `session_path`
+    pub session_paths: HashMap<String, PathBuf>,
+    /// Reported by `server_started_at`
+    pub server_started_at: Option<u64>,
     pub killed_sessions: Mutex<Vec<String>>,
+    /// `session:pane` for each `kill_pane`
+    pub killed_panes: Mutex<Vec<String>>,
     pub created_sessions: Mutex<Vec<String>>,
+    /// Environment each created session was given, by session name
+    pub session_envs: Mutex<HashMap<String, Vec<(String, String)>>>,
     pub switched_sessions: Mutex<Vec<String>>,
     pub sent_keys: Mutex<Vec<(String, String)>>,
     pub piped_sessions: Mutex<Vec<(String, std::path::PathBuf)>>,
+    /// `(session, name)` for each `rename_window`
+    pub renamed_windows: Mutex<Vec<(String, String)>>,
+    /// `(session, pane, title)` for each `set_pane_title`
+    pub pane_titles: Mutex<Vec<(String, String, String)>>,
     pub clients: HashMap<String, Vec<String>>,
     pub capture_output: Mutex<String>,
     pub create_session_result: Mutex<Option<Result<()>>>,
     pub capture_pane_result: Mutex<Option<Result<String>>>,
     pub send_keys_result: Mutex<Option<Result<()>>>,
     pub pipe_pane_result: Mutex<Option<Result<()>>>,
+    /// Foreground command per session, reported by `pane_current_command` (default: `zsh`)
+    pub pane_commands: HashMap<String, String>,
+    /// Panes per session, reported by `list_panes_detailed` (default: a single pane 0 running
+    /// the session's `pane_commands` entry)
+    pub panes: HashMap<String, Vec<PaneDetails>>,
+    pub captured_ranges: Mutex<Vec<(String, CaptureRange)>>,
+    /// Simulate tmux not being installed
+    pub unavailable: bool,
+}
+
+impl MockTmuxProvider {
+    #[must_use]
+    pub fn with_session(self, name: &str) -> Self {
+        self.sessions.lock().unwrap().push(name.to_string());
+        self
+    }
+
+    /// Add a session whose pane is running `command` (e.g. an agent like `claude`).
+    #[must_use]
+    pub fn with_session_running(mut self, name: &str, command: &str) -> Self {
+        self.pane_commands
+            .insert(name.to_string(), command.to_string());
+        self.with_session(name)
+    }
+
+    /// Give `session` one pane per `(command, title)` pair, indexed from 0.
+    #[must_use]
+    pub fn with_panes(mut self, session: &str, panes: &[(&str, &str)]) -> Self {
+        let panes = panes
+            .iter()
+            .enumerate()
+            .map(|(index, (command, title))| PaneDetails {
+                index,
+                current_command: (*command).to_string(),
+                pid: 1000 + u32::try_from(index).unwrap_or_default(),
+                active: index == 0,
+                width: 80,
+                height: 24,
+                title: (*title).to_string(),
+            })
+            .collect();
+        self.panes.insert(session.to_string(), panes);
+        self
+    }
+
+    /// Add a session last active at `activity` (a unix timestamp). Once any session has an
+    /// activity, sessions added without one are no longer listed.
+    #[must_use]
+    pub fn with_session_activity(mut self, name: &str, activity: u64) -> Self {
+        self.sessions_with_activity
+            .push((name.to_string(), activity));
+        self.with_session(name)
+    }
+
+    /// Add a session whose directory is `path`.
+    #[must_use]
+    pub fn with_session_path(mut self, name: &str, path: impl Into<PathBuf>) -> Self {
+        self.session_paths.insert(name.to_string(), path.into());
+        self.with_session(name)
+    }
+
+    /// Pretend the caller is attached to `session`.
+    #[must_use]
+    pub fn attached_to(mut self, session: &str) -> Self {
+        self.current_session = Some(session.to_string());
+        self.inside_tmux()
+    }
+
+    /// Simulate tmux not being installed.
+    #[must_use]
+    pub fn unavailable(mut self) -> Self {
+        self.unavailable = true;
+        self
+    }
+
+    #[must_use]
+    pub fn with_client(mut self, session: &str, client: &str) -> Self {
+        self.clients
+            .entry(session.to_string())
+            .or_default()
+            .push(client.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn inside_tmux(mut self) -> Self {
+        self.inside_tmux = true;
+        self
+    }
+
+    #[must_use]
+    pub fn with_capture_output(self, output: &str) -> Self {
+        *self.capture_output.lock().unwrap() = output.to_string();
+        self
+    }
 }
 
 impl TmuxProvider for MockTmuxProvider {
@@ -44,13 +155,33 @@ impl TmuxProvider for MockTmuxProvider {
             .any(|session| session == name)
     }
 
+    fn current_session(&self) -> Option<String> {
+        self.current_session.clone()
+    }
+
+    fn session_path(&self, session: &str) -> anyhow::Result<PathBuf> {
+        self.session_paths
+            .get(session)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no session named '{session}'"))
+    }
+
+    fn server_started_at(&self) -> Option<u64> {
+        self.server_started_at
+    }
+
     fn create_session(
         &self,
         name: &str,
         _dir: &Path,
         _split_command: Option<&str>,
+        env: &[(String, String)],
     ) -> anyhow::Result<()> {
         self.created_sessions.lock().unwrap().push(name.to_string());
+        self.session_envs
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), env.to_vec());
         let mut sessions = self.sessions.lock().unwrap();
         if !sessions.iter().any(|session| session == name) {
             sessions.push(name.to_string());
@@ -94,6 +225,31 @@ impl TmuxProvider for MockTmuxProvider {
             .unwrap_or(Ok(()))
     }
 
+    fn rename_window(&self, session: &str, name: &str) -> anyhow::Result<()> {
+        self.renamed_windows
+            .lock()
+            .unwrap()
+            .push((session.to_string(), name.to_string()));
+        Ok(())
+    }
+
+    fn set_pane_title(&self, session: &str, pane: &str, title: &str) -> anyhow::Result<()> {
+        self.pane_titles.lock().unwrap().push((
+            session.to_string(),
+            pane.to_string(),
+            title.to_string(),
+        ));
+        Ok(())
+    }
+
+    fn kill_pane(&self, session: &str, pane: &str) -> anyhow::Result<()> {
+        self.killed_panes
+            .lock()
+            .unwrap()
+            .push(format!("{session}:{pane}"));
+        Ok(())
+    }
+
     fn list_clients(&self, session: &str) -> Vec<String> {
         self.clients.get(session).cloned().unwrap_or_default()
     }
@@ -113,6 +269,10 @@ impl TmuxProvider for MockTmuxProvider {
         self.inside_tmux
     }
 
+    fn is_available(&self) -> bool {
+        !self.unavailable
+    }
+
     fn send_keys_raw(&self, session: &str, pane: &str, keys: &[&str]) -> anyhow::Result<()> {
         self.sent_keys
             .lock()
@@ -129,6 +289,14 @@ impl TmuxProvider for MockTmuxProvider {
         Ok(())
     }
 
+    fn paste_text(&self, session: &str, pane: &str, text: &str) -> anyhow::Result<()> {
+        self.sent_keys
+            .lock()
+            .unwrap()
+            .push((format!("{session}:{pane}:paste"), text.to_string()));
+        Ok(())
+    }
+
     fn capture_pane_with_pane(
         &self,
         _session: &str,
@@ -138,15 +306,58 @@ impl TmuxProvider for MockTmuxProvider {
         Ok(self.capture_output.lock().unwrap().clone())
     }
 
-    fn pane_current_command(&self, _session: &str, _pane: &str) -> anyhow::Result<String> {
-        Ok("zsh".to_string())
+    fn capture_pane_range(
+        &self,
+        session: &str,
+        pane: &str,
+        range: &CaptureRange,
+    ) -> anyhow::Result<String> {
+        self.captured_ranges
+            .lock()
+            .unwrap()
+            .push((format!("{session}:{pane}"), range.clone()));
+        Ok(self.capture_output.lock().unwrap().clone())
     }
 
-    fn session_activity(&self, _session: &str) -> anyhow::Result<u64> {
-        Ok(1_234_567_890)
+    fn pane_current_command(&self, session: &str, pane: &str) -> anyhow::Result<String> {
+        if let Some(details) = self
+            .panes
+            .get(session)
+            .and_then(|panes| panes.iter().find(|p| p.index.to_string() == pane))
+        {
+            return Ok(details.current_command.clone());
+        }
+        Ok(self
+            .pane_commands
+            .get(session)
+            .cloned()
+            .unwrap_or_else(|| "zsh".to_string()))
+    }
+
+    fn session_activity(&self, session: &str) -> anyhow::Result<u64> {
+        Ok(self
+            .sessions_with_activity
+            .iter()
+            .find(|(name, _)| name == session)
+            .map_or(1_234_567_890, |(_, activity)| *activity))
     }
 
-    fn pane_count(&self, _session: &str) -> anyhow::Result<usize> {
-        Ok(1)
+    fn pane_count(&self, session: &str) -> anyhow::Result<usize> {
+        Ok(self.panes.get(session).map_or(1, Vec::len))
+    }
+
+    fn list_panes_detailed(&self, session: &str) -> anyhow::Result<Vec<PaneDetails>> {
+        if let Some(panes) = self.panes.get(session) {
+            return Ok(panes.clone());
+        }
+        Ok(vec![PaneDetails {
+            index: 0,
+            current_command: self.pane_current_command(session, "0")?,
+            pid: 1000,
+            active: true,
+            width: 80,
+            height: 24,
+            title: String::new(),
+        }])
     }
 }
@@ -1,6 +1,42 @@
 pub mod cli;
+pub mod control;
+#[cfg(any(test, feature = "testing"))]
 pub mod mock;
 pub mod provider;
+pub mod wezterm;
 
 pub use cli::CliTmuxProvider;
-pub use provider::TmuxProvider;
+pub use control::ControlTmuxProvider;
+pub use provider::{
+    CaptureRange, PaneDetails, SessionDetails, TmuxEvent, TmuxNotRun, TmuxProvider,
+};
+pub use wezterm::WeztermProvider;
+
+use crate::config::{Multiplexer, TmuxConfig};
+use std::sync::Arc;
+
+/// The session provider for the configured multiplexer. `Auto` picks tmux when it can be
+/// run, then WezTerm, and falls back to tmux (whose errors explain what's missing); whether
+/// each can be run is only checked once per process. tmux is
+/// reached over a control-mode connection when `tmux.control_mode` is set.
+pub fn provider_for(multiplexer: Multiplexer, tmux: &TmuxConfig) -> Arc<dyn TmuxProvider> {
+    let tmux_provider = || -> Arc<dyn TmuxProvider> {
+        if tmux.control_mode {
+            Arc::new(ControlTmuxProvider::new(tmux))
+        } else {
+            Arc::new(CliTmuxProvider::new(tmux))
+        }
+    };
+    match multiplexer {
+        Multiplexer::Tmux => tmux_provider(),
+        Multiplexer::Wezterm => Arc::new(WeztermProvider::default()),
+        Multiplexer::Auto => {
+            let wezterm = WeztermProvider::default();
+            if !CliTmuxProvider::new(tmux).is_available() && wezterm.is_available() {
+                Arc::new(wezterm)
+            } else {
+                tmux_provider()
+            }
+        }
+    }
+}
@@ -1,4 +1,74 @@
-use std::path::Path;
+use crate::config::TmuxConfig;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+};
+
+/// One pane of a session's first window, as reported by `tmux list-panes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaneDetails {
+    pub index: usize,
+    pub current_command: String,
+    pub pid: u32,
+    pub active: bool,
+    pub width: u32,
+    pub height: u32,
+    /// Pane title, set with `select-pane -T` or by the program via escape sequences
+    pub title: String,
+}
+
+/// A session with what `kiosk sessions` shows about it, as reported by
+/// `list_sessions_detailed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionDetails {
+    pub name: String,
+    /// Last activity (unix seconds)
+    pub activity: u64,
+    /// Clients attached to the session
+    pub clients: usize,
+    /// Panes in the session's current window
+    pub pane_count: usize,
+    /// Foreground command of the first pane of the session's first window
+    pub current_command: String,
+}
+
+/// tmux couldn't be started at all, so unlike a command that ran and failed there's no exit
+/// status to report.
+#[derive(Debug)]
+pub struct TmuxNotRun(pub std::io::Error);
+
+impl std::fmt::Display for TmuxNotRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "tmux could not be run: {}", self.0)
+    }
+}
+
+impl std::error::Error for TmuxNotRun {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// A change to the server reported by `subscribe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TmuxEvent {
+    /// A session was created, closed or renamed
+    SessionsChanged,
+    /// Output from a session's panes moved its activity on
+    ActivityChanged,
+}
+
+/// Which lines `capture_pane_range` returns, as `tmux capture-pane` line offsets: 0 is the
+/// first visible line and negative offsets reach back into the scrollback.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CaptureRange {
+    /// First line (default: the first visible line)
+    pub start: Option<i64>,
+    /// Last line (default: the last visible line)
+    pub end: Option<i64>,
+    /// Keep ANSI escape sequences for text and background attributes
+    pub escapes: bool,
+}
 
 pub trait TmuxProvider: Send + Sync {
     /// List sessions with their last activity timestamp (`session_name`, `unix_timestamp`)
@@ -10,12 +80,38 @@ pub trait TmuxProvider: Send + Sync {
             .map(|(name, _)| name)
             .collect()
     }
+    /// Every session with its activity, clients, panes and current command. Providers that
+    /// can should answer this in one query rather than several per session.
+    fn list_sessions_detailed(&self) -> Vec<SessionDetails> {
+        self.list_sessions_with_activity()
+            .into_iter()
+            .map(|(name, activity)| SessionDetails {
+                activity,
+                clients: self.list_clients(&name).len(),
+                pane_count: self.pane_count(&name).unwrap_or(1),
+                current_command: self
+                    .pane_current_command(&name, "0")
+                    .unwrap_or_else(|_| "unknown".to_string()),
+                name,
+            })
+            .collect()
+    }
     fn session_exists(&self, name: &str) -> bool;
+    /// The session this process runs in, when it runs inside one.
+    fn current_session(&self) -> Option<String>;
+    /// The directory `session` was created in.
+    fn session_path(&self, session: &str) -> anyhow::Result<PathBuf>;
+    /// When the server the sessions live in started (unix seconds), or `None` when no server
+    /// is running or the multiplexer doesn't say.
+    fn server_started_at(&self) -> Option<u64>;
+    /// Create a detached session in `dir`, with `env` set in its environment before the
+    /// first shell starts.
     fn create_session(
         &self,
         name: &str,
         dir: &Path,
         split_command: Option<&str>,
+        env: &[(String, String)],
     ) -> anyhow::Result<()>;
     fn capture_pane(&self, session: &str, lines: usize) -> anyhow::Result<String>;
     /// Capture pane output for a specific pane.
@@ -25,12 +121,21 @@ pub trait TmuxProvider: Send + Sync {
         pane: &str,
         lines: usize,
     ) -> anyhow::Result<String>;
+    /// Capture a range of lines from a specific pane, scrollback included.
+    fn capture_pane_range(
+        &self,
+        session: &str,
+        pane: &str,
+        range: &CaptureRange,
+    ) -> anyhow::Result<String>;
     /// Get the current command running in a specific pane.
     fn pane_current_command(&self, session: &str, pane: &str) -> anyhow::Result<String>;
     /// Get session activity timestamp.
     fn session_activity(&self, session: &str) -> anyhow::Result<u64>;
     /// Get pane count for a session.
     fn pane_count(&self, session: &str) -> anyhow::Result<usize>;
+    /// List the panes of the session's first window, in index order.
+    fn list_panes_detailed(&self, session: &str) -> anyhow::Result<Vec<PaneDetails>>;
     /// Send keys to the target session's primary pane.
     ///
     /// Implementations always append `Enter` after the supplied keys to execute
@@ -40,9 +145,31 @@ pub trait TmuxProvider: Send + Sync {
     fn send_keys_raw(&self, session: &str, pane: &str, keys: &[&str]) -> anyhow::Result<()>;
     /// Send literal text to the target pane WITHOUT auto-appending Enter.
     fn send_text_raw(&self, session: &str, pane: &str, text: &str) -> anyhow::Result<()>;
+    /// Paste `text` into the target pane as a single bracketed paste, WITHOUT auto-appending
+    /// Enter. Suited to long or multi-line input, which programs then receive as one block.
+    fn paste_text(&self, session: &str, pane: &str, text: &str) -> anyhow::Result<()>;
     fn pipe_pane(&self, session: &str, log_path: &Path) -> anyhow::Result<()>;
+    /// Name the session's first window, which stops tmux renaming it after the running command.
+    fn rename_window(&self, session: &str, name: &str) -> anyhow::Result<()>;
+    /// Set the title of a pane of the session's first window.
+    fn set_pane_title(&self, session: &str, pane: &str, title: &str) -> anyhow::Result<()>;
+    /// Close a pane of the session's first window, ending what runs in it. Closing the last
+    /// pane ends the session.
+    fn kill_pane(&self, session: &str, pane: &str) -> anyhow::Result<()>;
     fn list_clients(&self, session: &str) -> Vec<String>;
     fn switch_to_session(&self, name: &str);
     fn kill_session(&self, name: &str);
     fn is_inside_tmux(&self) -> bool;
+    /// Whether the tmux binary can be run at all.
+    fn is_available(&self) -> bool {
+        true
+    }
+    /// Changes to the server as they happen, for providers that hear about them. `None`
+    /// means callers have to poll.
+    fn subscribe(&self) -> Option<Receiver<TmuxEvent>> {
+        None
+    }
+    /// Take up a reloaded config's `[tmux]` settings, such as the command timeout. Providers
+    /// with nothing to configure ignore it.
+    fn apply_config(&self, _config: &TmuxConfig) {}
 }
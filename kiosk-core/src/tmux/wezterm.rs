@@ -0,0 +1,655 @@
+use super::provider::{CaptureRange, PaneDetails, TmuxProvider};
+use crate::{
+    paths::state_dir, pending_delete::now_unix_secs, process::CommandExt,
+    process_tree::command_name, state_store::update_state_file,
+};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+const SCREENS_FILE_NAME: &str = "wezterm_screens.toml";
+const SCREENS_STATE_VERSION: u32 = 1;
+
+/// Runs sessions in WezTerm through `wezterm cli`, for platforms without tmux such as
+/// native Windows. Each kiosk session is a WezTerm workspace; its first tab stands in for
+/// tmux's first window, so pane indices count the panes of that tab in creation order.
+///
+/// WezTerm doesn't track session activity, so a session's activity is when kiosk last saw
+/// its screen change, which costs a `get-text` per pane. Panes' shells and foreground
+/// commands come from `ps` on their terminal; where WezTerm doesn't report one, as on
+/// Windows, panes report their title and a pid of 0 instead. `wezterm cli spawn` can't set
+/// environment variables, so sessions don't get the ones from `[session.env]`. Nor does it
+/// report when its mux server started, so `kiosk resume` can't tell a closed session from
+/// one the server lost.
+pub struct WeztermProvider {
+    screens_path: PathBuf,
+}
+
+impl Default for WeztermProvider {
+    fn default() -> Self {
+        Self {
+            screens_path: state_dir().join(SCREENS_FILE_NAME),
+        }
+    }
+}
+
+/// What a session's screen looked like when kiosk last looked, and when it last changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SeenScreen {
+    session: String,
+    /// Hash of the text of the session's panes, in hex
+    hash: String,
+    changed_at_unix_secs: u64,
+}
+
+/// One entry of `wezterm cli list --format json`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct WeztermPane {
+    tab_id: u64,
+    pane_id: u64,
+    workspace: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    is_active: bool,
+    size: WeztermSize,
+    /// A `file://host/path` URL, when the shell reports its directory
+    #[serde(default)]
+    cwd: String,
+    /// The pane's terminal device, where there is one (not on Windows)
+    #[serde(default)]
+    tty_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct WeztermSize {
+    rows: u32,
+    cols: u32,
+}
+
+/// One entry of `wezterm cli list-clients --format json`.
+#[derive(Debug, Deserialize)]
+struct WeztermClient {
+    hostname: String,
+    pid: u32,
+    workspace: String,
+}
+
+fn parse_panes(json: &str) -> Result<Vec<WeztermPane>> {
+    serde_json::from_str(json).context("failed to parse wezterm cli list output")
+}
+
+/// The panes of the first tab of `workspace`, in creation order.
+fn session_panes(panes: &[WeztermPane], workspace: &str) -> Vec<WeztermPane> {
+    let Some(first_tab) = panes
+        .iter()
+        .filter(|pane| pane.workspace == workspace)
+        .map(|pane| pane.tab_id)
+        .min()
+    else {
+        return Vec::new();
+    };
+    let mut tab: Vec<WeztermPane> = panes
+        .iter()
+        .filter(|pane| pane.workspace == workspace && pane.tab_id == first_tab)
+        .cloned()
+        .collect();
+    tab.sort_by_key(|pane| pane.pane_id);
+    tab
+}
+
+/// The processes on a pane's terminal that tmux reports for its panes.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TtyProcesses {
+    /// The session leader, normally the pane's shell
+    shell: Option<u32>,
+    /// Name of the foreground process group's leader, like `pane_current_command`
+    foreground: Option<String>,
+}
+
+/// Parse `ps -o pid=,pgid=,tpgid=,stat=,args=` output for one terminal.
+fn parse_tty_processes(output: &str) -> TtyProcesses {
+    let mut processes = TtyProcesses::default();
+    for line in output.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(pid), Some(group), Some(foreground_group), Some(stat)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let Ok(pid) = pid.parse::<u32>() else {
+            continue;
+        };
+        if stat.contains('s') {
+            processes.shell.get_or_insert(pid);
+        }
+        if group == foreground_group && group == pid.to_string() {
+            let args = fields.collect::<Vec<_>>().join(" ");
+            processes.foreground = Some(command_name(&args));
+        }
+    }
+    processes
+}
+
+fn tty_processes(tty: &str) -> Result<TtyProcesses> {
+    let output = Command::new("ps")
+        .args(["-t", tty, "-o", "pid=,pgid=,tpgid=,stat=,args="])
+        .traced_output()
+        .context("failed to run ps")?;
+    // ps exits non-zero when nothing runs on the terminal
+    Ok(parse_tty_processes(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Hash the text of `panes`, to tell when a session's screen changes.
+fn screen_hash(panes: &[WeztermPane]) -> Result<String> {
+    let mut hasher = DefaultHasher::new();
+    for pane in panes {
+        wezterm_cli(get_text_args(pane.pane_id, &CaptureRange::default()))?.hash(&mut hasher);
+    }
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+/// Record `hash` as `session`'s screen at `now`, returning when the screen last changed. A
+/// session seen for the first time counts as having just changed.
+fn observe_screen(seen: &mut Vec<SeenScreen>, session: &str, hash: String, now: u64) -> u64 {
+    if let Some(screen) = seen.iter_mut().find(|screen| screen.session == session) {
+        if screen.hash != hash {
+            screen.hash = hash;
+            screen.changed_at_unix_secs = now;
+        }
+        return screen.changed_at_unix_secs;
+    }
+    seen.push(SeenScreen {
+        session: session.to_string(),
+        hash,
+        changed_at_unix_secs: now,
+    });
+    now
+}
+
+/// Bytes to send for a tmux key name such as `Enter`, `Escape` or `C-c`. Names tmux
+/// wouldn't recognise either are sent as literal text.
+fn key_text(key: &str) -> String {
+    let text = match key {
+        "Enter" => "\r",
+        "Escape" => "\x1b",
+        "Tab" => "\t",
+        "BSpace" => "\x7f",
+        "Space" => " ",
+        "Up" => "\x1b[A",
+        "Down" => "\x1b[B",
+        "Right" => "\x1b[C",
+        "Left" => "\x1b[D",
+        _ => {
+            if let Some(letter) = key.strip_prefix("C-")
+                && let [byte] = letter.as_bytes()
+                && byte.is_ascii_alphabetic()
+            {
+                return char::from(byte.to_ascii_lowercase() & 0x1f).to_string();
+            }
+            key
+        }
+    };
+    text.to_string()
+}
+
+fn get_text_args(pane_id: u64, range: &CaptureRange) -> Vec<String> {
+    let mut args = vec![
+        "get-text".to_string(),
+        "--pane-id".to_string(),
+        pane_id.to_string(),
+    ];
+    if range.escapes {
+        args.push("--escapes".to_string());
+    }
+    if let Some(start) = range.start {
+        args.extend(["--start-line".to_string(), start.to_string()]);
+    }
+    if let Some(end) = range.end {
+        args.extend(["--end-line".to_string(), end.to_string()]);
+    }
+    args
+}
+
+/// The path of a `file://host/path` URL. Windows paths come as `/C:/...`.
+fn path_from_cwd_url(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    let path = &rest[rest.find('/')?..];
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => &path[1..],
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+fn list_panes() -> Result<Vec<WeztermPane>> {
+    parse_panes(&wezterm_cli(["list", "--format", "json"])?)
+}
+
+fn wezterm_cli<I, S>(args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    let args: Vec<S> = args.into_iter().collect();
+    let description = args
+        .iter()
+        .map(|arg| arg.as_ref().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let output = Command::new("wezterm")
+        .arg("cli")
+        .args(&args)
+        .traced_output()
+        .with_context(|| format!("failed to execute wezterm cli {description}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("wezterm cli {description} failed: {}", stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The WezTerm pane id of pane `pane` (an index) in `session`.
+fn pane_id(session: &str, pane: &str) -> Result<u64> {
+    let index: usize = pane
+        .parse()
+        .with_context(|| format!("invalid pane index '{pane}'"))?;
+    let panes = session_panes(&list_panes()?, session);
+    if panes.is_empty() {
+        bail!("no wezterm workspace named '{session}'");
+    }
+    panes
+        .get(index)
+        .map(|pane| pane.pane_id)
+        .with_context(|| format!("no pane {index} in session '{session}'"))
+}
+
+fn send_text(session: &str, pane: &str, text: &str, paste: bool) -> Result<()> {
+    let pane_id = pane_id(session, pane)?.to_string();
+    let mut args = vec!["send-text", "--pane-id", &pane_id];
+    if !paste {
+        args.push("--no-paste");
+    }
+    args.extend(["--", text]);
+    wezterm_cli(args).map(|_| ())
+}
+
+impl WeztermProvider {
+    /// When each of `sessions` last changed, remembering their screens for next time.
+    /// Sessions missing from `sessions` are forgotten when `all` is set.
+    fn screen_activity(
+        &self,
+        sessions: &[(String, Vec<WeztermPane>)],
+        all: bool,
+    ) -> Result<Vec<(String, u64)>> {
+        let hashes = sessions
+            .iter()
+            .map(|(name, panes)| Ok((name.as_str(), screen_hash(panes)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let now = now_unix_secs();
+        update_state_file(&self.screens_path, SCREENS_STATE_VERSION, |seen| {
+            if all {
+                seen.retain(|screen: &SeenScreen| {
+                    hashes.iter().any(|(name, _)| *name == screen.session)
+                });
+            }
+            hashes
+                .into_iter()
+                .map(|(name, hash)| (name.to_string(), observe_screen(seen, name, hash, now)))
+                .collect()
+        })
+    }
+}
+
+impl TmuxProvider for WeztermProvider {
+    fn list_sessions_with_activity(&self) -> Vec<(String, u64)> {
+        let Ok(panes) = list_panes() else {
+            return Vec::new();
+        };
+        let mut workspaces: Vec<String> = panes.iter().map(|pane| pane.workspace.clone()).collect();
+        workspaces.sort();
+        workspaces.dedup();
+        let sessions: Vec<(String, Vec<WeztermPane>)> = workspaces
+            .into_iter()
+            .map(|name| {
+                let tab = session_panes(&panes, &name);
+                (name, tab)
+            })
+            .collect();
+        self.screen_activity(&sessions, true).unwrap_or_else(|e| {
+            tracing::warn!("Failed to read WezTerm sessions' screens: {e:#}");
+            sessions.into_iter().map(|(name, _)| (name, 0)).collect()
+        })
+    }
+
+    fn session_exists(&self, name: &str) -> bool {
+        list_panes().is_ok_and(|panes| panes.iter().any(|pane| pane.workspace == name))
+    }
+
+    fn current_session(&self) -> Option<String> {
+        let pane_id: u64 = std::env::var("WEZTERM_PANE").ok()?.parse().ok()?;
+        list_panes()
+            .ok()?
+            .into_iter()
+            .find(|pane| pane.pane_id == pane_id)
+            .map(|pane| pane.workspace)
+    }
+
+    fn server_started_at(&self) -> Option<u64> {
+        None
+    }
+
+    fn session_path(&self, session: &str) -> Result<PathBuf> {
+        let panes = session_panes(&list_panes()?, session);
+        let pane = panes
+            .first()
+            .with_context(|| format!("no wezterm workspace named '{session}'"))?;
+        path_from_cwd_url(&pane.cwd)
+            .with_context(|| format!("wezterm doesn't know the directory of '{session}'"))
+    }
+
+    fn create_session(
+        &self,
+        name: &str,
+        dir: &Path,
+        split_command: Option<&str>,
+        _env: &[(String, String)],
+    ) -> Result<()> {
+        let dir = dir.as_os_str();
+        let pane_id = wezterm_cli([
+            "spawn".as_ref(),
+            "--new-window".as_ref(),
+            "--workspace".as_ref(),
+            name.as_ref(),
+            "--cwd".as_ref(),
+            dir,
+        ])?;
+        if let Some(cmd) = split_command.filter(|cmd| !cmd.trim().is_empty()) {
+            // The command is typed into the new pane's shell rather than passed as the
+            // pane's program, so it goes through the same shell parsing as under tmux
+            let split_id = wezterm_cli([
+                "split-pane".as_ref(),
+                "--pane-id".as_ref(),
+                pane_id.trim().as_ref(),
+                "--right".as_ref(),
+                "--cwd".as_ref(),
+                dir,
+            ])?;
+            wezterm_cli([
+                "send-text",
+                "--pane-id",
+                split_id.trim(),
+                "--no-paste",
+                "--",
+                &format!("{cmd}\r"),
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn capture_pane(&self, session: &str, lines: usize) -> Result<String> {
+        self.capture_pane_with_pane(session, "0", lines)
+    }
+
+    fn capture_pane_with_pane(&self, session: &str, pane: &str, lines: usize) -> Result<String> {
+        let start = -i64::try_from(lines).unwrap_or(i64::MAX);
+        self.capture_pane_range(
+            session,
+            pane,
+            &CaptureRange {
+                start: Some(start),
+                ..CaptureRange::default()
+            },
+        )
+    }
+
+    fn capture_pane_range(
+        &self,
+        session: &str,
+        pane: &str,
+        range: &CaptureRange,
+    ) -> Result<String> {
+        let pane_id = pane_id(session, pane)?;
+        wezterm_cli(get_text_args(pane_id, range))
+    }
+
+    fn pane_current_command(&self, session: &str, pane: &str) -> Result<String> {
+        let index: usize = pane
+            .parse()
+            .with_context(|| format!("invalid pane index '{pane}'"))?;
+        self.list_panes_detailed(session)?
+            .into_iter()
+            .find(|details| details.index == index)
+            .map(|details| details.current_command)
+            .with_context(|| format!("no pane {index} in session '{session}'"))
+    }
+
+    fn session_activity(&self, session: &str) -> Result<u64> {
+        let panes = session_panes(&list_panes()?, session);
+        if panes.is_empty() {
+            bail!("no wezterm workspace named '{session}'");
+        }
+        let activity = self.screen_activity(&[(session.to_string(), panes)], false)?;
+        Ok(activity.first().map_or(0, |(_, changed_at)| *changed_at))
+    }
+
+    fn pane_count(&self, session: &str) -> Result<usize> {
+        Ok(self.list_panes_detailed(session)?.len())
+    }
+
+    fn list_panes_detailed(&self, session: &str) -> Result<Vec<PaneDetails>> {
+        let panes = session_panes(&list_panes()?, session);
+        if panes.is_empty() {
+            bail!("no wezterm workspace named '{session}'");
+        }
+        Ok(panes
+            .into_iter()
+            .enumerate()
+            .map(|(index, pane)| {
+                let processes = pane
+                    .tty_name
+                    .as_deref()
+                    .and_then(|tty| tty_processes(tty).ok())
+                    .unwrap_or_default();
+                PaneDetails {
+                    index,
+                    current_command: processes.foreground.unwrap_or_else(|| pane.title.clone()),
+                    pid: processes.shell.unwrap_or(0),
+                    active: pane.is_active,
+                    width: pane.size.cols,
+                    height: pane.size.rows,
+                    title: pane.title,
+                }
+            })
+            .collect())
+    }
+
+    fn send_keys(&self, session: &str, keys: &str) -> Result<()> {
+        send_text(session, "0", &format!("{keys}\r"), false)
+    }
+
+    fn send_keys_raw(&self, session: &str, pane: &str, keys: &[&str]) -> Result<()> {
+        let text: String = keys.iter().map(|key| key_text(key)).collect();
+        send_text(session, pane, &text, false)
+    }
+
+    fn send_text_raw(&self, session: &str, pane: &str, text: &str) -> Result<()> {
+        send_text(session, pane, text, false)
+    }
+
+    fn paste_text(&self, session: &str, pane: &str, text: &str) -> Result<()> {
+        send_text(session, pane, text, true)
+    }
+
+    fn pipe_pane(&self, _session: &str, _log_path: &Path) -> Result<()> {
+        bail!("session logs need tmux; WezTerm has no equivalent of pipe-pane")
+    }
+
+    fn rename_window(&self, session: &str, name: &str) -> Result<()> {
+        let pane_id = pane_id(session, "0")?.to_string();
+        wezterm_cli(["set-tab-title", "--pane-id", &pane_id, name]).map(|_| ())
+    }
+
+    fn set_pane_title(&self, _session: &str, _pane: &str, _title: &str) -> Result<()> {
+        // WezTerm takes pane titles from the program running in them, with no way to set one
+        Ok(())
+    }
+
+    fn kill_pane(&self, session: &str, pane: &str) -> Result<()> {
+        let pane_id = pane_id(session, pane)?.to_string();
+        wezterm_cli(["kill-pane", "--pane-id", &pane_id]).map(|_| ())
+    }
+
+    fn list_clients(&self, session: &str) -> Vec<String> {
+        let Ok(output) = wezterm_cli(["list-clients", "--format", "json"]) else {
+            return Vec::new();
+        };
+        serde_json::from_str::<Vec<WeztermClient>>(&output)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|client| client.workspace == session)
+            .map(|client| format!("{}:{}", client.hostname, client.pid))
+            .collect()
+    }
+
+    fn switch_to_session(&self, name: &str) {
+        if let Ok(pane_id) = pane_id(name, "0") {
+            let _ = wezterm_cli(["activate-pane", "--pane-id", &pane_id.to_string()]);
+        }
+    }
+
+    fn kill_session(&self, name: &str) {
+        let Ok(panes) = list_panes() else {
+            return;
+        };
+        for pane in panes.iter().filter(|pane| pane.workspace == name) {
+            let _ = wezterm_cli(["kill-pane", "--pane-id", &pane.pane_id.to_string()]);
+        }
+    }
+
+    fn is_inside_tmux(&self) -> bool {
+        std::env::var("WEZTERM_PANE").is_ok()
+    }
+
+    fn is_available(&self) -> bool {
+        // `multiplexer = "auto"` asks each time a provider is made
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(|| {
+            Command::new("wezterm")
+                .arg("--version")
+                .traced_output()
+                .is_ok()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST_OUTPUT: &str = r#"[
+        {"window_id": 0, "tab_id": 3, "pane_id": 7, "workspace": "kiosk", "title": "hx",
+         "is_active": false, "size": {"rows": 40, "cols": 80, "pixel_width": 0, "pixel_height": 0, "dpi": 96}},
+        {"window_id": 0, "tab_id": 3, "pane_id": 5, "workspace": "kiosk", "title": "pwsh",
+         "is_active": true, "size": {"rows": 40, "cols": 80, "pixel_width": 0, "pixel_height": 0, "dpi": 96}},
+        {"window_id": 0, "tab_id": 4, "pane_id": 2, "workspace": "kiosk", "title": "other tab",
+         "is_active": false, "size": {"rows": 40, "cols": 160, "pixel_width": 0, "pixel_height": 0, "dpi": 96}},
+        {"window_id": 1, "tab_id": 1, "pane_id": 1, "workspace": "default", "title": "bash",
+         "is_active": true, "size": {"rows": 24, "cols": 80, "pixel_width": 0, "pixel_height": 0, "dpi": 96}}
+    ]"#;
+
+    #[test]
+    fn session_panes_are_the_first_tab_in_creation_order() {
+        let panes = parse_panes(LIST_OUTPUT).unwrap();
+        let ids: Vec<u64> = session_panes(&panes, "kiosk")
+            .iter()
+            .map(|pane| pane.pane_id)
+            .collect();
+        assert_eq!(ids, [5, 7]);
+        assert!(session_panes(&panes, "missing").is_empty());
+    }
+
+    #[test]
+    fn finds_the_shell_and_foreground_command_of_a_terminal() {
+        let processes = parse_tty_processes(
+            "  100   100   300 Ss   -zsh\n  300   300   300 S+   /usr/local/bin/claude --resume\n  301   300   300 S+   node helper.js\n",
+        );
+        assert_eq!(
+            processes,
+            TtyProcesses {
+                shell: Some(100),
+                foreground: Some("claude".to_string()),
+            }
+        );
+        assert_eq!(parse_tty_processes(""), TtyProcesses::default());
+    }
+
+    #[test]
+    fn screen_activity_is_when_the_screen_last_changed() {
+        let mut seen = Vec::new();
+        assert_eq!(observe_screen(&mut seen, "app", "a".to_string(), 100), 100);
+        assert_eq!(observe_screen(&mut seen, "app", "a".to_string(), 160), 100);
+        assert_eq!(observe_screen(&mut seen, "app", "b".to_string(), 200), 200);
+        assert_eq!(observe_screen(&mut seen, "api", "a".to_string(), 210), 210);
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn key_names_map_to_terminal_input() {
+        assert_eq!(key_text("Enter"), "\r");
+        assert_eq!(key_text("C-c"), "\x03");
+        assert_eq!(key_text("C-D"), "\x04");
+        assert_eq!(key_text("Up"), "\x1b[A");
+        assert_eq!(key_text("y"), "y");
+        assert_eq!(key_text("C-"), "C-");
+    }
+
+    #[test]
+    fn get_text_args_only_passes_given_bounds() {
+        assert_eq!(
+            get_text_args(5, &CaptureRange::default()),
+            ["get-text", "--pane-id", "5"]
+        );
+        assert_eq!(
+            get_text_args(
+                5,
+                &CaptureRange {
+                    start: Some(-100),
+                    end: Some(-1),
+                    escapes: true,
+                }
+            ),
+            [
+                "get-text",
+                "--pane-id",
+                "5",
+                "--escapes",
+                "--start-line",
+                "-100",
+                "--end-line",
+                "-1"
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_paths_from_cwd_urls() {
+        assert_eq!(
+            path_from_cwd_url("file://host/home/me/src/kiosk"),
+            Some(PathBuf::from("/home/me/src/kiosk"))
+        );
+        assert_eq!(
+            path_from_cwd_url("file://host/C:/src/kiosk"),
+            Some(PathBuf::from("C:/src/kiosk"))
+        );
+        assert_eq!(path_from_cwd_url(""), None);
+    }
+}
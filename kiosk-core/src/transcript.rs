@@ -0,0 +1,363 @@
+//! Finding and reading the conversations coding agents save for a worktree.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// User messages Codex adds itself to pass along context, not typed by anyone.
+const CODEX_CONTEXT_TAGS: &[&str] = &["<environment_context>", "<user_instructions>"];
+
+/// Agents whose transcripts kiosk can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptAgent {
+    Claude,
+    Codex,
+}
+
+impl TranscriptAgent {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Claude => "claude",
+            Self::Codex => "codex",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Assistant => "assistant",
+        }
+    }
+}
+
+/// The text of one turn of the conversation. Tool calls and their results are left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptMessage {
+    pub role: Role,
+    pub text: String,
+    /// When the agent recorded it, as the agent wrote it (RFC 3339)
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcript {
+    pub agent: TranscriptAgent,
+    /// The agent's own file the conversation was read from
+    pub file: PathBuf,
+    pub messages: Vec<TranscriptMessage>,
+}
+
+/// Where each agent keeps its transcripts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptDirs {
+    /// Claude Code's config directory, with one folder of transcripts per project directory
+    pub claude: PathBuf,
+    /// Codex's home, with transcripts under `sessions/` by date
+    pub codex: PathBuf,
+}
+
+impl TranscriptDirs {
+    /// `$CLAUDE_CONFIG_DIR` (default `~/.claude`) and `$CODEX_HOME` (default `~/.codex`).
+    pub fn from_env() -> Option<Self> {
+        let home = dirs::home_dir()?;
+        let dir = |var: &str, default: &str| {
+            std::env::var(var)
+                .ok()
+                .filter(|dir| !dir.is_empty())
+                .map_or_else(|| home.join(default), PathBuf::from)
+        };
+        Some(Self {
+            claude: dir("CLAUDE_CONFIG_DIR", ".claude"),
+            codex: dir("CODEX_HOME", ".codex"),
+        })
+    }
+
+    /// The most recently written transcript of a conversation held in `worktree`, by
+    /// `agent` or by any agent.
+    pub fn latest(
+        &self,
+        worktree: &Path,
+        agent: Option<TranscriptAgent>,
+    ) -> Result<Option<Transcript>> {
+        let mut candidates = Vec::new();
+        if agent.is_none_or(|agent| agent == TranscriptAgent::Claude) {
+            candidates.extend(
+                self.claude_files(worktree)
+                    .into_iter()
+                    .map(|file| (TranscriptAgent::Claude, file)),
+            );
+        }
+        if agent.is_none_or(|agent| agent == TranscriptAgent::Codex) {
+            candidates.extend(
+                self.codex_file(worktree)
+                    .map(|file| (TranscriptAgent::Codex, file)),
+            );
+        }
+        let Some((agent, file, _)) = candidates
+            .into_iter()
+            .filter_map(|(agent, file)| Some((agent, file.clone(), modified(&file)?)))
+            .max_by_key(|(_, _, modified)| *modified)
+        else {
+            return Ok(None);
+        };
+        let contents = fs::read_to_string(&file)
+            .with_context(|| format!("failed to read {}", file.display()))?;
+        let messages = match agent {
+            TranscriptAgent::Claude => parse_claude(&contents),
+            TranscriptAgent::Codex => parse_codex(&contents),
+        };
+        Ok(Some(Transcript {
+            agent,
+            file,
+            messages,
+        }))
+    }
+
+    /// Claude Code keeps a project's transcripts in a folder named after its directory.
+    fn claude_files(&self, worktree: &Path) -> Vec<PathBuf> {
+        let dir = self
+            .claude
+            .join("projects")
+            .join(claude_project_name(worktree));
+        jsonl_files(&dir)
+    }
+
+    /// Codex files transcripts by date, each starting with the directory it ran in, so the
+    /// newest whose first line names `worktree` is the one.
+    fn codex_file(&self, worktree: &Path) -> Option<PathBuf> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.codex.join("sessions")];
+        while let Some(dir) = dirs.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "jsonl") {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort_by_cached_key(|file| std::cmp::Reverse(modified(file)));
+        files.into_iter().find(|file| {
+            let mut first_line = String::new();
+            fs::File::open(file)
+                .and_then(|file| BufReader::new(file).read_line(&mut first_line))
+                .is_ok()
+                && serde_json::from_str::<Value>(&first_line)
+                    .ok()
+                    .and_then(|meta| meta.pointer("/payload/cwd")?.as_str().map(PathBuf::from))
+                    .is_some_and(|cwd| cwd == worktree)
+        })
+    }
+}
+
+/// Claude Code's folder name for a project directory: every character other than a letter or
+/// digit becomes `-`.
+fn claude_project_name(dir: &Path) -> String {
+    dir.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+fn jsonl_files(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn modified(file: &Path) -> Option<SystemTime> {
+    fs::metadata(file).and_then(|meta| meta.modified()).ok()
+}
+
+/// The `text` blocks of a message's content, or the content itself when it's plain text.
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.trim().to_string(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => String::new(),
+    }
+}
+
+fn role(name: &str) -> Option<Role> {
+    match name {
+        "user" => Some(Role::User),
+        "assistant" => Some(Role::Assistant),
+        _ => None,
+    }
+}
+
+/// Messages from a Claude Code transcript, leaving out subagents' and Claude's own notes.
+fn parse_claude(contents: &str) -> Vec<TranscriptMessage> {
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|entry| {
+            let flag = |name: &str| entry.get(name).and_then(Value::as_bool) == Some(true);
+            !flag("isMeta") && !flag("isSidechain")
+        })
+        .filter_map(|entry| {
+            let role = role(entry.get("type")?.as_str()?)?;
+            let text = content_text(entry.pointer("/message/content")?);
+            (!text.is_empty()).then(|| TranscriptMessage {
+                role,
+                text,
+                timestamp: timestamp(&entry),
+            })
+        })
+        .collect()
+}
+
+/// Messages from a Codex transcript, leaving out the context Codex sends along.
+fn parse_codex(contents: &str) -> Vec<TranscriptMessage> {
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|entry| entry.get("type").and_then(Value::as_str) == Some("response_item"))
+        .filter_map(|entry| {
+            let payload = entry.get("payload")?;
+            if payload.get("type").and_then(Value::as_str) != Some("message") {
+                return None;
+            }
+            let role = role(payload.get("role")?.as_str()?)?;
+            let text = content_text(payload.get("content")?);
+            let context = CODEX_CONTEXT_TAGS.iter().any(|tag| text.starts_with(tag));
+            (!text.is_empty() && !context).then(|| TranscriptMessage {
+                role,
+                text,
+                timestamp: timestamp(&entry),
+            })
+        })
+        .collect()
+}
+
+fn timestamp(entry: &Value) -> Option<String> {
+    entry
+        .get("timestamp")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn message(role: Role, text: &str, timestamp: &str) -> TranscriptMessage {
+        TranscriptMessage {
+            role,
+            text: text.to_string(),
+            timestamp: Some(timestamp.to_string()),
+        }
+    }
+
+    #[test]
+    fn names_claude_projects_after_their_directory() {
+        assert_eq!(
+            claude_project_name(Path::new("/home/me/.kiosk_worktrees/app--feat_x")),
+            "-home-me--kiosk-worktrees-app--feat-x"
+        );
+    }
+
+    #[test]
+    fn reads_claude_transcripts() {
+        let contents = r#"{"type":"queue-operation","operation":"enqueue"}
+{"type":"user","message":{"role":"user","content":"Fix the login page"},"timestamp":"2026-01-01T10:00:00Z"}
+{"type":"user","isMeta":true,"message":{"role":"user","content":"<command-name>/clear</command-name>"}}
+{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Looking now."},{"type":"tool_use","name":"Read","input":{}}]},"timestamp":"2026-01-01T10:00:05Z"}
+{"type":"user","message":{"role":"user","content":[{"type":"tool_result","content":"..."}]}}
+{"type":"assistant","isSidechain":true,"message":{"role":"assistant","content":[{"type":"text","text":"subagent"}]}}
+not json"#;
+        assert_eq!(
+            parse_claude(contents),
+            [
+                message(Role::User, "Fix the login page", "2026-01-01T10:00:00Z"),
+                message(Role::Assistant, "Looking now.", "2026-01-01T10:00:05Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_codex_transcripts() {
+        let contents = r#"{"timestamp":"2026-01-01T10:00:00Z","type":"session_meta","payload":{"cwd":"/work/app"}}
+{"timestamp":"2026-01-01T10:00:00Z","type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"<environment_context>\n  <cwd>/work/app</cwd>\n</environment_context>"}]}}
+{"timestamp":"2026-01-01T10:00:01Z","type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"Add a test"}]}}
+{"timestamp":"2026-01-01T10:00:02Z","type":"response_item","payload":{"type":"function_call","name":"shell"}}
+{"timestamp":"2026-01-01T10:00:03Z","type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"Added one."}]}}"#;
+        assert_eq!(
+            parse_codex(contents),
+            [
+                message(Role::User, "Add a test", "2026-01-01T10:00:01Z"),
+                message(Role::Assistant, "Added one.", "2026-01-01T10:00:03Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_the_latest_transcript_for_a_worktree() {
+        let home = tempdir().unwrap();
+        let dirs = TranscriptDirs {
+            claude: home.path().join(".claude"),
+            codex: home.path().join(".codex"),
+        };
+        let worktree = Path::new("/work/app");
+        assert_eq!(dirs.latest(worktree, None).unwrap(), None);
+
+        let claude_dir = dirs.claude.join("projects").join("-work-app");
+        fs::create_dir_all(&claude_dir).unwrap();
+        fs::write(
+            claude_dir.join("a.jsonl"),
+            r#"{"type":"user","message":{"content":"hi claude"}}"#,
+        )
+        .unwrap();
+        let codex_dir = dirs.codex.join("sessions/2026/01/01");
+        fs::create_dir_all(&codex_dir).unwrap();
+        fs::write(
+            codex_dir.join("other.jsonl"),
+            r#"{"type":"session_meta","payload":{"cwd":"/work/other"}}"#,
+        )
+        .unwrap();
+        let latest = dirs.latest(worktree, None).unwrap().unwrap();
+        assert_eq!(latest.agent, TranscriptAgent::Claude);
+        assert_eq!(latest.messages[0].text, "hi claude");
+
+        fs::write(
+            codex_dir.join("rollout.jsonl"),
+            r#"{"type":"session_meta","payload":{"cwd":"/work/app"}}"#,
+        )
+        .unwrap();
+        let codex = dirs
+            .latest(worktree, Some(TranscriptAgent::Codex))
+            .unwrap()
+            .unwrap();
+        assert_eq!(codex.file, codex_dir.join("rollout.jsonl"));
+        assert!(codex.messages.is_empty());
+    }
+}
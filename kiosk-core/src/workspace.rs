@@ -0,0 +1,195 @@
+use crate::{
+    git::Repo,
+    paths::state_dir,
+    state_store::{load_state_file, update_state_file},
+};
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+
+const WORKSPACES_FILE_NAME: &str = "workspaces.toml";
+const WORKSPACES_STATE_VERSION: u32 = 1;
+
+/// A named set of branches opened together with `kiosk workspace open`. Members are written
+/// `repo/branch`, or just `repo` for its main checkout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+/// Workspaces created from the TUI, alongside the ones in the config's `[workspaces]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceStore {
+    path: Option<PathBuf>,
+}
+
+impl Default for WorkspaceStore {
+    fn default() -> Self {
+        Self::at(state_dir().join(WORKSPACES_FILE_NAME))
+    }
+}
+
+impl WorkspaceStore {
+    /// Use a store at a specific file path.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// A store that never saves or returns any workspaces.
+    pub fn disabled() -> Self {
+        Self { path: None }
+    }
+
+    pub fn load(&self) -> Vec<Workspace> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        load_state_file::<Workspace>(path, WORKSPACES_STATE_VERSION)
+    }
+
+    /// Add `member` to the workspace called `name`, creating it if needed. Returns the
+    /// workspace's members.
+    pub fn add(&self, name: &str, member: &str) -> Result<Vec<String>> {
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("workspace names can't be empty");
+        }
+        let Some(path) = &self.path else {
+            return Ok(vec![member.to_string()]);
+        };
+        update_state_file(
+            path,
+            WORKSPACES_STATE_VERSION,
+            |workspaces: &mut Vec<Workspace>| {
+                let index = workspaces
+                    .iter()
+                    .position(|workspace| workspace.name == name)
+                    .unwrap_or_else(|| {
+                        workspaces.push(Workspace {
+                            name: name.to_string(),
+                            members: Vec::new(),
+                        });
+                        workspaces.len() - 1
+                    });
+                let members = &mut workspaces[index].members;
+                if !members.iter().any(|m| m == member) {
+                    members.push(member.to_string());
+                }
+                let members = members.clone();
+                workspaces.sort_by(|a, b| a.name.cmp(&b.name));
+                members
+            },
+        )
+    }
+}
+
+/// Workspaces from the config and the store, sorted by name. A name in both gets the
+/// config's members followed by the store's.
+pub fn all(configured: &BTreeMap<String, Vec<String>>, saved: Vec<Workspace>) -> Vec<Workspace> {
+    let mut merged = configured.clone();
+    for workspace in saved {
+        let members = merged.entry(workspace.name).or_default();
+        for member in workspace.members {
+            if !members.contains(&member) {
+                members.push(member);
+            }
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(name, members)| Workspace { name, members })
+        .collect()
+}
+
+/// How a workspace lists `repo`'s `branch` (or its main checkout, for `None`).
+pub fn member_spec(repo: &str, branch: Option<&str>) -> String {
+    match branch {
+        Some(branch) => format!("{repo}/{branch}"),
+        None => repo.to_string(),
+    }
+}
+
+/// The repo and branch a workspace member names. An exact repo name is its main checkout.
+pub fn resolve_member<'a>(repos: &'a [Repo], member: &str) -> Option<(&'a Repo, Option<String>)> {
+    if let Some(repo) = repos.iter().find(|repo| repo.name == member) {
+        return Some((repo, None));
+    }
+    let (name, branch) = member.split_once('/')?;
+    let repo = repos.iter().find(|repo| repo.name == name)?;
+    (!branch.is_empty()).then(|| (repo, Some(branch.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn repo(name: &str) -> Repo {
+        Repo {
+            name: name.to_string(),
+            path: PathBuf::from(format!("/src/{name}")),
+            worktrees: Vec::new(),
+            session_name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn add_creates_workspaces_and_skips_duplicate_members() {
+        let tmp = tempdir().unwrap();
+        let store = WorkspaceStore::at(tmp.path().join("workspaces.toml"));
+        store.add("trio", "frontend/main").unwrap();
+        store.add("solo", "infra").unwrap();
+        let members = store.add("trio", "backend/main").unwrap();
+        assert_eq!(store.add("trio", "frontend/main").unwrap(), members);
+        assert_eq!(members, vec!["frontend/main", "backend/main"]);
+        let names: Vec<_> = store.load().into_iter().map(|w| w.name).collect();
+        assert_eq!(names, vec!["solo", "trio"]);
+        assert!(store.add(" ", "infra").is_err());
+    }
+
+    #[test]
+    fn all_merges_config_and_saved_workspaces() {
+        let configured = BTreeMap::from([
+            ("trio".to_string(), vec!["frontend/main".to_string()]),
+            ("zeta".to_string(), vec!["infra".to_string()]),
+        ]);
+        let saved = vec![
+            Workspace {
+                name: "trio".to_string(),
+                members: vec!["frontend/main".to_string(), "backend/main".to_string()],
+            },
+            Workspace {
+                name: "alpha".to_string(),
+                members: vec!["infra".to_string()],
+            },
+        ];
+        let merged = all(&configured, saved);
+        let names: Vec<_> = merged.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, vec!["alpha", "trio", "zeta"]);
+        assert_eq!(merged[1].members, vec!["frontend/main", "backend/main"]);
+    }
+
+    #[test]
+    fn members_resolve_to_a_repo_and_branch() {
+        let repos = vec![repo("frontend"), repo("infra"), repo("team/tools")];
+        let resolved = |member| {
+            resolve_member(&repos, member).map(|(repo, branch)| (repo.name.clone(), branch))
+        };
+        assert_eq!(resolved("infra"), Some(("infra".to_string(), None)));
+        assert_eq!(
+            resolved("frontend/feat/login"),
+            Some(("frontend".to_string(), Some("feat/login".to_string())))
+        );
+        assert_eq!(
+            resolved("team/tools"),
+            Some(("team/tools".to_string(), None))
+        );
+        assert_eq!(resolved("frontend/"), None);
+        assert_eq!(resolved("missing/main"), None);
+        assert_eq!(member_spec("infra", Some("main")), "infra/main");
+        assert_eq!(member_spec("infra", None), "infra");
+    }
+}
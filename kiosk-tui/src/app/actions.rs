@@ -1,21 +1,37 @@
-use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use kiosk_core::{
-    config::KeysConfig,
-    git::GitProvider,
-    pending_delete::{PendingWorktreeDelete, save_pending_worktree_deletes},
+    action::Action,
+    branch_name,
+    config::{BranchColumn, KeysConfig, keys::Command},
+    git::{GitProvider, repo_name_from_url},
+    instance::FetchClaim,
+    pending_delete::PendingWorktreeDelete,
+    recovery::PendingWorktreeCreate,
+    scripting::Script,
+    session_journal::kill_session,
     state::{
-        AppState, BaseBranchSelection, HelpOverlayState, Mode, SearchableList, SetupStep,
-        worktree_dir,
+        AppState, BaseBranchSelection, BranchEntry, BranchNameFields, CloneDirSelection,
+        DeletePreview, GlobalSearchEntry, GlobalSearchState, HelpOverlayState, Mode,
+        NewRepoSelection, NoteEdit, PickScope, SearchableList, SetupStep, TagSelectState,
+        TextInput, WorkspaceSelectState, worktree_dir,
     },
+    ticket::{self, Ticket},
     tmux::TmuxProvider,
+    workspace,
 };
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
+
+use crate::matcher::Matcher;
 
 use super::spawn::{
-    spawn_branch_and_worktree_creation, spawn_branch_loading, spawn_tracking_worktree_creation,
-    spawn_worktree_creation, spawn_worktree_removal,
+    BACKGROUND_FILTER_MIN_ITEMS, WorktreeSession, spawn_branch_loading, spawn_clone,
+    spawn_fuzzy_filter, spawn_git_fetch, spawn_new_repo, spawn_tag_loading, spawn_ticket_fetch,
+    spawn_worktree_changes_loading, spawn_worktree_creation, spawn_worktree_removal,
+    spawn_worktree_restore,
+};
+use super::{
+    EventSender, OpenAction, open_session, rebuild_filtered_preserving_search,
+    sort_repos_preserving_selection, update_active_list_scroll_offset,
 };
-use super::{EventSender, OpenAction};
 
 pub(super) fn handle_go_back(state: &mut AppState) {
     match state.mode.clone() {
@@ -27,10 +43,49 @@ pub(super) fn handle_go_back(state: &mut AppState) {
             state.base_branch_selection = None;
             state.mode = Mode::BranchSelect;
         }
+        Mode::GlobalSearch => {
+            state.global_search = None;
+            state.mode = Mode::RepoSelect;
+        }
+        Mode::TagSelect => {
+            state.tag_select = None;
+            state.mode = Mode::BranchSelect;
+        }
+        Mode::WorkspaceSelect => {
+            let adding = state
+                .workspace_select
+                .take()
+                .is_some_and(|select| select.adding.is_some());
+            state.mode = if adding {
+                Mode::BranchSelect
+            } else {
+                Mode::RepoSelect
+            };
+        }
+        Mode::SelectCloneDir => {
+            state.clone_dir_selection = None;
+            state.mode = Mode::RepoSelect;
+        }
+        Mode::NewRepo => {
+            state.new_repo_selection = None;
+            state.mode = Mode::RepoSelect;
+        }
         Mode::ConfirmWorktreeDelete { .. } => {
+            state.delete_preview = None;
+            state.mode = Mode::BranchSelect;
+        }
+        Mode::ConfirmResume { .. } => handle_cancel_resume(state),
+        // Left for `kiosk recover` or the next start
+        Mode::ConfirmRecover { .. } => state.mode = Mode::RepoSelect,
+        Mode::EditNote => {
+            state.note_edit = None;
             state.mode = Mode::BranchSelect;
         }
-        Mode::Help { previous } => {
+        Mode::BranchNameFields => {
+            state.branch_name_fields = None;
+            state.mode = Mode::BranchSelect;
+        }
+        Mode::Help { previous } | Mode::CommandPalette { previous } => {
             state.help_overlay = None;
             state.mode = *previous;
         }
@@ -38,23 +93,71 @@ pub(super) fn handle_go_back(state: &mut AppState) {
     }
 }
 
+/// Decline to recreate the lost sessions, so they aren't offered again.
+pub(super) fn handle_cancel_resume(state: &mut AppState) {
+    if let Mode::ConfirmResume { sessions } = &state.mode
+        && let Err(e) = state.session_journal.forget(sessions)
+    {
+        tracing::warn!("failed to forget lost sessions: {e}");
+    }
+    state.mode = Mode::RepoSelect;
+}
+
 pub(super) fn handle_show_help(state: &mut AppState, keys: &KeysConfig) {
-    if let Mode::Help { previous } = state.mode.clone() {
+    toggle_help_overlay(state, keys, false);
+}
+
+pub(super) fn handle_show_command_palette(state: &mut AppState, keys: &KeysConfig) {
+    toggle_help_overlay(state, keys, true);
+}
+
+/// Open help or the command palette over the current mode. Asking for the overlay that's
+/// already open closes it; asking for the other one swaps it in over the same mode.
+fn toggle_help_overlay(state: &mut AppState, keys: &KeysConfig, palette: bool) {
+    let (previous, was_palette) = match state.mode.clone() {
+        Mode::Help { previous } => (*previous, false),
+        Mode::CommandPalette { previous } => (*previous, true),
+        mode => {
+            let catalog = keys.catalog_for_mode(&mode);
+            state.help_overlay = Some(HelpOverlayState {
+                list: SearchableList::new(catalog.flattened.len()),
+                rows: catalog.flattened,
+            });
+            let previous = Box::new(mode);
+            state.mode = if palette {
+                Mode::CommandPalette { previous }
+            } else {
+                Mode::Help { previous }
+            };
+            return;
+        }
+    };
+    if palette == was_palette {
         state.help_overlay = None;
-        state.mode = *previous;
+        state.mode = previous;
     } else {
-        let catalog = keys.catalog_for_mode(&state.mode);
-        state.help_overlay = Some(HelpOverlayState {
-            list: SearchableList::new(catalog.flattened.len()),
-            rows: catalog.flattened,
-        });
-        state.mode = Mode::Help {
-            previous: Box::new(state.mode.clone()),
+        let previous = Box::new(previous);
+        state.mode = if palette {
+            Mode::CommandPalette { previous }
+        } else {
+            Mode::Help { previous }
         };
     }
 }
 
-pub(super) fn handle_start_new_branch(state: &mut AppState) {
+/// Close the command palette, returning the command on its selected row so it can run in
+/// the mode underneath.
+pub(super) fn take_palette_command(state: &mut AppState) -> Option<Command> {
+    let Mode::CommandPalette { previous } = state.mode.clone() else {
+        return None;
+    };
+    let overlay = state.help_overlay.take()?;
+    state.mode = *previous;
+    let &(row_idx, _) = overlay.list.filtered.get(overlay.list.selected?)?;
+    overlay.rows.get(row_idx).map(|row| row.command.clone())
+}
+
+pub(super) fn handle_start_new_branch(state: &mut AppState, sender: &EventSender) {
     if state.branch_list.input.text.is_empty() {
         state.set_error("Type a branch name first");
         return;
@@ -62,12 +165,82 @@ pub(super) fn handle_start_new_branch(state: &mut AppState) {
     if state.selected_repo_idx.is_none() {
         return;
     }
+    let text = state.branch_list.input.text.clone();
+    if state.ticket.provider.is_some() && ticket::is_ticket_id(text.trim()) {
+        let id = text.trim().to_string();
+        let cancelled = state.start_loading(format!("Fetching {id}..."));
+        spawn_ticket_fetch(sender, state.ticket.clone(), id, cancelled);
+        return;
+    }
+    let Some(template) = state.branch_name_template.clone() else {
+        start_base_selection(state, text, None);
+        return;
+    };
+    name_new_branch(state, BranchNameFields::new(template, text, Vec::new()));
+}
+
+/// Name a new branch after the ticket fetched for it, with the ticket's link as its note.
+pub(super) fn start_ticket_branch(state: &mut AppState, ticket: &Ticket) {
+    let template = state
+        .branch_name_template
+        .clone()
+        .unwrap_or_else(|| ticket::DEFAULT_NAME_TEMPLATE.to_string());
+    let mut fields =
+        BranchNameFields::new(template, ticket.title.clone(), ticket.template_values());
+    fields.note = Some(ticket.url.clone());
+    name_new_branch(state, fields);
+}
+
+/// Ask for the template fields still to fill, if any, before picking a base.
+fn name_new_branch(state: &mut AppState, fields: BranchNameFields) {
+    if fields.current().is_some() {
+        state.branch_name_fields = Some(fields);
+        state.mode = Mode::BranchNameFields;
+    } else {
+        start_base_selection(state, fields.name(), fields.note);
+    }
+}
+
+/// Keep the typed template field and ask for the next one, or pick a base once all are in.
+pub(super) fn handle_save_branch_name_field(state: &mut AppState) {
+    let Some(fields) = state.branch_name_fields.as_mut() else {
+        return;
+    };
+    let field = fields.current().unwrap_or_default().to_string();
+    if fields.input.text.trim().is_empty() {
+        state.set_error(&format!("Type a value for {{{field}}} first"));
+        return;
+    }
+    let name = fields.name();
+    if fields.remaining.len() == 1
+        && let Err(problem) = branch_name::validate(&name)
+    {
+        state.set_error(&format!("Invalid branch name \"{name}\": {problem}"));
+        return;
+    }
+    if fields.advance() {
+        return;
+    }
+    let note = fields.note.take();
+    state.branch_name_fields = None;
+    state.mode = Mode::BranchSelect;
+    start_base_selection(state, name, note);
+}
+
+/// Offer the local branches as bases for a new branch called `name`, if git would accept
+/// the name.
+fn start_base_selection(state: &mut AppState, name: String, note: Option<String>) {
+    if let Err(problem) = branch_name::validate(&name) {
+        state.set_error(&format!("Invalid branch name \"{name}\": {problem}"));
+        return;
+    }
     // Derive base branches from the already-loaded branch list, preserving its ordering
-    // and filtering out remote-only branches (which can't serve as local bases).
+    // and filtering out remote-only branches and detached worktrees (which can't serve as
+    // local bases).
     let bases: Vec<String> = state
         .branches
         .iter()
-        .filter(|b| b.remote.is_none())
+        .filter(|b| b.is_local())
         .map(|b| b.name.clone())
         .collect();
     if bases.is_empty() {
@@ -77,14 +250,243 @@ pub(super) fn handle_start_new_branch(state: &mut AppState) {
     let list = SearchableList::new(bases.len());
 
     state.base_branch_selection = Some(BaseBranchSelection {
-        new_name: state.branch_list.input.text.clone(),
+        new_name: name,
         bases,
         list,
+        note,
     });
     state.mode = Mode::SelectBaseBranch;
 }
 
-pub(super) fn handle_delete_worktree(state: &mut AppState) {
+pub(super) fn handle_start_global_search(state: &mut AppState) {
+    let entries = state.global_search_entries();
+    let list = SearchableList::new(entries.len());
+    state.global_search = Some(GlobalSearchState { entries, list });
+    state.mode = Mode::GlobalSearch;
+}
+
+/// Switch the branch picker to the selected repo's tags, listed in the background.
+pub(super) fn handle_show_tags(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+) {
+    let Some(repo_path) = state
+        .selected_repo_idx
+        .and_then(|idx| state.repos.get(idx))
+        .map(|repo| repo.path.clone())
+    else {
+        return;
+    };
+    state.tag_select = Some(TagSelectState {
+        tags: Vec::new(),
+        list: SearchableList::new(0),
+        loading: true,
+    });
+    state.mode = Mode::TagSelect;
+    spawn_tag_loading(git, sender, repo_path);
+}
+
+pub(super) fn handle_show_workspaces(state: &mut AppState) {
+    show_workspaces(state, None);
+}
+
+/// Offer the workspaces to add the selected branch to.
+pub(super) fn handle_add_to_workspace(state: &mut AppState) {
+    let Some(repo) = state.selected_repo_idx.and_then(|idx| state.repos.get(idx)) else {
+        return;
+    };
+    let Some(&(idx, _)) = state
+        .branch_list
+        .selected
+        .and_then(|sel| state.branch_list.filtered.get(sel))
+    else {
+        return;
+    };
+    let member = workspace::member_spec(&repo.name, Some(&state.branches[idx].name));
+    show_workspaces(state, Some(member));
+}
+
+fn show_workspaces(state: &mut AppState, adding: Option<String>) {
+    let workspaces = workspace::all(&state.workspaces, state.workspace_store.load());
+    let list = SearchableList::new(workspaces.len());
+    state.workspace_select = Some(WorkspaceSelectState {
+        workspaces,
+        list,
+        adding,
+    });
+    state.mode = Mode::WorkspaceSelect;
+}
+
+/// Open the picked workspace, or add the branch to it. When adding, a search that matches no
+/// workspace names a new one.
+pub(super) fn handle_confirm_workspace(state: &mut AppState) -> Option<OpenAction> {
+    let select = state.workspace_select.as_ref()?;
+    let picked = select
+        .list
+        .selected
+        .and_then(|sel| select.list.filtered.get(sel))
+        .map(|&(idx, _)| select.workspaces[idx].name.clone());
+    let Some(member) = select.adding.clone() else {
+        return picked.map(|name| OpenAction::Workspace { name });
+    };
+    let name = picked.unwrap_or_else(|| select.list.input.text.trim().to_string());
+    if name.is_empty() {
+        state.set_error("Type a name for the new workspace");
+        return None;
+    }
+    if let Err(e) = state.workspace_store.add(&name, &member) {
+        state.set_error(&format!("Failed to save workspace: {e}"));
+        return None;
+    }
+    state.workspace_select = None;
+    state.mode = Mode::BranchSelect;
+    None
+}
+
+pub(super) fn handle_start_clone(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+) {
+    let url = state.repo_list.input.text.trim().to_string();
+    if url.is_empty() {
+        state.set_error("Type a URL to clone first");
+        return;
+    }
+    let Some(name) = repo_name_from_url(&url) else {
+        state.set_error(&format!("Can't tell a directory name from '{url}'"));
+        return;
+    };
+    let dirs: Vec<PathBuf> = state
+        .search_dirs
+        .iter()
+        .map(|(dir, _)| dir.clone())
+        .collect();
+    match dirs.as_slice() {
+        [] => state.set_error("No search dirs to clone into"),
+        [dir] => {
+            let dest = dir.join(&name);
+            start_clone(state, git, sender, url, &name, dest);
+        }
+        _ => {
+            let list = SearchableList::new(dirs.len());
+            state.clone_dir_selection = Some(CloneDirSelection {
+                url,
+                name,
+                dirs,
+                list,
+            });
+            state.mode = Mode::SelectCloneDir;
+        }
+    }
+}
+
+pub(super) fn handle_confirm_clone(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+) {
+    let Some(flow) = &state.clone_dir_selection else {
+        return;
+    };
+    let Some(&(idx, _)) = flow
+        .list
+        .selected
+        .and_then(|sel| flow.list.filtered.get(sel))
+    else {
+        return;
+    };
+    let (url, name) = (flow.url.clone(), flow.name.clone());
+    let dest = flow.dirs[idx].join(&name);
+    start_clone(state, git, sender, url, &name, dest);
+}
+
+fn start_clone(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+    url: String,
+    name: &str,
+    dest: PathBuf,
+) {
+    if dest.exists() {
+        state.set_error(&format!("{} already exists", dest.display()));
+        return;
+    }
+    state.clone_dir_selection = None;
+    state.mode = Mode::RepoSelect;
+    let cancelled = state.start_loading(format!("Cloning {name}..."));
+    spawn_clone(git, sender, url, dest, state.search_dirs.clone(), cancelled);
+}
+
+pub(super) fn handle_start_new_repo(state: &mut AppState) {
+    let name = state.repo_list.input.text.trim().to_string();
+    if name.is_empty() {
+        state.set_error("Type a repo name first");
+        return;
+    }
+    if name.contains(['/', '\\']) || name == "." || name == ".." {
+        state.set_error(&format!("'{name}' isn't a valid repo name"));
+        return;
+    }
+    let templates: Vec<String> = state.new_repo.all_templates().into_keys().collect();
+    let list = SearchableList::new(templates.len());
+    state.new_repo_selection = Some(NewRepoSelection {
+        name,
+        templates,
+        list,
+    });
+    state.mode = Mode::NewRepo;
+}
+
+pub(super) fn handle_confirm_new_repo(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+) {
+    let Some(flow) = &state.new_repo_selection else {
+        return;
+    };
+    let Some(&(idx, _)) = flow
+        .list
+        .selected
+        .and_then(|sel| flow.list.filtered.get(sel))
+    else {
+        return;
+    };
+    let name = flow.name.clone();
+    let Some(template) = state.new_repo.all_templates().remove(&flow.templates[idx]) else {
+        return;
+    };
+    let Some(dir) = state.new_repo.resolved_dir(&state.search_dirs) else {
+        state.set_error("No search dirs to create the repo in");
+        return;
+    };
+    let dest = dir.join(&name);
+    if dest.exists() {
+        state.set_error(&format!("{} already exists", dest.display()));
+        return;
+    }
+    state.new_repo_selection = None;
+    state.mode = Mode::RepoSelect;
+    let cancelled = state.start_loading(format!("Creating {name}..."));
+    spawn_new_repo(
+        git,
+        sender,
+        name,
+        dest,
+        template,
+        state.search_dirs.clone(),
+        cancelled,
+    );
+}
+
+pub(super) fn handle_delete_worktree(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+) {
     if let Some(sel) = state.branch_list.selected
         && let Some(&(idx, _)) = state.branch_list.filtered.get(sel)
     {
@@ -100,11 +502,13 @@ pub(super) fn handle_delete_worktree(state: &mut AppState) {
             state.set_error("No worktree to delete");
         } else if branch.is_current {
             state.set_error("Cannot delete the current branch's worktree");
-        } else {
+        } else if let Some(worktree_path) = branch.worktree_path.clone() {
             state.mode = Mode::ConfirmWorktreeDelete {
                 branch_name: branch.name.clone(),
                 has_session: branch.has_session,
             };
+            state.delete_preview = Some(DeletePreview::new(worktree_path.clone()));
+            spawn_worktree_changes_loading(git, sender, worktree_path);
         }
     }
 }
@@ -115,42 +519,287 @@ pub(super) fn handle_confirm_delete<T: TmuxProvider + ?Sized>(
     tmux: &T,
     sender: &EventSender,
 ) {
-    if let Mode::ConfirmWorktreeDelete {
+    if let Some(preview) = &mut state.delete_preview
+        && preview.needs_second_confirm()
+    {
+        preview.armed = true;
+        return;
+    }
+    let Mode::ConfirmWorktreeDelete {
         branch_name,
         has_session,
     } = &state.mode
-    {
-        let branch_name = branch_name.clone();
-        let has_session = *has_session;
-        if let Some(branch) = state.branches.iter().find(|b| b.name == branch_name)
-            && let Some(worktree_path) = &branch.worktree_path
-        {
-            // Kill the tmux session first if it exists
-            if has_session && let Some(repo_idx) = state.selected_repo_idx {
-                let repo = &state.repos[repo_idx];
-                let session_name = repo.tmux_session_name(worktree_path);
-                tmux.kill_session(&session_name);
-            }
+    else {
+        return;
+    };
+    state.delete_preview = None;
+    let branch_name = branch_name.clone();
+    let has_session = *has_session;
+    // Without a repo there's nothing to delete from, so nothing gets recorded as pending either
+    let Some(repo) = state.selected_repo_idx.and_then(|idx| state.repos.get(idx)) else {
+        return;
+    };
+    let Some(worktree_path) = state
+        .branches
+        .iter()
+        .find(|b| b.name == branch_name)
+        .and_then(|branch| branch.worktree_path.clone())
+    else {
+        return;
+    };
 
-            let worktree_path = worktree_path.clone();
-            if let Some(repo_idx) = state.selected_repo_idx {
-                let repo_path = state.repos[repo_idx].path.clone();
-                let pending = PendingWorktreeDelete::new(
-                    repo_path,
-                    branch_name.clone(),
-                    worktree_path.clone(),
-                );
-                state.mark_pending_worktree_delete(pending);
-                if let Err(e) = save_pending_worktree_deletes(&state.pending_worktree_deletes) {
-                    state.set_error(&format!("Failed to persist pending deletes: {e}"));
-                }
+    // Kill the tmux session first if it exists
+    if has_session {
+        let session_name = repo.tmux_session_name(&worktree_path);
+        kill_session(tmux, &state.session_journal, &session_name);
+    }
+
+    let repo_path = repo.path.clone();
+    state.mode = Mode::BranchSelect;
+    let pending = PendingWorktreeDelete::new(
+        repo_path.clone(),
+        branch_name.clone(),
+        worktree_path.clone(),
+    );
+    match state.pending_delete_store.begin(pending.clone()) {
+        Ok(true) => {}
+        Ok(false) => {
+            state.set_error(&format!(
+                "{branch_name}'s worktree is already being deleted by another kiosk"
+            ));
+            return;
+        }
+        Err(e) => state.set_error(&format!("Failed to persist pending deletes: {e}")),
+    }
+    state.mark_pending_worktree_delete(pending);
+    spawn_worktree_removal(
+        git,
+        sender,
+        state.delete_journal.clone(),
+        repo_path,
+        worktree_path,
+        branch_name,
+        state.clean.use_trash,
+    );
+}
+
+pub(super) fn handle_toggle_pin(state: &mut AppState) {
+    let Some(repo_path) = state
+        .repo_list
+        .selected
+        .and_then(|sel| state.repo_list.filtered.get(sel))
+        .map(|&(idx, _)| state.repos[idx].path.clone())
+    else {
+        return;
+    };
+
+    match state.pin_store.toggle(&repo_path) {
+        Ok(pinned) => {
+            state.pinned = pinned;
+            sort_repos_preserving_selection(state);
+            // Keep the cursor on the repo that was just (un)pinned
+            if let Some(idx) = state.repos.iter().position(|r| r.path == repo_path) {
+                state.repo_list.selected =
+                    state.repo_list.filtered.iter().position(|&(i, _)| i == idx);
             }
-            state.mode = Mode::BranchSelect;
-            spawn_worktree_removal(git, sender, worktree_path, branch_name);
+            let page_rows = state.active_list_page_rows();
+            update_active_list_scroll_offset(state, page_rows);
         }
+        Err(e) => state.set_error(&format!("Failed to save pinned repos: {e}")),
+    }
+}
+
+pub(super) fn handle_toggle_preview(state: &mut AppState) {
+    state.layout.preview = !state.layout.preview;
+    save_layout(state);
+}
+
+/// Resize the preview pane, showing it if it was hidden.
+pub(super) fn handle_resize_preview(state: &mut AppState, delta: i16) {
+    state.layout.preview = true;
+    state.layout.resize_preview(delta);
+    save_layout(state);
+}
+
+fn save_layout(state: &mut AppState) {
+    if let Err(e) = state.layout_store.save(state.layout) {
+        state.set_error(&format!("Failed to save layout: {e}"));
     }
 }
 
+pub(super) fn handle_hide_repo(state: &mut AppState) {
+    let Some(idx) = state
+        .repo_list
+        .selected
+        .and_then(|sel| state.repo_list.filtered.get(sel))
+        .map(|&(idx, _)| idx)
+    else {
+        return;
+    };
+    let repo_path = state.repos[idx].path.clone();
+
+    match state.hidden_store.hide(&repo_path) {
+        Ok(hidden) => {
+            state.repo_filter.hidden = hidden;
+            let selected_repo_path = state
+                .selected_repo_idx
+                .and_then(|i| state.repos.get(i).map(|r| r.path.clone()));
+            state.repos.remove(idx);
+            state.selected_repo_idx =
+                selected_repo_path.and_then(|path| state.repos.iter().position(|r| r.path == path));
+            // The cursor stays in place and lands on the next repo
+            let names: Vec<&str> = state.repos.iter().map(|r| r.name.as_str()).collect();
+            rebuild_filtered_preserving_search(&mut state.repo_list, &names, state.search);
+            let page_rows = state.active_list_page_rows();
+            update_active_list_scroll_offset(state, page_rows);
+        }
+        Err(e) => state.set_error(&format!("Failed to save hidden repos: {e}")),
+    }
+}
+
+pub(super) fn handle_undo_delete(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+) {
+    let Some(repo_path) = state
+        .selected_repo_idx
+        .and_then(|idx| state.repos.get(idx))
+        .map(|repo| repo.path.clone())
+    else {
+        return;
+    };
+
+    let Some(entry) = state.delete_journal.latest(Some(&repo_path), None) else {
+        state.set_error("No deleted worktree to restore");
+        return;
+    };
+
+    let cancelled = state.start_loading(format!("Restoring worktree for {}...", entry.branch_name));
+    spawn_worktree_restore(git, sender, state.delete_journal.clone(), entry, cancelled);
+}
+
+pub(super) fn handle_edit_note(state: &mut AppState) {
+    let Some(branch) = state
+        .branch_list
+        .selected
+        .and_then(|sel| state.branch_list.filtered.get(sel))
+        .and_then(|&(idx, _)| state.branches.get(idx))
+    else {
+        return;
+    };
+    if !branch.is_local() {
+        state.set_error("Only local branches can have notes");
+        return;
+    }
+    let mut input = TextInput::new();
+    if let Some(note) = &branch.note {
+        input.text.clone_from(note);
+        input.cursor = note.len();
+    }
+    state.note_edit = Some(NoteEdit {
+        branch: branch.name.clone(),
+        input,
+    });
+    state.mode = Mode::EditNote;
+}
+
+/// Save the typed note; an empty one removes the branch's note.
+pub(super) fn handle_save_note(state: &mut AppState) {
+    let Some(edit) = state.note_edit.take() else {
+        return;
+    };
+    state.mode = Mode::BranchSelect;
+    let Some(repo_path) = state
+        .selected_repo_idx
+        .and_then(|idx| state.repos.get(idx))
+        .map(|repo| repo.path.clone())
+    else {
+        return;
+    };
+    if let Err(e) = state
+        .note_store
+        .set(&repo_path, &edit.branch, &edit.input.text)
+    {
+        state.set_error(&format!("Failed to save note: {e}"));
+        return;
+    }
+    let note = edit.input.text.trim();
+    if let Some(branch) = state
+        .branches
+        .iter_mut()
+        .find(|branch| branch.is_local() && branch.name == edit.branch)
+    {
+        branch.note = (!note.is_empty()).then(|| note.to_string());
+    }
+}
+
+pub(super) fn handle_fetch_remotes(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+) {
+    let Some(repo_path) = state
+        .selected_repo_idx
+        .and_then(|idx| state.repos.get(idx))
+        .map(|repo| repo.path.clone())
+    else {
+        return;
+    };
+    if state.fetching_remotes {
+        return;
+    }
+    let local_names = state
+        .branches
+        .iter()
+        .filter(|branch| branch.is_local())
+        .map(|branch| branch.name.clone())
+        .collect();
+    start_git_fetch(state, git, sender, repo_path, local_names);
+}
+
+/// Fetch the selected repo's remotes in the background, unless another kiosk is already
+/// fetching them. The time is only noted while `fetch.min_interval_secs` limits how often
+/// that happens.
+pub(super) fn start_git_fetch(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+    repo_path: PathBuf,
+    local_names: Vec<String>,
+) {
+    let lock = match state.fetch_locks.claim(&repo_path) {
+        FetchClaim::Granted(lock) => lock,
+        FetchClaim::Busy { pid } => {
+            let pid = pid.map_or_else(String::new, |pid| format!(" (pid {pid})"));
+            let repo = state
+                .repos
+                .iter()
+                .find(|repo| repo.path == repo_path)
+                .map_or_else(|| repo_path.display().to_string(), |repo| repo.name.clone());
+            state.set_error(&format!(
+                "Another kiosk{pid} is already fetching {repo}, so this one won't"
+            ));
+            return;
+        }
+    };
+    if state.fetch.min_interval_secs > 0
+        && let Err(e) = state.fetch_history.record_fetch(&repo_path)
+    {
+        tracing::warn!("failed to record fetch of {}: {e}", repo_path.display());
+    }
+    state.fetching_remotes = true;
+    state.fetch_progress = None;
+    spawn_git_fetch(
+        git,
+        sender,
+        repo_path,
+        local_names,
+        state.fetch.clone(),
+        lock,
+    );
+}
+
 pub(super) fn handle_open_branch(
     state: &mut AppState,
     git: &Arc<dyn GitProvider>,
@@ -167,71 +816,46 @@ pub(super) fn handle_open_branch(
 
                 if let Some(wt_path) = &branch.worktree_path {
                     let session_name = repo.tmux_session_name(wt_path);
-                    return Some(OpenAction::Open {
-                        path: wt_path.clone(),
-                        session_name,
-                        split_command: state.split_command.clone(),
-                    });
+                    return Some(open_session(state, wt_path.clone(), session_name, None));
                 }
-                let is_remote = branch.remote.is_some();
+                let remote = branch.remote.clone();
                 match worktree_dir(repo, &branch.name) {
                     Ok(wt_path) => {
                         let branch_name = branch.name.clone();
                         let session_name = repo.tmux_session_name(&wt_path);
-                        if is_remote {
-                            state.mode = Mode::Loading(format!(
-                                "Checking out remote branch {branch_name}..."
-                            ));
-                            spawn_tracking_worktree_creation(
-                                git,
-                                sender,
-                                repo.path.clone(),
-                                branch_name,
-                                wt_path,
-                                session_name,
-                            );
+                        let repo_path = repo.path.clone();
+                        let message = if let Some(remote) = &remote {
+                            format!("Checking out {remote}/{branch_name}...")
                         } else {
-                            state.mode =
-                                Mode::Loading(format!("Creating worktree for {branch_name}..."));
-                            spawn_worktree_creation(
-                                git,
-                                sender,
-                                repo.path.clone(),
-                                branch_name,
-                                wt_path,
-                                session_name,
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        state.set_error(&format!("Failed to determine worktree path: {e}"));
-                        return None;
-                    }
-                }
-            }
-        }
-        Mode::SelectBaseBranch => {
-            if let Some(flow) = &state.base_branch_selection
-                && let Some(sel) = flow.list.selected
-                && let Some(&(idx, _)) = flow.list.filtered.get(sel)
-            {
-                let base = flow.bases[idx].clone();
-                let new_name = flow.new_name.clone();
-                let repo_idx = state.selected_repo_idx?;
-                let repo = &state.repos[repo_idx];
-                match worktree_dir(repo, &new_name) {
-                    Ok(wt_path) => {
-                        let session_name = repo.tmux_session_name(&wt_path);
-                        state.mode =
-                            Mode::Loading(format!("Creating branch {new_name} from {base}..."));
-                        spawn_branch_and_worktree_creation(
+                            format!("Creating worktree for {branch_name}...")
+                        };
+                        let cancelled = state.start_loading(message);
+                        spawn_worktree_creation(
                             git,
                             sender,
-                            repo.path.clone(),
-                            new_name,
-                            base,
-                            wt_path,
-                            session_name,
+                            state.pending_create_store.clone(),
+                            PendingWorktreeCreate::new(
+                                repo_path.clone(),
+                                Some(branch_name.clone()),
+                                wt_path,
+                            ),
+                            WorktreeSession {
+                                name: session_name,
+                                base: None,
+                            },
+                            cancelled,
+                            move |git, wt_path| {
+                                if let Some(remote) = &remote {
+                                    git.create_tracking_branch_and_worktree(
+                                        &repo_path,
+                                        remote,
+                                        &branch_name,
+                                        wt_path,
+                                    )
+                                } else {
+                                    git.add_worktree(&repo_path, &branch_name, wt_path)
+                                }
+                            },
                         );
                     }
                     Err(e) => {
@@ -241,15 +865,238 @@ pub(super) fn handle_open_branch(
                 }
             }
         }
+        Mode::SelectBaseBranch => create_new_branch(state, git, sender),
+        Mode::GlobalSearch => return open_global_search_entry(state),
+        Mode::TagSelect => return open_tag(state, git, sender),
+        Mode::WorkspaceSelect => return handle_confirm_workspace(state),
         Mode::RepoSelect
+        | Mode::SelectCloneDir
+        | Mode::NewRepo
         | Mode::ConfirmWorktreeDelete { .. }
+        | Mode::ConfirmResume { .. }
+        | Mode::ConfirmRecover { .. }
+        | Mode::EditNote
+        | Mode::BranchNameFields
         | Mode::Loading(_)
         | Mode::Help { .. }
+        | Mode::CommandPalette { .. }
         | Mode::Setup(_) => {}
     }
     None
 }
 
+/// Create the new branch from the selected base, with a worktree, in the background.
+fn create_new_branch(state: &mut AppState, git: &Arc<dyn GitProvider>, sender: &EventSender) {
+    let Some(flow) = &state.base_branch_selection else {
+        return;
+    };
+    let Some(&(idx, _)) = flow
+        .list
+        .selected
+        .and_then(|sel| flow.list.filtered.get(sel))
+    else {
+        return;
+    };
+    let base = flow.bases[idx].clone();
+    let new_name = flow.new_name.clone();
+    let note = flow.note.clone();
+    let notes = state.note_store.clone();
+    let Some(repo) = state.selected_repo_idx.and_then(|idx| state.repos.get(idx)) else {
+        return;
+    };
+    let wt_path = match worktree_dir(repo, &new_name) {
+        Ok(wt_path) => wt_path,
+        Err(e) => {
+            state.set_error(&format!("Failed to determine worktree path: {e}"));
+            return;
+        }
+    };
+    let session_name = repo.tmux_session_name(&wt_path);
+    let repo_path = repo.path.clone();
+    let cancelled = state.start_loading(format!("Creating branch {new_name} from {base}..."));
+    spawn_worktree_creation(
+        git,
+        sender,
+        state.pending_create_store.clone(),
+        PendingWorktreeCreate::new(repo_path.clone(), Some(new_name.clone()), wt_path),
+        WorktreeSession {
+            name: session_name,
+            base: Some(base.clone()),
+        },
+        cancelled,
+        move |git, wt_path| {
+            git.create_branch_and_worktree(&repo_path, &new_name, &base, wt_path)?;
+            if let Some(note) = note
+                && let Err(e) = notes.set(&repo_path, &new_name, &note)
+            {
+                tracing::warn!("failed to save note for {new_name}: {e}");
+            }
+            Ok(())
+        },
+    );
+}
+
+/// Call `function` from `[script]` on the selected repo or branch, typing any text it returns
+/// into the selection's session.
+pub(super) fn handle_run_script<T: TmuxProvider + ?Sized>(
+    state: &mut AppState,
+    tmux: &T,
+    function: &str,
+) {
+    let Some((event, session)) = script_event(state) else {
+        return;
+    };
+    let script = match Script::load(&state.script) {
+        Ok(Some(script)) => script,
+        Ok(None) => {
+            state.set_error("No script to run: set `file` under [script] in the config");
+            return;
+        }
+        Err(e) => {
+            state.set_error(&format!("Failed to load the script: {e:#}"));
+            return;
+        }
+    };
+    if !script.defines(function) {
+        state.set_error(&format!("The script has no function {function}(event)"));
+        return;
+    }
+    match script.call(function, &event) {
+        Ok(None) => {}
+        Ok(Some(text)) => match session {
+            Some(session) if tmux.session_exists(&session) => {
+                if let Err(e) = tmux.send_keys(&session, &text) {
+                    state.set_error(&format!("Failed to send to {session}: {e}"));
+                }
+            }
+            _ => state.set_error(&format!(
+                "{function} returned text, but there's no session to type it into"
+            )),
+        },
+        Err(e) => state.set_error(&format!("Script failed: {e:#}")),
+    }
+}
+
+/// What a script bound to a key is told about the selection, and the selection's session.
+fn script_event(state: &AppState) -> Option<(serde_json::Value, Option<String>)> {
+    let (repo, branch, worktree) = match state.mode {
+        Mode::RepoSelect => {
+            let &(idx, _) = state.repo_list.filtered.get(state.repo_list.selected?)?;
+            let repo = &state.repos[idx];
+            (repo, None, Some(repo.path.clone()))
+        }
+        Mode::BranchSelect => {
+            let &(idx, _) = state
+                .branch_list
+                .filtered
+                .get(state.branch_list.selected?)?;
+            let entry = &state.branches[idx];
+            let repo = state.repos.get(state.selected_repo_idx?)?;
+            (repo, Some(entry.name.clone()), entry.worktree_path.clone())
+        }
+        _ => return None,
+    };
+    let session = worktree
+        .as_deref()
+        .map(|worktree| repo.tmux_session_name(worktree));
+    let event = serde_json::json!({
+        "repo": repo.name,
+        "branch": branch,
+        "worktree": worktree,
+        "session": session,
+    });
+    Some((event, session))
+}
+
+/// Keep `kiosk pick` within its scope: drop actions that would leave it or launch something,
+/// and send Enter on a repo to its branch view when only worktrees may be picked.
+pub(super) fn restrict_to_pick_scope(action: Action, pick: Option<PickScope>) -> Option<Action> {
+    match (pick, action) {
+        (Some(_), Action::OpenInEditor | Action::ShowWorkspaces)
+        | (Some(PickScope::Repos), Action::EnterRepo | Action::StartGlobalSearch) => None,
+        (Some(PickScope::Branches), Action::OpenRepo) => Some(Action::EnterRepo),
+        (_, action) => Some(action),
+    }
+}
+
+pub(super) fn handle_open_in_editor(state: &mut AppState) -> Option<OpenAction> {
+    let path = match state.mode {
+        Mode::RepoSelect => {
+            let &(idx, _) = state.repo_list.filtered.get(state.repo_list.selected?)?;
+            state.repos[idx].path.clone()
+        }
+        Mode::BranchSelect => {
+            let &(idx, _) = state
+                .branch_list
+                .filtered
+                .get(state.branch_list.selected?)?;
+            let Some(path) = state.branches[idx].worktree_path.clone() else {
+                state.set_error("No worktree to open; open the branch to create one");
+                return None;
+            };
+            path
+        }
+        _ => return None,
+    };
+    Some(OpenAction::Editor {
+        path,
+        editor: state.editor.clone(),
+    })
+}
+
+/// Open the selected tag's detached worktree, adding it in the background if there isn't one.
+fn open_tag(
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+) -> Option<OpenAction> {
+    let tag_select = state.tag_select.as_ref()?;
+    let &(idx, _) = tag_select.list.filtered.get(tag_select.list.selected?)?;
+    let entry = &tag_select.tags[idx];
+    let repo = state.repos.get(state.selected_repo_idx?)?;
+    if let Some(wt_path) = &entry.worktree_path {
+        let session_name = repo.tmux_session_name(wt_path);
+        return Some(open_session(state, wt_path.clone(), session_name, None));
+    }
+    let tag = entry.tag.name.clone();
+    let commit = entry.tag.commit.clone();
+    let wt_path = match worktree_dir(repo, &tag) {
+        Ok(wt_path) => wt_path,
+        Err(e) => {
+            state.set_error(&format!("Failed to determine worktree path: {e}"));
+            return None;
+        }
+    };
+    let session_name = repo.tmux_session_name(&wt_path);
+    let repo_path = repo.path.clone();
+    let cancelled = state.start_loading(format!("Creating worktree for tag {tag}..."));
+    spawn_worktree_creation(
+        git,
+        sender,
+        state.pending_create_store.clone(),
+        PendingWorktreeCreate::new(repo_path.clone(), None, wt_path),
+        WorktreeSession {
+            name: session_name,
+            base: None,
+        },
+        cancelled,
+        move |git, wt_path| git.add_detached_worktree(&repo_path, &commit, wt_path),
+    );
+    None
+}
+
+fn open_global_search_entry(state: &AppState) -> Option<OpenAction> {
+    let search = state.global_search.as_ref()?;
+    let &(idx, _) = search.list.filtered.get(search.list.selected?)?;
+    let entry = &search.entries[idx];
+    Some(open_session(
+        state,
+        entry.path.clone(),
+        entry.session_name.clone(),
+        None,
+    ))
+}
+
 pub(super) fn enter_branch_select<T: TmuxProvider + ?Sized + 'static>(
     state: &mut AppState,
     repo_idx: usize,
@@ -278,64 +1125,67 @@ pub(super) fn enter_branch_select_with_loading<T: TmuxProvider + ?Sized + 'stati
     }
     state.loading_branches = true;
     state.fetching_remotes = false;
-    spawn_branch_loading(git, tmux, sender, repo, cwd);
+    let agents = (state.branch_columns.contains(&BranchColumn::Agent)
+        && !state.skip_agent_repos.contains(&repo.name))
+    .then(|| Arc::clone(&state.agent_cache));
+    spawn_branch_loading(git, tmux, sender, repo, cwd, agents);
 }
 
-pub(super) fn handle_search_push(state: &mut AppState, matcher: &SkimMatcherV2, c: char) {
+pub(super) fn handle_search_push(state: &mut AppState, sender: &EventSender, c: char) {
     if let Some(input) = state.active_text_input() {
         input.insert_char(c);
     }
-    post_text_edit(state, matcher);
+    post_text_edit(state, sender);
 }
 
-pub(super) fn handle_search_pop(state: &mut AppState, matcher: &SkimMatcherV2) {
+pub(super) fn handle_search_pop(state: &mut AppState, sender: &EventSender) {
     if let Some(input) = state.active_text_input() {
         input.backspace();
     }
-    post_text_edit(state, matcher);
+    post_text_edit(state, sender);
 }
 
-pub(super) fn handle_search_delete_word(state: &mut AppState, matcher: &SkimMatcherV2) {
+pub(super) fn handle_search_delete_word(state: &mut AppState, sender: &EventSender) {
     if let Some(input) = state.active_text_input() {
         input.delete_word();
     }
-    post_text_edit(state, matcher);
+    post_text_edit(state, sender);
 }
 
-pub(super) fn handle_search_delete_forward(state: &mut AppState, matcher: &SkimMatcherV2) {
+pub(super) fn handle_search_delete_forward(state: &mut AppState, sender: &EventSender) {
     if let Some(input) = state.active_text_input() {
         input.delete_forward_char();
     }
-    post_text_edit(state, matcher);
+    post_text_edit(state, sender);
 }
 
-pub(super) fn handle_search_delete_word_forward(state: &mut AppState, matcher: &SkimMatcherV2) {
+pub(super) fn handle_search_delete_word_forward(state: &mut AppState, sender: &EventSender) {
     if let Some(input) = state.active_text_input() {
         input.delete_word_forward();
     }
-    post_text_edit(state, matcher);
+    post_text_edit(state, sender);
 }
 
-pub(super) fn handle_search_delete_to_start(state: &mut AppState, matcher: &SkimMatcherV2) {
+pub(super) fn handle_search_delete_to_start(state: &mut AppState, sender: &EventSender) {
     if let Some(input) = state.active_text_input() {
         input.delete_to_start();
     }
-    post_text_edit(state, matcher);
+    post_text_edit(state, sender);
 }
 
-pub(super) fn handle_search_delete_to_end(state: &mut AppState, matcher: &SkimMatcherV2) {
+pub(super) fn handle_search_delete_to_end(state: &mut AppState, sender: &EventSender) {
     if let Some(input) = state.active_text_input() {
         input.delete_to_end();
     }
-    post_text_edit(state, matcher);
+    post_text_edit(state, sender);
 }
 
 /// Dispatch post-edit updates: setup completions or fuzzy filter.
-fn post_text_edit(state: &mut AppState, matcher: &SkimMatcherV2) {
+fn post_text_edit(state: &mut AppState, sender: &EventSender) {
     if matches!(state.mode, Mode::Setup(SetupStep::SearchDirs)) {
         update_setup_completions(state);
     } else {
-        update_active_filter(state, matcher);
+        update_active_filter(state, sender);
     }
 }
 
@@ -479,25 +1329,97 @@ fn update_setup_completions(state: &mut AppState) {
     setup.selected_completion = None;
 }
 
-fn update_active_filter(state: &mut AppState, matcher: &SkimMatcherV2) {
+fn update_active_filter(state: &mut AppState, sender: &EventSender) {
+    let Some(items) = active_filter_items(state) else {
+        return;
+    };
+    let search = state.search;
+    let Some(list) = state.active_list_mut() else {
+        return;
+    };
+    if items.len() >= BACKGROUND_FILTER_MIN_ITEMS && !list.input.text.is_empty() {
+        let query = list.input.text.clone();
+        spawn_fuzzy_filter(list.items_id, items.into(), query, search, sender);
+        return;
+    }
+    // A background filter for an earlier edit would overwrite this one
+    sender.next_filter_generation();
+    apply_fuzzy_filter(list, &items, &Matcher::new(search));
+    group_help_sections(state);
+}
+
+/// Show a chunk of matches scored by a background filter, unless the search or the list
+/// changed since.
+pub(super) fn apply_filter_results(
+    state: &mut AppState,
+    query: &str,
+    items_id: u64,
+    items: &[String],
+    first: bool,
+    matches: Vec<(usize, i64)>,
+) {
+    let Some(list) = state.active_list_mut() else {
+        return;
+    };
+    if list.items_id != items_id || list.input.text != query {
+        return;
+    }
+    if first {
+        list.filtered = matches;
+    } else {
+        merge_matches(&mut list.filtered, matches, items);
+    }
+    select_first_match(list);
+    group_help_sections(state);
+}
+
+/// The text each item of the current mode's list is searched by.
+fn active_filter_items(state: &AppState) -> Option<Vec<String>> {
     match state.mode {
-        Mode::RepoSelect => {
-            let names: Vec<String> = state.repos.iter().map(|r| r.name.clone()).collect();
-            apply_fuzzy_filter(&mut state.repo_list, &names, matcher);
-        }
-        Mode::BranchSelect => {
-            let names: Vec<String> = state.branches.iter().map(|b| b.name.clone()).collect();
-            apply_fuzzy_filter(&mut state.branch_list, &names, matcher);
-        }
-        Mode::SelectBaseBranch => {
-            if let Some(flow) = &mut state.base_branch_selection {
-                let bases = flow.bases.clone();
-                apply_fuzzy_filter(&mut flow.list, &bases, matcher);
-            }
-        }
-        Mode::Help { .. } => {
-            if let Some(overlay) = &mut state.help_overlay {
-                let search_items: Vec<String> = overlay
+        Mode::RepoSelect => Some(state.repos.iter().map(|r| r.name.clone()).collect()),
+        Mode::BranchSelect => Some(
+            state
+                .branches
+                .iter()
+                .map(BranchEntry::search_text)
+                .collect(),
+        ),
+        Mode::SelectBaseBranch => state
+            .base_branch_selection
+            .as_ref()
+            .map(|flow| flow.bases.clone()),
+        Mode::SelectCloneDir => state
+            .clone_dir_selection
+            .as_ref()
+            .map(|flow| flow.dirs.iter().map(|d| d.display().to_string()).collect()),
+        Mode::NewRepo => state
+            .new_repo_selection
+            .as_ref()
+            .map(|flow| flow.templates.clone()),
+        Mode::TagSelect => state.tag_select.as_ref().map(|tag_select| {
+            tag_select
+                .tags
+                .iter()
+                .map(|entry| entry.tag.name.clone())
+                .collect()
+        }),
+        Mode::WorkspaceSelect => state.workspace_select.as_ref().map(|select| {
+            select
+                .workspaces
+                .iter()
+                .map(|workspace| workspace.name.clone())
+                .collect()
+        }),
+        Mode::GlobalSearch => state.global_search.as_ref().map(|search| {
+            search
+                .entries
+                .iter()
+                .map(GlobalSearchEntry::label)
+                .collect()
+        }),
+        Mode::Help { .. } | Mode::CommandPalette { .. } => {
+            state.help_overlay.as_ref().map(|overlay| {
+                overlay
                     .rows
                     .iter()
                     .map(|row| {
@@ -506,40 +1428,69 @@ fn update_active_filter(state: &mut AppState, matcher: &SkimMatcherV2) {
                             row.section_name, row.key_display, row.command, row.description
                         )
                     })
-                    .collect();
-                apply_fuzzy_filter(&mut overlay.list, &search_items, matcher);
-                // Stable-sort filtered results by section_index so that
-                // compute_help_layout never emits duplicate section headers
-                // when fuzzy scoring reorders items across sections.
-                overlay.list.filtered.sort_by_key(|(row_idx, _score)| {
-                    overlay.rows.get(*row_idx).map_or(0, |r| r.section_index)
-                });
-            }
+                    .collect()
+            })
         }
-        _ => {}
+        _ => None,
+    }
+}
+
+/// Stable-sort filtered help rows by `section_index` so that `compute_help_layout` never emits
+/// duplicate section headers when fuzzy scoring reorders items across sections.
+fn group_help_sections(state: &mut AppState) {
+    if !matches!(state.mode, Mode::Help { .. } | Mode::CommandPalette { .. }) {
+        return;
+    }
+    if let Some(overlay) = &mut state.help_overlay {
+        overlay.list.filtered.sort_by_key(|(row_idx, _score)| {
+            overlay.rows.get(*row_idx).map_or(0, |r| r.section_index)
+        });
     }
 }
 
-fn apply_fuzzy_filter(list: &mut SearchableList, items: &[String], matcher: &SkimMatcherV2) {
+fn apply_fuzzy_filter(list: &mut SearchableList, items: &[String], matcher: &Matcher) {
     if list.input.text.is_empty() {
         list.filtered = items.iter().enumerate().map(|(i, _)| (i, 0)).collect();
     } else {
-        let mut scored: Vec<(usize, i64)> = items
-            .iter()
-            .enumerate()
-            .filter_map(|(i, item)| {
-                matcher
-                    .fuzzy_match(item, &list.input.text)
-                    .map(|score| (i, score))
-            })
-            .collect();
-        scored.sort_by(|a, b| {
-            b.1.cmp(&a.1)
-                .then_with(|| items[a.0].len().cmp(&items[b.0].len()))
-                .then_with(|| items[a.0].cmp(&items[b.0]))
-        });
+        let mut scored = matcher.matches(items, 0..items.len(), &list.input.text);
+        sort_matches(&mut scored, items);
         list.filtered = scored;
     }
+    select_first_match(list);
+}
+
+/// Best match first, then shorter items, then alphabetical.
+pub(super) fn sort_matches(scored: &mut [(usize, i64)], items: &[String]) {
+    scored.sort_by(|a, b| match_order(a, b, items));
+}
+
+fn match_order(a: &(usize, i64), b: &(usize, i64), items: &[String]) -> std::cmp::Ordering {
+    b.1.cmp(&a.1)
+        .then_with(|| items[a.0].len().cmp(&items[b.0].len()))
+        .then_with(|| items[a.0].cmp(&items[b.0]))
+}
+
+/// Merge `new` matches into `filtered`, both in [`sort_matches`] order, keeping that order.
+fn merge_matches(filtered: &mut Vec<(usize, i64)>, new: Vec<(usize, i64)>, items: &[String]) {
+    if new.is_empty() {
+        return;
+    }
+    let old = std::mem::take(filtered);
+    filtered.reserve(old.len() + new.len());
+    let (mut old, mut new) = (old.into_iter().peekable(), new.into_iter().peekable());
+    while let (Some(a), Some(b)) = (old.peek(), new.peek()) {
+        let next = if match_order(a, b, items).is_le() {
+            old.next()
+        } else {
+            new.next()
+        };
+        filtered.extend(next);
+    }
+    filtered.extend(old);
+    filtered.extend(new);
+}
+
+fn select_first_match(list: &mut SearchableList) {
     list.selected = if list.filtered.is_empty() {
         None
     } else {
@@ -561,6 +1512,7 @@ mod tests {
             filtered: Vec::new(),
             selected: None,
             scroll_offset: 0,
+            items_id: 0,
         }
     }
 
@@ -578,7 +1530,7 @@ mod tests {
             .map(String::from)
             .collect();
         let mut list = make_list("");
-        let matcher = SkimMatcherV2::default();
+        let matcher = Matcher::default();
 
         apply_fuzzy_filter(&mut list, &items, &matcher);
 
@@ -589,6 +1541,26 @@ mod tests {
         assert_eq!(list.selected, Some(0));
     }
 
+    #[test]
+    fn merged_chunks_match_sorting_everything_at_once() {
+        let items: Vec<String> = ["cli-b", "x-cli", "cli", "cli-a", "clip", "c-l-i"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let matcher = Matcher::default();
+        let mut everything = matcher.matches(&items, 0..items.len(), "cli");
+        sort_matches(&mut everything, &items);
+
+        let mut merged = Vec::new();
+        for chunk in [0..2, 2..4, 4..6] {
+            let mut scored = matcher.matches(&items, chunk, "cli");
+            sort_matches(&mut scored, &items);
+            merge_matches(&mut merged, scored, &items);
+        }
+
+        assert_eq!(merged, everything);
+    }
+
     #[test]
     fn equal_scores_sorted_by_length_then_alphabetically() {
         // All items start with "cli" so the match occurs at the same position,
@@ -598,7 +1570,7 @@ mod tests {
             .map(String::from)
             .collect();
         let mut list = make_list("cli");
-        let matcher = SkimMatcherV2::default();
+        let matcher = Matcher::default();
 
         apply_fuzzy_filter(&mut list, &items, &matcher);
 
@@ -616,7 +1588,7 @@ mod tests {
             .map(String::from)
             .collect();
         let mut list = make_list("main");
-        let matcher = SkimMatcherV2::default();
+        let matcher = Matcher::default();
 
         apply_fuzzy_filter(&mut list, &items, &matcher);
 
@@ -634,7 +1606,7 @@ mod tests {
             .map(String::from)
             .collect();
         let mut list = make_list("zzzzz");
-        let matcher = SkimMatcherV2::default();
+        let matcher = Matcher::default();
 
         apply_fuzzy_filter(&mut list, &items, &matcher);
 
@@ -649,7 +1621,7 @@ mod tests {
             .map(String::from)
             .collect();
         let mut list = make_list("foo");
-        let matcher = SkimMatcherV2::default();
+        let matcher = Matcher::default();
 
         apply_fuzzy_filter(&mut list, &items, &matcher);
 
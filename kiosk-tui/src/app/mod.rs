@@ -1,51 +1,95 @@
 mod actions;
+mod mouse;
 mod spawn;
 
-use crate::{components, keymap};
+use crate::{components, keymap, matcher::Matcher};
 use actions::{
-    enter_branch_select, enter_branch_select_with_loading, handle_confirm_delete,
-    handle_delete_worktree, handle_go_back, handle_open_branch, handle_search_delete_forward,
-    handle_search_delete_to_end, handle_search_delete_to_start, handle_search_delete_word,
-    handle_search_delete_word_forward, handle_search_pop, handle_search_push, handle_setup_add_dir,
-    handle_setup_cancel, handle_setup_continue, handle_setup_move_selection,
-    handle_setup_tab_complete, handle_show_help, handle_start_new_branch,
+    apply_filter_results, enter_branch_select, enter_branch_select_with_loading,
+    handle_add_to_workspace, handle_cancel_resume, handle_confirm_clone, handle_confirm_delete,
+    handle_confirm_new_repo, handle_confirm_workspace, handle_delete_worktree, handle_edit_note,
+    handle_fetch_remotes, handle_go_back, handle_hide_repo, handle_open_branch,
+    handle_open_in_editor, handle_resize_preview, handle_run_script, handle_save_branch_name_field,
+    handle_save_note, handle_search_delete_forward, handle_search_delete_to_end,
+    handle_search_delete_to_start, handle_search_delete_word, handle_search_delete_word_forward,
+    handle_search_pop, handle_search_push, handle_setup_add_dir, handle_setup_cancel,
+    handle_setup_continue, handle_setup_move_selection, handle_setup_tab_complete,
+    handle_show_command_palette, handle_show_help, handle_show_tags, handle_show_workspaces,
+    handle_start_clone, handle_start_global_search, handle_start_new_branch, handle_start_new_repo,
+    handle_toggle_pin, handle_toggle_preview, handle_undo_delete, restrict_to_pick_scope,
+    start_git_fetch, start_ticket_branch, take_palette_command,
+};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind, MouseEvent,
 };
-use crossterm::event::{self, Event, KeyEventKind};
-use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use kiosk_core::{
     action::Action,
-    config::{KeysConfig, keys::Command},
+    config::{AgentProfile, Config, KeysConfig, SearchConfig, keys::Command},
     event::AppEvent,
-    git::GitProvider,
-    pending_delete::save_pending_worktree_deletes,
-    state::{AppState, BranchEntry, Mode, SearchableList},
+    git::{GitProgress, GitProvider, Repo},
+    layout::LayoutPrefs,
+    recovery::Interrupted,
+    state::{
+        AppState, BranchEntry, DeletePreview, GlobalSearchEntry, Mode, PickScope, SearchableList,
+    },
     tmux::TmuxProvider,
 };
+use mouse::{ClickTracker, resolve_mouse_action};
 use ratatui::{
-    DefaultTerminal, Frame,
-    layout::{Alignment, Constraint, Layout, Rect},
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Layout, Position, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Padding, Paragraph},
 };
-use spawn::spawn_repo_discovery;
+use spawn::{spawn_config_watcher, spawn_repo_discovery, spawn_session_watcher};
 use std::{
     fmt::Write as _,
+    io,
     path::PathBuf,
     sync::{
         Arc,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         mpsc,
     },
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 /// What to do after the TUI exits
 pub enum OpenAction {
     Open {
         path: PathBuf,
+        /// Name of the repo `path` belongs to, for `{repo}` and `[session.repo_env]`
+        repo: Option<String>,
         session_name: String,
         split_command: Option<String>,
+        /// Typed into the main pane when the session is created
+        run: Option<String>,
+        /// Started in the main pane in place of `run`, even in an existing session
+        agent: Option<AgentProfile>,
+        /// The branch a new branch was just created from, else the repo's default branch is
+        /// `{base}`. Set only for a new branch, whose session then runs `on_branch_created`
+        /// as `kiosk open` does
+        base: Option<String>,
+    },
+    /// Open `path` in an editor: `editor` from the config, else `$VISUAL` or `$EDITOR`
+    Editor {
+        path: PathBuf,
+        editor: Option<String>,
+    },
+    /// Print `path` on stdout (`kiosk pick`)
+    PrintPath {
+        path: PathBuf,
+    },
+    /// Recreate the sessions lost with the tmux server (`kiosk resume --all`)
+    Resume,
+    /// Finish the interrupted worktree operations, or undo them if `undo` (`kiosk recover`)
+    Recover {
+        undo: bool,
+    },
+    /// Open every session of a workspace (`kiosk workspace open`)
+    Workspace {
+        name: String,
     },
     /// Setup wizard completed — dirs are stored in `AppState.setup`
     SetupComplete,
@@ -57,6 +101,8 @@ pub enum OpenAction {
 pub struct EventSender {
     tx: mpsc::Sender<AppEvent>,
     cancel: Arc<AtomicBool>,
+    /// Bumped for every search edit, so a background filter can tell it's been superseded
+    filter_generation: Arc<AtomicU64>,
 }
 
 impl EventSender {
@@ -64,17 +110,116 @@ impl EventSender {
     pub fn send(&self, event: AppEvent) {
         let _ = self.tx.send(event);
     }
+
+    /// Supersede any background filter still running, returning the new generation
+    fn next_filter_generation(&self) -> u64 {
+        self.filter_generation.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn is_current_filter(&self, generation: u64) -> bool {
+        self.filter_generation.load(Ordering::Relaxed) == generation
+    }
 }
 
 const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
+/// Settings from the config file that the TUI reads on every frame, replaced when the
+/// config is reloaded.
+struct LiveConfig {
+    theme: crate::theme::Theme,
+    keys: KeysConfig,
+}
+
 fn initialize_repo_scan(state: &mut AppState) {
     state.loading_repos = true;
     state.seen_repo_paths = state.repos.iter().map(|repo| repo.path.clone()).collect();
 }
 
-pub fn run(
-    terminal: &mut DefaultTerminal,
+/// Run the TUI until the user opens something or quits. It draws to whatever `terminal`
+/// writes to, so `kiosk pick` can keep stdout free for the picked path.
+pub fn run<W: io::Write>(
+    terminal: &mut Terminal<CrosstermBackend<W>>,
+    state: &mut AppState,
+    git: &Arc<dyn GitProvider>,
+    tmux: &Arc<dyn TmuxProvider>,
+    theme: &crate::theme::Theme,
+    keys: &kiosk_core::config::KeysConfig,
+    search_dirs: Vec<(std::path::PathBuf, u16)>,
+) -> anyhow::Result<Option<OpenAction>> {
+    set_mouse_capture(terminal.backend_mut(), state.mouse)?;
+    let result = run_loop(terminal, state, git, tmux, theme, keys, search_dirs);
+    if state.mouse {
+        set_mouse_capture(terminal.backend_mut(), false)?;
+    }
+    let pick = state.pick;
+    result.map(|action| action.map(|action| picked(action, pick)))
+}
+
+/// [`run`] against `runtime`'s providers, with the theme, keys and search dirs of its config.
+pub fn run_runtime<W: io::Write>(
+    terminal: &mut Terminal<CrosstermBackend<W>>,
+    state: &mut AppState,
+    runtime: &kiosk_core::Runtime,
+) -> anyhow::Result<Option<OpenAction>> {
+    let config = runtime.config();
+    let theme = crate::theme::Theme::for_terminal(&config.theme).with_icons(config.icons);
+    run(
+        terminal,
+        state,
+        runtime.git(),
+        runtime.tmux(),
+        &theme,
+        &config.keys,
+        config.resolved_search_dirs(),
+    )
+}
+
+/// When picking, what would have been opened is printed instead.
+fn picked(action: OpenAction, pick: Option<PickScope>) -> OpenAction {
+    match action {
+        OpenAction::Open { path, .. } if pick.is_some() => OpenAction::PrintPath { path },
+        action => action,
+    }
+}
+
+/// Open (or create) the session `session_name` in `path`, set up by the template for the
+/// project kind in `path`, with the agent after `open_with_agent`. `base` is the branch a
+/// new branch in `path` was just created from.
+fn open_session(
+    state: &AppState,
+    path: PathBuf,
+    session_name: String,
+    base: Option<String>,
+) -> OpenAction {
+    let session = state.session_for(&path);
+    // A worktree just created isn't among its repo's worktrees yet, but is in the selected repo
+    let repo = state
+        .repos
+        .iter()
+        .find(|repo| repo.path == path || repo.worktrees.iter().any(|wt| wt.path == path))
+        .or_else(|| state.selected_repo_idx.and_then(|idx| state.repos.get(idx)))
+        .map(|repo| repo.name.clone());
+    OpenAction::Open {
+        path,
+        repo,
+        session_name,
+        split_command: session.split_command,
+        run: session.run,
+        agent: state.launch_agent.then(|| state.agent.clone()).flatten(),
+        base,
+    }
+}
+
+fn set_mouse_capture(out: &mut impl io::Write, enabled: bool) -> io::Result<()> {
+    if enabled {
+        crossterm::execute!(out, EnableMouseCapture)
+    } else {
+        crossterm::execute!(out, DisableMouseCapture)
+    }
+}
+
+fn run_loop<W: io::Write>(
+    terminal: &mut Terminal<CrosstermBackend<W>>,
     state: &mut AppState,
     git: &Arc<dyn GitProvider>,
     tmux: &Arc<dyn TmuxProvider>,
@@ -82,23 +227,49 @@ pub fn run(
     keys: &kiosk_core::config::KeysConfig,
     search_dirs: Vec<(std::path::PathBuf, u16)>,
 ) -> anyhow::Result<Option<OpenAction>> {
-    let matcher = SkimMatcherV2::default();
+    let mut clicks = ClickTracker::default();
     let (tx, rx) = mpsc::channel::<AppEvent>();
     let cancel = Arc::new(AtomicBool::new(false));
     let event_sender = EventSender {
         tx,
         cancel: Arc::clone(&cancel),
+        filter_generation: Arc::default(),
     };
     let spinner_start = Instant::now();
+    let mut live = LiveConfig {
+        theme: theme.clone(),
+        keys: keys.clone(),
+    };
 
+    state.search_dirs.clone_from(&search_dirs);
     // Start repo discovery in background
     if state.loading_repos || state.repos.is_empty() {
         initialize_repo_scan(state);
         spawn_repo_discovery(git, tmux, &event_sender, search_dirs);
     }
+    let reloads = state
+        .config_source
+        .clone()
+        .map(|source| spawn_config_watcher(source, &event_sender));
+    spawn_session_watcher(tmux, &event_sender);
 
     loop {
-        terminal.draw(|f| draw(f, state, theme, keys, &spinner_start))?;
+        let keys = &live.keys;
+        terminal.draw(|f| draw(f, state, &live.theme, keys, &spinner_start))?;
+
+        if let Some(reload) = reloads.as_ref().and_then(|reloads| reloads.try_recv().ok()) {
+            match reload {
+                Ok(config) => {
+                    let mouse_before = state.mouse;
+                    apply_reloaded_config(*config, state, &mut live, git, tmux, &event_sender);
+                    if state.mouse != mouse_before {
+                        set_mouse_capture(terminal.backend_mut(), state.mouse)?;
+                    }
+                }
+                Err(e) => state.set_error(&format!("Config not reloaded: {e}")),
+            }
+            continue;
+        }
 
         // Check background channel (non-blocking)
         if let Ok(app_event) = rx.try_recv() {
@@ -108,24 +279,40 @@ pub fn run(
             continue;
         }
 
+        let ctx = ActionContext {
+            git,
+            tmux,
+            keys,
+            sender: &event_sender,
+        };
+        if let Some(result) = resume_pending_commands(state, &ctx) {
+            return Ok(Some(result));
+        }
+
         // Poll terminal events with a timeout so we can update spinner + check channel
-        if event::poll(Duration::from_millis(80))?
-            && let Event::Key(key) = event::read()?
-        {
+        if event::poll(Duration::from_millis(80))? {
+            let key = match event::read()? {
+                Event::Key(key) => key,
+                Event::Mouse(mouse) => {
+                    let full_area = Rect::from((Position::ORIGIN, terminal.size()?));
+                    if let Some(result) = handle_mouse(mouse, full_area, state, &ctx, &mut clicks) {
+                        return Ok(Some(result));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
             if key.kind != KeyEventKind::Press {
                 continue;
             }
+            // Typing takes over from a chain still waiting for branches
+            state.pending_commands.clear();
 
-            // In loading mode, only allow Ctrl+C
             if matches!(state.mode, Mode::Loading(_)) {
-                if key.code == crossterm::event::KeyCode::Char('c')
-                    && key
-                        .modifiers
-                        .contains(crossterm::event::KeyModifiers::CONTROL)
-                {
+                if let Some(action) = handle_loading_key(key, state, keys) {
                     // Signal cancellation to background threads
                     cancel.store(true, Ordering::Relaxed);
-                    return Ok(Some(OpenAction::Quit));
+                    return Ok(Some(action));
                 }
                 continue;
             }
@@ -143,13 +330,6 @@ pub fn run(
                 continue;
             }
 
-            let ctx = ActionContext {
-                git,
-                tmux,
-                keys,
-                matcher: &matcher,
-                sender: &event_sender,
-            };
             if let Some(action) = keymap::resolve_action(key, state, keys)
                 && let Some(result) = process_action(action, state, &ctx)
             {
@@ -159,6 +339,87 @@ pub fn run(
     }
 }
 
+/// While loading, Cancel or Ctrl+C stops the git operation behind the dialog and goes back to
+/// where it was started. Ctrl+C quits when there's nothing to cancel, like repo discovery.
+fn handle_loading_key(
+    key: crossterm::event::KeyEvent,
+    state: &mut AppState,
+    keys: &KeysConfig,
+) -> Option<OpenAction> {
+    let ctrl_c = key.code == crossterm::event::KeyCode::Char('c')
+        && key
+            .modifiers
+            .contains(crossterm::event::KeyModifiers::CONTROL);
+    let mut our_key: kiosk_core::keyboard::KeyEvent = key.into();
+    our_key.canonicalize();
+    if !ctrl_c && keys.modal.get(&our_key) != Some(&Command::Cancel) {
+        return None;
+    }
+    if let Some(message) = state.cancel_loading() {
+        state.set_error(&format!("Cancelled: {}", message.trim_end_matches("...")));
+        return None;
+    }
+    ctrl_c.then_some(OpenAction::Quit)
+}
+
+/// Apply a mouse event, unless the mouse is turned off or an error toast is showing.
+fn handle_mouse<T: TmuxProvider + ?Sized + 'static>(
+    mouse: MouseEvent,
+    full_area: Rect,
+    state: &mut AppState,
+    ctx: &ActionContext<'_, T>,
+    clicks: &mut ClickTracker,
+) -> Option<OpenAction> {
+    if !state.mouse || state.error.is_some() {
+        return None;
+    }
+    let list_area = active_list_area(full_area, main_area(full_area), &state.mode, state.layout)?;
+    let action = resolve_mouse_action(mouse, state, list_area, clicks, Instant::now())?;
+    process_action(action, state, ctx)
+}
+
+/// Apply a config that changed on disk: theme and keys take effect on the next frame, and
+/// repos are rescanned so changes to `search_dirs` and `ignore` show up. Repos that are no
+/// longer under a search dir, or are now ignored, are dropped, except the one being browsed.
+fn apply_reloaded_config<T: TmuxProvider + ?Sized + 'static>(
+    config: Config,
+    state: &mut AppState,
+    live: &mut LiveConfig,
+    git: &Arc<dyn GitProvider>,
+    tmux: &Arc<T>,
+    sender: &EventSender,
+) {
+    let search_dirs = config.resolved_search_dirs();
+    git.apply_config(&config.git);
+    tmux.apply_config(&config.tmux);
+    if let Err(e) = state.apply_config(&config) {
+        state.set_error(&format!("Config not fully reloaded: {e}"));
+    }
+    live.theme = crate::theme::Theme::reloaded(&config.theme).with_icons(config.icons);
+    live.keys = config.keys;
+    state.search_dirs.clone_from(&search_dirs);
+
+    let selected_repo_path = state
+        .selected_repo_idx
+        .and_then(|idx| state.repos.get(idx).map(|r| r.path.clone()));
+    let dropped: Vec<PathBuf> = state
+        .repos
+        .iter()
+        .map(|repo| repo.path.clone())
+        .filter(|path| {
+            selected_repo_path.as_ref() != Some(path)
+                && state.current_repo_path.as_ref() != Some(path)
+                && (state.is_repo_hidden(path)
+                    || !search_dirs.iter().any(|(dir, _)| path.starts_with(dir)))
+        })
+        .collect();
+    state.repos.retain(|repo| !dropped.contains(&repo.path));
+    sort_repos_preserving_selection(state);
+
+    initialize_repo_scan(state);
+    spawn_repo_discovery(git, tmux, sender, search_dirs);
+}
+
 fn draw(
     f: &mut Frame,
     state: &mut AppState,
@@ -168,7 +429,14 @@ fn draw(
 ) {
     // Loading mode: full-screen spinner
     if let Mode::Loading(ref msg) = state.mode {
-        draw_loading(f, f.area(), msg, theme, spinner_start);
+        draw_loading(
+            f,
+            f.area(),
+            msg,
+            state.loading_progress.as_ref(),
+            theme,
+            spinner_start,
+        );
         return;
     }
 
@@ -177,53 +445,19 @@ fn draw(
     let main_area = outer[0];
     let footer_area = outer[1];
 
-    let page_rows = active_list_page_rows(f.area(), main_area, &state.mode);
+    let page_rows = active_list_page_rows(f.area(), main_area, &state.mode, state.layout);
     state.set_active_list_page_rows(page_rows);
 
     // Determine the effective mode for footer hints
     let effective_mode = state.mode.effective();
 
     match &state.mode {
-        Mode::RepoSelect => components::repo_list::draw(f, main_area, state, theme, keys),
-        Mode::BranchSelect => components::branch_picker::draw(f, main_area, state, theme, keys),
-        Mode::SelectBaseBranch => {
-            components::branch_picker::draw(f, main_area, state, theme, keys);
-            components::new_branch::draw(f, state, theme);
-        }
-        Mode::ConfirmWorktreeDelete { .. } => {
-            components::branch_picker::draw(f, main_area, state, theme, keys);
-            draw_confirm_delete_dialog(f, main_area, state, theme, keys);
-        }
-        Mode::Setup(_) => {
-            components::setup::draw(f, state, theme);
-        }
-        Mode::Help { previous } => {
-            // Draw the previous mode as background
-            match previous.as_ref() {
-                Mode::RepoSelect => {
-                    components::repo_list::draw(f, main_area, state, theme, keys);
-                }
-                Mode::BranchSelect => {
-                    components::branch_picker::draw(f, main_area, state, theme, keys);
-                }
-                Mode::SelectBaseBranch => {
-                    components::branch_picker::draw(f, main_area, state, theme, keys);
-                    components::new_branch::draw(f, state, theme);
-                }
-                Mode::ConfirmWorktreeDelete { .. } => {
-                    components::branch_picker::draw(f, main_area, state, theme, keys);
-                    draw_confirm_delete_dialog(f, main_area, state, theme, keys);
-                }
-                Mode::Setup(_) => {
-                    components::setup::draw(f, state, theme);
-                }
-                // Loading is handled by the early-return guard; Help cannot nest.
-                Mode::Loading(_) | Mode::Help { .. } => {}
-            }
-            // Draw help overlay on top
+        Mode::Help { previous } | Mode::CommandPalette { previous } => {
+            // Draw the previous mode as background, then the help overlay on top
+            draw_mode(f, main_area, previous, state, theme, keys);
             components::help::draw(f, state, theme);
         }
-        Mode::Loading(_) => unreachable!(),
+        mode => draw_mode(f, main_area, mode, state, theme, keys),
     }
 
     // Error toast overlay (rendered on top of everything)
@@ -238,11 +472,11 @@ fn draw(
             .flat_map(|(i, (key, desc))| {
                 let mut spans = Vec::new();
                 if i > 0 {
-                    spans.push(Span::styled(" │ ", Style::default().fg(theme.border)));
+                    spans.push(Span::styled(theme.glyphs.separator, theme.styles.border));
                 }
                 spans.push(Span::styled(
                     key,
-                    Style::default().fg(theme.hint).add_modifier(Modifier::BOLD),
+                    theme.styles.hint.add_modifier(Modifier::BOLD),
                 ));
                 spans.push(Span::raw(format!(": {desc}")));
                 spans
@@ -253,6 +487,82 @@ fn draw(
     f.render_widget(footer, footer_area);
 }
 
+/// Draw the screen for `mode`, which is `state.mode` or the mode behind the help overlay.
+fn draw_mode(
+    f: &mut Frame,
+    main_area: Rect,
+    mode: &Mode,
+    state: &AppState,
+    theme: &crate::theme::Theme,
+    keys: &kiosk_core::config::KeysConfig,
+) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (list_area, preview_area) = split_preview(main_area, mode, state.layout);
+    if let Some(area) = preview_area {
+        components::preview::draw(f, area, mode, state, theme, now);
+    }
+    match mode {
+        Mode::RepoSelect => components::repo_list::draw(f, list_area, state, theme, keys, now),
+        Mode::SelectCloneDir => {
+            components::repo_list::draw(f, list_area, state, theme, keys, now);
+            components::clone_repo::draw(f, state, theme);
+        }
+        Mode::NewRepo => {
+            components::repo_list::draw(f, list_area, state, theme, keys, now);
+            components::new_repo::draw(f, state, theme);
+        }
+        Mode::GlobalSearch => components::global_search::draw(f, main_area, state, theme),
+        Mode::TagSelect => components::tag_picker::draw(f, main_area, state, theme, now),
+        Mode::WorkspaceSelect => components::workspace_picker::draw(f, main_area, state, theme),
+        Mode::BranchSelect => {
+            components::branch_picker::draw(f, list_area, state, theme, keys, now);
+        }
+        Mode::SelectBaseBranch => {
+            components::branch_picker::draw(f, list_area, state, theme, keys, now);
+            components::new_branch::draw(f, state, theme);
+        }
+        Mode::ConfirmWorktreeDelete { .. } => {
+            components::branch_picker::draw(f, list_area, state, theme, keys, now);
+            draw_confirm_delete_dialog(f, main_area, state, theme, keys);
+        }
+        Mode::EditNote => {
+            components::branch_picker::draw(f, list_area, state, theme, keys, now);
+            components::note::draw(f, state, theme);
+        }
+        Mode::BranchNameFields => {
+            components::branch_picker::draw(f, list_area, state, theme, keys, now);
+            components::branch_name::draw(f, state, theme);
+        }
+        Mode::ConfirmResume { sessions } => {
+            components::repo_list::draw(f, list_area, state, theme, keys, now);
+            draw_confirm_resume_dialog(f, main_area, sessions, mode, theme, keys);
+        }
+        Mode::ConfirmRecover { operations } => {
+            components::repo_list::draw(f, list_area, state, theme, keys, now);
+            draw_confirm_recover_dialog(f, main_area, operations, mode, theme, keys);
+        }
+        Mode::Setup(_) => components::setup::draw(f, state, theme),
+        // Loading is drawn full screen before this is reached; overlays cannot nest.
+        Mode::Loading(_) | Mode::Help { .. } | Mode::CommandPalette { .. } => {}
+    }
+}
+
+/// Split `main_area` into the list and, when the preview is on and `mode` has something to
+/// preview, the preview pane to its right.
+fn split_preview(main_area: Rect, mode: &Mode, layout: LayoutPrefs) -> (Rect, Option<Rect>) {
+    if !layout.preview || components::preview::PreviewKind::for_mode(mode).is_none() {
+        return (main_area, None);
+    }
+    let chunks = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Percentage(layout.preview_width),
+    ])
+    .split(main_area);
+    (chunks[0], Some(chunks[1]))
+}
+
 fn build_footer_hints(mode: &Mode, keys: &KeysConfig) -> Vec<(String, &'static str)> {
     let keymap = keys.keymap_for_mode(mode);
     mode.footer_commands()
@@ -268,31 +578,56 @@ fn list_rows_from_list_area(list_area: Rect) -> usize {
     usize::from(list_area.height.saturating_sub(2)).max(1)
 }
 
-fn active_list_page_rows(full_area: Rect, main_area: Rect, mode: &Mode) -> usize {
-    match mode {
-        Mode::RepoSelect | Mode::BranchSelect | Mode::ConfirmWorktreeDelete { .. } => {
-            let chunks =
-                Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(main_area);
-            list_rows_from_list_area(chunks[1])
-        }
-        Mode::SelectBaseBranch => {
-            let popup = components::centered_rect(60, 60, full_area);
-            let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(popup);
-            list_rows_from_list_area(chunks[1])
+fn active_list_page_rows(
+    full_area: Rect,
+    main_area: Rect,
+    mode: &Mode,
+    layout: LayoutPrefs,
+) -> usize {
+    active_list_area(full_area, main_area, mode, layout).map_or(1, list_rows_from_list_area)
+}
+
+/// Where the active list is drawn, borders included, or `None` if the mode has no list.
+fn active_list_area(
+    full_area: Rect,
+    main_area: Rect,
+    mode: &Mode,
+    layout: LayoutPrefs,
+) -> Option<Rect> {
+    let container = match mode {
+        Mode::RepoSelect
+        | Mode::BranchSelect
+        | Mode::GlobalSearch
+        | Mode::TagSelect
+        | Mode::WorkspaceSelect
+        | Mode::EditNote
+        | Mode::BranchNameFields
+        | Mode::ConfirmWorktreeDelete { .. }
+        | Mode::ConfirmResume { .. }
+        | Mode::ConfirmRecover { .. } => split_preview(main_area, mode, layout).0,
+        Mode::SelectBaseBranch | Mode::SelectCloneDir | Mode::NewRepo => {
+            components::centered_rect(60, 60, full_area)
         }
-        Mode::Help { .. } => {
-            let popup = components::centered_rect(80, 85, full_area);
-            let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(popup);
-            list_rows_from_list_area(chunks[1])
+        Mode::Help { .. } | Mode::CommandPalette { .. } => {
+            components::centered_rect(80, 85, full_area)
         }
-        Mode::Setup(_) | Mode::Loading(_) => 1,
-    }
+        Mode::Setup(_) | Mode::Loading(_) => return None,
+    };
+    let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(container);
+    Some(chunks[1])
+}
+
+/// The main area (everything above the footer) of a frame of `full_area`.
+fn main_area(full_area: Rect) -> Rect {
+    Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).split(full_area)[0]
 }
 
 fn build_loading_dialog<'a>(
     spinner_prefix: String,
     message: &str,
+    progress: Option<&GitProgress>,
     accent_color: Color,
+    muted_color: Color,
 ) -> components::dialog::Dialog<'a> {
     let text = Line::from(vec![
         Span::styled(
@@ -303,8 +638,15 @@ fn build_loading_dialog<'a>(
         ),
         Span::raw(message.to_string()),
     ]);
+    let mut lines = vec![text];
+    if let Some(progress) = progress {
+        lines.push(Line::styled(
+            progress.to_string(),
+            Style::default().fg(muted_color),
+        ));
+    }
 
-    components::dialog::Dialog::new(vec![text])
+    components::dialog::Dialog::new(lines)
         .border_color(accent_color)
         .padding(Padding::horizontal(1))
         .alignment(Alignment::Center)
@@ -314,6 +656,7 @@ fn draw_loading(
     f: &mut Frame,
     area: Rect,
     message: &str,
+    progress: Option<&GitProgress>,
     theme: &crate::theme::Theme,
     start: &Instant,
 ) {
@@ -321,12 +664,20 @@ fn draw_loading(
     let frame_idx = (elapsed / 80) % SPINNER_FRAMES.len();
     let spinner = SPINNER_FRAMES[frame_idx];
 
-    build_loading_dialog(format!("{spinner} "), message, theme.accent).render(f, area);
+    build_loading_dialog(
+        format!("{spinner} "),
+        message,
+        progress,
+        theme.accent,
+        theme.muted,
+    )
+    .render(f, area);
 }
 
 fn build_confirm_delete_dialog<'a>(
     branch_name: &str,
     has_session: bool,
+    preview_line: Option<Line<'a>>,
     confirm_key: &str,
     cancel_key: &str,
     accent_color: Color,
@@ -367,13 +718,52 @@ fn build_confirm_delete_dialog<'a>(
         Span::raw(")"),
     ]);
 
-    components::dialog::Dialog::new(vec![message_line, blank_line, hints_line])
+    let mut lines = vec![message_line, blank_line.clone()];
+    if let Some(preview_line) = preview_line {
+        lines.extend([preview_line, blank_line]);
+    }
+    lines.push(hints_line);
+
+    components::dialog::Dialog::new(lines)
         .border_color(accent_color)
         .title(" Confirm delete ")
         .padding(Padding::uniform(1))
         .alignment(Alignment::Center)
 }
 
+/// Summarise what deleting the worktree would throw away. Once a confirm has been held back
+/// because the worktree isn't known to be clean, ask for the second one.
+fn build_delete_preview_line<'a>(
+    preview: &DeletePreview,
+    confirm_key: &str,
+    warning_color: Color,
+) -> Line<'a> {
+    let warning = Style::default()
+        .fg(warning_color)
+        .add_modifier(Modifier::BOLD);
+    let (summary, style) = match &preview.changes {
+        None => (
+            "Checking for uncommitted changes...".to_string(),
+            Style::default(),
+        ),
+        Some(Err(error)) => (format!("Couldn't check for changes: {error}"), warning),
+        Some(Ok(changes)) if !changes.is_dirty() => (
+            "No uncommitted changes or unpushed commits".to_string(),
+            Style::default(),
+        ),
+        Some(Ok(changes)) => (changes.describe(), warning),
+    };
+    if preview.armed {
+        Line::from(vec![
+            Span::styled(format!("{summary}. Press "), style),
+            Span::styled(confirm_key.to_string(), warning),
+            Span::styled(" again to delete anyway", style),
+        ])
+    } else {
+        Line::styled(summary, style)
+    }
+}
+
 fn draw_confirm_delete_dialog(
     f: &mut Frame,
     area: Rect,
@@ -394,10 +784,15 @@ fn draw_confirm_delete_dialog(
             .map_or("enter".to_string(), |k| k.to_string());
         let cancel_key = KeysConfig::find_key(&keymap, &Command::Cancel)
             .map_or("esc".to_string(), |k| k.to_string());
+        let preview_line = state
+            .delete_preview
+            .as_ref()
+            .map(|preview| build_delete_preview_line(preview, &confirm_key, theme.warning));
 
         build_confirm_delete_dialog(
             branch_name,
             *has_session,
+            preview_line,
             &confirm_key,
             &cancel_key,
             theme.accent,
@@ -407,47 +802,274 @@ fn draw_confirm_delete_dialog(
     }
 }
 
+/// How many lost sessions or interrupted operations a dialog names before summarising the rest.
+const DIALOG_MAX_ITEMS: usize = 8;
+
+fn draw_confirm_resume_dialog(
+    f: &mut Frame,
+    area: Rect,
+    sessions: &[String],
+    mode: &Mode,
+    theme: &crate::theme::Theme,
+    keys: &kiosk_core::config::KeysConfig,
+) {
+    let keymap = keys.keymap_for_mode(mode);
+    let confirm_key = KeysConfig::find_key(&keymap, &Command::Confirm)
+        .map_or("enter".to_string(), |k| k.to_string());
+    let cancel_key = KeysConfig::find_key(&keymap, &Command::Cancel)
+        .map_or("esc".to_string(), |k| k.to_string());
+    build_confirm_resume_dialog(
+        sessions,
+        &confirm_key,
+        &cancel_key,
+        theme.accent,
+        theme.hint,
+    )
+    .render(f, area);
+}
+
+fn build_confirm_resume_dialog<'a>(
+    sessions: &[String],
+    confirm_key: &str,
+    cancel_key: &str,
+    accent_color: Color,
+    hint_color: Color,
+) -> components::dialog::Dialog<'a> {
+    let noun = if sessions.len() == 1 {
+        "session was"
+    } else {
+        "sessions were"
+    };
+    let mut lines = vec![
+        Line::raw(format!(
+            "{} {noun} lost when tmux stopped. Recreate them?",
+            sessions.len()
+        )),
+        Line::raw(""),
+    ];
+    lines.extend(sessions.iter().take(DIALOG_MAX_ITEMS).map(|session| {
+        Line::styled(
+            session.clone(),
+            Style::default()
+                .fg(accent_color)
+                .add_modifier(Modifier::BOLD),
+        )
+    }));
+    if sessions.len() > DIALOG_MAX_ITEMS {
+        lines.push(Line::raw(format!(
+            "and {} more",
+            sessions.len() - DIALOG_MAX_ITEMS
+        )));
+    }
+    lines.extend([
+        Line::raw(""),
+        Line::from(vec![
+            Span::raw("resume ("),
+            Span::styled(
+                confirm_key.to_string(),
+                Style::default().fg(hint_color).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(") / "),
+            Span::raw("forget ("),
+            Span::styled(
+                cancel_key.to_string(),
+                Style::default().fg(hint_color).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(")"),
+        ]),
+    ]);
+
+    components::dialog::Dialog::new(lines)
+        .border_color(accent_color)
+        .title(" Resume sessions ")
+        .padding(Padding::uniform(1))
+        .alignment(Alignment::Center)
+}
+
+fn draw_confirm_recover_dialog(
+    f: &mut Frame,
+    area: Rect,
+    operations: &[Interrupted],
+    mode: &Mode,
+    theme: &crate::theme::Theme,
+    keys: &kiosk_core::config::KeysConfig,
+) {
+    let keymap = keys.keymap_for_mode(mode);
+    let key = |command: &Command, default: &str| {
+        KeysConfig::find_key(&keymap, command).map_or(default.to_string(), |k| k.to_string())
+    };
+    build_confirm_recover_dialog(
+        operations,
+        [
+            ("finish", key(&Command::Confirm, "enter")),
+            ("undo", key(&Command::TabComplete, "tab")),
+            ("later", key(&Command::Cancel, "esc")),
+        ],
+        theme.accent,
+        theme.hint,
+    )
+    .render(f, area);
+}
+
+fn build_confirm_recover_dialog<'a>(
+    operations: &[Interrupted],
+    choices: [(&str, String); 3],
+    accent_color: Color,
+    hint_color: Color,
+) -> components::dialog::Dialog<'a> {
+    let noun = if operations.len() == 1 {
+        "worktree operation was"
+    } else {
+        "worktree operations were"
+    };
+    let mut lines = vec![
+        Line::raw(format!(
+            "{} {noun} interrupted. Finish or undo them?",
+            operations.len()
+        )),
+        Line::raw(""),
+    ];
+    lines.extend(operations.iter().take(DIALOG_MAX_ITEMS).map(|operation| {
+        Line::styled(
+            operation.describe(),
+            Style::default()
+                .fg(accent_color)
+                .add_modifier(Modifier::BOLD),
+        )
+    }));
+    if operations.len() > DIALOG_MAX_ITEMS {
+        lines.push(Line::raw(format!(
+            "and {} more",
+            operations.len() - DIALOG_MAX_ITEMS
+        )));
+    }
+    let mut hints = Vec::new();
+    for (i, (label, key)) in choices.into_iter().enumerate() {
+        if i > 0 {
+            hints.push(Span::raw(" / "));
+        }
+        hints.extend([
+            Span::raw(format!("{label} (")),
+            Span::styled(
+                key,
+                Style::default().fg(hint_color).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(")"),
+        ]);
+    }
+    lines.extend([Line::raw(""), Line::from(hints)]);
+
+    components::dialog::Dialog::new(lines)
+        .border_color(accent_color)
+        .title(" Recover worktrees ")
+        .padding(Padding::uniform(1))
+        .alignment(Alignment::Center)
+}
+
 /// Deduplicate `incoming` branches against `state.branches`, append any new ones,
 /// and rebuild the filtered list preserving search.
-fn extend_branches_deduped(state: &mut AppState, incoming: Vec<BranchEntry>) {
+fn extend_branches_deduped(state: &mut AppState, mut incoming: Vec<BranchEntry>) {
     if incoming.is_empty() {
         return;
     }
+    BranchEntry::apply_last_commits(&mut incoming, &state.branch_commits);
     let mut seen: std::collections::HashSet<(String, Option<String>)> = state
         .branches
         .iter()
         .map(|b| (b.name.clone(), b.remote.clone()))
         .collect();
-    let new_branches: Vec<_> = incoming
+    incoming.retain(|b| seen.insert((b.name.clone(), b.remote.clone())));
+    if !incoming.is_empty() {
+        let first_new = state.branches.len();
+        state.branches.extend(incoming);
+        let texts: Vec<String> = state
+            .branches
+            .iter()
+            .map(BranchEntry::search_text)
+            .collect();
+        let names: Vec<&str> = texts.iter().map(String::as_str).collect();
+        extend_filtered_preserving_search(&mut state.branch_list, &names, first_new, state.search);
+    }
+}
+
+/// Pick up newly discovered worktrees while global search is open, keeping the search.
+fn refresh_global_search(state: &mut AppState) {
+    if state.global_search.is_none() {
+        return;
+    }
+    let entries = state.global_search_entries();
+    let config = state.search;
+    if let Some(search) = &mut state.global_search {
+        let labels: Vec<String> = entries.iter().map(GlobalSearchEntry::label).collect();
+        let names: Vec<&str> = labels.iter().map(String::as_str).collect();
+        search.entries = entries;
+        rebuild_filtered_preserving_search(&mut search.list, &names, config);
+    }
+}
+
+/// Drop pending deletes whose worktrees are gone, from the state and the shared store.
+fn reconcile_pending_deletes(state: &mut AppState) {
+    let before = state.pending_worktree_deletes.clone();
+    if !state.reconcile_pending_worktree_deletes() {
+        return;
+    }
+    let dropped: Vec<_> = before
         .into_iter()
-        .filter(|b| seen.insert((b.name.clone(), b.remote.clone())))
+        .filter(|pending| !state.pending_worktree_deletes.contains(pending))
         .collect();
-    if !new_branches.is_empty() {
-        state.branches.extend(new_branches);
-        let names: Vec<&str> = state.branches.iter().map(|b| b.name.as_str()).collect();
-        rebuild_filtered_preserving_search(&mut state.branch_list, &names);
+    if let Err(e) = state
+        .pending_delete_store
+        .finish(|pending| dropped.contains(pending))
+    {
+        state.set_error(&format!("Failed to persist pending deletes: {e}"));
     }
 }
 
 /// Rebuild a `SearchableList`'s filtered entries from new item names while preserving
 /// the current search text, cursor position, and selection (clamped to bounds).
-fn rebuild_filtered_preserving_search(list: &mut SearchableList, names: &[&str]) {
+fn rebuild_filtered_preserving_search(
+    list: &mut SearchableList,
+    names: &[&str],
+    search: SearchConfig,
+) {
+    list.items_changed();
     if list.input.text.is_empty() {
         list.filtered = (0..names.len()).map(|i| (i, 0)).collect();
     } else {
-        let matcher = SkimMatcherV2::default();
-        let mut scored: Vec<(usize, i64)> = names
-            .iter()
-            .enumerate()
-            .filter_map(|(i, name)| {
-                matcher
-                    .fuzzy_match(name, &list.input.text)
-                    .map(|score| (i, score))
-            })
-            .collect();
-        scored.sort_by(|a, b| b.1.cmp(&a.1));
+        let mut scored = Matcher::new(search).matches(names, 0..names.len(), &list.input.text);
+        scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
         list.filtered = scored;
     }
+    clamp_selection(list);
+}
+
+/// Add the items of `names` from `first_new` on to a `SearchableList`'s filtered entries,
+/// scoring only those against the current search. Gives the same order as
+/// [`rebuild_filtered_preserving_search`] without rescoring a long list on every addition.
+fn extend_filtered_preserving_search(
+    list: &mut SearchableList,
+    names: &[&str],
+    first_new: usize,
+    search: SearchConfig,
+) {
+    list.items_changed();
+    if list.input.text.is_empty() {
+        list.filtered
+            .extend((first_new..names.len()).map(|i| (i, 0)));
+    } else {
+        list.filtered.extend(Matcher::new(search).matches(
+            names,
+            first_new..names.len(),
+            &list.input.text,
+        ));
+        // Stable, so earlier items stay ahead of new ones with the same score
+        list.filtered
+            .sort_by_key(|entry| std::cmp::Reverse(entry.1));
+    }
+    clamp_selection(list);
+}
+
+fn clamp_selection(list: &mut SearchableList) {
     if let Some(sel) = list.selected {
         if sel >= list.filtered.len() {
             list.selected = if list.filtered.is_empty() {
@@ -461,24 +1083,85 @@ fn rebuild_filtered_preserving_search(list: &mut SearchableList, names: &[&str])
     }
 }
 
-/// Sort repos by activity/name, remap the selected index to follow the same repo,
+/// Sort repos by the configured order, remap the selected index to follow the same repo,
 /// and rebuild the filtered list preserving any active search query.
 fn sort_repos_preserving_selection(state: &mut AppState) {
     let selected_repo_path = state
         .selected_repo_idx
         .and_then(|idx| state.repos.get(idx).map(|r| r.path.clone()));
 
-    kiosk_core::state::sort_repos(
+    kiosk_core::state::sort_repos_by(
         &mut state.repos,
         state.current_repo_path.as_deref(),
+        state.sort,
         &state.session_activity,
+        &state.frecency.repo_scores(),
+        &state.pinned,
     );
 
     state.selected_repo_idx =
         selected_repo_path.and_then(|path| state.repos.iter().position(|r| r.path == path));
 
     let names: Vec<&str> = state.repos.iter().map(|r| r.name.as_str()).collect();
-    rebuild_filtered_preserving_search(&mut state.repo_list, &names);
+    rebuild_filtered_preserving_search(&mut state.repo_list, &names, state.search);
+}
+
+/// Add a repo kiosk just made to the list and select it.
+fn register_new_repo(state: &mut AppState, repo: Repo) {
+    state.loading_operation = None;
+    state.mode = Mode::RepoSelect;
+    let path = repo.path.clone();
+    if !state.repos.iter().any(|r| r.path == path) {
+        state.repos.push(repo);
+    }
+    state.repo_list.input.clear();
+    sort_repos_preserving_selection(state);
+    state.repo_list.selected = state
+        .repo_list
+        .filtered
+        .iter()
+        .position(|&(idx, _)| state.repos[idx].path == path);
+    let page_rows = state.active_list_page_rows();
+    update_active_list_scroll_offset(state, page_rows);
+}
+
+/// Run a chained key binding's commands in order, each resolved against the state the one
+/// before left. While branches load, the rest wait in `pending_commands`, so a chain can
+/// enter a repo and then act on its branches.
+fn run_commands<T: TmuxProvider + ?Sized + 'static>(
+    commands: Vec<Command>,
+    state: &mut AppState,
+    ctx: &ActionContext<'_, T>,
+) -> Option<OpenAction> {
+    let mut commands = commands.into_iter();
+    while let Some(command) = commands.next() {
+        if let Some(action) = keymap::command_to_action(&command, state)
+            && let Some(result) = process_action(action, state, ctx)
+        {
+            return Some(result);
+        }
+        if state.loading_branches {
+            state.pending_commands = commands.collect();
+            break;
+        }
+    }
+    None
+}
+
+/// Carry on with a chain that was waiting for branches, once they've loaded. A failed load
+/// drops it.
+fn resume_pending_commands<T: TmuxProvider + ?Sized + 'static>(
+    state: &mut AppState,
+    ctx: &ActionContext<'_, T>,
+) -> Option<OpenAction> {
+    if state.loading_branches || state.pending_commands.is_empty() {
+        return None;
+    }
+    let commands = std::mem::take(&mut state.pending_commands);
+    if state.error.is_some() {
+        return None;
+    }
+    run_commands(commands, state, ctx)
 }
 
 /// Handle events from background tasks
@@ -510,6 +1193,7 @@ fn process_app_event<T: TmuxProvider + ?Sized + 'static>(
                 }
             }
 
+            repos.retain(|repo| !state.is_repo_hidden(&repo.path));
             state.repos = repos;
             state.loading_repos = false;
             state.loading_branches = false;
@@ -523,11 +1207,12 @@ fn process_app_event<T: TmuxProvider + ?Sized + 'static>(
         }
         AppEvent::ReposFound { repo } => {
             // O(1) dedup via HashSet
-            if state.seen_repo_paths.insert(repo.path.clone()) {
+            if state.seen_repo_paths.insert(repo.path.clone()) && !state.is_repo_hidden(&repo.path)
+            {
                 state.repos.push(repo);
 
                 let names: Vec<&str> = state.repos.iter().map(|r| r.name.as_str()).collect();
-                rebuild_filtered_preserving_search(&mut state.repo_list, &names);
+                rebuild_filtered_preserving_search(&mut state.repo_list, &names, state.search);
             }
 
             // Switch to RepoSelect from Loading (so user sees repos appearing)
@@ -574,7 +1259,19 @@ fn process_app_event<T: TmuxProvider + ?Sized + 'static>(
                 state.mode = Mode::RepoSelect;
             }
         }
-        AppEvent::SessionActivityLoaded { session_activity } => {
+        AppEvent::FilterResults {
+            generation,
+            query,
+            items_id,
+            items,
+            first,
+            matches,
+        } => {
+            if sender.is_current_filter(generation) {
+                apply_filter_results(state, &query, items_id, &items, first, matches);
+            }
+        }
+        AppEvent::SessionActivityLoaded { session_activity } => {
             state.session_activity = session_activity;
 
             // Re-sort with activity data
@@ -589,26 +1286,42 @@ fn process_app_event<T: TmuxProvider + ?Sized + 'static>(
             if let Some(repo) = state.repos.iter_mut().find(|r| r.path == repo_path) {
                 repo.worktrees = worktrees;
             }
+            refresh_global_search(state);
 
-            if state.reconcile_pending_worktree_deletes()
-                && let Err(e) = save_pending_worktree_deletes(&state.pending_worktree_deletes)
-            {
-                state.set_error(&format!("Failed to persist pending deletes: {e}"));
-            }
+            reconcile_pending_deletes(state);
         }
-        AppEvent::WorktreeCreated { path, session_name } => {
-            return Some(OpenAction::Open {
-                path,
-                session_name,
-                split_command: state.split_command.clone(),
-            });
+        AppEvent::WorktreeCreated {
+            path,
+            session_name,
+            base,
+        } => {
+            // Cancelled once the worktree was made but before this arrived: the user has
+            // already gone back, so leave them there
+            state.loading_operation.take()?;
+            return Some(open_session(state, path, session_name, base));
+        }
+        AppEvent::RepoCloned { repo } => register_new_repo(state, repo),
+        AppEvent::RepoCreated { repo, github_error } => {
+            let path = repo.path.clone();
+            let session_name = repo.tmux_session_name(&repo.path);
+            let name = repo.name.clone();
+            register_new_repo(state, repo);
+            // Stay in the list, with the new repo selected, so the error can be read
+            if let Some(e) = github_error {
+                state.set_error(&format!("Created {name}, but not its GitHub repo: {e}"));
+                return None;
+            }
+            return Some(open_session(state, path, session_name, None));
         }
         AppEvent::WorktreeRemoved {
             branch_name: _,
             worktree_path,
         } => {
             state.clear_pending_worktree_delete_by_path(&worktree_path);
-            if let Err(e) = save_pending_worktree_deletes(&state.pending_worktree_deletes) {
+            if let Err(e) = state
+                .pending_delete_store
+                .finish(|pending| pending.worktree_path == worktree_path)
+            {
                 state.set_error(&format!("Failed to persist pending deletes: {e}"));
             }
             // Return to branch select and refresh the branch list
@@ -618,18 +1331,46 @@ fn process_app_event<T: TmuxProvider + ?Sized + 'static>(
                 state.mode = Mode::BranchSelect;
             }
         }
+        AppEvent::WorktreeRestored => {
+            state.loading_operation = None;
+            if let Some(repo_idx) = state.selected_repo_idx {
+                enter_branch_select_with_loading(state, repo_idx, git, tmux, sender, false);
+            } else {
+                state.mode = Mode::BranchSelect;
+            }
+        }
+        AppEvent::WorktreeChangesLoaded {
+            worktree_path,
+            changes,
+        } => {
+            if let Some(preview) = &mut state.delete_preview
+                && preview.worktree_path == worktree_path
+            {
+                preview.changes = Some(changes);
+            }
+        }
         AppEvent::WorktreeRemoveFailed {
             branch_name,
             worktree_path,
             error,
         } => {
-            if let Some(repo) = state.selected_repo_idx.and_then(|idx| state.repos.get(idx)) {
-                state.clear_pending_worktree_delete_by_branch(&repo.path.clone(), &branch_name);
+            let persisted = if let Some(repo_path) = state
+                .selected_repo_idx
+                .and_then(|idx| state.repos.get(idx))
+                .map(|repo| repo.path.clone())
+            {
+                state.clear_pending_worktree_delete_by_branch(&repo_path, &branch_name);
+                state
+                    .pending_delete_store
+                    .finish(|pending| pending.is_for(&repo_path, &branch_name))
             } else {
                 state.clear_pending_worktree_delete_by_path(&worktree_path);
-            }
+                state
+                    .pending_delete_store
+                    .finish(|pending| pending.worktree_path == worktree_path)
+            };
             let mut error_message = format!("Failed to remove worktree for {branch_name}: {error}");
-            if let Err(e) = save_pending_worktree_deletes(&state.pending_worktree_deletes) {
+            if let Err(e) = persisted {
                 let _ = write!(
                     error_message,
                     " (also failed to persist pending deletes: {e})"
@@ -640,7 +1381,7 @@ fn process_app_event<T: TmuxProvider + ?Sized + 'static>(
             state.mode = Mode::BranchSelect;
         }
         AppEvent::BranchesLoaded {
-            branches,
+            mut branches,
             worktrees,
             local_names,
             session_activity,
@@ -651,15 +1392,20 @@ fn process_app_event<T: TmuxProvider + ?Sized + 'static>(
                 .and_then(|idx| state.repos.get_mut(idx))
             {
                 repo.worktrees = worktrees;
+                BranchEntry::apply_notes(&mut branches, &state.note_store.load(&repo.path));
+                BranchEntry::apply_labels(&mut branches, &state.label_store.load(&repo.path));
+                BranchEntry::sort_entries_by(
+                    &mut branches,
+                    state.sort,
+                    &state.frecency.branch_scores(&repo.path),
+                );
+                BranchEntry::group_by_labels(&mut branches, &state.label_order);
             }
             state.branches = branches;
             state.branch_list.reset(state.branches.len());
+            state.branch_commits.clear();
             state.loading_branches = false;
-            if state.reconcile_pending_worktree_deletes()
-                && let Err(e) = save_pending_worktree_deletes(&state.pending_worktree_deletes)
-            {
-                state.set_error(&format!("Failed to persist pending deletes: {e}"));
-            }
+            reconcile_pending_deletes(state);
             state.mode = Mode::BranchSelect;
 
             // Kick off remote branch loading and background fetch
@@ -674,12 +1420,35 @@ fn process_app_event<T: TmuxProvider + ?Sized + 'static>(
                     repo_path.clone(),
                     local_names.clone(),
                 );
-                state.fetching_remotes = true;
-                spawn::spawn_git_fetch(git, sender, repo_path, local_names);
+                spawn::spawn_commit_loading(git, sender, repo_path.clone());
+                if state.fetch.on_enter
+                    && state
+                        .fetch_history
+                        .is_due(&repo_path, state.fetch.min_interval_secs)
+                {
+                    start_git_fetch(state, git, sender, repo_path, local_names);
+                }
+            }
+        }
+        AppEvent::BranchCommitsLoaded { repo_path, commits } => {
+            let current_repo_path = state
+                .selected_repo_idx
+                .and_then(|idx| state.repos.get(idx).map(|r| &r.path));
+            if current_repo_path == Some(&repo_path) {
+                BranchEntry::apply_last_commits(&mut state.branches, &commits);
+                state.branch_commits = commits;
             }
         }
-        AppEvent::RemoteBranchesLoaded { branches } => {
-            if *state.mode.effective() == Mode::BranchSelect {
+        AppEvent::RemoteBranchesLoaded {
+            repo_path,
+            branches,
+        } => {
+            let current_repo_path = state
+                .selected_repo_idx
+                .and_then(|idx| state.repos.get(idx).map(|r| &r.path));
+            if *state.mode.effective() == Mode::BranchSelect
+                && current_repo_path == Some(&repo_path)
+            {
                 extend_branches_deduped(state, branches);
             }
         }
@@ -696,16 +1465,61 @@ fn process_app_event<T: TmuxProvider + ?Sized + 'static>(
             {
                 if is_final {
                     state.fetching_remotes = false;
+                    state.fetch_progress = None;
+                    // Fetching moves remote-tracking branches, so their last commits change
+                    spawn::spawn_commit_loading(git, sender, repo_path);
                 }
                 extend_branches_deduped(state, branches);
             }
         }
+        AppEvent::FetchProgress {
+            repo_path,
+            progress,
+        } => {
+            let current_repo_path = state
+                .selected_repo_idx
+                .and_then(|idx| state.repos.get(idx).map(|r| &r.path));
+            if state.fetching_remotes && current_repo_path == Some(&repo_path) {
+                state.fetch_progress = Some(progress);
+            }
+        }
+        AppEvent::LoadingProgress { progress } => {
+            if matches!(state.mode, Mode::Loading(_)) {
+                state.loading_progress = Some(progress);
+            }
+        }
+        AppEvent::TicketFetched(result) => {
+            state.loading_operation = None;
+            state.mode = Mode::BranchSelect;
+            match result {
+                Ok(ticket) => start_ticket_branch(state, &ticket),
+                Err(e) => state.set_error(&e),
+            }
+        }
+        AppEvent::TagsLoaded { repo_path, tags } => {
+            let selected = state
+                .selected_repo_idx
+                .and_then(|idx| state.repos.get(idx))
+                .is_some_and(|repo| repo.path == repo_path);
+            if selected && let Some(tag_select) = &mut state.tag_select {
+                tag_select.tags = tags;
+                tag_select.loading = false;
+                let names: Vec<&str> = tag_select
+                    .tags
+                    .iter()
+                    .map(|entry| entry.tag.name.as_str())
+                    .collect();
+                rebuild_filtered_preserving_search(&mut tag_select.list, &names, state.search);
+            }
+        }
         AppEvent::GitError(msg) => {
+            let operation = state.loading_operation.take();
             // Return to the appropriate mode
-            if state.base_branch_selection.is_some() {
-                state.base_branch_selection = None;
-                state.mode = Mode::BranchSelect;
+            if operation.is_some_and(|op| op.return_mode == Mode::RepoSelect) {
+                state.mode = Mode::RepoSelect;
             } else {
+                state.base_branch_selection = None;
+                state.tag_select = None;
                 state.mode = Mode::BranchSelect;
             }
             state.loading_branches = false;
@@ -743,6 +1557,11 @@ fn handle_movement_actions(action: &Action, state: &mut AppState) -> bool {
         Action::MoveBottom => {
             list.move_to_bottom();
         }
+        Action::SelectRow(index) => {
+            if *index < list.filtered.len() {
+                list.selected = Some(*index);
+            }
+        }
         _ => return false,
     }
     update_active_list_scroll_offset(state, page_rows_usize);
@@ -750,7 +1569,7 @@ fn handle_movement_actions(action: &Action, state: &mut AppState) -> bool {
 }
 
 fn update_active_list_scroll_offset(state: &mut AppState, viewport_rows: usize) {
-    if let Mode::Help { .. } = state.mode {
+    if let Mode::Help { .. } | Mode::CommandPalette { .. } = state.mode {
         if let Some(overlay) = &mut state.help_overlay {
             update_help_scroll_offset(overlay, viewport_rows);
         }
@@ -832,6 +1651,7 @@ fn handle_simple_actions(action: &Action, state: &mut AppState) -> bool {
             true
         }
         Action::CancelDeleteWorktree => {
+            state.delete_preview = None;
             state.mode = Mode::BranchSelect;
             true
         }
@@ -843,7 +1663,6 @@ struct ActionContext<'a, T: TmuxProvider + ?Sized + 'static> {
     git: &'a Arc<dyn GitProvider>,
     tmux: &'a Arc<T>,
     keys: &'a KeysConfig,
-    matcher: &'a SkimMatcherV2,
     sender: &'a EventSender,
 }
 
@@ -854,6 +1673,10 @@ fn process_action<T: TmuxProvider + ?Sized + 'static>(
     state: &mut AppState,
     ctx: &ActionContext<'_, T>,
 ) -> Option<OpenAction> {
+    let action = restrict_to_pick_scope(action, state.pick)?;
+    // A count only applies to the action right after it
+    let count = state.pending_count.take();
+
     // Handle movement and simple actions first
     if handle_movement_actions(&action, state) || handle_simple_actions(&action, state) {
         return None;
@@ -866,13 +1689,10 @@ fn process_action<T: TmuxProvider + ?Sized + 'static>(
             if let Some(sel) = state.repo_list.selected
                 && let Some(&(idx, _)) = state.repo_list.filtered.get(sel)
             {
+                state.launch_agent = false;
                 let repo = &state.repos[idx];
                 let session_name = repo.tmux_session_name(&repo.path);
-                return Some(OpenAction::Open {
-                    path: repo.path.clone(),
-                    session_name,
-                    split_command: state.split_command.clone(),
-                });
+                return Some(open_session(state, repo.path.clone(), session_name, None));
             }
         }
 
@@ -884,17 +1704,43 @@ fn process_action<T: TmuxProvider + ?Sized + 'static>(
             }
         }
 
+        Action::OpenInEditor => return handle_open_in_editor(state),
+
         Action::GoBack => handle_go_back(state),
 
         Action::OpenBranch => {
+            state.launch_agent = false;
+            if let Some(result) = handle_open_branch(state, ctx.git, ctx.sender) {
+                return Some(result);
+            }
+        }
+
+        Action::OpenWithAgent => {
+            if state.agent.is_none() {
+                state.set_error("No agent to open with: set default_agent or add one to [agents]");
+                return None;
+            }
+            state.launch_agent = true;
             if let Some(result) = handle_open_branch(state, ctx.git, ctx.sender) {
                 return Some(result);
             }
         }
 
         Action::StartNewBranchFlow => {
-            handle_start_new_branch(state);
+            handle_start_new_branch(state, ctx.sender);
         }
+        Action::StartGlobalSearch => handle_start_global_search(state),
+        Action::ShowTags => handle_show_tags(state, ctx.git, ctx.sender),
+        Action::ShowWorkspaces => handle_show_workspaces(state),
+        Action::AddToWorkspace => handle_add_to_workspace(state),
+        Action::ConfirmWorkspace => return handle_confirm_workspace(state),
+        Action::StartCloneFlow => handle_start_clone(state, ctx.git, ctx.sender),
+        Action::ConfirmClone => handle_confirm_clone(state, ctx.git, ctx.sender),
+        Action::StartNewRepoFlow => handle_start_new_repo(state),
+        Action::ConfirmNewRepo => handle_confirm_new_repo(state, ctx.git, ctx.sender),
+
+        Action::TogglePin => handle_toggle_pin(state),
+        Action::HideRepo => handle_hide_repo(state),
 
         Action::MoveSelection(delta) => {
             if matches!(
@@ -912,34 +1758,56 @@ fn process_action<T: TmuxProvider + ?Sized + 'static>(
         }
 
         Action::SearchPush(c) => {
-            handle_search_push(state, ctx.matcher, c);
+            handle_search_push(state, ctx.sender, c);
         }
         Action::SearchPop => {
-            handle_search_pop(state, ctx.matcher);
+            handle_search_pop(state, ctx.sender);
         }
         Action::SearchDeleteForward => {
-            handle_search_delete_forward(state, ctx.matcher);
+            handle_search_delete_forward(state, ctx.sender);
         }
         Action::SearchDeleteWordForward => {
-            handle_search_delete_word_forward(state, ctx.matcher);
+            handle_search_delete_word_forward(state, ctx.sender);
         }
         Action::SearchDeleteToStart => {
-            handle_search_delete_to_start(state, ctx.matcher);
+            handle_search_delete_to_start(state, ctx.sender);
         }
         Action::SearchDeleteToEnd => {
-            handle_search_delete_to_end(state, ctx.matcher);
+            handle_search_delete_to_end(state, ctx.sender);
         }
 
-        Action::DeleteWorktree => handle_delete_worktree(state),
+        Action::DeleteWorktree => handle_delete_worktree(state, ctx.git, ctx.sender),
         Action::ConfirmDeleteWorktree => {
             handle_confirm_delete(state, ctx.git, ctx.tmux.as_ref(), ctx.sender);
         }
+        Action::UndoDelete => handle_undo_delete(state, ctx.git, ctx.sender),
+        Action::ConfirmResume => return Some(OpenAction::Resume),
+        Action::CancelResume => handle_cancel_resume(state),
+        Action::FinishInterrupted => return Some(OpenAction::Recover { undo: false }),
+        Action::UndoInterrupted => return Some(OpenAction::Recover { undo: true }),
+        Action::FetchRemotes => handle_fetch_remotes(state, ctx.git, ctx.sender),
+        Action::EditNote => handle_edit_note(state),
+        Action::SaveNote => handle_save_note(state),
+        Action::SaveBranchNameField => handle_save_branch_name_field(state),
+        Action::TogglePreview => handle_toggle_preview(state),
+        Action::ResizePreview(delta) => handle_resize_preview(state, delta),
 
         Action::SearchDeleteWord => {
-            handle_search_delete_word(state, ctx.matcher);
+            handle_search_delete_word(state, ctx.sender);
         }
 
         Action::ShowHelp => handle_show_help(state, ctx.keys),
+        Action::ShowCommandPalette => handle_show_command_palette(state, ctx.keys),
+        Action::RunCommands(commands) => return run_commands(commands, state, ctx),
+        Action::RunScript(function) => handle_run_script(state, ctx.tmux.as_ref(), &function),
+        Action::CountDigit(digit) => {
+            state.pending_count = keymap::push_count_digit(count, digit);
+        }
+        Action::RunPaletteCommand => {
+            let action = take_palette_command(state)
+                .and_then(|command| keymap::command_to_action(&command, state))?;
+            return process_action(action, state, ctx);
+        }
 
         // Setup actions
         Action::SetupContinue => handle_setup_continue(state),
@@ -964,6 +1832,7 @@ fn process_action<T: TmuxProvider + ?Sized + 'static>(
         | Action::PageDown
         | Action::MoveTop
         | Action::MoveBottom
+        | Action::SelectRow(_)
         | Action::CursorLeft
         | Action::CursorRight
         | Action::CursorWordLeft
@@ -979,16 +1848,27 @@ fn process_action<T: TmuxProvider + ?Sized + 'static>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kiosk_core::fetch_history::FetchHistoryStore;
     use kiosk_core::git::mock::MockGitProvider;
-    use kiosk_core::git::{Repo, Worktree};
+    use kiosk_core::git::{Repo, Tag, Worktree, WorktreeChanges};
+    use kiosk_core::hidden::{HiddenStore, RepoFilter};
+    use kiosk_core::instance::{FetchClaim, FetchLocks};
+    use kiosk_core::layout::LayoutStore;
+    use kiosk_core::notes::NoteStore;
+    use kiosk_core::pending_delete::{DeleteJournal, DeletedWorktree, PendingDeleteStore};
+    use kiosk_core::pins::PinStore;
+    use kiosk_core::recovery::{PendingCreateStore, PendingWorktreeCreate};
+    use kiosk_core::session_journal::{OpenedSession, SessionJournal};
     use kiosk_core::state::{AppState, BranchEntry, Mode, SearchableList};
     use kiosk_core::tmux::{TmuxProvider, mock::MockTmuxProvider};
+    use kiosk_core::workspace::WorkspaceStore;
 
     fn make_sender() -> EventSender {
         let (tx, _rx) = mpsc::channel();
         EventSender {
             tx,
             cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
         }
     }
 
@@ -1009,14 +1889,12 @@ mod tests {
         git: &'a Arc<dyn GitProvider>,
         tmux: &'a Arc<dyn TmuxProvider>,
         keys: &'a KeysConfig,
-        matcher: &'a SkimMatcherV2,
         sender: &'a EventSender,
     ) -> ActionContext<'a, dyn TmuxProvider> {
         ActionContext {
             git,
             tmux,
             keys,
-            matcher,
             sender,
         }
     }
@@ -1033,13 +1911,13 @@ mod tests {
         });
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let (tx, rx) = std::sync::mpsc::channel();
         let sender = EventSender {
             tx,
             cancel: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            filter_generation: std::sync::Arc::default(),
         };
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         let result = process_action(Action::EnterRepo, &mut state, &ctx);
         assert!(result.is_none());
@@ -1055,6 +1933,210 @@ mod tests {
         assert_eq!(state.branches.len(), 2);
     }
 
+    #[test]
+    fn long_lists_are_filtered_in_the_background() {
+        let repos = (0..spawn::BACKGROUND_FILTER_MIN_ITEMS)
+            .map(|i| make_repo(&format!("repo-{i}")))
+            .collect();
+        let mut state = AppState::new(repos, None);
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+
+        for c in "4999".chars() {
+            handle_search_push(&mut state, &sender, c);
+        }
+        // Typing doesn't wait for the scoring
+        assert_eq!(
+            state.repo_list.filtered.len(),
+            spawn::BACKGROUND_FILTER_MIN_ITEMS
+        );
+
+        let mut events = 0;
+        while let Ok(event) = rx.recv_timeout(Duration::from_millis(500)) {
+            let AppEvent::FilterResults { query, .. } = &event else {
+                panic!("unexpected event {event:?}");
+            };
+            // Earlier edits were superseded before they scored anything
+            assert_eq!(query, "4999");
+            process_app_event(event, &mut state, &git, &tmux, &sender);
+            events += 1;
+        }
+        assert!(events > 1, "results should arrive a chunk at a time");
+        let names: Vec<&str> = state
+            .repo_list
+            .filtered
+            .iter()
+            .map(|(i, _)| state.repos[*i].name.as_str())
+            .collect();
+        assert_eq!(names, vec!["repo-4999"]);
+        assert_eq!(state.repo_list.selected, Some(0));
+
+        // Results for a search that's since been edited are dropped
+        handle_search_pop(&mut state, &sender);
+        let outdated = AppEvent::FilterResults {
+            generation: sender.filter_generation.load(Ordering::Relaxed),
+            query: "4999".to_string(),
+            items_id: state.repo_list.items_id,
+            items: state.repos.iter().map(|r| r.name.clone()).collect(),
+            first: true,
+            matches: vec![(0, 1)],
+        };
+        process_app_event(outdated, &mut state, &git, &tmux, &sender);
+        assert_ne!(state.repo_list.filtered, vec![(0, 1)]);
+
+        // And so are results for the list before its repos changed
+        let items_id = state.repo_list.items_id;
+        sort_repos_preserving_selection(&mut state);
+        let for_old_list = AppEvent::FilterResults {
+            generation: sender.filter_generation.load(Ordering::Relaxed),
+            query: state.repo_list.input.text.clone(),
+            items_id,
+            items: state.repos.iter().map(|r| r.name.clone()).collect(),
+            first: true,
+            matches: vec![(0, 1)],
+        };
+        process_app_event(for_old_list, &mut state, &git, &tmux, &sender);
+        assert_ne!(state.repo_list.filtered, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_command_chain_waits_for_branches_before_continuing() {
+        let repos = vec![make_repo("alpha")];
+        let mut state = AppState::new(repos, None);
+        state.repo_list.selected = Some(0);
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider {
+            branches: vec!["main".into(), "dev".into()],
+            ..Default::default()
+        });
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            filter_generation: std::sync::Arc::default(),
+        };
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        let chain = vec![Command::EnterRepo, Command::MoveDown, Command::EditNote];
+        let result = process_action(Action::RunCommands(chain), &mut state, &ctx);
+        assert!(result.is_none());
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert_eq!(
+            state.pending_commands,
+            vec![Command::MoveDown, Command::EditNote]
+        );
+        assert!(resume_pending_commands(&mut state, &ctx).is_none());
+        assert_eq!(state.pending_commands.len(), 2, "still loading branches");
+
+        let event = rx.recv_timeout(std::time::Duration::from_secs(1)).unwrap();
+        process_app_event(event, &mut state, &git, &tmux, &sender);
+        resume_pending_commands(&mut state, &ctx);
+        assert!(state.pending_commands.is_empty());
+        assert_eq!(state.mode, Mode::EditNote);
+        let (second, _) = state.branch_list.filtered[1];
+        assert_eq!(
+            state.note_edit.as_ref().map(|edit| edit.branch.as_str()),
+            Some(state.branches[second].name.as_str())
+        );
+    }
+
+    #[test]
+    fn test_run_script_types_the_result_into_the_selection_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("kiosk.rhai");
+        std::fs::write(&file, "fn greet(event) { `hello ${event.repo}` }").unwrap();
+
+        let repo = make_repo("alpha");
+        let session = repo.tmux_session_name(&repo.path);
+        let mut state = AppState::new(vec![repo], None);
+        state.repo_list.selected = Some(0);
+        state.script.file = Some(file.to_string_lossy().into_owned());
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let mock = Arc::new(MockTmuxProvider::default().with_session(&session));
+        let tmux: Arc<dyn TmuxProvider> = mock.clone();
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::RunScript("greet".into()), &mut state, &ctx);
+        assert!(state.error.is_none(), "{:?}", state.error);
+        assert_eq!(
+            *mock.sent_keys.lock().unwrap(),
+            [(session, "hello alpha".to_string())]
+        );
+
+        process_action(Action::RunScript("missing".into()), &mut state, &ctx);
+        assert!(
+            state
+                .error
+                .as_deref()
+                .unwrap()
+                .contains("no function missing")
+        );
+    }
+
+    #[test]
+    fn test_count_prefix_repeats_movement_and_jumps_to_row() {
+        use crossterm::event::{
+            KeyCode as CtKeyCode, KeyEvent as CtKeyEvent, KeyModifiers as CtMods,
+        };
+
+        let repos = (0..20).map(|i| make_repo(&format!("repo{i:02}"))).collect();
+        let mut state = AppState::new(repos, None);
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+        let mut press = |code, modifiers| {
+            let key = CtKeyEvent::new(code, modifiers);
+            if let Some(action) = keymap::resolve_action(key, &state, &keys) {
+                process_action(action, &mut state, &ctx);
+            }
+            state.repo_list.selected
+        };
+
+        press(CtKeyCode::Char('1'), CtMods::ALT);
+        assert_eq!(press(CtKeyCode::Char('2'), CtMods::ALT), Some(0));
+        assert_eq!(press(CtKeyCode::Down, CtMods::NONE), Some(12));
+        assert_eq!(
+            press(CtKeyCode::Down, CtMods::NONE),
+            Some(13),
+            "count is used up"
+        );
+
+        press(CtKeyCode::Char('3'), CtMods::ALT);
+        assert_eq!(
+            press(CtKeyCode::Char('g'), CtMods::ALT),
+            Some(2),
+            "3 gg goes to row 3"
+        );
+        press(CtKeyCode::Char('9'), CtMods::ALT);
+        press(CtKeyCode::Char('9'), CtMods::ALT);
+        assert_eq!(
+            press(CtKeyCode::Char('G'), CtMods::ALT),
+            Some(19),
+            "past the end clamps"
+        );
+
+        press(CtKeyCode::Char('5'), CtMods::ALT);
+        assert_eq!(press(CtKeyCode::Up, CtMods::NONE), Some(14));
+        press(CtKeyCode::Char('0'), CtMods::ALT);
+        assert_eq!(
+            press(CtKeyCode::Up, CtMods::NONE),
+            Some(13),
+            "a lone 0 isn't a count"
+        );
+    }
+
     #[test]
     fn test_remote_branches_appended() {
         let repos = vec![make_repo("alpha")];
@@ -1069,6 +2151,11 @@ mod tests {
             remote: None,
             is_default: false,
             session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         }];
         state.branch_list.reset(1);
 
@@ -1086,6 +2173,11 @@ mod tests {
                 remote: Some("origin".to_string()),
                 is_default: false,
                 session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
             },
             BranchEntry {
                 name: "feature-y".to_string(),
@@ -1095,11 +2187,17 @@ mod tests {
                 remote: Some("origin".to_string()),
                 is_default: false,
                 session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
             },
         ];
 
         process_app_event(
             AppEvent::RemoteBranchesLoaded {
+                repo_path: PathBuf::from("/tmp/alpha"),
                 branches: remote_branches,
             },
             &mut state,
@@ -1129,6 +2227,11 @@ mod tests {
             remote: None,
             is_default: false,
             session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         }];
         state.branch_list.reset(1);
         state.branch_list.input.text = "feat".to_string();
@@ -1143,6 +2246,7 @@ mod tests {
 
         process_app_event(
             AppEvent::RemoteBranchesLoaded {
+                repo_path: PathBuf::from("/tmp/alpha"),
                 branches: vec![BranchEntry {
                     name: "feature-x".to_string(),
                     worktree_path: None,
@@ -1151,6 +2255,11 @@ mod tests {
                     remote: Some("origin".to_string()),
                     is_default: false,
                     session_activity_ts: None,
+                    last_commit: None,
+                    agent: None,
+                    note: None,
+                    labels: Vec::new(),
+                    detached: false,
                 }],
             },
             &mut state,
@@ -1166,6 +2275,85 @@ mod tests {
         assert_eq!(state.branches[matched_idx].name, "feature-x");
     }
 
+    #[test]
+    fn test_extending_filtered_entries_matches_rebuilding_them() {
+        let names = [
+            "main",
+            "feat-a",
+            "fix",
+            "feature-b",
+            "feat",
+            "other",
+            "afeat",
+        ];
+        for text in ["", "feat", "f"] {
+            let mut rebuilt = SearchableList::new(0);
+            rebuilt.input.text = text.to_string();
+            rebuild_filtered_preserving_search(&mut rebuilt, &names, SearchConfig::default());
+
+            let mut extended = SearchableList::new(0);
+            extended.input.text = text.to_string();
+            rebuild_filtered_preserving_search(&mut extended, &names[..3], SearchConfig::default());
+            extend_filtered_preserving_search(
+                &mut extended,
+                &names[..5],
+                3,
+                SearchConfig::default(),
+            );
+            extend_filtered_preserving_search(&mut extended, &names, 5, SearchConfig::default());
+
+            assert_eq!(extended.filtered, rebuilt.filtered, "search {text:?}");
+            assert_eq!(extended.selected, rebuilt.selected, "search {text:?}");
+        }
+    }
+
+    #[test]
+    fn test_remote_branches_load_in_pages_for_the_current_repo() {
+        let repos = vec![make_repo("alpha")];
+        let mut state = AppState::new(repos, None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+
+        let remote_branches: Vec<String> = (0..1200).map(|i| format!("feature-{i}")).collect();
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider {
+            remotes: vec!["origin".into()],
+            remote_branches,
+            ..Default::default()
+        });
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+
+        spawn::spawn_remote_branch_loading(&git, &sender, PathBuf::from("/tmp/alpha"), vec![]);
+        let pages: Vec<AppEvent> = rx.iter().take(3).collect();
+        assert!(pages.iter().all(|event| matches!(
+            event,
+            AppEvent::RemoteBranchesLoaded { branches, .. } if branches.len() <= 500
+        )));
+        for event in pages {
+            process_app_event(event, &mut state, &git, &tmux, &sender);
+        }
+        assert_eq!(state.branches.len(), 1200);
+        assert_eq!(state.branch_list.filtered.len(), 1200);
+
+        // Pages for a repo that's no longer selected are dropped
+        process_app_event(
+            AppEvent::RemoteBranchesLoaded {
+                repo_path: PathBuf::from("/tmp/beta"),
+                branches: vec![make_branch("stale", Some("origin"))],
+            },
+            &mut state,
+            &git,
+            &tmux,
+            &sender,
+        );
+        assert_eq!(state.branches.len(), 1200);
+    }
+
     #[test]
     fn test_go_back_from_branch_to_repo() {
         let repos = vec![make_repo("alpha")];
@@ -1175,9 +2363,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::GoBack, &mut state, &ctx);
         assert_eq!(state.mode, Mode::RepoSelect);
@@ -1192,14 +2379,14 @@ mod tests {
             new_name: "feat".into(),
             bases: vec!["main".into()],
             list: SearchableList::new(1),
+            note: None,
         });
 
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::GoBack, &mut state, &ctx);
         assert_eq!(state.mode, Mode::BranchSelect);
@@ -1214,9 +2401,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -1239,9 +2425,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -1282,9 +2467,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -1314,9 +2498,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -1353,6 +2536,11 @@ mod tests {
             remote: None,
             is_default: false,
             session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         }];
         state.branch_list.filtered = vec![(0, 0)];
         state.branch_list.selected = Some(0);
@@ -1360,20 +2548,31 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         let result = process_action(Action::OpenBranch, &mut state, &ctx);
         assert!(result.is_some());
         match result.unwrap() {
             OpenAction::Open {
-                path, session_name, ..
+                path,
+                repo,
+                session_name,
+                ..
             } => {
                 assert_eq!(path, PathBuf::from("/tmp/alpha"));
+                assert_eq!(repo.as_deref(), Some("alpha"));
                 assert_eq!(session_name, "alpha");
             }
-            OpenAction::Quit | OpenAction::SetupComplete => panic!("Expected OpenAction::Open"),
+            OpenAction::Quit
+            | OpenAction::SetupComplete
+            | OpenAction::Resume
+            | OpenAction::Recover { .. }
+            | OpenAction::Workspace { .. }
+            | OpenAction::Editor { .. }
+            | OpenAction::PrintPath { .. } => {
+                panic!("Expected OpenAction::Open")
+            }
         }
     }
 
@@ -1391,6 +2590,11 @@ mod tests {
             remote: None,
             is_default: false,
             session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         }];
         state.branch_list.filtered = vec![(0, 0)];
         state.branch_list.selected = Some(0);
@@ -1398,9 +2602,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         let result = process_action(Action::OpenBranch, &mut state, &ctx);
         assert!(result.is_none());
@@ -1416,9 +2619,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::SearchPush('a'), &mut state, &ctx);
         assert_eq!(state.repo_list.input.text, "a");
@@ -1435,9 +2637,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::MoveSelection(1), &mut state, &ctx);
         assert_eq!(state.repo_list.selected, Some(1));
@@ -1459,9 +2660,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         for _ in 0..25 {
             process_action(Action::MoveSelection(1), &mut state, &ctx);
@@ -1486,9 +2686,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::HalfPageDown, &mut state, &ctx);
         assert_eq!(state.repo_list.selected, Some(4));
@@ -1509,9 +2708,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::PageDown, &mut state, &ctx);
         assert_eq!(state.repo_list.selected, Some(5));
@@ -1536,9 +2734,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::HalfPageDown, &mut state, &ctx);
         assert_eq!(
@@ -1555,6 +2752,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_open_repo_uses_the_template_for_its_project_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        let repo = Repo {
+            path: dir.path().to_path_buf(),
+            ..make_repo("web")
+        };
+        let mut state = AppState::new(vec![repo], Some("hx".into()));
+        state.session_templates.insert(
+            kiosk_core::project::ProjectKind::Node,
+            kiosk_core::config::SessionTemplate {
+                split_command: None,
+                run: Some("npm run dev".to_string()),
+            },
+        );
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        let result = process_action(Action::OpenRepo, &mut state, &ctx);
+        assert!(matches!(
+            result,
+            Some(OpenAction::Open { split_command, run, .. })
+                if split_command.as_deref() == Some("hx") && run.as_deref() == Some("npm run dev")
+        ));
+    }
+
     #[test]
     fn test_open_repo_returns_repo_path() {
         let repos = vec![make_repo("alpha"), make_repo("beta")];
@@ -1564,9 +2792,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         let result = process_action(Action::OpenRepo, &mut state, &ctx);
         assert!(result.is_some());
@@ -1575,53 +2802,166 @@ mod tests {
                 path,
                 session_name,
                 split_command,
+                ..
             } => {
                 assert_eq!(path, PathBuf::from("/tmp/beta"));
                 assert_eq!(session_name, "beta");
                 assert_eq!(split_command.as_deref(), Some("hx"));
             }
-            OpenAction::Quit | OpenAction::SetupComplete => panic!("Expected OpenAction::Open"),
+            OpenAction::Quit
+            | OpenAction::SetupComplete
+            | OpenAction::Resume
+            | OpenAction::Recover { .. }
+            | OpenAction::Workspace { .. }
+            | OpenAction::Editor { .. }
+            | OpenAction::PrintPath { .. } => {
+                panic!("Expected OpenAction::Open")
+            }
         }
     }
 
     #[test]
-    fn test_new_branch_empty_name_shows_error() {
-        let repos = vec![make_repo("alpha")];
-        let mut state = AppState::new(repos, None);
-        state.mode = Mode::BranchSelect;
-        state.selected_repo_idx = Some(0);
-        state.branch_list.input.text = String::new(); // empty
+    fn test_preview_toggle_and_resize_are_saved() {
+        let layout_dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.layout_store = LayoutStore::at(layout_dir.path().join("layout.toml"));
 
-        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider {
-            branches: vec!["main".into()],
-            ..Default::default()
-        });
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
-        process_action(Action::StartNewBranchFlow, &mut state, &ctx);
+        process_action(Action::TogglePreview, &mut state, &ctx);
+        assert!(state.layout.preview);
+        process_action(Action::ResizePreview(10), &mut state, &ctx);
+        assert_eq!(state.layout.preview_width, 50);
+        assert_eq!(state.layout_store.load(), state.layout);
 
+        process_action(Action::TogglePreview, &mut state, &ctx);
         assert_eq!(
-            state.mode,
-            Mode::BranchSelect,
-            "Should stay in BranchSelect"
-        );
-        assert!(
-            state.error.is_some(),
-            "Should show an error for empty branch name"
+            state.layout_store.load(),
+            LayoutPrefs {
+                preview: false,
+                preview_width: 50
+            }
         );
-        assert!(state.error.unwrap().contains("branch name"));
     }
 
     #[test]
-    fn test_new_branch_with_name_enters_flow() {
-        let repos = vec![make_repo("alpha")];
-        let mut state = AppState::new(repos, None);
-        state.mode = Mode::BranchSelect;
-        state.selected_repo_idx = Some(0);
+    fn test_preview_narrows_the_list() {
+        let full_area = Rect::new(0, 0, 100, 30);
+        let layout = LayoutPrefs {
+            preview: true,
+            preview_width: 40,
+        };
+        let (list, preview) = split_preview(main_area(full_area), &Mode::BranchSelect, layout);
+        assert_eq!(list.width, 60);
+        assert_eq!(preview.map(|area| area.x), Some(60));
+        let list_area =
+            active_list_area(full_area, main_area(full_area), &Mode::BranchSelect, layout);
+        assert_eq!(list_area.map(|area| area.width), Some(60));
+
+        let (list, preview) = split_preview(main_area(full_area), &Mode::GlobalSearch, layout);
+        assert_eq!((list.width, preview), (100, None));
+    }
+
+    #[test]
+    fn test_toggle_pin_moves_repo_to_top_and_keeps_cursor_on_it() {
+        let pins_dir = tempfile::tempdir().unwrap();
+        let repos = vec![make_repo("alpha"), make_repo("beta"), make_repo("gamma")];
+        let mut state = AppState::new(repos, None);
+        state.pin_store = PinStore::at(pins_dir.path().join("pins.toml"));
+        state.repo_list.selected = Some(2);
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::TogglePin, &mut state, &ctx);
+        assert_eq!(state.repos[0].name, "gamma");
+        assert_eq!(state.repo_list.selected, Some(0));
+        assert!(
+            state
+                .pin_store
+                .load()
+                .contains(&PathBuf::from("/tmp/gamma"))
+        );
+
+        process_action(Action::TogglePin, &mut state, &ctx);
+        assert_eq!(state.repos[2].name, "gamma");
+        assert_eq!(state.repo_list.selected, Some(2));
+        assert!(state.pinned.is_empty());
+    }
+
+    #[test]
+    fn test_hide_repo_removes_it_and_persists() {
+        let hidden_dir = tempfile::tempdir().unwrap();
+        let repos = vec![make_repo("alpha"), make_repo("beta"), make_repo("gamma")];
+        let mut state = AppState::new(repos, None);
+        state.hidden_store = HiddenStore::at(hidden_dir.path().join("hidden.toml"));
+        state.repo_list.selected = Some(1);
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::HideRepo, &mut state, &ctx);
+
+        let names: Vec<&str> = state.repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["alpha", "gamma"]);
+        assert_eq!(state.repo_list.filtered.len(), 2);
+        assert_eq!(state.repo_list.selected, Some(1));
+        assert!(state.is_repo_hidden(std::path::Path::new("/tmp/beta")));
+        assert!(
+            state
+                .hidden_store
+                .load()
+                .contains(&PathBuf::from("/tmp/beta"))
+        );
+    }
+
+    #[test]
+    fn test_new_branch_empty_name_shows_error() {
+        let repos = vec![make_repo("alpha")];
+        let mut state = AppState::new(repos, None);
+        state.mode = Mode::BranchSelect;
+        state.selected_repo_idx = Some(0);
+        state.branch_list.input.text = String::new(); // empty
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider {
+            branches: vec!["main".into()],
+            ..Default::default()
+        });
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::StartNewBranchFlow, &mut state, &ctx);
+
+        assert_eq!(
+            state.mode,
+            Mode::BranchSelect,
+            "Should stay in BranchSelect"
+        );
+        assert!(
+            state.error.is_some(),
+            "Should show an error for empty branch name"
+        );
+        assert!(state.error.unwrap().contains("branch name"));
+    }
+
+    #[test]
+    fn test_new_branch_with_name_enters_flow() {
+        let repos = vec![make_repo("alpha")];
+        let mut state = AppState::new(repos, None);
+        state.mode = Mode::BranchSelect;
+        state.selected_repo_idx = Some(0);
         state.branch_list.input.text = "feat/new".to_string();
         state.branches = vec![BranchEntry {
             name: "main".into(),
@@ -1631,6 +2971,11 @@ mod tests {
             remote: None,
             is_default: true,
             session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         }];
 
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider {
@@ -1639,9 +2984,8 @@ mod tests {
         });
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::StartNewBranchFlow, &mut state, &ctx);
 
@@ -1650,6 +2994,522 @@ mod tests {
         assert_eq!(state.base_branch_selection.unwrap().new_name, "feat/new");
     }
 
+    #[test]
+    fn test_clone_into_picked_search_dir_selects_the_new_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (first, second) = (tmp.path().join("dev"), tmp.path().join("work"));
+        let cloned = Repo {
+            name: "gamma".to_string(),
+            session_name: "gamma".to_string(),
+            path: second.join("gamma"),
+            worktrees: vec![],
+        };
+        let mut state = AppState::new(vec![make_repo("alpha"), make_repo("beta")], None);
+        state.search_dirs = vec![(first, 1), (second.clone(), 1)];
+        state.repo_list.input.text = "git@github.com:acme/gamma.git".to_string();
+
+        let mock = Arc::new(MockGitProvider {
+            repos: vec![cloned],
+            ..Default::default()
+        });
+        let git: Arc<dyn GitProvider> = mock.clone();
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::StartCloneFlow, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::SelectCloneDir);
+        process_action(Action::MoveSelection(1), &mut state, &ctx);
+        process_action(Action::ConfirmClone, &mut state, &ctx);
+        assert!(matches!(&state.mode, Mode::Loading(msg) if msg.contains("gamma")));
+        assert!(state.clone_dir_selection.is_none());
+
+        let event = loop {
+            let event = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+            if matches!(event, AppEvent::RepoCloned { .. }) {
+                break event;
+            }
+        };
+        process_app_event(event, &mut state, &git, &tmux, &sender);
+
+        assert_eq!(
+            *mock.clone_calls.lock().unwrap(),
+            vec![(
+                "git@github.com:acme/gamma.git".to_string(),
+                second.join("gamma")
+            )]
+        );
+        assert_eq!(state.mode, Mode::RepoSelect);
+        assert!(state.loading_operation.is_none());
+        assert!(state.repo_list.input.text.is_empty());
+        let &(idx, _) = &state.repo_list.filtered[state.repo_list.selected.unwrap()];
+        assert_eq!(state.repos[idx].name, "gamma");
+    }
+
+    #[test]
+    fn test_clone_needs_a_url_in_the_search_bar() {
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.search_dirs = vec![(PathBuf::from("/tmp"), 1)];
+        process_action(Action::StartCloneFlow, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::RepoSelect);
+        assert_eq!(state.error.as_deref(), Some("Type a URL to clone first"));
+    }
+
+    #[test]
+    fn test_new_repo_from_template_is_created_and_opened() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dest = tmp.path().join("scratch");
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.search_dirs = vec![(tmp.path().to_path_buf(), 1)];
+        state.repo_list.input.text = "scratch".to_string();
+
+        let mock = Arc::new(MockGitProvider {
+            repos: vec![Repo {
+                name: "scratch".to_string(),
+                session_name: "scratch".to_string(),
+                path: dest.clone(),
+                worktrees: vec![],
+            }],
+            ..Default::default()
+        });
+        let git: Arc<dyn GitProvider> = mock.clone();
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::StartNewRepoFlow, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::NewRepo);
+        let flow = state.new_repo_selection.as_ref().unwrap();
+        assert_eq!(flow.templates, ["empty", "github"]);
+        process_action(Action::ConfirmNewRepo, &mut state, &ctx);
+        assert!(matches!(&state.mode, Mode::Loading(msg) if msg.contains("scratch")));
+
+        let event = loop {
+            let event = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+            if matches!(event, AppEvent::RepoCreated { .. }) {
+                break event;
+            }
+        };
+        let result = process_app_event(event, &mut state, &git, &tmux, &sender);
+
+        assert!(dest.is_dir());
+        assert_eq!(*mock.init_calls.lock().unwrap(), vec![dest.clone()]);
+        assert!(matches!(
+            result,
+            Some(OpenAction::Open { path, session_name, .. })
+                if path == dest && session_name == "scratch"
+        ));
+        let &(idx, _) = &state.repo_list.filtered[state.repo_list.selected.unwrap()];
+        assert_eq!(state.repos[idx].name, "scratch");
+    }
+
+    #[test]
+    fn test_new_repo_without_its_github_repo_is_registered_but_not_opened() {
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let sender = make_sender();
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+
+        let event = AppEvent::RepoCreated {
+            repo: make_repo("scratch"),
+            github_error: Some("gh: not logged in".to_string()),
+        };
+        let result = process_app_event(event, &mut state, &git, &tmux, &sender);
+
+        assert!(result.is_none());
+        assert_eq!(
+            state.error.as_deref(),
+            Some("Created scratch, but not its GitHub repo: gh: not logged in")
+        );
+        let &(idx, _) = &state.repo_list.filtered[state.repo_list.selected.unwrap()];
+        assert_eq!(state.repos[idx].name, "scratch");
+    }
+
+    #[test]
+    fn test_pick_scope_limits_what_can_be_picked() {
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.pick = Some(PickScope::Repos);
+        assert!(process_action(Action::EnterRepo, &mut state, &ctx).is_none());
+        assert!(process_action(Action::StartGlobalSearch, &mut state, &ctx).is_none());
+        assert!(process_action(Action::OpenInEditor, &mut state, &ctx).is_none());
+        assert_eq!(state.mode, Mode::RepoSelect);
+        let result = process_action(Action::OpenRepo, &mut state, &ctx)
+            .map(|action| picked(action, state.pick));
+        assert!(matches!(
+            result,
+            Some(OpenAction::PrintPath { path }) if path == std::path::Path::new("/tmp/alpha")
+        ));
+
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.pick = Some(PickScope::Branches);
+        assert!(process_action(Action::OpenRepo, &mut state, &ctx).is_none());
+        assert_eq!(state.mode, Mode::BranchSelect);
+    }
+
+    #[test]
+    fn test_picked_leaves_actions_alone_outside_pick() {
+        let open = || OpenAction::Open {
+            repo: None,
+            path: PathBuf::from("/tmp/alpha"),
+            session_name: "alpha".to_string(),
+            split_command: None,
+            run: None,
+            agent: None,
+            base: None,
+        };
+        assert!(matches!(picked(open(), None), OpenAction::Open { .. }));
+        assert!(matches!(
+            picked(open(), Some(PickScope::Any)),
+            OpenAction::PrintPath { .. }
+        ));
+        assert!(matches!(
+            picked(OpenAction::Quit, Some(PickScope::Any)),
+            OpenAction::Quit
+        ));
+    }
+
+    #[test]
+    fn test_open_with_agent_starts_the_default_agent() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.mode = Mode::BranchSelect;
+        state.selected_repo_idx = Some(0);
+        state.branches = vec![BranchEntry {
+            worktree_path: Some(PathBuf::from("/tmp/alpha--feat")),
+            ..make_branch("feat", None)
+        }];
+        state.branch_list.reset(1);
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        assert!(process_action(Action::OpenWithAgent, &mut state, &ctx).is_none());
+        assert!(state.error.take().unwrap().contains("No agent"));
+
+        let claude = AgentProfile {
+            command: "claude".to_string(),
+            log: true,
+        };
+        state.agent = Some(claude.clone());
+        let result = process_action(Action::OpenWithAgent, &mut state, &ctx);
+        assert!(matches!(
+            &result,
+            Some(OpenAction::Open { path, agent: Some(agent), .. })
+                if path == &PathBuf::from("/tmp/alpha--feat") && agent == &claude
+        ));
+
+        let result = process_action(Action::OpenBranch, &mut state, &ctx);
+        assert!(matches!(result, Some(OpenAction::Open { agent: None, .. })));
+    }
+
+    #[test]
+    fn test_open_in_editor_uses_the_selected_repo_or_worktree() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.editor = Some("code".to_string());
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        let result = process_action(Action::OpenInEditor, &mut state, &ctx);
+        assert!(matches!(
+            &result,
+            Some(OpenAction::Editor { path, editor })
+                if path == &PathBuf::from("/tmp/alpha") && editor.as_deref() == Some("code")
+        ));
+
+        state.mode = Mode::BranchSelect;
+        state.selected_repo_idx = Some(0);
+        state.branches = vec![
+            BranchEntry {
+                worktree_path: Some(PathBuf::from("/tmp/alpha--feat")),
+                ..make_branch("feat", None)
+            },
+            make_branch("no-worktree", None),
+        ];
+        state.branch_list.reset(2);
+        let result = process_action(Action::OpenInEditor, &mut state, &ctx);
+        assert!(matches!(
+            &result,
+            Some(OpenAction::Editor { path, .. }) if path == &PathBuf::from("/tmp/alpha--feat")
+        ));
+
+        state.branch_list.selected = Some(1);
+        assert!(process_action(Action::OpenInEditor, &mut state, &ctx).is_none());
+        assert!(state.error.unwrap().contains("No worktree"));
+    }
+
+    #[test]
+    fn test_global_search_opens_a_worktree_from_any_repo() {
+        let mut alpha = make_repo("alpha");
+        alpha.worktrees.push(Worktree {
+            path: PathBuf::from("/tmp/alpha--feat-search"),
+            branch: Some("feat/search".to_string()),
+            is_main: false,
+        });
+        let mut state = AppState::new(vec![alpha, make_repo("beta")], None);
+        state
+            .session_activity
+            .insert("alpha--feat-search".to_string(), 100);
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::StartGlobalSearch, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::GlobalSearch);
+        let labels: Vec<String> = state
+            .global_search
+            .as_ref()
+            .unwrap()
+            .entries
+            .iter()
+            .map(GlobalSearchEntry::label)
+            .collect();
+        assert_eq!(
+            labels,
+            vec!["alpha/feat/search", "alpha/main", "beta/main"],
+            "worktrees with sessions come first"
+        );
+
+        for c in "bm".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+        let result = process_action(Action::OpenBranch, &mut state, &ctx);
+        match result {
+            Some(OpenAction::Open {
+                path, session_name, ..
+            }) => {
+                assert_eq!(path, PathBuf::from("/tmp/beta"));
+                assert_eq!(session_name, "beta");
+            }
+            _ => panic!("expected OpenAction::Open"),
+        }
+    }
+
+    #[test]
+    fn test_remote_branches_track_the_remote_they_are_listed_under() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        state.branches = ["origin", "upstream"]
+            .into_iter()
+            .map(|remote| BranchEntry {
+                name: "fix".to_string(),
+                worktree_path: None,
+                has_session: false,
+                is_current: false,
+                is_default: false,
+                remote: Some(remote.to_string()),
+                session_activity_ts: None,
+                last_commit: None,
+                agent: None,
+                note: None,
+                labels: Vec::new(),
+                detached: false,
+            })
+            .collect();
+        state.branch_list.reset(2);
+        state.branch_list.selected = Some(1);
+        let mock = Arc::new(MockGitProvider::default());
+        let git: Arc<dyn GitProvider> = mock.clone();
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        assert!(process_action(Action::OpenBranch, &mut state, &ctx).is_none());
+        assert_eq!(
+            state.mode,
+            Mode::Loading("Checking out upstream/fix...".to_string())
+        );
+        let created = rx
+            .iter()
+            .find(|event| matches!(event, AppEvent::WorktreeCreated { .. }));
+        assert!(created.is_some());
+        let calls = mock.create_tracking_branch_calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(
+            (calls[0].0.as_str(), calls[0].1.as_str()),
+            ("upstream", "fix")
+        );
+    }
+
+    #[test]
+    fn test_tags_open_in_detached_worktrees() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        let tag = |name: &str, commit: &str| Tag {
+            name: name.to_string(),
+            commit: commit.to_string(),
+            timestamp: 0,
+            annotation: None,
+        };
+        let mut mock = MockGitProvider::default()
+            .with_repo("alpha", "/tmp/alpha")
+            .with_detached_worktree("/tmp/alpha--v1.0.0");
+        mock.head_commit = Some("aaa".to_string());
+        mock.tags = vec![tag("v2.0.0", "bbb"), tag("v1.0.0", "aaa")];
+        let mock = Arc::new(mock);
+        let git: Arc<dyn GitProvider> = mock.clone();
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::ShowTags, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::TagSelect);
+        assert!(state.tag_select.as_ref().unwrap().loading);
+        let loaded = rx
+            .iter()
+            .find(|event| matches!(event, AppEvent::TagsLoaded { .. }))
+            .unwrap();
+        process_app_event(loaded, &mut state, &git, &tmux, &sender);
+        let tag_select = state.tag_select.as_ref().unwrap();
+        assert!(!tag_select.loading);
+        assert_eq!(tag_select.list.filtered.len(), 2);
+        assert_eq!(tag_select.tags[0].worktree_path, None);
+        assert_eq!(
+            tag_select.tags[1].worktree_path,
+            Some(PathBuf::from("/tmp/alpha--v1.0.0"))
+        );
+
+        // A tag with a worktree already detached at it opens that worktree
+        for c in "v1".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+        match process_action(Action::OpenBranch, &mut state, &ctx) {
+            Some(OpenAction::Open { path, .. }) => {
+                assert_eq!(path, PathBuf::from("/tmp/alpha--v1.0.0"));
+            }
+            _ => panic!("expected OpenAction::Open"),
+        }
+
+        // Others get a new one
+        process_action(Action::SearchDeleteToStart, &mut state, &ctx);
+        assert!(process_action(Action::OpenBranch, &mut state, &ctx).is_none());
+        assert!(matches!(state.mode, Mode::Loading(_)));
+        let created = rx
+            .iter()
+            .find(|event| matches!(event, AppEvent::WorktreeCreated { .. }));
+        assert!(created.is_some());
+        let calls = mock.add_detached_worktree_calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].1, "bbb");
+        assert!(calls[0].2.to_string_lossy().ends_with("v2.0.0"));
+    }
+
+    #[test]
+    fn test_tag_picker_goes_back_to_the_branches() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::ShowTags, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::TagSelect);
+        process_action(Action::GoBack, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert!(state.tag_select.is_none());
+
+        // Tags that arrive after leaving the picker are dropped
+        process_app_event(
+            AppEvent::TagsLoaded {
+                repo_path: PathBuf::from("/tmp/alpha"),
+                tags: Vec::new(),
+            },
+            &mut state,
+            &git,
+            &tmux,
+            &sender,
+        );
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert!(state.tag_select.is_none());
+    }
+
+    #[test]
+    fn test_global_search_refreshes_then_goes_back_to_repo_select() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::StartGlobalSearch, &mut state, &ctx);
+        process_app_event(
+            AppEvent::RepoEnriched {
+                repo_path: PathBuf::from("/tmp/alpha"),
+                worktrees: vec![
+                    Worktree {
+                        path: PathBuf::from("/tmp/alpha"),
+                        branch: Some("main".to_string()),
+                        is_main: true,
+                    },
+                    Worktree {
+                        path: PathBuf::from("/tmp/alpha--feat"),
+                        branch: Some("feat".to_string()),
+                        is_main: false,
+                    },
+                ],
+            },
+            &mut state,
+            &git,
+            &tmux,
+            &sender,
+        );
+        let search = state.global_search.as_ref().unwrap();
+        assert_eq!(search.entries.len(), 2, "enriched worktrees show up");
+        assert_eq!(search.list.filtered.len(), 2);
+
+        process_action(Action::GoBack, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::RepoSelect);
+        assert!(state.global_search.is_none());
+    }
+
     #[test]
     fn test_delete_worktree_no_worktree_shows_error() {
         let repos = vec![make_repo("alpha")];
@@ -1663,6 +3523,11 @@ mod tests {
             remote: None,
             is_default: false,
             session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         }];
         state.branch_list.filtered = vec![(0, 0)];
         state.branch_list.selected = Some(0);
@@ -1670,9 +3535,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::DeleteWorktree, &mut state, &ctx);
 
@@ -1694,6 +3558,11 @@ mod tests {
             remote: None,
             is_default: false,
             session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         }];
         state.branch_list.filtered = vec![(0, 0)];
         state.branch_list.selected = Some(0);
@@ -1701,9 +3570,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::DeleteWorktree, &mut state, &ctx);
 
@@ -1725,6 +3593,11 @@ mod tests {
             remote: None,
             is_default: false,
             session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         }];
         state.branch_list.filtered = vec![(0, 0)];
         state.branch_list.selected = Some(0);
@@ -1732,9 +3605,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::DeleteWorktree, &mut state, &ctx);
 
@@ -1761,6 +3633,11 @@ mod tests {
             remote: None,
             is_default: false,
             session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
         }];
         state.branch_list.filtered = vec![(0, 0)];
         state.branch_list.selected = Some(0);
@@ -1768,9 +3645,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::DeleteWorktree, &mut state, &ctx);
 
@@ -1783,6 +3659,87 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_confirm_resume_asks_to_resume() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.mode = Mode::ConfirmResume {
+            sessions: vec!["alpha--dev".to_string()],
+        };
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        let result = process_action(Action::ConfirmResume, &mut state, &ctx);
+        assert!(matches!(result, Some(OpenAction::Resume)));
+    }
+
+    #[test]
+    fn test_recover_prompt_finishes_undoes_or_leaves_operations() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.mode = Mode::ConfirmRecover {
+            operations: vec![Interrupted::Create(PendingWorktreeCreate::new(
+                PathBuf::from("/tmp/alpha"),
+                Some("dev".to_string()),
+                PathBuf::from("/tmp/alpha-dev"),
+            ))],
+        };
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        let result = process_action(Action::FinishInterrupted, &mut state, &ctx);
+        assert!(matches!(result, Some(OpenAction::Recover { undo: false })));
+        let result = process_action(Action::UndoInterrupted, &mut state, &ctx);
+        assert!(matches!(result, Some(OpenAction::Recover { undo: true })));
+
+        assert!(process_action(Action::GoBack, &mut state, &ctx).is_none());
+        assert_eq!(state.mode, Mode::RepoSelect);
+    }
+
+    #[test]
+    fn test_cancel_resume_forgets_lost_sessions() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        let journal_dir = tempfile::tempdir().unwrap();
+        state.session_journal = SessionJournal::at(journal_dir.path().join("sessions.toml"));
+        for session in ["alpha--dev", "alpha--docs"] {
+            state
+                .session_journal
+                .record(OpenedSession::new(
+                    session.to_string(),
+                    PathBuf::from("/tmp/alpha"),
+                    PathBuf::from(format!("/tmp/{session}")),
+                    None,
+                    None,
+                ))
+                .unwrap();
+        }
+        state.mode = Mode::ConfirmResume {
+            sessions: vec!["alpha--dev".to_string()],
+        };
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        assert!(process_action(Action::CancelResume, &mut state, &ctx).is_none());
+        assert_eq!(state.mode, Mode::RepoSelect);
+        let remaining: Vec<String> = state
+            .session_journal
+            .load()
+            .into_iter()
+            .map(|entry| entry.session)
+            .collect();
+        assert_eq!(remaining, ["alpha--docs"]);
+    }
+
     #[test]
     fn test_confirm_delete_kills_tmux_session() {
         let mut repos = vec![make_repo("alpha")];
@@ -1792,85 +3749,338 @@ mod tests {
             is_main: false,
         });
         let mut state = AppState::new(repos, None);
+        let journal_dir = tempfile::tempdir().unwrap();
+        state.delete_journal = DeleteJournal::at(journal_dir.path().join("journal.toml"));
+        state.pending_delete_store =
+            PendingDeleteStore::at(journal_dir.path().join("pending.toml"));
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::ConfirmWorktreeDelete {
+            branch_name: "dev".to_string(),
+            has_session: true,
+        };
+        state.branches = vec![BranchEntry {
+            name: "dev".to_string(),
+            worktree_path: Some(PathBuf::from("/tmp/alpha-dev")),
+            has_session: true,
+            is_current: false,
+            remote: None,
+            is_default: false,
+            session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
+        }];
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = ActionContext {
+            git: &git,
+            tmux: &tmux,
+            keys: &keys,
+            sender: &sender,
+        };
+
+        process_action(Action::ConfirmDeleteWorktree, &mut state, &ctx);
+
+        let killed = tmux.killed_sessions.lock().unwrap();
+        assert_eq!(killed.as_slice(), &["alpha-dev"]);
+        assert!(matches!(state.mode, Mode::BranchSelect));
+        assert_eq!(state.pending_worktree_deletes.len(), 1);
+    }
+
+    #[test]
+    fn test_confirm_delete_without_session_does_not_kill() {
+        let mut repos = vec![make_repo("alpha")];
+        repos[0].worktrees.push(Worktree {
+            path: PathBuf::from("/tmp/alpha-dev"),
+            branch: Some("dev".to_string()),
+            is_main: false,
+        });
+        let mut state = AppState::new(repos, None);
+        let journal_dir = tempfile::tempdir().unwrap();
+        state.delete_journal = DeleteJournal::at(journal_dir.path().join("journal.toml"));
+        state.pending_delete_store =
+            PendingDeleteStore::at(journal_dir.path().join("pending.toml"));
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::ConfirmWorktreeDelete {
+            branch_name: "dev".to_string(),
+            has_session: false,
+        };
+        state.branches = vec![BranchEntry {
+            name: "dev".to_string(),
+            worktree_path: Some(PathBuf::from("/tmp/alpha-dev")),
+            has_session: false,
+            is_current: false,
+            remote: None,
+            is_default: false,
+            session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
+        }];
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = ActionContext {
+            git: &git,
+            tmux: &tmux,
+            keys: &keys,
+            sender: &sender,
+        };
+
+        process_action(Action::ConfirmDeleteWorktree, &mut state, &ctx);
+
+        let killed = tmux.killed_sessions.lock().unwrap();
+        assert!(killed.is_empty());
+        assert!(matches!(state.mode, Mode::BranchSelect));
+        assert_eq!(state.pending_worktree_deletes.len(), 1);
+    }
+
+    #[test]
+    fn test_confirm_delete_of_dirty_worktree_needs_a_second_confirm() {
+        let mut repos = vec![make_repo("alpha")];
+        repos[0].worktrees.push(Worktree {
+            path: PathBuf::from("/tmp/alpha-dev"),
+            branch: Some("dev".to_string()),
+            is_main: false,
+        });
+        let mut state = AppState::new(repos, None);
+        let journal_dir = tempfile::tempdir().unwrap();
+        state.delete_journal = DeleteJournal::at(journal_dir.path().join("journal.toml"));
+        state.pending_delete_store =
+            PendingDeleteStore::at(journal_dir.path().join("pending.toml"));
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        state.branches = vec![BranchEntry {
+            name: "dev".to_string(),
+            worktree_path: Some(PathBuf::from("/tmp/alpha-dev")),
+            has_session: false,
+            is_current: false,
+            remote: None,
+            is_default: false,
+            session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
+        }];
+        state.branch_list.filtered = vec![(0, 0)];
+        state.branch_list.selected = Some(0);
+
+        let changes = WorktreeChanges {
+            files_changed: 2,
+            insertions: 5,
+            deletions: 1,
+            unpushed_commits: 1,
+        };
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider {
+            worktree_changes: std::collections::HashMap::from([(
+                PathBuf::from("/tmp/alpha-dev"),
+                changes.clone(),
+            )]),
+            ..Default::default()
+        });
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::DeleteWorktree, &mut state, &ctx);
+        let event = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        process_app_event(event, &mut state, &git, &tmux, &sender);
+        let preview = state.delete_preview.clone().unwrap();
+        assert_eq!(preview.changes, Some(Ok(changes.clone())));
+        assert_eq!(
+            changes.describe(),
+            "2 files changed (+5 -1), 1 unpushed commit"
+        );
+
+        process_action(Action::ConfirmDeleteWorktree, &mut state, &ctx);
+        assert!(matches!(state.mode, Mode::ConfirmWorktreeDelete { .. }));
+        assert!(state.delete_preview.as_ref().unwrap().armed);
+        assert!(state.pending_worktree_deletes.is_empty());
+
+        process_action(Action::ConfirmDeleteWorktree, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert!(state.delete_preview.is_none());
+        assert_eq!(state.pending_worktree_deletes.len(), 1);
+    }
+
+    #[test]
+    fn test_delete_preview_of_clean_worktree_confirms_at_once() {
+        let mut preview = DeletePreview::new(PathBuf::from("/tmp/alpha-dev"));
+        assert!(preview.needs_second_confirm(), "unknown until loaded");
+        preview.changes = Some(Err("git status failed".to_string()));
+        assert!(preview.needs_second_confirm());
+        preview.changes = Some(Ok(WorktreeChanges::default()));
+        assert!(!preview.needs_second_confirm());
+    }
+
+    #[test]
+    fn test_undo_delete_without_journal_entry_sets_error() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        let journal_dir = tempfile::tempdir().unwrap();
+        state.delete_journal = DeleteJournal::at(journal_dir.path().join("journal.toml"));
+        state.pending_delete_store =
+            PendingDeleteStore::at(journal_dir.path().join("pending.toml"));
         state.selected_repo_idx = Some(0);
-        state.mode = Mode::ConfirmWorktreeDelete {
-            branch_name: "dev".to_string(),
-            has_session: true,
-        };
-        state.branches = vec![BranchEntry {
-            name: "dev".to_string(),
-            worktree_path: Some(PathBuf::from("/tmp/alpha-dev")),
-            has_session: true,
-            is_current: false,
-            remote: None,
-            is_default: false,
-            session_activity_ts: None,
-        }];
+        state.mode = Mode::BranchSelect;
 
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
         let ctx = ActionContext {
             git: &git,
             tmux: &tmux,
             keys: &keys,
-            matcher: &matcher,
             sender: &sender,
         };
 
-        process_action(Action::ConfirmDeleteWorktree, &mut state, &ctx);
+        process_action(Action::UndoDelete, &mut state, &ctx);
 
-        let killed = tmux.killed_sessions.lock().unwrap();
-        assert_eq!(killed.as_slice(), &["alpha-dev"]);
         assert!(matches!(state.mode, Mode::BranchSelect));
-        assert_eq!(state.pending_worktree_deletes.len(), 1);
+        assert!(
+            state
+                .error
+                .as_deref()
+                .is_some_and(|msg| msg.contains("No deleted worktree"))
+        );
     }
 
     #[test]
-    fn test_confirm_delete_without_session_does_not_kill() {
-        let mut repos = vec![make_repo("alpha")];
-        repos[0].worktrees.push(Worktree {
-            path: PathBuf::from("/tmp/alpha-dev"),
-            branch: Some("dev".to_string()),
-            is_main: false,
-        });
-        let mut state = AppState::new(repos, None);
+    fn test_undo_delete_restores_latest_entry_for_repo() {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        let journal_dir = tempfile::tempdir().unwrap();
+        state.delete_journal = DeleteJournal::at(journal_dir.path().join("journal.toml"));
+        state
+            .delete_journal
+            .save(&[DeletedWorktree {
+                repo_path: state.repos[0].path.clone(),
+                branch_name: "dev".to_string(),
+                worktree_path: journal_dir.path().join("alpha--dev"),
+                head: "abc123".to_string(),
+                stash: None,
+                deleted_at_unix_secs: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+            }])
+            .unwrap();
         state.selected_repo_idx = Some(0);
-        state.mode = Mode::ConfirmWorktreeDelete {
-            branch_name: "dev".to_string(),
-            has_session: false,
-        };
-        state.branches = vec![BranchEntry {
-            name: "dev".to_string(),
-            worktree_path: Some(PathBuf::from("/tmp/alpha-dev")),
-            has_session: false,
-            is_current: false,
-            remote: None,
-            is_default: false,
-            session_activity_ts: None,
-        }];
+        state.mode = Mode::BranchSelect;
 
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
         let ctx = ActionContext {
             git: &git,
             tmux: &tmux,
             keys: &keys,
-            matcher: &matcher,
             sender: &sender,
         };
 
-        process_action(Action::ConfirmDeleteWorktree, &mut state, &ctx);
+        // `u` only undoes while the search is empty; otherwise it's typed into it
+        let u = KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE);
+        state.branch_list.input.text = "fix".to_string();
+        assert!(matches!(
+            keymap::resolve_action(u, &state, &keys),
+            Some(Action::SearchPush('u'))
+        ));
+        state.branch_list.input.text.clear();
+        let action = keymap::resolve_action(u, &state, &keys).unwrap();
+        assert!(matches!(action, Action::UndoDelete));
 
-        let killed = tmux.killed_sessions.lock().unwrap();
-        assert!(killed.is_empty());
-        assert!(matches!(state.mode, Mode::BranchSelect));
-        assert_eq!(state.pending_worktree_deletes.len(), 1);
+        process_action(action, &mut state, &ctx);
+
+        assert!(matches!(&state.mode, Mode::Loading(msg) if msg.contains("dev")));
+        assert!(state.error.is_none());
+    }
+
+    #[test]
+    fn test_cancel_key_abandons_worktree_creation() {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        let keys = KeysConfig::default();
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+
+        let cancelled = state.start_loading("Creating worktree for dev...".to_string());
+        assert!(handle_loading_key(esc, &mut state, &keys).is_none());
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert_eq!(
+            state.error.as_deref(),
+            Some("Cancelled: Creating worktree for dev")
+        );
+
+        // The cancelled operation stops its git commands and reports nothing
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+        let creates_dir = tempfile::tempdir().unwrap();
+        spawn::spawn_worktree_creation(
+            &git,
+            &sender,
+            PendingCreateStore::at(creates_dir.path().join("creates.toml")),
+            PendingWorktreeCreate::new(
+                PathBuf::from("/tmp/alpha"),
+                Some("dev".to_string()),
+                PathBuf::from("/tmp/alpha-dev"),
+            ),
+            spawn::WorktreeSession {
+                name: "alpha--dev".to_string(),
+                base: None,
+            },
+            cancelled,
+            |git, wt_path| git.add_worktree(std::path::Path::new("/tmp/alpha"), "dev", wt_path),
+        );
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+
+        // Nor does a creation that finished just before the cancel landed open its worktree
+        let tmux = Arc::new(MockTmuxProvider::default());
+        let created = || AppEvent::WorktreeCreated {
+            path: PathBuf::from("/tmp/alpha-dev"),
+            session_name: "alpha--dev".to_string(),
+            base: None,
+        };
+        assert!(process_app_event(created(), &mut state, &git, &tmux, &sender).is_none());
+        assert_eq!(state.mode, Mode::BranchSelect);
+        state.start_loading("Creating worktree for dev...".to_string());
+        assert!(process_app_event(created(), &mut state, &git, &tmux, &sender).is_some());
+        assert!(state.loading_operation.is_none());
+
+        // Repo discovery can't be cancelled, but Ctrl+C still quits
+        state.mode = Mode::Loading("Discovering repos...".to_string());
+        assert!(handle_loading_key(esc, &mut state, &keys).is_none());
+        assert!(matches!(state.mode, Mode::Loading(_)));
+        assert!(matches!(
+            handle_loading_key(ctrl_c, &mut state, &keys),
+            Some(OpenAction::Quit)
+        ));
     }
 
     #[test]
@@ -1955,9 +4165,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         // Move left from end should skip over the 2-byte 'é'
         process_action(Action::CursorLeft, &mut state, &ctx);
@@ -1986,9 +4195,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         // Backspace should remove 'é' (2 bytes)
         process_action(Action::SearchPop, &mut state, &ctx);
@@ -2006,9 +4214,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         // Move cursor left
         process_action(Action::CursorLeft, &mut state, &ctx);
@@ -2165,9 +4372,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -2200,9 +4406,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -2238,9 +4443,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -2263,9 +4467,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -2311,9 +4514,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
         assert!(matches!(state.mode, Mode::Help { .. }));
@@ -2327,6 +4529,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_command_palette_runs_selected_command_in_underlying_mode() {
+        let repos = vec![make_repo("alpha")];
+        let mut state = AppState::new(repos, None);
+        state.mode = Mode::RepoSelect;
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::ShowCommandPalette, &mut state, &ctx);
+        assert!(matches!(state.mode, Mode::CommandPalette { .. }));
+
+        // Switching to help keeps the mode underneath, and back again
+        process_action(Action::ShowHelp, &mut state, &ctx);
+        assert!(
+            matches!(state.mode, Mode::Help { ref previous } if **previous == Mode::RepoSelect)
+        );
+        process_action(Action::ShowCommandPalette, &mut state, &ctx);
+        assert!(matches!(state.mode, Mode::CommandPalette { .. }));
+
+        for c in "global_search".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+        let overlay = state.help_overlay.as_ref().unwrap();
+        let &(row_idx, _) = overlay.list.filtered.first().unwrap();
+        assert_eq!(overlay.rows[row_idx].command, Command::GlobalSearch);
+
+        process_action(Action::RunPaletteCommand, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::GlobalSearch);
+        assert!(state.help_overlay.is_none());
+    }
+
     #[test]
     fn test_help_toggle_from_select_base_branch_restores_mode() {
         let repos = vec![make_repo("alpha")];
@@ -2336,14 +4573,14 @@ mod tests {
             new_name: "feat".into(),
             bases: vec!["main".into()],
             list: SearchableList::new(1),
+            note: None,
         });
 
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
         assert!(matches!(state.mode, Mode::Help { .. }));
@@ -2369,9 +4606,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
         assert!(matches!(state.mode, Mode::Help { .. }));
@@ -2397,9 +4633,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -2428,9 +4663,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -2483,9 +4717,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -2535,9 +4768,8 @@ mod tests {
         let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
         let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
         let keys = KeysConfig::default();
-        let matcher = SkimMatcherV2::default();
         let sender = make_sender();
-        let ctx = default_ctx(&git, &tmux, &keys, &matcher, &sender);
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
         process_action(Action::ShowHelp, &mut state, &ctx);
 
@@ -2585,12 +4817,12 @@ mod tests {
         // Simulate user typing "al" in search
         state.repo_list.input.text = "al".to_string();
         state.repo_list.input.cursor = 2;
-        let matcher = SkimMatcherV2::default();
         state.repo_list.filtered = state
             .repos
             .iter()
             .enumerate()
-            .filter_map(|(i, r)| matcher.fuzzy_match(&r.name, "al").map(|score| (i, score)))
+            .filter(|(_, r)| r.name.contains("al"))
+            .map(|(i, _)| (i, 1))
             .collect();
         state.repo_list.selected = if state.repo_list.filtered.is_empty() {
             None
@@ -2671,7 +4903,13 @@ mod tests {
 
     #[test]
     fn test_loading_dialog_short_message_single_line() {
-        let dialog = build_loading_dialog("⠋ ".to_string(), "Fetching...", Color::Magenta);
+        let dialog = build_loading_dialog(
+            "⠋ ".to_string(),
+            "Fetching...",
+            None,
+            Color::Magenta,
+            Color::Gray,
+        );
         let (width, height) = dialog.size(100);
         assert_eq!(width, 80);
         assert_eq!(height, 3);
@@ -2680,7 +4918,7 @@ mod tests {
     #[test]
     fn test_loading_dialog_long_message_wraps() {
         let msg = "Creating branch my-very-long-feature-branch-name from origin/main-development-branch...";
-        let dialog = build_loading_dialog("⠋ ".to_string(), msg, Color::Magenta);
+        let dialog = build_loading_dialog("⠋ ".to_string(), msg, None, Color::Magenta, Color::Gray);
         let (width, height) = dialog.size(60);
         assert_eq!(width, 48);
         assert!(
@@ -2694,7 +4932,9 @@ mod tests {
         let dialog = build_loading_dialog(
             "⠋ ".to_string(),
             "Creating branch foo from bar...",
+            None,
             Color::Magenta,
+            Color::Gray,
         );
         let (width, _height) = dialog.size(30);
         assert_eq!(width, 24);
@@ -2702,12 +4942,27 @@ mod tests {
 
     #[test]
     fn test_loading_dialog_uses_dialog_width() {
-        let dialog = build_loading_dialog("⠋ ".to_string(), "test", Color::Magenta);
+        let dialog =
+            build_loading_dialog("⠋ ".to_string(), "test", None, Color::Magenta, Color::Gray);
         let (w1, _) = dialog.size(80);
         let (w2, _) = dialog.size(40);
         assert!(w1 > w2, "wider terminal should produce wider dialog");
     }
 
+    #[test]
+    fn test_loading_dialog_shows_git_progress_below_message() {
+        let progress = GitProgress::parse("Receiving objects:  45% (450/1000)").unwrap();
+        let dialog = build_loading_dialog(
+            "⠋ ".to_string(),
+            "Fetching...",
+            Some(&progress),
+            Color::Magenta,
+            Color::Gray,
+        );
+        let (_, height) = dialog.size(100);
+        assert_eq!(height, 4);
+    }
+
     // -- confirm_delete_dialog sizing tests (via Dialog) --
 
     fn confirm_dialog_size(
@@ -2718,6 +4973,7 @@ mod tests {
         build_confirm_delete_dialog(
             branch_name,
             has_session,
+            None,
             "enter",
             "esc",
             Color::Magenta,
@@ -2800,6 +5056,7 @@ mod tests {
         let dialog = build_confirm_delete_dialog(
             branch_name,
             has_session,
+            None,
             "enter",
             "esc",
             Color::Magenta,
@@ -3193,8 +5450,7 @@ mod tests {
         setup.completions = vec![format!("{base}Desktop")];
         setup.selected_completion = Some(0);
 
-        let matcher = SkimMatcherV2::default();
-        handle_search_push(&mut state, &matcher, 'e');
+        handle_search_push(&mut state, &make_sender(), 'e');
 
         let setup = state.setup.as_ref().unwrap();
         assert_eq!(setup.selected_completion, None);
@@ -3210,8 +5466,7 @@ mod tests {
         setup.input.text = format!("{base}D");
         setup.input.cursor = setup.input.text.len();
 
-        let matcher = SkimMatcherV2::default();
-        handle_search_push(&mut state, &matcher, 'e');
+        handle_search_push(&mut state, &make_sender(), 'e');
 
         let setup = state.setup.as_ref().unwrap();
         assert_eq!(setup.completions.len(), 2);
@@ -3233,8 +5488,7 @@ mod tests {
         setup.input.text = format!("{base}De");
         setup.input.cursor = setup.input.text.len();
 
-        let matcher = SkimMatcherV2::default();
-        handle_search_pop(&mut state, &matcher);
+        handle_search_pop(&mut state, &make_sender());
 
         let setup = state.setup.as_ref().unwrap();
         assert_eq!(setup.completions.len(), 3);
@@ -3280,11 +5534,10 @@ mod tests {
         let (_tmp, base) = setup_temp_dirs(&["Desktop", "Development"]);
 
         let mut state = make_setup_state();
-        let matcher = SkimMatcherV2::default();
 
         // Type the base path + "De"
         for c in format!("{base}De").chars() {
-            handle_search_push(&mut state, &matcher, c);
+            handle_search_push(&mut state, &make_sender(), c);
         }
 
         let setup = state.setup.as_ref().unwrap();
@@ -3373,35 +5626,342 @@ mod tests {
         handle_setup_move_selection(&mut state, 1);
         assert_eq!(state.setup.as_ref().unwrap().selected_completion, Some(1));
 
-        // Down past last → None (back to text)
-        handle_setup_move_selection(&mut state, 1);
-        assert_eq!(state.setup.as_ref().unwrap().selected_completion, None);
+        // Down past last → None (back to text)
+        handle_setup_move_selection(&mut state, 1);
+        assert_eq!(state.setup.as_ref().unwrap().selected_completion, None);
+
+        // Up from None → last
+        handle_setup_move_selection(&mut state, -1);
+        assert_eq!(state.setup.as_ref().unwrap().selected_completion, Some(1));
+
+        // Up → first
+        handle_setup_move_selection(&mut state, -1);
+        assert_eq!(state.setup.as_ref().unwrap().selected_completion, Some(0));
+
+        // Up past first → None (back to text)
+        handle_setup_move_selection(&mut state, -1);
+        assert_eq!(state.setup.as_ref().unwrap().selected_completion, None);
+    }
+
+    // ── GitFetchCompleted tests ──
+
+    fn make_branch(name: &str, remote: Option<&str>) -> BranchEntry {
+        BranchEntry {
+            name: name.to_string(),
+            worktree_path: None,
+            has_session: false,
+            is_current: false,
+            is_default: false,
+            remote: remote.map(String::from),
+            session_activity_ts: None,
+            last_commit: None,
+            agent: None,
+            note: None,
+            labels: Vec::new(),
+            detached: false,
+        }
+    }
+
+    #[test]
+    fn test_branch_search_matches_labels() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        let mut sandbox = make_branch("sandbox-1", None);
+        sandbox.labels = vec!["agent".to_string()];
+        state.branches = vec![make_branch("feature", None), sandbox];
+        state.branch_list.reset(state.branches.len());
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        for c in "#agent".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+
+        let shown: Vec<&str> = state
+            .branch_list
+            .filtered
+            .iter()
+            .map(|&(idx, _)| state.branches[idx].name.as_str())
+            .collect();
+        assert_eq!(shown, ["sandbox-1"]);
+    }
+
+    #[test]
+    fn test_workspaces_add_the_branch_then_open() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        let workspaces_dir = tempfile::tempdir().unwrap();
+        state.workspace_store = WorkspaceStore::at(workspaces_dir.path().join("workspaces.toml"));
+        state
+            .workspaces
+            .insert("trio".to_string(), vec!["beta/main".to_string()]);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        state.branches = vec![make_branch("dev", None)];
+        state.branch_list.reset(state.branches.len());
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        // A search matching no workspace names a new one
+        process_action(Action::AddToWorkspace, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::WorkspaceSelect);
+        for c in "solo".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+        assert!(process_action(Action::ConfirmWorkspace, &mut state, &ctx).is_none());
+        assert_eq!(state.mode, Mode::BranchSelect);
+
+        // Otherwise the branch joins the selected one
+        process_action(Action::AddToWorkspace, &mut state, &ctx);
+        process_action(Action::MoveSelection(1), &mut state, &ctx);
+        process_action(Action::ConfirmWorkspace, &mut state, &ctx);
+        let saved = state.workspace_store.load();
+        let members: Vec<(&str, &[String])> = saved
+            .iter()
+            .map(|w| (w.name.as_str(), w.members.as_slice()))
+            .collect();
+        assert_eq!(
+            members,
+            [
+                ("solo", ["alpha/dev".to_string()].as_slice()),
+                ("trio", ["alpha/dev".to_string()].as_slice())
+            ]
+        );
+
+        process_action(Action::GoBack, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::RepoSelect);
+        process_action(Action::ShowWorkspaces, &mut state, &ctx);
+        let shown = state.workspace_select.as_ref().unwrap();
+        assert_eq!(shown.workspaces[1].members, ["beta/main", "alpha/dev"]);
+        process_action(Action::MoveSelection(1), &mut state, &ctx);
+        match process_action(Action::ConfirmWorkspace, &mut state, &ctx) {
+            Some(OpenAction::Workspace { name }) => assert_eq!(name, "trio"),
+            _ => panic!("expected OpenAction::Workspace"),
+        }
+    }
+
+    #[test]
+    fn test_edit_note_saves_and_clears_branch_note() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        let notes_dir = tempfile::tempdir().unwrap();
+        state.note_store = NoteStore::at(notes_dir.path().join("notes.toml"));
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        state.branches = vec![make_branch("dev", None), make_branch("dev", Some("origin"))];
+        state.branch_list.reset(state.branches.len());
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::EditNote, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::EditNote);
+        for c in "review".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+        process_action(Action::SaveNote, &mut state, &ctx);
+
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert_eq!(state.branches[0].note.as_deref(), Some("review"));
+        assert_eq!(state.branches[1].note, None);
+        let saved = state.note_store.load(&PathBuf::from("/tmp/alpha"));
+        assert_eq!(saved.get("dev").map(String::as_str), Some("review"));
+
+        // Editing starts from the saved note; clearing it removes the note
+        process_action(Action::EditNote, &mut state, &ctx);
+        process_action(Action::SearchDeleteToStart, &mut state, &ctx);
+        process_action(Action::SaveNote, &mut state, &ctx);
+        assert_eq!(state.branches[0].note, None);
+        assert!(
+            state
+                .note_store
+                .load(&PathBuf::from("/tmp/alpha"))
+                .is_empty()
+        );
+
+        // Remote-only branches can't have notes
+        state.branch_list.selected = Some(1);
+        process_action(Action::EditNote, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert!(state.error.is_some());
+    }
+
+    #[test]
+    fn test_new_branch_rejects_invalid_names() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        state.branches = vec![make_branch("main", None)];
+        state.branch_list.input.text = "fix login".to_string();
+        state.branch_list.filtered.clear();
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::StartNewBranchFlow, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert!(state.base_branch_selection.is_none());
+        assert!(state.error.is_some());
+    }
+
+    #[test]
+    fn test_new_branch_fills_in_name_template_fields() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        state.branches = vec![make_branch("main", None)];
+        state.branch_list.input.text = "Fix login".to_string();
+        state.branch_list.filtered.clear();
+        state.branch_name_template = Some("{team}/{ticket}-{slug}".to_string());
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let sender = make_sender();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+
+        process_action(Action::StartNewBranchFlow, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::BranchNameFields);
+        for c in "web".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+        process_action(Action::SaveBranchNameField, &mut state, &ctx);
+        assert_eq!(
+            state.branch_name_fields.as_ref().and_then(|f| f.current()),
+            Some("ticket")
+        );
+
+        // A value git wouldn't accept keeps the field open
+        for c in "AB 1".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+        process_action(Action::SaveBranchNameField, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::BranchNameFields);
+        assert!(state.error.is_some());
+
+        process_action(Action::SearchDeleteToStart, &mut state, &ctx);
+        for c in "AB-1".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+        process_action(Action::SaveBranchNameField, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::SelectBaseBranch);
+        assert!(state.branch_name_fields.is_none());
+        assert_eq!(
+            state.base_branch_selection.map(|sel| sel.new_name),
+            Some("web/AB-1-fix-login".to_string())
+        );
+    }
 
-        // Up from None → last
-        handle_setup_move_selection(&mut state, -1);
-        assert_eq!(state.setup.as_ref().unwrap().selected_completion, Some(1));
+    #[test]
+    fn test_fetched_ticket_names_the_branch_and_saves_its_link() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        let notes_dir = tempfile::tempdir().unwrap();
+        state.note_store = NoteStore::at(notes_dir.path().join("notes.toml"));
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        state.branches = vec![make_branch("main", None), make_branch("develop", None)];
+        state.branch_name_template = Some("{team}/{ticket}-{slug}".to_string());
 
-        // Up → first
-        handle_setup_move_selection(&mut state, -1);
-        assert_eq!(state.setup.as_ref().unwrap().selected_completion, Some(0));
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
 
-        // Up past first → None (back to text)
-        handle_setup_move_selection(&mut state, -1);
-        assert_eq!(state.setup.as_ref().unwrap().selected_completion, None);
+        state.start_loading("Fetching ABC-123...".to_string());
+        let ticket = kiosk_core::ticket::Ticket {
+            id: "ABC-123".to_string(),
+            title: "Fix login".to_string(),
+            url: "https://linear.app/acme/issue/ABC-123".to_string(),
+        };
+        process_app_event(
+            AppEvent::TicketFetched(Ok(ticket)),
+            &mut state,
+            &git,
+            &tmux,
+            &sender,
+        );
+        assert_eq!(state.mode, Mode::BranchNameFields);
+        for c in "web".chars() {
+            process_action(Action::SearchPush(c), &mut state, &ctx);
+        }
+        process_action(Action::SaveBranchNameField, &mut state, &ctx);
+        assert_eq!(state.mode, Mode::SelectBaseBranch);
+        assert_eq!(
+            state.base_branch_selection.as_ref().unwrap().new_name,
+            "web/ABC-123-fix-login"
+        );
+        let flow = state.base_branch_selection.as_mut().unwrap();
+        let develop = flow
+            .bases
+            .iter()
+            .position(|base| base == "develop")
+            .unwrap();
+        flow.list.selected = flow
+            .list
+            .filtered
+            .iter()
+            .position(|&(idx, _)| idx == develop);
+
+        process_action(Action::OpenBranch, &mut state, &ctx);
+        let event = rx
+            .iter()
+            .find(|event| matches!(event, AppEvent::WorktreeCreated { .. }))
+            .unwrap();
+        // The session's `{base}` is the branch picked as the base
+        let open = process_app_event(event, &mut state, &git, &tmux, &sender);
+        assert!(matches!(
+            open,
+            Some(OpenAction::Open { base: Some(base), .. }) if base == "develop"
+        ));
+        let notes = state.note_store.load(&PathBuf::from("/tmp/alpha"));
+        assert_eq!(
+            notes.get("web/ABC-123-fix-login").map(String::as_str),
+            Some("https://linear.app/acme/issue/ABC-123")
+        );
     }
 
-    // ── GitFetchCompleted tests ──
+    #[test]
+    fn test_failed_ticket_fetch_returns_to_the_branch_list() {
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let sender = make_sender();
 
-    fn make_branch(name: &str, remote: Option<&str>) -> BranchEntry {
-        BranchEntry {
-            name: name.to_string(),
-            worktree_path: None,
-            has_session: false,
-            is_current: false,
-            is_default: false,
-            remote: remote.map(String::from),
-            session_activity_ts: None,
-        }
+        state.start_loading("Fetching ABC-123...".to_string());
+        process_app_event(
+            AppEvent::TicketFetched(Err("failed to fetch ABC-123: Entity not found".to_string())),
+            &mut state,
+            &git,
+            &tmux,
+            &sender,
+        );
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert!(state.loading_operation.is_none());
+        assert_eq!(
+            state.error.as_deref(),
+            Some("failed to fetch ABC-123: Entity not found")
+        );
     }
 
     #[test]
@@ -3437,6 +5997,76 @@ mod tests {
         assert_eq!(state.branch_list.filtered.len(), 2);
     }
 
+    #[test]
+    fn test_branch_commits_fill_in_current_and_late_branches() {
+        let repos = vec![make_repo("alpha")];
+        let mut state = AppState::new(repos, None);
+        state.selected_repo_idx = Some(0);
+        state.mode = Mode::BranchSelect;
+        state.branches = vec![make_branch("main", None)];
+        state.branch_list.reset(1);
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let sender = make_sender();
+        let commit = |subject: &str| kiosk_core::git::CommitInfo {
+            subject: subject.to_string(),
+            author: "Ada".to_string(),
+            timestamp: 1,
+            ahead_behind: None,
+        };
+        let commits: std::collections::HashMap<_, _> = [
+            ("main".to_string(), commit("On main")),
+            ("origin/feat".to_string(), commit("On feat")),
+        ]
+        .into();
+
+        process_app_event(
+            AppEvent::BranchCommitsLoaded {
+                repo_path: PathBuf::from("/tmp/other"),
+                commits: commits.clone(),
+            },
+            &mut state,
+            &git,
+            &tmux,
+            &sender,
+        );
+        assert!(
+            state.branches[0].last_commit.is_none(),
+            "commits for another repo are ignored"
+        );
+
+        process_app_event(
+            AppEvent::BranchCommitsLoaded {
+                repo_path: PathBuf::from("/tmp/alpha"),
+                commits,
+            },
+            &mut state,
+            &git,
+            &tmux,
+            &sender,
+        );
+        assert_eq!(
+            state.branches[0].last_commit.as_ref().unwrap().subject,
+            "On main"
+        );
+
+        process_app_event(
+            AppEvent::RemoteBranchesLoaded {
+                repo_path: PathBuf::from("/tmp/alpha"),
+                branches: vec![make_branch("feat", Some("origin"))],
+            },
+            &mut state,
+            &git,
+            &tmux,
+            &sender,
+        );
+        assert_eq!(
+            state.branches[1].last_commit.as_ref().unwrap().subject,
+            "On feat"
+        );
+    }
+
     #[test]
     fn test_git_fetch_completed_deduplicates() {
         let repos = vec![make_repo("alpha")];
@@ -3596,6 +6226,7 @@ mod tests {
 
         process_app_event(
             AppEvent::RemoteBranchesLoaded {
+                repo_path: PathBuf::from("/tmp/alpha"),
                 branches: vec![make_branch("feature-y", Some("origin"))],
             },
             &mut state,
@@ -3676,6 +6307,112 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_repos_found_skips_ignored_and_hidden_repos() {
+        let mut state = AppState::new_loading("Discovering repos...", None);
+        state.repo_filter = RepoFilter::new(
+            &["/tmp/vendor-*".to_string()],
+            std::collections::HashSet::from([PathBuf::from("/tmp/old")]),
+        )
+        .unwrap();
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let sender = make_sender();
+
+        for name in ["kiosk", "vendor-lib", "old"] {
+            process_app_event(
+                AppEvent::ReposFound {
+                    repo: make_repo(name),
+                },
+                &mut state,
+                &git,
+                &tmux,
+                &sender,
+            );
+        }
+
+        let names: Vec<&str> = state.repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["kiosk"]);
+    }
+
+    #[test]
+    fn test_reloaded_config_replaces_theme_keys_and_drops_ignored_repos() {
+        let mut state = AppState::new(vec![make_repo("alpha"), make_repo("beta")], None);
+        let mut live = LiveConfig {
+            theme: crate::theme::Theme::from_config(&kiosk_core::config::ThemeConfig::default()),
+            keys: KeysConfig::default(),
+        };
+        let config = kiosk_core::config::load_config_from_str(
+            r#"
+search_dirs = ["/tmp"]
+ignore = ["/tmp/beta"]
+sort = "alphabetical"
+mouse = true
+[theme]
+accent = "blue"
+[keys.general]
+C-x = "quit"
+"#,
+        )
+        .unwrap();
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider::default());
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let sender = make_sender();
+        apply_reloaded_config(config, &mut state, &mut live, &git, &tmux, &sender);
+
+        assert_eq!(live.theme.accent, Color::Blue);
+        let ctrl_x = kiosk_core::keyboard::KeyEvent::new(
+            kiosk_core::keyboard::KeyCode::Char('x'),
+            kiosk_core::keyboard::KeyModifiers::CONTROL,
+        );
+        assert_eq!(live.keys.general.get(&ctrl_x), Some(&Command::Quit));
+        assert_eq!(state.sort, kiosk_core::config::SortOrder::Alphabetical);
+        assert!(state.mouse);
+        let names: Vec<&str> = state.repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["alpha"]);
+        assert!(state.loading_repos, "repos are rescanned");
+    }
+
+    #[test]
+    fn test_config_watcher_sends_reloads_and_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "search_dirs = []\n").unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let sender = EventSender {
+            tx,
+            cancel: Arc::clone(&cancel),
+            filter_generation: Arc::default(),
+        };
+        let reloads = spawn_config_watcher(
+            kiosk_core::config::ConfigSource {
+                path: path.clone(),
+                profile: None,
+            },
+            &sender,
+        );
+
+        // Make sure the size changes, in case the filesystem's mtime is coarse
+        std::fs::write(&path, "search_dirs = []\nsort = \"activity\"\n").unwrap();
+        let reload = reloads.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(
+            matches!(&reload, Ok(config) if config.sort == kiosk_core::config::SortOrder::Activity),
+            "{reload:?}"
+        );
+
+        std::fs::write(&path, "search_dirs = []\nsrot = \"activity\"\n").unwrap();
+        let reload = reloads.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(
+            matches!(&reload, Err(e) if e.contains("srot")),
+            "{reload:?}"
+        );
+        cancel.store(true, Ordering::Relaxed);
+    }
+
     #[test]
     fn test_git_fetch_completed_ignored_wrong_repo() {
         let repos = vec![make_repo("alpha")];
@@ -3813,6 +6550,7 @@ mod tests {
         let sender = EventSender {
             tx,
             cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
         };
 
         // Enter branch select
@@ -3845,6 +6583,148 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_fetch_remotes_key_fetches_configured_remotes_when_on_enter_is_off() {
+        let repos = vec![make_repo("alpha")];
+        let mut state = AppState::new(repos, None);
+        state.fetch.on_enter = false;
+        state.fetch.remotes = vec!["upstream".into()];
+
+        let mock = Arc::new(MockGitProvider {
+            branches: vec!["main".into()],
+            remotes: vec!["origin".into(), "upstream".into()],
+            ..Default::default()
+        });
+        let git: Arc<dyn GitProvider> = mock.clone();
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let keys = KeysConfig::default();
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+
+        enter_branch_select(&mut state, 0, &git, &tmux, &sender);
+        let event = rx.recv_timeout(std::time::Duration::from_secs(2)).unwrap();
+        process_app_event(event, &mut state, &git, &tmux, &sender);
+        assert!(!state.fetching_remotes);
+
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+        process_action(Action::FetchRemotes, &mut state, &ctx);
+        assert!(state.fetching_remotes);
+        loop {
+            let event = rx.recv_timeout(std::time::Duration::from_secs(2)).unwrap();
+            if matches!(event, AppEvent::GitFetchCompleted { is_final: true, .. }) {
+                break;
+            }
+        }
+        assert_eq!(
+            mock.fetch_remote_calls.lock().unwrap().as_slice(),
+            &[(PathBuf::from("/tmp/alpha"), "upstream".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn test_fetch_on_enter_waits_for_min_interval() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let repos = vec![make_repo("alpha")];
+        let mut state = AppState::new(repos, None);
+        state.fetch.min_interval_secs = 3600;
+        state.fetch_history = FetchHistoryStore::at(history_dir.path().join("fetches.toml"));
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider {
+            branches: vec!["main".into()],
+            ..Default::default()
+        });
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+
+        enter_branch_select(&mut state, 0, &git, &tmux, &sender);
+        let event = rx.recv_timeout(std::time::Duration::from_secs(2)).unwrap();
+        process_app_event(event, &mut state, &git, &tmux, &sender);
+        assert!(state.fetching_remotes);
+        assert!(
+            state
+                .fetch_history
+                .last_fetch_unix_secs(std::path::Path::new("/tmp/alpha"))
+                .is_some()
+        );
+
+        // Entering again within the interval doesn't fetch
+        enter_branch_select(&mut state, 0, &git, &tmux, &sender);
+        let event = loop {
+            let event = rx.recv_timeout(std::time::Duration::from_secs(2)).unwrap();
+            if matches!(event, AppEvent::BranchesLoaded { .. }) {
+                break event;
+            }
+        };
+        process_app_event(event, &mut state, &git, &tmux, &sender);
+        assert!(!state.fetching_remotes);
+    }
+
+    #[test]
+    fn test_fetch_is_left_to_another_kiosk_fetching_the_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let locks = FetchLocks::in_dir(dir.path().join("fetch-locks"));
+        let FetchClaim::Granted(other) = locks.claim(std::path::Path::new("/tmp/alpha")) else {
+            panic!("nothing else is fetching alpha");
+        };
+        let mut state = AppState::new(vec![make_repo("alpha")], None);
+        state.fetch_locks = locks;
+        state.fetch_history = FetchHistoryStore::at(dir.path().join("fetches.toml"));
+
+        let git: Arc<dyn GitProvider> = Arc::new(MockGitProvider {
+            branches: vec!["main".into()],
+            ..Default::default()
+        });
+        let tmux: Arc<dyn TmuxProvider> = Arc::new(MockTmuxProvider::default());
+        let (tx, rx) = mpsc::channel();
+        let sender = EventSender {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            filter_generation: Arc::default(),
+        };
+
+        enter_branch_select(&mut state, 0, &git, &tmux, &sender);
+        let event = rx.recv_timeout(std::time::Duration::from_secs(2)).unwrap();
+        process_app_event(event, &mut state, &git, &tmux, &sender);
+        assert_eq!(state.mode, Mode::BranchSelect);
+        assert!(!state.fetching_remotes);
+        assert!(
+            state
+                .error
+                .as_deref()
+                .is_some_and(|error| error.contains("already fetching alpha"))
+        );
+
+        // A manual fetch is left to it too
+        state.clear_error();
+        let keys = KeysConfig::default();
+        let ctx = default_ctx(&git, &tmux, &keys, &sender);
+        process_action(Action::FetchRemotes, &mut state, &ctx);
+        assert!(!state.fetching_remotes);
+        assert!(state.error.is_some());
+
+        // Once the other kiosk's fetch is done, this one fetches again
+        drop(other);
+        state.clear_error();
+        enter_branch_select(&mut state, 0, &git, &tmux, &sender);
+        let event = loop {
+            let event = rx.recv_timeout(std::time::Duration::from_secs(2)).unwrap();
+            if matches!(event, AppEvent::BranchesLoaded { .. }) {
+                break event;
+            }
+        };
+        process_app_event(event, &mut state, &git, &tmux, &sender);
+        assert!(state.fetching_remotes);
+    }
+
     #[test]
     fn test_repos_found_preserves_search_state() {
         let repos = vec![make_repo("alpha")];
@@ -3853,12 +6733,12 @@ mod tests {
         state.repo_list.input.text = "al".to_string();
         state.repo_list.input.cursor = 2;
 
-        let matcher = SkimMatcherV2::default();
         state.repo_list.filtered = state
             .repos
             .iter()
             .enumerate()
-            .filter_map(|(i, r)| matcher.fuzzy_match(&r.name, "al").map(|score| (i, score)))
+            .filter(|(_, r)| r.name.contains("al"))
+            .map(|(i, _)| (i, 1))
             .collect();
         state.repo_list.selected = Some(0);
 
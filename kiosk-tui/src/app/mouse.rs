@@ -0,0 +1,268 @@
+use crate::components;
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use kiosk_core::{
+    action::Action,
+    state::{AppState, Mode},
+};
+use ratatui::layout::{Position, Rect};
+use std::time::{Duration, Instant};
+
+/// A second click on the same row within this long opens it.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// The last row clicked, for spotting double-clicks.
+#[derive(Debug, Default)]
+pub(super) struct ClickTracker {
+    last: Option<(Instant, usize)>,
+}
+
+/// Resolve a mouse event into an Action: clicking a row of the active list selects it,
+/// clicking it again opens it, and the wheel moves the selection. `list_area` is where the
+/// active list is drawn, borders included.
+pub(super) fn resolve_mouse_action(
+    event: MouseEvent,
+    state: &AppState,
+    list_area: Rect,
+    clicks: &mut ClickTracker,
+    now: Instant,
+) -> Option<Action> {
+    // Confirm dialogs and the note and branch name inputs sit on top of the list and need an
+    // explicit answer
+    if matches!(
+        state.mode,
+        Mode::ConfirmWorktreeDelete { .. }
+            | Mode::ConfirmResume { .. }
+            | Mode::ConfirmRecover { .. }
+            | Mode::EditNote
+            | Mode::BranchNameFields
+    ) {
+        return None;
+    }
+    match event.kind {
+        MouseEventKind::ScrollUp => Some(Action::MoveSelection(-1)),
+        MouseEventKind::ScrollDown => Some(Action::MoveSelection(1)),
+        MouseEventKind::Down(MouseButton::Left) => {
+            let Some(index) = clicked_index(state, list_area, event.column, event.row) else {
+                clicks.last = None;
+                return None;
+            };
+            let double_click = clicks.last.is_some_and(|(at, last_index)| {
+                last_index == index && now.duration_since(at) <= DOUBLE_CLICK_INTERVAL
+            });
+            if double_click {
+                clicks.last = None;
+                open_action(&state.mode).or(Some(Action::SelectRow(index)))
+            } else {
+                clicks.last = Some((now, index));
+                Some(Action::SelectRow(index))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// What double-clicking a row does in `mode`, matching Enter.
+fn open_action(mode: &Mode) -> Option<Action> {
+    match mode {
+        Mode::RepoSelect => Some(Action::OpenRepo),
+        Mode::BranchSelect | Mode::SelectBaseBranch | Mode::GlobalSearch | Mode::TagSelect => {
+            Some(Action::OpenBranch)
+        }
+        Mode::WorkspaceSelect => Some(Action::ConfirmWorkspace),
+        Mode::CommandPalette { .. } => Some(Action::RunPaletteCommand),
+        _ => None,
+    }
+}
+
+/// Position in the active list's filtered entries of the row at (`column`, `row`).
+fn clicked_index(state: &AppState, list_area: Rect, column: u16, row: u16) -> Option<usize> {
+    let inner = Rect::new(
+        list_area.x.saturating_add(1),
+        list_area.y.saturating_add(1),
+        list_area.width.saturating_sub(2),
+        list_area.height.saturating_sub(2),
+    );
+    if !inner.contains(Position::new(column, row)) {
+        return None;
+    }
+    let list = state.active_list()?;
+    let visual_row = list.scroll_offset + usize::from(row - inner.y);
+
+    let index = if let (Mode::Help { .. } | Mode::CommandPalette { .. }, Some(overlay)) =
+        (&state.mode, &state.help_overlay)
+    {
+        // Help scrolls by visual row, and section headers and blank lines aren't entries
+        let (row_item_indices, _) = components::help::help_visual_metrics(overlay);
+        row_item_indices
+            .iter()
+            .position(|&visual| visual == visual_row)?
+    } else {
+        visual_row
+    };
+    (index < list.filtered.len()).then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use kiosk_core::{
+        config::KeysConfig,
+        git::Repo,
+        state::{HelpOverlayState, SearchableList},
+    };
+    use std::path::PathBuf;
+
+    /// A list drawn at the top left with room for 5 rows inside its borders.
+    const LIST_AREA: Rect = Rect::new(0, 3, 40, 7);
+
+    fn state_with_repos(count: usize) -> AppState {
+        let repos = (0..count)
+            .map(|i| Repo {
+                name: format!("repo{i}"),
+                session_name: format!("repo{i}"),
+                path: PathBuf::from(format!("/tmp/repo{i}")),
+                worktrees: vec![],
+            })
+            .collect();
+        AppState::new(repos, None)
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    fn click(row: u16) -> MouseEvent {
+        mouse(MouseEventKind::Down(MouseButton::Left), 5, row)
+    }
+
+    #[test]
+    fn click_selects_the_row_under_the_pointer() {
+        let mut state = state_with_repos(10);
+        state.repo_list.scroll_offset = 2;
+        let mut clicks = ClickTracker::default();
+        let now = Instant::now();
+
+        let action = resolve_mouse_action(click(5), &state, LIST_AREA, &mut clicks, now);
+        assert!(matches!(action, Some(Action::SelectRow(3))), "{action:?}");
+
+        for outside in [
+            click(3),
+            click(9),
+            mouse(MouseEventKind::Down(MouseButton::Left), 39, 5),
+        ] {
+            let action = resolve_mouse_action(outside, &state, LIST_AREA, &mut clicks, now);
+            assert!(action.is_none(), "{action:?}");
+        }
+    }
+
+    #[test]
+    fn clicks_past_the_last_entry_are_ignored() {
+        let state = state_with_repos(2);
+        let mut clicks = ClickTracker::default();
+        let action = resolve_mouse_action(click(6), &state, LIST_AREA, &mut clicks, Instant::now());
+        assert!(action.is_none(), "{action:?}");
+    }
+
+    #[test]
+    fn double_click_opens_and_slow_clicks_only_select() {
+        let state = state_with_repos(3);
+        let mut clicks = ClickTracker::default();
+        let start = Instant::now();
+
+        resolve_mouse_action(click(4), &state, LIST_AREA, &mut clicks, start);
+        let action = resolve_mouse_action(
+            click(4),
+            &state,
+            LIST_AREA,
+            &mut clicks,
+            start + Duration::from_millis(200),
+        );
+        assert!(matches!(action, Some(Action::OpenRepo)), "{action:?}");
+
+        let later = start + Duration::from_secs(5);
+        resolve_mouse_action(click(4), &state, LIST_AREA, &mut clicks, later);
+        let action = resolve_mouse_action(
+            click(4),
+            &state,
+            LIST_AREA,
+            &mut clicks,
+            later + DOUBLE_CLICK_INTERVAL * 2,
+        );
+        assert!(matches!(action, Some(Action::SelectRow(0))), "{action:?}");
+
+        let last = later + Duration::from_secs(5);
+        resolve_mouse_action(click(4), &state, LIST_AREA, &mut clicks, last);
+        let action = resolve_mouse_action(click(5), &state, LIST_AREA, &mut clicks, last);
+        assert!(
+            matches!(action, Some(Action::SelectRow(1))),
+            "a click on another row isn't a double-click: {action:?}"
+        );
+    }
+
+    #[test]
+    fn wheel_moves_the_selection() {
+        let state = state_with_repos(3);
+        let mut clicks = ClickTracker::default();
+        let now = Instant::now();
+        let up = resolve_mouse_action(
+            mouse(MouseEventKind::ScrollUp, 0, 0),
+            &state,
+            LIST_AREA,
+            &mut clicks,
+            now,
+        );
+        assert!(matches!(up, Some(Action::MoveSelection(-1))));
+        let down = resolve_mouse_action(
+            mouse(MouseEventKind::ScrollDown, 0, 0),
+            &state,
+            LIST_AREA,
+            &mut clicks,
+            now,
+        );
+        assert!(matches!(down, Some(Action::MoveSelection(1))));
+    }
+
+    #[test]
+    fn help_clicks_skip_section_headers() {
+        let mut state = state_with_repos(1);
+        let catalog = KeysConfig::default().catalog_for_mode(&Mode::RepoSelect);
+        state.help_overlay = Some(HelpOverlayState {
+            list: SearchableList::new(catalog.flattened.len()),
+            rows: catalog.flattened,
+        });
+        state.mode = Mode::Help {
+            previous: Box::new(Mode::RepoSelect),
+        };
+        let mut clicks = ClickTracker::default();
+        let now = Instant::now();
+
+        // The first visual row is a section header
+        let header = resolve_mouse_action(click(4), &state, LIST_AREA, &mut clicks, now);
+        assert!(header.is_none(), "{header:?}");
+        let first = resolve_mouse_action(click(5), &state, LIST_AREA, &mut clicks, now);
+        assert!(matches!(first, Some(Action::SelectRow(0))), "{first:?}");
+        let again = resolve_mouse_action(click(5), &state, LIST_AREA, &mut clicks, now);
+        assert!(
+            matches!(again, Some(Action::SelectRow(0))),
+            "double-clicking help only selects: {again:?}"
+        );
+    }
+
+    #[test]
+    fn delete_dialog_ignores_the_mouse() {
+        let mut state = state_with_repos(3);
+        state.mode = Mode::ConfirmWorktreeDelete {
+            branch_name: "feat".to_string(),
+            has_session: false,
+        };
+        let mut clicks = ClickTracker::default();
+        let action = resolve_mouse_action(click(4), &state, LIST_AREA, &mut clicks, Instant::now());
+        assert!(action.is_none());
+    }
+}
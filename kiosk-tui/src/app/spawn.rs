@@ -1,22 +1,162 @@
-use kiosk_core::{event::AppEvent, git::GitProvider, state::BranchEntry};
+use kiosk_core::{
+    agent::AgentCache,
+    config::{Config, ConfigSource, FetchConfig, RepoTemplate, SearchConfig, TicketConfig},
+    event::AppEvent,
+    git::{GitProvider, cancel::with_cancel, progress::with_progress},
+    instance::InstanceLock,
+    new_repo::create_repo,
+    pending_delete::{
+        DeleteJournal, DeletedWorktree, remove_worktree_with_journal, restore_deleted_worktree,
+    },
+    recovery::{PendingCreateStore, PendingWorktreeCreate},
+    state::{BranchEntry, TagEntry},
+    ticket,
+};
 use rayon::ThreadPoolBuilder;
 use std::{
     collections::HashMap,
-    path::PathBuf,
-    sync::{Arc, atomic::Ordering},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
     thread,
+    time::{Duration, SystemTime},
 };
 
 use kiosk_core::git::Repo;
 use kiosk_core::tmux::TmuxProvider;
 
 use super::EventSender;
+use super::actions::sort_matches;
+use crate::matcher::Matcher;
 
 /// Maximum number of concurrent `git worktree list` enrichment calls.
 const ENRICHMENT_POOL_SIZE: usize = 8;
 
 /// Maximum number of concurrent per-remote `git fetch` calls.
 const FETCH_POOL_SIZE: usize = 4;
+/// Remote branches are added to the branch picker this many at a time, so the picker keeps
+/// drawing and taking keys while a repo with thousands of them loads.
+const REMOTE_BRANCH_PAGE_SIZE: usize = 500;
+
+/// Lists at least this long are searched on a background thread, so typing stays responsive
+/// in repos with thousands of branches.
+pub(super) const BACKGROUND_FILTER_MIN_ITEMS: usize = 5000;
+/// How long a background filter waits for more typing before scoring anything.
+const FILTER_DEBOUNCE: Duration = Duration::from_millis(40);
+/// Items a background filter scores between sending results.
+const FILTER_CHUNK_SIZE: usize = 2000;
+
+/// How often the config file is checked for changes while the TUI runs.
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A config reloaded after its file changed on disk, or why it couldn't be loaded.
+pub(super) type ConfigReload = Result<Box<Config>, String>;
+
+/// Reload the config whenever the modification time or size of the config file, or of a
+/// file it includes, changes, until the TUI quits. A config file that briefly disappears
+/// (editors that save by renaming) is skipped until it's back.
+///
+/// Reloads come on their own channel rather than as an `AppEvent`, as applying them takes
+/// the theme and keys that `run_loop` owns.
+pub(super) fn spawn_config_watcher(
+    source: ConfigSource,
+    sender: &EventSender,
+) -> mpsc::Receiver<ConfigReload> {
+    let cancel = Arc::clone(&sender.cancel);
+    let (tx, rx) = mpsc::channel();
+    let mut files = source
+        .load_with_files()
+        .map_or_else(|_| vec![source.path.clone()], |(_, files)| files);
+    let mut last_seen: Vec<_> = files.iter().map(|file| file_stamp(file)).collect();
+    thread::spawn(move || {
+        while !cancel.load(Ordering::Relaxed) {
+            thread::sleep(CONFIG_POLL_INTERVAL);
+            let stamps: Vec<_> = files.iter().map(|file| file_stamp(file)).collect();
+            if file_stamp(&source.path).is_none() || stamps == last_seen {
+                continue;
+            }
+            let result = source.load_with_files();
+            if let Ok((_, loaded)) = &result {
+                files.clone_from(loaded);
+            }
+            last_seen = files.iter().map(|file| file_stamp(file)).collect();
+            let reload = result
+                .map(|(config, _)| Box::new(config))
+                .map_err(|e| e.to_string());
+            if tx.send(reload).is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+/// Reload session activity whenever the multiplexer reports sessions coming or going or
+/// their panes' output, for providers that report it, until the TUI quits.
+pub(super) fn spawn_session_watcher<T: TmuxProvider + ?Sized + 'static>(
+    tmux: &Arc<T>,
+    sender: &EventSender,
+) {
+    let tmux = Arc::clone(tmux);
+    let sender = sender.clone();
+    thread::spawn(move || {
+        let Some(events) = tmux.subscribe() else {
+            return;
+        };
+        while events.recv().is_ok() {
+            if sender.cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            // One reload covers a burst of changes
+            while events.try_recv().is_ok() {}
+            let session_activity = tmux.list_sessions_with_activity().into_iter().collect();
+            sender.send(AppEvent::SessionActivityLoaded { session_activity });
+        }
+    });
+}
+
+/// Score the items of the list `items_id` against `query` in the background, sending each
+/// chunk's matches as it's scored. Gives up as soon as the search is edited again, so a
+/// burst of typing only scores the last edit.
+pub(super) fn spawn_fuzzy_filter(
+    items_id: u64,
+    items: Arc<[String]>,
+    query: String,
+    search: SearchConfig,
+    sender: &EventSender,
+) {
+    let sender = sender.clone();
+    let generation = sender.next_filter_generation();
+    thread::spawn(move || {
+        thread::sleep(FILTER_DEBOUNCE);
+        let matcher = Matcher::new(search);
+        for start in (0..items.len()).step_by(FILTER_CHUNK_SIZE) {
+            if !sender.is_current_filter(generation) || sender.cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            let end = (start + FILTER_CHUNK_SIZE).min(items.len());
+            let mut scored = matcher.matches(&items, start..end, &query);
+            sort_matches(&mut scored, &items);
+            sender.send(AppEvent::FilterResults {
+                generation,
+                query: query.clone(),
+                items_id,
+                items: Arc::clone(&items),
+                first: start == 0,
+                matches: scored,
+            });
+        }
+    });
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
 
 pub(super) fn spawn_repo_discovery<T: TmuxProvider + ?Sized + 'static>(
     git: &Arc<dyn GitProvider>,
@@ -120,13 +260,50 @@ pub(super) fn spawn_repo_discovery<T: TmuxProvider + ?Sized + 'static>(
     });
 }
 
+/// Run the git operation behind the loading dialog, forwarding progress from its git commands
+/// and stopping them once it's `cancelled` or kiosk quits. Returns `None` if it was cancelled,
+/// as the dialog has already gone.
+fn run_loading_operation<T>(
+    sender: &EventSender,
+    cancelled: &Arc<AtomicBool>,
+    work: impl FnOnce() -> T,
+) -> Option<T> {
+    let progress_sender = sender.clone();
+    let quit = Arc::clone(&sender.cancel);
+    let stop = Arc::clone(cancelled);
+    let result = with_cancel(
+        move || quit.load(Ordering::Relaxed) || stop.load(Ordering::Relaxed),
+        || {
+            with_progress(
+                move |progress| {
+                    progress_sender.send(AppEvent::LoadingProgress {
+                        progress: progress.clone(),
+                    });
+                },
+                work,
+            )
+        },
+    );
+    (!cancelled.load(Ordering::Relaxed)).then_some(result)
+}
+
+/// The session to open in a worktree once it's created.
+pub(super) struct WorktreeSession {
+    pub(super) name: String,
+    /// The branch a new branch is created from, for `{base}` in the session's commands
+    pub(super) base: Option<String>,
+}
+
+/// Create the worktree at `wt_path` with `create` in the background, then open `session`
+/// in it.
 pub(super) fn spawn_worktree_creation(
     git: &Arc<dyn GitProvider>,
     sender: &EventSender,
-    repo_path: PathBuf,
-    branch: String,
-    wt_path: PathBuf,
-    session_name: String,
+    creates: PendingCreateStore,
+    pending: PendingWorktreeCreate,
+    session: WorktreeSession,
+    cancelled: Arc<AtomicBool>,
+    create: impl FnOnce(&dyn GitProvider, &Path) -> anyhow::Result<()> + Send + 'static,
 ) {
     let git = Arc::clone(git);
     let sender = sender.clone();
@@ -134,21 +311,137 @@ pub(super) fn spawn_worktree_creation(
         if sender.cancel.load(Ordering::Relaxed) {
             return;
         }
-        match git.add_worktree(&repo_path, &branch, &wt_path) {
-            Ok(()) => sender.send(AppEvent::WorktreeCreated {
+        let wt_path = pending.worktree_path.clone();
+        let result = run_loading_operation(&sender, &cancelled, || {
+            creates.track(&pending, || create(git.as_ref(), &wt_path))
+        });
+        match result {
+            Some(Ok(())) => sender.send(AppEvent::WorktreeCreated {
                 path: wt_path,
-                session_name,
+                session_name: session.name,
+                base: session.base,
+            }),
+            Some(Err(e)) => sender.send(AppEvent::GitError(format!("{e}"))),
+            None => {}
+        }
+    });
+}
+
+/// Clone `url` into `dest` in the background, then register the new repo.
+pub(super) fn spawn_clone(
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+    url: String,
+    dest: PathBuf,
+    search_dirs: Vec<(PathBuf, u16)>,
+    cancelled: Arc<AtomicBool>,
+) {
+    let git = Arc::clone(git);
+    let sender = sender.clone();
+    thread::spawn(move || {
+        if sender.cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        match run_loading_operation(&sender, &cancelled, || git.clone_repo(&url, &dest)) {
+            Some(Ok(())) => send_new_repo(git.as_ref(), &sender, &dest, &search_dirs, |repo| {
+                AppEvent::RepoCloned { repo }
             }),
-            Err(e) => sender.send(AppEvent::GitError(format!("{e}"))),
+            Some(Err(e)) => sender.send(AppEvent::GitError(format!("{e}"))),
+            None => {}
         }
     });
 }
 
+/// Create the repo `name` at `dest` from `template` in the background, then open it.
+pub(super) fn spawn_new_repo(
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+    name: String,
+    dest: PathBuf,
+    template: RepoTemplate,
+    search_dirs: Vec<(PathBuf, u16)>,
+    cancelled: Arc<AtomicBool>,
+) {
+    let git = Arc::clone(git);
+    let sender = sender.clone();
+    thread::spawn(move || {
+        if sender.cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        let create = || create_repo(git.as_ref(), &dest, &name, &template);
+        match run_loading_operation(&sender, &cancelled, create) {
+            Some(Ok(created)) => {
+                send_new_repo(git.as_ref(), &sender, &dest, &search_dirs, |repo| {
+                    AppEvent::RepoCreated {
+                        repo,
+                        github_error: created.github_error,
+                    }
+                });
+            }
+            Some(Err(e)) => sender.send(AppEvent::GitError(format!("{e}"))),
+            None => {}
+        }
+    });
+}
+
+/// Fetch ticket `id` in the background, to name a new branch after it.
+pub(super) fn spawn_ticket_fetch(
+    sender: &EventSender,
+    config: TicketConfig,
+    id: String,
+    cancelled: Arc<AtomicBool>,
+) {
+    let sender = sender.clone();
+    thread::spawn(move || {
+        if sender.cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Some(result) = run_loading_operation(&sender, &cancelled, || {
+            ticket::fetch(&config, &id).map_err(|e| format!("{e:#}"))
+        }) {
+            sender.send(AppEvent::TicketFetched(result));
+        }
+    });
+}
+
+/// Find the repo just made at `dest` and report it with `event`. Its parent is searched too,
+/// in case it isn't one of the search dirs.
+fn send_new_repo(
+    git: &dyn GitProvider,
+    sender: &EventSender,
+    dest: &Path,
+    search_dirs: &[(PathBuf, u16)],
+    event: impl FnOnce(Repo) -> AppEvent,
+) {
+    let mut dirs = search_dirs.to_vec();
+    if let Some(parent) = dest.parent()
+        && !dirs.iter().any(|(dir, _)| dir == parent)
+    {
+        dirs.push((parent.to_path_buf(), 1));
+    }
+    let repo = git
+        .discover_repos(&dirs)
+        .into_iter()
+        .find(|repo| repo.path == dest);
+    sender.send(repo.map_or_else(
+        || {
+            AppEvent::GitError(format!(
+                "Created {}, but it isn't a repo kiosk can find",
+                dest.display()
+            ))
+        },
+        event,
+    ));
+}
+
 pub(super) fn spawn_worktree_removal(
     git: &Arc<dyn GitProvider>,
     sender: &EventSender,
+    journal: DeleteJournal,
+    repo_path: PathBuf,
     worktree_path: PathBuf,
     branch_name: String,
+    use_trash: bool,
 ) {
     let git = Arc::clone(git);
     let sender = sender.clone();
@@ -156,7 +449,14 @@ pub(super) fn spawn_worktree_removal(
         if sender.cancel.load(Ordering::Relaxed) {
             return;
         }
-        match git.remove_worktree(&worktree_path) {
+        match remove_worktree_with_journal(
+            git.as_ref(),
+            &journal,
+            &repo_path,
+            &branch_name,
+            &worktree_path,
+            use_trash,
+        ) {
             Ok(()) => sender.send(AppEvent::WorktreeRemoved {
                 branch_name,
                 worktree_path,
@@ -170,14 +470,10 @@ pub(super) fn spawn_worktree_removal(
     });
 }
 
-pub(super) fn spawn_branch_and_worktree_creation(
+pub(super) fn spawn_worktree_changes_loading(
     git: &Arc<dyn GitProvider>,
     sender: &EventSender,
-    repo_path: PathBuf,
-    new_branch: String,
-    base: String,
-    wt_path: PathBuf,
-    session_name: String,
+    worktree_path: PathBuf,
 ) {
     let git = Arc::clone(git);
     let sender = sender.clone();
@@ -185,12 +481,35 @@ pub(super) fn spawn_branch_and_worktree_creation(
         if sender.cancel.load(Ordering::Relaxed) {
             return;
         }
-        match git.create_branch_and_worktree(&repo_path, &new_branch, &base, &wt_path) {
-            Ok(()) => sender.send(AppEvent::WorktreeCreated {
-                path: wt_path,
-                session_name,
-            }),
-            Err(e) => sender.send(AppEvent::GitError(format!("{e}"))),
+        let changes = git
+            .worktree_changes(&worktree_path)
+            .map_err(|e| e.to_string());
+        sender.send(AppEvent::WorktreeChangesLoaded {
+            worktree_path,
+            changes,
+        });
+    });
+}
+
+pub(super) fn spawn_worktree_restore(
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+    journal: DeleteJournal,
+    entry: DeletedWorktree,
+    cancelled: Arc<AtomicBool>,
+) {
+    let git = Arc::clone(git);
+    let sender = sender.clone();
+    thread::spawn(move || {
+        if sender.cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        match run_loading_operation(&sender, &cancelled, || {
+            restore_deleted_worktree(git.as_ref(), &journal, &entry)
+        }) {
+            Some(Ok(())) => sender.send(AppEvent::WorktreeRestored),
+            Some(Err(e)) => sender.send(AppEvent::GitError(format!("{e}"))),
+            None => {}
         }
     });
 }
@@ -201,6 +520,7 @@ pub(super) fn spawn_branch_loading<T: TmuxProvider + ?Sized + 'static>(
     sender: &EventSender,
     mut repo: Repo,
     cwd: Option<PathBuf>,
+    agents: Option<Arc<AgentCache>>,
 ) {
     let git = Arc::clone(git);
     let tmux = Arc::clone(tmux);
@@ -218,7 +538,7 @@ pub(super) fn spawn_branch_loading<T: TmuxProvider + ?Sized + 'static>(
         repo.worktrees = git.list_worktrees(&repo.path);
         let local_names = git.list_branches(&repo.path);
         let default_branch = git.default_branch(&repo.path, &local_names);
-        let branches = BranchEntry::build_sorted_with_activity(
+        let mut branches = BranchEntry::build_sorted_with_activity(
             &repo,
             &local_names,
             &active_sessions,
@@ -226,6 +546,21 @@ pub(super) fn spawn_branch_loading<T: TmuxProvider + ?Sized + 'static>(
             &session_activity,
             cwd.as_deref(),
         );
+        branches.extend(BranchEntry::build_detached(
+            &repo,
+            &active_sessions,
+            &session_activity,
+        ));
+        if let Some(cache) = agents {
+            for branch in branches.iter_mut().filter(|branch| branch.has_session) {
+                if let Some(path) = &branch.worktree_path {
+                    let session = repo.tmux_session_name(path);
+                    branch.agent = cache
+                        .session_agent_pane(&*tmux, &session)
+                        .map(|(_, agent)| agent);
+                }
+            }
+        }
         sender.send(AppEvent::BranchesLoaded {
             branches,
             worktrees: repo.worktrees,
@@ -235,6 +570,39 @@ pub(super) fn spawn_branch_loading<T: TmuxProvider + ?Sized + 'static>(
     });
 }
 
+/// List a repo's tags for the tag picker, noting the worktrees already detached at each.
+pub(super) fn spawn_tag_loading(
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+    repo_path: PathBuf,
+) {
+    let git = Arc::clone(git);
+    let sender = sender.clone();
+    thread::spawn(move || {
+        if sender.cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        let detached: Vec<(String, PathBuf)> = git
+            .list_worktrees(&repo_path)
+            .into_iter()
+            .filter(|wt| !wt.is_main && wt.branch.is_none())
+            .filter_map(|wt| Some((git.head_commit(&wt.path).ok()?, wt.path)))
+            .collect();
+        let tags = git
+            .list_tags(&repo_path)
+            .into_iter()
+            .map(|tag| TagEntry {
+                worktree_path: detached
+                    .iter()
+                    .find(|(commit, _)| *commit == tag.commit)
+                    .map(|(_, path)| path.clone()),
+                tag,
+            })
+            .collect();
+        sender.send(AppEvent::TagsLoaded { repo_path, tags });
+    });
+}
+
 pub(super) fn spawn_remote_branch_loading(
     git: &Arc<dyn GitProvider>,
     sender: &EventSender,
@@ -247,19 +615,35 @@ pub(super) fn spawn_remote_branch_loading(
         if sender.cancel.load(Ordering::Relaxed) {
             return;
         }
-        let remotes = git.list_remotes(&repo_path);
-        let mut branches = Vec::new();
-        for remote in &remotes {
-            let remote_names = git.list_remote_branches_for_remote(&repo_path, remote);
-            branches.extend(BranchEntry::build_remote(
-                remote,
-                &remote_names,
-                &local_names,
-            ));
+        for remote in git.list_remotes(&repo_path) {
+            let remote_names = git.list_remote_branches_for_remote(&repo_path, &remote);
+            let branches = BranchEntry::build_remote(&remote, &remote_names, &local_names);
+            for page in branches.chunks(REMOTE_BRANCH_PAGE_SIZE) {
+                if sender.cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                sender.send(AppEvent::RemoteBranchesLoaded {
+                    repo_path: repo_path.clone(),
+                    branches: page.to_vec(),
+                });
+            }
         }
-        if !branches.is_empty() {
-            sender.send(AppEvent::RemoteBranchesLoaded { branches });
+    });
+}
+
+pub(super) fn spawn_commit_loading(
+    git: &Arc<dyn GitProvider>,
+    sender: &EventSender,
+    repo_path: PathBuf,
+) {
+    let git = Arc::clone(git);
+    let sender = sender.clone();
+    thread::spawn(move || {
+        if sender.cancel.load(Ordering::Relaxed) {
+            return;
         }
+        let commits = git.last_commits(&repo_path);
+        sender.send(AppEvent::BranchCommitsLoaded { repo_path, commits });
     });
 }
 
@@ -268,6 +652,8 @@ pub(super) fn spawn_git_fetch(
     sender: &EventSender,
     repo_path: PathBuf,
     local_names: Vec<String>,
+    fetch: FetchConfig,
+    lock: Option<InstanceLock>,
 ) {
     let git = Arc::clone(git);
     let sender = sender.clone();
@@ -275,7 +661,11 @@ pub(super) fn spawn_git_fetch(
         if sender.cancel.load(Ordering::Relaxed) {
             return;
         }
-        let remotes = git.list_remotes(&repo_path);
+        let remotes: Vec<String> = git
+            .list_remotes(&repo_path)
+            .into_iter()
+            .filter(|remote| fetch.includes_remote(remote))
+            .collect();
         if remotes.is_empty() {
             sender.send(AppEvent::GitFetchCompleted {
                 branches: vec![],
@@ -287,6 +677,8 @@ pub(super) fn spawn_git_fetch(
 
         let remaining = Arc::new(std::sync::atomic::AtomicUsize::new(remotes.len()));
         let local_names = Arc::new(local_names);
+        // Released once the last remote's fetch is done
+        let lock = Arc::new(lock);
 
         let pool = match ThreadPoolBuilder::new()
             .num_threads(FETCH_POOL_SIZE)
@@ -294,7 +686,7 @@ pub(super) fn spawn_git_fetch(
         {
             Ok(pool) => pool,
             Err(e) => {
-                log::warn!("failed to build fetch thread pool: {e}");
+                tracing::warn!("failed to build fetch thread pool: {e}");
                 sender.send(AppEvent::GitFetchCompleted {
                     branches: vec![],
                     repo_path,
@@ -310,7 +702,9 @@ pub(super) fn spawn_git_fetch(
             let repo_path = repo_path.clone();
             let remaining = Arc::clone(&remaining);
             let local_names = Arc::clone(&local_names);
+            let lock = Arc::clone(&lock);
             pool.spawn(move || {
+                let _lock = lock;
                 if sender.cancel.load(Ordering::Relaxed) {
                     let old = remaining.fetch_sub(1, Ordering::AcqRel);
                     sender.send(AppEvent::GitFetchCompleted {
@@ -320,7 +714,24 @@ pub(super) fn spawn_git_fetch(
                     });
                     return;
                 }
-                let branches = match git.fetch_remote(&repo_path, &remote) {
+                let progress_sender = sender.clone();
+                let progress_repo = repo_path.clone();
+                let quit = Arc::clone(&sender.cancel);
+                let fetched = with_cancel(
+                    move || quit.load(Ordering::Relaxed),
+                    || {
+                        with_progress(
+                            move |progress| {
+                                progress_sender.send(AppEvent::FetchProgress {
+                                    repo_path: progress_repo.clone(),
+                                    progress: progress.clone(),
+                                });
+                            },
+                            || git.fetch_remote(&repo_path, &remote, false),
+                        )
+                    },
+                );
+                let branches = match fetched {
                     Ok(()) => {
                         if sender.cancel.load(Ordering::Relaxed) {
                             let old = remaining.fetch_sub(1, Ordering::AcqRel);
@@ -335,7 +746,7 @@ pub(super) fn spawn_git_fetch(
                         BranchEntry::build_remote(&remote, &remote_names, &local_names)
                     }
                     Err(e) => {
-                        log::warn!("git fetch failed for remote {remote}: {e}");
+                        tracing::warn!("git fetch failed for remote {remote}: {e}");
                         vec![]
                     }
                 };
@@ -349,27 +760,3 @@ pub(super) fn spawn_git_fetch(
         }
     });
 }
-
-pub(super) fn spawn_tracking_worktree_creation(
-    git: &Arc<dyn GitProvider>,
-    sender: &EventSender,
-    repo_path: PathBuf,
-    branch: String,
-    wt_path: PathBuf,
-    session_name: String,
-) {
-    let git = Arc::clone(git);
-    let sender = sender.clone();
-    thread::spawn(move || {
-        if sender.cancel.load(Ordering::Relaxed) {
-            return;
-        }
-        match git.create_tracking_branch_and_worktree(&repo_path, &branch, &wt_path) {
-            Ok(()) => sender.send(AppEvent::WorktreeCreated {
-                path: wt_path,
-                session_name,
-            }),
-            Err(e) => sender.send(AppEvent::GitError(format!("{e}"))),
-        }
-    });
-}
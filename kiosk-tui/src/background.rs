@@ -0,0 +1,167 @@
+//! Working out whether the terminal has a light or dark background, so the theme's `light`
+//! or `dark` variant can be picked.
+
+use kiosk_core::config::Background;
+
+/// How long to wait for the terminal to answer before giving up on it.
+#[cfg(unix)]
+const QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// The terminal's background: asked for with OSC 11, or else read from `COLORFGBG`, or else
+/// assumed to be dark. This reads from the terminal, so it mustn't run while the TUI is
+/// reading input.
+pub(crate) fn detect() -> Background {
+    query_terminal()
+        .and_then(|reply| parse_osc11_reply(&reply))
+        .map(background_for_rgb)
+        .or_else(|| {
+            std::env::var("COLORFGBG")
+                .ok()
+                .and_then(|value| parse_colorfgbg(&value))
+        })
+        .unwrap_or_default()
+}
+
+/// Ask the terminal for its background color, followed by a primary device attributes
+/// request. Every terminal answers the latter, so its reply marks the end of the answer
+/// without waiting out the timeout on terminals that ignore OSC 11.
+#[cfg(unix)]
+fn query_terminal() -> Option<String> {
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled};
+    use std::io::Write;
+
+    let mut tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let was_raw = is_raw_mode_enabled().ok()?;
+    if !was_raw {
+        enable_raw_mode().ok()?;
+    }
+    let reply = tty
+        .write_all(b"\x1b]11;?\x1b\\\x1b[c")
+        .and_then(|()| tty.flush())
+        .ok()
+        .and_then(|()| read_reply(&mut tty));
+    if !was_raw {
+        let _ = disable_raw_mode();
+    }
+    reply
+}
+
+#[cfg(not(unix))]
+fn query_terminal() -> Option<String> {
+    None
+}
+
+/// Read from the terminal until the device attributes reply (`ESC [ ? ... c`) arrives.
+#[cfg(unix)]
+fn read_reply(tty: &mut std::fs::File) -> Option<String> {
+    use rustix::event::{PollFd, PollFlags, Timespec, poll};
+    use std::io::Read;
+    use std::time::Instant;
+
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut reply = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let remaining = deadline.checked_duration_since(Instant::now())?;
+        let timeout = Timespec::try_from(remaining).ok()?;
+        let mut fds = [PollFd::new(&*tty, PollFlags::IN)];
+        if poll(&mut fds, Some(&timeout)).ok()? == 0 {
+            return None;
+        }
+        let read = tty.read(&mut buf).ok()?;
+        if read == 0 {
+            return None;
+        }
+        reply.extend_from_slice(&buf[..read]);
+        let text = String::from_utf8_lossy(&reply);
+        if let Some(start) = text.rfind("\x1b[?")
+            && text[start..].contains('c')
+        {
+            return Some(text.into_owned());
+        }
+    }
+}
+
+/// The color in an OSC 11 reply such as `ESC ] 11 ; rgb:ffff/ffff/ffff BEL`, with each
+/// component scaled to 0.0-1.0.
+fn parse_osc11_reply(reply: &str) -> Option<(f64, f64, f64)> {
+    let start = reply.find("]11;rgb:")? + "]11;rgb:".len();
+    let rest = &reply[start..];
+    let end = rest.find(['\x07', '\x1b']).unwrap_or(rest.len());
+    let mut components = rest[..end].split('/').map(|hex| {
+        let digits = u32::try_from(hex.len())
+            .ok()
+            .filter(|n| (1..=4).contains(n))?;
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        Some(f64::from(value) / f64::from(16u32.pow(digits) - 1))
+    });
+    let rgb = (
+        components.next()??,
+        components.next()??,
+        components.next()??,
+    );
+    components.next().is_none().then_some(rgb)
+}
+
+fn background_for_rgb((r, g, b): (f64, f64, f64)) -> Background {
+    let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    if luminance > 0.5 {
+        Background::Light
+    } else {
+        Background::Dark
+    }
+}
+
+/// `COLORFGBG` is `fg;bg` (or `fg;default;bg`), as ANSI color numbers. The background is light
+/// for white (7) and the bright colors other than bright black (8).
+fn parse_colorfgbg(value: &str) -> Option<Background> {
+    let bg: u8 = value.rsplit(';').next()?.parse().ok()?;
+    Some(if bg == 7 || (9..=15).contains(&bg) {
+        Background::Light
+    } else {
+        Background::Dark
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_osc11_reply() {
+        assert_eq!(
+            parse_osc11_reply("\x1b]11;rgb:ffff/ffff/ffff\x07\x1b[?62;22c"),
+            Some((1.0, 1.0, 1.0))
+        );
+        assert_eq!(
+            parse_osc11_reply("\x1b]11;rgb:00/80/ff\x1b\\\x1b[?1;2c"),
+            Some((0.0, 128.0 / 255.0, 1.0))
+        );
+        // Terminals that don't support OSC 11 only answer the device attributes request
+        assert_eq!(parse_osc11_reply("\x1b[?1;2c"), None);
+        assert_eq!(parse_osc11_reply("\x1b]11;rgb:ffff/ffff\x07"), None);
+        assert_eq!(parse_osc11_reply("\x1b]11;rgb:fffff/0/0\x07"), None);
+    }
+
+    #[test]
+    fn test_background_for_rgb() {
+        assert_eq!(background_for_rgb((1.0, 1.0, 1.0)), Background::Light);
+        assert_eq!(background_for_rgb((0.99, 0.96, 0.89)), Background::Light);
+        assert_eq!(background_for_rgb((0.12, 0.12, 0.18)), Background::Dark);
+        // Saturated blue is dark despite a maxed-out channel
+        assert_eq!(background_for_rgb((0.0, 0.0, 1.0)), Background::Dark);
+    }
+
+    #[test]
+    fn test_parse_colorfgbg() {
+        assert_eq!(parse_colorfgbg("15;0"), Some(Background::Dark));
+        assert_eq!(parse_colorfgbg("0;15"), Some(Background::Light));
+        assert_eq!(parse_colorfgbg("0;default;7"), Some(Background::Light));
+        assert_eq!(parse_colorfgbg("7;8"), Some(Background::Dark));
+        assert_eq!(parse_colorfgbg("default"), None);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::theme::Theme;
+use kiosk_core::{branch_name, state::AppState};
+use ratatui::{Frame, widgets::Clear};
+
+/// Search-bar-style input for the current `branch.name_template` field, centred over the
+/// branch picker. The title shows the name so far, and turns to the error colour with the
+/// reason while git wouldn't accept it.
+pub fn draw(f: &mut Frame, state: &AppState, theme: &Theme) {
+    let Some(fields) = &state.branch_name_fields else {
+        return;
+    };
+    let Some(field) = fields.current() else {
+        return;
+    };
+
+    let area = super::centered_fixed_rect(super::dialog_width(f.area().width), 3, f.area());
+    f.render_widget(Clear, area);
+
+    let name = fields.name();
+    let (title, border_color) = match branch_name::validate(&name) {
+        Ok(()) => (format!("{{{field}}} for \"{name}\""), theme.tertiary),
+        Err(problem) => (
+            format!("{{{field}}} for \"{name}\": {problem}"),
+            theme.error,
+        ),
+    };
+    let placeholder = format!("Type the {field}");
+    super::search_bar::draw(
+        f,
+        area,
+        &super::search_bar::SearchBarStyle {
+            title: &title,
+            placeholder: &placeholder,
+            border_color,
+            muted_color: theme.muted,
+        },
+        &fields.input.text,
+        fields.input.cursor,
+    );
+}
@@ -1,20 +1,40 @@
 use crate::theme::Theme;
-use kiosk_core::config::KeysConfig;
-use kiosk_core::state::AppState;
+use kiosk_core::config::{BranchColumn, KeysConfig};
+use kiosk_core::git::{CommitInfo, Repo};
+use kiosk_core::state::{AppState, BranchEntry, session_activity_history};
+use kiosk_core::{branch_name, ticket};
 use ratatui::{
     Frame,
     layout::{Constraint, Layout, Rect},
-    style::{Modifier, Style},
+    style::Modifier,
     text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, ListState},
 };
+use std::ops::Range;
+use unicode_width::UnicodeWidthStr;
 
-#[allow(clippy::too_many_lines)]
-pub fn draw(f: &mut Frame, area: Rect, state: &AppState, theme: &Theme, _keys: &KeysConfig) {
-    let repo_name = state
-        .selected_repo_idx
-        .map_or("??", |i| state.repos[i].name.as_str());
-    let selected_repo_path = state.selected_repo_idx.map(|i| state.repos[i].path.clone());
+/// Branch names are padded to line up the other columns, but no wider than this.
+const MAX_LABEL_WIDTH: usize = 40;
+/// Longer authors are truncated.
+const MAX_AUTHOR_WIDTH: usize = 16;
+/// Longer subjects are truncated when another column follows them.
+const MAX_SUBJECT_WIDTH: usize = 60;
+/// Longer branch notes are truncated in the label.
+const MAX_NOTE_WIDTH: usize = 30;
+/// Space between columns.
+const COLUMN_GAP: &str = "  ";
+
+/// Draw the branch picker, with session activity aged as of `now` (Unix seconds).
+pub fn draw(
+    f: &mut Frame,
+    area: Rect,
+    state: &AppState,
+    theme: &Theme,
+    _keys: &KeysConfig,
+    now: u64,
+) {
+    let selected_repo = state.selected_repo_idx.map(|i| &state.repos[i]);
+    let repo_name = selected_repo.map_or("??", |repo| repo.name.as_str());
 
     let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(area);
 
@@ -33,99 +53,572 @@ pub fn draw(f: &mut Frame, area: Rect, state: &AppState, theme: &Theme, _keys: &
         state.branch_list.input.cursor,
     );
 
-    // Branch list
-    let mut items: Vec<ListItem> = state
-        .branch_list
-        .filtered
+    // Branch list. Only the rows on screen are built, which keeps drawing quick in repos
+    // with thousands of (remote) branches.
+    let page_rows = usize::from(chunks[1].height.saturating_sub(2)).max(1);
+    let visible = visible_rows(
+        state.branch_list.scroll_offset,
+        state.branch_list.selected,
+        page_rows,
+        state.branch_list.filtered.len(),
+    );
+    let branches: Vec<&BranchEntry> = state.branch_list.filtered[visible.clone()]
         .iter()
-        .map(|(idx, _)| {
-            let branch = &state.branches[*idx];
-
-            if let Some(remote) = &branch.remote {
-                // Remote branches rendered with muted style
-                let mut spans = vec![Span::styled(&branch.name, Style::default().fg(theme.muted))];
-                spans.push(Span::styled(
-                    format!(" ({remote})"),
-                    Style::default()
-                        .fg(theme.muted)
-                        .add_modifier(Modifier::ITALIC),
-                ));
-                return ListItem::new(Line::from(spans));
-            }
-
-            let mut spans = vec![Span::raw(&branch.name)];
-            let is_deleting = selected_repo_path
-                .as_ref()
-                .is_some_and(|repo_path| state.is_branch_pending_delete(repo_path, &branch.name));
-
-            if is_deleting {
-                spans.push(Span::styled(
-                    " (deleting...)",
-                    Style::default().fg(theme.accent),
-                ));
-            } else if branch.has_session {
-                spans.push(Span::styled(
-                    " (session)",
-                    Style::default().fg(theme.success),
-                ));
-            } else if branch.worktree_path.is_some() {
-                spans.push(Span::styled(
-                    " (worktree)",
-                    Style::default().fg(theme.warning),
-                ));
-            }
-            if branch.is_current {
-                spans.push(Span::styled(" *", Style::default().fg(theme.accent)));
-            }
-
-            ListItem::new(Line::from(spans))
-        })
+        .map(|(idx, _)| &state.branches[*idx])
+        .collect();
+    let cells = CellContext {
+        state,
+        selected_repo,
+        theme,
+        now,
+    };
+    let rows = branches
+        .iter()
+        .map(|branch| cells.row(branch, &state.branch_columns))
+        .collect();
+    let caps: Vec<usize> = state.branch_columns.iter().map(|c| max_width(*c)).collect();
+    let mut items: Vec<ListItem> = layout_columns(rows, &caps)
+        .into_iter()
+        .zip(visible.clone())
+        .map(|(line, index)| theme.stripe(ListItem::new(line), index))
         .collect();
 
     // If search doesn't match anything, show "create new branch" option
     if state.loading_branches && state.branch_list.filtered.is_empty() {
         items.push(ListItem::new(Line::from(vec![Span::styled(
             "Loading branches...",
-            Style::default().fg(theme.muted),
+            theme.styles.muted,
         )])));
     } else if state.branch_list.filtered.is_empty() && !state.branch_list.input.text.is_empty() {
-        items.push(ListItem::new(Line::from(vec![
-            Span::styled("+ Create branch ", Style::default().fg(theme.success)),
-            Span::styled(
-                format!("\"{}\"", state.branch_list.input.text),
-                Style::default()
-                    .fg(theme.success)
-                    .add_modifier(Modifier::BOLD),
-            ),
-            Span::styled(" (Enter to pick base)", Style::default().fg(theme.muted)),
-        ])));
+        items.push(ListItem::new(create_branch_line(state, theme)));
     }
 
     let count = state.branch_list.filtered.len();
     let loading_suffix = if state.loading_branches {
-        " | loading..."
+        " | loading...".to_string()
     } else if state.fetching_remotes {
-        " | fetching..."
+        state.fetch_progress.as_ref().map_or_else(
+            || " | fetching...".to_string(),
+            |progress| format!(" | fetching: {progress}"),
+        )
     } else {
-        ""
+        String::new()
     };
     let list = List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .title(format!(" {count} branches{loading_suffix} "))
-                .border_style(Style::default().fg(theme.border)),
+                .border_style(theme.styles.border),
         )
-        .highlight_style(
-            Style::default()
-                .bg(theme.secondary)
-                .fg(theme.highlight_fg)
-                .add_modifier(Modifier::BOLD),
-        )
-        .highlight_symbol("▸ ");
+        .highlight_style(theme.selection_style(theme.secondary))
+        .highlight_symbol(theme.glyphs.selected);
 
     let mut list_state = ListState::default();
-    list_state.select(state.branch_list.selected);
-    *list_state.offset_mut() = state.branch_list.scroll_offset;
+    list_state.select(
+        state
+            .branch_list
+            .selected
+            .map(|selected| selected - visible.start),
+    );
     f.render_stateful_widget(list, chunks[1], &mut list_state);
 }
+
+/// The row offering to create a branch from the search text, named by `branch.name_template`
+/// if one is set, or after the ticket when the text is a ticket ID. Names git would refuse
+/// are shown in the error style with the reason.
+fn create_branch_line<'a>(state: &AppState, theme: &Theme) -> Line<'a> {
+    let text = &state.branch_list.input.text;
+    if state.ticket.provider.is_some() && ticket::is_ticket_id(text.trim()) {
+        return Line::from(vec![
+            Span::styled("+ Create branch for ", theme.styles.success),
+            Span::styled(
+                text.trim().to_string(),
+                theme.styles.success.add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(" (Enter to fetch the ticket)", theme.styles.muted),
+        ]);
+    }
+    let (name, has_fields) = state.branch_name_template.as_deref().map_or_else(
+        || (text.clone(), false),
+        |template| {
+            (
+                branch_name::from_template(template, text, &[]),
+                !branch_name::template_fields(template).is_empty(),
+            )
+        },
+    );
+    let problem = if has_fields {
+        None
+    } else {
+        branch_name::validate(&name).err()
+    };
+    let style = if problem.is_some() {
+        theme.styles.error
+    } else {
+        theme.styles.success
+    };
+    let mut spans = vec![
+        Span::styled("+ Create branch ", style),
+        Span::styled(format!("\"{name}\""), style.add_modifier(Modifier::BOLD)),
+    ];
+    spans.push(match problem {
+        Some(problem) => Span::styled(format!(" ({problem})"), theme.styles.error),
+        None if has_fields => Span::styled(" (Enter to fill in the name)", theme.styles.muted),
+        None => Span::styled(" (Enter to pick base)", theme.styles.muted),
+    });
+    Line::from(spans)
+}
+
+/// Range of filtered entries on screen: `page_rows` from the list's scroll offset, moved
+/// if need be to keep the selection in view.
+fn visible_rows(
+    scroll_offset: usize,
+    selected: Option<usize>,
+    page_rows: usize,
+    len: usize,
+) -> Range<usize> {
+    let mut start = scroll_offset.min(len.saturating_sub(1));
+    if let Some(selected) = selected {
+        start = start
+            .min(selected)
+            .max((selected + 1).saturating_sub(page_rows));
+    }
+    start..(start + page_rows).min(len)
+}
+
+/// What the cells of a branch row are drawn with.
+struct CellContext<'a> {
+    state: &'a AppState,
+    selected_repo: Option<&'a Repo>,
+    theme: &'a Theme,
+    now: u64,
+}
+
+impl CellContext<'_> {
+    /// One cell per column for `branch`, empty where the branch has nothing to show.
+    fn row<'b>(&self, branch: &'b BranchEntry, columns: &[BranchColumn]) -> Vec<Vec<Span<'b>>> {
+        columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| self.cell(branch, *column, i + 1 == columns.len()))
+            .collect()
+    }
+
+    fn cell<'b>(
+        &self,
+        branch: &'b BranchEntry,
+        column: BranchColumn,
+        is_last: bool,
+    ) -> Vec<Span<'b>> {
+        let theme = self.theme;
+        match column {
+            BranchColumn::Name => name_cell(branch, theme),
+            BranchColumn::Session => self.session_cell(branch).into_iter().collect(),
+            BranchColumn::Activity => self.activity_cell(branch),
+            BranchColumn::Agent => branch
+                .agent
+                .iter()
+                .map(|agent| Span::styled(agent.as_str(), theme.styles.secondary))
+                .collect(),
+            BranchColumn::AheadBehind => branch
+                .last_commit
+                .as_ref()
+                .and_then(|commit| commit.ahead_behind)
+                .map(|(ahead, behind)| ahead_behind(ahead, behind, theme))
+                .filter(|text| !text.is_empty())
+                .map(|text| Span::styled(text, theme.styles.muted))
+                .into_iter()
+                .collect(),
+            BranchColumn::LastCommitAge
+            | BranchColumn::LastCommitAuthor
+            | BranchColumn::LastCommitSubject => branch
+                .last_commit
+                .as_ref()
+                .map(|commit| {
+                    Span::styled(
+                        commit_cell(commit, column, is_last, self.now),
+                        theme.styles.muted,
+                    )
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// The age of the branch session's last activity and its heat.
+    fn activity_cell(&self, branch: &BranchEntry) -> Vec<Span<'static>> {
+        let Some(activity) = branch.session_activity_ts else {
+            return Vec::new();
+        };
+        let mut spans = vec![super::activity_age(activity, self.now, self.theme)];
+        if let (Some(repo), Some(worktree_path)) = (self.selected_repo, &branch.worktree_path) {
+            let history = session_activity_history(
+                &repo.tmux_session_name(worktree_path),
+                &self.state.session_activity,
+                &self.state.activity_history,
+            );
+            spans.push(Span::raw(" "));
+            spans.push(super::activity_heat(&history, self.now, self.theme));
+        }
+        spans
+    }
+
+    fn session_cell(&self, branch: &BranchEntry) -> Option<Span<'static>> {
+        let theme = self.theme;
+        if branch.remote.is_some() {
+            return None;
+        }
+        let is_deleting = self.selected_repo.is_some_and(|repo| {
+            self.state
+                .is_branch_pending_delete(&repo.path, &branch.name)
+        });
+        if is_deleting {
+            Some(Span::styled("(deleting...)", theme.styles.accent))
+        } else if branch.has_session {
+            Some(Span::styled(
+                theme.glyphs.session.trim_start(),
+                theme.styles.success,
+            ))
+        } else if branch.worktree_path.is_some() {
+            Some(Span::styled(
+                theme.glyphs.worktree.trim_start(),
+                theme.styles.warning,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// The branch's name, with its remote, current marker, labels and note.
+fn name_cell<'a>(branch: &'a BranchEntry, theme: &Theme) -> Vec<Span<'a>> {
+    if let Some(remote) = &branch.remote {
+        // Remote branches rendered with muted style
+        return vec![
+            Span::styled(theme.glyphs.branch, theme.styles.muted),
+            Span::styled(&branch.name, theme.styles.muted),
+            Span::styled(
+                format!(" ({remote})"),
+                theme.styles.muted.add_modifier(Modifier::ITALIC),
+            ),
+        ];
+    }
+
+    if branch.detached {
+        return vec![
+            Span::raw(&branch.name),
+            Span::styled(theme.glyphs.detached, theme.styles.muted),
+        ];
+    }
+
+    let mut spans = vec![Span::raw(theme.glyphs.branch), Span::raw(&branch.name)];
+    if branch.is_current {
+        spans.push(Span::styled(theme.glyphs.current, theme.styles.accent));
+    }
+    for label in &branch.labels {
+        spans.push(Span::styled(format!(" #{label}"), theme.styles.tertiary));
+    }
+    if let Some(note) = &branch.note {
+        spans.push(Span::styled(
+            format!(" — {}", truncate(note, MAX_NOTE_WIDTH)),
+            theme.styles.tertiary.add_modifier(Modifier::ITALIC),
+        ));
+    }
+    spans
+}
+
+/// Commits ahead of and behind the upstream, e.g. `↑2 ↓1`; empty when in sync.
+fn ahead_behind(ahead: u32, behind: u32, theme: &Theme) -> String {
+    let mut parts = Vec::new();
+    if ahead > 0 {
+        parts.push(format!("{}{ahead}", theme.glyphs.ahead));
+    }
+    if behind > 0 {
+        parts.push(format!("{}{behind}", theme.glyphs.behind));
+    }
+    parts.join(" ")
+}
+
+fn commit_cell(commit: &CommitInfo, column: BranchColumn, is_last: bool, now: u64) -> String {
+    match column {
+        BranchColumn::LastCommitAge => super::format_age(now.saturating_sub(commit.timestamp)),
+        BranchColumn::LastCommitAuthor => truncate(&commit.author, MAX_AUTHOR_WIDTH),
+        // The list clips a trailing subject to the terminal width
+        BranchColumn::LastCommitSubject if is_last => commit.subject.clone(),
+        BranchColumn::LastCommitSubject => truncate(&commit.subject, MAX_SUBJECT_WIDTH),
+        _ => String::new(),
+    }
+}
+
+/// The most a column is padded to; longer cells push the rest of their row along.
+fn max_width(column: BranchColumn) -> usize {
+    match column {
+        BranchColumn::Name => MAX_LABEL_WIDTH,
+        _ => usize::MAX,
+    }
+}
+
+/// Line up rows of cells in columns `COLUMN_GAP` apart, each as wide as its widest cell up to
+/// its entry in `caps`. Columns that are empty in every row are left out, and each row ends
+/// after its last non-empty cell.
+fn layout_columns<'a>(rows: Vec<Vec<Vec<Span<'a>>>>, caps: &[usize]) -> Vec<Line<'a>> {
+    let width = |cell: &[Span]| cell.iter().map(Span::width).sum::<usize>();
+    let widths: Vec<usize> = caps
+        .iter()
+        .enumerate()
+        .map(|(i, cap)| {
+            rows.iter()
+                .map(|row| width(&row[i]))
+                .max()
+                .unwrap_or(0)
+                .min(*cap)
+        })
+        .collect();
+
+    rows.into_iter()
+        .map(|row| {
+            let cells: Vec<(usize, Vec<Span>)> = widths
+                .iter()
+                .copied()
+                .zip(row)
+                .filter(|(column_width, _)| *column_width > 0)
+                .collect();
+            let Some(last) = cells.iter().rposition(|(_, cell)| width(cell) > 0) else {
+                return Line::default();
+            };
+            let mut spans = Vec::new();
+            for (i, (column_width, cell)) in cells.into_iter().take(last + 1).enumerate() {
+                if i > 0 {
+                    spans.push(Span::raw(COLUMN_GAP));
+                }
+                let padding = column_width.saturating_sub(width(&cell));
+                spans.extend(cell);
+                if i < last && padding > 0 {
+                    spans.push(Span::raw(" ".repeat(padding)));
+                }
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Cut `text` to at most `max_width` columns, ending in "…" when anything was cut.
+fn truncate(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    for c in text.chars() {
+        if out.width() + c.to_string().width() + 1 > max_width {
+            break;
+        }
+        out.push(c);
+    }
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(subject: &str, author: &str, timestamp: u64) -> CommitInfo {
+        CommitInfo {
+            subject: subject.to_string(),
+            author: author.to_string(),
+            timestamp,
+            ahead_behind: None,
+        }
+    }
+
+    fn render(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(ToString::to_string).collect()
+    }
+
+    fn cells(texts: &[&'static str]) -> Vec<Vec<Span<'static>>> {
+        texts
+            .iter()
+            .map(|text| {
+                if text.is_empty() {
+                    vec![]
+                } else {
+                    vec![Span::raw(*text)]
+                }
+            })
+            .collect()
+    }
+
+    fn commit_row(commit: Option<&CommitInfo>, columns: &[BranchColumn], now: u64) -> Vec<String> {
+        columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                commit.map_or_else(String::new, |commit| {
+                    commit_cell(commit, *column, i + 1 == columns.len(), now)
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn columns_line_up_after_the_names() {
+        let first = commit("Add the thing", "Ada", 20_000);
+        let second = commit("Fix it", "Grace Hopper", 20_000 - 3 * 3600);
+        let columns = [
+            BranchColumn::LastCommitAge,
+            BranchColumn::LastCommitAuthor,
+            BranchColumn::LastCommitSubject,
+        ];
+        let rows = [
+            ("main *", Some(&first)),
+            ("feature-x", Some(&second)),
+            ("new", None),
+        ]
+        .into_iter()
+        .map(|(name, commit)| {
+            let mut row = vec![vec![Span::raw(name)]];
+            row.extend(
+                commit_row(commit, &columns, 20_000)
+                    .into_iter()
+                    .map(|cell| {
+                        if cell.is_empty() {
+                            vec![]
+                        } else {
+                            vec![Span::raw(cell)]
+                        }
+                    }),
+            );
+            row
+        })
+        .collect();
+        let lines = layout_columns(rows, &[MAX_LABEL_WIDTH, usize::MAX, usize::MAX, usize::MAX]);
+        assert_eq!(
+            render(&lines),
+            vec![
+                "main *     now  Ada           Add the thing",
+                "feature-x  3h   Grace Hopper  Fix it",
+                "new",
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_columns_are_left_out() {
+        let lines = layout_columns(
+            vec![
+                cells(&["main", "", "↑2", "(session)"]),
+                cells(&["feat", "", "", "(worktree)"]),
+                cells(&["old", "", "", ""]),
+            ],
+            &[MAX_LABEL_WIDTH, usize::MAX, usize::MAX, usize::MAX],
+        );
+        assert_eq!(
+            render(&lines),
+            vec!["main  ↑2  (session)", "feat      (worktree)", "old"]
+        );
+    }
+
+    #[test]
+    fn long_names_push_their_row_along() {
+        let long = "x".repeat(MAX_LABEL_WIDTH + 2);
+        let lines = layout_columns(
+            vec![
+                vec![vec![Span::raw(long.clone())], vec![Span::raw("now")]],
+                cells(&["main", "3h"]),
+            ],
+            &[MAX_LABEL_WIDTH, usize::MAX],
+        );
+        assert_eq!(
+            render(&lines),
+            vec![
+                format!("{long}  now"),
+                format!("main{}  3h", " ".repeat(MAX_LABEL_WIDTH - 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_subjects_are_not_truncated() {
+        let info = commit(&"s".repeat(MAX_SUBJECT_WIDTH + 5), "Ada", 0);
+        let columns = [
+            BranchColumn::LastCommitSubject,
+            BranchColumn::LastCommitAuthor,
+        ];
+        assert_eq!(
+            commit_row(Some(&info), &columns, 0)[0].width(),
+            MAX_SUBJECT_WIDTH
+        );
+        assert_eq!(
+            commit_row(Some(&info), &columns[..1], 0)[0].width(),
+            MAX_SUBJECT_WIDTH + 5
+        );
+    }
+
+    #[test]
+    fn ahead_behind_counts() {
+        let theme = Theme::from_config(&kiosk_core::config::ThemeConfig::default())
+            .with_icons(kiosk_core::config::Icons::Unicode);
+        assert_eq!(ahead_behind(2, 1, &theme), "↑2 ↓1");
+        assert_eq!(ahead_behind(0, 3, &theme), "↓3");
+        assert_eq!(ahead_behind(0, 0, &theme), "");
+    }
+
+    #[test]
+    fn long_authors_are_truncated() {
+        assert_eq!(truncate("Ada", 16), "Ada");
+        assert_eq!(truncate("Augusta Ada King-Noel", 16), "Augusta Ada Kin…");
+    }
+
+    #[test]
+    fn visible_rows_follow_the_scroll_offset_and_selection() {
+        assert_eq!(visible_rows(0, Some(0), 10, 12_000), 0..10);
+        assert_eq!(visible_rows(40, Some(45), 10, 12_000), 40..50);
+        assert_eq!(visible_rows(40, Some(60), 10, 12_000), 51..61);
+        assert_eq!(visible_rows(40, Some(5), 10, 12_000), 5..15);
+        assert_eq!(visible_rows(0, None, 10, 3), 0..3);
+        assert_eq!(visible_rows(0, None, 10, 0), 0..0);
+    }
+
+    #[test]
+    fn create_row_checks_the_name_as_you_type() {
+        let theme = Theme::from_config(&kiosk_core::config::ThemeConfig::default());
+        let mut state = AppState::new(vec![], None);
+        let line = |state: &AppState| create_branch_line(state, &theme).to_string();
+
+        state.branch_list.input.text = "feat/ok".to_string();
+        assert_eq!(
+            line(&state),
+            "+ Create branch \"feat/ok\" (Enter to pick base)"
+        );
+
+        state.branch_list.input.text = "add thing".to_string();
+        assert_eq!(
+            line(&state),
+            "+ Create branch \"add thing\" (it can't contain spaces)"
+        );
+        assert_eq!(
+            create_branch_line(&state, &theme).spans[0].style,
+            theme.styles.error
+        );
+
+        state.branch_name_template = Some("wip/{slug}".to_string());
+        assert_eq!(
+            line(&state),
+            "+ Create branch \"wip/add-thing\" (Enter to pick base)"
+        );
+
+        state.branch_name_template = Some("{user}/{slug}".to_string());
+        assert_eq!(
+            line(&state),
+            "+ Create branch \"{user}/add-thing\" (Enter to fill in the name)"
+        );
+
+        state.ticket.provider = Some(kiosk_core::config::TicketProvider::Linear);
+        state.branch_list.input.text = "ABC-123".to_string();
+        assert_eq!(
+            line(&state),
+            "+ Create branch for ABC-123 (Enter to fetch the ticket)"
+        );
+    }
+}
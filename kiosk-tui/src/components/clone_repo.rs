@@ -0,0 +1,18 @@
+use crate::theme::Theme;
+use kiosk_core::state::AppState;
+use ratatui::{Frame, widgets::ListItem};
+
+pub fn draw(f: &mut Frame, state: &AppState, theme: &Theme) {
+    let Some(flow) = &state.clone_dir_selection else {
+        return;
+    };
+
+    super::picker_popup::draw(
+        f,
+        theme,
+        &format!("Clone \"{}\" — pick search dir", flow.name),
+        "Select search dir...",
+        &flow.list,
+        |idx| ListItem::new(flow.dirs[idx].display().to_string()),
+    );
+}
@@ -7,27 +7,19 @@ use kiosk_core::{
 use ratatui::{
     Frame,
     layout::{Alignment, Rect},
-    style::{Modifier, Style},
+    style::Modifier,
     text::{Line, Span},
     widgets::Padding,
 };
 
 fn build_error_dialog<'a>(error: &'a str, dismiss_key: &'a str, theme: &Theme) -> Dialog<'a> {
     let text = Line::from(vec![
-        Span::styled(
-            "Error: ",
-            Style::default()
-                .fg(theme.error)
-                .add_modifier(Modifier::BOLD),
-        ),
+        Span::styled("Error: ", theme.styles.error.add_modifier(Modifier::BOLD)),
         Span::raw(error),
     ]);
 
     let hint = Line::from(vec![
-        Span::styled(
-            dismiss_key,
-            Style::default().fg(theme.hint).add_modifier(Modifier::BOLD),
-        ),
+        Span::styled(dismiss_key, theme.styles.hint.add_modifier(Modifier::BOLD)),
         Span::raw(": close"),
     ]);
 
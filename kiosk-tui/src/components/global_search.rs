@@ -0,0 +1,76 @@
+use crate::theme::Theme;
+use kiosk_core::state::AppState;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState},
+};
+
+pub fn draw(f: &mut Frame, area: Rect, state: &AppState, theme: &Theme) {
+    let Some(search) = &state.global_search else {
+        return;
+    };
+    let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(area);
+
+    // Search bar
+    super::search_bar::draw(
+        f,
+        chunks[0],
+        &super::search_bar::SearchBarStyle {
+            title: "kiosk — jump to branch",
+            placeholder: "Type to search repo/branch...",
+            border_color: theme.accent,
+            muted_color: theme.muted,
+        },
+        &search.list.input.text,
+        search.list.input.cursor,
+    );
+
+    // Worktree list
+    let mut items: Vec<ListItem> = search
+        .list
+        .filtered
+        .iter()
+        .enumerate()
+        .map(|(row, (idx, _))| {
+            let entry = &search.entries[*idx];
+            let mut spans = vec![
+                Span::styled(format!("{}/", entry.repo_name), theme.styles.muted),
+                Span::raw(&entry.branch),
+            ];
+            if entry.has_session {
+                spans.push(Span::styled(theme.glyphs.session, theme.styles.success));
+            }
+            theme.stripe(ListItem::new(Line::from(spans)), row)
+        })
+        .collect();
+
+    if search.entries.is_empty() {
+        let message = if state.loading_repos {
+            "Discovering repos..."
+        } else {
+            "No worktrees found"
+        };
+        items.push(ListItem::new(Line::from(vec![Span::styled(
+            message,
+            theme.styles.muted,
+        )])));
+    }
+
+    let count = search.list.filtered.len();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" {count} worktrees "))
+                .border_style(theme.styles.border),
+        )
+        .highlight_style(theme.selection_style(theme.accent))
+        .highlight_symbol(theme.glyphs.selected);
+
+    let mut list_state = ListState::default();
+    list_state.select(search.list.selected);
+    *list_state.offset_mut() = search.list.scroll_offset;
+    f.render_stateful_widget(list, chunks[1], &mut list_state);
+}
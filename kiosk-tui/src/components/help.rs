@@ -1,4 +1,4 @@
-use kiosk_core::state::{AppState, HelpOverlayState};
+use kiosk_core::state::{AppState, HelpOverlayState, Mode};
 use ratatui::{
     Frame,
     layout::{Constraint, Layout},
@@ -36,11 +36,28 @@ fn compute_help_layout(overlay: &HelpOverlayState) -> Vec<HelpLayoutEntry> {
     entries
 }
 
-/// Help overlay showing keybindings.
+/// Help overlay showing keybindings, or the command palette listing the same rows.
 pub fn draw(f: &mut Frame, state: &AppState, theme: &crate::theme::Theme) {
     let Some(overlay) = state.help_overlay.as_ref() else {
         return;
     };
+    let palette = matches!(state.mode, Mode::CommandPalette { .. });
+    let (search_title, placeholder, list_title) = if palette {
+        (
+            "command palette",
+            "Type to find a command, enter runs it...",
+            format!(
+                " {} commands (enter: run, esc: close) ",
+                overlay.list.filtered.len()
+            ),
+        )
+    } else {
+        (
+            "help - key bindings",
+            "Type to filter by key, command, or description...",
+            format!(" {} bindings (esc: close) ", overlay.list.filtered.len()),
+        )
+    };
 
     let popup_area = super::centered_rect(80, 85, f.area());
     f.render_widget(Clear, popup_area);
@@ -50,8 +67,8 @@ pub fn draw(f: &mut Frame, state: &AppState, theme: &crate::theme::Theme) {
         f,
         chunks[0],
         &super::search_bar::SearchBarStyle {
-            title: "help - key bindings",
-            placeholder: "Type to filter by key, command, or description...",
+            title: search_title,
+            placeholder,
             border_color: theme.tertiary,
             muted_color: theme.muted,
         },
@@ -70,21 +87,15 @@ pub fn draw(f: &mut Frame, state: &AppState, theme: &crate::theme::Theme) {
     // a logical item index. This is because the help list has non-selectable
     // visual rows (section headers, blank separators) that shift the mapping.
     let list_offset = overlay.list.scroll_offset;
-    let title = format!(" {} bindings (esc: close) ", overlay.list.filtered.len());
     let list = List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(title)
-                .border_style(Style::default().fg(theme.tertiary)),
-        )
-        .highlight_style(
-            Style::default()
-                .bg(theme.tertiary)
-                .fg(theme.highlight_fg)
-                .add_modifier(Modifier::BOLD),
+                .title(list_title)
+                .border_style(theme.styles.tertiary),
         )
-        .highlight_symbol("▸ ");
+        .highlight_style(theme.selection_style(theme.tertiary))
+        .highlight_symbol(theme.glyphs.selected);
 
     let mut list_state = ListState::default();
     list_state.select(selected_item);
@@ -1,14 +1,28 @@
-use ratatui::layout::{Constraint, Layout, Rect};
+use crate::theme::Theme;
+use kiosk_core::activity_history::HISTORY_WINDOW_SECS;
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    text::Span,
+};
 
+pub mod branch_name;
 pub mod branch_picker;
+pub mod clone_repo;
 pub mod dialog;
 pub mod error_toast;
+pub mod global_search;
 pub mod help;
 pub mod new_branch;
+pub mod new_repo;
+pub mod note;
 pub mod path_input;
+pub mod picker_popup;
+pub mod preview;
 pub mod repo_list;
 pub mod search_bar;
 pub mod setup;
+pub mod tag_picker;
+pub mod workspace_picker;
 
 /// Helper function to center a rect within another rect
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -41,10 +55,125 @@ pub fn centered_fixed_rect(width: u16, height: u16, r: Rect) -> Rect {
     Rect::new(offset_x, offset_y, clamped_width, clamped_height)
 }
 
+/// Compact age of something `secs` seconds old: "now", "5m", "3h", "2d", "6w" or "1y".
+pub fn format_age(secs: u64) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    const WEEK: u64 = 7 * DAY;
+    const YEAR: u64 = 365 * DAY;
+    match secs {
+        s if s < MINUTE => "now".to_string(),
+        s if s < HOUR => format!("{}m", s / MINUTE),
+        s if s < DAY => format!("{}h", s / HOUR),
+        s if s < 2 * WEEK => format!("{}d", s / DAY),
+        s if s < YEAR => format!("{}w", s / WEEK),
+        s => format!("{}y", s / YEAR),
+    }
+}
+
+/// Sessions active more recently than this show as hot.
+const HOT_ACTIVITY_SECS: u64 = 60 * 60;
+/// Sessions active more recently than this, but not hot, show as warm.
+const WARM_ACTIVITY_SECS: u64 = 24 * 60 * 60;
+
+/// Age of a session's last activity at `activity` (Unix seconds), coloured by how recently
+/// it was touched: the success colour within the hour, the warning colour within the day,
+/// muted after that.
+pub fn activity_age(activity: u64, now: u64, theme: &Theme) -> Span<'static> {
+    let secs = now.saturating_sub(activity);
+    let style = if secs < HOT_ACTIVITY_SECS {
+        theme.styles.success
+    } else if secs < WARM_ACTIVITY_SECS {
+        theme.styles.warning
+    } else {
+        theme.styles.muted
+    };
+    Span::styled(format_age(secs), style)
+}
+
+/// How many spans of time the activity heat splits the history window into.
+const HEAT_BUCKETS: usize = 4;
+
+/// Heat of a session's recent `activity` (Unix seconds, oldest first): one glyph per
+/// stretch of the day before `now`, oldest first, rising with the activity seen in it.
+/// Coloured like the age of the latest activity.
+pub fn activity_heat(activity: &[u64], now: u64, theme: &Theme) -> Span<'static> {
+    let bucket_secs = HISTORY_WINDOW_SECS / HEAT_BUCKETS as u64;
+    let mut counts = [0usize; HEAT_BUCKETS];
+    for &at in activity {
+        if let Ok(bucket) = usize::try_from(now.saturating_sub(at) / bucket_secs)
+            && bucket < HEAT_BUCKETS
+        {
+            counts[HEAT_BUCKETS - 1 - bucket] += 1;
+        }
+    }
+    let levels = &theme.glyphs.heat;
+    let heat: String = counts
+        .iter()
+        .map(|&count| levels[count.min(levels.len() - 1)])
+        .collect();
+    let style = activity.last().map_or(theme.styles.muted, |&latest| {
+        activity_age(latest, now, theme).style
+    });
+    Span::styled(heat, style)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(0), "now");
+        assert_eq!(format_age(59), "now");
+        assert_eq!(format_age(5 * 60), "5m");
+        assert_eq!(format_age(3 * 3600 + 59), "3h");
+        assert_eq!(format_age(13 * 86_400), "13d");
+        assert_eq!(format_age(14 * 86_400), "2w");
+        assert_eq!(format_age(400 * 86_400), "1y");
+    }
+
+    #[test]
+    fn test_activity_age_heats_up_with_recent_activity() {
+        let theme = Theme::from_config(&kiosk_core::config::ThemeConfig::default());
+        let now = 10 * 86_400;
+        let hot = activity_age(now - 2 * 60, now, &theme);
+        assert_eq!(hot.content, "2m");
+        assert_eq!(hot.style, theme.styles.success);
+        let warm = activity_age(now - 3 * 3600, now, &theme);
+        assert_eq!(warm.content, "3h");
+        assert_eq!(warm.style, theme.styles.warning);
+        let cold = activity_age(now - 4 * 86_400, now, &theme);
+        assert_eq!(cold.content, "4d");
+        assert_eq!(cold.style, theme.styles.muted);
+    }
+
+    #[test]
+    fn test_activity_heat_rises_with_activity_in_each_part_of_the_day() {
+        let mut theme = Theme::from_config(&kiosk_core::config::ThemeConfig::default());
+        theme.glyphs = kiosk_core::config::Icons::Ascii.glyphs();
+        let now = 10 * 86_400;
+        let hours = |h: u64| now - h * 3600;
+        let activity = [
+            hours(30),
+            hours(20),
+            hours(13),
+            hours(12),
+            hours(2),
+            hours(1),
+            now,
+        ];
+
+        let heat = activity_heat(&activity, now, &theme);
+        assert_eq!(heat.content, "-=.#");
+        assert_eq!(heat.style, theme.styles.success);
+
+        let quiet = activity_heat(&[hours(20)], now, &theme);
+        assert_eq!(quiet.content, "-...");
+        assert_eq!(quiet.style, theme.styles.warning);
+    }
+
     #[test]
     fn test_dialog_width_normal_terminal() {
         assert_eq!(dialog_width(100), 80);
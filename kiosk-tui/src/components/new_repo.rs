@@ -0,0 +1,18 @@
+use crate::theme::Theme;
+use kiosk_core::state::AppState;
+use ratatui::{Frame, widgets::ListItem};
+
+pub fn draw(f: &mut Frame, state: &AppState, theme: &Theme) {
+    let Some(flow) = &state.new_repo_selection else {
+        return;
+    };
+
+    super::picker_popup::draw(
+        f,
+        theme,
+        &format!("New repo \"{}\" — pick template", flow.name),
+        "Select template...",
+        &flow.list,
+        |idx| ListItem::new(flow.templates[idx].as_str()),
+    );
+}
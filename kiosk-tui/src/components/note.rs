@@ -0,0 +1,27 @@
+use crate::theme::Theme;
+use kiosk_core::state::AppState;
+use ratatui::{Frame, widgets::Clear};
+
+/// Search-bar-style input for a branch's note, centred over the branch picker.
+pub fn draw(f: &mut Frame, state: &AppState, theme: &Theme) {
+    let Some(edit) = &state.note_edit else {
+        return;
+    };
+
+    let area = super::centered_fixed_rect(super::dialog_width(f.area().width), 3, f.area());
+    f.render_widget(Clear, area);
+
+    let title = format!("Note for \"{}\"", edit.branch);
+    super::search_bar::draw(
+        f,
+        area,
+        &super::search_bar::SearchBarStyle {
+            title: &title,
+            placeholder: "What is this branch for? (empty removes the note)",
+            border_color: theme.tertiary,
+            muted_color: theme.muted,
+        },
+        &edit.input.text,
+        edit.input.cursor,
+    );
+}
@@ -0,0 +1,52 @@
+use crate::theme::Theme;
+use kiosk_core::state::SearchableList;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+};
+
+/// A centered popup to pick one of a flow's options: a search bar over the filtered list,
+/// with `label` naming the option at each index.
+pub fn draw<'a>(
+    f: &mut Frame,
+    theme: &Theme,
+    title: &str,
+    placeholder: &str,
+    list: &SearchableList,
+    label: impl Fn(usize) -> ListItem<'a>,
+) {
+    let area = super::centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(area);
+
+    super::search_bar::draw(
+        f,
+        chunks[0],
+        &super::search_bar::SearchBarStyle {
+            title,
+            placeholder,
+            border_color: theme.tertiary,
+            muted_color: theme.muted,
+        },
+        &list.input.text,
+        list.input.cursor,
+    );
+
+    let items: Vec<ListItem> = list.filtered.iter().map(|(idx, _)| label(*idx)).collect();
+
+    let widget = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.styles.tertiary),
+        )
+        .highlight_style(theme.selection_style(theme.tertiary))
+        .highlight_symbol(theme.glyphs.selected);
+
+    let mut list_state = ListState::default();
+    list_state.select(list.selected);
+    *list_state.offset_mut() = list.scroll_offset;
+    f.render_stateful_widget(widget, chunks[1], &mut list_state);
+}
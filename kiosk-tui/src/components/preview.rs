@@ -0,0 +1,256 @@
+use crate::theme::Theme;
+use kiosk_core::{
+    BranchEntry, Repo,
+    state::{AppState, Mode},
+};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::Modifier,
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+/// What the preview pane shows beside the list of a mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewKind {
+    Repo,
+    Branch,
+}
+
+impl PreviewKind {
+    /// The preview for `mode`, or `None` if the mode has no repo or branch list to preview.
+    pub fn for_mode(mode: &Mode) -> Option<Self> {
+        match mode {
+            Mode::RepoSelect
+            | Mode::SelectCloneDir
+            | Mode::NewRepo
+            | Mode::ConfirmResume { .. }
+            | Mode::ConfirmRecover { .. } => Some(Self::Repo),
+            Mode::BranchSelect
+            | Mode::SelectBaseBranch
+            | Mode::ConfirmWorktreeDelete { .. }
+            | Mode::EditNote
+            | Mode::BranchNameFields => Some(Self::Branch),
+            Mode::GlobalSearch
+            | Mode::TagSelect
+            | Mode::WorkspaceSelect
+            | Mode::Setup(_)
+            | Mode::Loading(_)
+            | Mode::Help { .. }
+            | Mode::CommandPalette { .. } => None,
+        }
+    }
+}
+
+/// Details of the selected repo or branch, drawn in `area` beside the list, with ages as of
+/// `now` (Unix seconds).
+pub fn draw(f: &mut Frame, area: Rect, mode: &Mode, state: &AppState, theme: &Theme, now: u64) {
+    let (title, lines) = match PreviewKind::for_mode(mode) {
+        Some(PreviewKind::Repo) => selected_repo(state).map_or_else(
+            || (String::new(), Vec::new()),
+            |repo| (repo.name.clone(), repo_lines(repo, state, theme)),
+        ),
+        Some(PreviewKind::Branch) => selected_branch(state).map_or_else(
+            || (String::new(), Vec::new()),
+            |branch| (branch.ref_name(), branch_lines(branch, state, theme, now)),
+        ),
+        None => return,
+    };
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {title} "))
+            .border_style(theme.styles.border),
+    );
+    f.render_widget(paragraph, area);
+}
+
+fn selected_repo(state: &AppState) -> Option<&Repo> {
+    let (idx, _) = state.repo_list.filtered.get(state.repo_list.selected?)?;
+    state.repos.get(*idx)
+}
+
+fn selected_branch(state: &AppState) -> Option<&BranchEntry> {
+    let (idx, _) = state
+        .branch_list
+        .filtered
+        .get(state.branch_list.selected?)?;
+    state.branches.get(*idx)
+}
+
+fn field<'a>(name: &'a str, value: impl Into<Span<'a>>, theme: &Theme) -> Line<'a> {
+    Line::from(vec![
+        Span::styled(format!("{name}: "), theme.styles.muted),
+        value.into(),
+    ])
+}
+
+fn repo_lines<'a>(repo: &'a Repo, state: &AppState, theme: &Theme) -> Vec<Line<'a>> {
+    let mut lines = vec![field("Path", repo.path.display().to_string(), theme)];
+    if state.pinned.contains(&repo.path) {
+        lines.push(field(
+            "Pinned",
+            Span::styled("yes", theme.styles.accent),
+            theme,
+        ));
+    }
+    lines.push(Line::default());
+    lines.push(Line::styled(
+        format!("Worktrees ({})", repo.worktrees.len()),
+        theme.styles.secondary.add_modifier(Modifier::BOLD),
+    ));
+    for worktree in &repo.worktrees {
+        let has_session = state
+            .session_activity
+            .contains_key(&repo.tmux_session_name(&worktree.path));
+        let mut spans = vec![
+            Span::raw(theme.glyphs.branch),
+            Span::raw(worktree.branch.as_deref().unwrap_or("(detached)")),
+        ];
+        if has_session {
+            spans.push(Span::styled(theme.glyphs.session, theme.styles.success));
+        }
+        lines.push(Line::from(spans));
+        if !worktree.is_main {
+            lines.push(Line::styled(
+                format!("  {}", worktree.path.display()),
+                theme.styles.muted,
+            ));
+        }
+    }
+    lines
+}
+
+fn branch_lines<'a>(
+    branch: &'a BranchEntry,
+    state: &AppState,
+    theme: &Theme,
+    now: u64,
+) -> Vec<Line<'a>> {
+    let mut lines = Vec::new();
+    if let Some(remote) = &branch.remote {
+        lines.push(field("Remote", remote.as_str(), theme));
+    }
+    if let Some(path) = &branch.worktree_path {
+        lines.push(field("Worktree", path.display().to_string(), theme));
+    }
+    if branch.has_session {
+        let session = branch
+            .agent
+            .as_ref()
+            .map_or_else(|| "running".to_string(), |agent| format!("running {agent}"));
+        lines.push(field(
+            "Session",
+            Span::styled(session, theme.styles.success),
+            theme,
+        ));
+    }
+    if !branch.labels.is_empty() {
+        let labels: Vec<String> = branch.labels.iter().map(|l| format!("#{l}")).collect();
+        lines.push(field(
+            "Labels",
+            Span::styled(labels.join(" "), theme.styles.tertiary),
+            theme,
+        ));
+    }
+    if let Some(commit) = &branch.last_commit {
+        if let Some((ahead, behind)) = commit.ahead_behind {
+            lines.push(field(
+                "Upstream",
+                format!(
+                    "{}{ahead} {}{behind}",
+                    theme.glyphs.ahead, theme.glyphs.behind
+                ),
+                theme,
+            ));
+        }
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            "Last commit",
+            theme.styles.secondary.add_modifier(Modifier::BOLD),
+        ));
+        lines.push(Line::raw(commit.subject.as_str()));
+        lines.push(Line::styled(
+            format!(
+                "{}, {}",
+                commit.author,
+                super::format_age(now.saturating_sub(commit.timestamp))
+            ),
+            theme.styles.muted,
+        ));
+    } else if state.branch_commits.is_empty() {
+        lines.push(Line::styled("Loading last commit...", theme.styles.muted));
+    }
+    if let Some(note) = &branch.note {
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            note.as_str(),
+            theme.styles.tertiary.add_modifier(Modifier::ITALIC),
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kiosk_core::config::{Icons, ThemeConfig};
+    use kiosk_core::git::CommitInfo;
+
+    fn render(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn previews_follow_the_list_under_dialogs() {
+        assert_eq!(
+            PreviewKind::for_mode(&Mode::RepoSelect),
+            Some(PreviewKind::Repo)
+        );
+        assert_eq!(
+            PreviewKind::for_mode(&Mode::EditNote),
+            Some(PreviewKind::Branch)
+        );
+        assert_eq!(PreviewKind::for_mode(&Mode::GlobalSearch), None);
+    }
+
+    #[test]
+    fn branch_preview_shows_its_details() {
+        let theme = Theme::from_config(&ThemeConfig::default()).with_icons(Icons::Unicode);
+        let state = AppState::new(vec![], None);
+        let branch = BranchEntry {
+            name: "feat".to_string(),
+            worktree_path: Some("/repo-feat".into()),
+            has_session: true,
+            is_current: false,
+            is_default: false,
+            remote: None,
+            session_activity_ts: None,
+            last_commit: Some(CommitInfo {
+                subject: "Add the thing".to_string(),
+                author: "Ada".to_string(),
+                timestamp: 0,
+                ahead_behind: Some((2, 0)),
+            }),
+            agent: Some("claude".to_string()),
+            note: Some("Waiting on review".to_string()),
+            labels: vec!["agent".to_string()],
+            detached: false,
+        };
+        let lines = render(&branch_lines(&branch, &state, &theme, 3 * 86_400));
+        assert_eq!(
+            lines[..5],
+            [
+                "Worktree: /repo-feat",
+                "Session: running claude",
+                "Labels: #agent",
+                "Upstream: ↑2 ↓0",
+                ""
+            ]
+        );
+        assert_eq!(lines[6], "Add the thing");
+        assert_eq!(lines[7], "Ada, 3d");
+        assert_eq!(lines.last().unwrap(), "Waiting on review");
+    }
+}
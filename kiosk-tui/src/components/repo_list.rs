@@ -1,15 +1,22 @@
 use crate::theme::Theme;
 use kiosk_core::config::KeysConfig;
-use kiosk_core::state::AppState;
+use kiosk_core::state::{AppState, repo_activity_history, repo_max_activity};
 use ratatui::{
     Frame,
     layout::{Constraint, Layout, Rect},
-    style::{Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, ListState},
 };
 
-pub fn draw(f: &mut Frame, area: Rect, state: &AppState, theme: &Theme, _keys: &KeysConfig) {
+/// Draw the repo list, with session activity aged as of `now` (Unix seconds).
+pub fn draw(
+    f: &mut Frame,
+    area: Rect,
+    state: &AppState,
+    theme: &Theme,
+    _keys: &KeysConfig,
+    now: u64,
+) {
     let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(area);
 
     // Search bar
@@ -31,7 +38,8 @@ pub fn draw(f: &mut Frame, area: Rect, state: &AppState, theme: &Theme, _keys: &
         .repo_list
         .filtered
         .iter()
-        .map(|(idx, _)| {
+        .enumerate()
+        .map(|(row, (idx, _))| {
             let repo = &state.repos[*idx];
             let wt_count = repo.worktrees.len();
             let branch = repo
@@ -40,26 +48,36 @@ pub fn draw(f: &mut Frame, area: Rect, state: &AppState, theme: &Theme, _keys: &
                 .and_then(|wt| wt.branch.as_deref())
                 .unwrap_or("??");
 
-            let mut spans = vec![Span::raw(&repo.name)];
-            spans.push(Span::styled(
-                format!(" [{branch}]"),
-                Style::default().fg(theme.muted),
-            ));
+            let mut spans = Vec::new();
+            if state.pinned.contains(&repo.path) {
+                spans.push(Span::styled(theme.glyphs.pinned, theme.styles.warning));
+            }
+            spans.push(Span::raw(theme.glyphs.repo));
+            spans.push(Span::raw(&repo.name));
+            spans.push(Span::styled(format!(" [{branch}]"), theme.styles.muted));
             if wt_count > 1 {
                 spans.push(Span::styled(
                     format!(" +{} worktrees", wt_count - 1),
-                    Style::default().fg(theme.warning),
+                    theme.styles.warning,
                 ));
             }
+            if let Some(activity) = repo_max_activity(repo, &state.session_activity) {
+                spans.push(Span::raw(" "));
+                spans.push(super::activity_age(activity, now, theme));
+                let history =
+                    repo_activity_history(repo, &state.session_activity, &state.activity_history);
+                spans.push(Span::raw(" "));
+                spans.push(super::activity_heat(&history, now, theme));
+            }
 
-            ListItem::new(Line::from(spans))
+            theme.stripe(ListItem::new(Line::from(spans)), row)
         })
         .collect();
 
     if state.loading_repos && state.repo_list.filtered.is_empty() {
         items.push(ListItem::new(Line::from(vec![Span::styled(
             "Discovering repos...",
-            Style::default().fg(theme.muted),
+            theme.styles.muted,
         )])));
     }
 
@@ -74,15 +92,10 @@ pub fn draw(f: &mut Frame, area: Rect, state: &AppState, theme: &Theme, _keys: &
             Block::default()
                 .borders(Borders::ALL)
                 .title(format!(" {count} repos{loading_suffix} "))
-                .border_style(Style::default().fg(theme.border)),
-        )
-        .highlight_style(
-            Style::default()
-                .bg(theme.accent)
-                .fg(theme.highlight_fg)
-                .add_modifier(Modifier::BOLD),
+                .border_style(theme.styles.border),
         )
-        .highlight_symbol("▸ ");
+        .highlight_style(theme.selection_style(theme.accent))
+        .highlight_symbol(theme.glyphs.selected);
 
     let mut list_state = ListState::default();
     list_state.select(state.repo_list.selected);
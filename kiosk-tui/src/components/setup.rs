@@ -21,16 +21,14 @@ fn draw_welcome(f: &mut Frame, theme: &Theme) {
     let block = Block::default()
         .borders(Borders::ALL)
         .title(" Welcome ")
-        .border_style(Style::default().fg(theme.accent))
+        .border_style(theme.styles.accent)
         .padding(Padding::uniform(1));
 
     let content = vec![
         Line::from(""),
         Line::from(Span::styled(
             "Welcome to Kiosk!",
-            Style::default()
-                .fg(theme.accent)
-                .add_modifier(Modifier::BOLD),
+            theme.styles.accent.add_modifier(Modifier::BOLD),
         )),
         Line::from(""),
         Line::from("Kiosk is a git-aware tmux session manager that helps"),
@@ -40,10 +38,7 @@ fn draw_welcome(f: &mut Frame, theme: &Theme) {
         Line::from(""),
         Line::from(vec![
             Span::raw("Press "),
-            Span::styled(
-                "Enter",
-                Style::default().fg(theme.hint).add_modifier(Modifier::BOLD),
-            ),
+            Span::styled("Enter", theme.styles.hint.add_modifier(Modifier::BOLD)),
             Span::raw(" to continue."),
         ]),
     ];
@@ -68,7 +63,7 @@ fn draw_search_dirs(f: &mut Frame, state: &AppState, theme: &Theme) {
     let block = Block::default()
         .borders(Borders::ALL)
         .title(" Add search directories ")
-        .border_style(Style::default().fg(theme.accent))
+        .border_style(theme.styles.accent)
         .padding(Padding::uniform(1));
     let inner = block.inner(area);
     f.render_widget(block, area);
@@ -125,15 +120,10 @@ fn draw_search_dirs(f: &mut Frame, state: &AppState, theme: &Theme) {
             .block(
                 Block::default()
                     .borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM)
-                    .border_style(Style::default().fg(theme.border)),
+                    .border_style(theme.styles.border),
             )
-            .highlight_style(
-                Style::default()
-                    .fg(theme.highlight_fg)
-                    .bg(theme.accent)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .highlight_symbol("▸ ");
+            .highlight_style(theme.selection_style(theme.accent))
+            .highlight_symbol(theme.glyphs.selected);
 
         let mut list_state = ListState::default();
         list_state.select(setup.selected_completion);
@@ -144,7 +134,7 @@ fn draw_search_dirs(f: &mut Frame, state: &AppState, theme: &Theme) {
     let dirs_area = chunks[3];
     if setup.dirs.is_empty() {
         let msg = Paragraph::new("No directories added yet.")
-            .style(Style::default().fg(theme.muted))
+            .style(theme.styles.muted)
             .alignment(Alignment::Center);
         f.render_widget(msg, dirs_area);
     } else {
@@ -164,12 +154,12 @@ fn draw_search_dirs(f: &mut Frame, state: &AppState, theme: &Theme) {
                 if path_input::path_exists(dir) {
                     ListItem::new(Line::from(Span::styled(
                         format!("  ✓ {dir}"),
-                        Style::default().fg(theme.success),
+                        theme.styles.success,
                     )))
                 } else {
                     ListItem::new(Line::from(Span::styled(
                         format!("  ⚠ {dir} (doesn't exist yet)"),
-                        Style::default().fg(theme.warning),
+                        theme.styles.warning,
                     )))
                 }
             })
@@ -182,20 +172,11 @@ fn draw_search_dirs(f: &mut Frame, state: &AppState, theme: &Theme) {
     // Instructions
     let instructions_area = chunks[4];
     let hints = Line::from(vec![
-        Span::styled(
-            "[Enter]",
-            Style::default().fg(theme.hint).add_modifier(Modifier::BOLD),
-        ),
+        Span::styled("[Enter]", theme.styles.hint.add_modifier(Modifier::BOLD)),
         Span::raw(" add / finish  "),
-        Span::styled(
-            "[Tab]",
-            Style::default().fg(theme.hint).add_modifier(Modifier::BOLD),
-        ),
+        Span::styled("[Tab]", theme.styles.hint.add_modifier(Modifier::BOLD)),
         Span::raw(" autocomplete  "),
-        Span::styled(
-            "[Esc]",
-            Style::default().fg(theme.hint).add_modifier(Modifier::BOLD),
-        ),
+        Span::styled("[Esc]", theme.styles.hint.add_modifier(Modifier::BOLD)),
         Span::raw(" deselect / quit"),
     ]);
     let instructions = Paragraph::new(hints).alignment(Alignment::Center);
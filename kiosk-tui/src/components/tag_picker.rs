@@ -0,0 +1,104 @@
+use crate::theme::Theme;
+use kiosk_core::state::AppState;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::Modifier,
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState},
+};
+
+/// Longest tag name the age column is lined up after
+const MAX_NAME_WIDTH: usize = 40;
+
+/// The selected repo's tags, newest first, each with its age, its annotation, and whether a
+/// worktree is already detached at it. Ages are as of `now` (Unix seconds).
+pub fn draw(f: &mut Frame, area: Rect, state: &AppState, theme: &Theme, now: u64) {
+    let Some(tag_select) = &state.tag_select else {
+        return;
+    };
+    let repo_name = state
+        .selected_repo_idx
+        .and_then(|idx| state.repos.get(idx))
+        .map_or("", |repo| repo.name.as_str());
+    let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(area);
+
+    let title = format!("{repo_name} — tags");
+    super::search_bar::draw(
+        f,
+        chunks[0],
+        &super::search_bar::SearchBarStyle {
+            title: &title,
+            placeholder: "Type to search tags...",
+            border_color: theme.secondary,
+            muted_color: theme.muted,
+        },
+        &tag_select.list.input.text,
+        tag_select.list.input.cursor,
+    );
+
+    let name_width = tag_select
+        .tags
+        .iter()
+        .map(|entry| entry.tag.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(MAX_NAME_WIDTH);
+    let mut items: Vec<ListItem> = tag_select
+        .list
+        .filtered
+        .iter()
+        .enumerate()
+        .map(|(row, (idx, _))| {
+            let entry = &tag_select.tags[*idx];
+            let mut spans = vec![
+                Span::raw(format!("{:<name_width$}", entry.tag.name)),
+                Span::styled(
+                    format!(
+                        "  {:>3}",
+                        super::format_age(now.saturating_sub(entry.tag.timestamp))
+                    ),
+                    theme.styles.muted,
+                ),
+            ];
+            if entry.worktree_path.is_some() {
+                spans.push(Span::styled(theme.glyphs.worktree, theme.styles.warning));
+            }
+            if let Some(annotation) = &entry.tag.annotation {
+                spans.push(Span::styled(
+                    format!(" — {annotation}"),
+                    theme.styles.tertiary.add_modifier(Modifier::ITALIC),
+                ));
+            }
+            theme.stripe(ListItem::new(Line::from(spans)), row)
+        })
+        .collect();
+
+    if tag_select.tags.is_empty() {
+        let message = if tag_select.loading {
+            "Loading tags..."
+        } else {
+            "No tags"
+        };
+        items.push(ListItem::new(Line::from(vec![Span::styled(
+            message,
+            theme.styles.muted,
+        )])));
+    }
+
+    let count = tag_select.list.filtered.len();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" {count} tags "))
+                .border_style(theme.styles.border),
+        )
+        .highlight_style(theme.selection_style(theme.secondary))
+        .highlight_symbol(theme.glyphs.selected);
+
+    let mut list_state = ListState::default();
+    list_state.select(tag_select.list.selected);
+    *list_state.offset_mut() = tag_select.list.scroll_offset;
+    f.render_stateful_widget(list, chunks[1], &mut list_state);
+}
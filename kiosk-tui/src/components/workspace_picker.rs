@@ -0,0 +1,96 @@
+use crate::theme::Theme;
+use kiosk_core::state::AppState;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState},
+};
+
+/// Longest workspace name the members are lined up after
+const MAX_NAME_WIDTH: usize = 30;
+
+/// Workspaces from the config and the ones created here, each with its members. When a branch
+/// is being added, a search that matches none offers a new workspace by that name.
+pub fn draw(f: &mut Frame, area: Rect, state: &AppState, theme: &Theme) {
+    let Some(select) = &state.workspace_select else {
+        return;
+    };
+    let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(area);
+
+    let title = match &select.adding {
+        Some(member) => format!("Add {member} to a workspace"),
+        None => "Workspaces".to_string(),
+    };
+    super::search_bar::draw(
+        f,
+        chunks[0],
+        &super::search_bar::SearchBarStyle {
+            title: &title,
+            placeholder: "Type to search workspaces...",
+            border_color: theme.secondary,
+            muted_color: theme.muted,
+        },
+        &select.list.input.text,
+        select.list.input.cursor,
+    );
+
+    let name_width = select
+        .workspaces
+        .iter()
+        .map(|workspace| workspace.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(MAX_NAME_WIDTH);
+    let mut items: Vec<ListItem> = select
+        .list
+        .filtered
+        .iter()
+        .enumerate()
+        .map(|(row, (idx, _))| {
+            let workspace = &select.workspaces[*idx];
+            let spans = vec![
+                Span::raw(format!("{:<name_width$}", workspace.name)),
+                Span::styled(
+                    format!("  {}", workspace.members.join(", ")),
+                    theme.styles.muted,
+                ),
+            ];
+            theme.stripe(ListItem::new(Line::from(spans)), row)
+        })
+        .collect();
+
+    let search = select.list.input.text.trim();
+    if select.list.filtered.is_empty() {
+        let message = match (&select.adding, search.is_empty()) {
+            (Some(_), false) => format!("Enter: new workspace \"{search}\""),
+            (Some(_), true) => "Type a name for a new workspace".to_string(),
+            (None, _) if select.workspaces.is_empty() => {
+                "No workspaces. Add them under [workspaces] in the config, or with Alt-w in the \
+                 branch list"
+                    .to_string()
+            }
+            (None, _) => "No matching workspaces".to_string(),
+        };
+        items.push(ListItem::new(Line::from(vec![Span::styled(
+            message,
+            theme.styles.muted,
+        )])));
+    }
+
+    let count = select.list.filtered.len();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" {count} workspaces "))
+                .border_style(theme.styles.border),
+        )
+        .highlight_style(theme.selection_style(theme.secondary))
+        .highlight_symbol(theme.glyphs.selected);
+
+    let mut list_state = ListState::default();
+    list_state.select(select.list.selected);
+    *list_state.offset_mut() = select.list.scroll_offset;
+    f.render_stateful_widget(list, chunks[1], &mut list_state);
+}
@@ -1,8 +1,12 @@
 use kiosk_core::action::Action;
 use kiosk_core::config::{Command, KeysConfig};
 use kiosk_core::keyboard::{KeyCode, KeyEvent, KeyModifiers};
+use kiosk_core::layout::LayoutPrefs;
 use kiosk_core::state::{AppState, Mode, SetupStep};
 
+/// Longest count that can prefix a movement
+const MAX_COUNT: usize = 9999;
+
 /// Resolve a key event into an Action based on current mode and key configuration
 pub fn resolve_action(
     key: crossterm::event::KeyEvent,
@@ -21,10 +25,26 @@ pub fn resolve_action(
     }
 
     let mode_keymap = keys.keymap_for_mode(&state.mode);
-    if let Some(command) = mode_keymap.get(&our_key)
-        && let Some(action) = command_to_action(command, state)
+    if let Some(command) = mode_keymap.get(&our_key) {
+        if let Some(count) = state.pending_count
+            && let Some(action) = counted_movement(command, count, state)
+        {
+            return Some(action);
+        }
+        if let Some(action) = command_to_action(command, state) {
+            return Some(action);
+        }
+    }
+
+    // Alt and a digit start or extend a count for the next movement, like vim's `5j`. Plain
+    // digits and letters type into the search, so unlike vim there's no pending-key state
+    // for them: the count is the only key state kept between presses
+    if state.active_list().is_some()
+        && our_key.modifiers == KeyModifiers::ALT
+        && let KeyCode::Char(c) = our_key.code
+        && let Some(digit) = c.to_digit(10)
     {
-        return Some(action);
+        return Some(Action::CountDigit(digit));
     }
 
     // Handle printable characters for search in search-enabled modes
@@ -38,12 +58,41 @@ pub fn resolve_action(
     None
 }
 
+/// Repeat a movement `count` times, or for the top and bottom motions jump to row `count`
+/// (vim's `5gg` and `5G`).
+fn counted_movement(command: &Command, count: usize, state: &AppState) -> Option<Action> {
+    match command {
+        Command::MoveUp
+        | Command::MoveDown
+        | Command::HalfPageUp
+        | Command::HalfPageDown
+        | Command::PageUp
+        | Command::PageDown => Some(Action::RunCommands(vec![command.clone(); count])),
+        Command::MoveTop | Command::MoveBottom => {
+            let last = state.active_list()?.filtered.len().checked_sub(1)?;
+            Some(Action::SelectRow((count - 1).min(last)))
+        }
+        _ => None,
+    }
+}
+
+/// The count after typing `digit`, capped at `MAX_COUNT`. A leading zero isn't a count.
+pub(crate) fn push_count_digit(count: Option<usize>, digit: u32) -> Option<usize> {
+    let count = count.unwrap_or(0) * 10 + digit as usize;
+    (count > 0).then_some(count.min(MAX_COUNT))
+}
+
 /// Convert a Command to an Action, taking into account the current state
-fn command_to_action(command: &Command, state: &AppState) -> Option<Action> {
+#[allow(clippy::too_many_lines)]
+pub(crate) fn command_to_action(command: &Command, state: &AppState) -> Option<Action> {
     match command {
         Command::Noop => None,
+        Command::Chain(commands) => Some(Action::RunCommands(commands.clone())),
+        Command::Script(function) => matches!(state.mode, Mode::RepoSelect | Mode::BranchSelect)
+            .then(|| Action::RunScript(function.clone())),
         Command::Quit => Some(Action::Quit),
         Command::ShowHelp => Some(Action::ShowHelp),
+        Command::CommandPalette => Some(Action::ShowCommandPalette),
         Command::OpenRepo => Some(Action::OpenRepo),
         Command::EnterRepo => Some(Action::EnterRepo),
         Command::OpenBranch => {
@@ -56,8 +105,41 @@ fn command_to_action(command: &Command, state: &AppState) -> Option<Action> {
             }
             Some(Action::OpenBranch)
         }
+        Command::OpenInEditor => Some(Action::OpenInEditor),
+        Command::OpenWithAgent => Some(Action::OpenWithAgent),
         Command::GoBack => Some(Action::GoBack),
+        Command::TogglePin => {
+            if let Mode::RepoSelect = state.mode {
+                Some(Action::TogglePin)
+            } else {
+                None
+            }
+        }
+        Command::HideRepo => {
+            if let Mode::RepoSelect = state.mode {
+                Some(Action::HideRepo)
+            } else {
+                None
+            }
+        }
         Command::NewBranch => Some(Action::StartNewBranchFlow),
+        Command::NewRepo => {
+            matches!(state.mode, Mode::RepoSelect).then_some(Action::StartNewRepoFlow)
+        }
+        Command::CloneRepo => {
+            if let Mode::RepoSelect = state.mode {
+                Some(Action::StartCloneFlow)
+            } else {
+                None
+            }
+        }
+        Command::GlobalSearch => {
+            if let Mode::RepoSelect = state.mode {
+                Some(Action::StartGlobalSearch)
+            } else {
+                None
+            }
+        }
         Command::DeleteWorktree => {
             if let Mode::BranchSelect = state.mode {
                 Some(Action::DeleteWorktree)
@@ -65,6 +147,50 @@ fn command_to_action(command: &Command, state: &AppState) -> Option<Action> {
                 None
             }
         }
+        Command::UndoDelete => {
+            // Bound to plain `u`, so it types into the search instead while there's a search
+            // or nothing of this repo's to restore
+            let repo = state.selected_repo_idx.and_then(|idx| state.repos.get(idx));
+            (matches!(state.mode, Mode::BranchSelect)
+                && state.branch_list.input.text.is_empty()
+                && repo.is_some_and(|repo| {
+                    state
+                        .delete_journal
+                        .latest(Some(&repo.path), None)
+                        .is_some()
+                }))
+            .then_some(Action::UndoDelete)
+        }
+        Command::FetchRemotes => {
+            if let Mode::BranchSelect = state.mode {
+                Some(Action::FetchRemotes)
+            } else {
+                None
+            }
+        }
+        Command::EditNote => {
+            if let Mode::BranchSelect = state.mode {
+                Some(Action::EditNote)
+            } else {
+                None
+            }
+        }
+        Command::ShowTags => {
+            if let Mode::BranchSelect = state.mode {
+                Some(Action::ShowTags)
+            } else {
+                None
+            }
+        }
+        Command::ShowWorkspaces => {
+            matches!(state.mode, Mode::RepoSelect).then_some(Action::ShowWorkspaces)
+        }
+        Command::AddToWorkspace => {
+            matches!(state.mode, Mode::BranchSelect).then_some(Action::AddToWorkspace)
+        }
+        Command::TogglePreview => Some(Action::TogglePreview),
+        Command::GrowPreview => Some(Action::ResizePreview(LayoutPrefs::RESIZE_STEP)),
+        Command::ShrinkPreview => Some(Action::ResizePreview(-LayoutPrefs::RESIZE_STEP)),
         Command::MoveUp => Some(Action::MoveSelection(-1)),
         Command::MoveDown => Some(Action::MoveSelection(1)),
         Command::HalfPageUp => Some(Action::HalfPageUp),
@@ -87,20 +213,41 @@ fn command_to_action(command: &Command, state: &AppState) -> Option<Action> {
         Command::MoveCursorEnd => Some(Action::CursorEnd),
         Command::Confirm => match state.mode {
             Mode::ConfirmWorktreeDelete { .. } => Some(Action::ConfirmDeleteWorktree),
-            Mode::SelectBaseBranch => Some(Action::OpenBranch),
+            Mode::ConfirmResume { .. } => Some(Action::ConfirmResume),
+            Mode::ConfirmRecover { .. } => Some(Action::FinishInterrupted),
+            Mode::EditNote => Some(Action::SaveNote),
+            Mode::BranchNameFields => Some(Action::SaveBranchNameField),
+            Mode::CommandPalette { .. } => Some(Action::RunPaletteCommand),
+            Mode::SelectBaseBranch | Mode::GlobalSearch | Mode::TagSelect => {
+                Some(Action::OpenBranch)
+            }
+            Mode::WorkspaceSelect => Some(Action::ConfirmWorkspace),
+            Mode::SelectCloneDir => Some(Action::ConfirmClone),
+            Mode::NewRepo => Some(Action::ConfirmNewRepo),
             Mode::Setup(SetupStep::Welcome) => Some(Action::SetupContinue),
             Mode::Setup(SetupStep::SearchDirs) => Some(Action::SetupAddDir),
             _ => None,
         },
         Command::Cancel => match state.mode {
             Mode::ConfirmWorktreeDelete { .. } => Some(Action::CancelDeleteWorktree),
-            Mode::SelectBaseBranch => Some(Action::GoBack),
+            Mode::ConfirmResume { .. } => Some(Action::CancelResume),
+            Mode::SelectBaseBranch
+            | Mode::SelectCloneDir
+            | Mode::NewRepo
+            | Mode::GlobalSearch
+            | Mode::TagSelect
+            | Mode::WorkspaceSelect
+            | Mode::EditNote
+            | Mode::BranchNameFields
+            | Mode::ConfirmRecover { .. }
+            | Mode::CommandPalette { .. } => Some(Action::GoBack),
             Mode::Setup(SetupStep::Welcome) => Some(Action::Quit),
             Mode::Setup(SetupStep::SearchDirs) => Some(Action::SetupCancel),
             _ => None,
         },
         Command::TabComplete => match state.mode {
             Mode::Setup(SetupStep::SearchDirs) => Some(Action::SetupTabComplete),
+            Mode::ConfirmRecover { .. } => Some(Action::UndoInterrupted),
             _ => None,
         },
     }
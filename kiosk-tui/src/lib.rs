@@ -1,7 +1,9 @@
 pub mod app;
+mod background;
 pub mod components;
 pub mod keymap;
+mod matcher;
 pub mod theme;
 
-pub use app::{OpenAction, run};
+pub use app::{OpenAction, run, run_runtime};
 pub use theme::Theme;
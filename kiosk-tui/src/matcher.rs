@@ -0,0 +1,312 @@
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use kiosk_core::config::{SearchCase, SearchConfig, SearchMatcher, SearchMode};
+use nucleo_matcher::{
+    Utf32Str,
+    pattern::{Atom, AtomKind, CaseMatching, Normalization, Pattern},
+};
+use std::{cell::RefCell, ops::Range};
+
+/// Scores list items against the search, as set by `[search]` in the config.
+pub(crate) struct Matcher {
+    config: SearchConfig,
+    skim: SkimMatcherV2,
+    nucleo: RefCell<nucleo_matcher::Matcher>,
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Self::new(SearchConfig::default())
+    }
+}
+
+impl Matcher {
+    pub(crate) fn new(config: SearchConfig) -> Self {
+        let skim = match config.case {
+            SearchCase::Smart => SkimMatcherV2::default().smart_case(),
+            SearchCase::Ignore => SkimMatcherV2::default().ignore_case(),
+            SearchCase::Respect => SkimMatcherV2::default().respect_case(),
+        };
+        Self {
+            config,
+            skim,
+            nucleo: RefCell::new(nucleo_matcher::Matcher::new(
+                nucleo_matcher::Config::DEFAULT,
+            )),
+        }
+    }
+
+    /// Score each of `items[range]` against `query`, keeping the ones that match. Higher
+    /// scores are better matches.
+    pub(crate) fn matches<S: AsRef<str>>(
+        &self,
+        items: &[S],
+        range: Range<usize>,
+        query: &str,
+    ) -> Vec<(usize, i64)> {
+        let terms = parse_query(query, self.config.mode);
+        let items = items[range.clone()].iter().zip(range);
+        match self.config.matcher {
+            SearchMatcher::Skim => items
+                .filter_map(|(item, i)| self.skim_score(item.as_ref(), &terms).map(|s| (i, s)))
+                .collect(),
+            SearchMatcher::Nucleo => {
+                let pattern = self.nucleo_pattern(&terms);
+                let mut matcher = self.nucleo.borrow_mut();
+                let mut buf = Vec::new();
+                items
+                    .filter_map(|(item, i)| {
+                        pattern
+                            .score(Utf32Str::new(item.as_ref(), &mut buf), &mut matcher)
+                            .map(|score| (i, i64::from(score)))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// The sum of the scores of the terms that aren't negated, if every term matches.
+    fn skim_score(&self, item: &str, terms: &[Term]) -> Option<i64> {
+        let mut total = 0;
+        for term in terms {
+            let found = if term.kind == TermKind::Fuzzy {
+                self.skim.fuzzy_match(item, term.text)
+            } else if self.matches_exactly(item, term) {
+                // Rank by where and how the text matched, as for fuzzy terms
+                Some(self.skim.fuzzy_match(item, term.text).unwrap_or(0))
+            } else {
+                None
+            };
+            match (found, term.negated) {
+                (Some(score), false) => total += score,
+                (None, true) => {}
+                _ => return None,
+            }
+        }
+        Some(total)
+    }
+
+    /// Whether `item` has `term`'s text where its kind asks for it, ignoring case as
+    /// configured.
+    fn matches_exactly(&self, item: &str, term: &Term) -> bool {
+        let (item, text) = if self.ignores_case(term.text) {
+            (item.to_lowercase(), term.text.to_lowercase())
+        } else {
+            (item.to_string(), term.text.to_string())
+        };
+        match term.kind {
+            TermKind::Fuzzy | TermKind::Substring => item.contains(&text),
+            TermKind::Prefix => item.starts_with(&text),
+            TermKind::Suffix => item.ends_with(&text),
+            TermKind::Exact => item == text,
+        }
+    }
+
+    fn ignores_case(&self, text: &str) -> bool {
+        match self.config.case {
+            SearchCase::Smart => !text.chars().any(char::is_uppercase),
+            SearchCase::Ignore => true,
+            SearchCase::Respect => false,
+        }
+    }
+
+    fn nucleo_pattern(&self, terms: &[Term]) -> Pattern {
+        let case = match self.config.case {
+            SearchCase::Smart => CaseMatching::Smart,
+            SearchCase::Ignore => CaseMatching::Ignore,
+            SearchCase::Respect => CaseMatching::Respect,
+        };
+        let mut pattern = Pattern::default();
+        pattern.atoms = terms
+            .iter()
+            .map(|term| {
+                let kind = match term.kind {
+                    TermKind::Fuzzy => AtomKind::Fuzzy,
+                    TermKind::Substring => AtomKind::Substring,
+                    TermKind::Prefix => AtomKind::Prefix,
+                    TermKind::Suffix => AtomKind::Postfix,
+                    TermKind::Exact => AtomKind::Exact,
+                };
+                let mut atom = Atom::new(term.text, case, Normalization::Smart, kind, false);
+                atom.negative = term.negated;
+                atom
+            })
+            .collect();
+        pattern
+    }
+}
+
+/// One space-separated word of a search, with its operators taken off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Term<'a> {
+    text: &'a str,
+    kind: TermKind,
+    /// Set by a leading `!`: only items that don't match are kept
+    negated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TermKind {
+    /// The text's characters in order, with anything between them
+    Fuzzy,
+    /// The text as written, anywhere: `'text`
+    Substring,
+    /// `^text`
+    Prefix,
+    /// `text$`
+    Suffix,
+    /// The whole item: `^text$`
+    Exact,
+}
+
+/// Split a search into terms that must all match, with fzf's operators: `'exact`,
+/// `^prefix`, `suffix$` and `!negated`. Negated terms never match fuzzily, and in
+/// substring mode no term does. Operators with no text after them are dropped.
+fn parse_query(query: &str, mode: SearchMode) -> Vec<Term<'_>> {
+    query
+        .split_whitespace()
+        .filter_map(|word| {
+            let (negated, word) = match word.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, word),
+            };
+            let (mut kind, word) = if let Some(rest) = word.strip_prefix('^') {
+                (TermKind::Prefix, rest)
+            } else if let Some(rest) = word.strip_prefix('\'') {
+                (TermKind::Substring, rest)
+            } else {
+                (TermKind::Fuzzy, word)
+            };
+            let word = match word.strip_suffix('$') {
+                Some(rest) if kind == TermKind::Prefix => {
+                    kind = TermKind::Exact;
+                    rest
+                }
+                Some(rest) if kind == TermKind::Fuzzy => {
+                    kind = TermKind::Suffix;
+                    rest
+                }
+                _ => word,
+            };
+            if kind == TermKind::Fuzzy && (negated || mode == SearchMode::Substring) {
+                kind = TermKind::Substring;
+            }
+            (!word.is_empty()).then_some(Term {
+                text: word,
+                kind,
+                negated,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched(config: SearchConfig, items: &[&str], query: &str) -> Vec<String> {
+        let mut matches = Matcher::new(config).matches(items, 0..items.len(), query);
+        matches.sort_by_key(|(i, score)| (std::cmp::Reverse(*score), *i));
+        matches.iter().map(|(i, _)| items[*i].to_string()).collect()
+    }
+
+    const ITEMS: &[&str] = &["feature/login", "fix-logging", "Release"];
+
+    #[test]
+    fn both_matchers_match_fuzzily() {
+        for matcher in [SearchMatcher::Skim, SearchMatcher::Nucleo] {
+            let config = SearchConfig {
+                matcher,
+                ..SearchConfig::default()
+            };
+            let found = matched(config, ITEMS, "flog");
+            assert_eq!(found.len(), 2, "{matcher:?}: {found:?}");
+            assert!(matched(config, ITEMS, "zzz").is_empty());
+        }
+    }
+
+    #[test]
+    fn substring_mode_needs_the_search_as_written() {
+        for matcher in [SearchMatcher::Skim, SearchMatcher::Nucleo] {
+            let config = SearchConfig {
+                matcher,
+                mode: SearchMode::Substring,
+                ..SearchConfig::default()
+            };
+            assert!(matched(config, ITEMS, "flog").is_empty(), "{matcher:?}");
+            assert_eq!(
+                matched(config, ITEMS, "logg"),
+                vec!["fix-logging"],
+                "{matcher:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn case_follows_the_config() {
+        for matcher in [SearchMatcher::Skim, SearchMatcher::Nucleo] {
+            let with_case = |case| SearchConfig {
+                matcher,
+                case,
+                ..SearchConfig::default()
+            };
+            assert_eq!(
+                matched(with_case(SearchCase::Smart), ITEMS, "rea"),
+                vec!["Release"]
+            );
+            assert!(matched(with_case(SearchCase::Smart), ITEMS, "REA").is_empty());
+            assert_eq!(
+                matched(with_case(SearchCase::Ignore), ITEMS, "REA"),
+                vec!["Release"]
+            );
+            assert!(matched(with_case(SearchCase::Respect), ITEMS, "rea").is_empty());
+        }
+    }
+
+    #[test]
+    fn queries_split_into_terms_with_operators() {
+        let term = |text, kind, negated| Term {
+            text,
+            kind,
+            negated,
+        };
+        assert_eq!(
+            parse_query("log  'fix ^feat main$ ^v1$ !wip !^tmp", SearchMode::Fuzzy),
+            vec![
+                term("log", TermKind::Fuzzy, false),
+                term("fix", TermKind::Substring, false),
+                term("feat", TermKind::Prefix, false),
+                term("main", TermKind::Suffix, false),
+                term("v1", TermKind::Exact, false),
+                term("wip", TermKind::Substring, true),
+                term("tmp", TermKind::Prefix, true),
+            ]
+        );
+        assert_eq!(
+            parse_query("log", SearchMode::Substring),
+            vec![term("log", TermKind::Substring, false)]
+        );
+        assert!(parse_query("! ^ ' $", SearchMode::Fuzzy).is_empty());
+    }
+
+    #[test]
+    fn operators_narrow_matches_with_both_matchers() {
+        let items = &["feature/login", "fix-logging", "feature/logout", "main"];
+        for matcher in [SearchMatcher::Skim, SearchMatcher::Nucleo] {
+            let config = SearchConfig {
+                matcher,
+                ..SearchConfig::default()
+            };
+            let found = |query| {
+                let mut found = matched(config, items, query);
+                found.sort();
+                found
+            };
+            assert_eq!(found("^feat"), vec!["feature/login", "feature/logout"]);
+            assert_eq!(found("^feat !out"), vec!["feature/login"]);
+            assert_eq!(found("log ing$"), vec!["fix-logging"]);
+            assert_eq!(found("'fix"), vec!["fix-logging"]);
+            assert_eq!(found("^main$"), vec!["main"]);
+            assert_eq!(found("!^feat"), vec!["fix-logging", "main"], "{matcher:?}");
+        }
+    }
+}
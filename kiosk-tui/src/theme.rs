@@ -1,18 +1,40 @@
-use kiosk_core::config::{NamedColor, ThemeColor};
-use ratatui::style::Color;
+use kiosk_core::config::{Background, Icons, NamedColor, ThemeColor, ThemeConfig, ThemeStyle};
+use kiosk_core::icons::Glyphs;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    widgets::ListItem,
+};
+use std::sync::OnceLock;
 
-/// Generates `Theme` and `from_config` from a list of field names,
+/// The terminal's background, detected the first time a theme needs it.
+static TERMINAL_BACKGROUND: OnceLock<Background> = OnceLock::new();
+
+/// Generates `Theme`, `ThemeStyles` and `from_config` from a list of field names,
 /// mirroring `ThemeConfig` without manual repetition.
 macro_rules! define_theme {
     ($($field:ident),* $(,)?) => {
+        /// Each slot's foreground color, for widgets that take a color; `styles` has the
+        /// slots in full.
+        #[derive(Clone)]
         pub struct Theme {
             $(pub $field: Color,)*
+            pub styles: ThemeStyles,
+            pub glyphs: &'static Glyphs,
+        }
+
+        #[derive(Clone)]
+        pub struct ThemeStyles {
+            $(pub $field: Style,)*
         }
 
         impl Theme {
             pub fn from_config(config: &kiosk_core::config::ThemeConfig) -> Self {
                 Self {
-                    $($field: to_ratatui_color(&config.$field),)*
+                    $($field: config.$field.fg.as_ref().map_or(Color::Reset, to_ratatui_color),)*
+                    styles: ThemeStyles {
+                        $($field: to_ratatui_style(&config.$field),)*
+                    },
+                    glyphs: Icons::default().glyphs(),
                 }
             }
         }
@@ -29,9 +51,82 @@ define_theme!(
     muted,
     border,
     hint,
-    highlight_fg
+    highlight_fg,
+    selection,
+    stripe
 );
 
+impl Theme {
+    /// The theme for the terminal's background: `theme.background`, or else the background
+    /// detected from the terminal. Call this before the TUI starts reading input, as the
+    /// first call can query the terminal.
+    pub fn for_terminal(config: &ThemeConfig) -> Self {
+        let background = config
+            .background
+            .unwrap_or_else(|| *TERMINAL_BACKGROUND.get_or_init(crate::background::detect));
+        Self::from_config(&config.for_background(background))
+    }
+
+    /// Like [`Theme::for_terminal`], but never queries the terminal, for reloading the config
+    /// while the TUI is reading input. A background that wasn't detected at startup counts as
+    /// dark.
+    pub fn reloaded(config: &ThemeConfig) -> Self {
+        let background = config
+            .background
+            .or_else(|| TERMINAL_BACKGROUND.get().copied())
+            .unwrap_or_default();
+        Self::from_config(&config.for_background(background))
+    }
+
+    #[must_use]
+    pub fn with_icons(self, icons: Icons) -> Self {
+        Self {
+            glyphs: icons.glyphs(),
+            ..self
+        }
+    }
+
+    /// Style of the selected row in a list whose accent is `accent`, with the `selection`
+    /// slot laid over it.
+    pub fn selection_style(&self, accent: Color) -> Style {
+        Style::default()
+            .bg(accent)
+            .fg(self.highlight_fg)
+            .add_modifier(Modifier::BOLD)
+            .patch(self.styles.selection)
+    }
+
+    /// Give every other row the `stripe` style.
+    pub fn stripe<'a>(&self, item: ListItem<'a>, index: usize) -> ListItem<'a> {
+        if index % 2 == 1 {
+            item.style(self.styles.stripe)
+        } else {
+            item
+        }
+    }
+}
+
+fn to_ratatui_style(style: &ThemeStyle) -> Style {
+    let mut result = Style::default();
+    if let Some(fg) = &style.fg {
+        result = result.fg(to_ratatui_color(fg));
+    }
+    if let Some(bg) = &style.bg {
+        result = result.bg(to_ratatui_color(bg));
+    }
+    for (on, modifier) in [
+        (style.bold, Modifier::BOLD),
+        (style.italic, Modifier::ITALIC),
+        (style.underline, Modifier::UNDERLINED),
+        (style.dim, Modifier::DIM),
+    ] {
+        if on {
+            result = result.add_modifier(modifier);
+        }
+    }
+    result
+}
+
 fn to_ratatui_color(color: &ThemeColor) -> Color {
     match color {
         ThemeColor::Rgb(r, g, b) => Color::Rgb(*r, *g, *b),
@@ -53,7 +148,6 @@ fn to_ratatui_color(color: &ThemeColor) -> Color {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use kiosk_core::config::ThemeConfig;
 
     #[test]
     fn test_theme_defaults() {
@@ -73,10 +167,10 @@ mod tests {
     #[test]
     fn test_theme_custom() {
         let config = ThemeConfig {
-            accent: ThemeColor::Named(NamedColor::Blue),
-            secondary: ThemeColor::Rgb(255, 0, 255),
-            error: ThemeColor::Named(NamedColor::Magenta),
-            highlight_fg: ThemeColor::Named(NamedColor::Yellow),
+            accent: ThemeColor::Named(NamedColor::Blue).into(),
+            secondary: ThemeColor::Rgb(255, 0, 255).into(),
+            error: ThemeColor::Named(NamedColor::Magenta).into(),
+            highlight_fg: ThemeColor::Named(NamedColor::Yellow).into(),
             ..ThemeConfig::default()
         };
         let theme = Theme::from_config(&config);
@@ -90,10 +184,101 @@ mod tests {
     #[test]
     fn test_theme_dark_gray_color() {
         let config = ThemeConfig {
-            muted: ThemeColor::Named(NamedColor::DarkGray),
+            muted: ThemeColor::Named(NamedColor::DarkGray).into(),
             ..ThemeConfig::default()
         };
         let theme = Theme::from_config(&config);
         assert_eq!(theme.muted, Color::DarkGray);
     }
+
+    #[test]
+    fn test_theme_slot_styles() {
+        let config = ThemeConfig {
+            accent: ThemeStyle {
+                fg: Some(ThemeColor::Named(NamedColor::Blue)),
+                bg: Some(ThemeColor::Rgb(0, 0, 0)),
+                bold: true,
+                italic: true,
+                ..ThemeStyle::default()
+            },
+            ..ThemeConfig::default()
+        };
+        let theme = Theme::from_config(&config);
+        assert_eq!(theme.accent, Color::Blue);
+        assert_eq!(
+            theme.styles.accent,
+            Style::default()
+                .fg(Color::Blue)
+                .bg(Color::Rgb(0, 0, 0))
+                .add_modifier(Modifier::BOLD | Modifier::ITALIC)
+        );
+        assert_eq!(theme.styles.muted, Style::default().fg(Color::DarkGray));
+    }
+
+    #[test]
+    fn test_selection_style_lays_slot_over_list_accent() {
+        let default = Theme::from_config(&ThemeConfig::default());
+        assert_eq!(
+            default.selection_style(Color::Cyan),
+            Style::default()
+                .bg(Color::Cyan)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD)
+        );
+
+        let config = ThemeConfig {
+            selection: ThemeStyle {
+                bg: Some(ThemeColor::Rgb(49, 50, 68)),
+                underline: true,
+                ..ThemeStyle::default()
+            },
+            ..ThemeConfig::default()
+        };
+        let theme = Theme::from_config(&config);
+        assert_eq!(
+            theme.selection_style(Color::Cyan),
+            Style::default()
+                .bg(Color::Rgb(49, 50, 68))
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        );
+    }
+
+    #[test]
+    fn test_stripe_styles_odd_rows() {
+        let config = ThemeConfig {
+            stripe: ThemeStyle {
+                bg: Some(ThemeColor::Named(NamedColor::DarkGray)),
+                ..ThemeStyle::default()
+            },
+            ..ThemeConfig::default()
+        };
+        let theme = Theme::from_config(&config);
+        let striped = Style::default().bg(Color::DarkGray);
+        assert_eq!(theme.stripe(ListItem::new("a"), 0), ListItem::new("a"));
+        assert_eq!(
+            theme.stripe(ListItem::new("b"), 1),
+            ListItem::new("b").style(striped)
+        );
+    }
+
+    #[test]
+    fn test_reloaded_theme_uses_configured_background() {
+        let config = ThemeConfig {
+            background: Some(Background::Light),
+            ..ThemeConfig::default()
+        };
+        let light = Theme::from_config(&ThemeConfig::default().for_background(Background::Light));
+        assert_eq!(Theme::reloaded(&config).border, light.border);
+        assert_ne!(light.border, Color::DarkGray);
+    }
+
+    #[test]
+    fn test_with_icons() {
+        let theme = Theme::from_config(&ThemeConfig::default()).with_icons(Icons::Ascii);
+        assert_eq!(theme.glyphs.selected, "> ");
+        assert_eq!(theme.glyphs.separator, " | ");
+        let theme = theme.with_icons(Icons::Unicode);
+        assert_eq!(theme.glyphs.selected, "▸ ");
+    }
 }
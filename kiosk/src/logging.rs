@@ -1,28 +1,65 @@
-use std::path::PathBuf;
+use kiosk_core::paths::state_dir;
+use std::{
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
 
-const APP_NAME: &str = "kiosk";
 const LOG_FILE_NAME: &str = "kiosk.log";
+/// Rotate the log to `kiosk.log.1` once it grows past this size.
+const MAX_LOG_FILE_BYTES: u64 = 10 * 1024 * 1024;
 
 pub const DEFAULT_LOG_LEVEL: &str = "warn";
+/// Environment variable holding a `tracing` filter directive, e.g. `kiosk::command=debug`.
+pub const LOG_ENV_VAR: &str = "KIOSK_LOG";
 
-pub fn cache_dir() -> PathBuf {
-    dirs::cache_dir()
-        .unwrap_or_else(std::env::temp_dir)
-        .join(APP_NAME)
+pub fn default_log_file() -> PathBuf {
+    state_dir().join(LOG_FILE_NAME)
 }
 
-pub fn default_log_file() -> PathBuf {
-    cache_dir().join(LOG_FILE_NAME)
+/// `--verbose` raises the level given by `--log-level`: `-v` for debug, `-vv` for trace.
+pub fn effective_level(log_level: LevelFilter, verbose: u8) -> LevelFilter {
+    let requested = match verbose {
+        0 => return log_level,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    requested.max(log_level)
+}
+
+fn env_filter(level: LevelFilter) -> EnvFilter {
+    match std::env::var(LOG_ENV_VAR) {
+        Ok(directives) if !directives.trim().is_empty() => EnvFilter::new(directives),
+        _ => EnvFilter::default().add_directive(level.into()),
+    }
 }
 
-pub fn setup_logging(level: log::LevelFilter) -> anyhow::Result<()> {
+fn rotate_if_large(log_file: &Path) -> std::io::Result<()> {
+    if fs::metadata(log_file).is_ok_and(|meta| meta.len() > MAX_LOG_FILE_BYTES) {
+        fs::rename(log_file, log_file.with_extension("log.1"))?;
+    }
+    Ok(())
+}
+
+pub fn setup_logging(level: LevelFilter) -> anyhow::Result<()> {
     let log_file = default_log_file();
     if let Some(parent) = log_file.parent() {
-        std::fs::create_dir_all(parent)?;
+        fs::create_dir_all(parent)?;
     }
-    simple_log::file(log_file.to_string_lossy().into_owned(), level, 10, 10)
+    rotate_if_large(&log_file)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_file)?;
+    tracing_subscriber::fmt()
+        .with_env_filter(env_filter(level))
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .try_init()
         .map_err(|e| anyhow::anyhow!(e))?;
-    log::info!("kiosk logging initialised (level={level})");
+    tracing::info!("kiosk logging initialised (level={level})");
     Ok(())
 }
 
@@ -30,16 +67,31 @@ pub fn setup_logging(level: log::LevelFilter) -> anyhow::Result<()> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn cache_dir_ends_with_app_name() {
-        let dir = cache_dir();
-        assert_eq!(dir.file_name().unwrap(), APP_NAME);
-    }
-
     #[test]
     fn default_log_file_ends_with_log_filename() {
         let path = default_log_file();
         assert_eq!(path.file_name().unwrap(), LOG_FILE_NAME);
-        assert!(path.parent().unwrap().ends_with(APP_NAME));
+        assert!(path.parent().unwrap().ends_with("kiosk"));
+    }
+
+    #[test]
+    fn verbose_raises_log_level() {
+        assert_eq!(effective_level(LevelFilter::WARN, 0), LevelFilter::WARN);
+        assert_eq!(effective_level(LevelFilter::WARN, 1), LevelFilter::DEBUG);
+        assert_eq!(effective_level(LevelFilter::WARN, 2), LevelFilter::TRACE);
+        assert_eq!(effective_level(LevelFilter::TRACE, 1), LevelFilter::TRACE);
+    }
+
+    #[test]
+    fn large_log_is_rotated() {
+        let tmp = tempfile::tempdir().unwrap();
+        let log_file = tmp.path().join(LOG_FILE_NAME);
+        let file = fs::File::create(&log_file).unwrap();
+        file.set_len(MAX_LOG_FILE_BYTES + 1).unwrap();
+
+        rotate_if_large(&log_file).unwrap();
+
+        assert!(!log_file.exists());
+        assert!(tmp.path().join("kiosk.log.1").exists());
     }
 }
@@ -1,18 +1,38 @@
 mod cli;
+
 mod logging;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use kiosk_cli::log_processing::LineFormat;
+use kiosk_cli::{OutputFormat, PaneTarget};
 use kiosk_core::{
+    agent::PromptAnswer,
+    audit::AuditLog,
     config,
     constants::{GIT_DIR_ENTRY, GITDIR_FILE_PREFIX, WORKTREE_DIR_NAME},
+    frecency::FrecencyStore,
     git::{CliGitProvider, GitProvider},
-    pending_delete::load_pending_worktree_deletes,
-    state::AppState,
-    tmux::{CliTmuxProvider, TmuxProvider},
+    instance::FetchLocks,
+    process::CommandExt,
+    recovery::Recovery,
+    runtime::Runtime,
+    scripting::Hook,
+    session_journal::SessionJournal,
+    state::{AppState, Mode, PickScope},
+    tmux::{self, TmuxProvider},
+};
+use kiosk_tui::OpenAction;
+use ratatui::{
+    Terminal,
+    backend::CrosstermBackend,
+    crossterm::{
+        execute,
+        terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+    },
 };
-use kiosk_tui::{OpenAction, Theme};
-use std::{fs, io, path::Path, process::Command, process::ExitCode, sync::Arc};
+use std::{fs, io, ops::ControlFlow, path::Path, process::Command, process::ExitCode, sync::Arc};
+use tracing::level_filters::LevelFilter;
 
 #[derive(Parser)]
 #[command(
@@ -24,9 +44,37 @@ struct Cli {
     #[arg(short, long)]
     config: Option<std::path::PathBuf>,
 
-    /// Logging level (trace, debug, info, warn, error)
+    /// Logging level (trace, debug, info, warn, error). Logs are written to
+    /// `$XDG_STATE_HOME/kiosk/kiosk.log`; `KIOSK_LOG` (e.g. `kiosk::command=debug`) takes precedence
     #[arg(long, default_value = logging::DEFAULT_LOG_LEVEL)]
-    log_level: log::LevelFilter,
+    log_level: LevelFilter,
+
+    /// Log at debug level (`-vv` for trace), including every git/tmux command run with its duration and exit status
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Output format for CLI subcommands: table, json, yaml, tsv, or a template such as
+    /// '{name}\t{path}' rendered once per row (`--json` is shorthand for `--format json`)
+    #[arg(long, global = true)]
+    format: Option<OutputFormat>,
+
+    /// Render each output row with a template, e.g. '{name}\t{path}'. `{field}` interpolates a
+    /// field (`{a.b}` for nested ones), `{{`/`}}` are literal braces, and `\t`/`\n` are unescaped
+    #[arg(long, global = true, conflicts_with = "format", value_parser = parse_template)]
+    template: Option<OutputFormat>,
+
+    /// Merge the named `[profiles.<name>]` settings from the config on top of the rest. Also
+    /// set by `KIOSK_PROFILE`
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Answer yes to every confirmation prompt, e.g. for CI. Also set by `KIOSK_ASSUME_YES=1`
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// Name of the tmux server socket to use, as with `tmux -L`. Overrides `tmux.socket`
+    #[arg(short = 'L', long, global = true, value_name = "NAME")]
+    tmux_socket: Option<String>,
 
     #[command(subcommand)]
     command: Option<Commands>,
@@ -39,15 +87,30 @@ enum Commands {
         /// List orphaned worktrees without removing them
         #[arg(long)]
         dry_run: bool,
-        /// Skip interactive confirmation and remove immediately
-        #[arg(long)]
-        yes: bool,
         /// Output result as JSON (dry-run unless --yes is also set)
         #[arg(long)]
         json: bool,
     },
     /// List discovered repositories
     List {
+        /// Only list repos whose name matches this glob (e.g. 'kiosk*')
+        #[arg(long, value_name = "NAME_GLOB")]
+        filter: Option<String>,
+        /// Only list repos located under this directory
+        #[arg(long, value_name = "DIR")]
+        path_under: Option<std::path::PathBuf>,
+        /// Sort order (default: discovery order)
+        #[arg(long, value_enum)]
+        sort: Option<kiosk_cli::ListSort>,
+        /// Only list repos with at least one tmux session
+        #[arg(long)]
+        with_sessions_only: bool,
+        /// Only list pinned repos
+        #[arg(long)]
+        pinned: bool,
+        /// Also list repos matched by `ignore` in the config or hidden from the TUI
+        #[arg(long)]
+        include_hidden: bool,
         /// Output result as JSON
         #[arg(long)]
         json: bool,
@@ -56,59 +119,269 @@ enum Commands {
     Branches {
         /// Repository name (as shown by 'kiosk list')
         repo: String,
+        /// Only local branches
+        #[arg(long)]
+        local: bool,
+        /// Only remote branches without a local counterpart, from every remote or just NAME
+        #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "")]
+        remote: Option<String>,
+        /// Only branches checked out in a worktree
+        #[arg(long)]
+        with_worktree: bool,
+        /// Only branches with a tmux session
+        #[arg(long)]
+        with_session: bool,
+        /// Only branches whose session is running a coding agent (e.g. claude, codex, aider)
+        #[arg(long)]
+        with_agent: bool,
+        /// Only branches containing this commit
+        #[arg(long, value_name = "COMMIT")]
+        contains: Option<String>,
+        /// Only branches with this label (repeat to require several)
+        #[arg(long = "label", value_name = "LABEL")]
+        labels: Vec<String>,
         /// Output result as JSON
         #[arg(long)]
         json: bool,
     },
+    /// Show what changed in a branch's worktree since it diverged from the default branch,
+    /// uncommitted changes included
+    #[command(group(clap::ArgGroup::new("diff_mode").args(["stat", "name_only", "patch"])))]
+    Diff {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Branch whose worktree to diff
+        branch: String,
+        /// Branch to diff against (default: the repo's default branch)
+        #[arg(long)]
+        base: Option<String>,
+        /// Changed files with lines added and removed (the default)
+        #[arg(long)]
+        stat: bool,
+        /// Only the paths of changed files
+        #[arg(long)]
+        name_only: bool,
+        /// The full unified diff
+        #[arg(long)]
+        patch: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print where a worktree lives, or its session name or log file, without creating anything
+    Path {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Branch name (omit for main checkout); without a worktree, where one would be created
+        branch: Option<String>,
+        /// Print the session name instead
+        #[arg(long, conflicts_with = "log")]
+        session: bool,
+        /// Print the session's log file instead
+        #[arg(long)]
+        log: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show, set or clear the note attached to a branch
+    Note {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Local branch the note is for
+        branch: String,
+        /// New note (omit to show the current one)
+        #[arg(conflicts_with = "clear")]
+        note: Option<String>,
+        /// Remove the note
+        #[arg(long)]
+        clear: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show a repo hidden from the TUI in the repo list and `kiosk list` again
+    Unhide {
+        /// Repository name (as shown by 'kiosk list --include-hidden')
+        repo: String,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add or remove labels on a branch, to filter by with `kiosk branches --label` and
+    /// `#label` in the branch picker
+    Label {
+        #[command(subcommand)]
+        command: LabelCommands,
+    },
+    /// Print a one-line summary of a session (repo, branch, uncommitted changes and agent)
+    /// for tmux's status-right or a shell prompt
+    Statusline {
+        /// Session to summarise (default: the one kiosk runs in)
+        #[arg(long)]
+        session: Option<String>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print a Waybar custom-module JSON line summarising agents waiting for input. Polybar
+    /// and similar bars can show its `text` (e.g. with `jq -r .text`)
+    Waybar,
+    /// Print every worktree kiosk created, and each main checkout with a running session, as
+    /// JSON for `kiosk import`
+    Export,
+    /// Recreate the worktrees and sessions in a file from `kiosk export`, creating branches
+    /// that don't exist from the repo's default branch at export time
+    Import {
+        /// File written by `kiosk export`, or `-` for stdin
+        file: std::path::PathBuf,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Recreate sessions kiosk set up that went away with an earlier tmux server, e.g. after a
+    /// reboot, on their branches and with their `--run` commands. Without sessions or --all,
+    /// lists them
+    Resume {
+        /// Lost sessions to recreate
+        sessions: Vec<String>,
+        /// Recreate every lost session
+        #[arg(long, conflicts_with = "sessions")]
+        all: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List workspaces, or open all of a workspace's sessions at once. Workspaces are named
+    /// sets of branches, from `[workspaces]` in the config or created from the branch list
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommands,
+    },
     /// Open or create a worktree and tmux session
     Open {
-        /// Repository name (as shown by 'kiosk list')
+        /// Repository name (as shown by 'kiosk list'), `<repo>/<branch>`, or a branch of the repo in the current directory
         repo: String,
         /// Existing branch to open (as shown by 'kiosk branches')
         branch: Option<String>,
         /// Create a new branch with this name
         #[arg(long)]
         new_branch: Option<String>,
-        /// Base branch for --new-branch
+        /// Create a new branch named after this Jira or Linear ticket (see `[ticket]` in the
+        /// config), with the ticket's link as its note
+        #[arg(long, value_name = "ID", requires = "base", conflicts_with_all = ["branch", "new_branch"])]
+        ticket: Option<String>,
+        /// Base branch for --new-branch or --ticket
         #[arg(long)]
         base: Option<String>,
+        /// Open a worktree with a detached HEAD at this commit instead of a branch, e.g. to
+        /// bisect
+        #[arg(long, value_name = "REV", conflicts_with_all = ["branch", "new_branch", "ticket", "base", "in_place"])]
+        commit: Option<String>,
+        /// Open a worktree with a detached HEAD at this tag instead of a branch, e.g. to
+        /// reproduce a release
+        #[arg(long, conflicts_with_all = ["branch", "new_branch", "ticket", "base", "in_place", "commit"])]
+        tag: Option<String>,
+        /// Remote to track the branch from when it's only on remotes, e.g. `upstream` in a
+        /// fork (needed when several remotes have it)
+        #[arg(long, value_name = "NAME", requires = "branch", conflicts_with_all = ["new_branch", "ticket", "in_place"])]
+        remote: Option<String>,
         /// Create session without switching to it (required outside tmux)
         #[arg(long)]
         no_switch: bool,
         /// Command to execute in the session after creation (typed and Enter sent automatically). Use --log to preserve output after session exit
         #[arg(long)]
         run: Option<String>,
-        /// Block until the command from --run finishes (pane returns to shell). Requires --run
-        #[arg(long, requires = "run")]
+        /// Start this agent from `[agents]` in the config in place of --run, with its output
+        /// logged unless the agent sets `log = false`
+        #[arg(long, value_name = "NAME", conflicts_with = "run")]
+        agent: Option<String>,
+        /// Block until the command from --run or --agent finishes (pane returns to shell)
+        #[arg(long)]
         wait: bool,
         /// Timeout in seconds for --wait (default: 600)
         #[arg(long, default_value_t = 600)]
         wait_timeout: u64,
-        /// Target pane index for --wait (default: 0)
-        #[arg(long, default_value_t = 0)]
-        wait_pane: usize,
+        /// Target pane for --wait: an index, a command name like `claude`, or `@title` (default: 0)
+        #[arg(long, default_value = "0")]
+        wait_pane: PaneTarget,
         /// Enable logging of session output. Logs are stored in `$XDG_STATE_HOME/kiosk/logs/` (default: `~/.local/state/kiosk/logs/`)
         #[arg(long)]
         log: bool,
+        /// Roll back any worktree, branch, or session created by this command if a later step fails
+        #[arg(long)]
+        atomic: bool,
+        /// Close all other kiosk sessions nobody is attached to, even when `session.max_alive`
+        /// would keep some of them alive
+        #[arg(long)]
+        detach_others: bool,
+        /// Check the branch out in the main checkout, under its session, instead of creating a
+        /// worktree. Uncommitted changes are stashed and reapplied on switching back
+        #[arg(long, requires = "branch", conflicts_with_all = ["new_branch", "base"])]
+        in_place: bool,
+        /// Also open the branch in each repo in the config's `linked_repos`, creating it where
+        /// it's missing, and save them all as a workspace named after the branch
+        #[arg(long, conflicts_with_all = ["commit", "tag", "detach_others"])]
+        link: bool,
+        /// Report git's progress while fetching and checking out the worktree: on stderr, or
+        /// as one JSON document per update ahead of the result with --json
+        #[arg(long)]
+        progress: bool,
+        /// Set an environment variable in a new session, replacing any from the config.
+        /// Repeatable
+        #[arg(long, value_name = "KEY=VAL", value_parser = parse_env_var)]
+        env: Vec<(String, String)>,
         /// Output result as JSON
         #[arg(long)]
         json: bool,
     },
     /// Show status for a session
     Status {
+        /// Repository name (as shown by 'kiosk list')
+        #[arg(required_unless_present = "all")]
+        repo: Option<String>,
+        /// Branch name (omit for main checkout)
+        branch: Option<String>,
+        /// Show every running kiosk session instead of one
+        #[arg(long, conflicts_with_all = ["repo", "branch"])]
+        all: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+        /// Number of lines to include in output (default: 50, or 10 per session with --all)
+        #[arg(long)]
+        lines: Option<usize>,
+        /// Target pane: an index, a command name like `claude`, or `@title` (default: 0)
+        #[arg(long, default_value = "0")]
+        pane: PaneTarget,
+        /// Remove terminal escape sequences and control characters from the output
+        #[arg(long)]
+        strip_ansi: bool,
+    },
+    /// Print raw pane contents, scrollback included, exactly as tmux captures them
+    Capture {
         /// Repository name (as shown by 'kiosk list')
         repo: String,
         /// Branch name (omit for main checkout)
         branch: Option<String>,
+        /// Target pane: an index, a command name like `claude`, or `@title` (default: 0)
+        #[arg(long, default_value = "0")]
+        pane: PaneTarget,
+        /// Start N lines back in the scrollback
+        #[arg(long, value_name = "N", conflicts_with = "start")]
+        history: Option<usize>,
+        /// First line to capture: 0 is the top of the visible pane, negative values are scrollback
+        #[arg(long, allow_negative_numbers = true)]
+        start: Option<i64>,
+        /// Last line to capture (default: the bottom of the visible pane)
+        #[arg(long, allow_negative_numbers = true)]
+        end: Option<i64>,
+        /// Keep ANSI escape sequences for colours and text attributes
+        #[arg(long)]
+        escapes: bool,
         /// Output result as JSON
         #[arg(long)]
         json: bool,
-        /// Number of lines to include in output
-        #[arg(long, default_value_t = 50)]
-        lines: usize,
-        /// Target pane index (default: 0)
-        #[arg(long, default_value_t = 0)]
-        pane: usize,
     },
     /// List active kiosk sessions
     Sessions {
@@ -116,6 +389,17 @@ enum Commands {
         #[arg(long)]
         json: bool,
     },
+    /// Show the CPU and memory used by the processes running in each session's panes
+    Top {
+        /// Only show this repository's sessions (as shown by 'kiosk list')
+        repo: Option<String>,
+        /// Sort order
+        #[arg(long, value_enum, default_value_t)]
+        sort: kiosk_cli::TopSort,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Delete a worktree and session
     Delete {
         /// Repository name (as shown by 'kiosk list')
@@ -125,6 +409,105 @@ enum Commands {
         /// Force deletion even if the session is attached
         #[arg(long)]
         force: bool,
+        /// Delete even if the worktree has uncommitted changes or unpushed commits
+        #[arg(long)]
+        force_dirty: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Restore the most recently deleted worktree, including uncommitted changes
+    Undo {
+        /// Only restore worktrees deleted from this repository
+        repo: Option<String>,
+        /// Only restore the worktree for this branch
+        branch: Option<String>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Kill kiosk sessions that have been idle for a while, e.g. from cron or a systemd timer.
+    /// Sessions with a client attached are left alone
+    Reap {
+        /// Hours without activity before a session is reaped (default: `reap.idle_hours`)
+        #[arg(long)]
+        idle_hours: Option<u64>,
+        /// Also remove the worktree of a reaped session whose branch is merged into the
+        /// default branch (also set by `reap.remove_merged`)
+        #[arg(long)]
+        remove_merged: bool,
+        /// List what would be reaped without killing or removing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete local branches that have no worktree and no commits missing from the default
+    /// branch or a remote, e.g. after `clean` or `reap` removed their worktrees
+    PruneBranches {
+        /// Only prune branches of this repository (as shown by 'kiosk list')
+        repo: Option<String>,
+        /// List the branches that would be deleted without deleting them
+        #[arg(long)]
+        dry_run: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List worktree creates and deletes left unfinished by a kiosk that crashed or was killed,
+    /// and finish or undo them
+    Recover {
+        /// Finish each operation: add the worktree being created, or remove the one being deleted
+        #[arg(long, conflicts_with = "back")]
+        forward: bool,
+        /// Undo each operation: discard the partial worktree, or restore the one being deleted
+        /// with its uncommitted changes
+        #[arg(long)]
+        back: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the worktrees, sessions and keys kiosk has changed or sent, oldest first, with the
+    /// command that did it
+    History {
+        /// Only show operations on this repository (as shown by 'kiosk list'), its worktrees and
+        /// their sessions
+        repo: Option<String>,
+        /// Only show this many of the most recent operations
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Clone a repository into a search directory, where kiosk finds it straight away
+    Clone {
+        /// URL of the repository to clone
+        url: String,
+        /// Search directory to clone into (default: the first in `search_dirs`)
+        #[arg(long)]
+        dir: Option<std::path::PathBuf>,
+        /// Open a session in the clone (created without switching to it outside tmux)
+        #[arg(long)]
+        open: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Fetch the remotes of a repository, or of every one with --all, e.g. from cron so the
+    /// branch picker has fresh remote branches without waiting on a fetch
+    Fetch {
+        /// Repository name (as shown by 'kiosk list')
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        repo: Option<String>,
+        /// Fetch every repository
+        #[arg(long)]
+        all: bool,
+        /// Delete remote-tracking branches that are gone from their remote
+        #[arg(long)]
+        prune: bool,
         /// Output result as JSON
         #[arg(long)]
         json: bool,
@@ -133,8 +516,9 @@ enum Commands {
     #[command(group(
         clap::ArgGroup::new("send_mode")
             .required(true)
-            .args(["command", "keys", "text"])
+            .args(["command", "keys", "text", "stdin", "file"])
     ))]
+    #[command(group(clap::ArgGroup::new("paste_source").args(["stdin", "file"])))]
     Send {
         /// Repository name (as shown by 'kiosk list')
         repo: String,
@@ -149,9 +533,22 @@ enum Commands {
         /// Send literal text WITHOUT auto-appending Enter
         #[arg(long)]
         text: Option<String>,
-        /// Target pane index (default: 0)
-        #[arg(long, default_value_t = 0)]
-        pane: usize,
+        /// Read text from stdin and paste it as one block (bracketed paste), then press Enter
+        #[arg(long)]
+        stdin: bool,
+        /// Paste the contents of a file as one block (bracketed paste), then press Enter
+        #[arg(long, value_name = "PATH")]
+        file: Option<std::path::PathBuf>,
+        /// With --stdin or --file, paste without pressing Enter
+        #[arg(long, requires = "paste_source")]
+        no_enter: bool,
+        /// Capture the pane first and send nothing unless its visible contents, without trailing
+        /// whitespace, match this regex: e.g. '\$\z' for a shell prompt on the last line
+        #[arg(long, value_name = "REGEX")]
+        confirm_pattern: Option<String>,
+        /// Target pane: an index, a command name like `claude`, or `@title` (default: 0)
+        #[arg(long, default_value = "0")]
+        pane: PaneTarget,
         /// Output result as JSON
         #[arg(long)]
         json: bool,
@@ -166,6 +563,86 @@ enum Commands {
         #[arg(long)]
         json: bool,
     },
+    /// Show the processes running in a session's panes, each under the one that started it,
+    /// to see what an agent or script is actually running
+    Ps {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Branch name (omit for main checkout)
+        branch: Option<String>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Stop what an agent is doing without quitting it, sending the cancel key it expects
+    /// (Escape for claude, codex and others, Ctrl-C otherwise)
+    Interrupt {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Branch name (omit for main checkout)
+        branch: Option<String>,
+        /// Pane to interrupt: an index, a command name, or `@title` (default: the agent's
+        /// pane, or pane 0 without an agent)
+        #[arg(long)]
+        pane: Option<PaneTarget>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Close a session's pane, ending whatever runs in it, e.g. an agent that won't stop
+    KillPane {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Branch name (omit for main checkout)
+        branch: Option<String>,
+        /// Pane to close: an index, a command name, or `@title` (default: the agent's pane)
+        #[arg(long)]
+        pane: Option<PaneTarget>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Approve what a session's agent is asking permission for, with the key that agent
+    /// expects. Fails unless the agent's permission prompt is on screen
+    Approve {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Branch name (omit for main checkout)
+        branch: Option<String>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Deny what a session's agent is asking permission for, with the key that agent expects.
+    /// Fails unless the agent's permission prompt is on screen
+    Deny {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Branch name (omit for main checkout)
+        branch: Option<String>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the latest conversation an agent had in a worktree, from Claude Code's or
+    /// Codex's own transcripts, as markdown or JSON
+    Transcript {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Branch name (omit for main checkout)
+        branch: Option<String>,
+        /// Only look for this agent's transcripts (default: whichever is newest)
+        #[arg(long, value_enum)]
+        agent: Option<kiosk_cli::TranscriptSource>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Record and report how long agents spend working and waiting for input
+    Agents {
+        #[command(subcommand)]
+        command: AgentsCommands,
+    },
     /// Wait until a session pane appears idle
     Wait {
         /// Repository name (as shown by 'kiosk list')
@@ -175,9 +652,9 @@ enum Commands {
         /// Timeout in seconds (default: 600)
         #[arg(long, default_value_t = 600)]
         timeout: u64,
-        /// Target pane index (default: 0)
-        #[arg(long, default_value_t = 0)]
-        pane: usize,
+        /// Target pane: an index, a command name like `claude`, or `@title` (default: 0)
+        #[arg(long, default_value = "0")]
+        pane: PaneTarget,
         /// Output result as JSON
         #[arg(long)]
         json: bool,
@@ -185,21 +662,65 @@ enum Commands {
     /// Read log files from a session
     Log {
         /// Repository name (as shown by 'kiosk list')
-        repo: String,
+        #[arg(required_unless_present_any = ["list", "purge"])]
+        repo: Option<String>,
         /// Branch name (omit for main checkout)
         branch: Option<String>,
         /// Show last N lines (default: 50)
         #[arg(long, default_value_t = 50)]
         tail: usize,
+        /// Keep printing lines as they are appended to the log
+        #[arg(short, long, conflicts_with_all = ["list", "purge"])]
+        follow: bool,
+        /// Only show lines matching this regular expression
+        #[arg(long, value_name = "PATTERN", conflicts_with_all = ["list", "purge"])]
+        grep: Option<String>,
+        /// Remove terminal escape sequences and control characters (applied before --grep)
+        #[arg(long)]
+        strip_ansi: bool,
+        /// Prefix each line with the time it was written (RFC 3339, UTC). Only lines written
+        /// from now on are shown, as the log doesn't record when earlier ones were
+        #[arg(long, requires = "follow")]
+        timestamps: bool,
+        /// List all session log files, including rotated ones
+        #[arg(long, conflicts_with = "purge")]
+        list: bool,
+        /// Delete session logs: all of them, or only those for the given repo/branch
+        #[arg(long)]
+        purge: bool,
         /// Output result as JSON
         #[arg(long)]
         json: bool,
     },
+    /// Choose a repo or worktree in the TUI and print its path instead of opening a session,
+    /// e.g. `cd "$(kiosk pick)"`. Exits 1 if nothing is picked
+    Pick {
+        /// Only offer repos
+        #[arg(long, conflicts_with = "branches")]
+        repos: bool,
+        /// Only offer branches; one without a worktree gets one created
+        #[arg(long)]
+        branches: bool,
+    },
     /// Show configuration
     Config {
         #[command(subcommand)]
         command: Option<ConfigCommands>,
     },
+    /// Print the JSON Schema for a command's `--json` output, including the versioned envelope
+    Schema {
+        /// Command to describe
+        #[arg(value_enum)]
+        command: kiosk_cli::SchemaCommand,
+    },
+    /// List plugins: executables named `kiosk-<name>` on PATH, which run as `kiosk <name>`
+    Plugins {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    #[command(external_subcommand)]
+    Plugin(Vec<String>),
 }
 
 #[derive(Subcommand)]
@@ -210,23 +731,169 @@ enum ConfigCommands {
         #[arg(long)]
         json: bool,
     },
+    /// Print the path of the config file kiosk reads
+    Path {
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Open the config file in $VISUAL or $EDITOR, creating it if it doesn't exist
+    Edit,
+    /// Check a config file for syntax errors, unknown keys, invalid keybindings and missing
+    /// search directories. Exits non-zero when problems are found
+    Validate {
+        /// Config file to check (default: the file kiosk would load)
+        #[arg(long)]
+        file: Option<std::path::PathBuf>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AgentsCommands {
+    /// Sample what the agent in every kiosk session is doing until interrupted, e.g. in a
+    /// spare tmux window, keeping 30 days of history for `kiosk agents report` and running the
+    /// `[agent]` hooks
+    Record {
+        /// Seconds between samples
+        #[arg(long, default_value_t = 10)]
+        interval: u64,
+        /// Take a single sample and exit, e.g. from cron
+        #[arg(long)]
+        once: bool,
+    },
+    /// Show how long each branch's agent spent working, waiting for input and idle
+    Report {
+        /// How far back to report on, e.g. `30m`, `24h` or `7d`
+        #[arg(long, default_value = "24h", value_parser = parse_duration)]
+        since: u64,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkspaceCommands {
+    /// List workspaces and their members
+    List {
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Open a session for every member of a workspace, then switch to the first one
+    Open {
+        /// Workspace name (as shown by 'kiosk workspace list')
+        name: String,
+        /// Open the sessions without switching to the first one
+        #[arg(long)]
+        no_switch: bool,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum LabelCommands {
+    /// Attach labels to a branch
+    Add {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Local branch to label
+        branch: String,
+        /// Labels to add: single words such as `agent`
+        #[arg(required = true)]
+        labels: Vec<String>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Take labels off a branch
+    Remove {
+        /// Repository name (as shown by 'kiosk list')
+        repo: String,
+        /// Local branch to take the labels off
+        branch: String,
+        /// Labels to remove
+        #[arg(required = true)]
+        labels: Vec<String>,
+        /// Output result as JSON
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 impl Commands {
     fn wants_json(&self) -> bool {
         match self {
             Self::Clean { json, .. }
-            | Self::List { json }
+            | Self::List { json, .. }
             | Self::Branches { json, .. }
+            | Self::Diff { json, .. }
+            | Self::Path { json, .. }
+            | Self::Note { json, .. }
+            | Self::Unhide { json, .. }
+            | Self::Statusline { json, .. }
             | Self::Open { json, .. }
             | Self::Status { json, .. }
+            | Self::Capture { json, .. }
             | Self::Sessions { json }
+            | Self::Top { json, .. }
             | Self::Delete { json, .. }
+            | Self::Undo { json, .. }
+            | Self::Reap { json, .. }
+            | Self::PruneBranches { json, .. }
+            | Self::Recover { json, .. }
+            | Self::History { json, .. }
+            | Self::Clone { json, .. }
+            | Self::Fetch { json, .. }
+            | Self::Import { json, .. }
+            | Self::Resume { json, .. }
             | Self::Send { json, .. }
             | Self::Panes { json, .. }
+            | Self::Ps { json, .. }
+            | Self::Interrupt { json, .. }
+            | Self::KillPane { json, .. }
+            | Self::Approve { json, .. }
+            | Self::Deny { json, .. }
+            | Self::Transcript { json, .. }
             | Self::Wait { json, .. }
-            | Self::Log { json, .. } => *json,
+            | Self::Log { json, .. }
+            | Self::Plugins { json } => *json,
             Self::Config { command } => command.as_ref().is_some_and(ConfigCommands::wants_json),
+            Self::Label { command } => command.wants_json(),
+            Self::Workspace { command } => command.wants_json(),
+            Self::Agents { command } => command.wants_json(),
+            Self::Export => true,
+            Self::Pick { .. } | Self::Schema { .. } | Self::Waybar | Self::Plugin(_) => false,
+        }
+    }
+}
+
+impl LabelCommands {
+    fn wants_json(&self) -> bool {
+        match self {
+            Self::Add { json, .. } | Self::Remove { json, .. } => *json,
+        }
+    }
+}
+
+impl WorkspaceCommands {
+    fn wants_json(&self) -> bool {
+        match self {
+            Self::List { json } | Self::Open { json, .. } => *json,
+        }
+    }
+}
+
+impl AgentsCommands {
+    fn wants_json(&self) -> bool {
+        match self {
+            Self::Record { .. } => false,
+            Self::Report { json, .. } => *json,
         }
     }
 }
@@ -234,155 +901,737 @@ impl Commands {
 impl ConfigCommands {
     fn wants_json(&self) -> bool {
         match self {
-            Self::Show { json } => *json,
+            Self::Show { json } | Self::Path { json } | Self::Validate { json, .. } => *json,
+            Self::Edit => false,
         }
     }
 }
 
+/// A duration such as `90s`, `30m`, `24h` or `7d`, in seconds.
+fn parse_duration(duration: &str) -> Result<u64, String> {
+    let invalid = || format!("expected a duration like 30m, 24h or 7d, got '{duration}'");
+    let unit = duration.chars().last().ok_or_else(invalid)?;
+    let scale = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86_400,
+        _ => return Err(invalid()),
+    };
+    duration[..duration.len() - unit.len_utf8()]
+        .parse::<u64>()
+        .map(|number| number.saturating_mul(scale))
+        .map_err(|_| invalid())
+}
+
+fn parse_template(template: &str) -> Result<OutputFormat, String> {
+    template.parse().map(OutputFormat::Template)
+}
+
+fn parse_env_var(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VAL, got '{var}'")),
+    }
+}
+
 fn main() -> ExitCode {
     let cli = Cli::parse();
-    let json_errors = command_wants_json(cli.command.as_ref());
+    let json_errors =
+        cli.format == Some(OutputFormat::Json) || command_wants_json(cli.command.as_ref());
 
-    if let Err(e) = logging::setup_logging(cli.log_level)
+    if let Err(e) = logging::setup_logging(logging::effective_level(cli.log_level, cli.verbose))
         && !json_errors
     {
         eprintln!("Warning: failed to initialise logging: {e}");
     }
 
+    // Schemas describe kiosk itself, so they don't need a config file
+    if let Some(Commands::Schema { command }) = cli.command {
+        return exit_code(crate::cli::cmd_schema(command), json_errors);
+    }
+    if let Some(Commands::Plugins { json }) = cli.command {
+        let format = OutputFormat::resolve(cli.template.or(cli.format).as_ref(), json);
+        let args = kiosk_cli::PluginsArgs { format };
+        return exit_code(crate::cli::cmd_plugins(&args), json_errors);
+    }
+
+    // These work on the config file itself, so they must not require it to exist or load
+    if let Some(Commands::Config {
+        command:
+            Some(
+                command @ (ConfigCommands::Path { .. }
+                | ConfigCommands::Edit
+                | ConfigCommands::Validate { .. }),
+            ),
+    }) = cli.command
+    {
+        let path = config::config_file_path(cli.config.as_deref());
+        let format = cli.template.or(cli.format);
+        return exit_code(
+            dispatch_config_file_command(command, path, format.as_ref()),
+            json_errors,
+        );
+    }
+
     // No explicit --config, default doesn't exist, TUI mode → setup wizard
     if cli.config.is_none() && cli.command.is_none() && !config::config_file_exists() {
-        return run_setup_then_tui();
+        return run_setup_then_tui(cli.tmux_socket);
     }
 
-    let config = match config::load_config(cli.config.as_deref()) {
+    let profile = cli
+        .profile
+        .clone()
+        .or_else(|| std::env::var("KIOSK_PROFILE").ok())
+        .filter(|profile| !profile.is_empty());
+    let mut config = match config::load_config(cli.config.as_deref(), profile.as_deref()) {
         Ok(config) => config,
         Err(error) => {
-            let cli_error = crate::cli::CliError::system(error.to_string());
+            let mut message = error.to_string();
+            if config::config_file_path(cli.config.as_deref()).exists() {
+                message.push_str("\nRun `kiosk config validate` to list every problem");
+            }
+            let cli_error = kiosk_cli::CliError::system(message);
             crate::cli::print_error(&cli_error, json_errors);
             return ExitCode::from(2);
         }
     };
 
-    let git: Arc<dyn GitProvider> = Arc::new(CliGitProvider);
-    let tmux: Arc<dyn TmuxProvider> = Arc::new(CliTmuxProvider);
+    if let Some(socket) = cli.tmux_socket {
+        config.tmux.socket = Some(socket);
+    }
+    let runtime = Runtime::builder(config).audit(AuditLog::default()).build();
+
+    let format = cli.template.or(cli.format);
+    let confirm = crate::cli::terminal_confirm(cli.yes);
+    let config_source = config::ConfigSource {
+        path: config::config_file_path(cli.config.as_deref()),
+        profile,
+    };
+    if let Some(Commands::Plugin(args)) = cli.command {
+        return run_plugin(&runtime, config_source.path, args, json_errors);
+    }
+    let result = dispatch_command(
+        cli.command,
+        format.as_ref(),
+        &confirm,
+        &runtime,
+        &config_source,
+    );
+    exit_code(result, json_errors)
+}
+
+/// Run a plugin, exiting with its exit code.
+fn run_plugin(
+    runtime: &Runtime,
+    config_path: std::path::PathBuf,
+    mut args: Vec<String>,
+    json_errors: bool,
+) -> ExitCode {
+    let name = args.remove(0);
+    let args = kiosk_cli::PluginArgs {
+        name,
+        args,
+        config_path,
+    };
+    match runtime.run(|config, git, tmux| kiosk_cli::plugin(config, git, tmux, &args)) {
+        Ok(code) => ExitCode::from(u8::try_from(code).unwrap_or(1)),
+        Err(error) => exit_code(Err(error), json_errors),
+    }
+}
 
-    let result = dispatch_command(cli.command, &config, &git, &tmux);
+fn dispatch_config_file_command(
+    command: ConfigCommands,
+    path: std::path::PathBuf,
+    format: Option<&OutputFormat>,
+) -> kiosk_cli::CliResult<()> {
+    let output = |json| OutputFormat::resolve(format, json);
+    match command {
+        ConfigCommands::Path { json } => crate::cli::cmd_config_path(&kiosk_cli::ConfigPathArgs {
+            path,
+            format: output(json),
+        }),
+        ConfigCommands::Edit => crate::cli::cmd_config_edit(&kiosk_cli::ConfigEditArgs { path }),
+        ConfigCommands::Validate { file, json } => {
+            crate::cli::cmd_config_validate(&kiosk_cli::ConfigValidateArgs {
+                file: file.unwrap_or(path),
+                format: output(json),
+            })
+        }
+        ConfigCommands::Show { .. } => {
+            unreachable!("config show needs the loaded config")
+        }
+    }
+}
 
+fn exit_code(result: kiosk_cli::CliResult<()>, json_errors: bool) -> ExitCode {
     match result {
         Ok(()) => ExitCode::from(0),
         Err(error) => {
             crate::cli::print_error(&error, json_errors);
-            let code: u8 = match error.code() {
-                1 => 1,
-                _ => 2,
+            ExitCode::from(u8::try_from(error.code()).unwrap_or(2))
+        }
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn dispatch_command(
+    command: Option<Commands>,
+    format: Option<&OutputFormat>,
+    confirm: &kiosk_cli::Confirm,
+    runtime: &Runtime,
+    config_source: &config::ConfigSource,
+) -> kiosk_cli::CliResult<()> {
+    let (config, git, tmux) = (runtime.config(), runtime.git(), runtime.tmux());
+    let output = |json| OutputFormat::resolve(format, json);
+    match command {
+        Some(Commands::Clean { dry_run, json }) => {
+            let search_dirs = config.resolved_search_dirs();
+            clean_orphaned_worktrees(
+                &search_dirs,
+                git.as_ref(),
+                dry_run,
+                confirm,
+                &output(json),
+                &config.clean,
+            )
+            .map_err(kiosk_cli::CliError::from)
+        }
+        Some(Commands::List {
+            filter,
+            path_under,
+            sort,
+            with_sessions_only,
+            pinned,
+            include_hidden,
+            json,
+        }) => {
+            let args = kiosk_cli::ListArgs {
+                filter,
+                path_under,
+                sort,
+                with_sessions_only,
+                pinned,
+                include_hidden,
+                format: output(json),
+            };
+            crate::cli::cmd_list(config, git.as_ref(), tmux.as_ref(), &args)
+        }
+        Some(Commands::Branches {
+            repo,
+            local,
+            remote,
+            with_worktree,
+            with_session,
+            with_agent,
+            contains,
+            labels,
+            json,
+        }) => {
+            let args = kiosk_cli::BranchesArgs {
+                repo,
+                local,
+                remote: remote.is_some(),
+                remote_name: remote.filter(|name| !name.is_empty()),
+                with_worktree,
+                with_session,
+                with_agent,
+                contains,
+                labels,
+                format: output(json),
+            };
+            crate::cli::cmd_branches(config, git.as_ref(), tmux.as_ref(), &args)
+        }
+        Some(Commands::Path {
+            repo,
+            branch,
+            session,
+            log,
+            json,
+        }) => {
+            let kind = if session {
+                kiosk_cli::PathKind::Session
+            } else if log {
+                kiosk_cli::PathKind::Log
+            } else {
+                kiosk_cli::PathKind::Worktree
+            };
+            let args = kiosk_cli::PathArgs {
+                repo,
+                branch,
+                kind,
+                format: output(json),
+            };
+            crate::cli::cmd_path(config, git.as_ref(), &args)
+        }
+        Some(Commands::Note {
+            repo,
+            branch,
+            note,
+            clear,
+            json,
+        }) => {
+            let args = kiosk_cli::NoteArgs {
+                repo,
+                branch,
+                note: if clear { Some(String::new()) } else { note },
+                format: output(json),
+            };
+            crate::cli::cmd_note(config, git.as_ref(), &args)
+        }
+        Some(Commands::Unhide { repo, json }) => {
+            let args = kiosk_cli::UnhideArgs {
+                repo,
+                format: output(json),
+            };
+            crate::cli::cmd_unhide(config, git.as_ref(), &args)
+        }
+        Some(Commands::Label { command }) => {
+            let (change, repo, branch, labels, json) = match command {
+                LabelCommands::Add {
+                    repo,
+                    branch,
+                    labels,
+                    json,
+                } => (kiosk_cli::LabelChange::Add, repo, branch, labels, json),
+                LabelCommands::Remove {
+                    repo,
+                    branch,
+                    labels,
+                    json,
+                } => (kiosk_cli::LabelChange::Remove, repo, branch, labels, json),
+            };
+            let args = kiosk_cli::LabelArgs {
+                repo,
+                branch,
+                change,
+                labels,
+                format: output(json),
+            };
+            crate::cli::cmd_label(config, git.as_ref(), &args)
+        }
+        Some(Commands::Export) => crate::cli::cmd_export(config, git.as_ref(), tmux.as_ref()),
+        Some(Commands::Import { file, json }) => {
+            let stdin = if file.as_os_str() == "-" {
+                Some(crate::cli::read_stdin()?)
+            } else {
+                None
+            };
+            let args = kiosk_cli::ImportArgs {
+                file,
+                stdin,
+                format: output(json),
+            };
+            crate::cli::cmd_import(config, git.as_ref(), tmux.as_ref(), &args)
+        }
+        Some(Commands::Resume {
+            sessions,
+            all,
+            json,
+        }) => {
+            let args = kiosk_cli::ResumeArgs {
+                sessions,
+                all,
+                format: output(json),
+            };
+            crate::cli::cmd_resume(config, git.as_ref(), tmux.as_ref(), &args)
+        }
+        Some(Commands::Workspace { command }) => match command {
+            WorkspaceCommands::List { json } => {
+                let args = kiosk_cli::WorkspaceListArgs {
+                    format: output(json),
+                };
+                crate::cli::cmd_workspace_list(config, &args)
+            }
+            WorkspaceCommands::Open {
+                name,
+                no_switch,
+                json,
+            } => {
+                let args = kiosk_cli::WorkspaceOpenArgs {
+                    name,
+                    no_switch,
+                    format: output(json),
+                };
+                crate::cli::cmd_workspace_open(config, git.as_ref(), tmux.as_ref(), &args)
+            }
+        },
+        Some(Commands::Waybar) => crate::cli::cmd_waybar(config, git.as_ref(), tmux.as_ref()),
+        Some(Commands::Statusline { session, json }) => {
+            let args = kiosk_cli::StatuslineArgs {
+                session,
+                format: output(json),
             };
-            ExitCode::from(code)
-        }
-    }
-}
-
-#[allow(clippy::too_many_lines)]
-fn dispatch_command(
-    command: Option<Commands>,
-    config: &config::Config,
-    git: &Arc<dyn GitProvider>,
-    tmux: &Arc<dyn TmuxProvider>,
-) -> crate::cli::CliResult<()> {
-    match command {
-        Some(Commands::Clean { dry_run, yes, json }) => {
-            let search_dirs = config.resolved_search_dirs();
-            clean_orphaned_worktrees(&search_dirs, git.as_ref(), dry_run, yes, json)
-                .map_err(crate::cli::CliError::from)
+            crate::cli::cmd_statusline(config, git.as_ref(), tmux.as_ref(), &args)
         }
-        Some(Commands::List { json }) => crate::cli::cmd_list(config, git.as_ref(), json),
-        Some(Commands::Branches { repo, json }) => {
-            crate::cli::cmd_branches(config, git.as_ref(), tmux.as_ref(), &repo, json)
+        Some(Commands::Diff {
+            repo,
+            branch,
+            base,
+            stat: _,
+            name_only,
+            patch,
+            json,
+        }) => {
+            let mode = if name_only {
+                kiosk_cli::DiffMode::NameOnly
+            } else if patch {
+                kiosk_cli::DiffMode::Patch
+            } else {
+                kiosk_cli::DiffMode::Stat
+            };
+            let args = kiosk_cli::DiffArgs {
+                repo,
+                branch,
+                base,
+                mode,
+                format: output(json),
+            };
+            crate::cli::cmd_diff(config, git.as_ref(), &args)
         }
         Some(Commands::Open {
             repo,
             branch,
             new_branch,
+            ticket,
             base,
+            commit,
+            tag,
+            remote,
             no_switch,
             run,
+            agent,
             wait,
             wait_timeout,
             wait_pane,
             log,
+            atomic,
+            detach_others,
+            in_place,
+            link,
+            progress,
+            env,
             json,
         }) => {
-            let args = crate::cli::OpenArgs {
+            let args = kiosk_cli::OpenArgs {
                 repo,
                 branch,
                 new_branch,
+                ticket,
                 base,
+                detached: commit
+                    .map(kiosk_cli::DetachedTarget::Commit)
+                    .or(tag.map(kiosk_cli::DetachedTarget::Tag)),
+                remote,
                 no_switch,
                 run,
+                split_command: None,
+                agent,
                 wait,
                 wait_timeout,
                 wait_pane,
                 log,
-                json,
+                atomic,
+                detach_others,
+                in_place,
+                link,
+                progress,
+                env,
+                format: output(json),
             };
             crate::cli::cmd_open(config, git.as_ref(), tmux.as_ref(), &args)
         }
         Some(Commands::Status {
             repo,
             branch,
+            all,
             json,
             lines,
             pane,
+            strip_ansi,
         }) => {
-            let args = crate::cli::StatusArgs {
-                repo,
+            if all {
+                let args = kiosk_cli::StatusAllArgs {
+                    lines: lines.unwrap_or(10),
+                    pane,
+                    strip_ansi,
+                    format: output(json),
+                };
+                return crate::cli::cmd_status_all(config, git.as_ref(), tmux.as_ref(), &args);
+            }
+            let args = kiosk_cli::StatusArgs {
+                repo: repo.unwrap_or_default(),
                 branch,
-                json,
-                lines,
+                format: output(json),
+                lines: lines.unwrap_or(50),
                 pane,
+                strip_ansi,
             };
             crate::cli::cmd_status(config, git.as_ref(), tmux.as_ref(), &args)
         }
+        Some(Commands::Capture {
+            repo,
+            branch,
+            pane,
+            history,
+            start,
+            end,
+            escapes,
+            json,
+        }) => {
+            let args = kiosk_cli::CaptureArgs {
+                repo,
+                branch,
+                pane,
+                history,
+                start,
+                end,
+                escapes,
+                format: output(json),
+            };
+            crate::cli::cmd_capture(config, git.as_ref(), tmux.as_ref(), &args)
+        }
         Some(Commands::Send {
             repo,
             branch,
             command,
             keys,
             text,
+            stdin,
+            file,
+            no_enter,
+            confirm_pattern,
             pane,
             json,
         }) => {
-            let args = crate::cli::SendArgs {
+            let args = kiosk_cli::SendArgs {
                 repo,
                 branch,
                 command,
                 keys,
                 text,
+                stdin: stdin.then(crate::cli::read_stdin).transpose()?,
+                file,
+                no_enter,
+                confirm_pattern,
                 pane,
-                json,
+                format: output(json),
             };
             crate::cli::cmd_send(config, git.as_ref(), tmux.as_ref(), &args)
         }
         Some(Commands::Sessions { json }) => {
-            crate::cli::cmd_sessions(config, git.as_ref(), tmux.as_ref(), json)
+            crate::cli::cmd_sessions(config, git.as_ref(), tmux.as_ref(), &output(json))
+        }
+        Some(Commands::Top { repo, sort, json }) => {
+            let args = kiosk_cli::TopArgs {
+                repo,
+                sort,
+                format: output(json),
+            };
+            crate::cli::cmd_top(config, git.as_ref(), tmux.as_ref(), &args)
         }
         Some(Commands::Delete {
             repo,
             branch,
             force,
+            force_dirty,
             json,
         }) => {
-            let args = crate::cli::DeleteArgs {
+            let args = kiosk_cli::DeleteArgs {
                 repo,
                 branch,
                 force,
-                json,
+                force_dirty,
+                format: output(json),
+            };
+            crate::cli::cmd_delete(config, git.as_ref(), tmux.as_ref(), &args, confirm)
+        }
+        Some(Commands::Undo { repo, branch, json }) => {
+            let args = kiosk_cli::UndoArgs {
+                repo,
+                branch,
+                format: output(json),
+            };
+            crate::cli::cmd_undo(config, git.as_ref(), &args)
+        }
+        Some(Commands::Reap {
+            idle_hours,
+            remove_merged,
+            dry_run,
+            json,
+        }) => {
+            let args = kiosk_cli::ReapArgs {
+                idle_hours: idle_hours.unwrap_or(config.reap.idle_hours),
+                remove_merged: remove_merged || config.reap.remove_merged,
+                dry_run,
+                format: output(json),
+            };
+            crate::cli::cmd_reap(config, git.as_ref(), tmux.as_ref(), &args, confirm)
+        }
+        Some(Commands::PruneBranches {
+            repo,
+            dry_run,
+            json,
+        }) => {
+            let args = kiosk_cli::PruneBranchesArgs {
+                repo,
+                dry_run,
+                format: output(json),
+            };
+            crate::cli::cmd_prune_branches(config, git.as_ref(), &args, confirm)
+        }
+        Some(Commands::Recover {
+            forward,
+            back,
+            json,
+        }) => {
+            let direction = if forward {
+                Some(kiosk_cli::RecoverDirection::Forward)
+            } else if back {
+                Some(kiosk_cli::RecoverDirection::Back)
+            } else {
+                None
+            };
+            let args = kiosk_cli::RecoverArgs {
+                direction,
+                format: output(json),
+            };
+            crate::cli::cmd_recover(config, git.as_ref(), &args, confirm)
+        }
+        Some(Commands::History { repo, limit, json }) => {
+            let args = kiosk_cli::HistoryArgs {
+                repo,
+                limit,
+                format: output(json),
             };
-            crate::cli::cmd_delete(config, git.as_ref(), tmux.as_ref(), &args)
+            crate::cli::cmd_history(config, git.as_ref(), &args)
+        }
+        Some(Commands::Fetch {
+            repo,
+            all: _,
+            prune,
+            json,
+        }) => {
+            let args = kiosk_cli::FetchArgs {
+                repo,
+                prune,
+                format: output(json),
+            };
+            crate::cli::cmd_fetch(config, git.as_ref(), &args)
+        }
+        Some(Commands::Clone {
+            url,
+            dir,
+            open,
+            json,
+        }) => {
+            let args = kiosk_cli::CloneArgs {
+                url,
+                dir,
+                open,
+                format: output(json),
+            };
+            crate::cli::cmd_clone(config, git.as_ref(), tmux.as_ref(), &args)
         }
         Some(Commands::Panes { repo, branch, json }) => {
-            let args = crate::cli::PanesArgs { repo, branch, json };
+            let args = kiosk_cli::PanesArgs {
+                repo,
+                branch,
+                format: output(json),
+            };
             crate::cli::cmd_panes(config, git.as_ref(), tmux.as_ref(), &args)
         }
+        Some(Commands::Ps { repo, branch, json }) => {
+            let args = kiosk_cli::PsArgs {
+                repo,
+                branch,
+                format: output(json),
+            };
+            crate::cli::cmd_ps(config, git.as_ref(), tmux.as_ref(), &args)
+        }
+        Some(Commands::Interrupt {
+            repo,
+            branch,
+            pane,
+            json,
+        }) => {
+            let args = kiosk_cli::InterruptArgs {
+                repo,
+                branch,
+                pane,
+                format: output(json),
+            };
+            crate::cli::cmd_interrupt(config, git.as_ref(), tmux.as_ref(), &args)
+        }
+        Some(Commands::KillPane {
+            repo,
+            branch,
+            pane,
+            json,
+        }) => {
+            let args = kiosk_cli::KillPaneArgs {
+                repo,
+                branch,
+                pane,
+                format: output(json),
+            };
+            crate::cli::cmd_kill_pane(config, git.as_ref(), tmux.as_ref(), &args)
+        }
+        Some(Commands::Approve { repo, branch, json }) => {
+            let args = kiosk_cli::AnswerArgs {
+                repo,
+                branch,
+                format: output(json),
+            };
+            crate::cli::cmd_answer(
+                config,
+                git.as_ref(),
+                tmux.as_ref(),
+                &args,
+                PromptAnswer::Approve,
+            )
+        }
+        Some(Commands::Deny { repo, branch, json }) => {
+            let args = kiosk_cli::AnswerArgs {
+                repo,
+                branch,
+                format: output(json),
+            };
+            crate::cli::cmd_answer(
+                config,
+                git.as_ref(),
+                tmux.as_ref(),
+                &args,
+                PromptAnswer::Deny,
+            )
+        }
+        Some(Commands::Transcript {
+            repo,
+            branch,
+            agent,
+            json,
+        }) => {
+            let args = kiosk_cli::TranscriptArgs {
+                repo,
+                branch,
+                agent,
+                format: output(json),
+            };
+            crate::cli::cmd_transcript(config, git.as_ref(), &args)
+        }
+        Some(Commands::Agents { command }) => match command {
+            AgentsCommands::Record { interval, once } => {
+                let args = kiosk_cli::AgentsRecordArgs { interval, once };
+                crate::cli::cmd_agents_record(config, git.as_ref(), tmux.as_ref(), &args)
+            }
+            AgentsCommands::Report { since, json } => {
+                let args = kiosk_cli::AgentsReportArgs {
+                    since,
+                    format: output(json),
+                };
+                crate::cli::cmd_agents_report(&args)
+            }
+        },
         Some(Commands::Wait {
             repo,
             branch,
@@ -390,12 +1639,12 @@ fn dispatch_command(
             pane,
             json,
         }) => {
-            let args = crate::cli::WaitArgs {
+            let args = kiosk_cli::WaitArgs {
                 repo,
                 branch,
                 timeout,
                 pane,
-                json,
+                format: output(json),
             };
             crate::cli::cmd_wait(config, git.as_ref(), tmux.as_ref(), &args)
         }
@@ -403,37 +1652,354 @@ fn dispatch_command(
             repo,
             branch,
             tail,
+            follow,
+            grep,
+            strip_ansi,
+            timestamps,
+            list,
+            purge,
             json,
         }) => {
-            let args = crate::cli::LogArgs {
-                repo,
+            if list {
+                return crate::cli::cmd_log_list(
+                    config,
+                    &kiosk_cli::LogListArgs {
+                        format: output(json),
+                    },
+                );
+            }
+            if purge {
+                let args = kiosk_cli::LogPurgeArgs {
+                    repo,
+                    branch,
+                    format: output(json),
+                };
+                return crate::cli::cmd_log_purge(config, git.as_ref(), &args);
+            }
+            let args = kiosk_cli::LogArgs {
+                repo: repo.unwrap_or_default(),
                 branch,
                 tail,
-                json,
+                follow,
+                grep,
+                line_format: LineFormat {
+                    strip_ansi,
+                    timestamps,
+                },
+                format: output(json),
             };
             crate::cli::cmd_log(config, git.as_ref(), tmux.as_ref(), &args)
         }
         Some(Commands::Config { command }) => match command {
             Some(ConfigCommands::Show { json }) => {
-                let args = crate::cli::ConfigShowArgs { json };
+                let args = kiosk_cli::ConfigShowArgs {
+                    format: output(json),
+                };
                 crate::cli::cmd_config_show(config, &args)
             }
+            Some(
+                ConfigCommands::Path { .. }
+                | ConfigCommands::Edit
+                | ConfigCommands::Validate { .. },
+            ) => {
+                unreachable!("config file commands run before the config is loaded")
+            }
             None => {
                 eprintln!("config subcommand required. Use --help for usage.");
-                Err(crate::cli::CliError::user("config subcommand required"))
+                Err(kiosk_cli::CliError::user("config subcommand required"))
             }
         },
-        None => run_tui(config, git, tmux).map_err(crate::cli::CliError::from),
+        Some(Commands::Schema { command }) => crate::cli::cmd_schema(command),
+        Some(Commands::Plugins { json }) => crate::cli::cmd_plugins(&kiosk_cli::PluginsArgs {
+            format: output(json),
+        }),
+        Some(Commands::Plugin(_)) => unreachable!("plugins run before dispatch"),
+        Some(Commands::Pick { repos, branches }) => {
+            let scope = if repos {
+                PickScope::Repos
+            } else if branches {
+                PickScope::Branches
+            } else {
+                PickScope::Any
+            };
+            run_pick(runtime, config_source, scope)
+        }
+        None => run_tui(runtime, config_source).map_err(kiosk_cli::CliError::from),
     }
 }
 
-fn run_tui(
-    config: &config::Config,
-    git: &Arc<dyn GitProvider>,
-    tmux: &Arc<dyn TmuxProvider>,
+/// Run the TUI and act on what was picked, reopening it after resuming or recovering sessions
+/// or when opening a workspace fails.
+fn run_tui(runtime: &Runtime, config_source: &config::ConfigSource) -> Result<()> {
+    let mut error = None;
+    loop {
+        let action = show_tui(runtime, config_source, error.as_deref())?;
+        match act_on_tui_choice(runtime, action)? {
+            ControlFlow::Continue(reopen_error) => error = reopen_error,
+            ControlFlow::Break(()) => return Ok(()),
+        }
+    }
+}
+
+/// Show the TUI until something is picked, first offering to recreate lost sessions, or else
+/// showing `error`.
+fn show_tui(
+    runtime: &Runtime,
+    config_source: &config::ConfigSource,
+    error: Option<&str>,
+) -> Result<Option<OpenAction>> {
+    let (config, git, tmux) = (runtime.config(), runtime.git(), runtime.tmux());
+    let search_dirs = config.resolved_search_dirs();
+    let mut state = tui_state(config, config_source, git.as_ref(), &search_dirs)?;
+    state.fetch_locks = FetchLocks::new();
+    match error {
+        Some(error) => state.set_error(error),
+        None => {
+            if !offer_recovery(&mut state) {
+                offer_resume(&mut state, tmux.as_ref());
+            }
+        }
+    }
+
+    let mut terminal = if should_disable_alt_screen() {
+        // Inline viewport keeps drawing in the primary screen buffer, which makes
+        // tmux capture-pane output usable for automation/debugging.
+        ratatui::init_with_options(ratatui::TerminalOptions {
+            viewport: ratatui::Viewport::Inline(30),
+        })
+    } else {
+        ratatui::init()
+    };
+    let result = kiosk_tui::run_runtime(&mut terminal, &mut state, runtime);
+    ratatui::restore();
+    remember_activity(&state);
+    // Release the instance lock before attaching to a session, which blocks
+    drop(state);
+    result
+}
+
+/// Act on what the TUI picked, continuing with the error to show if the TUI should reopen.
+fn act_on_tui_choice(
+    runtime: &Runtime,
+    action: Option<OpenAction>,
+) -> Result<ControlFlow<(), Option<String>>> {
+    let (config, git, tmux) = (runtime.config(), runtime.git(), runtime.tmux());
+    match action {
+        Some(OpenAction::Open {
+            path,
+            repo,
+            session_name,
+            split_command,
+            run,
+            agent,
+            base,
+        }) => {
+            set_up_tui_session(
+                runtime,
+                &path,
+                repo,
+                &session_name,
+                &config::SessionTemplate { split_command, run },
+                agent.as_ref(),
+                base,
+            )?;
+
+            kiosk_cli::switch_and_close_idle_sessions(
+                config,
+                git.as_ref(),
+                tmux.as_ref(),
+                &kiosk_cli::Stores::default(),
+                &session_name,
+                config.session.max_alive,
+            );
+            record_visit(git.as_ref(), &path);
+        }
+        Some(OpenAction::Editor { path, editor }) => {
+            kiosk_cli::open_in_editor(&path, editor.as_deref())?;
+            record_visit(git.as_ref(), &path);
+        }
+        Some(OpenAction::Resume) => {
+            let failures = kiosk_cli::resume_lost_sessions(config, git.as_ref(), tmux.as_ref())?;
+            let error =
+                (!failures.is_empty()).then(|| format!("Failed to resume {}", failures.join("; ")));
+            return Ok(ControlFlow::Continue(error));
+        }
+        Some(OpenAction::Recover { undo }) => {
+            let failures = kiosk_cli::recover_interrupted(config, git.as_ref(), undo)?;
+            let error = (!failures.is_empty())
+                .then(|| format!("Failed to recover {}", failures.join("; ")));
+            return Ok(ControlFlow::Continue(error));
+        }
+        Some(OpenAction::Workspace { name }) => {
+            let failures = kiosk_cli::open_workspace(config, git.as_ref(), tmux.as_ref(), &name)?;
+            if !failures.is_empty() {
+                let error = format!("Failed to open {}", failures.join("; "));
+                return Ok(ControlFlow::Continue(Some(error)));
+            }
+        }
+        Some(OpenAction::PrintPath { .. } | OpenAction::Quit | OpenAction::SetupComplete)
+        | None => {}
+    }
+
+    Ok(ControlFlow::Break(()))
+}
+
+/// Set up the session the TUI picked before switching to it: create it if it's gone, start
+/// the picked agent in it, and title its agent pane.
+fn set_up_tui_session(
+    runtime: &Runtime,
+    path: &Path,
+    repo: Option<String>,
+    session_name: &str,
+    template: &config::SessionTemplate,
+    agent: Option<&config::AgentProfile>,
+    base: Option<String>,
 ) -> Result<()> {
+    let (config, git, tmux) = (runtime.config(), runtime.git(), runtime.tmux());
+    let created = !tmux.session_exists(session_name);
+    // Only a branch the TUI just created comes with its base
+    let branch_created = base.is_some();
+    let set_up = created || agent.is_some();
+    if set_up || config.session.set_titles {
+        let (repo_path, context) =
+            kiosk_cli::session_context(config, git.as_ref(), repo, path, session_name, base);
+        if set_up {
+            let session = template.expanded(&context);
+            if created {
+                kiosk_cli::create_session(
+                    config,
+                    tmux.as_ref(),
+                    &context,
+                    session.split_command.as_deref(),
+                    &[],
+                )?;
+                if branch_created {
+                    kiosk_cli::run_script_hook(
+                        config,
+                        tmux.as_ref(),
+                        Hook::BranchCreated,
+                        &context,
+                    );
+                }
+            }
+            // Like `kiosk open --agent`, the agent starts even in an existing session
+            let run = match agent {
+                Some(agent) => Some(context.expand(&agent.command)),
+                None => session.run,
+            };
+            if agent.is_some_and(|agent| agent.log) {
+                kiosk_cli::start_session_log(config, tmux.as_ref(), session_name)?;
+            }
+            if let Some(run) = &run {
+                tmux.send_keys(session_name, run)?;
+            }
+            kiosk_cli::record_session(
+                &SessionJournal::default(),
+                &repo_path,
+                &context,
+                template.split_command.as_deref(),
+                run.as_deref(),
+                branch_created,
+            );
+        }
+        kiosk_cli::title_agent_pane(config, tmux.as_ref(), &context);
+    }
+    Ok(())
+}
+
+/// Open on a prompt to finish or undo the worktree operations a crashed kiosk left half-done,
+/// returning whether there were any.
+fn offer_recovery(state: &mut AppState) -> bool {
+    let operations = Recovery::default().interrupted();
+    if operations.is_empty() {
+        return false;
+    }
+    state.mode = Mode::ConfirmRecover { operations };
+    true
+}
+
+/// Open on a prompt to recreate the sessions lost when the tmux server last went away.
+fn offer_resume(state: &mut AppState, tmux: &dyn TmuxProvider) {
+    if !tmux.is_available() {
+        return;
+    }
+    let running = tmux.list_session_names();
+    let server_started_at = tmux.server_started_at();
+    if let Err(e) = state
+        .session_journal
+        .forget_closed(&running, server_started_at)
+    {
+        tracing::warn!("Failed to update the session journal: {e}");
+    }
+    let lost = state.session_journal.lost(&running, server_started_at);
+    if !lost.is_empty() {
+        state.mode = Mode::ConfirmResume {
+            sessions: lost.into_iter().map(|entry| entry.session).collect(),
+        };
+    }
+}
+
+/// `kiosk pick`: run the TUI on stderr and print the chosen path on stdout, so the TUI still
+/// shows when stdout is captured by `$(...)`.
+fn run_pick(
+    runtime: &Runtime,
+    config_source: &config::ConfigSource,
+    scope: PickScope,
+) -> kiosk_cli::CliResult<()> {
+    let (config, git) = (runtime.config(), runtime.git());
     let search_dirs = config.resolved_search_dirs();
+    let mut state = tui_state(config, config_source, git.as_ref(), &search_dirs)?;
+    state.pick = Some(scope);
+
+    let mut terminal = init_stderr_terminal().map_err(|e| {
+        restore_stderr_terminal();
+        kiosk_cli::CliError::system(format!("Failed to set up the terminal: {e}"))
+    })?;
+    let result = kiosk_tui::run_runtime(&mut terminal, &mut state, runtime);
+    drop(terminal);
+    restore_stderr_terminal();
+    remember_activity(&state);
+
+    match result? {
+        Some(OpenAction::PrintPath { path }) => {
+            println!("{}", path.display());
+            record_visit(git.as_ref(), &path);
+            Ok(())
+        }
+        Some(
+            OpenAction::Open { .. }
+            | OpenAction::Editor { .. }
+            | OpenAction::Resume
+            | OpenAction::Recover { .. }
+            | OpenAction::Workspace { .. }
+            | OpenAction::Quit
+            | OpenAction::SetupComplete,
+        )
+        | None => Err(kiosk_cli::CliError::user("Nothing picked")),
+    }
+}
+
+/// Always full screen: an inline viewport queries the cursor position through stdout, which
+/// would end up in the picked output (so `KIOSK_NO_ALT_SCREEN` is ignored here).
+fn init_stderr_terminal() -> io::Result<Terminal<CrosstermBackend<io::Stderr>>> {
+    enable_raw_mode()?;
+    execute!(io::stderr(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(io::stderr()))
+}
 
+fn restore_stderr_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stderr(), LeaveAlternateScreen);
+}
+
+/// TUI state for the configured search dirs, with the repo or worktree containing the CWD
+/// shown straight away.
+fn tui_state(
+    config: &config::Config,
+    config_source: &config::ConfigSource,
+    git: &dyn GitProvider,
+    search_dirs: &[(std::path::PathBuf, u16)],
+) -> Result<AppState> {
     // Detect CWD repo/worktree for instant display and ordering.
     // cwd_worktree_path: the toplevel of whatever git tree the user is in (main repo or worktree)
     // current_repo_path: the main repo root (resolved through worktree .git pointers)
@@ -442,10 +2008,10 @@ fn run_tui(
         .and_then(|p| dunce::canonicalize(&p).ok());
     let current_repo_path = cwd_worktree_path
         .as_ref()
-        .and_then(|p| resolve_main_repo_root(p))
+        .and_then(|p| kiosk_core::git::resolve_main_repo_root(p))
         .and_then(|main_root| {
             let canonical = dunce::canonicalize(&main_root).unwrap_or(main_root);
-            is_within_search_dirs(&canonical, &search_dirs).then_some(canonical)
+            is_within_search_dirs(&canonical, search_dirs).then_some(canonical)
         });
     let initial_repo = current_repo_path.as_ref().and_then(|repo_path| {
         let name = repo_path.file_name()?.to_string_lossy().to_string();
@@ -472,54 +2038,26 @@ fn run_tui(
         s.cwd_worktree_path = cwd_worktree_path;
         s
     };
-    state.pending_worktree_deletes = load_pending_worktree_deletes();
-
-    let theme = Theme::from_config(&config.theme);
-
-    let mut terminal = if should_disable_alt_screen() {
-        // Inline viewport keeps drawing in the primary screen buffer, which makes
-        // tmux capture-pane output usable for automation/debugging.
-        ratatui::init_with_options(ratatui::TerminalOptions {
-            viewport: ratatui::Viewport::Inline(30),
-        })
-    } else {
-        ratatui::init()
-    };
-    let result = kiosk_tui::run(
-        &mut terminal,
-        &mut state,
-        git,
-        tmux,
-        &theme,
-        &config.keys,
-        search_dirs,
-    );
-    ratatui::restore();
-
-    match result? {
-        Some(OpenAction::Open {
-            path,
-            session_name,
-            split_command,
-        }) => {
-            if !tmux.session_exists(&session_name) {
-                tmux.create_session(&session_name, &path, split_command.as_deref())?;
-            }
-
-            tmux.switch_to_session(&session_name);
-        }
-        Some(OpenAction::Quit | OpenAction::SetupComplete) | None => {}
-    }
-
-    Ok(())
+    state.pending_worktree_deletes = state.pending_delete_store.load();
+    state.frecency = FrecencyStore::default().load();
+    state.pinned = state.pin_store.load();
+    state.layout = state.layout_store.load();
+    state.repo_filter.hidden = state.hidden_store.load();
+    state.activity_history = state.activity_store.load();
+    state.apply_config(config)?;
+    state.config_source = Some(config_source.clone());
+    Ok(state)
 }
 
-fn run_setup_then_tui() -> ExitCode {
-    let git: Arc<dyn GitProvider> = Arc::new(CliGitProvider);
-    let tmux: Arc<dyn TmuxProvider> = Arc::new(CliTmuxProvider);
+fn run_setup_then_tui(tmux_socket: Option<String>) -> ExitCode {
+    let git: Arc<dyn GitProvider> = Arc::new(CliGitProvider::default());
+    let tmux = tmux::provider_for(
+        config::Multiplexer::default(),
+        &config::TmuxConfig::default(),
+    );
 
     let mut state = AppState::new_setup();
-    let theme = kiosk_tui::Theme::from_config(&config::ThemeConfig::default());
+    let theme = kiosk_tui::Theme::for_terminal(&config::ThemeConfig::default());
     let keys = config::KeysConfig::default();
 
     let mut terminal = if should_disable_alt_screen() {
@@ -562,14 +2100,24 @@ fn run_setup_then_tui() -> ExitCode {
                 }
             }
             // Load the newly written config and continue into normal TUI
-            match config::load_config(None) {
-                Ok(config) => match run_tui(&config, &git, &tmux) {
-                    Ok(()) => ExitCode::from(0),
-                    Err(e) => {
-                        eprintln!("Error: {e}");
-                        ExitCode::from(2)
+            let source = config::ConfigSource {
+                path: config::config_file_path(None),
+                profile: None,
+            };
+            match config::load_config(Some(&source.path), None) {
+                Ok(mut config) => {
+                    if tmux_socket.is_some() {
+                        config.tmux.socket = tmux_socket;
                     }
-                },
+                    let runtime = Runtime::builder(config).audit(AuditLog::default()).build();
+                    match run_tui(&runtime, &source) {
+                        Ok(()) => ExitCode::from(0),
+                        Err(e) => {
+                            eprintln!("Error: {e}");
+                            ExitCode::from(2)
+                        }
+                    }
+                }
                 Err(e) => {
                     eprintln!("Failed to load config after setup: {e}");
                     ExitCode::from(2)
@@ -577,7 +2125,14 @@ fn run_setup_then_tui() -> ExitCode {
             }
         }
         Ok(Some(kiosk_tui::OpenAction::Quit) | None) => ExitCode::from(0),
-        Ok(Some(kiosk_tui::OpenAction::Open { .. })) => {
+        Ok(Some(
+            kiosk_tui::OpenAction::Open { .. }
+            | kiosk_tui::OpenAction::Editor { .. }
+            | kiosk_tui::OpenAction::PrintPath { .. }
+            | kiosk_tui::OpenAction::Resume
+            | kiosk_tui::OpenAction::Recover { .. }
+            | kiosk_tui::OpenAction::Workspace { .. },
+        )) => {
             eprintln!("Unexpected OpenAction::Open during setup flow");
             ExitCode::from(2)
         }
@@ -595,31 +2150,20 @@ fn is_within_search_dirs(path: &Path, search_dirs: &[(std::path::PathBuf, u16)])
     })
 }
 
-/// If `path` is a secondary git worktree root, resolve to the main repository root.
-/// Returns the path unchanged if it's already a main repository root.
-fn resolve_main_repo_root(path: &Path) -> Option<std::path::PathBuf> {
-    let git_entry = path.join(GIT_DIR_ENTRY);
-    if git_entry.is_file() {
-        // Secondary worktree: .git is a file containing "gitdir: /path/to/main/.git/worktrees/name"
-        let content = fs::read_to_string(&git_entry).ok()?;
-        let gitdir_str = content
-            .lines()
-            .find(|l| l.starts_with(GITDIR_FILE_PREFIX))?
-            .strip_prefix(GITDIR_FILE_PREFIX)?
-            .trim();
-        let gitdir_raw = Path::new(gitdir_str);
-        // Resolve relative gitdir paths against the worktree root
-        let gitdir = if gitdir_raw.is_relative() {
-            path.join(gitdir_raw)
-        } else {
-            gitdir_raw.to_path_buf()
-        };
-        // .git/worktrees/<name> → .git/worktrees → .git → repo root
-        gitdir.parent()?.parent()?.parent().map(Path::to_path_buf)
-    } else if git_entry.is_dir() {
-        Some(path.to_path_buf())
-    } else {
-        None
+/// Count a session opened from the TUI towards its repo's and branch's frecency.
+fn record_visit(git: &dyn GitProvider, path: &Path) {
+    let Some((repo_path, branch)) = kiosk_core::git::worktree_of(git, path) else {
+        return;
+    };
+    if let Err(error) = FrecencyStore::default().record_open(&repo_path, branch.as_deref()) {
+        tracing::warn!("Failed to record visit to {}: {error}", path.display());
+    }
+}
+
+/// Add the session activity the TUI last saw to the history behind its activity heat.
+fn remember_activity(state: &AppState) {
+    if let Err(error) = state.activity_store.record(&state.session_activity) {
+        tracing::warn!("Failed to record session activity: {error}");
     }
 }
 
@@ -641,9 +2185,11 @@ fn clean_orphaned_worktrees(
     search_dirs: &[(std::path::PathBuf, u16)],
     git: &dyn GitProvider,
     dry_run: bool,
-    yes: bool,
-    json: bool,
+    confirm: &kiosk_cli::Confirm,
+    format: &OutputFormat,
+    clean: &config::CleanConfig,
 ) -> Result<()> {
+    let use_trash = clean.use_trash;
     let mut orphaned_worktrees = Vec::new();
 
     // Scan all search directories for .kiosk_worktrees directories
@@ -664,24 +2210,23 @@ fn clean_orphaned_worktrees(
         }
     }
 
-    if json {
-        let should_remove = yes && !dry_run;
+    if *format != OutputFormat::Table {
+        let should_remove = confirm.assume_yes() && !dry_run;
         let mut removed = Vec::new();
         if should_remove {
             for worktree in &orphaned_worktrees {
-                if remove_worktree(worktree).is_ok() {
+                if remove_worktree(worktree, use_trash).is_ok() {
                     removed.push(worktree.clone());
                 }
             }
         }
-        let orphaned: Vec<String> = orphaned_worktrees
-            .iter()
-            .map(|p| p.display().to_string())
-            .collect();
-        let removed: Vec<String> = removed.iter().map(|p| p.display().to_string()).collect();
-        let output = serde_json::json!({ "orphaned": orphaned, "removed": removed });
-        println!("{output}");
-        clean_prunable_worktree_metadata(search_dirs, git, dry_run || !yes);
+        let output = kiosk_cli::CleanOutput {
+            orphaned: orphaned_worktrees,
+            removed,
+        };
+        // Table output is handled below, with interactive confirmation
+        cli::print_output(format, &output, |_| String::new())?;
+        clean_prunable_worktree_metadata(search_dirs, git, dry_run || !confirm.assume_yes());
         return Ok(());
     }
 
@@ -695,31 +2240,15 @@ fn clean_orphaned_worktrees(
 
         if dry_run {
             println!("\n(Dry run - no changes made. Run without --dry-run to remove them.)");
-        } else if yes {
+        } else if confirm.ask("\nRemove these orphaned worktrees?")? {
             for worktree in orphaned_worktrees {
-                match remove_worktree(&worktree) {
+                match remove_worktree(&worktree, use_trash) {
                     Ok(()) => println!("Removed: {}", worktree.display()),
                     Err(e) => eprintln!("Failed to remove {}: {}", worktree.display(), e),
                 }
             }
         } else {
-            // Prompt for confirmation
-            print!("\nRemove these orphaned worktrees? (y/N): ");
-            io::Write::flush(&mut io::stdout())?;
-
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-
-            if input.trim().to_lowercase() == "y" {
-                for worktree in orphaned_worktrees {
-                    match remove_worktree(&worktree) {
-                        Ok(()) => println!("Removed: {}", worktree.display()),
-                        Err(e) => eprintln!("Failed to remove {}: {}", worktree.display(), e),
-                    }
-                }
-            } else {
-                println!("Skipped orphaned worktree directory removal.");
-            }
+            println!("Skipped orphaned worktree directory removal.");
         }
     }
 
@@ -786,8 +2315,13 @@ fn is_orphaned_worktree(path: &Path) -> bool {
         return true; // Malformed .git file
     };
 
-    let gitdir_path = gitdir_line.strip_prefix(GITDIR_FILE_PREFIX).unwrap_or("");
-    let gitdir = Path::new(gitdir_path);
+    // Relative when git's worktree.useRelativePaths is set; on Windows the path uses
+    // forward slashes, which `Path` accepts as separators there too
+    let gitdir_path = gitdir_line
+        .strip_prefix(GITDIR_FILE_PREFIX)
+        .unwrap_or("")
+        .trim();
+    let gitdir = &path.join(gitdir_path);
 
     // Check if the gitdir path exists and is valid
     if !gitdir.exists() {
@@ -837,7 +2371,7 @@ fn is_worktree_known_to_git(main_repo_path: &Path, worktree_path: &Path) -> Opti
     let output = Command::new("git")
         .args(["worktree", "list", "--porcelain"])
         .current_dir(main_repo_path)
-        .output()
+        .traced_output()
         .ok()?;
 
     if !output.status.success() {
@@ -864,11 +2398,17 @@ fn is_worktree_known_to_git(main_repo_path: &Path, worktree_path: &Path) -> Opti
     Some(false)
 }
 
-fn remove_worktree(path: &Path) -> Result<()> {
+fn remove_worktree(path: &Path, use_trash: bool) -> Result<()> {
+    // `git worktree remove` deletes permanently, so go straight to the trash; the
+    // stale metadata is pruned afterwards by `clean_prunable_worktree_metadata`.
+    if use_trash {
+        return kiosk_core::removal::discard_dir(path, true);
+    }
+
     // First try to use git worktree remove if possible
     let output = Command::new("git")
         .args(["worktree", "remove", &path.to_string_lossy()])
-        .output();
+        .traced_output();
 
     match output {
         Ok(output) if output.status.success() => {
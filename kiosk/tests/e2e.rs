@@ -55,6 +55,16 @@ fn init_test_repo(dir: &Path) {
     run_git(dir, &["commit", "-m", "init"]);
 }
 
+/// Parse `--json` output and unwrap its versioned envelope.
+fn json_data(stdout: &[u8]) -> Value {
+    let mut envelope: Value = serde_json::from_slice(stdout).unwrap();
+    assert_eq!(
+        envelope["schema_version"], 1,
+        "unexpected envelope: {envelope}"
+    );
+    envelope["data"].take()
+}
+
 fn tmux_capture(socket: &str, session: &str) -> String {
     let output = Command::new("tmux")
         .args(["-L", socket, "capture-pane", "-t", session, "-p"])
@@ -953,6 +963,77 @@ fn test_e2e_clean_dry_run() {
     );
 }
 
+#[test]
+fn test_e2e_clean_reads_piped_answers_and_honours_assume_yes() {
+    let env = TestEnv::new("clean-assume-yes");
+    let search_dir = env.search_dir();
+    let fake_worktree = search_dir
+        .join(WORKTREE_DIR_NAME)
+        .join("fake-repo--yes-branch");
+    env.write_config(&search_dir);
+
+    let clean = |assume_yes: &str, answer: Option<&str>| {
+        fs::create_dir_all(&fake_worktree).unwrap();
+        let mut child = Command::new(kiosk_binary())
+            .arg("clean")
+            .env("XDG_CONFIG_HOME", &env.config_dir)
+            .env("XDG_STATE_HOME", &env.state_dir)
+            .env("KIOSK_ASSUME_YES", assume_yes)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        if let Some(answer) = answer {
+            use std::io::Write;
+            stdin.write_all(answer.as_bytes()).unwrap();
+        }
+        drop(stdin);
+        child.wait_with_output().unwrap()
+    };
+
+    let declined = clean("0", Some("n\n"));
+    assert!(declined.status.success());
+    assert!(
+        String::from_utf8_lossy(&declined.stdout).contains("Skipped"),
+        "a piped no declines: {}",
+        String::from_utf8_lossy(&declined.stdout)
+    );
+    assert!(fake_worktree.exists());
+
+    let unanswered = clean("0", None);
+    assert!(!unanswered.status.success());
+    assert!(
+        String::from_utf8_lossy(&unanswered.stderr).contains("Pass --yes"),
+        "running out of stdin should ask for --yes: {}",
+        String::from_utf8_lossy(&unanswered.stderr)
+    );
+    assert!(fake_worktree.exists());
+
+    let piped_yes = clean("0", Some("y\n"));
+    assert!(
+        piped_yes.status.success(),
+        "{}",
+        String::from_utf8_lossy(&piped_yes.stderr)
+    );
+    assert!(
+        !fake_worktree.exists(),
+        "a piped yes should remove the orphan"
+    );
+
+    let assumed = clean("1", None);
+    assert!(
+        assumed.status.success(),
+        "{}",
+        String::from_utf8_lossy(&assumed.stderr)
+    );
+    assert!(
+        !fake_worktree.exists(),
+        "KIOSK_ASSUME_YES should remove the orphan"
+    );
+}
+
 #[test]
 fn test_e2e_custom_keybindings() {
     let env = TestEnv::new("custom-keys");
@@ -1231,7 +1312,7 @@ fn test_e2e_help_rapid_down_only_hits_visual_bottom_at_true_end() {
         let (selected_row, body_rows, selected_line) = help_view_detail(&env.capture());
         if selected_row + 1 == body_rows {
             assert!(
-                selected_line.contains("Show help"),
+                selected_line.contains("Search for a command and run it"),
                 "Bottom row should only be the true end in branch help, got: {selected_line}"
             );
         }
@@ -1822,7 +1903,7 @@ fn test_e2e_headless_list_and_branches_json() {
         "list should succeed: {}",
         String::from_utf8_lossy(&list_output.stderr)
     );
-    let list_json: Value = serde_json::from_slice(&list_output.stdout).unwrap();
+    let list_json: Value = json_data(&list_output.stdout);
     assert!(
         list_json
             .as_array()
@@ -1838,7 +1919,7 @@ fn test_e2e_headless_list_and_branches_json() {
         "branches should succeed: {}",
         String::from_utf8_lossy(&branches_output.stderr)
     );
-    let branches_json: Value = serde_json::from_slice(&branches_output.stdout).unwrap();
+    let branches_json: Value = json_data(&branches_output.stdout);
     assert!(
         branches_json
             .as_array()
@@ -1849,6 +1930,22 @@ fn test_e2e_headless_list_and_branches_json() {
     );
 }
 
+/// The operations `kiosk history` lists for `repo`, oldest first.
+fn history_operations(env: &TestEnv, repo: &str) -> Vec<String> {
+    let output = env.run_cli(&["history", repo, "--json"]);
+    assert!(
+        output.status.success(),
+        "history should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    json_data(&output.stdout)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["operation"].as_str().unwrap().to_string())
+        .collect()
+}
+
 #[test]
 fn test_e2e_headless_open_status_delete_workflow() {
     let env = TestEnv::new("headless-workflow");
@@ -1886,7 +1983,7 @@ fn test_e2e_headless_open_status_delete_workflow() {
         "open should succeed: {}",
         String::from_utf8_lossy(&open_output.stderr)
     );
-    let open_json: Value = serde_json::from_slice(&open_output.stdout).unwrap();
+    let open_json: Value = json_data(&open_output.stdout);
     let session = open_json["session"].as_str().unwrap().to_string();
     assert!(
         wait_for_tmux_session(None, &session, 5000),
@@ -1908,7 +2005,7 @@ fn test_e2e_headless_open_status_delete_workflow() {
             "status should succeed: {}",
             String::from_utf8_lossy(&status_output.stderr)
         );
-        let status_json: Value = serde_json::from_slice(&status_output.stdout).unwrap();
+        let status_json: Value = json_data(&status_output.stdout);
         output_text = status_json["output"]
             .as_str()
             .unwrap_or_default()
@@ -1929,11 +2026,24 @@ fn test_e2e_headless_open_status_delete_workflow() {
         "delete should succeed: {}",
         String::from_utf8_lossy(&delete_output.stderr)
     );
-    let delete_json: Value = serde_json::from_slice(&delete_output.stdout).unwrap();
+    let delete_json: Value = json_data(&delete_output.stdout);
     assert_eq!(delete_json["deleted"], Value::Bool(true));
-    assert_eq!(delete_json["repo"], Value::String(repo_name));
+    assert_eq!(delete_json["repo"], Value::String(repo_name.clone()));
     assert_eq!(delete_json["branch"], Value::String(branch_name));
     cleanup.disable();
+
+    let operations = history_operations(&env, &repo_name);
+    assert_eq!(
+        operations,
+        [
+            "worktree_created",
+            "session_created",
+            "keys_sent",
+            "session_killed",
+            "worktree_removed",
+            "worktrees_pruned",
+        ]
+    );
 }
 
 #[test]
@@ -1955,7 +2065,7 @@ fn test_e2e_headless_open_is_idempotent() {
         "first open should succeed: {}",
         String::from_utf8_lossy(&first.stderr)
     );
-    let first_json: Value = serde_json::from_slice(&first.stdout).unwrap();
+    let first_json: Value = json_data(&first.stdout);
     assert_eq!(first_json["created"], Value::Bool(true));
     guard.session = first_json["session"].as_str().map(String::from);
 
@@ -1965,10 +2075,66 @@ fn test_e2e_headless_open_is_idempotent() {
         "second open should succeed: {}",
         String::from_utf8_lossy(&second.stderr)
     );
-    let second_json: Value = serde_json::from_slice(&second.stdout).unwrap();
+    let second_json: Value = json_data(&second.stdout);
     assert_eq!(second_json["created"], Value::Bool(false));
 }
 
+#[test]
+fn test_e2e_headless_workspace_open_opens_every_member() {
+    let env = TestEnv::new("headless-workspace");
+    let search_dir = env.search_dir();
+    let id = unique_id();
+    let front = format!("ws-front-{id}");
+    let back = format!("ws-back-{id}");
+    for name in [&front, &back] {
+        let repo = search_dir.join(name);
+        fs::create_dir_all(&repo).unwrap();
+        init_test_repo(&repo);
+    }
+    run_git(&search_dir.join(&back), &["branch", "feat"]);
+    env.write_config_with_extra(
+        &search_dir,
+        &format!("[workspaces]\npair = [\"{front}\", \"{back}/feat\"]\n"),
+    );
+
+    let listed = env.run_cli(&["workspace", "list", "--json"]);
+    assert!(listed.status.success());
+    let listed: Value = json_data(&listed.stdout);
+    assert_eq!(listed[0]["name"], "pair");
+
+    let output = env.run_cli(&["workspace", "open", "pair", "--no-switch", "--json"]);
+    assert!(
+        output.status.success(),
+        "workspace open should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json: Value = json_data(&output.stdout);
+    let sessions: Vec<String> = json["opened"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|opened| opened["session"].as_str().unwrap().to_string())
+        .collect();
+    let _guards: Vec<SessionCleanupGuard> = sessions
+        .iter()
+        .map(|session| SessionCleanupGuard {
+            session: Some(session.clone()),
+        })
+        .collect();
+    assert_eq!(sessions.len(), 2, "{json}");
+    assert_eq!(json["failed"], Value::Array(Vec::new()));
+    assert_eq!(json["switched_to"], Value::Null);
+    for session in &sessions {
+        assert!(
+            wait_for_tmux_session(None, session, 5000),
+            "tmux session {session} should exist"
+        );
+    }
+
+    let missing = env.run_cli(&["workspace", "open", "nope", "--no-switch"]);
+    assert!(!missing.status.success());
+}
+
 #[test]
 fn test_e2e_headless_sessions_json() {
     let env = TestEnv::new("headless-sessions");
@@ -1986,7 +2152,7 @@ fn test_e2e_headless_sessions_json() {
         "open should succeed: {}",
         String::from_utf8_lossy(&open_output.stderr)
     );
-    let open_json: Value = serde_json::from_slice(&open_output.stdout).unwrap();
+    let open_json: Value = json_data(&open_output.stdout);
     let session = open_json["session"].as_str().unwrap().to_string();
     assert!(
         wait_for_tmux_session(None, &session, 5000),
@@ -1999,7 +2165,7 @@ fn test_e2e_headless_sessions_json() {
         "sessions should succeed: {}",
         String::from_utf8_lossy(&sessions_output.stderr)
     );
-    let sessions_json: Value = serde_json::from_slice(&sessions_output.stdout).unwrap();
+    let sessions_json: Value = json_data(&sessions_output.stdout);
     let sessions = sessions_json.as_array().unwrap();
     let matching = sessions
         .iter()
@@ -2020,6 +2186,72 @@ fn test_e2e_headless_sessions_json() {
         .output();
 }
 
+#[test]
+fn test_e2e_headless_sessions_over_control_mode() {
+    let env = TestEnv::new("headless-sessions-control");
+    let search_dir = env.search_dir();
+    let id = unique_id();
+    let repo_name = format!("control-repo-{id}");
+    let repo = search_dir.join(&repo_name);
+    fs::create_dir_all(&repo).unwrap();
+    init_test_repo(&repo);
+    env.write_config_with_extra(&search_dir, "[tmux]\ncontrol_mode = true\n");
+
+    let open_output = env.run_cli(&["open", &repo_name, "main", "--no-switch", "--json"]);
+    assert!(
+        open_output.status.success(),
+        "open should succeed: {}",
+        String::from_utf8_lossy(&open_output.stderr)
+    );
+    let session = json_data(&open_output.stdout)["session"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(
+        wait_for_tmux_session(None, &session, 5000),
+        "tmux session {session} should exist"
+    );
+
+    let sessions_output = env.run_cli(&["sessions", "--json"]);
+    assert!(
+        sessions_output.status.success(),
+        "sessions should succeed: {}",
+        String::from_utf8_lossy(&sessions_output.stderr)
+    );
+    let sessions_json = json_data(&sessions_output.stdout);
+    let entry = sessions_json
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["session"].as_str() == Some(&session))
+        .unwrap_or_else(|| panic!("sessions output should include {session}: {sessions_json}"));
+    assert_eq!(entry["repo"].as_str(), Some(repo_name.as_str()));
+    assert_eq!(entry["pane_count"], 1);
+    // kiosk's own control client doesn't count as someone attached
+    assert_eq!(entry["attached"], Value::Bool(false));
+    // and the session it attaches to closes once it's gone
+    let control_session_closed = (0..50).any(|_| {
+        let exists = Command::new("tmux")
+            .args(["has-session", "-t", "=_kiosk_control"])
+            .output()
+            .unwrap()
+            .status
+            .success();
+        if exists {
+            wait_ms(100);
+        }
+        !exists
+    });
+    assert!(
+        control_session_closed,
+        "kiosk's control session should close"
+    );
+
+    let _ = Command::new("tmux")
+        .args(["kill-session", "-t", &session])
+        .output();
+}
+
 #[test]
 fn test_e2e_headless_open_json_includes_repo_and_branch() {
     let env = TestEnv::new("headless-open-fields");
@@ -2037,7 +2269,7 @@ fn test_e2e_headless_open_json_includes_repo_and_branch() {
         "open should succeed: {}",
         String::from_utf8_lossy(&output.stderr)
     );
-    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let json: Value = json_data(&output.stdout);
     assert_eq!(json["repo"].as_str(), Some(repo_name.as_str()));
     assert_eq!(json["branch"].as_str(), Some("main"));
 
@@ -2065,7 +2297,7 @@ fn test_e2e_headless_status_source_field() {
         "open should succeed: {}",
         String::from_utf8_lossy(&open_output.stderr)
     );
-    let open_json: Value = serde_json::from_slice(&open_output.stdout).unwrap();
+    let open_json: Value = json_data(&open_output.stdout);
     let session = open_json["session"].as_str().unwrap().to_string();
     assert!(
         wait_for_tmux_session(None, &session, 5000),
@@ -2078,7 +2310,7 @@ fn test_e2e_headless_status_source_field() {
         "status should succeed: {}",
         String::from_utf8_lossy(&status_output.stderr)
     );
-    let status_json: Value = serde_json::from_slice(&status_output.stdout).unwrap();
+    let status_json: Value = json_data(&status_output.stdout);
     assert_eq!(
         status_json["source"].as_str(),
         Some("live"),
@@ -2090,6 +2322,90 @@ fn test_e2e_headless_status_source_field() {
         .output();
 }
 
+#[test]
+fn test_e2e_headless_send_stdin_pastes_multiline_text() {
+    let env = TestEnv::new("headless-send-stdin");
+    let search_dir = env.search_dir();
+    let id = unique_id();
+    let repo_name = format!("send-repo-{id}");
+    let repo = search_dir.join(&repo_name);
+    fs::create_dir_all(&repo).unwrap();
+    init_test_repo(&repo);
+    env.write_config(&search_dir);
+
+    let open_output = env.run_cli(&["open", &repo_name, "--no-switch", "--json"]);
+    assert!(
+        open_output.status.success(),
+        "open should succeed: {}",
+        String::from_utf8_lossy(&open_output.stderr)
+    );
+    let session = json_data(&open_output.stdout)["session"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(
+        wait_for_tmux_session(None, &session, 5000),
+        "tmux session {session} should exist"
+    );
+
+    // `cat` echoes what it reads, so the pasted lines show up in the pane twice
+    let cat = env.run_cli(&["send", &repo_name, "--command", "cat"]);
+    assert!(cat.status.success());
+    wait_ms(300);
+
+    let mut child = Command::new(kiosk_binary())
+        .args(["send", &repo_name, "--stdin", "--json"])
+        .env("XDG_CONFIG_HOME", &env.config_dir)
+        .env("XDG_STATE_HOME", &env.state_dir)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        use std::io::Write;
+        let mut stdin = child.stdin.take().unwrap();
+        stdin
+            .write_all(format!("first-{id}\nsecond 'quoted' \"line\"\n").as_bytes())
+            .unwrap();
+    }
+    let send = child.wait_with_output().unwrap();
+    assert!(
+        send.status.success(),
+        "send --stdin should succeed: {}",
+        String::from_utf8_lossy(&send.stderr)
+    );
+    assert_eq!(
+        json_data(&send.stdout)["pasted_bytes"].as_u64(),
+        Some(format!("first-{id}\nsecond 'quoted' \"line\"").len() as u64)
+    );
+
+    let mut output = String::new();
+    for _ in 0..50 {
+        let status = env.run_cli(&["status", &repo_name, "--json"]);
+        output = json_data(&status.stdout)["output"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        if output.matches("second 'quoted' \"line\"").count() >= 2 {
+            break;
+        }
+        wait_ms(100);
+    }
+    assert!(
+        output.contains(&format!("first-{id}")),
+        "pasted text should reach the pane: {output}"
+    );
+    assert!(
+        output.matches("second 'quoted' \"line\"").count() >= 2,
+        "cat should echo the pasted lines: {output}"
+    );
+
+    let _ = Command::new("tmux")
+        .args(["kill-session", "-t", &session])
+        .output();
+}
+
 #[test]
 fn test_e2e_headless_open_log_and_status_log_fallback() {
     let env = TestEnv::new("headless-log-fallback");
@@ -2128,7 +2444,7 @@ fn test_e2e_headless_open_log_and_status_log_fallback() {
         "open with --log should succeed: {}",
         String::from_utf8_lossy(&open_output.stderr)
     );
-    let open_json: Value = serde_json::from_slice(&open_output.stdout).unwrap();
+    let open_json: Value = json_data(&open_output.stdout);
     let session = open_json["session"].as_str().unwrap().to_string();
     assert!(
         wait_for_tmux_session(None, &session, 5000),
@@ -2157,7 +2473,7 @@ fn test_e2e_headless_open_log_and_status_log_fallback() {
         "status should succeed via log fallback: {}",
         String::from_utf8_lossy(&status_output.stderr)
     );
-    let status_json: Value = serde_json::from_slice(&status_output.stdout).unwrap();
+    let status_json: Value = json_data(&status_output.stdout);
     assert_eq!(
         status_json["source"].as_str(),
         Some("log"),
@@ -2186,11 +2502,13 @@ fn test_e2e_headless_error_unknown_repo() {
     assert!(!output.status.success(), "should fail for unknown repo");
     assert_eq!(
         output.status.code(),
-        Some(1),
-        "exit code should be 1 for user error"
+        Some(3),
+        "exit code should be 3 for not found"
     );
     let stderr = String::from_utf8_lossy(&output.stderr);
     let error_json: Value = serde_json::from_str(stderr.trim()).unwrap();
+    assert_eq!(error_json["schema_version"], 1);
+    assert_eq!(error_json["exit_code"], 3);
     assert!(
         error_json["error"]
             .as_str()
@@ -2219,8 +2537,8 @@ fn test_e2e_headless_error_unknown_branch() {
     assert!(!output.status.success(), "should fail for unknown branch");
     assert_eq!(
         output.status.code(),
-        Some(1),
-        "exit code should be 1 for user error"
+        Some(3),
+        "exit code should be 3 for not found"
     );
     let stderr = String::from_utf8_lossy(&output.stderr);
     let error_json: Value = serde_json::from_str(stderr.trim()).unwrap();
@@ -2250,8 +2568,8 @@ fn test_e2e_headless_error_delete_no_worktree() {
     );
     assert_eq!(
         output.status.code(),
-        Some(1),
-        "exit code should be 1 for user error"
+        Some(3),
+        "exit code should be 3 for not found"
     );
     let stderr = String::from_utf8_lossy(&output.stderr);
     let error_json: Value = serde_json::from_str(stderr.trim()).unwrap();
@@ -2280,7 +2598,7 @@ fn test_e2e_headless_branches_json_stable_schema() {
         "branches should succeed: {}",
         String::from_utf8_lossy(&output.stderr)
     );
-    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let json: Value = json_data(&output.stdout);
     let branches = json.as_array().unwrap();
     assert!(!branches.is_empty());
 
@@ -2295,6 +2613,9 @@ fn test_e2e_headless_branches_json_stable_schema() {
         "should have 'is_current' field"
     );
     assert!(first.get("remote").is_some(), "should have 'remote' field");
+    assert_eq!(first["last_commit"]["subject"], "init");
+    assert_eq!(first["last_commit"]["author"], "Test");
+    assert!(first["last_commit"]["timestamp"].as_u64().unwrap() > 0);
     // Internal fields should NOT be exposed
     assert!(
         first.get("is_default").is_none(),
@@ -2306,6 +2627,52 @@ fn test_e2e_headless_branches_json_stable_schema() {
     );
 }
 
+#[test]
+fn test_e2e_headless_fetch_all_fetches_new_remote_branches() {
+    let env = TestEnv::new("headless-fetch");
+    let search_dir = env.search_dir();
+    let remote = env.tmp.path().join("fetch-remote.git");
+    fs::create_dir_all(&remote).unwrap();
+    run_git(&remote, &["init", "--bare"]);
+    let repo = search_dir.join("fetch-repo");
+    fs::create_dir_all(&repo).unwrap();
+    init_test_repo(&repo);
+    run_git(
+        &repo,
+        &["remote", "add", "origin", &remote.to_string_lossy()],
+    );
+    run_git(&repo, &["push", "origin", "HEAD"]);
+    // Pushed straight to the remote, so only a fetch finds it
+    run_git(&repo, &["push", "origin", "HEAD:refs/heads/feat/fetched"]);
+    run_git(
+        &repo,
+        &["update-ref", "-d", "refs/remotes/origin/feat/fetched"],
+    );
+    env.write_config(&search_dir);
+
+    let output = env.run_cli(&["fetch", "--all", "--prune", "--json"]);
+    assert!(
+        output.status.success(),
+        "fetch should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json = json_data(&output.stdout);
+    let fetched = &json["repos"][0];
+    assert_eq!(fetched["repo"], "fetch-repo");
+    assert_eq!(fetched["remotes"][0]["remote"], "origin");
+    assert_eq!(fetched["remotes"][0]["branches"], 2);
+    assert!(fetched["remotes"][0]["error"].is_null());
+
+    let branches = env.run_cli(&["branches", "fetch-repo", "--remote", "origin", "--json"]);
+    let names: Vec<String> = json_data(&branches.stdout)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|branch| branch["name"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["feat/fetched"]);
+}
+
 // ── Setup wizard E2E tests ──
 
 #[test]
@@ -2433,6 +2800,67 @@ fn test_e2e_setup_wizard_esc_quits() {
     );
 }
 
+#[test]
+fn test_e2e_pick_prints_the_chosen_path_with_stdout_redirected() {
+    let env = TestEnv::new("pick");
+    let search_dir = env.search_dir();
+    for name in ["alpha-project", "beta-project"] {
+        let repo = search_dir.join(name);
+        fs::create_dir_all(&repo).unwrap();
+        init_test_repo(&repo);
+    }
+    env.write_config(&search_dir);
+
+    let picked = env.tmp.path().join("picked.txt");
+    cleanup_session(&env.tmux_socket, &env.session_name);
+    Command::new("tmux")
+        .args([
+            "-L",
+            &env.tmux_socket,
+            "new-session",
+            "-d",
+            "-s",
+            &env.session_name,
+            "-x",
+            "120",
+            "-y",
+            "30",
+            &format!(
+                "XDG_CONFIG_HOME={} XDG_STATE_HOME={} KIOSK_NO_ALT_SCREEN=1 {} pick --repos > {} ; sleep 2",
+                env.config_dir.to_string_lossy(),
+                env.state_dir.to_string_lossy(),
+                kiosk_binary().to_string_lossy(),
+                picked.to_string_lossy()
+            ),
+        ])
+        .output()
+        .unwrap();
+
+    // The TUI draws on stderr, so it shows even though stdout goes to a file
+    wait_for_screen(&env, 3000, |s| s.contains("beta-project"));
+    env.send("beta");
+    env.send_special("Enter");
+
+    let start = std::time::Instant::now();
+    let mut output = String::new();
+    while start.elapsed() < std::time::Duration::from_secs(3) {
+        output = fs::read_to_string(&picked).unwrap_or_default();
+        if !output.is_empty() {
+            break;
+        }
+        wait_ms(100);
+    }
+    assert_eq!(
+        output.trim_end(),
+        search_dir.join("beta-project").to_string_lossy(),
+        "should print only the picked repo's path"
+    );
+    assert!(
+        !output.contains("alpha-project") && !output.contains('\x1b'),
+        "stdout should hold nothing but the path: {output:?}"
+    );
+}
+
 #[test]
 fn test_e2e_setup_wizard_not_triggered_for_cli_subcommand() {
     let env = TestEnv::new("setup-cli-sub");
@@ -2449,6 +2877,155 @@ fn test_e2e_setup_wizard_not_triggered_for_cli_subcommand() {
     );
 }
 
+#[test]
+fn test_e2e_config_path_and_edit_work_without_a_config_file() {
+    let env = TestEnv::new("config-edit");
+    let output = env.run_cli(&["config", "path", "--json"]);
+    assert!(output.status.success());
+    let data = json_data(&output.stdout);
+    assert_eq!(
+        data["path"],
+        env.config_file_path().to_string_lossy().as_ref()
+    );
+    assert_eq!(data["exists"], false);
+
+    // `true` stands in for an editor that saves without changes
+    let output = Command::new(kiosk_binary())
+        .args(["config", "edit"])
+        .env("XDG_CONFIG_HOME", &env.config_dir)
+        .env("XDG_STATE_HOME", &env.state_dir)
+        .env_remove("VISUAL")
+        .env("EDITOR", "true")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let written = fs::read_to_string(env.config_file_path()).unwrap();
+    assert!(written.contains("search_dirs = []"), "{written}");
+}
+
+#[test]
+fn test_e2e_plugins_run_as_subcommands_with_context_on_stdin() {
+    let env = TestEnv::new("plugin");
+    let search_dir = env.search_dir();
+    let repo = search_dir.join("plugin-repo");
+    fs::create_dir_all(&repo).unwrap();
+    init_test_repo(&repo);
+    env.write_config(&search_dir);
+
+    let plugin = env.bin_dir.join("kiosk-hello");
+    fs::write(
+        &plugin,
+        "#!/bin/sh\ncat > \"$(dirname \"$0\")/context.json\"\necho \"$KIOSK_REPO $KIOSK_BRANCH: $*\"\nexit 7\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let path = format!(
+        "{}:{}",
+        env.bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let kiosk = |args: &[&str]| {
+        Command::new(kiosk_binary())
+            .args(args)
+            .current_dir(&repo)
+            .env("XDG_CONFIG_HOME", &env.config_dir)
+            .env("XDG_STATE_HOME", &env.state_dir)
+            .env("PATH", &path)
+            .env_remove("TMUX")
+            .output()
+            .unwrap()
+    };
+
+    let output = kiosk(&["hello", "world", "--flag"]);
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "plugin-repo main: world --flag\n"
+    );
+    let context: Value =
+        serde_json::from_str(&fs::read_to_string(env.bin_dir.join("context.json")).unwrap())
+            .unwrap();
+    assert_eq!(context["name"], "hello");
+    assert_eq!(context["args"], serde_json::json!(["world", "--flag"]));
+    assert_eq!(context["repo"]["name"], "plugin-repo");
+    assert_eq!(context["repo"]["branch"], "main");
+    assert_eq!(
+        context["config"]["search_dirs"][0],
+        search_dir.to_string_lossy().as_ref()
+    );
+
+    let output = kiosk(&["plugins", "--json"]);
+    assert!(output.status.success());
+    let plugins = json_data(&output.stdout);
+    let names: Vec<_> = plugins
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|plugin| plugin["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"hello"), "{plugins}");
+
+    let output = kiosk(&["no-such-plugin"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("kiosk-no-such-plugin"));
+}
+
+#[test]
+fn test_e2e_profile_merges_its_search_dirs() {
+    let env = TestEnv::new("config-profile");
+    let search_dir = env.search_dir();
+    let work_dir = env.tmp.path().join("work");
+    for repo in [search_dir.join("home-repo"), work_dir.join("work-repo")] {
+        fs::create_dir_all(&repo).unwrap();
+        init_test_repo(&repo);
+    }
+    env.write_config_with_extra(
+        &search_dir,
+        &format!(
+            "[profiles.work]\nsearch_dirs = [\"{}\"]\n",
+            work_dir.to_string_lossy()
+        ),
+    );
+
+    let names = |args: &[&str]| -> Vec<String> {
+        let output = env.run_cli(args);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let mut names: Vec<String> = json_data(&output.stdout)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(names(&["list", "--json"]), ["home-repo"]);
+    assert_eq!(
+        names(&["--profile", "work", "list", "--json"]),
+        ["home-repo", "work-repo"]
+    );
+
+    let output = env.run_cli(&["--profile", "home", "list"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("unknown profile 'home' (available: work)"),
+        "{stderr}"
+    );
+}
+
 #[test]
 fn test_e2e_setup_wizard_typing_shows_all_matching_completions() {
     let env = TestEnv::new("setup-autocomplete");
@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use kiosk_core::config::{KeysConfig, NamedColor, ThemeConfig};
+use kiosk_core::config::{KeysConfig, NamedColor, ThemeConfig, ThemePreset};
 use quote::ToTokens;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
@@ -228,6 +228,22 @@ fn generate_theme_docs() -> Result<String> {
             .join(", "),
         alias_notes.join(", "),
     );
+    docs.push_str(
+        "Each slot is a color for the foreground, or a table that can also set the background and modifiers: `accent = { fg = \"#ff00ff\", bg = \"black\", bold = true }`. The keys are `fg`, `bg`, `bold`, `italic`, `underline` and `dim`. `selection` styles the selected row on top of the list's own highlight, and `stripe` styles every other row.\n\n",
+    );
+    let _ = writeln!(
+        docs,
+        "`preset` starts from a built-in palette ({}), and any slots set alongside it override the preset's.\n",
+        ThemePreset::all()
+            .iter()
+            .map(|(name, _)| format!("`{name}`"))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+
+    docs.push_str(
+        "`light` and `dark` take a `preset` and slots, which are laid over the rest of `[theme]` when the terminal's background is light or dark. kiosk asks the terminal for its background color when it starts, falling back to `COLORFGBG` and then to dark; set `background = \"light\"` or `\"dark\"` to skip the detection. On a light background the defaults use darker `muted` and `border` colors.\n\n```toml\n[theme]\npreset = \"catppuccin-mocha\"\nlight = { preset = \"catppuccin-latte\" }\n```\n\n",
+    );
 
     // Generate default TOML
     docs.push_str("Defaults:\n\n```toml\n");